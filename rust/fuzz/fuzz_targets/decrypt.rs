@@ -0,0 +1,18 @@
+// Ciphertext from a database or backup that may be damaged or foreign:
+// decrypting must fail with an error, never panic
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use selfspy_core::encryption::{Encryptor, KdfSettings};
+use std::sync::OnceLock;
+
+// Deriving the key is deliberately slow, so one is shared by all runs
+static ENCRYPTOR: OnceLock<Encryptor> = OnceLock::new();
+
+fuzz_target!(|data: &[u8]| {
+    let encryptor = ENCRYPTOR.get_or_init(|| {
+        let (encryptor, _) = Encryptor::create("fuzz", KdfSettings::default()).expect("key derivation");
+        encryptor
+    });
+    let _ = encryptor.decrypt(data);
+});
@@ -0,0 +1,10 @@
+// Phone and watch exports for `selfstats health import`, CSV or Apple Health
+// XML: malformed files must fail with an error, never panic
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use selfspy_core::health;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = health::parse(data);
+});
@@ -0,0 +1,46 @@
+// Arbitrary files handed to `selfspy import --legacy`: anything that isn't a
+// well-formed database of the original selfspy must be rejected or imported
+// in part, never panic. Every input is imported into a fresh in-memory
+// database so rows left by earlier inputs can't mask or cause a crash.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use selfspy_core::db::LegacyText;
+use selfspy_core::{Database, Progress};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tokio::runtime::Runtime;
+
+struct Target {
+    runtime: Runtime,
+    legacy: PathBuf,
+}
+
+static TARGET: OnceLock<Target> = OnceLock::new();
+
+fn target() -> &'static Target {
+    TARGET.get_or_init(|| {
+        let dir = std::env::temp_dir().join(format!("selfspy-fuzz-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).expect("temp dir");
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("runtime");
+        Target {
+            runtime,
+            legacy: dir.join("legacy.sqlite"),
+        }
+    })
+}
+
+fuzz_target!(|data: &[u8]| {
+    let target = target();
+    if std::fs::write(&target.legacy, data).is_err() {
+        return;
+    }
+    target.runtime.block_on(async {
+        let db = Database::in_memory().await.expect("database");
+        let _ = db.import_legacy(&target.legacy, LegacyText::Plain, false, &Progress::none()).await;
+        db.close().await;
+    });
+});
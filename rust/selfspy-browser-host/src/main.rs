@@ -0,0 +1,160 @@
+// Native messaging host for the selfspy browser extension. The browser starts
+// this binary and exchanges messages over stdin/stdout, each a JSON document
+// preceded by its length as a native-endian u32. The extension sends
+//
+//   {"type": "tab", "url": "https://example.com/page", "title": "Page title"}
+//
+// whenever the active tab changes; the URL is privacy-filtered (see
+// selfspy_core::browser) and attached to the matching window record.
+//
+// `selfspy-browser-host manifest <chrome|firefox> <extension-id>` prints the
+// manifest that registers this binary with the browser.
+use anyhow::{bail, Result};
+use chrono::{Duration, Utc};
+use selfspy_core::browser::TabLocation;
+use selfspy_core::categories::CategoryRule;
+use selfspy_core::{Config, Database};
+use serde::Deserialize;
+use std::process::ExitCode;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tracing::{debug, warn};
+
+// Name the manifest registers; the extension connects with this
+const HOST_NAME: &str = "org.selfspy.browser_host";
+// Browsers cap messages to the host at 1 MB (Chrome) / 4 GB (Firefox); a tab
+// report is tiny, so anything bigger is a protocol error
+const MAX_MESSAGE_BYTES: u32 = 1024 * 1024;
+// The monitor records a window on its next poll; keep trying to find it for a few seconds
+const MATCH_ATTEMPTS: u32 = 5;
+// Only windows this recent can belong to the tab being reported
+const MATCH_WINDOW_SECONDS: i64 = 120;
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Message {
+    Tab { url: String, title: String },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // stdout carries the protocol, so logs must go to stderr (the browser's log)
+    tracing_subscriber::fmt().with_writer(std::io::stderr).init();
+    
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => selfspy_core::error::report(&*e),
+    }
+}
+
+async fn run() -> Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("manifest") {
+        return print_manifest(&args[1..]);
+    }
+    
+    // Browsers pass the caller's origin (and Firefox the manifest path) as
+    // arguments; the host serves any extension the manifest allows
+    let config = Config::load()?;
+    let db = Arc::new(Database::connect(&config.database_path, &config.database).await?);
+    let settings = config.browser;
+    let rules = Arc::new(config.category_rules);
+    
+    let mut stdin = tokio::io::stdin();
+    let mut stdout = tokio::io::stdout();
+    
+    // The browser closes stdin when the extension disconnects
+    while let Some(message) = read_message(&mut stdin).await? {
+        let reply = match serde_json::from_slice::<Message>(&message) {
+            Ok(Message::Tab { url, title }) => {
+                let recorded = settings.filter(&url).filter(|_| !title.is_empty());
+                if let Some(location) = recorded.clone() {
+                    tokio::spawn(attach_location(db.clone(), rules.clone(), title, location));
+                }
+                serde_json::json!({ "ok": true, "domain": recorded.map(|location| location.domain) })
+            }
+            Err(e) => {
+                warn!("Ignoring malformed message: {}", e);
+                serde_json::json!({ "ok": false, "error": e.to_string() })
+            }
+        };
+        write_message(&mut stdout, &reply).await?;
+    }
+    
+    Ok(())
+}
+
+async fn attach_location(db: Arc<Database>, rules: Arc<Vec<CategoryRule>>, title: String, location: TabLocation) {
+    for attempt in 0..MATCH_ATTEMPTS {
+        if attempt > 0 {
+            tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+        }
+        
+        let since = Utc::now() - Duration::seconds(MATCH_WINDOW_SECONDS);
+        match db.set_window_location(&title, since, &location, &rules).await {
+            Ok(Some(window_id)) => {
+                debug!("Attached {} to window {}", location.domain, window_id);
+                return;
+            }
+            Ok(None) => {}
+            Err(e) => {
+                warn!("Failed to store tab location: {}", e);
+                return;
+            }
+        }
+    }
+    debug!("No recorded window matches tab '{}'", title);
+}
+
+async fn read_message(stdin: &mut tokio::io::Stdin) -> Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 4];
+    match stdin.read_exact(&mut length).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    
+    let length = u32::from_ne_bytes(length);
+    if length > MAX_MESSAGE_BYTES {
+        bail!("message of {} bytes exceeds the {} byte limit", length, MAX_MESSAGE_BYTES);
+    }
+    
+    let mut message = vec![0u8; length as usize];
+    stdin.read_exact(&mut message).await?;
+    Ok(Some(message))
+}
+
+async fn write_message(stdout: &mut tokio::io::Stdout, message: &serde_json::Value) -> Result<()> {
+    let body = serde_json::to_vec(message)?;
+    stdout.write_all(&(body.len() as u32).to_ne_bytes()).await?;
+    stdout.write_all(&body).await?;
+    stdout.flush().await?;
+    Ok(())
+}
+
+fn print_manifest(args: &[String]) -> Result<()> {
+    let (Some(browser), Some(extension_id)) = (args.first(), args.get(1)) else {
+        bail!("usage: selfspy-browser-host manifest <chrome|firefox> <extension-id>");
+    };
+    
+    let path = std::env::current_exe()?;
+    let mut manifest = serde_json::json!({
+        "name": HOST_NAME,
+        "description": "Selfspy active tab tracking",
+        "path": path,
+        "type": "stdio",
+    });
+    match browser.as_str() {
+        "chrome" | "chromium" => {
+            manifest["allowed_origins"] = serde_json::json!([format!("chrome-extension://{}/", extension_id)]);
+        }
+        "firefox" => {
+            manifest["allowed_extensions"] = serde_json::json!([extension_id]);
+        }
+        other => bail!("unknown browser '{}', expected chrome or firefox", other),
+    }
+    
+    println!("{}", serde_json::to_string_pretty(&manifest)?);
+    Ok(())
+}
+
@@ -0,0 +1,71 @@
+// The stats queries over a FixtureBuilder database of a busy month: three
+// apps through ten working hours a day. Run with `cargo bench -p selfspy-core`
+use std::path::PathBuf;
+
+use chrono::{Duration, Local, NaiveDate, TimeZone, Utc};
+use criterion::{criterion_group, criterion_main, Criterion};
+use selfspy_core::fixtures::FixtureBuilder;
+use selfspy_core::Database;
+
+const DAYS: i64 = 30;
+
+fn first_day() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 3, 2).unwrap()
+}
+
+fn month() -> FixtureBuilder {
+    let mut fixture = FixtureBuilder::new();
+    for offset in 0..DAYS {
+        let day = first_day() + Duration::days(offset);
+        for hour in 8..18 {
+            fixture = fixture
+                .keystrokes("Code", day, hour, 2400)
+                .clicks("Code", day, hour, 120)
+                .active_minutes("Code", day, hour, 35)
+                .keystrokes("Browser", day, hour, 400)
+                .clicks("Browser", day, hour, 200)
+                .active_minutes("Browser", day, hour, 20)
+                .keystrokes("Slack", day, hour, 300)
+                .active_minutes("Slack", day, hour, 5);
+        }
+    }
+    fixture
+}
+
+fn database(runtime: &tokio::runtime::Runtime) -> (PathBuf, Database) {
+    let dir = std::env::temp_dir().join(format!("selfspy-bench-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let db = runtime.block_on(month().build(&dir.join("selfspy.db"))).unwrap();
+    (dir, db)
+}
+
+fn stats_queries(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (dir, db) = database(&runtime);
+    let (start, end) = (first_day(), first_day() + Duration::days(DAYS));
+    let range = Local.from_local_datetime(&start.and_hms_opt(0, 0, 0).unwrap()).unwrap().with_timezone(&Utc)
+        ..Local.from_local_datetime(&end.and_hms_opt(0, 0, 0).unwrap()).unwrap().with_timezone(&Utc);
+    
+    c.bench_function("get_daily_summaries", |b| {
+        b.to_async(&runtime).iter(|| async { db.get_daily_summaries(start, end).await.unwrap() })
+    });
+    c.bench_function("get_hourly_activity", |b| {
+        b.to_async(&runtime).iter(|| async { db.get_hourly_activity(start, end).await.unwrap() })
+    });
+    c.bench_function("get_stats_between", |b| {
+        b.to_async(&runtime).iter(|| async { db.get_stats_between(range.start, range.end).await.unwrap() })
+    });
+    c.bench_function("get_process_stats", |b| {
+        b.to_async(&runtime).iter(|| async { db.get_process_stats(range.clone()).await.unwrap() })
+    });
+    c.bench_function("get_app_active_seconds", |b| {
+        b.to_async(&runtime).iter(|| async { db.get_app_active_seconds(range.clone()).await.unwrap() })
+    });
+    
+    runtime.block_on(db.close());
+    let _ = std::fs::remove_dir_all(dir);
+}
+
+criterion_group!(benches, stats_queries);
+criterion_main!(benches);
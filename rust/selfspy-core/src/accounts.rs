@@ -0,0 +1,192 @@
+// Screen time per OS account on a shared computer. Each account runs its own
+// monitor into its own data directory; an administrator lists those
+// directories in `account_reports` and `selfstats accounts` reports daily
+// active time for each.
+//
+// Sharing is opt-in per account and visible to it: the account's user grants
+// it with `selfspy share-screen-time grant`, which writes a consent record
+// into their data directory, and the report leaves out every account without
+// one. While the record exists the monitor says so at start, in its status
+// and on the dashboard. Only active time per day is reported, never windows,
+// titles or keys.
+//
+// As with aggregate.rs, the report never opens an account's database: while
+// consent is given, the account's own monitor writes its daily active time to
+// SHARED_FILE once a day, and the report reads only that file.
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::error::Result;
+use crate::Database;
+
+pub const CONSENT_FILE: &str = "screen_time_sharing.json";
+pub const SHARED_FILE: &str = "screen_time_shared.json";
+// Complete days before today that each publish covers
+pub const SHARED_DAYS: i64 = 31;
+
+// What the account's user agreed to, shown to them when granting
+pub const SHARING_NOTICE: &str = "The administrator of this computer will be able to see how long this account \
+was active each day. Window titles, apps, documents and typed text are not shared. You can stop sharing at any \
+time with `selfspy share-screen-time revoke`.";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharingConsent {
+    pub account: String,
+    pub granted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AccountReportSettings {
+    pub accounts: Vec<AccountSource>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountSource {
+    // Shown in reports; usually the account's login name
+    pub name: String,
+    // The data directory the account's monitor writes to
+    pub data_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountDay {
+    pub date: NaiveDate,
+    pub active_seconds: i64,
+}
+
+// What a consenting account's monitor publishes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedScreenTime {
+    pub published_at: DateTime<Utc>,
+    pub days: Vec<AccountDay>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountStatus {
+    Shared(SharingConsent),
+    NotShared,
+    // Consent is there but the account's monitor hasn't published since
+    NotPublished(SharingConsent),
+    // Consent is there but the published file couldn't be read
+    Unreadable(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountScreenTime {
+    pub name: String,
+    pub status: AccountStatus,
+    // Empty unless the account shares
+    pub days: Vec<AccountDay>,
+}
+
+// The login name of the account running this process
+pub fn current_account() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+pub fn read_consent(data_dir: &Path) -> Option<SharingConsent> {
+    let text = std::fs::read_to_string(data_dir.join(CONSENT_FILE)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+pub fn grant_consent(data_dir: &Path) -> Result<SharingConsent> {
+    std::fs::create_dir_all(data_dir)?;
+    let consent = SharingConsent {
+        account: current_account(),
+        granted_at: Utc::now(),
+    };
+    std::fs::write(data_dir.join(CONSENT_FILE), serde_json::to_string_pretty(&consent)?)?;
+    Ok(consent)
+}
+
+// Returns whether sharing was on. What was published goes too, so the
+// administrator stops seeing it right away rather than at the next daily run.
+pub fn revoke_consent(data_dir: &Path) -> Result<bool> {
+    unpublish(data_dir)?;
+    let path = data_dir.join(CONSENT_FILE);
+    if !path.exists() {
+        return Ok(false);
+    }
+    std::fs::remove_file(path)?;
+    Ok(true)
+}
+
+// Writes this account's active time for the SHARED_DAYS days before `today`
+// to SHARED_FILE in `data_dir`, read through `get_daily_summaries` alone
+pub async fn publish(db: &Database, data_dir: &Path, today: NaiveDate) -> Result<()> {
+    let start = today - chrono::Duration::days(SHARED_DAYS);
+    let days = db
+        .get_daily_summaries(start, today)
+        .await?
+        .into_iter()
+        .map(|summary| AccountDay {
+            date: summary.date,
+            active_seconds: summary.active_seconds,
+        })
+        .collect();
+    let shared = SharedScreenTime {
+        published_at: Utc::now(),
+        days,
+    };
+    std::fs::write(data_dir.join(SHARED_FILE), serde_json::to_string_pretty(&shared)?)?;
+    Ok(())
+}
+
+// Removes a previous publish; returns whether there was one
+pub fn unpublish(data_dir: &Path) -> Result<bool> {
+    let path = data_dir.join(SHARED_FILE);
+    if !path.exists() {
+        return Ok(false);
+    }
+    std::fs::remove_file(path)?;
+    Ok(true)
+}
+
+// Active time per day in [start, end) for every configured account, in the
+// configured order, from what each consenting account published. Days from
+// today on aren't published yet.
+pub fn screen_time(settings: &AccountReportSettings, start: NaiveDate, end: NaiveDate) -> Vec<AccountScreenTime> {
+    let mut reports = Vec::with_capacity(settings.accounts.len());
+    for account in &settings.accounts {
+        let Some(consent) = read_consent(&account.data_dir) else {
+            reports.push(AccountScreenTime {
+                name: account.name.clone(),
+                status: AccountStatus::NotShared,
+                days: Vec::new(),
+            });
+            continue;
+        };
+        
+        if !account.data_dir.join(SHARED_FILE).exists() {
+            reports.push(AccountScreenTime {
+                name: account.name.clone(),
+                status: AccountStatus::NotPublished(consent),
+                days: Vec::new(),
+            });
+            continue;
+        }
+        let (status, days) = match read_shared(&account.data_dir) {
+            Ok(shared) => {
+                let days = shared.days.into_iter().filter(|day| day.date >= start && day.date < end).collect();
+                (AccountStatus::Shared(consent), days)
+            }
+            Err(e) => (AccountStatus::Unreadable(e.to_string()), Vec::new()),
+        };
+        reports.push(AccountScreenTime {
+            name: account.name.clone(),
+            status,
+            days,
+        });
+    }
+    reports
+}
+
+fn read_shared(data_dir: &Path) -> Result<SharedScreenTime> {
+    let text = std::fs::read_to_string(data_dir.join(SHARED_FILE))?;
+    Ok(serde_json::from_str(&text)?)
+}
@@ -0,0 +1,200 @@
+// Activity outside configured working hours: evenings on workdays and
+// anything on non-working days.
+use chrono::{Datelike, Duration, Local, NaiveDate, NaiveDateTime, Timelike, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::week_start;
+use crate::{HourlyActivity, MinuteTotals};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkingHours {
+    // Local hours, start inclusive and end exclusive (9 to 17 is 09:00-16:59)
+    pub start_hour: u32,
+    pub end_hour: u32,
+    pub workdays: Vec<Weekday>,
+    // Different hours on particular workdays, e.g. a short Friday
+    pub days: Vec<DayHours>,
+    // Days off that would otherwise be workdays
+    pub holidays: Vec<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayHours {
+    pub day: Weekday,
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl Default for WorkingHours {
+    fn default() -> Self {
+        Self {
+            start_hour: 9,
+            end_hour: 17,
+            workdays: vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri],
+            days: Vec::new(),
+            holidays: Vec::new(),
+        }
+    }
+}
+
+impl WorkingHours {
+    pub fn is_workday(&self, date: NaiveDate) -> bool {
+        self.workdays.contains(&date.weekday()) && !self.holidays.contains(&date)
+    }
+    
+    // Start and end hour on a date; None on days off
+    pub fn hours_on(&self, date: NaiveDate) -> Option<(u32, u32)> {
+        if !self.is_workday(date) {
+            return None;
+        }
+        Some(self.hours_for(date.weekday()))
+    }
+    
+    // Hours on a workday, ignoring holidays
+    fn hours_for(&self, day: Weekday) -> (u32, u32) {
+        self.days
+            .iter()
+            .find(|hours| hours.day == day)
+            .map_or((self.start_hour, self.end_hour), |hours| (hours.start_hour, hours.end_hour))
+    }
+    
+    pub fn is_working_time(&self, time: NaiveDateTime) -> bool {
+        self.hours_on(time.date())
+            .is_some_and(|(start, end)| time.hour() >= start && time.hour() < end)
+    }
+    
+    // Hours of the day that are working time on at least one workday, for
+    // shading charts that cover several days
+    pub fn usual_hours(&self) -> [bool; 24] {
+        let mut hours = [false; 24];
+        for day in &self.workdays {
+            let (start, end) = self.hours_for(*day);
+            for hour in start.min(24)..end.min(24) {
+                hours[hour as usize] = true;
+            }
+        }
+        hours
+    }
+    
+    // "Mon, Tue, Wed, Thu 09:00-17:00; Fri 09:00-13:00" summary for reports
+    pub fn describe(&self) -> String {
+        let mut parts: Vec<(Vec<Weekday>, (u32, u32))> = Vec::new();
+        for day in &self.workdays {
+            let hours = self.hours_for(*day);
+            match parts.iter_mut().find(|(_, existing)| *existing == hours) {
+                Some((days, _)) => days.push(*day),
+                None => parts.push((vec![*day], hours)),
+            }
+        }
+        
+        let mut description = parts
+            .iter()
+            .map(|(days, (start, end))| {
+                let days: Vec<String> = days.iter().map(|day| day.to_string()).collect();
+                format!("{} {:02}:00-{:02}:00", days.join(", "), start, end)
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        if !self.holidays.is_empty() {
+            description.push_str(&format!(" ({} holidays)", self.holidays.len()));
+        }
+        description
+    }
+}
+
+// Activity inside and outside working hours over one day
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WorkSplit {
+    pub day: NaiveDate,
+    pub work_minutes: i64,
+    pub off_minutes: i64,
+    pub work_keystrokes: i64,
+    pub off_keystrokes: i64,
+    pub work_clicks: i64,
+    pub off_clicks: i64,
+}
+
+// Splits per-minute totals into working time and time off, one entry per
+// local day from `first_day` to `last_day` inclusive
+pub fn work_split(
+    minutes: &[MinuteTotals],
+    working_hours: &WorkingHours,
+    first_day: NaiveDate,
+    last_day: NaiveDate,
+) -> Vec<WorkSplit> {
+    let mut days: Vec<WorkSplit> = first_day
+        .iter_days()
+        .take_while(|day| *day <= last_day)
+        .map(|day| WorkSplit {
+            day,
+            ..WorkSplit::default()
+        })
+        .collect();
+    
+    for minute in minutes {
+        let local = minute.minute.with_timezone(&Local).naive_local();
+        let index = (local.date() - first_day).num_days();
+        let Some(split) = usize::try_from(index).ok().and_then(|i| days.get_mut(i)) else {
+            continue;
+        };
+        
+        if working_hours.is_working_time(local) {
+            split.work_minutes += 1;
+            split.work_keystrokes += minute.keystrokes;
+            split.work_clicks += minute.clicks;
+        } else {
+            split.off_minutes += 1;
+            split.off_keystrokes += minute.keystrokes;
+            split.off_clicks += minute.clicks;
+        }
+    }
+    
+    days
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AfterHoursWeek {
+    pub week_start: NaiveDate,
+    // Outside working hours on workdays
+    pub evening_minutes: i64,
+    // Any time on non-working days
+    pub weekend_minutes: i64,
+}
+
+impl AfterHoursWeek {
+    pub fn total_minutes(&self) -> i64 {
+        self.evening_minutes + self.weekend_minutes
+    }
+}
+
+pub fn weekly_after_hours(
+    activity: &[HourlyActivity],
+    working_hours: &WorkingHours,
+    first_week: NaiveDate,
+    weeks: i64,
+    first_day: Weekday,
+) -> Vec<AfterHoursWeek> {
+    let first_week = week_start(first_week, first_day);
+    let mut result: Vec<AfterHoursWeek> = (0..weeks)
+        .map(|offset| AfterHoursWeek {
+            week_start: first_week + Duration::weeks(offset),
+            ..AfterHoursWeek::default()
+        })
+        .collect();
+    
+    for hour in activity {
+        let index = (week_start(hour.hour.date(), first_day) - first_week).num_weeks();
+        let Some(week) = usize::try_from(index).ok().and_then(|i| result.get_mut(i)) else {
+            continue;
+        };
+        
+        if !working_hours.is_workday(hour.hour.date()) {
+            week.weekend_minutes += hour.active_minutes;
+        } else if !working_hours.is_working_time(hour.hour) {
+            week.evening_minutes += hour.active_minutes;
+        }
+    }
+    
+    result
+}
\ No newline at end of file
@@ -0,0 +1,216 @@
+// Aggregation across several people's Selfspy data, for a family computer or
+// a team that agreed to share. Only coarse totals come out: active hours and
+// hours per app category, summed over members and rounded, and only where at
+// least `min_members` people contributed, so no figure can be traced back to
+// one person.
+//
+// The aggregator never opens a member's database. Each member's own monitor
+// publishes their daily totals (active time and time per category, nothing
+// else) to PUBLISHED_FILE in their data directory once a day, when they turn
+// on `aggregation.publish`; the aggregator reads only those files. Members
+// list the same `categories` so their totals line up.
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tracing::warn;
+
+use crate::error::Result;
+use crate::Database;
+
+// A floor of one would publish individual figures, and so would two: either
+// member can subtract their own numbers from the total and get the other's
+const MIN_MEMBERS_FLOOR: usize = 3;
+const OTHER_CATEGORY: &str = "Other";
+// Complete days before today that each publish covers
+pub const PUBLISHED_DAYS: i64 = 31;
+pub const PUBLISHED_FILE: &str = "aggregate_totals.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AggregationSettings {
+    // Each member's data directory, holding their PUBLISHED_FILE
+    pub members: Vec<PathBuf>,
+    // Figures based on fewer members than this are withheld
+    pub min_members: usize,
+    // Hours are rounded to this many minutes
+    pub rounding_minutes: i64,
+    // Apps are reported only through these; anything unlisted is "Other"
+    pub categories: Vec<AppCategory>,
+    // Have the monitor publish this account's daily totals for an aggregator
+    pub publish: bool,
+}
+
+impl Default for AggregationSettings {
+    fn default() -> Self {
+        Self {
+            members: Vec::new(),
+            min_members: 3,
+            rounding_minutes: 30,
+            categories: Vec::new(),
+            publish: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppCategory {
+    pub name: String,
+    // Process names, matched case-insensitively
+    pub apps: Vec<String>,
+}
+
+impl AggregationSettings {
+    pub fn min_members(&self) -> usize {
+        self.min_members.max(MIN_MEMBERS_FLOOR)
+    }
+    
+    pub fn category(&self, process: &str) -> &str {
+        self.categories
+            .iter()
+            .find(|category| category.apps.iter().any(|app| app.eq_ignore_ascii_case(process)))
+            .map_or(OTHER_CATEGORY, |category| category.name.as_str())
+    }
+    
+    fn round(&self, seconds: i64) -> i64 {
+        let step = self.rounding_minutes.max(1) * 60;
+        (seconds + step / 2) / step * step
+    }
+}
+
+// One day of combined activity; days with too few active members are left out
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AggregateDay {
+    pub date: NaiveDate,
+    pub members: usize,
+    pub active_seconds: i64,
+    // Categories used by too few members that day are left out
+    pub categories: Vec<CategoryTotal>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryTotal {
+    pub category: String,
+    pub members: usize,
+    pub seconds: i64,
+}
+
+// What a member publishes: their own unrounded totals, per day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedTotals {
+    pub published_at: DateTime<Utc>,
+    pub days: Vec<PublishedDay>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublishedDay {
+    pub date: NaiveDate,
+    pub active_seconds: i64,
+    // Seconds per category
+    pub categories: BTreeMap<String, i64>,
+}
+
+#[derive(Default)]
+struct DayTotals {
+    members: usize,
+    active_seconds: i64,
+    // Seconds and how many members contributed
+    categories: BTreeMap<String, (i64, usize)>,
+}
+
+// Writes this account's totals for the PUBLISHED_DAYS days before `today`
+// to PUBLISHED_FILE in `data_dir`. Read through `get_daily_summaries` and
+// `get_app_days` alone, which never touch titles, documents or keys.
+pub async fn publish(db: &Database, settings: &AggregationSettings, data_dir: &Path, today: NaiveDate) -> Result<()> {
+    let start = today - chrono::Duration::days(PUBLISHED_DAYS);
+    let mut days: BTreeMap<NaiveDate, PublishedDay> = BTreeMap::new();
+    
+    for summary in db.get_daily_summaries(start, today).await? {
+        published_day(&mut days, summary.date).active_seconds = summary.active_seconds;
+    }
+    for app in db.get_app_days(start, today).await? {
+        let category = settings.category(&app.process).to_string();
+        *published_day(&mut days, app.date).categories.entry(category).or_default() += app.seconds;
+    }
+    
+    let totals = PublishedTotals {
+        published_at: Utc::now(),
+        days: days.into_values().collect(),
+    };
+    std::fs::write(data_dir.join(PUBLISHED_FILE), serde_json::to_string_pretty(&totals)?)?;
+    Ok(())
+}
+
+// Removes a previous publish; returns whether there was one
+pub fn unpublish(data_dir: &Path) -> Result<bool> {
+    let path = data_dir.join(PUBLISHED_FILE);
+    if !path.exists() {
+        return Ok(false);
+    }
+    std::fs::remove_file(path)?;
+    Ok(true)
+}
+
+fn published_day(days: &mut BTreeMap<NaiveDate, PublishedDay>, date: NaiveDate) -> &mut PublishedDay {
+    days.entry(date).or_insert_with(|| PublishedDay {
+        date,
+        active_seconds: 0,
+        categories: BTreeMap::new(),
+    })
+}
+
+fn read_published(data_dir: &Path) -> Result<PublishedTotals> {
+    let text = std::fs::read_to_string(data_dir.join(PUBLISHED_FILE))?;
+    Ok(serde_json::from_str(&text)?)
+}
+
+// Combined days in [start, end). Members who haven't published (or whose
+// file can't be read) are skipped with a warning; they then count toward
+// no figure.
+pub async fn aggregate(settings: &AggregationSettings, start: NaiveDate, end: NaiveDate) -> Result<Vec<AggregateDay>> {
+    let mut days: BTreeMap<NaiveDate, DayTotals> = BTreeMap::new();
+    
+    for member in &settings.members {
+        let published = match read_published(member) {
+            Ok(published) => published,
+            Err(e) => {
+                warn!("Skipping member {}: no readable {} ({})", member.display(), PUBLISHED_FILE, e);
+                continue;
+            }
+        };
+        
+        for published_day in published.days.into_iter().filter(|day| day.date >= start && day.date < end) {
+            let day = days.entry(published_day.date).or_default();
+            if published_day.active_seconds > 0 {
+                day.members += 1;
+                day.active_seconds += published_day.active_seconds;
+            }
+            for (category, seconds) in published_day.categories.into_iter().filter(|(_, seconds)| *seconds > 0) {
+                let entry = day.categories.entry(category).or_default();
+                entry.0 += seconds;
+                entry.1 += 1;
+            }
+        }
+    }
+    
+    let floor = settings.min_members();
+    Ok(days
+        .into_iter()
+        .filter(|(_, day)| day.members >= floor)
+        .map(|(date, day)| AggregateDay {
+            date,
+            members: day.members,
+            active_seconds: settings.round(day.active_seconds),
+            categories: day
+                .categories
+                .into_iter()
+                .filter(|(_, (_, members))| *members >= floor)
+                .map(|(category, (seconds, members))| CategoryTotal {
+                    category,
+                    members,
+                    seconds: settings.round(seconds),
+                })
+                .collect(),
+        })
+        .collect())
+}
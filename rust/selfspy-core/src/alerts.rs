@@ -0,0 +1,221 @@
+// Alert rules from config.json, checked by the monitor, for example
+//
+//   "alert_rules": [
+//     { "name": "Slack morning", "metric": "active_minutes", "process": "Slack",
+//       "comparison": "above", "threshold": 90, "window": { "end_hour": 12 } }
+//   ]
+//
+// A rule measures one metric over today's local hours in its window,
+// optionally for one app. An "above" rule fires as soon as the value passes
+// the threshold; a "below" rule can only be judged once the window has
+// closed. Each rule fires at most once a day, on its channel or on every
+// configured channel when it names none.
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::notifications::{Notification, NotificationChannel};
+use crate::Database;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlertRule {
+    pub name: String,
+    pub metric: AlertMetric,
+    // Only count this app, matched case-insensitively against process names
+    #[serde(default)]
+    pub process: Option<String>,
+    pub comparison: Comparison,
+    pub threshold: f64,
+    #[serde(default)]
+    pub window: AlertWindow,
+    #[serde(default)]
+    pub channel: Option<NotificationChannel>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertMetric {
+    ActiveMinutes,
+    Keystrokes,
+    Clicks,
+    Windows,
+}
+
+impl AlertMetric {
+    pub const ALL: [AlertMetric; 4] = [
+        AlertMetric::ActiveMinutes,
+        AlertMetric::Keystrokes,
+        AlertMetric::Clicks,
+        AlertMetric::Windows,
+    ];
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertMetric::ActiveMinutes => "active_minutes",
+            AlertMetric::Keystrokes => "keystrokes",
+            AlertMetric::Clicks => "clicks",
+            AlertMetric::Windows => "windows",
+        }
+    }
+    
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.as_str() == value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+impl Comparison {
+    pub fn holds(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::Above => value > threshold,
+            Comparison::Below => value < threshold,
+        }
+    }
+    
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Above => ">",
+            Comparison::Below => "<",
+        }
+    }
+}
+
+// Local hours of the day, start inclusive and end exclusive; 0 to 24 is the
+// whole day
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertWindow {
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl Default for AlertWindow {
+    fn default() -> Self {
+        Self {
+            start_hour: 0,
+            end_hour: 24,
+        }
+    }
+}
+
+impl AlertWindow {
+    // "all day", "before 12:00", "after 18:00" or "09:00-12:00"
+    pub fn describe(&self) -> String {
+        match (self.start_hour, self.end_hour.min(24)) {
+            (0, 24) => "all day".to_string(),
+            (0, end) => format!("before {:02}:00", end),
+            (start, 24) => format!("after {:02}:00", start),
+            (start, end) => format!("{:02}:00-{:02}:00", start, end),
+        }
+    }
+    
+    fn bounds(&self, day: NaiveDate) -> (DateTime<Utc>, DateTime<Utc>) {
+        (local_hour(day, self.start_hour), local_hour(day, self.end_hour))
+    }
+}
+
+impl AlertRule {
+    // "active_minutes in Slack > 90 before 12:00"
+    pub fn describe(&self) -> String {
+        format!(
+            "{}{} {} {} {}",
+            self.metric.as_str(),
+            self.process.as_ref().map(|process| format!(" in {}", process)).unwrap_or_default(),
+            self.comparison.symbol(),
+            self.threshold,
+            self.window.describe()
+        )
+    }
+    
+    pub fn notification(&self, check: &AlertCheck) -> Notification {
+        Notification::new(
+            self.name.clone(),
+            format!("{} is {} ({})", self.metric.as_str(), format_value(check.value), self.describe()),
+        )
+        .only_on(self.channel)
+    }
+}
+
+// A rule evaluated at one moment
+#[derive(Debug, Clone, Copy)]
+pub struct AlertCheck {
+    // The metric from the start of the window to now or its end
+    pub value: f64,
+    // The comparison holds for the value so far
+    pub breached: bool,
+    // Breached and decided: the window has opened, and for "below" closed
+    pub fires: bool,
+}
+
+pub async fn check(db: &Database, rule: &AlertRule, now: DateTime<Utc>) -> Result<AlertCheck> {
+    let (start, end) = rule.window.bounds(now.with_timezone(&Local).date_naive());
+    if now < start {
+        return Ok(AlertCheck {
+            value: 0.0,
+            breached: false,
+            fires: false,
+        });
+    }
+    let range = start..end.min(now);
+    
+    let value = match (&rule.process, rule.metric) {
+        // Activity periods also cover the window still open
+        (None, AlertMetric::ActiveMinutes) => db.get_active_seconds(range).await? as f64 / 60.0,
+        (Some(process), AlertMetric::ActiveMinutes) => {
+            let seconds: i64 = db
+                .get_app_active_seconds(range)
+                .await?
+                .iter()
+                .filter(|(app, _)| app.eq_ignore_ascii_case(process))
+                .map(|(_, seconds)| seconds)
+                .sum();
+            seconds as f64 / 60.0
+        }
+        (process, metric) => {
+            let stats = db.get_process_stats(range).await?;
+            stats
+                .iter()
+                .filter(|stats| process.as_ref().is_none_or(|process| stats.process.eq_ignore_ascii_case(process)))
+                .map(|stats| match metric {
+                    AlertMetric::ActiveMinutes => unreachable!("active minutes are read from activity periods"),
+                    AlertMetric::Keystrokes => stats.keystrokes as f64,
+                    AlertMetric::Clicks => stats.clicks as f64,
+                    AlertMetric::Windows => stats.windows as f64,
+                })
+                .sum()
+        }
+    };
+    
+    let breached = rule.comparison.holds(value, rule.threshold);
+    let decided = rule.comparison == Comparison::Above || now >= end;
+    Ok(AlertCheck {
+        value,
+        breached,
+        fires: breached && decided,
+    })
+}
+
+fn format_value(value: f64) -> String {
+    if value.fract() == 0.0 {
+        format!("{:.0}", value)
+    } else {
+        format!("{:.1}", value)
+    }
+}
+
+// Hour 24 is the next midnight
+fn local_hour(day: NaiveDate, hour: u32) -> DateTime<Utc> {
+    let day = day + chrono::Duration::days(i64::from(hour / 24));
+    let time = day.and_hms_opt(hour % 24, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&time)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&time))
+        .with_timezone(&Utc)
+}
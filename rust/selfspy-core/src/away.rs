@@ -0,0 +1,110 @@
+// Why the user was away from the computer: the machine was asleep (off), the
+// screen was locked, or it sat unlocked without input (idle). Built from the
+// system_events table and the "idle" tagged periods.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+use crate::{SystemEvent, TaggedPeriod};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AwayReason {
+    Off,
+    Locked,
+    Idle,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AwayBreakdown {
+    pub off_seconds: i64,
+    pub locked_seconds: i64,
+    // Idle time while the screen was unlocked and the machine awake
+    pub idle_seconds: i64,
+}
+
+// Off and locked stretches from events sorted by time. A lock still held when
+// the machine sleeps counts as off until wake, then locked again until unlock.
+// Anything still open runs until `until`.
+pub fn system_intervals(events: &[SystemEvent], until: DateTime<Utc>) -> Vec<(AwayReason, Range<DateTime<Utc>>)> {
+    let mut intervals = Vec::new();
+    let mut asleep = false;
+    let mut locked = false;
+    let mut since: Option<DateTime<Utc>> = None;
+    
+    for event in events {
+        let reason = if asleep {
+            Some(AwayReason::Off)
+        } else if locked {
+            Some(AwayReason::Locked)
+        } else {
+            None
+        };
+        if let (Some(reason), Some(start)) = (reason, since) {
+            if event.occurred_at > start {
+                intervals.push((reason, start..event.occurred_at));
+            }
+        }
+        
+        match event.kind.as_str() {
+            "sleep" => asleep = true,
+            "wake" => asleep = false,
+            "lock" => locked = true,
+            "unlock" => locked = false,
+            _ => continue,
+        }
+        since = Some(event.occurred_at);
+    }
+    
+    if let Some(start) = since.filter(|start| *start < until) {
+        if asleep {
+            intervals.push((AwayReason::Off, start..until));
+        } else if locked {
+            intervals.push((AwayReason::Locked, start..until));
+        }
+    }
+    
+    intervals
+}
+
+// Totals within `range`. Idle periods overlapping off or locked time only count
+// the remainder, so each second has a single reason.
+pub fn breakdown(
+    range: Range<DateTime<Utc>>,
+    system: &[(AwayReason, Range<DateTime<Utc>>)],
+    idle: &[TaggedPeriod],
+) -> AwayBreakdown {
+    let mut result = AwayBreakdown::default();
+    
+    for (reason, interval) in system {
+        let seconds = overlap_seconds(&range, interval);
+        match reason {
+            AwayReason::Off => result.off_seconds += seconds,
+            AwayReason::Locked => result.locked_seconds += seconds,
+            AwayReason::Idle => result.idle_seconds += seconds,
+        }
+    }
+    
+    for period in idle {
+        let end = period.ended_at.unwrap_or(range.end).min(range.end);
+        let start = period.started_at.max(range.start);
+        if end <= start {
+            continue;
+        }
+        let period = start..end;
+        let covered: i64 = system.iter().map(|(_, interval)| overlap_seconds(&period, interval)).sum();
+        result.idle_seconds += ((end - start).num_seconds() - covered).max(0);
+    }
+    
+    result
+}
+
+fn overlap_seconds(a: &Range<DateTime<Utc>>, b: &Range<DateTime<Utc>>) -> i64 {
+    let start = a.start.max(b.start);
+    let end = a.end.min(b.end);
+    if end > start {
+        (end - start).num_seconds()
+    } else {
+        0
+    }
+}
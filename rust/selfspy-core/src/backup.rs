@@ -0,0 +1,801 @@
+// Database backups. A backup is a page-for-page copy made with SQLite's online
+// backup API (safe while the monitor is writing), stored either in a local
+// directory or on a remote.
+//
+// Backups form chains: a full copy, then incrementals holding only the pages
+// that changed since the backup before them. The copy keeps SQLite's page
+// layout, so new activity rows only touch the pages at the end of each table.
+// The manifest (selfspy-manifest.json, next to the backups) records every
+// backup with its parent and a hash of the complete database it stands for;
+// restore replays the chain and checks the result against that hash.
+//
+// Remote backups are always encrypted to one or more age X25519 recipients
+// before they leave the machine, so offsite copies never contain plaintext
+// window titles. Remotes are rclone remotes ("s3:bucket/selfspy",
+// "webdav:selfspy", ...): rclone handles S3, WebDAV and the rest, with its
+// own credentials, and only ever sees ciphertext.
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::ffi::CString;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+use tracing::info;
+
+use crate::error::{Result, SelfspyError};
+use crate::{Config, Database};
+
+const FULL_EXTENSION: &str = "db";
+const DELTA_EXTENSION: &str = "delta";
+const AGE_SUFFIX: &str = ".age";
+const MANIFEST_NAME: &str = "selfspy-manifest.json";
+// Page hashes of the newest backup, kept locally to find changed pages
+const PAGE_STATE_NAME: &str = "pages.json";
+const DELTA_MAGIC: &[u8] = b"SELFSPY-DELTA1\n";
+// Files SQLite keeps next to a database, named <database>-<suffix>
+const SIDECAR_SUFFIXES: [&str; 3] = ["wal", "shm", "journal"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackupSettings {
+    // rclone remote path; when unset, backups go to <data_dir>/backups
+    pub remote: Option<String>,
+    // age public keys (age1...) backups are encrypted to; required for remotes
+    pub recipients: Vec<String>,
+    // age identity file holding the matching secret key, used by restore
+    pub identity_file: Option<PathBuf>,
+    // Start a new chain with a full backup after this many backups
+    pub full_every: usize,
+    // Backups to keep; after each backup older ones are deleted, except those
+    // a kept incremental still builds on. 0 keeps every backup
+    pub keep: usize,
+}
+
+impl Default for BackupSettings {
+    fn default() -> Self {
+        Self {
+            remote: None,
+            recipients: Vec::new(),
+            identity_file: None,
+            full_every: 7,
+            keep: 0,
+        }
+    }
+}
+
+impl BackupSettings {
+    pub fn local_dir(config: &Config) -> PathBuf {
+        config.data_dir.join("backups")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub name: String,
+    // The backup this one applies on top of; None for full backups
+    pub parent: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub page_size: u32,
+    pub page_count: u64,
+    // Pages stored in this backup
+    pub pages: u64,
+    // Hash of the database this backup restores to, see content_hash()
+    pub content_hash: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    pub backups: Vec<BackupEntry>,
+}
+
+impl Manifest {
+    pub fn find(&self, name: &str) -> Option<&BackupEntry> {
+        self.backups.iter().find(|entry| entry.name == name)
+    }
+    
+    // The full backup and incrementals leading to `name`, in the order they apply
+    pub fn chain(&self, name: &str) -> Result<Vec<&BackupEntry>> {
+        let mut chain = Vec::new();
+        let mut next = Some(name);
+        while let Some(name) = next {
+            let entry = self
+                .find(name)
+                .ok_or_else(|| SelfspyError::Backup(format!("backup {} is missing from the manifest", name)))?;
+            if chain.len() > self.backups.len() {
+                return Err(SelfspyError::Backup("manifest contains a cycle".to_string()));
+            }
+            chain.push(entry);
+            next = entry.parent.as_deref();
+        }
+        chain.reverse();
+        Ok(chain)
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct PageState {
+    name: String,
+    page_size: u32,
+    hashes: Vec<u64>,
+}
+
+// Copies the database, diffs it against the previous backup unless `full`,
+// and stores the result
+pub async fn create(config: &Config, settings: &BackupSettings, full: bool) -> Result<BackupEntry> {
+    if settings.remote.is_some() && settings.recipients.is_empty() {
+        return Err(SelfspyError::Backup(
+            "remote backups must be encrypted; add an age recipient to backup.recipients".to_string(),
+        ));
+    }
+    
+    let dir = BackupSettings::local_dir(config);
+    std::fs::create_dir_all(&dir)?;
+    // Down to the microsecond: backups made within one second must not share a name
+    let stamp = Utc::now().format("%Y%m%d-%H%M%S-%6f").to_string();
+    let snapshot = dir.join(format!("selfspy-{}.partial", stamp));
+    
+    let (source, target) = (config.database_path.clone(), snapshot.clone());
+    tokio::task::spawn_blocking(move || copy_database(&source, &target))
+        .await
+        .map_err(|e| SelfspyError::Backup(e.to_string()))??;
+    
+    // Hashing, encrypting and uploading block for as long as the copy did
+    let (config, settings) = (config.clone(), settings.clone());
+    tokio::task::spawn_blocking(move || {
+        let result = store_snapshot(&config, &settings, &dir, &stamp, &snapshot, full);
+        // The plaintext copy never outlives the backup it was made for
+        let _ = std::fs::remove_file(&snapshot);
+        let entry = result?;
+        rotate(&config, &settings)?;
+        Ok(entry)
+    })
+    .await
+    .map_err(|e| SelfspyError::Backup(e.to_string()))?
+}
+
+// A plain, unencrypted copy of the database outside the backup chain, e.g.
+// for `selfspy backup create --output`; replaces `destination`
+pub async fn copy_to(config: &Config, destination: &Path) -> Result<()> {
+    let (source, target) = (config.database_path.clone(), destination.to_path_buf());
+    tokio::task::spawn_blocking(move || copy_database(&source, &target))
+        .await
+        .map_err(|e| SelfspyError::Backup(e.to_string()))?
+}
+
+// Deletes backups beyond the newest `settings.keep`, keeping the full backup
+// and incrementals every kept backup needs; returns the names deleted
+pub fn rotate(config: &Config, settings: &BackupSettings) -> Result<Vec<String>> {
+    if settings.keep == 0 {
+        return Ok(Vec::new());
+    }
+    
+    let dir = BackupSettings::local_dir(config);
+    let mut manifest = load_manifest(config, settings)?;
+    let mut needed = std::collections::HashSet::new();
+    for entry in manifest.backups.iter().rev().take(settings.keep) {
+        needed.extend(manifest.chain(&entry.name)?.into_iter().map(|link| link.name.clone()));
+    }
+    let (kept, expired): (Vec<BackupEntry>, Vec<BackupEntry>) =
+        manifest.backups.drain(..).partition(|entry| needed.contains(&entry.name));
+    if expired.is_empty() {
+        return Ok(Vec::new());
+    }
+    
+    // The manifest goes first: a file left behind is harmless, a manifest
+    // naming a deleted file is not
+    manifest.backups = kept;
+    save_manifest(&dir, settings, &manifest)?;
+    for entry in &expired {
+        match &settings.remote {
+            Some(remote) => {
+                rclone(&["deletefile", &remote_path(remote, &entry.name)])?;
+            }
+            None => {
+                if let Err(e) = std::fs::remove_file(dir.join(&entry.name)) {
+                    if e.kind() != io::ErrorKind::NotFound {
+                        return Err(e.into());
+                    }
+                }
+            }
+        }
+    }
+    
+    info!("Deleted {} backups beyond the newest {}", expired.len(), settings.keep);
+    Ok(expired.into_iter().map(|entry| entry.name).collect())
+}
+
+fn store_snapshot(
+    config: &Config,
+    settings: &BackupSettings,
+    dir: &Path,
+    stamp: &str,
+    snapshot: &Path,
+    full: bool,
+) -> Result<BackupEntry> {
+    let (page_size, hashes) = hash_pages(snapshot)?;
+    let mut manifest = load_manifest(config, settings)?;
+    
+    // Incremental only on top of the newest backup, and only while the chain is short
+    let previous = if full { None } else { load_page_state(dir) }.filter(|state| {
+        state.page_size == page_size
+            && manifest.backups.last().is_some_and(|tip| tip.name == state.name)
+            && manifest
+                .chain(&state.name)
+                .is_ok_and(|chain| chain.len() < settings.full_every.max(1))
+    });
+    
+    let (extension, payload, pages) = match &previous {
+        None => (FULL_EXTENSION, snapshot.to_path_buf(), hashes.len() as u64),
+        Some(state) => {
+            let changed: Vec<u64> = hashes
+                .iter()
+                .enumerate()
+                .filter(|(index, hash)| state.hashes.get(*index) != Some(hash))
+                .map(|(index, _)| index as u64 + 1)
+                .collect();
+            let delta = dir.join(format!("selfspy-{}.delta.partial", stamp));
+            write_delta(snapshot, &delta, page_size, hashes.len() as u64, &changed)?;
+            (DELTA_EXTENSION, delta, changed.len() as u64)
+        }
+    };
+    
+    let encrypted = !settings.recipients.is_empty();
+    let name = format!(
+        "selfspy-{}.{}{}",
+        stamp,
+        extension,
+        if encrypted { AGE_SUFFIX } else { "" }
+    );
+    // A second backup under the same name would overwrite the first's file
+    // and be recorded as its own parent
+    if manifest.find(&name).is_some() {
+        if payload != snapshot {
+            let _ = std::fs::remove_file(&payload);
+        }
+        return Err(SelfspyError::Backup(format!("a backup named {} already exists", name)));
+    }
+    let stored = dir.join(&name);
+    let result = if encrypted {
+        encrypt_file(&payload, &stored, &settings.recipients)
+    } else {
+        std::fs::copy(&payload, &stored).map(|_| ()).map_err(SelfspyError::from)
+    };
+    if payload != snapshot {
+        let _ = std::fs::remove_file(&payload);
+    }
+    result?;
+    
+    if let Some(remote) = &settings.remote {
+        let uploaded = rclone(&["copyto", &path_str(&stored)?, &remote_path(remote, &name)]);
+        std::fs::remove_file(&stored)?;
+        uploaded?;
+    }
+    
+    let entry = BackupEntry {
+        name: name.clone(),
+        parent: previous.map(|state| state.name),
+        created_at: Utc::now(),
+        page_size,
+        page_count: hashes.len() as u64,
+        pages,
+        content_hash: content_hash(&hashes),
+    };
+    manifest.backups.push(entry.clone());
+    save_manifest(dir, settings, &manifest)?;
+    save_page_state(dir, &PageState { name, page_size, hashes })?;
+    
+    match &settings.remote {
+        Some(remote) => info!("Uploaded backup {} ({} pages) to {}", entry.name, pages, remote),
+        None => info!("Stored backup {} ({} pages) in {}", entry.name, pages, dir.display()),
+    }
+    Ok(entry)
+}
+
+// Backups in the manifest, oldest first
+pub fn list(config: &Config, settings: &BackupSettings) -> Result<Vec<BackupEntry>> {
+    Ok(load_manifest(config, settings)?.backups)
+}
+
+// Fetches, decrypts and replays a backup chain, verifies the result, then
+// swaps it in for the current database. The replaced database is kept next
+// to it as selfspy.db.before-restore-<time>. Fails while the monitor is running.
+pub async fn restore(config: &Config, settings: &BackupSettings, name: &str) -> Result<PathBuf> {
+    if !is_backup_name(name) || name.contains(['/', '\\']) {
+        return Err(SelfspyError::Backup(format!("'{}' is not a backup name", name)));
+    }
+    
+    let dir = BackupSettings::local_dir(config);
+    std::fs::create_dir_all(&dir)?;
+    let manifest = load_manifest(config, settings)?;
+    // Full backups made before the manifest existed restore on their own, unverified
+    let chain = match manifest.find(name) {
+        Some(_) => manifest.chain(name)?,
+        None if is_full_name(name) => Vec::new(),
+        None => return Err(SelfspyError::Backup(format!("backup {} is missing from the manifest", name))),
+    };
+    let names: Vec<&str> = if chain.is_empty() {
+        vec![name]
+    } else {
+        chain.iter().map(|entry| entry.name.as_str()).collect()
+    };
+    
+    let restored = config.database_path.with_extension("db.restore");
+    let _ = std::fs::remove_file(&restored);
+    let replayed = replay(&dir, settings, &names, &restored);
+    if let Err(e) = replayed {
+        let _ = std::fs::remove_file(&restored);
+        return Err(e);
+    }
+    
+    if let Some(tip) = chain.last() {
+        let (_, hashes) = hash_pages(&restored)?;
+        if content_hash(&hashes) != tip.content_hash {
+            std::fs::remove_file(&restored)?;
+            return Err(SelfspyError::InvalidData(format!(
+                "restoring {} did not reproduce the backed-up database",
+                name
+            )));
+        }
+    }
+    
+    swap_in(config, &restored, name).await
+}
+
+// Replaces the database with a plain copy, such as one made by `copy_to`,
+// after checking its integrity. Fails while the monitor is running.
+pub async fn restore_file(config: &Config, source: &Path) -> Result<PathBuf> {
+    if !source.exists() {
+        return Err(SelfspyError::Backup(format!("no database at {}", source.display())));
+    }
+    let restored = config.database_path.with_extension("db.restore");
+    let (from, to) = (source.to_path_buf(), restored.clone());
+    let copied = tokio::task::spawn_blocking(move || copy_database(&from, &to))
+        .await
+        .map_err(|e| SelfspyError::Backup(e.to_string()))?;
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(&restored);
+        return Err(e);
+    }
+    
+    swap_in(config, &restored, &source.display().to_string()).await
+}
+
+// Moves a restored copy over the database once it passes the integrity
+// check, keeping the replaced one as selfspy.db.before-restore-<time>.
+// Refuses while the monitor holds the data directory
+async fn swap_in(config: &Config, restored: &Path, name: &str) -> Result<PathBuf> {
+    let _lock = match crate::monitor::lock_data_dir(&config.data_dir) {
+        Ok(lock) => lock,
+        Err(e) => {
+            let _ = std::fs::remove_file(restored);
+            return Err(e);
+        }
+    };
+    
+    // The manifest hashes only catch accidental damage: the manifest itself
+    // is unsigned, so they prove nothing about who wrote a backup. This
+    // catches a database that was damaged before it was copied
+    let checked = async {
+        let check = Database::open_read_only(restored).await?;
+        let problems = check.integrity_check().await;
+        check.close().await;
+        problems
+    }
+    .await;
+    // Opening the copy leaves -shm/-wal files named after it; they hold nothing
+    remove_sidecars(restored);
+    let problems = match checked {
+        Ok(problems) => problems,
+        Err(e) => {
+            let _ = std::fs::remove_file(restored);
+            return Err(e);
+        }
+    };
+    if !problems.is_empty() {
+        std::fs::remove_file(restored)?;
+        return Err(SelfspyError::InvalidData(format!(
+            "backup {} failed the integrity check: {}",
+            name,
+            problems.join("; ")
+        )));
+    }
+    
+    let database = &config.database_path;
+    // Each restore keeps its own copy, so restoring twice never loses the first
+    let kept = format!("db.before-restore-{}", Utc::now().format("%Y%m%d-%H%M%S"));
+    if database.with_extension(&kept).exists() {
+        std::fs::remove_file(restored)?;
+        return Err(SelfspyError::Backup(format!(
+            "{} already exists; wait a second and restore again",
+            database.with_extension(&kept).display()
+        )));
+    }
+    if database.exists() {
+        std::fs::rename(database, database.with_extension(&kept))?;
+    }
+    // The replaced database's journal files go with it: applied to the restored
+    // one they would corrupt it, and its -wal may hold commits not yet in the file
+    for suffix in SIDECAR_SUFFIXES {
+        let journal = database.with_extension(format!("db-{}", suffix));
+        if journal.exists() {
+            std::fs::rename(&journal, database.with_extension(format!("{}-{}", kept, suffix)))?;
+        }
+    }
+    std::fs::rename(restored, database)?;
+    remove_sidecars(restored);
+    
+    info!("Restored {} to {}", name, database.display());
+    Ok(database.clone())
+}
+
+// Deletes the journal files SQLite keeps next to `path`
+fn remove_sidecars(path: &Path) {
+    for suffix in SIDECAR_SUFFIXES {
+        let mut sidecar = path.as_os_str().to_owned();
+        sidecar.push(format!("-{}", suffix));
+        let _ = std::fs::remove_file(sidecar);
+    }
+}
+
+// Applies each backup in order onto `target`
+fn replay(dir: &Path, settings: &BackupSettings, names: &[&str], target: &Path) -> Result<()> {
+    for name in names {
+        let (plain, temporary) = fetch_plaintext(dir, settings, name)?;
+        let applied = if is_full_name(name) {
+            std::fs::copy(&plain, target).map(|_| ()).map_err(SelfspyError::from)
+        } else {
+            apply_delta(&plain, target)
+        };
+        if temporary {
+            let _ = std::fs::remove_file(&plain);
+        }
+        applied?;
+    }
+    Ok(())
+}
+
+// A decrypted copy of a backup (downloading it first for remotes) and
+// whether that copy is temporary
+fn fetch_plaintext(dir: &Path, settings: &BackupSettings, name: &str) -> Result<(PathBuf, bool)> {
+    let source = dir.join(name);
+    let downloaded = match &settings.remote {
+        Some(remote) if !source.exists() => {
+            rclone(&["copyto", &remote_path(remote, name), &path_str(&source)?])?;
+            true
+        }
+        _ => false,
+    };
+    
+    if !name.ends_with(AGE_SUFFIX) {
+        return Ok((source, downloaded));
+    }
+    
+    let plain = dir.join(format!("{}.plain", name));
+    let result = match settings.identity_file.as_deref() {
+        Some(identity_file) => decrypt_file(&source, &plain, identity_file),
+        None => Err(SelfspyError::Backup(
+            "set backup.identity_file to the age key that can decrypt backups".to_string(),
+        )),
+    };
+    if downloaded {
+        let _ = std::fs::remove_file(&source);
+    }
+    result.map(|_| (plain, true))
+}
+
+fn is_full_name(name: &str) -> bool {
+    let name = name.strip_suffix(AGE_SUFFIX).unwrap_or(name);
+    name.ends_with(&format!(".{}", FULL_EXTENSION))
+}
+
+fn is_backup_name(name: &str) -> bool {
+    let bare = name.strip_suffix(AGE_SUFFIX).unwrap_or(name);
+    name.starts_with("selfspy-")
+        && name != MANIFEST_NAME
+        && (bare.ends_with(&format!(".{}", FULL_EXTENSION)) || bare.ends_with(&format!(".{}", DELTA_EXTENSION)))
+}
+
+fn load_manifest(config: &Config, settings: &BackupSettings) -> Result<Manifest> {
+    let text = match &settings.remote {
+        Some(remote) => {
+            let listing = rclone(&["lsf", "--files-only", remote])?;
+            if !listing.lines().any(|line| line.trim() == MANIFEST_NAME) {
+                return Ok(Manifest::default());
+            }
+            rclone(&["cat", &remote_path(remote, MANIFEST_NAME)])?
+        }
+        None => match std::fs::read_to_string(BackupSettings::local_dir(config).join(MANIFEST_NAME)) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Manifest::default()),
+            Err(e) => return Err(e.into()),
+        },
+    };
+    Ok(serde_json::from_str(&text)?)
+}
+
+// The manifest names no window titles, so it is stored unencrypted
+fn save_manifest(dir: &Path, settings: &BackupSettings, manifest: &Manifest) -> Result<()> {
+    let path = dir.join(MANIFEST_NAME);
+    std::fs::write(&path, serde_json::to_string_pretty(manifest)?)?;
+    if let Some(remote) = &settings.remote {
+        rclone(&["copyto", &path_str(&path)?, &remote_path(remote, MANIFEST_NAME)])?;
+    }
+    Ok(())
+}
+
+fn load_page_state(dir: &Path) -> Option<PageState> {
+    let text = std::fs::read_to_string(dir.join(PAGE_STATE_NAME)).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+fn save_page_state(dir: &Path, state: &PageState) -> Result<()> {
+    std::fs::write(dir.join(PAGE_STATE_NAME), serde_json::to_string(state)?)?;
+    Ok(())
+}
+
+// SQLite's online backup API copies pages verbatim, unlike VACUUM INTO, which
+// rebuilds the file and would turn every backup into a full one. The copy is
+// a single step: under WAL it only holds a read transaction, so the monitor
+// keeps writing, whereas copying in chunks restarts from the first page
+// whenever a write lands between two of them and may never finish.
+pub(crate) fn copy_database(source: &Path, destination: &Path) -> Result<()> {
+    use libsqlite3_sys as ffi;
+    
+    let open = |path: &Path, flags: i32| -> Result<*mut ffi::sqlite3> {
+        let path = CString::new(path_str(path)?).map_err(|e| SelfspyError::Backup(e.to_string()))?;
+        let mut db = std::ptr::null_mut();
+        let rc = unsafe { ffi::sqlite3_open_v2(path.as_ptr(), &mut db, flags, std::ptr::null()) };
+        if rc != ffi::SQLITE_OK {
+            let message = sqlite_message(db);
+            unsafe { ffi::sqlite3_close(db) };
+            return Err(SelfspyError::Backup(message));
+        }
+        Ok(db)
+    };
+    
+    let _ = std::fs::remove_file(destination);
+    let src = open(source, ffi::SQLITE_OPEN_READONLY)?;
+    let dst = match open(destination, ffi::SQLITE_OPEN_READWRITE | ffi::SQLITE_OPEN_CREATE) {
+        Ok(dst) => dst,
+        Err(e) => {
+            unsafe { ffi::sqlite3_close(src) };
+            return Err(e);
+        }
+    };
+    
+    let result = unsafe {
+        let backup = ffi::sqlite3_backup_init(dst, c"main".as_ptr(), src, c"main".as_ptr());
+        if backup.is_null() {
+            Err(SelfspyError::Backup(sqlite_message(dst)))
+        } else {
+            loop {
+                match ffi::sqlite3_backup_step(backup, -1) {
+                    ffi::SQLITE_OK => std::thread::sleep(std::time::Duration::from_millis(5)),
+                    ffi::SQLITE_BUSY | ffi::SQLITE_LOCKED => std::thread::sleep(std::time::Duration::from_millis(100)),
+                    _ => break,
+                }
+            }
+            match ffi::sqlite3_backup_finish(backup) {
+                ffi::SQLITE_OK => Ok(()),
+                _ => Err(SelfspyError::Backup(sqlite_message(dst))),
+            }
+        }
+    };
+    
+    unsafe {
+        ffi::sqlite3_close(dst);
+        ffi::sqlite3_close(src);
+    }
+    result
+}
+
+fn sqlite_message(db: *mut libsqlite3_sys::sqlite3) -> String {
+    unsafe {
+        let message = libsqlite3_sys::sqlite3_errmsg(db);
+        if message.is_null() {
+            "SQLite error".to_string()
+        } else {
+            std::ffi::CStr::from_ptr(message).to_string_lossy().into_owned()
+        }
+    }
+}
+
+// Page size from the database header (offset 16, big-endian; 1 means 65536)
+// and a hash of every page
+fn hash_pages(path: &Path) -> Result<(u32, Vec<u64>)> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut header = [0u8; 100];
+    file.read_exact(&mut header)?;
+    let page_size = match u16::from_be_bytes([header[16], header[17]]) {
+        1 => 65536,
+        size => size as u32,
+    };
+    if !page_size.is_power_of_two() || page_size < 512 {
+        return Err(SelfspyError::InvalidData(format!("{} is not a SQLite database", path.display())));
+    }
+    
+    file.seek(SeekFrom::Start(0))?;
+    let mut hashes = Vec::new();
+    let mut page = vec![0u8; page_size as usize];
+    loop {
+        match file.read_exact(&mut page) {
+            Ok(()) => hashes.push(fnv1a(&page)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok((page_size, hashes))
+}
+
+// FNV-1a: stable across builds and platforms, which std's hasher is not.
+// It detects damage, not tampering. Also fingerprints rows for db.rs.
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+// Hash of the whole database: the page count and every page hash
+fn content_hash(hashes: &[u64]) -> String {
+    let mut bytes = Vec::with_capacity(8 + hashes.len() * 8);
+    bytes.extend_from_slice(&(hashes.len() as u64).to_le_bytes());
+    for hash in hashes {
+        bytes.extend_from_slice(&hash.to_le_bytes());
+    }
+    format!("{:016x}", fnv1a(&bytes))
+}
+
+// Delta format: magic, page size (u32 LE), page count (u64 LE), then each
+// changed page as its 1-based number (u64 LE) followed by its bytes
+fn write_delta(snapshot: &Path, delta: &Path, page_size: u32, page_count: u64, changed: &[u64]) -> Result<()> {
+    let mut source = File::open(snapshot)?;
+    let mut out = BufWriter::new(File::create(delta)?);
+    out.write_all(DELTA_MAGIC)?;
+    out.write_all(&page_size.to_le_bytes())?;
+    out.write_all(&page_count.to_le_bytes())?;
+    
+    let mut page = vec![0u8; page_size as usize];
+    for number in changed {
+        source.seek(SeekFrom::Start((number - 1) * page_size as u64))?;
+        source.read_exact(&mut page)?;
+        out.write_all(&number.to_le_bytes())?;
+        out.write_all(&page)?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn apply_delta(delta: &Path, target: &Path) -> Result<()> {
+    let invalid = || SelfspyError::InvalidData(format!("{} is not a selfspy backup delta", delta.display()));
+    let mut input = BufReader::new(File::open(delta)?);
+    let mut magic = vec![0u8; DELTA_MAGIC.len()];
+    input.read_exact(&mut magic)?;
+    if magic != DELTA_MAGIC {
+        return Err(invalid());
+    }
+    let mut size = [0u8; 4];
+    let mut count = [0u8; 8];
+    input.read_exact(&mut size)?;
+    input.read_exact(&mut count)?;
+    let page_size = u32::from_le_bytes(size) as u64;
+    let page_count = u64::from_le_bytes(count);
+    
+    let mut output = OpenOptions::new().write(true).open(target)?;
+    let mut page = vec![0u8; page_size as usize];
+    let mut number = [0u8; 8];
+    loop {
+        match input.read_exact(&mut number) {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        }
+        let number = u64::from_le_bytes(number);
+        if number == 0 || number > page_count {
+            return Err(invalid());
+        }
+        input.read_exact(&mut page)?;
+        output.seek(SeekFrom::Start((number - 1) * page_size))?;
+        output.write_all(&page)?;
+    }
+    // Databases can shrink (after a vacuum), so trim to the recorded size
+    output.set_len(page_count * page_size)?;
+    Ok(())
+}
+
+fn encrypt_file(source: &Path, destination: &Path, recipients: &[String]) -> Result<()> {
+    let recipients = recipients
+        .iter()
+        .map(|key| {
+            age::x25519::Recipient::from_str(key.trim())
+                .map(|recipient| Box::new(recipient) as Box<dyn age::Recipient + Send>)
+                .map_err(|e| SelfspyError::Backup(format!("invalid age recipient '{}': {}", key, e)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let encryptor = age::Encryptor::with_recipients(recipients)
+        .ok_or_else(|| SelfspyError::Backup("no age recipients configured".to_string()))?;
+    
+    let mut writer = encryptor
+        .wrap_output(File::create(destination)?)
+        .map_err(|e| SelfspyError::Encryption(e.to_string()))?;
+    io::copy(&mut File::open(source)?, &mut writer)?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn decrypt_file(source: &Path, destination: &Path, identity_file: &Path) -> Result<()> {
+    let identities = read_identities(identity_file)?;
+    let decryptor = match age::Decryptor::new(BufReader::new(File::open(source)?)) {
+        Ok(age::Decryptor::Recipients(decryptor)) => decryptor,
+        Ok(_) => return Err(SelfspyError::Backup("backup is passphrase-encrypted, expected a key".to_string())),
+        Err(e) => return Err(SelfspyError::Encryption(e.to_string())),
+    };
+    
+    let mut reader = decryptor
+        .decrypt(identities.iter().map(|identity| identity as &dyn age::Identity))
+        .map_err(|e| match e {
+            age::DecryptError::NoMatchingKeys => SelfspyError::WrongPassword,
+            e => SelfspyError::Encryption(e.to_string()),
+        })?;
+    // A truncated or modified payload fails authentication mid-copy
+    let copied = io::copy(&mut reader, &mut File::create(destination)?);
+    if let Err(e) = copied {
+        let _ = std::fs::remove_file(destination);
+        return Err(SelfspyError::Encryption(format!("backup failed verification: {}", e)));
+    }
+    Ok(())
+}
+
+// age identity files: one AGE-SECRET-KEY-1... per line, # comments allowed
+fn read_identities(path: &Path) -> Result<Vec<age::x25519::Identity>> {
+    let mut identities = Vec::new();
+    for line in BufReader::new(File::open(path)?).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let identity = age::x25519::Identity::from_str(line)
+            .map_err(|e| SelfspyError::Backup(format!("invalid identity in {}: {}", path.display(), e)))?;
+        identities.push(identity);
+    }
+    
+    if identities.is_empty() {
+        return Err(SelfspyError::Backup(format!("no age identities in {}", path.display())));
+    }
+    Ok(identities)
+}
+
+fn remote_path(remote: &str, name: &str) -> String {
+    if remote.ends_with(':') || remote.ends_with('/') {
+        format!("{}{}", remote, name)
+    } else {
+        format!("{}/{}", remote, name)
+    }
+}
+
+fn path_str(path: &Path) -> Result<String> {
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| SelfspyError::Backup(format!("non UTF-8 path {}", path.display())))
+}
+
+fn rclone(args: &[&str]) -> Result<String> {
+    let output = Command::new("rclone").args(args).output().map_err(|e| {
+        if e.kind() == io::ErrorKind::NotFound {
+            SelfspyError::Backup("remote backups need rclone on the PATH (https://rclone.org)".to_string())
+        } else {
+            SelfspyError::Io(e)
+        }
+    })?;
+    
+    if !output.status.success() {
+        return Err(SelfspyError::Backup(format!(
+            "rclone {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,62 @@
+// Baselines ("my average day") computed from daily summaries, skipping
+// date ranges the user excluded (vacation, illness) so time off doesn't
+// drag the averages down.
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use crate::DailySummary;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExcludedRange {
+    // Both ends inclusive
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub reason: Option<String>,
+}
+
+impl ExcludedRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        date >= self.start && date <= self.end
+    }
+}
+
+pub fn is_excluded(exclusions: &[ExcludedRange], date: NaiveDate) -> bool {
+    exclusions.iter().any(|range| range.contains(date))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Baseline {
+    pub days: usize,
+    pub avg_active_seconds: f64,
+    pub avg_keystrokes: f64,
+    pub avg_clicks: f64,
+}
+
+// Averages per recorded day, leaving out excluded dates
+pub fn compute_baseline(summaries: &[DailySummary], exclusions: &[ExcludedRange]) -> Baseline {
+    let included: Vec<&DailySummary> = summaries
+        .iter()
+        .filter(|summary| !is_excluded(exclusions, summary.date))
+        .collect();
+    
+    if included.is_empty() {
+        return Baseline::default();
+    }
+    
+    let days = included.len() as f64;
+    Baseline {
+        days: included.len(),
+        avg_active_seconds: included.iter().map(|s| s.active_seconds as f64).sum::<f64>() / days,
+        avg_keystrokes: included.iter().map(|s| s.keystrokes as f64).sum::<f64>() / days,
+        avg_clicks: included.iter().map(|s| s.clicks as f64).sum::<f64>() / days,
+    }
+}
+
+// Relative change from baseline to current, None when there's no baseline
+pub fn percent_change(current: f64, baseline: f64) -> Option<f64> {
+    if baseline == 0.0 {
+        None
+    } else {
+        Some((current - baseline) / baseline * 100.0)
+    }
+}
\ No newline at end of file
@@ -0,0 +1,74 @@
+// Active tab URLs reported by the browser extension through
+// selfspy-browser-host. Only the domain is kept unless full URLs are
+// enabled, and excluded domains (with their subdomains) are dropped entirely.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BrowserSettings {
+    // Store the full URL (path and query included) instead of just the domain
+    pub store_full_urls: bool,
+    // Never record these domains or any of their subdomains
+    pub excluded_domains: Vec<String>,
+}
+
+impl Default for BrowserSettings {
+    fn default() -> Self {
+        Self {
+            store_full_urls: false,
+            excluded_domains: vec![
+                "accounts.google.com".to_string(),
+                "login.microsoftonline.com".to_string(),
+            ],
+        }
+    }
+}
+
+// What gets stored for one tab after privacy filtering
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabLocation {
+    pub domain: String,
+    pub url: Option<String>,
+}
+
+impl BrowserSettings {
+    // None for non-web pages (about:, file:, extension pages) and excluded domains
+    pub fn filter(&self, url: &str) -> Option<TabLocation> {
+        let domain = domain_of(url)?;
+        if self.is_excluded(&domain) {
+            return None;
+        }
+        
+        Some(TabLocation {
+            url: self.store_full_urls.then(|| url.to_string()),
+            domain,
+        })
+    }
+    
+    pub fn is_excluded(&self, domain: &str) -> bool {
+        self.excluded_domains.iter().any(|excluded| {
+            let excluded = excluded.trim().trim_start_matches("*.").to_ascii_lowercase();
+            !excluded.is_empty()
+                && (domain == excluded
+                    || domain.strip_suffix(&excluded).is_some_and(|prefix| prefix.ends_with('.')))
+        })
+    }
+}
+
+// Lowercased host of an http(s) URL, without credentials or port
+pub fn domain_of(url: &str) -> Option<String> {
+    let (scheme, rest) = url.split_once("://")?;
+    if !scheme.eq_ignore_ascii_case("http") && !scheme.eq_ignore_ascii_case("https") {
+        return None;
+    }
+    
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = match host.strip_prefix('[') {
+        // IPv6 literal, keep the brackets off
+        Some(ipv6) => ipv6.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+    
+    (!host.is_empty()).then(|| host.to_ascii_lowercase())
+}
@@ -0,0 +1,114 @@
+// Week boundaries and numbering. Weekly reports, goals and heatmaps start
+// the week on the configured day; by default that follows the locale, as
+// most calendars do (Sunday in the US, Saturday in much of the Middle East,
+// Monday elsewhere).
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirstDayOfWeek {
+    // From the region in LC_ALL / LC_TIME / LANG, Monday when unknown
+    #[default]
+    Locale,
+    Monday,
+    Sunday,
+    Saturday,
+}
+
+impl FirstDayOfWeek {
+    pub const ALL: [FirstDayOfWeek; 4] = [
+        FirstDayOfWeek::Locale,
+        FirstDayOfWeek::Monday,
+        FirstDayOfWeek::Sunday,
+        FirstDayOfWeek::Saturday,
+    ];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            FirstDayOfWeek::Locale => "From locale",
+            FirstDayOfWeek::Monday => "Monday",
+            FirstDayOfWeek::Sunday => "Sunday",
+            FirstDayOfWeek::Saturday => "Saturday",
+        }
+    }
+    
+    pub fn weekday(self) -> Weekday {
+        match self {
+            FirstDayOfWeek::Locale => locale_first_day(),
+            FirstDayOfWeek::Monday => Weekday::Mon,
+            FirstDayOfWeek::Sunday => Weekday::Sun,
+            FirstDayOfWeek::Saturday => Weekday::Sat,
+        }
+    }
+}
+
+// Regions whose calendars start the week on Sunday or Saturday (CLDR)
+const SUNDAY_REGIONS: &[&str] = &[
+    "AG", "AS", "BD", "BR", "BS", "BT", "BW", "BZ", "CA", "CO", "DM", "DO", "ET", "GT", "GU", "HK", "HN", "ID",
+    "IL", "IN", "JM", "JP", "KE", "KH", "KR", "LA", "MH", "MM", "MO", "MT", "MX", "MZ", "NI", "NP", "PA", "PE",
+    "PH", "PK", "PR", "PT", "PY", "SA", "SG", "SV", "TH", "TT", "TW", "UM", "US", "VE", "VI", "WS", "YE", "ZA",
+    "ZW",
+];
+const SATURDAY_REGIONS: &[&str] = &["AE", "AF", "BH", "DJ", "DZ", "EG", "IQ", "IR", "JO", "KW", "LY", "OM", "QA", "SD", "SY"];
+
+fn locale_first_day() -> Weekday {
+    // The first set variable wins, as in setlocale; "en_US.UTF-8" -> "US"
+    let locale = ["LC_ALL", "LC_TIME", "LANG"]
+        .iter()
+        .filter_map(|name| std::env::var(name).ok())
+        .find(|value| !value.is_empty());
+    let region = locale.as_deref().and_then(|locale| {
+        let locale = locale.split(['.', '@']).next()?;
+        let (_, region) = locale.split_once(['_', '-'])?;
+        Some(region.to_ascii_uppercase())
+    });
+    
+    match region.as_deref() {
+        Some(region) if SUNDAY_REGIONS.contains(&region) => Weekday::Sun,
+        Some(region) if SATURDAY_REGIONS.contains(&region) => Weekday::Sat,
+        _ => Weekday::Mon,
+    }
+}
+
+// First day of the week containing `date`
+pub fn week_start(date: NaiveDate, first_day: Weekday) -> NaiveDate {
+    date - Duration::days(date.weekday().days_since(first_day) as i64)
+}
+
+// Year and week number for labels. Monday weeks use ISO 8601 numbering
+// (week 1 holds the first Thursday); other weeks count from the one
+// containing 1 January, as US and Middle Eastern calendars do. That week
+// belongs to the new year even when it starts in December.
+pub fn week_number(date: NaiveDate, first_day: Weekday) -> (i32, u32) {
+    if first_day == Weekday::Mon {
+        let week = date.iso_week();
+        return (week.year(), week.week());
+    }
+    
+    let start = week_start(date, first_day);
+    let year = (start + Duration::days(6)).year();
+    let january_first = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+    let weeks = (start - week_start(january_first, first_day)).num_weeks();
+    (year, weeks as u32 + 1)
+}
+
+// "2026-W42", for the week containing `date`
+pub fn week_label(date: NaiveDate, first_day: Weekday) -> String {
+    let (year, week) = week_number(date, first_day);
+    format!("{}-W{:02}", year, week)
+}
+
+// The days of the week in display order, starting with `first_day`
+pub fn weekdays_from(first_day: Weekday) -> [Weekday; 7] {
+    const MONDAY_FIRST: [Weekday; 7] = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ];
+    std::array::from_fn(|offset| MONDAY_FIRST[(first_day.num_days_from_monday() as usize + offset) % 7])
+}
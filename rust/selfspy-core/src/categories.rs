@@ -0,0 +1,77 @@
+// Category and project tags for windows, for example
+//
+//   "category_rules": [
+//     { "category": "Communication", "apps": ["Slack", "Mail"] },
+//     { "project": "selfspy", "title_keywords": ["selfspy"], "domains": ["github.com"] }
+//   ]
+//
+// The monitor tags each window as it records it and stores the tags on the
+// window row, so reports don't re-run the rules. A window gets the category
+// of the first matching rule that sets one, and the project likewise, so a
+// project rule can add to a category rule. After the rules change,
+// `selfstats reclassify` re-tags windows recorded earlier.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CategoryRule {
+    pub category: Option<String>,
+    pub project: Option<String>,
+    // The rule matches when any of these does, compared case-insensitively
+    pub apps: Vec<String>,
+    pub title_keywords: Vec<String>,
+    // Browser tab domains, including their subdomains
+    pub domains: Vec<String>,
+}
+
+impl CategoryRule {
+    fn matches(&self, process: &str, title: &str, domain: Option<&str>) -> bool {
+        if self.apps.iter().any(|app| app.eq_ignore_ascii_case(process)) {
+            return true;
+        }
+        
+        let title = title.to_lowercase();
+        if self.title_keywords.iter().any(|keyword| title.contains(&keyword.to_lowercase())) {
+            return true;
+        }
+        
+        let Some(domain) = domain.map(str::to_lowercase) else {
+            return false;
+        };
+        self.domains.iter().any(|rule| {
+            let rule = rule.to_lowercase();
+            domain == rule || domain.ends_with(&format!(".{}", rule))
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WindowTags {
+    pub category: Option<String>,
+    pub project: Option<String>,
+}
+
+impl WindowTags {
+    pub fn is_empty(&self) -> bool {
+        self.category.is_none() && self.project.is_none()
+    }
+}
+
+pub fn classify(rules: &[CategoryRule], process: &str, title: &str, domain: Option<&str>) -> WindowTags {
+    let mut tags = WindowTags::default();
+    for rule in rules {
+        if (tags.category.is_some() || rule.category.is_none()) && (tags.project.is_some() || rule.project.is_none()) {
+            continue;
+        }
+        if !rule.matches(process, title, domain) {
+            continue;
+        }
+        if tags.category.is_none() {
+            tags.category = rule.category.clone();
+        }
+        if tags.project.is_none() {
+            tags.project = rule.project.clone();
+        }
+    }
+    tags
+}
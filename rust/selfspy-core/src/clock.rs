@@ -0,0 +1,79 @@
+// Time source for the monitor's time-based checks: idle and input stall
+// detection, sleep gaps, power sampling and the weekly after-hours alert.
+// Embedders and tests can swap in a ManualClock to drive them without
+// waiting on real time.
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+pub trait Clock: Send + Sync {
+    // Wall-clock time; may jump (suspend, NTP, DST changes local time only)
+    fn now(&self) -> DateTime<Utc>;
+    
+    // Monotonic time for measuring intervals
+    fn instant(&self) -> Instant;
+    
+    fn today(&self) -> NaiveDate {
+        self.now().with_timezone(&Local).date_naive()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+    
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// A clock that only moves when told to. `advance` moves wall-clock and
+// monotonic time together, like time passing; `jump_to` moves only the
+// wall clock, like a suspend or a clock change seen between two ticks.
+#[derive(Debug)]
+pub struct ManualClock {
+    origin: Instant,
+    state: Mutex<ManualState>,
+}
+
+#[derive(Debug)]
+struct ManualState {
+    now: DateTime<Utc>,
+    elapsed: Duration,
+}
+
+impl ManualClock {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        Self {
+            origin: Instant::now(),
+            state: Mutex::new(ManualState {
+                now,
+                elapsed: Duration::ZERO,
+            }),
+        }
+    }
+    
+    pub fn advance(&self, by: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.now += chrono::Duration::from_std(by).unwrap_or(chrono::Duration::MAX);
+        state.elapsed += by;
+    }
+    
+    pub fn jump_to(&self, now: DateTime<Utc>) {
+        self.state.lock().unwrap().now = now;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.state.lock().unwrap().now
+    }
+    
+    fn instant(&self) -> Instant {
+        self.origin + self.state.lock().unwrap().elapsed
+    }
+}
@@ -1,16 +1,181 @@
 use directories::ProjectDirs;
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
-use anyhow::Result;
+use std::time::Duration;
+use crate::error::Result;
+
+use crate::accounts::AccountReportSettings;
+use crate::after_hours::WorkingHours;
+use crate::alerts::AlertRule;
+use crate::aggregate::AggregationSettings;
+use crate::backup::BackupSettings;
+use crate::baseline::ExcludedRange;
+use crate::browser::BrowserSettings;
+use crate::calendar::FirstDayOfWeek;
+use crate::categories::CategoryRule;
+use crate::db::DatabaseSettings;
+use crate::control::ControlSettings;
+use crate::custom_metrics::CustomMetric;
+use crate::deep_work::DeepWorkSettings;
+use crate::distractions::DistractionSettings;
+use crate::encryption::{KdfSettings, KeyStorage};
+use crate::files;
+use crate::mqtt::MqttSettings;
+use crate::notifications::NotificationSettings;
+use crate::slash::SlashCommandSettings;
+use crate::titles::default_volatile_patterns;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Config {
     pub data_dir: PathBuf,
     pub database_path: PathBuf,
+    // Journal mode, sync level and lock timeout for writing connections
+    pub database: DatabaseSettings,
     pub encryption_enabled: bool,
+    // Argon2id cost of the next key derived, see encryption.rs
+    pub kdf: KdfSettings,
+    // "password", or "platform" for a key sealed by the TPM / Secure Enclave
+    pub key_storage: KeyStorage,
     pub exclude_apps: Vec<String>,
     pub idle_timeout_seconds: u64,
     pub flush_interval_seconds: u64,
+    pub start_hidden: bool,
+    pub respect_do_not_disturb: bool,
+    pub tag_do_not_disturb_periods: bool,
+    pub track_presenting: bool,
+    pub pause_text_while_presenting: bool,
+    // Record what the system media session is playing (opt-in)
+    pub track_media: bool,
+    // Log meetings from microphone and camera use (opt-in)
+    pub track_meetings: bool,
+    // Log the power source and battery charge on laptops
+    pub track_power: bool,
+    // Below this much free space on the data directory's filesystem the
+    // monitor records counts only; 0 disables the check, though a full disk
+    // still does this until space is freed
+    pub min_free_disk_mb: u64,
+    // Database calls at least this slow are logged to slow_queries.log;
+    // 0 disables the log
+    pub slow_query_ms: u64,
+    // Events older than this many days are deleted once a day by the monitor
+    // and by `selfspy prune`; None keeps everything
+    pub retention_days: Option<i64>,
+    // Keep per-day totals of pruned days so long-range reports still cover them
+    pub keep_daily_rollups: bool,
+    pub capture: CaptureSettings,
+    pub volatile_title_patterns: Vec<String>,
+    pub max_keys_per_second: u32,
+    pub max_clicks_per_second: u32,
+    pub stuck_key_repeat_limit: u32,
+    pub baseline_exclusions: Vec<ExcludedRange>,
+    // Where weekly reports, goals and heatmaps start the week
+    pub first_day_of_week: FirstDayOfWeek,
+    pub working_hours: WorkingHours,
+    // Notify once a week when after-hours activity passes this many minutes
+    pub after_hours_alert_minutes: Option<i64>,
+    // Desktop, webhook and email alerts with rate limits and quiet hours
+    pub notifications: NotificationSettings,
+    // Thresholds on today's activity that the monitor alerts on, see alerts.rs
+    pub alert_rules: Vec<AlertRule>,
+    pub deep_work: DeepWorkSettings,
+    pub distractions: DistractionSettings,
+    // Category and project tags stored on each window, see categories.rs
+    pub category_rules: Vec<CategoryRule>,
+    // Reports pinned to the GUI dashboard, in display order
+    pub pinned_reports: Vec<PinnedReport>,
+    // Derived metrics shown alongside the built-in ones, see custom_metrics.rs
+    pub custom_metrics: Vec<CustomMetric>,
+    // Localhost HTTP and MIDI control of a running monitor
+    pub control: ControlSettings,
+    // Tab URLs reported by the browser extension, see browser.rs
+    pub browser: BrowserSettings,
+    // Publish state and daily totals to an MQTT broker when set
+    pub mqtt: Option<MqttSettings>,
+    // Answer Slack/Teams "/selfspy today" commands when set
+    pub slash_commands: Option<SlashCommandSettings>,
+    // Other people's data directories for `selfstats household`
+    pub aggregation: AggregationSettings,
+    // Other accounts' data directories for `selfstats accounts`
+    pub account_reports: AccountReportSettings,
+    // Where `selfspy backup` stores snapshots and who can decrypt them
+    pub backup: BackupSettings,
+}
+
+// A report configuration shown as a dashboard card
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PinnedReport {
+    pub title: String,
+    pub metric: PinnedMetric,
+    // Days ending today; None for all time
+    pub days: Option<i64>,
+    // Name of the custom metric shown when `metric` is custom
+    #[serde(default)]
+    pub custom_metric: Option<String>,
+    // Narrow app time to one app and/or local hour of the day
+    #[serde(default)]
+    pub app: Option<String>,
+    #[serde(default)]
+    pub hour: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PinnedMetric {
+    Keystrokes,
+    Clicks,
+    ActiveTime,
+    AppTime,
+    DeepWork,
+    Custom,
+}
+
+impl PinnedMetric {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PinnedMetric::Keystrokes => "Keystrokes",
+            PinnedMetric::Clicks => "Clicks",
+            PinnedMetric::ActiveTime => "Active Time",
+            PinnedMetric::AppTime => "App Time",
+            PinnedMetric::DeepWork => "Deep Work",
+            PinnedMetric::Custom => "Custom Metric",
+        }
+    }
+}
+
+// Independent switches for each kind of data the monitor records, so
+// deployments can run e.g. title-only or counts-only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CaptureSettings {
+    pub key_text: bool,
+    pub key_counts: bool,
+    pub clicks: bool,
+    pub mouse_movement: bool,
+    pub scrolls: bool,
+    pub window_titles: bool,
+    // Open file path or tab name, on backends that report it
+    pub documents: bool,
+    pub window_geometry: bool,
+    // Timings of typed bigrams and trigrams for `selfstats typing`. Derived
+    // from which keys were typed, so off unless asked for.
+    pub key_ngrams: bool,
+}
+
+impl Default for CaptureSettings {
+    fn default() -> Self {
+        Self {
+            key_text: true,
+            key_counts: true,
+            clicks: true,
+            mouse_movement: true,
+            scrolls: true,
+            window_titles: true,
+            documents: true,
+            window_geometry: true,
+            key_ngrams: false,
+        }
+    }
 }
 
 impl Default for Config {
@@ -24,7 +189,10 @@ impl Default for Config {
         Self {
             data_dir,
             database_path,
+            database: DatabaseSettings::default(),
             encryption_enabled: true,
+            kdf: KdfSettings::default(),
+            key_storage: KeyStorage::default(),
             exclude_apps: vec![
                 "1Password".to_string(),
                 "Bitwarden".to_string(),
@@ -32,6 +200,41 @@ impl Default for Config {
             ],
             idle_timeout_seconds: 180,
             flush_interval_seconds: 10,
+            start_hidden: false,
+            respect_do_not_disturb: true,
+            tag_do_not_disturb_periods: false,
+            track_presenting: true,
+            pause_text_while_presenting: false,
+            track_media: false,
+            track_meetings: false,
+            track_power: true,
+            min_free_disk_mb: 500,
+            slow_query_ms: crate::query_stats::DEFAULT_SLOW_QUERY_MS,
+            retention_days: None,
+            keep_daily_rollups: true,
+            capture: CaptureSettings::default(),
+            volatile_title_patterns: default_volatile_patterns(),
+            max_keys_per_second: 25,
+            max_clicks_per_second: 15,
+            stuck_key_repeat_limit: 200,
+            baseline_exclusions: Vec::new(),
+            first_day_of_week: FirstDayOfWeek::default(),
+            working_hours: WorkingHours::default(),
+            after_hours_alert_minutes: None,
+            notifications: NotificationSettings::default(),
+            alert_rules: Vec::new(),
+            deep_work: DeepWorkSettings::default(),
+            distractions: DistractionSettings::default(),
+            category_rules: Vec::new(),
+            pinned_reports: Vec::new(),
+            custom_metrics: Vec::new(),
+            control: ControlSettings::default(),
+            browser: BrowserSettings::default(),
+            mqtt: None,
+            slash_commands: None,
+            aggregation: AggregationSettings::default(),
+            account_reports: AccountReportSettings::default(),
+            backup: BackupSettings::default(),
         }
     }
 }
@@ -51,4 +254,39 @@ impl Config {
         std::fs::create_dir_all(&self.data_dir)?;
         Ok(())
     }
+    
+    pub fn config_path(&self) -> PathBuf {
+        self.data_dir.join("config.json")
+    }
+    
+    pub fn slow_query_threshold(&self) -> Option<Duration> {
+        (self.slow_query_ms > 0).then(|| Duration::from_millis(self.slow_query_ms))
+    }
+    
+    // Load saved settings from the default data directory, falling back to defaults
+    pub fn load() -> Result<Self> {
+        Self::load_from(Self::default().data_dir)
+    }
+    
+    // Loads config.json from a specific data directory, falling back to
+    // defaults rooted there
+    pub fn load_from(data_dir: PathBuf) -> Result<Self> {
+        let config = Self::default().with_data_dir(data_dir);
+        let path = config.config_path();
+        
+        if !path.exists() {
+            return Ok(config);
+        }
+        
+        let contents = std::fs::read_to_string(&path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+    
+    // Written 0600 since it can hold credentials such as the MQTT password
+    pub fn save(&self) -> Result<()> {
+        self.ensure_directories()?;
+        let contents = serde_json::to_string_pretty(self)?;
+        files::write_private(&self.config_path(), contents.as_bytes())?;
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,342 @@
+// Remote control of a running monitor for hardware macro pads (Stream Deck,
+// MIDI controllers) and scripts. A small HTTP/1.1 server bound to localhost
+// exposes these routes:
+//
+//   GET  /status    monitor state as JSON
+//   POST /pause     stop recording until resumed
+//   POST /resume    start recording again
+//   POST /toggle    pause if recording, resume if paused
+//   POST /annotate  drop a timeline marker; the request body, if any, is its note
+//
+// Every route answers with a JSON body. Requests carrying an Origin header are
+// refused so web pages open in a browser can't drive the endpoint, and so are
+// requests naming any host but localhost, which a page could otherwise send
+// by rebinding its own domain to 127.0.0.1.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+use crate::error::Result;
+use crate::platform::PlatformTracker;
+use crate::ActivityMonitor;
+
+// Headers plus a short note; anything larger isn't a control request
+const MAX_REQUEST_BYTES: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ControlSettings {
+    // Port for the localhost HTTP endpoint; disabled when unset
+    pub http_port: Option<u16>,
+    // Note-on messages mapped to actions (needs the `midi` feature)
+    pub midi: Option<MidiSettings>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MidiSettings {
+    // Connect to the first input port whose name contains this; any port when empty
+    pub port: String,
+    // Note number (0-127) to action, e.g. {"36": "toggle", "37": {"annotate": "break"}}
+    pub notes: HashMap<u8, ControlAction>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlAction {
+    Pause,
+    Resume,
+    Toggle,
+    Annotate(Option<String>),
+}
+
+impl ControlAction {
+    // Returns the marker id for annotations
+    pub async fn apply<T: PlatformTracker + ?Sized + 'static>(&self, monitor: &ActivityMonitor<T>) -> Result<Option<i64>> {
+        match self {
+            ControlAction::Pause => monitor.pause().await?,
+            ControlAction::Resume => monitor.resume().await?,
+            ControlAction::Toggle => {
+                if monitor.is_paused().await {
+                    monitor.resume().await?
+                } else {
+                    monitor.pause().await?
+                }
+            }
+            ControlAction::Annotate(note) => return Ok(Some(monitor.annotate(note.as_deref()).await?)),
+        }
+        Ok(None)
+    }
+}
+
+// Serves the control routes until the monitor shuts down
+pub async fn serve<T: PlatformTracker + ?Sized + 'static>(monitor: Arc<ActivityMonitor<T>>, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    info!("Control endpoint listening on http://127.0.0.1:{}", port);
+    
+    let shutdown = monitor.shutdown_token();
+    loop {
+        let (stream, _) = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            accepted = listener.accept() => accepted?,
+        };
+        
+        let monitor = monitor.clone();
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &monitor).await {
+                debug!("Control request failed: {}", e);
+            }
+        });
+    }
+}
+
+// Public, with `read_request` and `refusal`, so tests can check requests
+// without a running monitor
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub has_origin: bool,
+    // Names as sent; look them up with `header`
+    pub headers: Vec<(String, String)>,
+    // As received: webhook signatures cover these exact bytes
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+    
+    pub fn body_text(&self) -> std::borrow::Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+    
+    // True unless a Host header names something other than this machine's
+    // loopback address; clients that send none (HTTP/1.0) are local anyway
+    pub fn has_local_host(&self) -> bool {
+        let Some(host) = self.header("host") else {
+            return true;
+        };
+        // Drop the port, minding the colons inside "[::1]"
+        let name = match host.rsplit_once(':') {
+            Some((name, port)) if !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()) => name,
+            _ => host,
+        };
+        name.eq_ignore_ascii_case("localhost") || name == "127.0.0.1" || name == "[::1]"
+    }
+}
+
+async fn handle_connection<T: PlatformTracker + ?Sized + 'static>(
+    mut stream: TcpStream,
+    monitor: &ActivityMonitor<T>,
+) -> Result<()> {
+    let (status, body) = match read_request(&mut stream).await? {
+        None => (400, serde_json::json!({ "error": "malformed request" })),
+        Some(request) => match refusal(&request) {
+            Some(refused) => refused,
+            None => route(&request, monitor).await,
+        },
+    };
+    write_response(&mut stream, status, &body).await
+}
+
+// The response for a request refused before routing: one sent by a browser,
+// or naming a host other than localhost
+pub fn refusal(request: &Request) -> Option<(u16, serde_json::Value)> {
+    if request.has_origin {
+        Some((403, serde_json::json!({ "error": "browser requests are not accepted" })))
+    } else if !request.has_local_host() {
+        Some((403, serde_json::json!({ "error": "unknown host" })))
+    } else {
+        None
+    }
+}
+
+pub(crate) async fn write_response(stream: &mut TcpStream, status: u16, body: &serde_json::Value) -> Result<()> {
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason(status),
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+    Ok(())
+}
+
+async fn route<T: PlatformTracker + ?Sized + 'static>(
+    request: &Request,
+    monitor: &ActivityMonitor<T>,
+) -> (u16, serde_json::Value) {
+    // Query strings are accepted and ignored, so cache-busting pads still work
+    let path = request.path.split('?').next().unwrap_or_default();
+    
+    let action = match (request.method.as_str(), path) {
+        ("GET", "/status") => {
+            return (200, serde_json::to_value(monitor.status().await).unwrap_or_default());
+        }
+        ("POST", "/pause") => ControlAction::Pause,
+        ("POST", "/resume") => ControlAction::Resume,
+        ("POST", "/toggle") => ControlAction::Toggle,
+        ("POST", "/annotate") => {
+            let body = request.body_text();
+            let note = body.trim();
+            ControlAction::Annotate((!note.is_empty()).then(|| note.to_string()))
+        }
+        (_, "/status") | (_, "/pause") | (_, "/resume") | (_, "/toggle") | (_, "/annotate") => {
+            return (405, serde_json::json!({ "error": "method not allowed" }));
+        }
+        _ => return (404, serde_json::json!({ "error": "unknown route" })),
+    };
+    
+    match action.apply(monitor).await {
+        Ok(marker_id) => (
+            200,
+            serde_json::json!({
+                "paused": monitor.is_paused().await,
+                "marker_id": marker_id,
+            }),
+        ),
+        Err(e) => {
+            warn!("Control action {:?} failed: {}", action, e);
+            (500, serde_json::json!({ "error": e.to_string() }))
+        }
+    }
+}
+
+// Reads one request; None when it isn't valid HTTP, is too large or ends early
+pub async fn read_request<S: AsyncRead + Unpin>(stream: &mut S) -> Result<Option<Request>> {
+    let mut data = Vec::new();
+    let mut buffer = [0u8; 1024];
+    
+    let header_end = loop {
+        if let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break end + 4;
+        }
+        if data.len() > MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        data.extend_from_slice(&buffer[..read]);
+    };
+    
+    let Ok(head) = std::str::from_utf8(&data[..header_end]) else {
+        return Ok(None);
+    };
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    // Owned, since reading the body below grows `data`
+    let (Some(method), Some(path)) = (request_line.next().map(str::to_string), request_line.next().map(str::to_string))
+    else {
+        return Ok(None);
+    };
+    
+    let mut content_length = 0;
+    let mut has_origin = false;
+    let mut headers = Vec::new();
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+        if name.eq_ignore_ascii_case("content-length") {
+            content_length = value.trim().parse().unwrap_or(0);
+        } else if name.eq_ignore_ascii_case("origin") {
+            has_origin = true;
+        }
+    }
+    if header_end + content_length > MAX_REQUEST_BYTES {
+        return Ok(None);
+    }
+    
+    while data.len() < header_end + content_length {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        data.extend_from_slice(&buffer[..read]);
+    }
+    
+    Ok(Some(Request {
+        method,
+        path,
+        has_origin,
+        headers,
+        body: data[header_end..header_end + content_length].to_vec(),
+    }))
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "Internal Server Error",
+    }
+}
+
+// Applies the mapped action for every note-on from the configured MIDI input
+// until the monitor shuts down
+#[cfg(feature = "midi")]
+pub async fn listen_midi<T: PlatformTracker + ?Sized + 'static>(
+    monitor: Arc<ActivityMonitor<T>>,
+    settings: MidiSettings,
+) -> Result<()> {
+    use crate::error::SelfspyError;
+    use midir::MidiInput;
+    
+    let input = MidiInput::new("selfspy").map_err(|e| SelfspyError::Platform(format!("MIDI unavailable: {}", e)))?;
+    let port = input
+        .ports()
+        .into_iter()
+        .find(|port| input.port_name(port).is_ok_and(|name| name.contains(&settings.port)))
+        .ok_or_else(|| SelfspyError::Platform(format!("No MIDI input matching '{}'", settings.port)))?;
+    let port_name = input.port_name(&port).unwrap_or_default();
+    
+    let (actions_tx, mut actions) = tokio::sync::mpsc::unbounded_channel();
+    let notes = settings.notes;
+    // midir calls back on its own thread; the connection stops when dropped
+    let _connection = input
+        .connect(
+            &port,
+            "selfspy-control",
+            move |_, message, _| {
+                // Note-on with non-zero velocity; zero velocity is a note-off
+                if let [status, note, velocity] = *message {
+                    if status & 0xF0 == 0x90 && velocity > 0 {
+                        if let Some(action) = notes.get(&note) {
+                            let _ = actions_tx.send(action.clone());
+                        }
+                    }
+                }
+            },
+            (),
+        )
+        .map_err(|e| SelfspyError::Platform(format!("Failed to open MIDI input: {}", e)))?;
+    info!("Listening for control notes on MIDI input '{}'", port_name);
+    
+    let shutdown = monitor.shutdown_token();
+    loop {
+        let action = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            Some(action) = actions.recv() => action,
+        };
+        if let Err(e) = action.apply(&monitor).await {
+            warn!("MIDI control action {:?} failed: {}", action, e);
+        }
+    }
+}
@@ -0,0 +1,339 @@
+// Derived metrics defined in config.json, for example
+//
+//   "custom_metrics": [
+//     { "name": "focus_ratio", "expression": "deep_work_minutes / active_minutes", "format": "percent" }
+//   ]
+//
+// An expression combines the built-in aggregates in `BUILTINS` with numbers,
+// + - * / and parentheses, and is evaluated over the same days as the report
+// showing it. Dividing by zero leaves the metric without a value rather than
+// reporting infinity.
+use chrono::NaiveDate;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::db::local_midnight;
+use crate::error::{Result, SelfspyError};
+use crate::Database;
+
+// Names usable in expressions, with what they hold over the period
+pub const BUILTINS: [(&str, &str); 10] = [
+    ("active_minutes", "minutes with keyboard or mouse activity"),
+    ("keystrokes", "keystrokes, pastes excluded"),
+    ("clicks", "mouse clicks"),
+    ("scrolls", "scroll events"),
+    ("corrections", "backspace and delete presses"),
+    ("pasted_characters", "characters inserted by pastes"),
+    ("windows", "window switches"),
+    ("deep_work_minutes", "minutes in detected deep-work stretches"),
+    ("days", "days in the period"),
+    ("active_days", "days with any activity"),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomMetric {
+    pub name: String,
+    pub expression: String,
+    #[serde(default)]
+    pub format: MetricFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricFormat {
+    #[default]
+    Number,
+    // A ratio shown times 100 with a percent sign
+    Percent,
+    // A number of minutes shown as hours and minutes
+    Minutes,
+}
+
+impl MetricFormat {
+    pub fn display(&self, value: Option<f64>) -> String {
+        let Some(value) = value else {
+            return "—".to_string();
+        };
+        match self {
+            MetricFormat::Number if value.fract() == 0.0 => format!("{:.0}", value),
+            MetricFormat::Number => format!("{:.2}", value),
+            MetricFormat::Percent => format!("{:.1}%", value * 100.0),
+            MetricFormat::Minutes => {
+                let minutes = value.round() as i64;
+                if minutes >= 60 {
+                    format!("{}h {}m", minutes / 60, minutes % 60)
+                } else {
+                    format!("{}m", minutes)
+                }
+            }
+        }
+    }
+}
+
+// One metric evaluated over a period
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct CustomMetricValue {
+    pub name: String,
+    // None when the expression divides by zero or doesn't parse
+    pub value: Option<f64>,
+    // Formatted as configured, e.g. "42.0%"
+    pub display: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Builtin(&'static str),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, Op, Box<Expr>),
+}
+
+impl Expr {
+    fn parse(source: &str) -> Result<Self> {
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0 };
+        let expr = parser.sum()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(expr),
+            Some(token) => Err(SelfspyError::Metric(format!("unexpected {} in '{}'", token, source))),
+        }
+    }
+    
+    fn eval(&self, aggregates: &Aggregates) -> Option<f64> {
+        let value = match self {
+            Expr::Number(value) => *value,
+            Expr::Builtin(name) => aggregates.get(name).copied().unwrap_or(0.0),
+            Expr::Neg(inner) => -inner.eval(aggregates)?,
+            Expr::Binary(left, op, right) => {
+                let (left, right) = (left.eval(aggregates)?, right.eval(aggregates)?);
+                match op {
+                    Op::Add => left + right,
+                    Op::Sub => left - right,
+                    Op::Mul => left * right,
+                    Op::Div if right == 0.0 => return None,
+                    Op::Div => left / right,
+                }
+            }
+        };
+        value.is_finite().then_some(value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "'{}'", value),
+            Token::Name(name) => write!(f, "'{}'", name),
+            Token::Op(op) => write!(
+                f,
+                "'{}'",
+                match op {
+                    Op::Add => '+',
+                    Op::Sub => '-',
+                    Op::Mul => '*',
+                    Op::Div => '/',
+                }
+            ),
+            Token::Open => f.write_str("'('"),
+            Token::Close => f.write_str("')'"),
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(start, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '0'..='9' | '.' => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_digit() || c == '.') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                let text = &source[start..end];
+                Token::Number(
+                    text.parse()
+                        .map_err(|_| SelfspyError::Metric(format!("bad number '{}' in '{}'", text, source)))?,
+                )
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = start;
+                while let Some(&(i, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = i + c.len_utf8();
+                    chars.next();
+                }
+                Token::Name(source[start..end].to_string())
+            }
+            _ => {
+                chars.next();
+                match c {
+                    '+' => Token::Op(Op::Add),
+                    '-' => Token::Op(Op::Sub),
+                    '*' => Token::Op(Op::Mul),
+                    '/' => Token::Op(Op::Div),
+                    '(' => Token::Open,
+                    ')' => Token::Close,
+                    _ => return Err(SelfspyError::Metric(format!("unexpected '{}' in '{}'", c, source))),
+                }
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+// Recursive descent: sum := product (('+' | '-') product)*,
+// product := unary (('*' | '/') unary)*, unary := '-' unary | atom
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+    
+    fn peek_op(&self, ops: &[Op]) -> Option<Op> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Op(op)) if ops.contains(op) => Some(*op),
+            _ => None,
+        }
+    }
+    
+    fn sum(&mut self) -> Result<Expr> {
+        let mut expr = self.product()?;
+        while let Some(op) = self.peek_op(&[Op::Add, Op::Sub]) {
+            self.pos += 1;
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+    
+    fn product(&mut self) -> Result<Expr> {
+        let mut expr = self.unary()?;
+        while let Some(op) = self.peek_op(&[Op::Mul, Op::Div]) {
+            self.pos += 1;
+            expr = Expr::Binary(Box::new(expr), op, Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+    
+    fn unary(&mut self) -> Result<Expr> {
+        if self.peek_op(&[Op::Sub]).is_some() {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+    
+    fn atom(&mut self) -> Result<Expr> {
+        match self.next() {
+            Some(Token::Number(value)) => Ok(Expr::Number(value)),
+            Some(Token::Name(name)) => BUILTINS
+                .iter()
+                .find(|(builtin, _)| *builtin == name)
+                .map(|(builtin, _)| Expr::Builtin(builtin))
+                .ok_or_else(|| SelfspyError::Metric(format!("unknown aggregate '{}'", name))),
+            Some(Token::Open) => {
+                let expr = self.sum()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(expr),
+                    _ => Err(SelfspyError::Metric("missing ')'".to_string())),
+                }
+            }
+            Some(token) => Err(SelfspyError::Metric(format!("unexpected {}", token))),
+            None => Err(SelfspyError::Metric("expression ends too early".to_string())),
+        }
+    }
+}
+
+// Built-in aggregate values by name
+pub type Aggregates = BTreeMap<&'static str, f64>;
+
+// The built-in aggregates over local days in [start, end)
+pub async fn aggregates(db: &Database, start: NaiveDate, end: NaiveDate) -> Result<Aggregates> {
+    let days = db.get_daily_summaries(start, end).await?;
+    let stats = db.get_stats_between(local_midnight(start), local_midnight(end)).await?;
+    let deep_work = db.get_deep_work_days(start, end).await?;
+    
+    let mut values = Aggregates::new();
+    values.insert("active_minutes", days.iter().map(|day| day.active_seconds).sum::<i64>() as f64 / 60.0);
+    values.insert("keystrokes", days.iter().map(|day| day.keystrokes).sum::<i64>() as f64);
+    values.insert("clicks", days.iter().map(|day| day.clicks).sum::<i64>() as f64);
+    values.insert("scrolls", stats.total_scrolls as f64);
+    values.insert("corrections", stats.total_corrections as f64);
+    values.insert("pasted_characters", stats.total_pasted_characters as f64);
+    values.insert("windows", stats.total_windows as f64);
+    values.insert("deep_work_minutes", deep_work.iter().map(|day| day.minutes).sum::<i64>() as f64);
+    values.insert("days", (end - start).num_days().max(0) as f64);
+    values.insert("active_days", days.iter().filter(|day| day.active_seconds > 0).count() as f64);
+    Ok(values)
+}
+
+// Every metric in config order; ones that don't parse carry their error
+pub fn evaluate(metrics: &[CustomMetric], aggregates: &Aggregates) -> Vec<CustomMetricValue> {
+    metrics
+        .iter()
+        .map(|metric| {
+            let (value, error) = match Expr::parse(&metric.expression) {
+                Ok(expr) => (expr.eval(aggregates), None),
+                Err(e) => (None, Some(e.to_string())),
+            };
+            CustomMetricValue {
+                name: metric.name.clone(),
+                value,
+                display: metric.format.display(value),
+                error,
+            }
+        })
+        .collect()
+}
+
+// Every metric per local day in [start, end), for charts; days without
+// activity are left out rather than charted as zero
+pub async fn daily_values(
+    db: &Database,
+    metrics: &[CustomMetric],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<Vec<(NaiveDate, Vec<CustomMetricValue>)>> {
+    let mut values = Vec::new();
+    for day in db.get_daily_summaries(start, end).await? {
+        let next = day.date.succ_opt().unwrap_or(day.date);
+        values.push((day.date, evaluate(metrics, &aggregates(db, day.date, next).await?)));
+    }
+    Ok(values)
+}
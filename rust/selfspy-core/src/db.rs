@@ -1,96 +1,799 @@
-use anyhow::Result;
+use crate::error::{Result, SelfspyError};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use futures::stream::{BoxStream, StreamExt, TryStreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::sqlite::{SqliteConnectOptions, SqliteConnection, SqliteJournalMode, SqliteQueryResult, SqliteSynchronous};
 use sqlx::{Pool, Sqlite, SqlitePool, Row};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::ops::Range;
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::instrument;
 
 use crate::models::*;
+use crate::browser::TabLocation;
+use crate::backup::fnv1a;
+use crate::categories::{self, CategoryRule, WindowTags};
+use crate::encryption::{Encryptor, KdfSettings, KeyDerivation, Zeroizing};
+use crate::fatigue::TypingRhythm;
+use crate::key_count::{self, KeyCounts};
+use crate::platform::WindowInfo;
+use crate::progress::Progress;
+use crate::query_stats::{QueryStat, QueryTimings};
+use crate::typing_practice::{NgramTimer, MIN_NGRAM_COUNT};
 
 pub struct Database {
     pool: Pool<Sqlite>,
+    timings: Arc<QueryTimings>,
+    pending: Mutex<VecDeque<PendingWrite>>,
+    // Queued rows dropped because the buffer was full
+    dropped: AtomicU64,
 }
 
+// Rows buffered while the database can't be written (locked, disk full);
+// past this the oldest are dropped, so a long outage can't exhaust memory
+pub const MAX_PENDING_WRITES: usize = 50_000;
+
+// A row queued with `Database::queue` and written by the next `flush`. Rows
+// refer to windows and devices that are already stored; windows themselves
+// are inserted right away because the rows after them need their ids. A row
+// whose window has gone since (pruned or repaired away) is dropped by the
+// flush rather than holding up the rows after it. Every row carries the time
+// it was queued (`at`), so rows held while the database can't be written
+// keep when the input happened rather than when the database came back.
+#[derive(Debug)]
+pub enum PendingWrite {
+    Keys {
+        window_id: i64,
+        encrypted_keys: Vec<u8>,
+        counts: KeyCounts,
+        at: DateTime<Utc>,
+    },
+    Click {
+        window_id: i64,
+        x: i32,
+        y: i32,
+        button: String,
+        double_click: bool,
+        scale: f64,
+        at: DateTime<Utc>,
+    },
+    MouseMoves {
+        window_id: i64,
+        distance: i64,
+        event_count: i32,
+        at: DateTime<Utc>,
+    },
+    Scrolls {
+        window_id: i64,
+        event_count: i32,
+        distance_x: f64,
+        distance_y: f64,
+        at: DateTime<Utc>,
+    },
+    TypingRhythm {
+        rhythm: TypingRhythm,
+        at: DateTime<Utc>,
+    },
+    // Summed into the local day `at` falls on
+    KeyNgrams {
+        timer: NgramTimer,
+        at: DateTime<Utc>,
+    },
+    DeviceInput {
+        device_id: i64,
+        keys: i64,
+        clicks: i64,
+        scrolls: i64,
+        at: DateTime<Utc>,
+    },
+}
+
+impl PendingWrite {
+    fn table(&self) -> &'static str {
+        match self {
+            PendingWrite::Keys { .. } => "keys",
+            PendingWrite::Click { .. } => "clicks",
+            PendingWrite::MouseMoves { .. } => "mouse_moves",
+            PendingWrite::Scrolls { .. } => "scrolls",
+            PendingWrite::TypingRhythm { .. } => "typing_rhythm",
+            PendingWrite::KeyNgrams { .. } => "key_ngram_days",
+            PendingWrite::DeviceInput { .. } => "device_input",
+        }
+    }
+    
+    async fn execute(&self, conn: &mut SqliteConnection) -> Result<i64> {
+        let result = match self {
+            PendingWrite::Keys { window_id, encrypted_keys, counts, at } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO keys (window_id, encrypted_keys, key_count, event_count, correction_count, paste_count,
+                                      count_method, created_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(window_id)
+                .bind(encrypted_keys)
+                .bind(counts.characters)
+                .bind(counts.events)
+                .bind(counts.corrections)
+                .bind(counts.pasted)
+                .bind(key_count::COUNT_GRAPHEMES)
+                .bind(format_timestamp(*at))
+                .execute(conn)
+                .await?
+            }
+            PendingWrite::Click { window_id, x, y, button, double_click, scale, at } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO clicks (window_id, x, y, button, double_click, scale, created_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(window_id)
+                .bind(x)
+                .bind(y)
+                .bind(button)
+                .bind(double_click)
+                .bind(scale)
+                .bind(format_timestamp(*at))
+                .execute(conn)
+                .await?
+            }
+            PendingWrite::MouseMoves { window_id, distance, event_count, at } => {
+                sqlx::query("INSERT INTO mouse_moves (window_id, distance, event_count, created_at) VALUES (?, ?, ?, ?)")
+                    .bind(window_id)
+                    .bind(distance)
+                    .bind(event_count)
+                    .bind(format_timestamp(*at))
+                    .execute(conn)
+                    .await?
+            }
+            PendingWrite::Scrolls { window_id, event_count, distance_x, distance_y, at } => {
+                sqlx::query(
+                    "INSERT INTO scrolls (window_id, event_count, distance_x, distance_y, created_at) VALUES (?, ?, ?, ?, ?)"
+                )
+                .bind(window_id)
+                .bind(event_count)
+                .bind(distance_x)
+                .bind(distance_y)
+                .bind(format_timestamp(*at))
+                .execute(conn)
+                .await?
+            }
+            PendingWrite::TypingRhythm { rhythm, at } => {
+                sqlx::query(
+                    r#"
+                    INSERT INTO typing_rhythm (
+                        key_count, backspace_count, interval_count, interval_sum_ms, interval_sq_sum_ms,
+                        hold_count, hold_sum_ms, hold_min_ms, hold_max_ms, created_at
+                    )
+                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+                    "#,
+                )
+                .bind(rhythm.keys)
+                .bind(rhythm.backspaces)
+                .bind(rhythm.intervals)
+                .bind(rhythm.interval_sum_ms)
+                .bind(rhythm.interval_sq_sum_ms)
+                .bind(rhythm.holds)
+                .bind(rhythm.hold_sum_ms)
+                .bind(rhythm.hold_min_ms)
+                .bind(rhythm.hold_max_ms)
+                .bind(format_timestamp(*at))
+                .execute(conn)
+                .await?
+            }
+            PendingWrite::KeyNgrams { timer, at } => {
+                let mut result = SqliteQueryResult::default();
+                for (ngram, (count, interval_sum_ms)) in &timer.ngrams {
+                    result = sqlx::query(
+                        r#"
+                        INSERT INTO key_ngram_days (day, ngram, key_count, interval_sum_ms)
+                        VALUES (date(?, 'localtime'), ?, ?, ?)
+                        ON CONFLICT(day, ngram) DO UPDATE SET
+                            key_count = key_count + excluded.key_count,
+                            interval_sum_ms = interval_sum_ms + excluded.interval_sum_ms
+                        "#,
+                    )
+                    .bind(format_timestamp(*at))
+                    .bind(ngram)
+                    .bind(count)
+                    .bind(interval_sum_ms)
+                    .execute(&mut *conn)
+                    .await?;
+                }
+                result
+            }
+            PendingWrite::DeviceInput { device_id, keys, clicks, scrolls, at } => {
+                sqlx::query(
+                    "INSERT INTO device_input (device_id, keys, clicks, scrolls, created_at) VALUES (?, ?, ?, ?, ?)"
+                )
+                .bind(device_id)
+                .bind(keys)
+                .bind(clicks)
+                .bind(scrolls)
+                .bind(format_timestamp(*at))
+                .execute(conn)
+                .await?
+            }
+        };
+        
+        Ok(result.last_insert_rowid())
+    }
+}
+
+// SQLITE_CONSTRAINT (primary code 19): the row itself is at fault, unlike a
+// locked or full database, which a later retry can get past
+fn breaks_constraint(err: &SelfspyError) -> bool {
+    match err {
+        SelfspyError::Database(sqlx::Error::Database(db)) => {
+            db.code().and_then(|c| c.parse::<i32>().ok()).is_some_and(|c| c & 0xff == 19)
+        }
+        _ => false,
+    }
+}
+
+// Connection pragmas for databases opened with `Database::connect`. WAL lets
+// selfstats and the GUI read while the monitor writes; NORMAL sync is safe in
+// WAL mode and only risks the last commits on power loss.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DatabaseSettings {
+    pub journal_mode: JournalMode,
+    pub synchronous: Synchronous,
+    // How long a connection waits on another's lock before failing
+    pub busy_timeout_ms: u64,
+    pub foreign_keys: bool,
+}
+
+impl Default for DatabaseSettings {
+    fn default() -> Self {
+        Self {
+            journal_mode: JournalMode::Wal,
+            synchronous: Synchronous::Normal,
+            busy_timeout_ms: 5000,
+            foreign_keys: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JournalMode {
+    Wal,
+    Delete,
+    Truncate,
+    Persist,
+    Memory,
+}
+
+impl From<JournalMode> for SqliteJournalMode {
+    fn from(mode: JournalMode) -> Self {
+        match mode {
+            JournalMode::Wal => SqliteJournalMode::Wal,
+            JournalMode::Delete => SqliteJournalMode::Delete,
+            JournalMode::Truncate => SqliteJournalMode::Truncate,
+            JournalMode::Persist => SqliteJournalMode::Persist,
+            JournalMode::Memory => SqliteJournalMode::Memory,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Synchronous {
+    Off,
+    Normal,
+    Full,
+    Extra,
+}
+
+impl From<Synchronous> for SqliteSynchronous {
+    fn from(synchronous: Synchronous) -> Self {
+        match synchronous {
+            Synchronous::Off => SqliteSynchronous::Off,
+            Synchronous::Normal => SqliteSynchronous::Normal,
+            Synchronous::Full => SqliteSynchronous::Full,
+            Synchronous::Extra => SqliteSynchronous::Extra,
+        }
+    }
+}
+
+// How long a snapshot is reused while the live database keeps changing
+const SNAPSHOT_MAX_AGE: Duration = Duration::from_secs(30);
+
+// Tables compared by `get_table_days`: the local day of each row and the
+// row contents that go into the checksum, every column but ids and
+// references to other rows since those differ between merged copies.
+// Nullable columns are coalesced, or one NULL would blank the whole row.
+const CHECKSUMMED_TABLES: [(&str, &str, &str); 25] = [
+    ("processes", "date(created_at, 'localtime')", "created_at || '|' || name || '|' || COALESCE(bundle_id, '')"),
+    (
+        "windows",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || title || '|' || COALESCE(x, '') || '|' || COALESCE(y, '') || '|' \
+         || COALESCE(width, '') || '|' || COALESCE(height, '') || '|' || COALESCE(domain, '') || '|' \
+         || COALESCE(url, '') || '|' || COALESCE(document, '') || '|' || COALESCE(category, '') || '|' \
+         || COALESCE(project, '')",
+    ),
+    (
+        "keys",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || hex(encrypted_keys) || '|' || key_count || '|' || correction_count || '|' \
+         || COALESCE(event_count, '') || '|' || COALESCE(count_method, '') || '|' || paste_count || '|' \
+         || COALESCE(outlier_reason, '')",
+    ),
+    (
+        "clicks",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || x || '|' || y || '|' || button || '|' || COALESCE(double_click, '') || '|' \
+         || COALESCE(scale, '') || '|' || COALESCE(outlier_reason, '')",
+    ),
+    ("mouse_moves", "date(created_at, 'localtime')", "created_at || '|' || distance || '|' || event_count"),
+    (
+        "scrolls",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || event_count || '|' || distance_x || '|' || distance_y",
+    ),
+    (
+        "typing_rhythm",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || key_count || '|' || backspace_count || '|' || interval_count || '|' \
+         || interval_sum_ms || '|' || interval_sq_sum_ms || '|' || hold_count || '|' || hold_sum_ms || '|' \
+         || COALESCE(hold_min_ms, '') || '|' || COALESCE(hold_max_ms, '')",
+    ),
+    (
+        "deep_work",
+        "day",
+        "day || '|' || started_at || '|' || ended_at || '|' || process || '|' || minutes || '|' || switches",
+    ),
+    (
+        "diagnostics",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || kind || '|' || dropped_events || '|' || detail",
+    ),
+    (
+        "sessions",
+        "date(started_at, 'localtime')",
+        "started_at || '|' || COALESCE(label, '') || '|' || COALESCE(ended_at, '')",
+    ),
+    (
+        "goals",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || metric || '|' || weekly_target || '|' || effective_from",
+    ),
+    ("tagged_periods", "date(started_at, 'localtime')", "started_at || '|' || kind || '|' || COALESCE(ended_at, '')"),
+    (
+        "media_plays",
+        "date(started_at, 'localtime')",
+        "started_at || '|' || player || '|' || title || '|' || COALESCE(artist, '') || '|' || COALESCE(ended_at, '')",
+    ),
+    (
+        "health_days",
+        "day",
+        "day || '|' || COALESCE(steps, '') || '|' || COALESCE(sleep_minutes, '') || '|' || imported_at",
+    ),
+    ("system_events", "date(occurred_at, 'localtime')", "occurred_at || '|' || kind"),
+    (
+        "power_samples",
+        "date(recorded_at, 'localtime')",
+        "recorded_at || '|' || on_battery || '|' || COALESCE(percent, '')",
+    ),
+    (
+        "input_devices",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || name || '|' || kind || '|' || built_in",
+    ),
+    (
+        "device_input",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || keys || '|' || clicks || '|' || scrolls",
+    ),
+    ("markers", "date(created_at, 'localtime')", "created_at || '|' || COALESCE(note, '')"),
+    ("periods", "date(started_at, 'localtime')", "started_at || '|' || ended_at"),
+    (
+        "daily_rollups",
+        "day",
+        "day || '|' || keystrokes || '|' || clicks || '|' || active_seconds",
+    ),
+    (
+        "key_derivation",
+        "date(created_at, 'localtime')",
+        "created_at || '|' || algorithm || '|' || memory_kib || '|' || iterations || '|' || parallelism || '|' \
+         || hex(salt) || '|' || hex(check_value) || '|' || COALESCE(hex(sealed_key), '') || '|' \
+         || COALESCE(hex(recovery_key), '')",
+    ),
+    (
+        "key_ngram_days",
+        "day",
+        "day || '|' || ngram || '|' || key_count || '|' || interval_sum_ms",
+    ),
+    ("alerts_fired", "day", "day || '|' || rule"),
+    ("legacy_imports", "date(imported_at, 'localtime')", "imported_at || '|' || source || '|' || path"),
+];
+
+// Event tables emptied of old rows by `prune_before`, with the expression
+// that dates each row. Spans are dated by their end; one still open that
+// began before the cutoff was left behind by a crash, so those go by start.
+const PRUNED_TABLES: [(&str, &str); 12] = [
+    ("keys", "created_at"),
+    ("clicks", "created_at"),
+    ("mouse_moves", "created_at"),
+    ("scrolls", "created_at"),
+    ("typing_rhythm", "created_at"),
+    ("diagnostics", "created_at"),
+    ("device_input", "created_at"),
+    ("system_events", "occurred_at"),
+    ("power_samples", "recorded_at"),
+    ("media_plays", "COALESCE(ended_at, started_at)"),
+    ("tagged_periods", "COALESCE(ended_at, started_at)"),
+    ("periods", "ended_at"),
+];
+
+// Rows deleted per statement by `prune_before`; each batch commits on its
+// own so the monitor's writes get in between
+const PRUNE_BATCH_ROWS: i64 = 5000;
+
+// What makes a windows, keys or clicks row an orphan, see `count_orphans`
+const ORPHANED_WINDOWS: &str = "NOT EXISTS (SELECT 1 FROM processes p WHERE p.id = windows.process_id)";
+const ORPHANED_KEYS: &str = "NOT EXISTS (SELECT 1 FROM windows w WHERE w.id = keys.window_id)";
+const ORPHANED_CLICKS: &str = "NOT EXISTS (SELECT 1 FROM windows w WHERE w.id = clicks.window_id)";
+
+// Process orphaned windows are moved to by `repair_orphans`
+pub const UNKNOWN_PROCESS: &str = "(unknown)";
+
 impl Database {
     pub async fn new(path: &Path) -> Result<Self> {
+        Self::connect(path, &DatabaseSettings::default()).await
+    }
+    
+    // Opens or creates the database for writing with the given pragmas and
+    // brings its schema up to date
+    pub async fn connect(path: &Path, settings: &DatabaseSettings) -> Result<Self> {
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent)?;
         }
         
-        let url = format!("sqlite:{}?mode=rwc", path.display());
-        let pool = SqlitePool::connect(&url).await?;
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .create_if_missing(true)
+            .journal_mode(settings.journal_mode.into())
+            .synchronous(settings.synchronous.into())
+            .busy_timeout(Duration::from_millis(settings.busy_timeout_ms))
+            .foreign_keys(settings.foreign_keys);
+        let pool = SqlitePool::connect_with(options).await?;
         
-        let db = Self { pool };
-        db.migrate().await?;
+        let db = Self {
+            pool,
+            timings: Arc::new(QueryTimings::new(path)),
+            pending: Mutex::new(VecDeque::new()),
+            dropped: AtomicU64::new(0),
+        };
+        crate::migrations::run(&db.pool).await?;
         Ok(db)
     }
     
-    async fn migrate(&self) -> Result<()> {
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS processes (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                name TEXT NOT NULL UNIQUE,
-                bundle_id TEXT,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+    // A migrated database that lives only in memory, for fuzzing and tests.
+    // Each SQLite connection to :memory: is its own database, so the pool is
+    // held to a single connection that is never recycled.
+    #[cfg(feature = "test-support")]
+    pub async fn in_memory() -> Result<Self> {
+        let options = SqliteConnectOptions::new()
+            .filename(":memory:")
+            .foreign_keys(true);
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .min_connections(1)
+            .idle_timeout(None)
+            .max_lifetime(None)
+            .connect_with(options)
+            .await?;
         
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS windows (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                process_id INTEGER NOT NULL,
-                title TEXT NOT NULL,
-                x INTEGER,
-                y INTEGER,
-                width INTEGER,
-                height INTEGER,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (process_id) REFERENCES processes(id)
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+        let db = Self {
+            pool,
+            timings: Arc::new(QueryTimings::unlogged()),
+            pending: Mutex::new(VecDeque::new()),
+            dropped: AtomicU64::new(0),
+        };
+        crate::migrations::run(&db.pool).await?;
+        Ok(db)
+    }
+    
+    // Opens an existing database without creating it or running migrations,
+    // for reporting tools that must never modify the data
+    pub async fn open_read_only(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(SelfspyError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no database at {}", path.display()),
+            )));
+        }
         
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS keys (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                window_id INTEGER NOT NULL,
-                encrypted_keys BLOB NOT NULL,
-                key_count INTEGER NOT NULL,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (window_id) REFERENCES windows(id)
-            )
-            "#,
-        )
-        .execute(&self.pool)
-        .await?;
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .read_only(true)
+            .create_if_missing(false);
+        let pool = SqlitePool::connect_with(options).await?;
         
-        sqlx::query(
-            r#"
-            CREATE TABLE IF NOT EXISTS clicks (
-                id INTEGER PRIMARY KEY AUTOINCREMENT,
-                window_id INTEGER NOT NULL,
-                x INTEGER NOT NULL,
-                y INTEGER NOT NULL,
-                button TEXT NOT NULL,
-                double_click BOOLEAN DEFAULT FALSE,
-                created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
-                FOREIGN KEY (window_id) REFERENCES windows(id)
-            )
-            "#,
+        Ok(Self {
+            pool,
+            timings: Arc::new(QueryTimings::new(path)),
+            pending: Mutex::new(VecDeque::new()),
+            dropped: AtomicU64::new(0),
+        })
+    }
+    
+    // Opens a recent copy of the database for heavy analytical reads. Under
+    // WAL a long read on the live file doesn't block the monitor's writes, but
+    // it pins the WAL: checkpoints can't get past it and the -wal file grows
+    // for as long as the read runs. The copy is taken in one backup step, a
+    // single short read, and is reused while it is fresh.
+    pub async fn open_snapshot(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(SelfspyError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no database at {}", path.display()),
+            )));
+        }
+        
+        let snapshot = path.with_extension("db.snapshot");
+        if !snapshot_is_fresh(path, &snapshot) {
+            refresh_snapshot(path, &snapshot).await?;
+        }
+        Self::open_immutable(&snapshot).await
+    }
+    
+    // Opens a file nothing else writes to (a snapshot or a backup) without any
+    // locking or change detection
+    pub async fn open_immutable(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Err(SelfspyError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no database at {}", path.display()),
+            )));
+        }
+        
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .read_only(true)
+            .immutable(true)
+            .create_if_missing(false);
+        let pool = SqlitePool::connect_with(options).await?;
+        
+        Ok(Self {
+            pool,
+            timings: Arc::new(QueryTimings::new(path)),
+            pending: Mutex::new(VecDeque::new()),
+            dropped: AtomicU64::new(0),
+        })
+    }
+    
+    // Calls at least this slow are logged to slow_queries.log; None turns the log off
+    pub fn with_slow_query_threshold(self, threshold: Option<Duration>) -> Self {
+        self.timings.set_threshold(threshold);
+        self
+    }
+    
+    // Adds a row to the write buffer; nothing reaches the database until
+    // `flush`. A full buffer drops its oldest row.
+    pub fn queue(&self, write: PendingWrite) {
+        let mut pending = self.pending.lock().unwrap();
+        if pending.len() >= MAX_PENDING_WRITES {
+            pending.pop_front();
+            if self.dropped.fetch_add(1, Ordering::Relaxed).is_multiple_of(1000) {
+                tracing::warn!("{} rows are waiting to be written; dropping the oldest", MAX_PENDING_WRITES);
+            }
+        }
+        pending.push_back(write);
+    }
+    
+    pub fn pending_writes(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+    
+    // Rows dropped from a full buffer since the database was opened
+    pub fn dropped_writes(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+    
+    // Writes the buffered rows in the order they were queued, in a single
+    // transaction, and returns how many were written. A row the database
+    // rejects (a constraint it breaks) is logged and dropped; any other
+    // failure writes nothing and puts the rows back at the front of the
+    // buffer for the next flush.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "flush"))]
+    pub async fn flush(&self) -> Result<usize> {
+        let _timer = self.timings.start("flush");
+        let writes = std::mem::take(&mut *self.pending.lock().unwrap());
+        if writes.is_empty() {
+            return Ok(0);
+        }
+        
+        match self.write_all(&writes).await {
+            Ok(rejected) => Ok(writes.len() - rejected),
+            Err(e) => {
+                let mut pending = self.pending.lock().unwrap();
+                let newer = std::mem::replace(&mut *pending, writes);
+                pending.extend(newer);
+                while pending.len() > MAX_PENDING_WRITES {
+                    pending.pop_front();
+                    self.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(e)
+            }
+        }
+    }
+    
+    // Each row gets a savepoint, so a rejected one leaves no partial writes
+    // (key n-grams are several inserts); returns the number rejected
+    async fn write_all(&self, writes: &VecDeque<PendingWrite>) -> Result<usize> {
+        let mut tx = self.pool.begin().await?;
+        let mut rejected = 0;
+        for write in writes {
+            sqlx::query("SAVEPOINT pending_write").execute(&mut *tx).await?;
+            match write.execute(&mut tx).await {
+                Ok(_) => {}
+                Err(e) if breaks_constraint(&e) => {
+                    tracing::warn!("Dropping a buffered {} row the database rejected: {}", write.table(), e);
+                    sqlx::query("ROLLBACK TO pending_write").execute(&mut *tx).await?;
+                    rejected += 1;
+                }
+                Err(e) => return Err(e),
+            }
+            sqlx::query("RELEASE pending_write").execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(rejected)
+    }
+    
+    async fn write_now(&self, write: PendingWrite) -> Result<i64> {
+        let mut conn = self.pool.acquire().await?;
+        write.execute(&mut conn).await
+    }
+    
+    // Time spent per Database method in this process
+    pub fn query_stats(&self) -> Vec<QueryStat> {
+        self.timings.snapshot()
+    }
+    
+    pub async fn close(&self) {
+        self.pool.close().await;
+    }
+    
+    // Problems reported by PRAGMA integrity_check; empty when the file is sound
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "integrity_check"))]
+    pub async fn integrity_check(&self) -> Result<Vec<String>> {
+        let _timer = self.timings.start("integrity_check");
+        let rows = sqlx::query("PRAGMA integrity_check").fetch_all(&self.pool).await?;
+        let problems = rows
+            .iter()
+            .map(|row| row.get::<String, _>(0))
+            .filter(|message| message != "ok")
+            .collect();
+        
+        Ok(problems)
+    }
+    
+    // Also finds dangling references in tables whose keys weren't enforced
+    // when the rows were written (foreign_keys off, or imported data)
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "foreign_key_violations"))]
+    pub async fn foreign_key_violations(&self) -> Result<Vec<ForeignKeyViolation>> {
+        let _timer = self.timings.start("foreign_key_violations");
+        let rows = sqlx::query("PRAGMA foreign_key_check").fetch_all(&self.pool).await?;
+        Ok(rows
+            .iter()
+            .map(|row| ForeignKeyViolation {
+                table: row.get("table"),
+                rowid: row.get("rowid"),
+                parent: row.get("parent"),
+            })
+            .collect())
+    }
+    
+    // Windows whose process is gone, and keys and clicks whose window is
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "count_orphans"))]
+    pub async fn count_orphans(&self) -> Result<Orphans> {
+        let _timer = self.timings.start("count_orphans");
+        let mut orphans = Orphans::default();
+        for (table, condition, count) in [
+            ("windows", ORPHANED_WINDOWS, &mut orphans.windows),
+            ("keys", ORPHANED_KEYS, &mut orphans.keys),
+            ("clicks", ORPHANED_CLICKS, &mut orphans.clicks),
+        ] {
+            let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {} WHERE {}", table, condition))
+                .fetch_one(&self.pool)
+                .await?;
+            *count = rows as u64;
+        }
+        Ok(orphans)
+    }
+    
+    // Moves orphaned windows to the UNKNOWN_PROCESS, keeping their input,
+    // and deletes keys and clicks without a window, which can't be
+    // attributed to anything, in one transaction. Returns what was repaired.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "repair_orphans"))]
+    pub async fn repair_orphans(&self) -> Result<Orphans> {
+        let _timer = self.timings.start("repair_orphans");
+        let mut tx = self.pool.begin().await?;
+        
+        let mut repaired = Orphans::default();
+        let sql = format!("SELECT COUNT(*) FROM windows WHERE {}", ORPHANED_WINDOWS);
+        let orphaned_windows: i64 = sqlx::query_scalar(&sql).fetch_one(&mut *tx).await?;
+        if orphaned_windows > 0 {
+            sqlx::query("INSERT OR IGNORE INTO processes (name) VALUES (?)")
+                .bind(UNKNOWN_PROCESS)
+                .execute(&mut *tx)
+                .await?;
+            repaired.windows = sqlx::query(&format!(
+                "UPDATE windows SET process_id = (SELECT id FROM processes WHERE name = ?) WHERE {}",
+                ORPHANED_WINDOWS
+            ))
+            .bind(UNKNOWN_PROCESS)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        }
+        repaired.keys = sqlx::query(&format!("DELETE FROM keys WHERE {}", ORPHANED_KEYS))
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        repaired.clicks = sqlx::query(&format!("DELETE FROM clicks WHERE {}", ORPHANED_CLICKS))
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+        
+        tx.commit().await?;
+        Ok(repaired)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "storage_info"))]
+    pub async fn storage_info(&self) -> Result<StorageInfo> {
+        let _timer = self.timings.start("storage_info");
+        let pragma = |name: &'static str| sqlx::query_scalar::<_, i64>(name).fetch_one(&self.pool);
+        Ok(StorageInfo {
+            page_size: pragma("PRAGMA page_size").await?,
+            page_count: pragma("PRAGMA page_count").await?,
+            freelist_count: pragma("PRAGMA freelist_count").await?,
+        })
+    }
+    
+    // Highest migration applied, see migrations.rs; 0 before versioning
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "schema_version"))]
+    pub async fn schema_version(&self) -> Result<i64> {
+        let _timer = self.timings.start("schema_version");
+        let mut conn = self.pool.acquire().await?;
+        crate::migrations::current_version(&mut conn).await
+    }
+    
+    // Rows in each table, largest first
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "table_row_counts"))]
+    pub async fn table_row_counts(&self) -> Result<Vec<(String, i64)>> {
+        let _timer = self.timings.start("table_row_counts");
+        let tables: Vec<String> = sqlx::query_scalar(
+            "SELECT name FROM sqlite_master WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
         )
-        .execute(&self.pool)
+        .fetch_all(&self.pool)
         .await?;
         
-        Ok(())
+        let mut counts = Vec::with_capacity(tables.len());
+        for table in tables {
+            let rows: i64 = sqlx::query_scalar(&format!("SELECT COUNT(*) FROM \"{}\"", table))
+                .fetch_one(&self.pool)
+                .await?;
+            counts.push((table, rows));
+        }
+        counts.sort_by_key(|&(_, rows)| std::cmp::Reverse(rows));
+        Ok(counts)
     }
     
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_process"))]
     pub async fn insert_process(&self, name: &str, bundle_id: Option<&str>) -> Result<i64> {
+        let _timer = self.timings.start("insert_process");
         let result = sqlx::query(
             r#"
             INSERT OR IGNORE INTO processes (name, bundle_id)
@@ -113,54 +816,181 @@ impl Database {
         }
     }
     
-    pub async fn insert_window(
-        &self,
-        process_id: i64,
-        title: &str,
-        x: Option<i32>,
-        y: Option<i32>,
-        width: Option<i32>,
-        height: Option<i32>,
-    ) -> Result<i64> {
+    // Stores the window's title, document and geometry under `process_id`
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_window"))]
+    pub async fn insert_window(&self, process_id: i64, window: &WindowInfo) -> Result<i64> {
+        let _timer = self.timings.start("insert_window");
         let result = sqlx::query(
             r#"
-            INSERT INTO windows (process_id, title, x, y, width, height)
-            VALUES (?, ?, ?, ?, ?, ?)
+            INSERT INTO windows (process_id, title, document, x, y, width, height)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(process_id)
-        .bind(title)
-        .bind(x)
-        .bind(y)
-        .bind(width)
-        .bind(height)
+        .bind(&window.window_title)
+        .bind(&window.document)
+        .bind(window.x)
+        .bind(window.y)
+        .bind(window.width)
+        .bind(window.height)
         .execute(&self.pool)
         .await?;
         
         Ok(result.last_insert_rowid())
     }
     
-    pub async fn insert_keys(
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "set_window_tags"))]
+    pub async fn set_window_tags(&self, window_id: i64, tags: &WindowTags) -> Result<()> {
+        let _timer = self.timings.start("set_window_tags");
+        sqlx::query("UPDATE windows SET category = ?, project = ? WHERE id = ?")
+            .bind(&tags.category)
+            .bind(&tags.project)
+            .bind(window_id)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    // Attaches a browser tab to the newest window recorded since `since` whose
+    // title contains the tab title, re-tagging it now that its domain is
+    // known. Returns the window id, or None when the window hasn't been
+    // recorded (yet), e.g. because the app is excluded.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "set_window_location"))]
+    pub async fn set_window_location(
         &self,
-        window_id: i64,
-        encrypted_keys: Vec<u8>,
-        key_count: i32,
-    ) -> Result<i64> {
-        let result = sqlx::query(
+        tab_title: &str,
+        since: DateTime<Utc>,
+        location: &TabLocation,
+        rules: &[CategoryRule],
+    ) -> Result<Option<i64>> {
+        let _timer = self.timings.start("set_window_location");
+        let row = sqlx::query(
             r#"
-            INSERT INTO keys (window_id, encrypted_keys, key_count)
-            VALUES (?, ?, ?)
-            "#,
+            SELECT w.id, w.title, p.name as process
+            FROM windows w
+            JOIN processes p ON p.id = w.process_id
+            WHERE instr(w.title, ?) > 0 AND w.created_at >= ?
+            ORDER BY w.created_at DESC
+            LIMIT 1
+            "#
         )
-        .bind(window_id)
-        .bind(encrypted_keys)
-        .bind(key_count)
-        .execute(&self.pool)
+        .bind(tab_title)
+        .bind(format_timestamp(since))
+        .fetch_optional(&self.pool)
         .await?;
         
-        Ok(result.last_insert_rowid())
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let window_id: i64 = row.get("id");
+        let tags = categories::classify(
+            rules,
+            &row.get::<String, _>("process"),
+            &row.get::<String, _>("title"),
+            Some(&location.domain),
+        );
+        
+        sqlx::query("UPDATE windows SET domain = ?, url = ?, category = ?, project = ? WHERE id = ?")
+            .bind(&location.domain)
+            .bind(&location.url)
+            .bind(&tags.category)
+            .bind(&tags.project)
+            .bind(window_id)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(Some(window_id))
+    }
+    
+    // Windows recorded in [range.start, range.end) whose stored tags differ
+    // from what `rules` give them now, oldest first
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "find_window_tag_changes"))]
+    pub async fn find_window_tag_changes(
+        &self,
+        rules: &[CategoryRule],
+        range: Range<DateTime<Utc>>,
+    ) -> Result<Vec<WindowTagChange>> {
+        let _timer = self.timings.start("find_window_tag_changes");
+        let mut rows = sqlx::query(
+            r#"
+            SELECT w.id, w.created_at, w.title, w.domain, w.category, w.project, p.name as process
+            FROM windows w
+            JOIN processes p ON p.id = w.process_id
+            WHERE w.created_at >= ? AND w.created_at < ?
+            ORDER BY w.id
+            "#
+        )
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.end))
+        .fetch(&self.pool);
+        
+        let mut changes = Vec::new();
+        while let Some(row) = rows.try_next().await? {
+            let process: String = row.get("process");
+            let title: String = row.get("title");
+            let domain: Option<String> = row.get("domain");
+            let old = WindowTags {
+                category: row.get("category"),
+                project: row.get("project"),
+            };
+            let new = categories::classify(rules, &process, &title, domain.as_deref());
+            if new != old {
+                changes.push(WindowTagChange {
+                    window_id: row.get("id"),
+                    created_at: row.get("created_at"),
+                    process,
+                    title,
+                    old,
+                    new,
+                });
+            }
+        }
+        
+        Ok(changes)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "update_window_tags"))]
+    pub async fn update_window_tags(&self, changes: &[WindowTagChange], progress: &Progress) -> Result<u64> {
+        let _timer = self.timings.start("update_window_tags");
+        let mut tx = self.pool.begin().await?;
+        let mut updated = 0;
+        progress.start_stage("Re-tagging windows", Some(changes.len() as u64));
+        
+        for change in changes {
+            progress.check_cancelled()?;
+            updated += sqlx::query("UPDATE windows SET category = ?, project = ? WHERE id = ?")
+                .bind(&change.new.category)
+                .bind(&change.new.project)
+                .bind(change.window_id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            progress.advance(1);
+        }
+        
+        tx.commit().await?;
+        Ok(updated)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_keys"))]
+    pub async fn insert_keys(
+        &self,
+        window_id: i64,
+        encrypted_keys: Vec<u8>,
+        counts: &KeyCounts,
+    ) -> Result<i64> {
+        let _timer = self.timings.start("insert_keys");
+        self.write_now(PendingWrite::Keys {
+            window_id,
+            encrypted_keys,
+            counts: *counts,
+            at: Utc::now(),
+        })
+        .await
     }
     
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_click"))]
     pub async fn insert_click(
         &self,
         window_id: i64,
@@ -168,67 +998,2626 @@ impl Database {
         y: i32,
         button: &str,
         double_click: bool,
+        scale: f64,
     ) -> Result<i64> {
-        let result = sqlx::query(
-            r#"
-            INSERT INTO clicks (window_id, x, y, button, double_click)
-            VALUES (?, ?, ?, ?, ?)
-            "#,
-        )
-        .bind(window_id)
-        .bind(x)
-        .bind(y)
-        .bind(button)
-        .bind(double_click)
-        .execute(&self.pool)
-        .await?;
-        
-        Ok(result.last_insert_rowid())
+        let _timer = self.timings.start("insert_click");
+        self.write_now(PendingWrite::Click {
+            window_id,
+            x,
+            y,
+            button: button.to_string(),
+            double_click,
+            scale,
+            at: Utc::now(),
+        })
+        .await
     }
     
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_stats"))]
     pub async fn get_stats(&self) -> Result<ActivityStats> {
-        let keystrokes_row = sqlx::query("SELECT COALESCE(SUM(key_count), 0) as total FROM keys")
+        let _timer = self.timings.start("get_stats");
+        self.get_stats_filtered(&StatsFilter::default()).await
+    }
+    
+    // The local day of the first recorded window, None for an empty database
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_first_day"))]
+    pub async fn get_first_day(&self) -> Result<Option<NaiveDate>> {
+        let _timer = self.timings.start("get_first_day");
+        let first: Option<String> = sqlx::query_scalar("SELECT date(MIN(created_at), 'localtime') FROM windows")
             .fetch_one(&self.pool)
             .await?;
-        let keystrokes = keystrokes_row.get::<i64, _>("total");
+        Ok(first.map(|day| NaiveDate::parse_from_str(&day, "%Y-%m-%d")).transpose()?)
+    }
+    
+    // Totals for records created in [start, end)
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_stats_between"))]
+    pub async fn get_stats_between(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<ActivityStats> {
+        let _timer = self.timings.start("get_stats_between");
+        self.get_stats_filtered(&StatsFilter {
+            start: Some(start),
+            end: Some(end),
+            ..StatsFilter::default()
+        })
+        .await
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_stats_filtered"))]
+    pub async fn get_stats_filtered(&self, filter: &StatsFilter) -> Result<ActivityStats> {
+        let _timer = self.timings.start("get_stats_filtered");
+        let (keys_filter, keys_binds) = filter_condition("keys", filter);
+        // Key presses, which include the corrections; rows from before
+        // event_count existed only have their character count
+        let keystrokes_sql = format!(
+            "SELECT COALESCE(SUM(COALESCE(event_count, key_count)), 0) as total, \
+             COALESCE(SUM(correction_count), 0) as corrections, \
+             COALESCE(SUM(paste_count), 0) as pasted FROM keys WHERE outlier_reason IS NULL AND {}",
+            keys_filter
+        );
+        let keystrokes_row = bind_all(sqlx::query(&keystrokes_sql), &keys_binds)
+            .fetch_one(&self.pool)
+            .await?;
+        let mut keystrokes = keystrokes_row.get::<i64, _>("total");
+        let corrections = keystrokes_row.get::<i64, _>("corrections");
+        let pasted = keystrokes_row.get::<i64, _>("pasted");
         
-        let clicks_row = sqlx::query("SELECT COUNT(*) as total FROM clicks")
+        let (clicks_filter, clicks_binds) = filter_condition("clicks", filter);
+        let clicks_sql = format!(
+            "SELECT COUNT(*) as total FROM clicks WHERE outlier_reason IS NULL AND {}",
+            clicks_filter
+        );
+        let clicks_row = bind_all(sqlx::query(&clicks_sql), &clicks_binds)
             .fetch_one(&self.pool)
             .await?;
-        let clicks = clicks_row.get::<i64, _>("total");
+        let mut clicks = clicks_row.get::<i64, _>("total");
+        
+        let (scrolls_filter, scrolls_binds) = filter_condition("scrolls", filter);
+        let scrolls_sql = format!(
+            "SELECT COALESCE(SUM(event_count), 0) as total, \
+             COALESCE(SUM(distance_x), 0.0) as distance_x, COALESCE(SUM(distance_y), 0.0) as distance_y \
+             FROM scrolls WHERE {}",
+            scrolls_filter
+        );
+        let scrolls_row = bind_all(sqlx::query(&scrolls_sql), &scrolls_binds)
+            .fetch_one(&self.pool)
+            .await?;
+        let scrolls = scrolls_row.get::<i64, _>("total");
+        let scroll_distance_x = scrolls_row.get::<f64, _>("distance_x");
+        let scroll_distance_y = scrolls_row.get::<f64, _>("distance_y");
         
-        let windows_row = sqlx::query("SELECT COUNT(*) as total FROM windows")
+        let (windows_filter, windows_binds) = filter_condition("w", filter);
+        let windows_sql = format!("SELECT COUNT(*) as total FROM windows w WHERE {}", windows_filter);
+        let windows_row = bind_all(sqlx::query(&windows_sql), &windows_binds)
             .fetch_one(&self.pool)
             .await?;
         let windows = windows_row.get::<i64, _>("total");
         
-        let processes_row = sqlx::query("SELECT COUNT(*) as total FROM processes")
+        let processes_sql = if filter.is_empty() {
+            "SELECT COUNT(*) as total FROM processes".to_string()
+        } else {
+            format!("SELECT COUNT(DISTINCT w.process_id) as total FROM windows w WHERE {}", windows_filter)
+        };
+        let processes_row = bind_all(sqlx::query(&processes_sql), &windows_binds)
             .fetch_one(&self.pool)
             .await?;
         let processes = processes_row.get::<i64, _>("total");
         
-        let most_active_process = sqlx::query(
+        let most_active_sql = format!(
             r#"
             SELECT p.name
             FROM processes p
             JOIN windows w ON p.id = w.process_id
+            WHERE {}
             GROUP BY p.id
             ORDER BY COUNT(*) DESC
             LIMIT 1
-            "#
-        )
-        .fetch_optional(&self.pool)
-        .await?
-        .map(|row| row.get::<String, _>("name"));
+            "#,
+            windows_filter
+        );
+        let most_active_process = bind_all(sqlx::query(&most_active_sql), &windows_binds)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.get::<String, _>("name"));
+        
+        // Activity periods clipped to the filter's range and, with a label, to
+        // the sessions carrying it
+        let range_start = filter.start.map_or("0000-01-01 00:00:00".to_string(), format_timestamp);
+        let range_end = filter.end.map_or("9999-12-31 23:59:59".to_string(), format_timestamp);
+        let mut session_duration: i64 = match &filter.session_label {
+            None => self.get_active_seconds_between(&range_start, &range_end).await?,
+            Some(label) => {
+                sqlx::query_scalar(
+                    r#"
+                    SELECT COALESCE(SUM(MAX(0,
+                        strftime('%s', MIN(p.ended_at, ?2, COALESCE(s.ended_at, '9999-12-31 23:59:59')))
+                        - strftime('%s', MAX(p.started_at, ?1, s.started_at))
+                    )), 0)
+                    FROM periods p
+                    JOIN sessions s ON s.label = ?3
+                    WHERE p.started_at < ?2 AND p.ended_at > ?1
+                    "#
+                )
+                .bind(&range_start)
+                .bind(&range_end)
+                .bind(label)
+                .fetch_one(&self.pool)
+                .await?
+            }
+        };
+        
+        // Pruned days only have their totals left. Those belong to no session,
+        // and count only when the whole day is in range
+        if filter.session_label.is_none() {
+            let first = NaiveDate::from_ymd_opt(1, 1, 1).unwrap();
+            let last = NaiveDate::from_ymd_opt(9999, 12, 31).unwrap();
+            for rollup in self.get_daily_rollups(first, last).await? {
+                let (day_start, day_end) = local_day_bounds(rollup.date);
+                if day_start >= range_start && day_end <= range_end {
+                    keystrokes += rollup.keystrokes;
+                    clicks += rollup.clicks;
+                    session_duration += rollup.active_seconds;
+                }
+            }
+        }
         
         Ok(ActivityStats {
             total_keystrokes: keystrokes,
+            total_corrections: corrections,
+            total_pasted_characters: pasted,
             total_clicks: clicks,
+            total_scrolls: scrolls,
+            total_scroll_distance_x: scroll_distance_x,
+            total_scroll_distance_y: scroll_distance_y,
             total_windows: windows,
             total_processes: processes,
-            session_duration: 0,
+            session_duration,
             most_active_process,
             most_active_window: None,
+            custom_metrics: Vec::new(),
+        })
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_process_names"))]
+    pub async fn get_process_names(&self) -> Result<HashMap<i64, String>> {
+        let _timer = self.timings.start("get_process_names");
+        let names = sqlx::query_as::<_, (i64, String)>("SELECT id, name FROM processes")
+            .fetch_all(&self.pool)
+            .await?;
+        
+        Ok(names.into_iter().collect())
+    }
+    
+    // Raw records created in [range.start, range.end), oldest first. Rows are
+    // fetched lazily, so exporters can walk years of data in constant memory.
+    pub fn stream_windows(&self, range: Range<DateTime<Utc>>) -> BoxStream<'_, Result<Window>> {
+        let rows = sqlx::query_as::<_, Window>(
+            "SELECT id, process_id, title, x, y, width, height, created_at FROM windows \
+             WHERE created_at >= ? AND created_at < ? ORDER BY created_at, id"
+        )
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.end))
+        .fetch(&self.pool)
+        .map_err(SelfspyError::from)
+        .boxed();
+        self.timed_stream("stream_windows", rows)
+    }
+    
+    pub fn stream_clicks(&self, range: Range<DateTime<Utc>>) -> BoxStream<'_, Result<Click>> {
+        let rows = sqlx::query_as::<_, Click>(
+            "SELECT id, window_id, x, y, button, double_click, scale, created_at FROM clicks \
+             WHERE created_at >= ? AND created_at < ? ORDER BY created_at, id"
+        )
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.end))
+        .fetch(&self.pool)
+        .map_err(SelfspyError::from)
+        .boxed();
+        self.timed_stream("stream_clicks", rows)
+    }
+    
+    // Key text stays as stored (encrypted when encryption is enabled)
+    pub fn stream_keys(&self, range: Range<DateTime<Utc>>) -> BoxStream<'_, Result<Keys>> {
+        let rows = sqlx::query_as::<_, Keys>(
+            "SELECT id, window_id, encrypted_keys, key_count, created_at FROM keys \
+             WHERE created_at >= ? AND created_at < ? ORDER BY created_at, id"
+        )
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.end))
+        .fetch(&self.pool)
+        .map_err(SelfspyError::from)
+        .boxed();
+        self.timed_stream("stream_keys", rows)
+    }
+    
+    // A stream is read for as long as its consumer wants, so rather than
+    // timing the call that creates it, the timer travels with the stream and
+    // stops when it is dropped, and each poll runs inside the db span
+    fn timed_stream<'a, T: 'a>(&'a self, query: &'static str, mut rows: BoxStream<'a, Result<T>>) -> BoxStream<'a, Result<T>> {
+        let timer = self.timings.start(query);
+        let span = tracing::debug_span!("db", query);
+        futures::stream::poll_fn(move |cx| {
+            let _timer = &timer;
+            let _entered = span.enter();
+            rows.poll_next_unpin(cx)
         })
+        .boxed()
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "start_session"))]
+    pub async fn start_session(&self, label: Option<&str>) -> Result<i64> {
+        let _timer = self.timings.start("start_session");
+        let result = sqlx::query("INSERT INTO sessions (label) VALUES (?)")
+            .bind(label)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "end_session"))]
+    pub async fn end_session(&self, session_id: i64) -> Result<()> {
+        let _timer = self.timings.start("end_session");
+        sqlx::query("UPDATE sessions SET ended_at = CURRENT_TIMESTAMP WHERE id = ? AND ended_at IS NULL")
+            .bind(session_id)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_sessions"))]
+    pub async fn get_sessions(&self, limit: i64) -> Result<Vec<Session>> {
+        let _timer = self.timings.start("get_sessions");
+        let sessions = sqlx::query_as::<_, Session>(
+            "SELECT id, label, started_at, ended_at FROM sessions ORDER BY started_at DESC LIMIT ?"
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(sessions)
     }
+    
+    // Distinct active minutes per local clock hour in [start, end)
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_hourly_activity"))]
+    pub async fn get_hourly_activity(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<HourlyActivity>> {
+        let _timer = self.timings.start("get_hourly_activity");
+        let (range_start, _) = local_day_bounds(start);
+        let (range_end, _) = local_day_bounds(end);
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT hour, COUNT(DISTINCT minute) as active_minutes
+            FROM (
+                SELECT strftime('%Y-%m-%d %H:00:00', created_at, 'localtime') as hour,
+                       strftime('%Y-%m-%d %H:%M', created_at) as minute
+                FROM keys
+                WHERE created_at >= ?1 AND created_at < ?2 AND outlier_reason IS NULL
+                UNION ALL
+                SELECT strftime('%Y-%m-%d %H:00:00', created_at, 'localtime') as hour,
+                       strftime('%Y-%m-%d %H:%M', created_at) as minute
+                FROM clicks
+                WHERE created_at >= ?1 AND created_at < ?2 AND outlier_reason IS NULL
+            )
+            GROUP BY hour
+            ORDER BY hour
+            "#
+        )
+        .bind(&range_start)
+        .bind(&range_end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut activity = Vec::with_capacity(rows.len());
+        for row in rows {
+            let hour: String = row.get("hour");
+            activity.push(HourlyActivity {
+                hour: NaiveDateTime::parse_from_str(&hour, "%Y-%m-%d %H:%M:%S")?,
+                active_minutes: row.get("active_minutes"),
+            });
+        }
+        
+        Ok(activity)
+    }
+    
+    // Keystrokes, clicks and active time per bucket in [range.start, range.end),
+    // oldest first. Buckets without input are included so series have no gaps.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_activity_buckets"))]
+    pub async fn get_activity_buckets(
+        &self,
+        range: Range<DateTime<Utc>>,
+        bucket: BucketSize,
+    ) -> Result<Vec<ActivityBucket>> {
+        let _timer = self.timings.start("get_activity_buckets");
+        
+        // Local hours are the finest bucket; coarser ones are folded from them
+        let rows = sqlx::query(
+            r#"
+            SELECT hour,
+                   COALESCE(SUM(keystrokes), 0) as keystrokes,
+                   COALESCE(SUM(clicks), 0) as clicks,
+                   COUNT(DISTINCT minute) as active_minutes
+            FROM (
+                SELECT strftime('%Y-%m-%d %H:00:00', created_at, 'localtime') as hour,
+                       strftime('%Y-%m-%d %H:%M', created_at) as minute,
+                       COALESCE(event_count, key_count) as keystrokes,
+                       0 as clicks
+                FROM keys
+                WHERE created_at >= ?1 AND created_at < ?2 AND outlier_reason IS NULL
+                UNION ALL
+                SELECT strftime('%Y-%m-%d %H:00:00', created_at, 'localtime') as hour,
+                       strftime('%Y-%m-%d %H:%M', created_at) as minute,
+                       0 as keystrokes,
+                       1 as clicks
+                FROM clicks
+                WHERE created_at >= ?1 AND created_at < ?2 AND outlier_reason IS NULL
+            )
+            GROUP BY hour
+            "#
+        )
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.end))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let first = bucket.start_of(range.start.with_timezone(&Local).naive_local());
+        let end = range.end.with_timezone(&Local).naive_local();
+        let mut buckets: BTreeMap<NaiveDateTime, ActivityBucket> = BTreeMap::new();
+        let mut start = first;
+        while start < end {
+            buckets.insert(start, ActivityBucket { start, keystrokes: 0, clicks: 0, active_seconds: 0 });
+            start += bucket.length();
+        }
+        
+        for row in rows {
+            let hour = NaiveDateTime::parse_from_str(&row.get::<String, _>("hour"), "%Y-%m-%d %H:%M:%S")?;
+            let start = bucket.start_of(hour);
+            let entry = buckets
+                .entry(start)
+                .or_insert(ActivityBucket { start, keystrokes: 0, clicks: 0, active_seconds: 0 });
+            entry.keystrokes += row.get::<i64, _>("keystrokes");
+            entry.clicks += row.get::<i64, _>("clicks");
+            entry.active_seconds += row.get::<i64, _>("active_minutes") * 60;
+        }
+        
+        Ok(buckets.into_values().collect())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_goal"))]
+    pub async fn insert_goal(&self, metric: &str, weekly_target: i64, effective_from: NaiveDate) -> Result<i64> {
+        let _timer = self.timings.start("insert_goal");
+        let result = sqlx::query(
+            "INSERT INTO goals (metric, weekly_target, effective_from) VALUES (?, ?, ?)"
+        )
+        .bind(metric)
+        .bind(weekly_target)
+        .bind(effective_from)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_goals"))]
+    pub async fn get_goals(&self) -> Result<Vec<Goal>> {
+        let _timer = self.timings.start("get_goals");
+        let goals = sqlx::query_as::<_, Goal>(
+            "SELECT id, metric, weekly_target, effective_from, created_at FROM goals ORDER BY effective_from, id"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(goals)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_mouse_moves"))]
+    pub async fn insert_mouse_moves(
+        &self,
+        window_id: i64,
+        distance: i64,
+        event_count: i32,
+    ) -> Result<i64> {
+        let _timer = self.timings.start("insert_mouse_moves");
+        self.write_now(PendingWrite::MouseMoves { window_id, distance, event_count, at: Utc::now() })
+            .await
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_scrolls"))]
+    pub async fn insert_scrolls(
+        &self,
+        window_id: i64,
+        event_count: i32,
+        distance_x: f64,
+        distance_y: f64,
+    ) -> Result<i64> {
+        let _timer = self.timings.start("insert_scrolls");
+        self.write_now(PendingWrite::Scrolls { window_id, event_count, distance_x, distance_y, at: Utc::now() })
+            .await
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_typing_rhythm"))]
+    pub async fn insert_typing_rhythm(&self, rhythm: TypingRhythm) -> Result<i64> {
+        let _timer = self.timings.start("insert_typing_rhythm");
+        self.write_now(PendingWrite::TypingRhythm { rhythm, at: Utc::now() }).await
+    }
+    
+    // Typing rhythm totals per local hour of one day
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_hourly_rhythm"))]
+    pub async fn get_hourly_rhythm(&self, day: NaiveDate) -> Result<Vec<HourlyRhythm>> {
+        let _timer = self.timings.start("get_hourly_rhythm");
+        let (start, end) = local_day_bounds(day);
+        
+        let hours = sqlx::query_as::<_, HourlyRhythm>(
+            r#"
+            SELECT CAST(strftime('%H', created_at, 'localtime') AS INTEGER) as hour,
+                   SUM(key_count) as keys,
+                   SUM(backspace_count) as backspaces,
+                   SUM(interval_count) as intervals,
+                   SUM(interval_sum_ms) as interval_sum_ms,
+                   SUM(interval_sq_sum_ms) as interval_sq_sum_ms,
+                   SUM(hold_count) as holds,
+                   SUM(hold_sum_ms) as hold_sum_ms,
+                   MIN(hold_min_ms) as hold_min_ms,
+                   MAX(hold_max_ms) as hold_max_ms
+            FROM typing_rhythm
+            WHERE created_at >= ? AND created_at < ?
+            GROUP BY hour
+            ORDER BY hour
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(hours)
+    }
+    
+    // Keystrokes, clicks and scroll events for local days in [start, end),
+    // grouped by app or by local hour of day, busiest first
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_input_mix"))]
+    pub async fn get_input_mix(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        grouping: InputMixGrouping,
+    ) -> Result<Vec<InputMix>> {
+        let _timer = self.timings.start("get_input_mix");
+        let (range_start, _) = local_day_bounds(start);
+        let (range_end, _) = local_day_bounds(end);
+        let group = match grouping {
+            InputMixGrouping::App => "p.name",
+            InputMixGrouping::HourOfDay => "strftime('%H', e.created_at, 'localtime')",
+        };
+        
+        let sql = format!(
+            r#"
+            SELECT {group} as "group",
+                   SUM(e.keystrokes) as keystrokes,
+                   SUM(e.clicks) as clicks,
+                   SUM(e.scrolls) as scrolls
+            FROM (
+                SELECT window_id, created_at, COALESCE(event_count, key_count) as keystrokes, 0 as clicks, 0 as scrolls
+                FROM keys WHERE outlier_reason IS NULL
+                UNION ALL
+                SELECT window_id, created_at, 0, 1, 0
+                FROM clicks WHERE outlier_reason IS NULL
+                UNION ALL
+                SELECT window_id, created_at, 0, 0, event_count
+                FROM scrolls
+            ) e
+            JOIN windows w ON w.id = e.window_id
+            JOIN processes p ON p.id = w.process_id
+            WHERE e.created_at >= ? AND e.created_at < ?
+            GROUP BY 1
+            ORDER BY SUM(e.keystrokes) + SUM(e.clicks) + SUM(e.scrolls) DESC
+            "#
+        );
+        
+        let mix = sqlx::query_as::<_, InputMix>(&sql)
+            .bind(&range_start)
+            .bind(&range_end)
+            .fetch_all(&self.pool)
+            .await?;
+        
+        Ok(mix)
+    }
+    
+    // Keystrokes and corrections for local days in [start, end), grouped by app
+    // or by local hour of day, most typing first
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_error_rates"))]
+    pub async fn get_error_rates(
+        &self,
+        start: NaiveDate,
+        end: NaiveDate,
+        grouping: InputMixGrouping,
+    ) -> Result<Vec<ErrorRate>> {
+        let _timer = self.timings.start("get_error_rates");
+        let (range_start, _) = local_day_bounds(start);
+        let (range_end, _) = local_day_bounds(end);
+        let group = match grouping {
+            InputMixGrouping::App => "p.name",
+            InputMixGrouping::HourOfDay => "strftime('%H', k.created_at, 'localtime')",
+        };
+        
+        let sql = format!(
+            r#"
+            SELECT {group} as "group",
+                   SUM(COALESCE(k.event_count, k.key_count)) as keystrokes,
+                   SUM(k.correction_count) as corrections
+            FROM keys k
+            JOIN windows w ON w.id = k.window_id
+            JOIN processes p ON p.id = w.process_id
+            WHERE k.outlier_reason IS NULL AND k.created_at >= ? AND k.created_at < ?
+            GROUP BY 1
+            ORDER BY 2 DESC
+            "#
+        );
+        
+        let rates = sqlx::query_as::<_, ErrorRate>(&sql)
+            .bind(&range_start)
+            .bind(&range_end)
+            .fetch_all(&self.pool)
+            .await?;
+        
+        Ok(rates)
+    }
+    
+    // Bigram and trigram timings summed over local days in [start, end),
+    // leaving out n-grams typed fewer than MIN_NGRAM_COUNT times
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_ngram_timings"))]
+    pub async fn get_ngram_timings(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<NgramTiming>> {
+        let _timer = self.timings.start("get_ngram_timings");
+        let timings = sqlx::query_as::<_, NgramTiming>(
+            r#"
+            SELECT ngram,
+                   SUM(key_count) as count,
+                   SUM(interval_sum_ms) as interval_sum_ms
+            FROM key_ngram_days
+            WHERE day >= ? AND day < ?
+            GROUP BY ngram
+            HAVING SUM(key_count) >= ?
+            "#
+        )
+        .bind(start)
+        .bind(end)
+        .bind(MIN_NGRAM_COUNT)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(timings)
+    }
+    
+    // Deletes the n-grams typed fewer than MIN_NGRAM_COUNT times on a day
+    // before `today`; the monitor calls this once a day
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "forget_rare_ngrams"))]
+    pub async fn forget_rare_ngrams(&self, today: NaiveDate) -> Result<u64> {
+        let _timer = self.timings.start("forget_rare_ngrams");
+        let deleted = sqlx::query("DELETE FROM key_ngram_days WHERE day < ? AND key_count < ?")
+            .bind(today)
+            .bind(MIN_NGRAM_COUNT)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        Ok(deleted)
+    }
+    
+    // Keystrokes and clicks per minute in [range.start, range.end), for minutes with input
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_minute_totals"))]
+    pub async fn get_minute_totals(&self, range: Range<DateTime<Utc>>) -> Result<Vec<MinuteTotals>> {
+        let _timer = self.timings.start("get_minute_totals");
+        let rows = sqlx::query(
+            r#"
+            SELECT strftime('%Y-%m-%d %H:%M:00', e.created_at) as minute,
+                   SUM(e.keystrokes) as keystrokes,
+                   SUM(e.clicks) as clicks
+            FROM (
+                SELECT created_at, COALESCE(event_count, key_count) as keystrokes, 0 as clicks
+                FROM keys WHERE outlier_reason IS NULL
+                UNION ALL
+                SELECT created_at, 0, 1
+                FROM clicks WHERE outlier_reason IS NULL
+            ) e
+            WHERE e.created_at >= ? AND e.created_at < ?
+            GROUP BY minute
+            ORDER BY minute
+            "#
+        )
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.end))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut totals = Vec::with_capacity(rows.len());
+        for row in rows {
+            let minute: String = row.get("minute");
+            totals.push(MinuteTotals {
+                minute: NaiveDateTime::parse_from_str(&minute, "%Y-%m-%d %H:%M:%S")?.and_utc(),
+                keystrokes: row.get("keystrokes"),
+                clicks: row.get("clicks"),
+            });
+        }
+        
+        Ok(totals)
+    }
+    
+    // For each local minute of the day with input, the app that got the most of it
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_minute_activity"))]
+    pub async fn get_minute_activity(&self, day: NaiveDate) -> Result<Vec<MinuteActivity>> {
+        let _timer = self.timings.start("get_minute_activity");
+        let (start, end) = local_day_bounds(day);
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT strftime('%Y-%m-%d %H:%M:00', e.created_at, 'localtime') as minute,
+                   p.name as process,
+                   SUM(e.weight) as events
+            FROM (
+                SELECT window_id, created_at, COALESCE(event_count, key_count) as weight
+                FROM keys WHERE outlier_reason IS NULL
+                UNION ALL
+                SELECT window_id, created_at, 1
+                FROM clicks WHERE outlier_reason IS NULL
+            ) e
+            JOIN windows w ON w.id = e.window_id
+            JOIN processes p ON p.id = w.process_id
+            WHERE e.created_at >= ? AND e.created_at < ?
+            GROUP BY minute, process
+            ORDER BY minute, events DESC
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut activity: Vec<MinuteActivity> = Vec::new();
+        for row in rows {
+            let minute: String = row.get("minute");
+            let minute = NaiveDateTime::parse_from_str(&minute, "%Y-%m-%d %H:%M:%S")?;
+            // Rows are ordered busiest first within a minute
+            if activity.last().is_some_and(|last| last.minute == minute) {
+                continue;
+            }
+            activity.push(MinuteActivity {
+                minute,
+                process: row.get("process"),
+            });
+        }
+        
+        Ok(activity)
+    }
+    
+    // Local times at which a new window was recorded during the day
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_window_switches"))]
+    pub async fn get_window_switches(&self, day: NaiveDate) -> Result<Vec<NaiveDateTime>> {
+        let _timer = self.timings.start("get_window_switches");
+        let (start, end) = local_day_bounds(day);
+        
+        let rows = sqlx::query(
+            "SELECT strftime('%Y-%m-%d %H:%M:%S', created_at, 'localtime') as at FROM windows \
+             WHERE created_at >= ? AND created_at < ? ORDER BY created_at"
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut switches = Vec::with_capacity(rows.len());
+        for row in rows {
+            let at: String = row.get("at");
+            switches.push(NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S")?);
+        }
+        
+        Ok(switches)
+    }
+    
+    // Keystroke batches of one local day with the app that received them, in order
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_key_activity"))]
+    pub async fn get_key_activity(&self, day: NaiveDate) -> Result<Vec<KeyActivity>> {
+        let _timer = self.timings.start("get_key_activity");
+        let (start, end) = local_day_bounds(day);
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT strftime('%Y-%m-%d %H:%M:%S', k.created_at, 'localtime') as at,
+                   p.name as process,
+                   COALESCE(k.event_count, k.key_count) as keys
+            FROM keys k
+            JOIN windows w ON w.id = k.window_id
+            JOIN processes p ON p.id = w.process_id
+            WHERE k.created_at >= ? AND k.created_at < ? AND k.outlier_reason IS NULL
+            ORDER BY k.created_at
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut activity = Vec::with_capacity(rows.len());
+        for row in rows {
+            let at: String = row.get("at");
+            activity.push(KeyActivity {
+                at: NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S")?,
+                process: row.get("process"),
+                keys: row.get("keys"),
+            });
+        }
+        
+        Ok(activity)
+    }
+    
+    // Window changes with their app and title between two local dates (end exclusive), in order
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_window_visits"))]
+    pub async fn get_window_visits(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<WindowVisit>> {
+        let _timer = self.timings.start("get_window_visits");
+        let (start, _) = local_day_bounds(start);
+        let (end, _) = local_day_bounds(end);
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT strftime('%Y-%m-%d %H:%M:%S', w.created_at, 'localtime') as at,
+                   p.name as process,
+                   w.title as title,
+                   w.document as document
+            FROM windows w
+            JOIN processes p ON p.id = w.process_id
+            WHERE w.created_at >= ? AND w.created_at < ?
+            ORDER BY w.created_at
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut visits = Vec::with_capacity(rows.len());
+        for row in rows {
+            let at: String = row.get("at");
+            visits.push(WindowVisit {
+                at: NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S")?,
+                process: row.get("process"),
+                title: row.get("title"),
+                document: row.get("document"),
+            });
+        }
+        
+        Ok(visits)
+    }
+    
+    // Keystrokes, clicks, windows and active time per process in
+    // [range.start, range.end), most active first. Only windows opened inside
+    // the range count towards active time, and the last one gets none.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_process_stats"))]
+    pub async fn get_process_stats(&self, range: Range<DateTime<Utc>>) -> Result<Vec<ProcessStats>> {
+        let _timer = self.timings.start("get_process_stats");
+        let (start, end) = (format_timestamp(range.start), format_timestamp(range.end));
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT p.name as process,
+                   SUM(e.keystrokes) as keystrokes,
+                   SUM(e.clicks) as clicks,
+                   SUM(e.windows) as windows
+            FROM (
+                SELECT window_id, created_at, COALESCE(event_count, key_count) as keystrokes, 0 as clicks, 0 as windows
+                FROM keys WHERE outlier_reason IS NULL
+                UNION ALL
+                SELECT window_id, created_at, 0, 1, 0
+                FROM clicks WHERE outlier_reason IS NULL
+                UNION ALL
+                SELECT id, created_at, 0, 0, 1
+                FROM windows
+            ) e
+            JOIN windows w ON w.id = e.window_id
+            JOIN processes p ON p.id = w.process_id
+            WHERE e.created_at >= ? AND e.created_at < ?
+            GROUP BY p.name
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut stats: BTreeMap<String, ProcessStats> = BTreeMap::new();
+        for row in rows {
+            let process: String = row.get("process");
+            stats.insert(
+                process.clone(),
+                ProcessStats {
+                    process,
+                    keystrokes: row.get("keystrokes"),
+                    clicks: row.get("clicks"),
+                    windows: row.get("windows"),
+                    active_seconds: 0,
+                },
+            );
+        }
+        
+        let changes = sqlx::query(
+            r#"
+            SELECT strftime('%Y-%m-%d %H:%M:%S', w.created_at) as at, p.name as process
+            FROM windows w
+            JOIN processes p ON p.id = w.process_id
+            WHERE w.created_at >= ? AND w.created_at < ?
+            ORDER BY w.created_at
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let cap = chrono::Duration::minutes(crate::distractions::MAX_VISIT_MINUTES);
+        let mut previous: Option<(NaiveDateTime, String)> = None;
+        for row in changes {
+            let at = NaiveDateTime::parse_from_str(&row.get::<String, _>("at"), "%Y-%m-%d %H:%M:%S")?;
+            if let Some((since, process)) = previous.take() {
+                let entry = stats.entry(process.clone()).or_insert_with(|| ProcessStats {
+                    process,
+                    ..ProcessStats::default()
+                });
+                entry.active_seconds += (at - since).min(cap).num_seconds();
+            }
+            previous = Some((at, row.get("process")));
+        }
+        
+        let mut stats: Vec<ProcessStats> = stats.into_values().collect();
+        stats.sort_by(|a, b| {
+            b.active_seconds
+                .cmp(&a.active_seconds)
+                .then(b.keystrokes.cmp(&a.keystrokes))
+                .then(a.process.cmp(&b.process))
+        });
+        Ok(stats)
+    }
+    
+    // Seconds per app per local day in [start, end), timed like
+    // `distractions::visit_seconds`. Titles are never read, so this is safe
+    // to expose where window contents must stay private.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_app_days"))]
+    pub async fn get_app_days(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<AppDay>> {
+        let _timer = self.timings.start("get_app_days");
+        let (start, _) = local_day_bounds(start);
+        let (end, _) = local_day_bounds(end);
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT strftime('%Y-%m-%d %H:%M:%S', w.created_at, 'localtime') as at,
+                   p.name as process
+            FROM windows w
+            JOIN processes p ON p.id = w.process_id
+            WHERE w.created_at >= ? AND w.created_at < ?
+            ORDER BY w.created_at
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut changes = Vec::with_capacity(rows.len());
+        for row in rows {
+            let at: String = row.get("at");
+            changes.push((NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S")?, row.get::<String, _>("process")));
+        }
+        
+        let cap = chrono::Duration::minutes(crate::distractions::MAX_VISIT_MINUTES);
+        let mut totals: BTreeMap<(NaiveDate, String), i64> = BTreeMap::new();
+        for pair in changes.windows(2) {
+            let ((at, process), (next, _)) = (&pair[0], &pair[1]);
+            if next.date() == at.date() {
+                *totals.entry((at.date(), process.clone())).or_default() += (*next - *at).min(cap).num_seconds();
+            }
+        }
+        
+        Ok(totals
+            .into_iter()
+            .filter(|(_, seconds)| *seconds > 0)
+            .map(|((date, process), seconds)| AppDay { date, process, seconds })
+            .collect())
+    }
+    
+    // Most recent windows whose title or app contains `query` (case-insensitive)
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "search_window_history"))]
+    pub async fn search_window_history(&self, query: &str, limit: i64) -> Result<Vec<WindowVisit>> {
+        let _timer = self.timings.start("search_window_history");
+        let rows = sqlx::query(
+            r#"
+            SELECT strftime('%Y-%m-%d %H:%M:%S', w.created_at, 'localtime') as at,
+                   p.name as process,
+                   w.title as title,
+                   w.document as document
+            FROM windows w
+            JOIN processes p ON p.id = w.process_id
+            WHERE instr(lower(w.title), lower(?1)) > 0 OR instr(lower(p.name), lower(?1)) > 0
+            ORDER BY w.created_at DESC
+            LIMIT ?2
+            "#
+        )
+        .bind(query)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut visits = Vec::with_capacity(rows.len());
+        for row in rows {
+            let at: String = row.get("at");
+            visits.push(WindowVisit {
+                at: NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S")?,
+                process: row.get("process"),
+                title: row.get("title"),
+                document: row.get("document"),
+            });
+        }
+        
+        Ok(visits)
+    }
+    
+    // The most recently focused window, if any was recorded
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_latest_window_visit"))]
+    pub async fn get_latest_window_visit(&self) -> Result<Option<WindowVisit>> {
+        let _timer = self.timings.start("get_latest_window_visit");
+        let row = sqlx::query(
+            r#"
+            SELECT strftime('%Y-%m-%d %H:%M:%S', w.created_at, 'localtime') as at,
+                   p.name as process,
+                   w.title as title,
+                   w.document as document
+            FROM windows w
+            JOIN processes p ON p.id = w.process_id
+            ORDER BY w.created_at DESC
+            LIMIT 1
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        match row {
+            Some(row) => {
+                let at: String = row.get("at");
+                Ok(Some(WindowVisit {
+                    at: NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S")?,
+                    process: row.get("process"),
+                    title: row.get("title"),
+                    document: row.get("document"),
+                }))
+            }
+            None => Ok(None),
+        }
+    }
+    
+    // Replaces the stored deep-work intervals of one day, so detection can be re-run
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "replace_deep_work"))]
+    pub async fn replace_deep_work(&self, day: NaiveDate, intervals: &[DeepWorkInterval]) -> Result<()> {
+        let _timer = self.timings.start("replace_deep_work");
+        let mut tx = self.pool.begin().await?;
+        
+        sqlx::query("DELETE FROM deep_work WHERE day = ?")
+            .bind(day)
+            .execute(&mut *tx)
+            .await?;
+        
+        for interval in intervals {
+            sqlx::query(
+                "INSERT INTO deep_work (day, started_at, ended_at, process, minutes, switches) \
+                 VALUES (?, ?, ?, ?, ?, ?)"
+            )
+            .bind(day)
+            .bind(interval.started_at)
+            .bind(interval.ended_at)
+            .bind(&interval.process)
+            .bind(interval.minutes)
+            .bind(interval.switches)
+            .execute(&mut *tx)
+            .await?;
+        }
+        
+        tx.commit().await?;
+        Ok(())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_deep_work_days"))]
+    pub async fn get_deep_work_days(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<DeepWorkDay>> {
+        let _timer = self.timings.start("get_deep_work_days");
+        let days = sqlx::query_as::<_, DeepWorkDay>(
+            r#"
+            SELECT day, SUM(minutes) as minutes, COUNT(*) as intervals, MAX(minutes) as longest_minutes
+            FROM deep_work
+            WHERE day >= ? AND day < ?
+            GROUP BY day
+            ORDER BY day
+            "#
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(days)
+    }
+    
+    // Days with the most deep work, best first
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_top_deep_work_days"))]
+    pub async fn get_top_deep_work_days(&self, limit: i64) -> Result<Vec<DeepWorkDay>> {
+        let _timer = self.timings.start("get_top_deep_work_days");
+        let days = sqlx::query_as::<_, DeepWorkDay>(
+            r#"
+            SELECT day, SUM(minutes) as minutes, COUNT(*) as intervals, MAX(minutes) as longest_minutes
+            FROM deep_work
+            GROUP BY day
+            ORDER BY minutes DESC
+            LIMIT ?
+            "#
+        )
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(days)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "open_media_play"))]
+    pub async fn open_media_play(&self, player: &str, title: &str, artist: Option<&str>) -> Result<i64> {
+        let _timer = self.timings.start("open_media_play");
+        let result = sqlx::query("INSERT INTO media_plays (player, title, artist) VALUES (?, ?, ?)")
+            .bind(player)
+            .bind(title)
+            .bind(artist)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "close_media_play"))]
+    pub async fn close_media_play(&self, play_id: i64) -> Result<()> {
+        let _timer = self.timings.start("close_media_play");
+        sqlx::query("UPDATE media_plays SET ended_at = CURRENT_TIMESTAMP WHERE id = ? AND ended_at IS NULL")
+            .bind(play_id)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    // Plays started within [start, end) local days; a play still open counts up to now
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_media_listening"))]
+    pub async fn get_media_listening(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<MediaListening>> {
+        let _timer = self.timings.start("get_media_listening");
+        let (range_start, _) = local_day_bounds(start);
+        let (range_end, _) = local_day_bounds(end);
+        let now = format_timestamp(Utc::now());
+        
+        let listening = sqlx::query_as::<_, MediaListening>(
+            r#"
+            SELECT label, SUM(seconds) as seconds, SUM(keystrokes) as keystrokes
+            FROM (
+                SELECT COALESCE(NULLIF(m.artist, ''), m.player) as label,
+                       CAST(strftime('%s', COALESCE(m.ended_at, ?)) AS INTEGER)
+                           - CAST(strftime('%s', m.started_at) AS INTEGER) as seconds,
+                       (SELECT COALESCE(SUM(COALESCE(k.event_count, k.key_count)), 0) FROM keys k
+                        WHERE k.outlier_reason IS NULL
+                          AND k.created_at >= m.started_at AND k.created_at < COALESCE(m.ended_at, ?)) as keystrokes
+                FROM media_plays m
+                WHERE m.started_at >= ? AND m.started_at < ?
+            )
+            GROUP BY label
+            ORDER BY seconds DESC
+            "#,
+        )
+        .bind(&now)
+        .bind(&now)
+        .bind(&range_start)
+        .bind(&range_end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(listening)
+    }
+    
+    // Copies the data of an original Python selfspy database (tables process,
+    // window, geometry, keys and click, timestamps in naive local time) into
+    // this one. Typed text is carried over as `text` says when the old
+    // database was recorded without a password (no password.digest beside
+    // it); otherwise it, like the key timings, was encrypted under the old
+    // password and stays behind, and imported keys rows hold the press count
+    // only. A window takes the geometry of its first keys or click row.
+    // Click releases and mouse move counts have no counterpart; wheel
+    // "clicks" (buttons 4 to 7) become scroll rows. Each legacy database is
+    // recorded by its first window, so importing it again, even after it grew
+    // or moved, fails with AlreadyImported. With `dry_run` nothing is written
+    // and the summary counts what would be imported.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "import_legacy"))]
+    pub async fn import_legacy(
+        &self,
+        path: &Path,
+        text: LegacyText<'_>,
+        dry_run: bool,
+        progress: &Progress,
+    ) -> Result<LegacyImport> {
+        let _timer = self.timings.start("import_legacy");
+        if !path.exists() {
+            return Err(SelfspyError::Io(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("no database at {}", path.display()),
+            )));
+        }
+        let options = SqliteConnectOptions::new()
+            .filename(path)
+            .read_only(true)
+            .create_if_missing(false);
+        let legacy = SqlitePool::connect_with(options).await?;
+        
+        let tables: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*) FROM sqlite_master
+            WHERE type = 'table' AND name IN ('process', 'window', 'geometry', 'keys', 'click')
+            "#
+        )
+        .fetch_one(&legacy)
+        .await?;
+        if tables < 5 {
+            return Err(SelfspyError::InvalidData(format!(
+                "{} is not a database of the original selfspy",
+                path.display()
+            )));
+        }
+        
+        let count = |sql: &'static str| sqlx::query_scalar::<Sqlite, i64>(sql).fetch_one(&legacy);
+        let totals = LegacyImport {
+            processes: count("SELECT COUNT(*) FROM process").await? as u64,
+            windows: count("SELECT COUNT(*) FROM window").await? as u64,
+            key_batches: count("SELECT COUNT(*) FROM keys").await? as u64,
+            keystrokes: count("SELECT COALESCE(SUM(nrkeys), 0) FROM keys").await?,
+            clicks: count("SELECT COUNT(*) FROM click WHERE press = 1 AND button IN (1, 2, 3)").await? as u64,
+            scrolls: count("SELECT COUNT(*) FROM click WHERE press = 1 AND button IN (4, 5, 6, 7)").await? as u64,
+            ..Default::default()
+        };
+        
+        let source = match legacy_source(&legacy).await? {
+            Some(source) => {
+                let imported_at: Option<String> =
+                    sqlx::query_scalar("SELECT imported_at FROM legacy_imports WHERE source = ?")
+                        .bind(&source)
+                        .fetch_optional(&self.pool)
+                        .await?;
+                if let Some(imported_at) = imported_at {
+                    return Err(SelfspyError::AlreadyImported(path.display().to_string(), imported_at));
+                }
+                Some(source)
+            }
+            // Nothing recorded in it, so nothing to import twice
+            None => None,
+        };
+        if dry_run {
+            return Ok(totals);
+        }
+        let text = match text {
+            _ if path.with_file_name(LEGACY_PASSWORD_DIGEST).exists() => LegacyText::Omit,
+            text => text,
+        };
+        
+        let mut tx = self.pool.begin().await?;
+        let mut imported = LegacyImport::default();
+        
+        // Processes are merged by name with the ones already recorded
+        progress.start_stage("Importing processes", Some(totals.processes));
+        let mut processes: HashMap<i64, i64> = HashMap::new();
+        let mut rows = sqlx::query("SELECT id, name FROM process").fetch(&legacy);
+        while let Some(row) = rows.try_next().await? {
+            progress.check_cancelled()?;
+            progress.advance(1);
+            // Rows come from a file of unknown origin, so nothing is assumed
+            // about their types: mismatches fail the import instead of panicking
+            let Some(name) = row.try_get::<Option<String>, _>("name")? else {
+                continue;
+            };
+            sqlx::query("INSERT INTO processes (name) VALUES (?) ON CONFLICT(name) DO NOTHING")
+                .bind(&name)
+                .execute(&mut *tx)
+                .await?;
+            let process_id: i64 = sqlx::query_scalar("SELECT id FROM processes WHERE name = ?")
+                .bind(&name)
+                .fetch_one(&mut *tx)
+                .await?;
+            processes.insert(row.try_get("id")?, process_id);
+            imported.processes += 1;
+        }
+        drop(rows);
+        
+        progress.start_stage("Importing windows", Some(totals.windows));
+        let mut windows: HashMap<i64, i64> = HashMap::new();
+        let mut rows = sqlx::query(
+            r#"
+            SELECT w.id, w.created_at, w.title, w.process_id, g.xpos, g.ypos, g.width, g.height
+            FROM window w
+            LEFT JOIN (
+                SELECT window_id, MIN(geometry_id) as geometry_id
+                FROM (SELECT window_id, geometry_id FROM keys UNION ALL SELECT window_id, geometry_id FROM click)
+                GROUP BY window_id
+            ) first ON first.window_id = w.id
+            LEFT JOIN geometry g ON g.id = first.geometry_id
+            ORDER BY w.id
+            "#
+        )
+        .fetch(&legacy);
+        while let Some(row) = rows.try_next().await? {
+            progress.check_cancelled()?;
+            progress.advance(1);
+            let Some(process_id) = row.try_get::<Option<i64>, _>("process_id")?.and_then(|id| processes.get(&id)) else {
+                continue;
+            };
+            let result = sqlx::query(
+                r#"
+                INSERT INTO windows (process_id, title, x, y, width, height, created_at)
+                VALUES (?, ?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(process_id)
+            .bind(row.try_get::<Option<String>, _>("title")?.unwrap_or_default())
+            .bind(row.try_get::<Option<i32>, _>("xpos")?)
+            .bind(row.try_get::<Option<i32>, _>("ypos")?)
+            .bind(row.try_get::<Option<i32>, _>("width")?)
+            .bind(row.try_get::<Option<i32>, _>("height")?)
+            .bind(legacy_timestamp(&row.try_get::<String, _>("created_at")?)?)
+            .execute(&mut *tx)
+            .await?;
+            windows.insert(row.try_get("id")?, result.last_insert_rowid());
+            imported.windows += 1;
+        }
+        drop(rows);
+        
+        progress.start_stage("Importing keystrokes", Some(totals.key_batches));
+        let mut rows = sqlx::query("SELECT window_id, text, nrkeys, created_at FROM keys ORDER BY id").fetch(&legacy);
+        while let Some(row) = rows.try_next().await? {
+            progress.check_cancelled()?;
+            progress.advance(1);
+            let Some(window_id) = row.try_get::<Option<i64>, _>("window_id")?.and_then(|id| windows.get(&id)) else {
+                continue;
+            };
+            let presses = row.try_get::<Option<i64>, _>("nrkeys")?.unwrap_or_default().max(0);
+            // Bytes that aren't UTF-8 are ciphertext after all, and stay behind
+            let typed = match &text {
+                LegacyText::Omit => None,
+                _ => row
+                    .try_get::<Option<Vec<u8>>, _>("text")?
+                    .map(Zeroizing::new)
+                    .filter(|typed| !typed.is_empty() && std::str::from_utf8(typed).is_ok()),
+            };
+            let stored = match (&text, &typed) {
+                (LegacyText::Encrypt(encryptor), Some(typed)) => encryptor.encrypt(typed)?,
+                (LegacyText::Plain, Some(typed)) => typed.to_vec(),
+                _ => Vec::new(),
+            };
+            sqlx::query(
+                r#"
+                INSERT INTO keys (window_id, encrypted_keys, key_count, event_count, count_method, created_at)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#
+            )
+            .bind(window_id)
+            .bind(stored)
+            .bind(presses)
+            .bind(presses)
+            .bind(key_count::COUNT_LEGACY)
+            .bind(legacy_timestamp(&row.try_get::<String, _>("created_at")?)?)
+            .execute(&mut *tx)
+            .await?;
+            imported.key_batches += 1;
+            if typed.is_some() {
+                imported.texts += 1;
+            }
+            imported.keystrokes = imported.keystrokes.saturating_add(presses);
+        }
+        drop(rows);
+        
+        progress.start_stage("Importing clicks", Some(totals.clicks + totals.scrolls));
+        let mut rows = sqlx::query(
+            "SELECT window_id, button, x, y, created_at FROM click WHERE press = 1 AND button BETWEEN 1 AND 7 ORDER BY id"
+        )
+        .fetch(&legacy);
+        while let Some(row) = rows.try_next().await? {
+            progress.check_cancelled()?;
+            progress.advance(1);
+            let Some(window_id) = row.try_get::<Option<i64>, _>("window_id")?.and_then(|id| windows.get(&id)) else {
+                continue;
+            };
+            let created_at = legacy_timestamp(&row.try_get::<String, _>("created_at")?)?;
+            // X11 numbering, which the original used on every platform
+            let button = match row.try_get::<i64, _>("button")? {
+                1 => "left",
+                2 => "middle",
+                3 => "right",
+                wheel => {
+                    let (distance_x, distance_y) = if wheel >= 6 { (1.0, 0.0) } else { (0.0, 1.0) };
+                    sqlx::query(
+                        r#"
+                        INSERT INTO scrolls (window_id, event_count, distance_x, distance_y, created_at)
+                        VALUES (?, 1, ?, ?, ?)
+                        "#
+                    )
+                    .bind(window_id)
+                    .bind(distance_x)
+                    .bind(distance_y)
+                    .bind(&created_at)
+                    .execute(&mut *tx)
+                    .await?;
+                    imported.scrolls += 1;
+                    continue;
+                }
+            };
+            sqlx::query("INSERT INTO clicks (window_id, x, y, button, created_at) VALUES (?, ?, ?, ?, ?)")
+                .bind(window_id)
+                .bind(row.try_get::<Option<i32>, _>("x")?.unwrap_or_default())
+                .bind(row.try_get::<Option<i32>, _>("y")?.unwrap_or_default())
+                .bind(button)
+                .bind(&created_at)
+                .execute(&mut *tx)
+                .await?;
+            imported.clicks += 1;
+        }
+        drop(rows);
+        
+        if let Some(source) = source {
+            sqlx::query("INSERT INTO legacy_imports (source, path, imported_at) VALUES (?, ?, ?)")
+                .bind(source)
+                .bind(path.display().to_string())
+                .bind(format_timestamp(Utc::now()))
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        legacy.close().await;
+        Ok(imported)
+    }
+    
+    // Writes a fixture laid out by `FixtureBuilder`, in one transaction
+    #[cfg(feature = "test-support")]
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_fixture"))]
+    pub async fn insert_fixture(
+        &self,
+        windows: &[crate::fixtures::FixtureWindow],
+        periods: &[(DateTime<Utc>, DateTime<Utc>)],
+    ) -> Result<()> {
+        let _timer = self.timings.start("insert_fixture");
+        let mut tx = self.pool.begin().await?;
+        
+        for window in windows {
+            sqlx::query("INSERT INTO processes (name) VALUES (?) ON CONFLICT(name) DO NOTHING")
+                .bind(&window.app)
+                .execute(&mut *tx)
+                .await?;
+            let process_id: i64 = sqlx::query_scalar("SELECT id FROM processes WHERE name = ?")
+                .bind(&window.app)
+                .fetch_one(&mut *tx)
+                .await?;
+            let window_id = sqlx::query("INSERT INTO windows (process_id, title, created_at) VALUES (?, ?, ?)")
+                .bind(process_id)
+                .bind(&window.app)
+                .bind(format_timestamp(window.opened_at))
+                .execute(&mut *tx)
+                .await?
+                .last_insert_rowid();
+            
+            for (at, count, corrections) in &window.keys {
+                sqlx::query(
+                    r#"
+                    INSERT INTO keys (window_id, encrypted_keys, key_count, event_count, correction_count,
+                                      count_method, created_at)
+                    VALUES (?, ?, ?, ?, ?, ?, ?)
+                    "#
+                )
+                .bind(window_id)
+                .bind(Vec::<u8>::new())
+                .bind(count)
+                .bind(count + corrections)
+                .bind(corrections)
+                .bind(key_count::COUNT_GRAPHEMES)
+                .bind(format_timestamp(*at))
+                .execute(&mut *tx)
+                .await?;
+            }
+            for at in &window.clicks {
+                sqlx::query("INSERT INTO clicks (window_id, x, y, button, created_at) VALUES (?, 0, 0, 'left', ?)")
+                    .bind(window_id)
+                    .bind(format_timestamp(*at))
+                    .execute(&mut *tx)
+                    .await?;
+            }
+        }
+        
+        for (start, end) in periods {
+            sqlx::query("INSERT INTO periods (started_at, ended_at) VALUES (?, ?)")
+                .bind(format_timestamp(*start))
+                .bind(format_timestamp(*end))
+                .execute(&mut *tx)
+                .await?;
+        }
+        
+        tx.commit().await?;
+        Ok(())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "upsert_health_days"))]
+    pub async fn upsert_health_days(&self, days: &[HealthDay]) -> Result<()> {
+        let _timer = self.timings.start("upsert_health_days");
+        let mut tx = self.pool.begin().await?;
+        
+        for day in days {
+            sqlx::query(
+                r#"
+                INSERT INTO health_days (day, steps, sleep_minutes) VALUES (?, ?, ?)
+                ON CONFLICT(day) DO UPDATE SET
+                    steps = COALESCE(excluded.steps, steps),
+                    sleep_minutes = COALESCE(excluded.sleep_minutes, sleep_minutes),
+                    imported_at = CURRENT_TIMESTAMP
+                "#,
+            )
+            .bind(day.day)
+            .bind(day.steps)
+            .bind(day.sleep_minutes)
+            .execute(&mut *tx)
+            .await?;
+        }
+        
+        tx.commit().await?;
+        Ok(())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_health_days"))]
+    pub async fn get_health_days(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<HealthDay>> {
+        let _timer = self.timings.start("get_health_days");
+        let days = sqlx::query_as::<_, HealthDay>(
+            "SELECT day, steps, sleep_minutes FROM health_days WHERE day >= ? AND day < ? ORDER BY day"
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(days)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_deep_work_intervals"))]
+    pub async fn get_deep_work_intervals(&self, day: NaiveDate) -> Result<Vec<DeepWorkInterval>> {
+        let _timer = self.timings.start("get_deep_work_intervals");
+        let intervals = sqlx::query_as::<_, DeepWorkInterval>(
+            "SELECT id, day, started_at, ended_at, process, minutes, switches FROM deep_work \
+             WHERE day = ? ORDER BY started_at"
+        )
+        .bind(day)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(intervals)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_diagnostic"))]
+    pub async fn insert_diagnostic(
+        &self,
+        kind: &str,
+        window_id: Option<i64>,
+        dropped_events: i64,
+        detail: &str,
+    ) -> Result<i64> {
+        let _timer = self.timings.start("insert_diagnostic");
+        let result = sqlx::query(
+            r#"
+            INSERT INTO diagnostics (kind, window_id, dropped_events, detail)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(kind)
+        .bind(window_id)
+        .bind(dropped_events)
+        .bind(detail)
+        .execute(&self.pool)
+        .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    // Records that can't be real input: the part of an hour's keystrokes or
+    // clicks beyond what anyone could produce, and clicks far outside any
+    // virtual desktop. Rows that were already flagged are skipped.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "find_outliers"))]
+    pub async fn find_outliers(&self, limits: &OutlierLimits) -> Result<Vec<Outlier>> {
+        let _timer = self.timings.start("find_outliers");
+        let mut outliers = Vec::new();
+        
+        // Only the excess is flagged, so real typing in the same hour stays:
+        // batches are kept smallest first up to the limit, and the larger ones
+        // past it (a single impossible batch, usually) are the outliers
+        let key_rows = sqlx::query(
+            r#"
+            SELECT id, created_at, total
+            FROM (
+                SELECT id, created_at,
+                       SUM(keystrokes) OVER (PARTITION BY hour) as total,
+                       SUM(keystrokes) OVER (PARTITION BY hour ORDER BY keystrokes, id) as running
+                FROM (
+                    SELECT id, created_at, COALESCE(event_count, key_count) as keystrokes,
+                           strftime('%Y-%m-%d %H', created_at) as hour
+                    FROM keys
+                    WHERE outlier_reason IS NULL
+                )
+            )
+            WHERE running > ?
+            ORDER BY id
+            "#
+        )
+        .bind(limits.max_keys_per_hour)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        for row in key_rows {
+            outliers.push(Outlier {
+                table: "keys".to_string(),
+                id: row.get("id"),
+                created_at: row.get("created_at"),
+                reason: format!(
+                    "{} keystrokes in one hour (limit {})",
+                    row.get::<i64, _>("total"),
+                    limits.max_keys_per_hour
+                ),
+            });
+        }
+        
+        // Clicks past the hour's limit, in the order they came
+        let click_rate_rows = sqlx::query(
+            r#"
+            SELECT id, created_at, total
+            FROM (
+                SELECT id, created_at,
+                       COUNT(*) OVER (PARTITION BY hour) as total,
+                       ROW_NUMBER() OVER (PARTITION BY hour ORDER BY created_at, id) as running
+                FROM (
+                    SELECT id, created_at, strftime('%Y-%m-%d %H', created_at) as hour
+                    FROM clicks
+                    WHERE outlier_reason IS NULL
+                )
+            )
+            WHERE running > ?
+            ORDER BY id
+            "#
+        )
+        .bind(limits.max_clicks_per_hour)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        for row in click_rate_rows {
+            outliers.push(Outlier {
+                table: "clicks".to_string(),
+                id: row.get("id"),
+                created_at: row.get("created_at"),
+                reason: format!(
+                    "{} clicks in one hour (limit {})",
+                    row.get::<i64, _>("total"),
+                    limits.max_clicks_per_hour
+                ),
+            });
+        }
+        
+        // Negative coordinates alone are fine: displays left of or above the
+        // primary one have them. Only values beyond any desktop are flagged.
+        let position_rows = sqlx::query(
+            r#"
+            SELECT id, created_at, x, y
+            FROM clicks
+            WHERE outlier_reason IS NULL
+              AND (ABS(x) > ?1 OR ABS(y) > ?1)
+            ORDER BY id
+            "#
+        )
+        .bind(limits.max_coordinate)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        for row in position_rows {
+            let id: i64 = row.get("id");
+            if outliers.iter().any(|o| o.table == "clicks" && o.id == id) {
+                continue;
+            }
+            outliers.push(Outlier {
+                table: "clicks".to_string(),
+                id,
+                created_at: row.get("created_at"),
+                reason: format!(
+                    "click at ({}, {}) is outside any display",
+                    row.get::<i32, _>("x"),
+                    row.get::<i32, _>("y")
+                ),
+            });
+        }
+        
+        Ok(outliers)
+    }
+    
+    // Mark the records as outliers, keeping them for reference
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "annotate_outliers"))]
+    pub async fn annotate_outliers(&self, outliers: &[Outlier], progress: &Progress) -> Result<u64> {
+        let _timer = self.timings.start("annotate_outliers");
+        let mut tx = self.pool.begin().await?;
+        let mut updated = 0;
+        progress.start_stage("Annotating outliers", Some(outliers.len() as u64));
+        
+        for outlier in outliers {
+            // Returning early rolls back partial work
+            progress.check_cancelled()?;
+            let sql = format!("UPDATE {} SET outlier_reason = ? WHERE id = ?", outlier_table(&outlier.table)?);
+            updated += sqlx::query(&sql)
+                .bind(&outlier.reason)
+                .bind(outlier.id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            progress.advance(1);
+        }
+        
+        tx.commit().await?;
+        Ok(updated)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "remove_outliers"))]
+    pub async fn remove_outliers(&self, outliers: &[Outlier], progress: &Progress) -> Result<u64> {
+        let _timer = self.timings.start("remove_outliers");
+        let mut tx = self.pool.begin().await?;
+        let mut removed = 0;
+        progress.start_stage("Removing outliers", Some(outliers.len() as u64));
+        
+        for outlier in outliers {
+            progress.check_cancelled()?;
+            let sql = format!("DELETE FROM {} WHERE id = ?", outlier_table(&outlier.table)?);
+            removed += sqlx::query(&sql)
+                .bind(outlier.id)
+                .execute(&mut *tx)
+                .await?
+                .rows_affected();
+            progress.advance(1);
+        }
+        
+        tx.commit().await?;
+        Ok(removed)
+    }
+    
+    // None when no key has been derived for this database yet, including
+    // read-only opens of databases from before the table existed
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_key_derivation"))]
+    pub async fn get_key_derivation(&self) -> Result<Option<KeyDerivation>> {
+        let _timer = self.timings.start("get_key_derivation");
+        let exists = sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'key_derivation'")
+            .fetch_optional(&self.pool)
+            .await?
+            .is_some();
+        if !exists {
+            return Ok(None);
+        }
+        
+        let row = sqlx::query(
+            r#"
+            SELECT algorithm, memory_kib, iterations, parallelism, salt, check_value, sealed_key, recovery_key
+            FROM key_derivation
+            WHERE id = 1
+            "#
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+        
+        Ok(row.map(|row| KeyDerivation {
+            algorithm: row.get("algorithm"),
+            settings: KdfSettings {
+                memory_kib: row.get("memory_kib"),
+                iterations: row.get("iterations"),
+                parallelism: row.get("parallelism"),
+            },
+            salt: row.get("salt"),
+            check_value: row.get("check_value"),
+            sealed_key: row.get("sealed_key"),
+            recovery_key: row.get("recovery_key"),
+        }))
+    }
+    
+    // Stores the first key derivation; false when the database already has one
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "init_key_derivation"))]
+    pub async fn init_key_derivation(&self, kdf: &KeyDerivation) -> Result<bool> {
+        let _timer = self.timings.start("init_key_derivation");
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO key_derivation
+                (id, algorithm, memory_kib, iterations, parallelism, salt, check_value, sealed_key, recovery_key)
+            VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO NOTHING
+            "#
+        )
+        .bind(&kdf.algorithm)
+        .bind(kdf.settings.memory_kib)
+        .bind(kdf.settings.iterations)
+        .bind(kdf.settings.parallelism)
+        .bind(&kdf.salt)
+        .bind(&kdf.check_value)
+        .bind(&kdf.sealed_key)
+        .bind(&kdf.recovery_key)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        Ok(inserted > 0)
+    }
+    
+    // Re-encrypts the typed text of every key batch from `old` to `new` and
+    // replaces the stored key derivation with `kdf`, in one transaction, so
+    // an interrupted change leaves the old password working. Batches `old`
+    // can't decrypt (or all of them, without an old key) are left as they are.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "rekey"))]
+    pub async fn rekey(
+        &self,
+        old: Option<&Encryptor>,
+        new: &Encryptor,
+        kdf: &KeyDerivation,
+        progress: &Progress,
+    ) -> Result<Rekey> {
+        let _timer = self.timings.start("rekey");
+        let mut conn = self.pool.acquire().await?;
+        // IMMEDIATE takes the write lock before the first read, so no other
+        // writer can add batches under the old key while this one runs
+        sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
+        let result = rekey_rows(&mut conn, old, new, kdf, progress).await;
+        let end = if result.is_ok() { "COMMIT" } else { "ROLLBACK" };
+        sqlx::query(end).execute(&mut *conn).await?;
+        result
+    }
+    
+    // Deletes events from before local midnight of `cutoff`, first storing
+    // the daily totals of those days in daily_rollups when `keep_rollups` is
+    // set. Sessions, goals, markers and imported health data are kept, as are
+    // windows anything kept still points at. Freed pages are reused by later
+    // writes rather than returned to the filesystem. Rows are deleted in
+    // batches; rollups go in first, so an interrupted prune only leaves rows
+    // the next one deletes.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "prune_before"))]
+    pub async fn prune_before(&self, cutoff: NaiveDate, keep_rollups: bool) -> Result<PruneSummary> {
+        let _timer = self.timings.start("prune_before");
+        let (cutoff_at, _) = local_day_bounds(cutoff);
+        
+        let mut days_rolled_up = 0;
+        if keep_rollups {
+            days_rolled_up = sqlx::query(
+                r#"
+                INSERT OR IGNORE INTO daily_rollups (day, keystrokes, clicks, active_seconds)
+                SELECT day, SUM(keystrokes), SUM(clicks), COUNT(DISTINCT minute) * 60
+                FROM (
+                    SELECT date(created_at, 'localtime') as day,
+                           strftime('%Y-%m-%d %H:%M', created_at) as minute,
+                           COALESCE(event_count, key_count) as keystrokes,
+                           0 as clicks
+                    FROM keys
+                    WHERE created_at < ?1 AND outlier_reason IS NULL
+                    UNION ALL
+                    SELECT date(created_at, 'localtime') as day,
+                           strftime('%Y-%m-%d %H:%M', created_at) as minute,
+                           0 as keystrokes,
+                           1 as clicks
+                    FROM clicks
+                    WHERE created_at < ?1 AND outlier_reason IS NULL
+                )
+                GROUP BY day
+                "#
+            )
+            .bind(&cutoff_at)
+            .execute(&self.pool)
+            .await?
+            .rows_affected();
+        }
+        
+        let mut rows_deleted = 0;
+        for (table, column) in PRUNED_TABLES {
+            let condition = format!("{} < ?", column);
+            rows_deleted += self.delete_in_batches(table, &condition, &cutoff_at).await?;
+        }
+        // Dated by local day rather than by timestamp
+        rows_deleted += self.delete_in_batches("key_ngram_days", "day < ?", &cutoff.to_string()).await?;
+        
+        // The newest window may be the one the monitor is still recording into
+        rows_deleted += self
+            .delete_in_batches(
+                "windows",
+                r#"
+                created_at < ?
+                  AND id < (SELECT MAX(id) FROM windows)
+                  AND NOT EXISTS (SELECT 1 FROM keys WHERE keys.window_id = windows.id)
+                  AND NOT EXISTS (SELECT 1 FROM clicks WHERE clicks.window_id = windows.id)
+                  AND NOT EXISTS (SELECT 1 FROM mouse_moves WHERE mouse_moves.window_id = windows.id)
+                  AND NOT EXISTS (SELECT 1 FROM scrolls WHERE scrolls.window_id = windows.id)
+                  AND NOT EXISTS (SELECT 1 FROM diagnostics WHERE diagnostics.window_id = windows.id)
+                  AND NOT EXISTS (SELECT 1 FROM markers WHERE markers.window_id = windows.id)
+                "#,
+                &cutoff_at,
+            )
+            .await?;
+        
+        Ok(PruneSummary {
+            cutoff,
+            rows_deleted,
+            days_rolled_up,
+        })
+    }
+    
+    // Deletes the rows of `table` matching `condition`, which takes one bound
+    // value, PRUNE_BATCH_ROWS at a time
+    async fn delete_in_batches(&self, table: &str, condition: &str, value: &str) -> Result<u64> {
+        let sql = format!(
+            "DELETE FROM {table} WHERE rowid IN (SELECT rowid FROM {table} WHERE {condition} LIMIT {PRUNE_BATCH_ROWS})"
+        );
+        let mut deleted = 0;
+        loop {
+            let batch = sqlx::query(&sql).bind(value).execute(&self.pool).await?.rows_affected();
+            deleted += batch;
+            if batch < PRUNE_BATCH_ROWS as u64 {
+                return Ok(deleted);
+            }
+        }
+    }
+    
+    // Row count and content checksum per table and local day, for checking
+    // that a backup, sync or merge copied everything. Tables an older schema
+    // lacks are skipped.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_table_days"))]
+    pub async fn get_table_days(&self) -> Result<Vec<TableDay>> {
+        let _timer = self.timings.start("get_table_days");
+        let existing: Vec<String> = sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table'")
+            .fetch_all(&self.pool)
+            .await?;
+        let mut days: BTreeMap<(&str, String), (i64, u64)> = BTreeMap::new();
+        
+        for (table, day, contents) in CHECKSUMMED_TABLES {
+            if !existing.iter().any(|name| name == table) {
+                continue;
+            }
+            let sql = format!("SELECT {day} as day, {contents} as contents FROM {table}");
+            let mut rows = sqlx::query(&sql).fetch(&self.pool);
+            while let Some(row) = rows.try_next().await? {
+                let Some(day) = row.get::<Option<String>, _>("day") else {
+                    continue;
+                };
+                let contents: Option<String> = row.get("contents");
+                let entry = days.entry((table, day)).or_default();
+                entry.0 += 1;
+                entry.1 = entry.1.wrapping_add(fnv1a(contents.unwrap_or_default().as_bytes()));
+            }
+        }
+        
+        let mut result = Vec::with_capacity(days.len());
+        for ((table, day), (rows, checksum)) in days {
+            result.push(TableDay {
+                table: table.to_string(),
+                day: NaiveDate::parse_from_str(&day, "%Y-%m-%d")?,
+                rows,
+                checksum,
+            });
+        }
+        
+        Ok(result)
+    }
+    
+    // Totals of pruned days in [start, end), see `prune_before`
+    async fn get_daily_rollups(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<DailySummary>> {
+        let rows = sqlx::query(
+            "SELECT day, keystrokes, clicks, active_seconds FROM daily_rollups WHERE day >= ? AND day < ? ORDER BY day"
+        )
+        .bind(start.to_string())
+        .bind(end.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut rollups = Vec::with_capacity(rows.len());
+        for row in rows {
+            let day: String = row.get("day");
+            rollups.push(DailySummary {
+                date: NaiveDate::parse_from_str(&day, "%Y-%m-%d")?,
+                active_seconds: row.get("active_seconds"),
+                keystrokes: row.get("keystrokes"),
+                clicks: row.get("clicks"),
+            });
+        }
+        
+        Ok(rollups)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "open_tagged_period"))]
+    pub async fn open_tagged_period(&self, kind: &str) -> Result<i64> {
+        let _timer = self.timings.start("open_tagged_period");
+        let result = sqlx::query("INSERT INTO tagged_periods (kind) VALUES (?)")
+            .bind(kind)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "close_tagged_period"))]
+    pub async fn close_tagged_period(&self, period_id: i64) -> Result<()> {
+        let _timer = self.timings.start("close_tagged_period");
+        sqlx::query("UPDATE tagged_periods SET ended_at = CURRENT_TIMESTAMP WHERE id = ? AND ended_at IS NULL")
+            .bind(period_id)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    // Starts an activity period at `at`; see the periods migration
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "open_period"))]
+    pub async fn open_period(&self, at: DateTime<Utc>) -> Result<i64> {
+        let _timer = self.timings.start("open_period");
+        let at = format_timestamp(at);
+        let result = sqlx::query("INSERT INTO periods (started_at, ended_at) VALUES (?, ?)")
+            .bind(&at)
+            .bind(&at)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    // Moves the end of an activity period to the latest input
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "extend_period"))]
+    pub async fn extend_period(&self, period_id: i64, ended_at: DateTime<Utc>) -> Result<()> {
+        let _timer = self.timings.start("extend_period");
+        sqlx::query("UPDATE periods SET ended_at = ? WHERE id = ?")
+            .bind(format_timestamp(ended_at))
+            .bind(period_id)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(())
+    }
+    
+    // The end of the latest activity period: the last input the monitor
+    // recorded, None before any
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_last_activity"))]
+    pub async fn get_last_activity(&self) -> Result<Option<DateTime<Utc>>> {
+        let _timer = self.timings.start("get_last_activity");
+        let last: Option<String> = sqlx::query_scalar("SELECT MAX(ended_at) FROM periods")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(last
+            .map(|at| NaiveDateTime::parse_from_str(&at, "%Y-%m-%d %H:%M:%S"))
+            .transpose()?
+            .map(|at| at.and_utc()))
+    }
+    
+    // Ends the media plays, tagged periods and meeting a monitor that didn't
+    // shut down cleanly left open, at `at` or their start if that is later;
+    // returns how many were closed
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "close_interrupted_spans"))]
+    pub async fn close_interrupted_spans(&self, at: DateTime<Utc>) -> Result<u64> {
+        let _timer = self.timings.start("close_interrupted_spans");
+        let at = format_timestamp(at);
+        let mut closed = 0;
+        for table in ["media_plays", "tagged_periods"] {
+            let sql = format!("UPDATE {} SET ended_at = MAX(started_at, ?) WHERE ended_at IS NULL", table);
+            closed += sqlx::query(&sql).bind(&at).execute(&self.pool).await?.rows_affected();
+        }
+        // Meetings are a pair of system events; the last one started and never ended
+        closed += sqlx::query(
+            r#"
+            INSERT INTO system_events (kind, occurred_at)
+            SELECT 'meeting_end', MAX(occurred_at, ?)
+            FROM (
+                SELECT kind, occurred_at FROM system_events
+                WHERE kind IN ('meeting_start', 'meeting_end')
+                ORDER BY occurred_at DESC, id DESC
+                LIMIT 1
+            )
+            WHERE kind = 'meeting_start'
+            "#
+        )
+        .bind(&at)
+        .execute(&self.pool)
+        .await?
+        .rows_affected();
+        
+        Ok(closed)
+    }
+    
+    // Seconds of activity periods falling inside [range.start, range.end)
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_active_seconds"))]
+    pub async fn get_active_seconds(&self, range: Range<DateTime<Utc>>) -> Result<i64> {
+        let _timer = self.timings.start("get_active_seconds");
+        self.get_active_seconds_between(&format_timestamp(range.start), &format_timestamp(range.end))
+            .await
+    }
+    
+    async fn get_active_seconds_between(&self, start: &str, end: &str) -> Result<i64> {
+        let seconds: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE(SUM(MAX(0, strftime('%s', MIN(ended_at, ?2)) - strftime('%s', MAX(started_at, ?1)))), 0)
+            FROM periods
+            WHERE started_at < ?2 AND ended_at > ?1
+            "#
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_one(&self.pool)
+        .await?;
+        
+        Ok(seconds)
+    }
+    
+    // Seconds of activity per app in the range: activity periods split by
+    // the window in focus, each focused until the next one opened (the last
+    // until the end of the range). Unlike `get_process_stats`, visits aren't
+    // capped, and the window still open counts.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_app_active_seconds"))]
+    pub async fn get_app_active_seconds(&self, range: Range<DateTime<Utc>>) -> Result<HashMap<String, i64>> {
+        let _timer = self.timings.start("get_app_active_seconds");
+        let (start, end) = (format_timestamp(range.start), format_timestamp(range.end));
+        
+        // The window in focus at the start, then the ones opened during the range
+        let focus: Vec<(i64, String)> = sqlx::query(
+            r#"
+            SELECT * FROM (
+                SELECT CAST(strftime('%s', w.created_at) AS INTEGER) as at, w.id as id, p.name as process
+                FROM windows w
+                JOIN processes p ON p.id = w.process_id
+                WHERE w.created_at < ?1
+                ORDER BY w.created_at DESC, w.id DESC LIMIT 1
+            )
+            UNION ALL
+            SELECT CAST(strftime('%s', w.created_at) AS INTEGER), w.id, p.name
+            FROM windows w
+            JOIN processes p ON p.id = w.process_id
+            WHERE w.created_at >= ?1 AND w.created_at < ?2
+            ORDER BY at, id
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.get("at"), row.get("process")))
+        .collect();
+        
+        let periods: Vec<(i64, i64)> = sqlx::query(
+            r#"
+            SELECT CAST(strftime('%s', MAX(started_at, ?1)) AS INTEGER) as started,
+                   CAST(strftime('%s', MIN(ended_at, ?2)) AS INTEGER) as ended
+            FROM periods
+            WHERE started_at < ?2 AND ended_at > ?1
+            ORDER BY started_at
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.get("started"), row.get("ended")))
+        .collect();
+        
+        // Periods don't overlap, so both lists are in time order and each
+        // focus interval only looks at the periods from the first one not
+        // over yet
+        let mut totals: HashMap<String, i64> = HashMap::new();
+        let mut first = 0;
+        for (i, (at, process)) in focus.iter().enumerate() {
+            let until = focus.get(i + 1).map_or(range.end.timestamp(), |(next, _)| *next);
+            while first < periods.len() && periods[first].1 <= *at {
+                first += 1;
+            }
+            for (started, ended) in periods[first..].iter().take_while(|(started, _)| *started < until) {
+                let overlap = (*ended).min(until) - (*started).max(*at);
+                if overlap > 0 {
+                    *totals.entry(process.clone()).or_default() += overlap;
+                }
+            }
+        }
+        Ok(totals)
+    }
+    
+    // The local day `rule` last fired on, see alerts.rs
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_alert_fired_day"))]
+    pub async fn get_alert_fired_day(&self, rule: &str) -> Result<Option<NaiveDate>> {
+        let _timer = self.timings.start("get_alert_fired_day");
+        Ok(sqlx::query_scalar("SELECT day FROM alerts_fired WHERE rule = ?")
+            .bind(rule)
+            .fetch_optional(&self.pool)
+            .await?)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "record_alert_fired"))]
+    pub async fn record_alert_fired(&self, rule: &str, day: NaiveDate) -> Result<()> {
+        let _timer = self.timings.start("record_alert_fired");
+        sqlx::query(
+            "INSERT INTO alerts_fired (rule, day) VALUES (?, ?) ON CONFLICT(rule) DO UPDATE SET day = excluded.day"
+        )
+        .bind(rule)
+        .bind(day)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_power_sample"))]
+    pub async fn insert_power_sample(&self, on_battery: bool, percent: Option<f64>) -> Result<i64> {
+        let _timer = self.timings.start("insert_power_sample");
+        let result = sqlx::query("INSERT INTO power_samples (on_battery, percent) VALUES (?, ?)")
+            .bind(on_battery)
+            .bind(percent)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    // Samples in the range, preceded by the last one before it so the power
+    // source at the start of the range is known
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_power_samples"))]
+    pub async fn get_power_samples(&self, range: Range<DateTime<Utc>>) -> Result<Vec<PowerSample>> {
+        let _timer = self.timings.start("get_power_samples");
+        let samples = sqlx::query_as::<_, PowerSample>(
+            "SELECT * FROM ( \
+                 SELECT id, on_battery, percent, recorded_at FROM power_samples \
+                 WHERE recorded_at < ? ORDER BY recorded_at DESC, id DESC LIMIT 1 \
+             ) UNION ALL \
+             SELECT id, on_battery, percent, recorded_at FROM power_samples \
+             WHERE recorded_at >= ? AND recorded_at < ? \
+             ORDER BY recorded_at, id"
+        )
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.end))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(samples)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_input_device"))]
+    pub async fn insert_input_device(&self, name: &str, kind: &str, built_in: bool) -> Result<i64> {
+        let _timer = self.timings.start("insert_input_device");
+        let result = sqlx::query("INSERT OR IGNORE INTO input_devices (name, kind, built_in) VALUES (?, ?, ?)")
+            .bind(name)
+            .bind(kind)
+            .bind(built_in)
+            .execute(&self.pool)
+            .await?;
+        
+        if result.rows_affected() == 0 {
+            let row = sqlx::query("SELECT id FROM input_devices WHERE name = ? AND kind = ? AND built_in = ?")
+                .bind(name)
+                .bind(kind)
+                .bind(built_in)
+                .fetch_one(&self.pool)
+                .await?;
+            Ok(row.get::<i64, _>("id"))
+        } else {
+            Ok(result.last_insert_rowid())
+        }
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_device_input"))]
+    pub async fn insert_device_input(&self, device_id: i64, keys: i64, clicks: i64, scrolls: i64) -> Result<i64> {
+        let _timer = self.timings.start("insert_device_input");
+        self.write_now(PendingWrite::DeviceInput { device_id, keys, clicks, scrolls, at: Utc::now() })
+            .await
+    }
+    
+    // Totals per device in the range, busiest first
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_device_usage"))]
+    pub async fn get_device_usage(&self, range: Range<DateTime<Utc>>) -> Result<Vec<DeviceUsage>> {
+        let _timer = self.timings.start("get_device_usage");
+        let usage = sqlx::query_as::<_, DeviceUsage>(
+            "SELECT d.name, d.kind, d.built_in, \
+                    SUM(i.keys) AS keys, SUM(i.clicks) AS clicks, SUM(i.scrolls) AS scrolls \
+             FROM device_input i JOIN input_devices d ON d.id = i.device_id \
+             WHERE i.created_at >= ? AND i.created_at < ? \
+             GROUP BY d.id \
+             ORDER BY SUM(i.keys) + SUM(i.clicks) + SUM(i.scrolls) DESC"
+        )
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.end))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(usage)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_system_event"))]
+    pub async fn insert_system_event(&self, kind: &str, occurred_at: DateTime<Utc>) -> Result<i64> {
+        let _timer = self.timings.start("insert_system_event");
+        let result = sqlx::query("INSERT INTO system_events (kind, occurred_at) VALUES (?, ?)")
+            .bind(kind)
+            .bind(format_timestamp(occurred_at))
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_system_events"))]
+    pub async fn get_system_events(&self, range: Range<DateTime<Utc>>) -> Result<Vec<SystemEvent>> {
+        let _timer = self.timings.start("get_system_events");
+        let events = sqlx::query_as::<_, SystemEvent>(
+            "SELECT id, kind, occurred_at FROM system_events \
+             WHERE occurred_at >= ? AND occurred_at < ? ORDER BY occurred_at, id"
+        )
+        .bind(format_timestamp(range.start))
+        .bind(format_timestamp(range.end))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(events)
+    }
+    
+    // Periods of one kind overlapping the range; open periods have no end
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_tagged_periods"))]
+    pub async fn get_tagged_periods(&self, kind: &str, range: Range<DateTime<Utc>>) -> Result<Vec<TaggedPeriod>> {
+        let _timer = self.timings.start("get_tagged_periods");
+        let periods = sqlx::query_as::<_, TaggedPeriod>(
+            "SELECT id, kind, started_at, ended_at FROM tagged_periods \
+             WHERE kind = ? AND started_at < ? AND (ended_at IS NULL OR ended_at >= ?) ORDER BY started_at"
+        )
+        .bind(kind)
+        .bind(format_timestamp(range.end))
+        .bind(format_timestamp(range.start))
+        .fetch_all(&self.pool)
+        .await?;
+        
+        Ok(periods)
+    }
+    
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "insert_marker"))]
+    pub async fn insert_marker(&self, window_id: Option<i64>, note: Option<&str>) -> Result<i64> {
+        let _timer = self.timings.start("insert_marker");
+        let result = sqlx::query("INSERT INTO markers (window_id, note) VALUES (?, ?)")
+            .bind(window_id)
+            .bind(note)
+            .execute(&self.pool)
+            .await?;
+        
+        Ok(result.last_insert_rowid())
+    }
+    
+    // Totals for a single local calendar day. Active time counts every minute
+    // that saw at least one keystroke batch or click.
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_daily_summary"))]
+    pub async fn get_daily_summary(&self, date: NaiveDate) -> Result<DailySummary> {
+        let _timer = self.timings.start("get_daily_summary");
+        let (start, end) = local_day_bounds(date);
+        
+        let keystrokes_row = sqlx::query(
+            "SELECT COALESCE(SUM(COALESCE(event_count, key_count)), 0) as total FROM keys \
+             WHERE created_at >= ? AND created_at < ? AND outlier_reason IS NULL"
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_one(&self.pool)
+        .await?;
+        let keystrokes = keystrokes_row.get::<i64, _>("total");
+        
+        let clicks_row = sqlx::query(
+            "SELECT COUNT(*) as total FROM clicks WHERE created_at >= ? AND created_at < ? AND outlier_reason IS NULL"
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_one(&self.pool)
+        .await?;
+        let clicks = clicks_row.get::<i64, _>("total");
+        
+        let active_row = sqlx::query(
+            r#"
+            SELECT COUNT(DISTINCT strftime('%Y-%m-%d %H:%M', created_at)) as minutes
+            FROM (
+                SELECT created_at FROM keys
+                WHERE created_at >= ?1 AND created_at < ?2 AND outlier_reason IS NULL
+                UNION ALL
+                SELECT created_at FROM clicks
+                WHERE created_at >= ?1 AND created_at < ?2 AND outlier_reason IS NULL
+            )
+            "#
+        )
+        .bind(&start)
+        .bind(&end)
+        .fetch_one(&self.pool)
+        .await?;
+        let active_minutes = active_row.get::<i64, _>("minutes");
+        
+        // Days that were pruned only have their totals left
+        if active_minutes == 0 {
+            let next = date.succ_opt().unwrap_or(date);
+            if let Some(rollup) = self.get_daily_rollups(date, next).await?.pop() {
+                return Ok(rollup);
+            }
+        }
+        
+        Ok(DailySummary {
+            date,
+            active_seconds: active_minutes * 60,
+            keystrokes,
+            clicks,
+        })
+    }
+    
+    // One summary per local calendar day in [start, end) that has any activity
+    #[instrument(name = "db", level = "debug", skip_all, fields(query = "get_daily_summaries"))]
+    pub async fn get_daily_summaries(&self, start: NaiveDate, end: NaiveDate) -> Result<Vec<DailySummary>> {
+        let _timer = self.timings.start("get_daily_summaries");
+        let (range_start, _) = local_day_bounds(start);
+        let (range_end, _) = local_day_bounds(end);
+        
+        let rows = sqlx::query(
+            r#"
+            SELECT day,
+                   COALESCE(SUM(keystrokes), 0) as keystrokes,
+                   COALESCE(SUM(clicks), 0) as clicks,
+                   COUNT(DISTINCT minute) as active_minutes
+            FROM (
+                SELECT date(created_at, 'localtime') as day,
+                       strftime('%Y-%m-%d %H:%M', created_at) as minute,
+                       COALESCE(event_count, key_count) as keystrokes,
+                       0 as clicks
+                FROM keys
+                WHERE created_at >= ?1 AND created_at < ?2 AND outlier_reason IS NULL
+                UNION ALL
+                SELECT date(created_at, 'localtime') as day,
+                       strftime('%Y-%m-%d %H:%M', created_at) as minute,
+                       0 as keystrokes,
+                       1 as clicks
+                FROM clicks
+                WHERE created_at >= ?1 AND created_at < ?2 AND outlier_reason IS NULL
+            )
+            GROUP BY day
+            ORDER BY day
+            "#
+        )
+        .bind(&range_start)
+        .bind(&range_end)
+        .fetch_all(&self.pool)
+        .await?;
+        
+        let mut summaries = Vec::with_capacity(rows.len());
+        for row in rows {
+            let day: String = row.get("day");
+            summaries.push(DailySummary {
+                date: NaiveDate::parse_from_str(&day, "%Y-%m-%d")?,
+                active_seconds: row.get::<i64, _>("active_minutes") * 60,
+                keystrokes: row.get("keystrokes"),
+                clicks: row.get("clicks"),
+            });
+        }
+        
+        // Days that were pruned only have their totals left
+        let rollups = self.get_daily_rollups(start, end).await?;
+        if !rollups.is_empty() {
+            for rollup in rollups {
+                if !summaries.iter().any(|summary| summary.date == rollup.date) {
+                    summaries.push(rollup);
+                }
+            }
+            summaries.sort_by_key(|summary| summary.date);
+        }
+        
+        Ok(summaries)
+    }
+}
+
+// Re-encrypts typed text from `old` to `new` and stores the new key
+// derivation, inside the transaction `Database::rekey` opened; text `old`
+// can't decrypt is skipped
+async fn rekey_rows(
+    conn: &mut SqliteConnection,
+    old: Option<&Encryptor>,
+    new: &Encryptor,
+    kdf: &KeyDerivation,
+    progress: &Progress,
+) -> Result<Rekey> {
+    let mut summary = Rekey::default();
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM keys WHERE length(encrypted_keys) > 0")
+        .fetch_one(&mut *conn)
+        .await?;
+    progress.start_stage("Re-encrypting typed text", Some(total as u64));
+    
+    let mut last_id = 0i64;
+    loop {
+        let rows = sqlx::query(
+            "SELECT id, encrypted_keys FROM keys WHERE id > ? AND length(encrypted_keys) > 0 ORDER BY id LIMIT 1000"
+        )
+        .bind(last_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        if rows.is_empty() {
+            break;
+        }
+        
+        for row in rows {
+            // Returning early rolls back partial work
+            progress.check_cancelled()?;
+            last_id = row.get("id");
+            let data: Vec<u8> = row.get("encrypted_keys");
+            progress.advance(1);
+            let Some(plaintext) = old.and_then(|old| old.decrypt(&data).ok()) else {
+                summary.skipped += 1;
+                continue;
+            };
+            sqlx::query("UPDATE keys SET encrypted_keys = ? WHERE id = ?")
+                .bind(new.encrypt(&plaintext)?)
+                .bind(last_id)
+                .execute(&mut *conn)
+                .await?;
+            summary.reencrypted += 1;
+        }
+    }
+    
+    sqlx::query(
+        r#"
+        INSERT OR REPLACE INTO key_derivation
+            (id, algorithm, memory_kib, iterations, parallelism, salt, check_value, sealed_key, recovery_key)
+        VALUES (1, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#
+    )
+    .bind(&kdf.algorithm)
+    .bind(kdf.settings.memory_kib)
+    .bind(kdf.settings.iterations)
+    .bind(kdf.settings.parallelism)
+    .bind(&kdf.salt)
+    .bind(&kdf.check_value)
+    .bind(&kdf.sealed_key)
+    .bind(&kdf.recovery_key)
+    .execute(&mut *conn)
+    .await?;
+    
+    Ok(summary)
+}
+
+// SQLite stores CURRENT_TIMESTAMP as UTC text, so local day boundaries are
+// converted before comparing.
+fn local_day_bounds(date: NaiveDate) -> (String, String) {
+    let next = date.succ_opt().unwrap_or(date);
+    (format_timestamp(local_midnight(date)), format_timestamp(local_midnight(next)))
+}
+
+// Start of a local day: the earlier instant when midnight repeats, and the
+// same wall time read as UTC when a DST change skips it
+pub fn local_midnight(day: NaiveDate) -> DateTime<Utc> {
+    let midnight = day.and_hms_opt(0, 0, 0).unwrap_or_default();
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&midnight))
+        .with_timezone(&Utc)
+}
+
+// SQL condition limiting `alias.created_at` to the filter, with the values
+// to bind in order. Always valid SQL, so it can be ANDed unconditionally.
+fn filter_condition(alias: &str, filter: &StatsFilter) -> (String, Vec<String>) {
+    let mut conditions = vec!["1 = 1".to_string()];
+    let mut binds = Vec::new();
+    
+    if let Some(label) = &filter.session_label {
+        conditions.push(format!(
+            "EXISTS (SELECT 1 FROM sessions s WHERE s.label = ? AND {alias}.created_at >= s.started_at \
+             AND {alias}.created_at < COALESCE(s.ended_at, '9999-12-31'))"
+        ));
+        binds.push(label.clone());
+    }
+    if let Some(start) = filter.start {
+        conditions.push(format!("{alias}.created_at >= ?"));
+        binds.push(format_timestamp(start));
+    }
+    if let Some(end) = filter.end {
+        conditions.push(format!("{alias}.created_at < ?"));
+        binds.push(format_timestamp(end));
+    }
+    
+    (conditions.join(" AND "), binds)
+}
+
+fn bind_all<'q>(
+    mut query: sqlx::query::Query<'q, Sqlite, sqlx::sqlite::SqliteArguments<'q>>,
+    binds: &'q [String],
+) -> sqlx::query::Query<'q, Sqlite, sqlx::sqlite::SqliteArguments<'q>> {
+    for value in binds {
+        query = query.bind(value);
+    }
+    query
+}
+
+// Table names can't be bound as parameters, so only known ones are accepted
+fn outlier_table(table: &str) -> Result<&'static str> {
+    match table {
+        "keys" => Ok("keys"),
+        "clicks" => Ok("clicks"),
+        other => Err(SelfspyError::InvalidData(format!("Unknown outlier table: {}", other))),
+    }
+}
+
+// Fresh while younger than SNAPSHOT_MAX_AGE, or when nothing was written to
+// the database (or its WAL) since it was taken
+fn snapshot_is_fresh(database: &Path, snapshot: &Path) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok();
+    let Some(taken) = modified(snapshot) else {
+        return false;
+    };
+    
+    if taken.elapsed().is_ok_and(|age| age < SNAPSHOT_MAX_AGE) {
+        return true;
+    }
+    [database.to_path_buf(), database.with_extension("db-wal")]
+        .iter()
+        .filter_map(|path| modified(path))
+        .all(|written| written <= taken)
+}
+
+// Copies into a private file first and renames it over the snapshot, so
+// readers holding the previous snapshot open keep a consistent file
+async fn refresh_snapshot(database: &Path, snapshot: &Path) -> Result<()> {
+    static NEXT: std::sync::atomic::AtomicU32 = std::sync::atomic::AtomicU32::new(0);
+    let partial = snapshot.with_extension(format!(
+        "snapshot.{}-{}.partial",
+        std::process::id(),
+        NEXT.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+    ));
+    
+    let (source, target) = (database.to_path_buf(), partial.clone());
+    tokio::task::spawn_blocking(move || crate::backup::copy_database(&source, &target))
+        .await
+        .map_err(|e| SelfspyError::Io(std::io::Error::other(e.to_string())))??;
+    
+    // Windows refuses to replace a file another reader has open; the older
+    // snapshot then serves until the next refresh
+    if std::fs::rename(&partial, snapshot).is_err() {
+        let _ = std::fs::remove_file(&partial);
+        if !snapshot.exists() {
+            return Err(SelfspyError::Io(std::io::Error::other(format!(
+                "could not create snapshot {}",
+                snapshot.display()
+            ))));
+        }
+    }
+    Ok(())
+}
+
+fn format_timestamp(ts: DateTime<Utc>) -> String {
+    ts.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+// Written beside an original selfspy database recorded with a password
+const LEGACY_PASSWORD_DIGEST: &str = "password.digest";
+
+// What to do with the typed text of a legacy database recorded without a
+// password, see Database::import_legacy
+pub enum LegacyText<'a> {
+    Omit,
+    // Stored as is, for databases without encryption
+    Plain,
+    Encrypt(&'a Encryptor),
+}
+
+// Hash of a legacy database's first window, which stays put as it grows;
+// None when it has no windows
+async fn legacy_source(legacy: &SqlitePool) -> Result<Option<String>> {
+    let Some(row) = sqlx::query("SELECT created_at, title FROM window ORDER BY id LIMIT 1")
+        .fetch_optional(legacy)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let mut hasher = Sha256::new();
+    hasher.update(row.try_get::<Option<String>, _>("created_at")?.unwrap_or_default());
+    hasher.update([0]);
+    hasher.update(row.try_get::<Option<String>, _>("title")?.unwrap_or_default());
+    Ok(Some(hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()))
+}
+
+// The original selfspy stored naive local time with microseconds
+fn legacy_timestamp(value: &str) -> Result<String> {
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S%.f")?;
+    let local = Local
+        .from_local_datetime(&naive)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&naive));
+    Ok(format_timestamp(local.with_timezone(&Utc)))
 }
\ No newline at end of file
@@ -0,0 +1,98 @@
+// Deep-work detection: long stretches of continuous input that stay on one
+// app with few window switches.
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::{DeepWorkInterval, MinuteActivity};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeepWorkSettings {
+    // Shortest stretch that counts as deep work
+    pub min_minutes: i64,
+    // Minutes without input that still count as continuous
+    pub max_idle_gap_minutes: i64,
+    pub max_switches_per_hour: f64,
+    // Fraction of active minutes that must belong to the main app
+    pub min_focus_share: f64,
+}
+
+impl Default for DeepWorkSettings {
+    fn default() -> Self {
+        Self {
+            min_minutes: 25,
+            max_idle_gap_minutes: 3,
+            max_switches_per_hour: 6.0,
+            min_focus_share: 0.8,
+        }
+    }
+}
+
+// `activity` must be sorted by minute; `switches` are the local times of
+// window changes on that day
+pub fn detect(
+    day: NaiveDate,
+    activity: &[MinuteActivity],
+    switches: &[NaiveDateTime],
+    settings: &DeepWorkSettings,
+) -> Vec<DeepWorkInterval> {
+    let mut intervals = Vec::new();
+    let mut segment: Vec<&MinuteActivity> = Vec::new();
+    
+    for minute in activity {
+        let continues = segment.last().is_none_or(|last| {
+            minute.minute - last.minute <= Duration::minutes(settings.max_idle_gap_minutes + 1)
+        });
+        if !continues {
+            intervals.extend(evaluate_segment(day, &segment, switches, settings));
+            segment.clear();
+        }
+        segment.push(minute);
+    }
+    intervals.extend(evaluate_segment(day, &segment, switches, settings));
+    
+    intervals
+}
+
+fn evaluate_segment(
+    day: NaiveDate,
+    segment: &[&MinuteActivity],
+    switches: &[NaiveDateTime],
+    settings: &DeepWorkSettings,
+) -> Option<DeepWorkInterval> {
+    let (first, last) = (segment.first()?, segment.last()?);
+    let started_at = first.minute;
+    let ended_at = last.minute + Duration::minutes(1);
+    let span = (ended_at - started_at).num_minutes();
+    if span < settings.min_minutes {
+        return None;
+    }
+    
+    let mut per_process: HashMap<&str, i64> = HashMap::new();
+    for minute in segment {
+        *per_process.entry(minute.process.as_str()).or_default() += 1;
+    }
+    let (process, focused) = per_process.into_iter().max_by_key(|(_, count)| *count)?;
+    if (focused as f64) < segment.len() as f64 * settings.min_focus_share {
+        return None;
+    }
+    
+    let switch_count = switches
+        .iter()
+        .filter(|at| **at >= started_at && **at < ended_at)
+        .count() as i64;
+    if switch_count as f64 / (span as f64 / 60.0) > settings.max_switches_per_hour {
+        return None;
+    }
+    
+    Some(DeepWorkInterval {
+        id: 0,
+        day,
+        started_at,
+        ended_at,
+        process: process.to_string(),
+        minutes: span,
+        switches: switch_count,
+    })
+}
\ No newline at end of file
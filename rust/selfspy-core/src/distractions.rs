@@ -0,0 +1,153 @@
+// Distraction triggers: the window transitions that most often lead into a
+// long run of distracting windows (a Slack -> YouTube spiral, for instance).
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::WindowVisit;
+
+// Time attributed to a single window is capped so idle gaps don't inflate sessions
+pub const MAX_VISIT_MINUTES: i64 = 15;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DistractionSettings {
+    // Apps that are distracting whatever the window shows (matched case-insensitively)
+    pub apps: Vec<String>,
+    // Window title fragments that mark a distracting page, e.g. in a browser
+    pub title_keywords: Vec<String>,
+    // Shortest distracting run that counts as a session
+    pub min_minutes: i64,
+}
+
+impl Default for DistractionSettings {
+    fn default() -> Self {
+        Self {
+            apps: vec!["Discord".to_string(), "Steam".to_string()],
+            title_keywords: vec![
+                "YouTube".to_string(),
+                "Reddit".to_string(),
+                "Twitter".to_string(),
+                "Netflix".to_string(),
+                "Twitch".to_string(),
+                "Facebook".to_string(),
+                "Instagram".to_string(),
+                "TikTok".to_string(),
+            ],
+            min_minutes: 10,
+        }
+    }
+}
+
+impl DistractionSettings {
+    // Short label for a distracting window (the matched keyword or app), or None
+    pub fn classify(&self, process: &str, title: &str) -> Option<String> {
+        if let Some(app) = self.apps.iter().find(|app| app.eq_ignore_ascii_case(process)) {
+            return Some(app.clone());
+        }
+        
+        let title = title.to_lowercase();
+        self.title_keywords
+            .iter()
+            .find(|keyword| title.contains(&keyword.to_lowercase()))
+            .cloned()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GatewayTransition {
+    // The app left behind and the distraction it led to
+    pub from: String,
+    pub to: String,
+    // Long distracting sessions that started with this transition
+    pub sessions: usize,
+    pub session_minutes: i64,
+    // Every time the transition happened, long session or not
+    pub occurrences: usize,
+}
+
+impl GatewayTransition {
+    // Percentage of occurrences that turned into a long session
+    pub fn spiral_rate(&self) -> f64 {
+        if self.occurrences == 0 {
+            0.0
+        } else {
+            self.sessions as f64 / self.occurrences as f64 * 100.0
+        }
+    }
+}
+
+// `visits` must be sorted by time. Returns transitions ranked by how many long
+// sessions they started, then by the time those sessions took.
+pub fn gateway_transitions(visits: &[WindowVisit], settings: &DistractionSettings) -> Vec<GatewayTransition> {
+    let labels: Vec<Option<String>> = visits
+        .iter()
+        .map(|visit| settings.classify(&visit.process, &visit.title))
+        .collect();
+    let mut transitions: HashMap<(String, String), GatewayTransition> = HashMap::new();
+    
+    let mut i = 1;
+    while i < visits.len() {
+        let (Some(to), None) = (&labels[i], &labels[i - 1]) else {
+            i += 1;
+            continue;
+        };
+        // A new day starts fresh rather than continuing from last night's window
+        if visits[i - 1].at.date() != visits[i].at.date() {
+            i += 1;
+            continue;
+        }
+        let from = &visits[i - 1].process;
+        
+        // The session runs until the next non-distracting window
+        let mut end = i;
+        let mut seconds = 0;
+        while end < visits.len() && labels[end].is_some() {
+            seconds += visit_seconds(visits, end);
+            end += 1;
+        }
+        let minutes = seconds / 60;
+        
+        let transition = transitions
+            .entry((from.clone(), to.clone()))
+            .or_insert_with(|| GatewayTransition {
+                from: from.clone(),
+                to: to.clone(),
+                sessions: 0,
+                session_minutes: 0,
+                occurrences: 0,
+            });
+        transition.occurrences += 1;
+        if minutes >= settings.min_minutes {
+            transition.sessions += 1;
+            transition.session_minutes += minutes;
+        }
+        
+        i = end;
+    }
+    
+    let mut ranked: Vec<GatewayTransition> = transitions
+        .into_values()
+        .filter(|transition| transition.sessions > 0)
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.sessions
+            .cmp(&a.sessions)
+            .then(b.session_minutes.cmp(&a.session_minutes))
+            .then_with(|| (&a.from, &a.to).cmp(&(&b.from, &b.to)))
+    });
+    ranked
+}
+
+// Seconds until the next window appeared, capped and limited to the same day
+pub fn visit_seconds(visits: &[WindowVisit], index: usize) -> i64 {
+    let Some(next) = visits.get(index + 1) else {
+        return 0;
+    };
+    let current = visits[index].at;
+    if next.at.date() != current.date() {
+        return 0;
+    }
+    
+    (next.at - current).min(Duration::minutes(MAX_VISIT_MINUTES)).num_seconds()
+}
\ No newline at end of file
@@ -1,31 +1,207 @@
+// Keystroke text encryption. The key derived from the password is only ever
+// held in wiped buffers, and the cipher built from it lives in its own
+// allocation that is locked into RAM where the platform allows (so it never
+// reaches swap) and wiped when the Encryptor is dropped.
+//
+// How the key is derived (algorithm, cost and salt) is stored in the
+// database's key_derivation table, with a value encrypted under the key to
+// recognise a wrong password. The `kdf` settings in config.json only apply
+// to keys derived from then on: the first one for a database, and the new
+// one on `selfspy change-password`, which re-encrypts the stored text under
+// it, so raising the cost never loses old data.
+//
+// With `"key_storage": "platform"` there is no password: the key is random
+// and stored sealed by this machine's TPM or Secure Enclave (see
+// platform::secure_element), so the database and config.json copied
+// elsewhere can't be decrypted. Neither can they after the TPM is cleared or
+// on a new machine, unless the key was also escrowed under a recovery
+// password, which opens it like a derived key would.
 use aes_gcm::{
     aead::{Aead, KeyInit, OsRng},
     Aes256Gcm, Key, Nonce,
 };
-use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
-use argon2::password_hash::{rand_core::RngCore, SaltString};
-use anyhow::{Result, anyhow};
+use argon2::password_hash::rand_core::RngCore;
+use argon2::{Algorithm, Argon2, Params, Version};
+use serde::{Deserialize, Serialize};
+use std::mem::ManuallyDrop;
+pub use zeroize::{Zeroize, Zeroizing};
+
+use crate::error::{Result, SelfspyError};
+use crate::models::Rekey;
+use crate::platform::secure_element;
+use crate::progress::Progress;
+use crate::Database;
+
+pub const ARGON2ID: &str = "argon2id";
+// Followed by the secure element backend, e.g. "sealed:tpm2"
+pub const SEALED_PREFIX: &str = "sealed:";
+
+// Encrypted under each new key as its check value
+const CHECK_PLAINTEXT: &[u8] = b"selfspy key check";
+
+// Argon2id cost of newly derived keys
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KdfSettings {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for KdfSettings {
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyStorage {
+    // Derived from the password the monitor is started with
+    #[default]
+    Password,
+    // Random, sealed by the platform's secure element
+    Platform,
+}
+
+// How a database's key is derived from its password, or where it is sealed,
+// as stored
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyDerivation {
+    pub algorithm: String,
+    pub settings: KdfSettings,
+    pub salt: Vec<u8>,
+    // CHECK_PLAINTEXT encrypted under the key
+    pub check_value: Vec<u8>,
+    // The key itself as sealed by the secure element; None for derived keys
+    pub sealed_key: Option<Vec<u8>>,
+    // The sealed key encrypted under one derived from the recovery password
+    // with `salt` and `settings`; None for derived keys and sealed keys
+    // without a recovery password
+    pub recovery_key: Option<Vec<u8>>,
+}
 
 pub struct Encryptor {
-    cipher: Aes256Gcm,
+    // Dropped by hand in Drop, so the key schedule is wiped while its pages
+    // are still locked
+    cipher: Box<ManuallyDrop<Aes256Gcm>>,
+    locked: bool,
 }
 
 impl Encryptor {
-    pub fn new(password: &str) -> Result<Self> {
-        let salt = SaltString::generate(&mut OsRng);
-        let argon2 = Argon2::default();
-        let password_hash = argon2
-            .hash_password(password.as_bytes(), &salt)
-            .map_err(|e| anyhow!("Failed to hash password: {}", e))?;
-        
-        let hash_output = password_hash.hash.unwrap();
-        let key_bytes = hash_output.as_bytes();
-        let mut key = [0u8; 32];
-        key.copy_from_slice(&key_bytes[..32]);
+    // Derives a key with a fresh salt; the returned derivation has to be
+    // stored for the key to be derived again
+    pub fn create(password: &str, settings: KdfSettings) -> Result<(Self, KeyDerivation)> {
+        let mut salt = vec![0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let mut kdf = KeyDerivation {
+            algorithm: ARGON2ID.to_string(),
+            settings,
+            salt,
+            check_value: Vec::new(),
+            sealed_key: None,
+            recovery_key: None,
+        };
+        let encryptor = Self::derive(password, &kdf)?;
+        kdf.check_value = encryptor.encrypt(CHECK_PLAINTEXT)?;
+        Ok((encryptor, kdf))
+    }
+    
+    // Generates a random key and seals it; opening it again needs the same
+    // secure element, or the recovery password if one is given
+    pub fn create_sealed(recovery: Option<(&str, KdfSettings)>) -> Result<(Self, KeyDerivation)> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(&mut key[..]);
+        let sealed_key = secure_element::seal(&key[..])?;
+        let encryptor = Self::from_key(&key[..]);
+        let mut kdf = KeyDerivation {
+            algorithm: format!("{}{}", SEALED_PREFIX, secure_element::BACKEND),
+            settings: KdfSettings {
+                memory_kib: 0,
+                iterations: 0,
+                parallelism: 0,
+            },
+            salt: Vec::new(),
+            check_value: encryptor.encrypt(CHECK_PLAINTEXT)?,
+            sealed_key: Some(sealed_key),
+            recovery_key: None,
+        };
+        if let Some((password, settings)) = recovery {
+            kdf.settings = settings;
+            kdf.salt = vec![0u8; 16];
+            OsRng.fill_bytes(&mut kdf.salt);
+            kdf.recovery_key = Some(Self::derive(password, &kdf)?.encrypt(&key[..])?);
+        }
+        Ok((encryptor, kdf))
+    }
+    
+    // Derives or unseals a stored key again; sealed keys only use the
+    // password, as the recovery password, when the secure element can't
+    // unseal them. WrongPassword unless its check value decrypts.
+    pub fn open(password: &str, kdf: &KeyDerivation) -> Result<Self> {
+        let encryptor = match (&kdf.sealed_key, &kdf.recovery_key) {
+            (Some(sealed_key), recovery_key) => match Self::unseal(kdf, sealed_key) {
+                Ok(encryptor) => encryptor,
+                Err(_) if recovery_key.is_some() && !password.is_empty() => Self::recover(password, kdf)?,
+                Err(e) => return Err(e),
+            },
+            (None, _) => Self::derive(password, kdf)?,
+        };
+        if encryptor.decrypt(&kdf.check_value)?.as_slice() != CHECK_PLAINTEXT {
+            return Err(SelfspyError::WrongPassword);
+        }
+        Ok(encryptor)
+    }
+    
+    fn derive(password: &str, kdf: &KeyDerivation) -> Result<Self> {
+        if kdf.algorithm != ARGON2ID && kdf.recovery_key.is_none() {
+            return Err(SelfspyError::Encryption(format!("Unknown key derivation {}", kdf.algorithm)));
+        }
+        let KdfSettings { memory_kib, iterations, parallelism } = kdf.settings;
+        let params = Params::new(memory_kib, iterations, parallelism, Some(32))
+            .map_err(|e| SelfspyError::Encryption(format!("Invalid key derivation parameters: {}", e)))?;
         
-        let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&key));
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(password.as_bytes(), &kdf.salt, &mut key[..])
+            .map_err(|e| SelfspyError::Encryption(format!("Failed to hash password: {}", e)))?;
         
-        Ok(Self { cipher })
+        Ok(Self::from_key(&key[..]))
+    }
+    
+    fn unseal(kdf: &KeyDerivation, sealed_key: &[u8]) -> Result<Self> {
+        let backend = kdf.algorithm.strip_prefix(SEALED_PREFIX).unwrap_or(&kdf.algorithm);
+        if backend != secure_element::BACKEND {
+            return Err(SelfspyError::Encryption(format!(
+                "The key is sealed by {}, which this machine doesn't have",
+                backend
+            )));
+        }
+        let key = secure_element::unseal(sealed_key)?;
+        if key.len() != 32 {
+            return Err(SelfspyError::Encryption("Unsealed key has the wrong length".to_string()));
+        }
+        Ok(Self::from_key(&key))
+    }
+    
+    // The sealed key from its escrowed copy, with Argon2id as for derived keys
+    fn recover(password: &str, kdf: &KeyDerivation) -> Result<Self> {
+        let recovery_key = kdf.recovery_key.as_deref().unwrap_or_default();
+        let key = Self::derive(password, kdf)?.decrypt(recovery_key)?;
+        if key.len() != 32 {
+            return Err(SelfspyError::Encryption("Recovered key has the wrong length".to_string()));
+        }
+        Ok(Self::from_key(&key))
+    }
+    
+    fn from_key(key: &[u8]) -> Self {
+        let cipher = Box::new(ManuallyDrop::new(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key))));
+        let locked = lock_memory(&*cipher);
+        Self { cipher, locked }
     }
     
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>> {
@@ -35,7 +211,7 @@ impl Encryptor {
         
         let ciphertext = self.cipher
             .encrypt(nonce, plaintext)
-            .map_err(|e| anyhow!("Encryption failed: {}", e))?;
+            .map_err(|e| SelfspyError::Encryption(format!("Encryption failed: {}", e)))?;
         
         let mut result = nonce_bytes.to_vec();
         result.extend_from_slice(&ciphertext);
@@ -43,9 +219,10 @@ impl Encryptor {
         Ok(result)
     }
     
-    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Vec<u8>> {
+    // The plaintext is wiped when the caller drops it
+    pub fn decrypt(&self, ciphertext: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
         if ciphertext.len() < 12 {
-            return Err(anyhow!("Invalid ciphertext"));
+            return Err(SelfspyError::Encryption("Invalid ciphertext".to_string()));
         }
         
         let (nonce_bytes, encrypted) = ciphertext.split_at(12);
@@ -53,8 +230,141 @@ impl Encryptor {
         
         let plaintext = self.cipher
             .decrypt(nonce, encrypted)
-            .map_err(|e| anyhow!("Decryption failed: {}", e))?;
+            .map_err(|_| SelfspyError::WrongPassword)?;
         
-        Ok(plaintext)
+        Ok(Zeroizing::new(plaintext))
+    }
+}
+
+// The encryptor for the database's key, deriving and storing one with
+// `settings` on first use
+pub async fn unlock(db: &Database, password: &str, settings: KdfSettings) -> Result<Encryptor> {
+    if let Some(kdf) = db.get_key_derivation().await? {
+        return Encryptor::open(password, &kdf);
     }
-}
\ No newline at end of file
+    store_first(db, Encryptor::create(password, settings)?, password).await
+}
+
+// The encryptor for the database's sealed key, sealing one without a
+// recovery password on first use. `recovery_password` is only tried when
+// the key can't be unsealed, and is WrongPassword if it doesn't open it.
+pub async fn unlock_sealed(db: &Database, recovery_password: &str) -> Result<Encryptor> {
+    match db.get_key_derivation().await? {
+        Some(kdf) if kdf.sealed_key.is_some() => Encryptor::open(recovery_password, &kdf),
+        Some(_) => Err(SelfspyError::Encryption(
+            "This database's key is derived from a password; seal it with `selfspy change-password --platform`"
+                .to_string(),
+        )),
+        None => {
+            tracing::warn!(
+                "Sealing a new encryption key without a recovery password: typed text will be unrecoverable \
+                 if the TPM is cleared or the database moves to another machine; set one with \
+                 `selfspy change-password --platform --recovery-password`"
+            );
+            store_first(db, Encryptor::create_sealed(None)?, "").await
+        }
+    }
+}
+
+async fn store_first(db: &Database, (encryptor, kdf): (Encryptor, KeyDerivation), password: &str) -> Result<Encryptor> {
+    if db.init_key_derivation(&kdf).await? {
+        return Ok(encryptor);
+    }
+    
+    // Another process stored one first
+    match db.get_key_derivation().await? {
+        Some(kdf) => Encryptor::open(password, &kdf),
+        None => Err(SelfspyError::Encryption("Key derivation vanished while storing it".to_string())),
+    }
+}
+
+// Like `unlock`, but never writes, for reading text back
+pub async fn open(db: &Database, password: &str) -> Result<Encryptor> {
+    match db.get_key_derivation().await? {
+        Some(kdf) => Encryptor::open(password, &kdf),
+        None => Err(SelfspyError::Encryption(
+            "No encryption key is stored in this database, so its typed text can't be decrypted".to_string(),
+        )),
+    }
+}
+
+// Re-encrypts the stored text under a key derived from `new_password` with
+// `settings`, which may also be the old password with a new cost. Text
+// written before keys were stored (under a salt that was never kept) can't
+// be decrypted and stays as it is.
+pub async fn change_password(
+    db: &Database,
+    old_password: &str,
+    new_password: &str,
+    settings: KdfSettings,
+    progress: &Progress,
+) -> Result<Rekey> {
+    replace_key(db, old_password, Encryptor::create(new_password, settings)?, progress).await
+}
+
+// Like `change_password`, but to a new sealed key, escrowed under
+// `recovery` when given
+pub async fn seal_key(
+    db: &Database,
+    old_password: &str,
+    recovery: Option<(&str, KdfSettings)>,
+    progress: &Progress,
+) -> Result<Rekey> {
+    replace_key(db, old_password, Encryptor::create_sealed(recovery)?, progress).await
+}
+
+async fn replace_key(
+    db: &Database,
+    old_password: &str,
+    (new, kdf): (Encryptor, KeyDerivation),
+    progress: &Progress,
+) -> Result<Rekey> {
+    let old = match db.get_key_derivation().await? {
+        Some(kdf) => Some(Encryptor::open(old_password, &kdf)?),
+        None => None,
+    };
+    db.rekey(old.as_ref(), &new, &kdf, progress).await
+}
+
+impl Drop for Encryptor {
+    fn drop(&mut self) {
+        // Wipes the key schedule in place; the Box frees the memory after
+        unsafe { ManuallyDrop::drop(&mut *self.cipher) };
+        if self.locked {
+            unlock_memory(&*self.cipher);
+        }
+    }
+}
+
+// mlock / VirtualLock; false when refused, e.g. past RLIMIT_MEMLOCK
+#[cfg(unix)]
+fn lock_memory<T>(value: &T) -> bool {
+    unsafe { libc::mlock(value as *const T as *const libc::c_void, std::mem::size_of::<T>()) == 0 }
+}
+
+#[cfg(unix)]
+fn unlock_memory<T>(value: &T) {
+    unsafe {
+        libc::munlock(value as *const T as *const libc::c_void, std::mem::size_of::<T>());
+    }
+}
+
+#[cfg(windows)]
+fn lock_memory<T>(value: &T) -> bool {
+    use windows::Win32::System::Memory::VirtualLock;
+    unsafe { VirtualLock(value as *const T as *const std::ffi::c_void, std::mem::size_of::<T>()).is_ok() }
+}
+
+#[cfg(windows)]
+fn unlock_memory<T>(value: &T) {
+    use windows::Win32::System::Memory::VirtualUnlock;
+    let _ = unsafe { VirtualUnlock(value as *const T as *const std::ffi::c_void, std::mem::size_of::<T>()) };
+}
+
+#[cfg(not(any(unix, windows)))]
+fn lock_memory<T>(_value: &T) -> bool {
+    false
+}
+
+#[cfg(not(any(unix, windows)))]
+fn unlock_memory<T>(_value: &T) {}
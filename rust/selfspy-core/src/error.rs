@@ -0,0 +1,234 @@
+// Error categories shared by all binaries, each with a stable exit code so
+// wrapper scripts and service managers can tell failures apart.
+//
+// Exit codes (stable across releases; new categories only add codes):
+//   0   success
+//   1   unexpected error
+//   2   invalid command line (reported by clap)
+//   10  permission denied (file access or OS input/accessibility permissions)
+//   11  database locked by another process
+//   12  database file corrupt or not a selfspy database
+//   13  wrong encryption password
+//   14  invalid configuration
+//   15  other I/O failure
+//   16  disk full
+//   130 cancelled by the user (Ctrl+C)
+use std::process::ExitCode;
+
+use thiserror::Error;
+
+pub const EXIT_CODES_HELP: &str = "\
+Exit codes:
+  0   success
+  1   unexpected error
+  2   invalid command line
+  10  permission denied
+  11  database locked by another process
+  12  database corrupt or not a selfspy database
+  13  wrong encryption password
+  14  invalid configuration
+  15  other I/O failure
+  16  disk full
+  130 cancelled by the user";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    PermissionDenied,
+    DatabaseLocked,
+    CorruptDb,
+    WrongPassword,
+    Config,
+    Io,
+    DiskFull,
+    Cancelled,
+    Other,
+}
+
+impl ErrorKind {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::PermissionDenied => 10,
+            ErrorKind::DatabaseLocked => 11,
+            ErrorKind::CorruptDb => 12,
+            ErrorKind::WrongPassword => 13,
+            ErrorKind::Config => 14,
+            ErrorKind::Io => 15,
+            ErrorKind::DiskFull => 16,
+            ErrorKind::Cancelled => 130,
+        }
+    }
+    
+    // Finds the first cause in the source chain that falls into a known category
+    pub fn classify(err: &(dyn std::error::Error + 'static)) -> Self {
+        std::iter::successors(Some(err), |cause| cause.source())
+            .find_map(|cause| {
+                if let Some(kind) = cause.downcast_ref::<ErrorKind>() {
+                    return Some(*kind);
+                }
+                if let Some(e) = cause.downcast_ref::<SelfspyError>() {
+                    return Some(e.kind());
+                }
+                if let Some(e) = cause.downcast_ref::<sqlx::Error>() {
+                    return classify_sqlx(e);
+                }
+                if let Some(e) = cause.downcast_ref::<std::io::Error>() {
+                    return Some(classify_io(e));
+                }
+                if cause.is::<serde_json::Error>() {
+                    return Some(ErrorKind::Config);
+                }
+                None
+            })
+            .unwrap_or(ErrorKind::Other)
+    }
+}
+
+impl std::fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            ErrorKind::PermissionDenied => "permission denied",
+            ErrorKind::DatabaseLocked => "database is locked by another process",
+            ErrorKind::CorruptDb => "database is corrupt or not a selfspy database",
+            ErrorKind::WrongPassword => "wrong encryption password",
+            ErrorKind::Config => "invalid configuration",
+            ErrorKind::Io => "I/O error",
+            ErrorKind::DiskFull => "disk full",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Other => "unexpected error",
+        };
+        f.write_str(text)
+    }
+}
+
+impl std::error::Error for ErrorKind {}
+
+// The error type returned by everything in selfspy-core
+#[derive(Debug, Error)]
+pub enum SelfspyError {
+    #[error("database error: {0}")]
+    Database(#[from] sqlx::Error),
+    
+    #[error("invalid data in database: {0}")]
+    InvalidData(String),
+    
+    #[error("encryption error: {0}")]
+    Encryption(String),
+    
+    // Ciphertext didn't authenticate under the key derived from the password
+    #[error("wrong password or tampered data")]
+    WrongPassword,
+    
+    #[error("platform error: {0}")]
+    Platform(String),
+    
+    #[error("invalid configuration: {0}")]
+    Config(#[from] serde_json::Error),
+    
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    
+    #[error("notification error: {0}")]
+    Notification(String),
+    
+    #[error("MQTT error: {0}")]
+    Mqtt(String),
+    
+    #[error("backup error: {0}")]
+    Backup(String),
+    
+    #[error("invalid custom metric: {0}")]
+    Metric(String),
+    
+    #[error("export error: {0}")]
+    Export(String),
+    
+    #[error("operation cancelled")]
+    Cancelled,
+    
+    // Another process holds the data directory's monitor lock
+    #[error("the monitor is recording into this database; stop it first")]
+    MonitorRunning,
+    
+    // A legacy database imported before, with when
+    #[error("{0} was already imported on {1}")]
+    AlreadyImported(String, String),
+    
+        #[error("failed to initialize logging: {0}")]
+    Logging(#[from] tracing::subscriber::SetGlobalDefaultError),
+}
+
+pub type Result<T> = std::result::Result<T, SelfspyError>;
+
+impl SelfspyError {
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SelfspyError::Database(e) => classify_sqlx(e).unwrap_or(ErrorKind::Other),
+            SelfspyError::InvalidData(_) => ErrorKind::CorruptDb,
+            SelfspyError::WrongPassword => ErrorKind::WrongPassword,
+            SelfspyError::Config(_) | SelfspyError::Metric(_) => ErrorKind::Config,
+            SelfspyError::Io(e) => classify_io(e),
+            SelfspyError::Cancelled => ErrorKind::Cancelled,
+            SelfspyError::MonitorRunning => ErrorKind::DatabaseLocked,
+            SelfspyError::Encryption(_)
+            | SelfspyError::Platform(_)
+            | SelfspyError::Notification(_)
+            | SelfspyError::Mqtt(_)
+            | SelfspyError::Backup(_)
+            | SelfspyError::Export(_)
+            | SelfspyError::AlreadyImported(..)
+            | SelfspyError::Logging(_) => ErrorKind::Other,
+        }
+    }
+}
+
+impl From<chrono::ParseError> for SelfspyError {
+    fn from(err: chrono::ParseError) -> Self {
+        SelfspyError::InvalidData(err.to_string())
+    }
+}
+
+fn classify_sqlx(err: &sqlx::Error) -> Option<ErrorKind> {
+    match err {
+        sqlx::Error::Database(db) => {
+            // SQLite primary result codes: 5 BUSY, 6 LOCKED, 11 CORRUPT, 13 FULL, 26 NOTADB
+            let code = db.code().and_then(|c| c.parse::<i32>().ok()).map(|c| c & 0xff);
+            match code {
+                Some(5) | Some(6) => Some(ErrorKind::DatabaseLocked),
+                Some(11) | Some(26) => Some(ErrorKind::CorruptDb),
+                Some(13) => Some(ErrorKind::DiskFull),
+                Some(23) => Some(ErrorKind::PermissionDenied),
+                _ => None,
+            }
+        }
+        sqlx::Error::Io(e) => Some(classify_io(e)),
+        _ => None,
+    }
+}
+
+fn classify_io(err: &std::io::Error) -> ErrorKind {
+    // ENOSPC on Linux and macOS, ERROR_HANDLE_DISK_FULL / ERROR_DISK_FULL on Windows
+    let disk_full = if cfg!(windows) { [39, 112] } else { [28, 28] };
+    match err.kind() {
+        std::io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+        _ if err.raw_os_error().is_some_and(|code| disk_full.contains(&code)) => ErrorKind::DiskFull,
+        _ => ErrorKind::Io,
+    }
+}
+
+// Prints the error chain and turns it into the process exit code
+pub fn report(err: &(dyn std::error::Error + 'static)) -> ExitCode {
+    let kind = ErrorKind::classify(err);
+    
+    let mut message = err.to_string();
+    for cause in std::iter::successors(err.source(), |cause| cause.source()) {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+    }
+    eprintln!("Error: {}", message);
+    
+    if kind != ErrorKind::Other {
+        eprintln!("({}, exit code {})", kind, kind.exit_code());
+    }
+    ExitCode::from(kind.exit_code())
+}
\ No newline at end of file
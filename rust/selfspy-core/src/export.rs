@@ -0,0 +1,343 @@
+// Raw event export for `selfspy export`: every window, click and key batch
+// recorded in a range, with its timestamp, as JSON lines or CSV. Windows
+// come first, then clicks, then key batches, each oldest first; clicks and
+// keys carry their window's id so the three can be joined again. Rows are
+// streamed from the database, so years of data export in constant memory.
+//
+// Typed text is only included when asked for. Encrypted text needs the
+// password it was recorded with; batches without text (e.g. imported from
+// the original selfspy) export with an empty text, and batches the password
+// can't decrypt export without one and are counted in the summary.
+//
+// With the `parquet` feature the same records can be written as one Parquet
+// file, typed (timestamps as UTC microseconds, nullable columns for fields
+// other kinds don't have), for loading into pandas, polars or DuckDB.
+use chrono::{DateTime, Utc};
+use futures::TryStreamExt;
+use serde::Serialize;
+use std::io::Write;
+use std::ops::Range;
+
+use crate::encryption::{Encryptor, Zeroizing};
+use crate::error::Result;
+#[cfg(feature = "parquet")]
+use crate::error::SelfspyError;
+use crate::{Database, Progress};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Jsonl,
+    Csv,
+}
+
+// What to do with the typed text of key batches
+pub enum KeyText<'a> {
+    Omit,
+    // Stored as plain text (encryption disabled)
+    Stored,
+    Decrypt(&'a Encryptor),
+}
+
+// One exported event; fields that don't apply to its kind stay empty
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ExportRecord {
+    pub kind: &'static str,
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub window_id: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub process: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub button: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub double_click: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_count: Option<i32>,
+    // Wiped when the record is dropped, like the decrypted buffer it came from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<Zeroizing<String>>,
+}
+
+const CSV_HEADER: &str = "kind,id,created_at,window_id,process,title,x,y,button,double_click,key_count,text";
+
+impl ExportRecord {
+    fn write<W: Write>(&self, format: ExportFormat, out: &mut W) -> Result<()> {
+        match format {
+            ExportFormat::Jsonl => {
+                serde_json::to_writer(&mut *out, self).map_err(std::io::Error::from)?;
+                writeln!(out)?;
+            }
+            ExportFormat::Csv => {
+                // The line holds the typed text too, so it is wiped as well
+                let fields = Zeroizing::new([
+                    self.kind.to_string(),
+                    self.id.to_string(),
+                    self.created_at.to_rfc3339(),
+                    optional(self.window_id),
+                    csv_field(self.process.as_deref()),
+                    csv_field(self.title.as_deref()),
+                    optional(self.x),
+                    optional(self.y),
+                    csv_field(self.button.as_deref()),
+                    optional(self.double_click),
+                    optional(self.key_count),
+                    csv_field(self.text.as_deref().map(String::as_str)),
+                ]);
+                writeln!(out, "{}", Zeroizing::new(fields.join(",")).as_str())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportSummary {
+    pub windows: u64,
+    pub clicks: u64,
+    pub key_batches: u64,
+    // Key batches exported without their text because it couldn't be
+    // decrypted, e.g. text from before the key derivation was stored
+    pub undecryptable: u64,
+}
+
+pub async fn write_events<W: Write>(
+    db: &Database,
+    range: Range<DateTime<Utc>>,
+    format: ExportFormat,
+    text: KeyText<'_>,
+    out: &mut W,
+    progress: &Progress,
+) -> Result<ExportSummary> {
+    if format == ExportFormat::Csv {
+        writeln!(out, "{}", CSV_HEADER)?;
+    }
+    let summary = each_record(db, range, text, progress, |record| record.write(format, &mut *out)).await?;
+    out.flush()?;
+    Ok(summary)
+}
+
+// Hands every record in export order to `emit`. Each kind is a stage of
+// unknown length, as the rows are streamed rather than counted first.
+async fn each_record(
+    db: &Database,
+    range: Range<DateTime<Utc>>,
+    text: KeyText<'_>,
+    progress: &Progress,
+    mut emit: impl FnMut(ExportRecord) -> Result<()>,
+) -> Result<ExportSummary> {
+    let mut summary = ExportSummary::default();
+    let processes = db.get_process_names().await?;
+    progress.start_stage("Exporting windows", None);
+    let mut windows = db.stream_windows(range.clone());
+    while let Some(window) = windows.try_next().await? {
+        progress.check_cancelled()?;
+        emit(ExportRecord {
+            kind: "window",
+            id: window.id,
+            created_at: window.created_at,
+            process: processes.get(&window.process_id).cloned(),
+            title: Some(window.title),
+            x: window.x,
+            y: window.y,
+            ..Default::default()
+        })?;
+        summary.windows += 1;
+        progress.advance(1);
+    }
+    drop(windows);
+    
+    progress.start_stage("Exporting clicks", None);
+    let mut clicks = db.stream_clicks(range.clone());
+    while let Some(click) = clicks.try_next().await? {
+        progress.check_cancelled()?;
+        emit(ExportRecord {
+            kind: "click",
+            id: click.id,
+            created_at: click.created_at,
+            window_id: Some(click.window_id),
+            x: Some(click.x),
+            y: Some(click.y),
+            button: Some(click.button),
+            double_click: Some(click.double_click),
+            ..Default::default()
+        })?;
+        summary.clicks += 1;
+        progress.advance(1);
+    }
+    drop(clicks);
+    
+    progress.start_stage("Exporting key batches", None);
+    let mut keys = db.stream_keys(range);
+    while let Some(batch) = keys.try_next().await? {
+        progress.check_cancelled()?;
+        let text = match &text {
+            KeyText::Omit => None,
+            _ if batch.encrypted_keys.is_empty() => Some(Zeroizing::new(String::new())),
+            KeyText::Stored => Some(Zeroizing::new(String::from_utf8_lossy(&batch.encrypted_keys).into_owned())),
+            KeyText::Decrypt(encryptor) => match encryptor.decrypt(&batch.encrypted_keys) {
+                Ok(plaintext) => Some(Zeroizing::new(String::from_utf8_lossy(&plaintext).into_owned())),
+                Err(_) => {
+                    summary.undecryptable += 1;
+                    None
+                }
+            },
+        };
+        emit(ExportRecord {
+            kind: "keys",
+            id: batch.id,
+            created_at: batch.created_at,
+            window_id: Some(batch.window_id),
+            key_count: Some(batch.key_count),
+            text,
+            ..Default::default()
+        })?;
+        summary.key_batches += 1;
+        progress.advance(1);
+    }
+    
+    Ok(summary)
+}
+
+#[cfg(feature = "parquet")]
+pub async fn write_parquet<W: Write + Send>(
+    db: &Database,
+    range: Range<DateTime<Utc>>,
+    text: KeyText<'_>,
+    out: W,
+    progress: &Progress,
+) -> Result<ExportSummary> {
+    let schema = parquet_export::schema();
+    let properties = parquet::file::properties::WriterProperties::builder()
+        .set_compression(parquet::basic::Compression::SNAPPY)
+        .build();
+    let mut writer = parquet::arrow::ArrowWriter::try_new(out, schema.clone(), Some(properties))
+        .map_err(|e| SelfspyError::Export(e.to_string()))?;
+    
+    let mut columns = parquet_export::Columns::default();
+    let summary = each_record(db, range, text, progress, |record| {
+        columns.push(record);
+        if columns.rows >= parquet_export::BATCH_ROWS {
+            writer
+                .write(&columns.finish(&schema)?)
+                .map_err(|e| SelfspyError::Export(e.to_string()))?;
+        }
+        Ok(())
+    })
+    .await?;
+    
+    if columns.rows > 0 {
+        writer
+            .write(&columns.finish(&schema)?)
+            .map_err(|e| SelfspyError::Export(e.to_string()))?;
+    }
+    writer.close().map_err(|e| SelfspyError::Export(e.to_string()))?;
+    Ok(summary)
+}
+
+#[cfg(feature = "parquet")]
+mod parquet_export {
+    use arrow_array::builder::{BooleanBuilder, Int32Builder, Int64Builder, StringBuilder, TimestampMicrosecondBuilder};
+    use arrow_array::{ArrayRef, RecordBatch};
+    use arrow_schema::{DataType, Field, Schema, SchemaRef, TimeUnit};
+    use std::sync::Arc;
+    
+    use super::ExportRecord;
+    use crate::error::{Result, SelfspyError};
+    
+    // Rows buffered before they are written as a row group's batch
+    pub const BATCH_ROWS: usize = 65_536;
+    
+    // The columns of ExportRecord, in the same order as the CSV
+    pub fn schema() -> SchemaRef {
+        let timestamp = DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()));
+        Arc::new(Schema::new(vec![
+            Field::new("kind", DataType::Utf8, false),
+            Field::new("id", DataType::Int64, false),
+            Field::new("created_at", timestamp, false),
+            Field::new("window_id", DataType::Int64, true),
+            Field::new("process", DataType::Utf8, true),
+            Field::new("title", DataType::Utf8, true),
+            Field::new("x", DataType::Int32, true),
+            Field::new("y", DataType::Int32, true),
+            Field::new("button", DataType::Utf8, true),
+            Field::new("double_click", DataType::Boolean, true),
+            Field::new("key_count", DataType::Int32, true),
+            Field::new("text", DataType::Utf8, true),
+        ]))
+    }
+    
+    #[derive(Default)]
+    pub struct Columns {
+        pub rows: usize,
+        kind: StringBuilder,
+        id: Int64Builder,
+        created_at: TimestampMicrosecondBuilder,
+        window_id: Int64Builder,
+        process: StringBuilder,
+        title: StringBuilder,
+        x: Int32Builder,
+        y: Int32Builder,
+        button: StringBuilder,
+        double_click: BooleanBuilder,
+        key_count: Int32Builder,
+        text: StringBuilder,
+    }
+    
+    impl Columns {
+        pub fn push(&mut self, record: ExportRecord) {
+            self.kind.append_value(record.kind);
+            self.id.append_value(record.id);
+            self.created_at.append_value(record.created_at.timestamp_micros());
+            self.window_id.append_option(record.window_id);
+            self.process.append_option(record.process);
+            self.title.append_option(record.title);
+            self.x.append_option(record.x);
+            self.y.append_option(record.y);
+            self.button.append_option(record.button);
+            self.double_click.append_option(record.double_click);
+            self.key_count.append_option(record.key_count);
+            self.text.append_option(record.text.as_deref().map(String::as_str));
+            self.rows += 1;
+        }
+        
+        // Takes the buffered rows as a batch, leaving the builders empty
+        pub fn finish(&mut self, schema: &SchemaRef) -> Result<RecordBatch> {
+            let columns: Vec<ArrayRef> = vec![
+                Arc::new(self.kind.finish()),
+                Arc::new(self.id.finish()),
+                Arc::new(self.created_at.finish().with_timezone("UTC")),
+                Arc::new(self.window_id.finish()),
+                Arc::new(self.process.finish()),
+                Arc::new(self.title.finish()),
+                Arc::new(self.x.finish()),
+                Arc::new(self.y.finish()),
+                Arc::new(self.button.finish()),
+                Arc::new(self.double_click.finish()),
+                Arc::new(self.key_count.finish()),
+                Arc::new(self.text.finish()),
+            ];
+            self.rows = 0;
+            RecordBatch::try_new(schema.clone(), columns).map_err(|e| SelfspyError::Export(e.to_string()))
+        }
+    }
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+// Quoted when it holds a separator, quote or line break
+fn csv_field(value: Option<&str>) -> String {
+    match value {
+        Some(value) if value.contains([',', '"', '\n', '\r']) => format!("\"{}\"", value.replace('"', "\"\"")),
+        Some(value) => value.to_string(),
+        None => String::new(),
+    }
+}
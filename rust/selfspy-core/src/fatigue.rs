@@ -0,0 +1,200 @@
+// Typing rhythm as a rough fatigue indicator. Only timing and counts are
+// kept: the gaps between key presses, how long keys are held down and how
+// many presses were corrections, never which keys were typed.
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::HourlyRhythm;
+
+// Longer gaps are pauses rather than part of the typing rhythm
+pub(crate) const MAX_RHYTHM_GAP_MS: u64 = 2000;
+// Keys held longer are being held on purpose (modifiers, auto-repeat), or
+// their release was missed
+const MAX_HOLD_MS: u64 = 2000;
+// Hourly samples with fewer presses are too noisy to trend
+const MIN_HOURLY_KEYS: i64 = 200;
+// Trends above these per-hour slopes are reported as likely fatigue
+const BACKSPACE_TREND_LIMIT: f64 = 0.005;
+const VARIATION_TREND_LIMIT: f64 = 0.03;
+
+// Per-flush accumulator kept by the monitor
+#[derive(Debug, Default)]
+pub struct TypingRhythm {
+    last_press: Option<Instant>,
+    // Keys pressed and not yet released, only until the release arrives
+    held: HashMap<String, Instant>,
+    pub keys: i64,
+    pub backspaces: i64,
+    pub intervals: i64,
+    pub interval_sum_ms: i64,
+    pub interval_sq_sum_ms: i64,
+    // Press-to-release (dwell) times of the keys released since the last flush
+    pub holds: i64,
+    pub hold_sum_ms: i64,
+    pub hold_min_ms: Option<i64>,
+    pub hold_max_ms: Option<i64>,
+}
+
+impl TypingRhythm {
+    pub fn record(&mut self, key: &str, now: Instant) {
+        self.keys += 1;
+        if is_correction(key) {
+            self.backspaces += 1;
+        }
+        
+        if let Some(last) = self.last_press {
+            let gap = now.duration_since(last).as_millis() as u64;
+            if gap <= MAX_RHYTHM_GAP_MS {
+                let gap = gap as i64;
+                self.intervals += 1;
+                self.interval_sum_ms += gap;
+                self.interval_sq_sum_ms += gap * gap;
+            }
+        }
+        self.last_press = Some(now);
+        
+        // Auto-repeat sends more presses for a held key; the hold runs from the first
+        self.held.retain(|_, pressed| now.duration_since(*pressed).as_millis() as u64 <= MAX_HOLD_MS);
+        self.held.entry(hold_key(key)).or_insert(now);
+    }
+    
+    pub fn release(&mut self, key: &str, now: Instant) {
+        let Some(pressed) = self.held.remove(&hold_key(key)) else {
+            return;
+        };
+        let hold = now.duration_since(pressed).as_millis() as u64;
+        if hold > MAX_HOLD_MS {
+            return;
+        }
+        
+        let hold = hold as i64;
+        self.holds += 1;
+        self.hold_sum_ms += hold;
+        self.hold_min_ms = Some(self.hold_min_ms.map_or(hold, |min| min.min(hold)));
+        self.hold_max_ms = Some(self.hold_max_ms.map_or(hold, |max| max.max(hold)));
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.keys == 0 && self.holds == 0
+    }
+    
+    // Moves the counts out for storage, like `reset`
+    pub fn take(&mut self) -> Self {
+        let mut counts = std::mem::take(self);
+        self.last_press = counts.last_press;
+        self.held = std::mem::take(&mut counts.held);
+        counts
+    }
+    
+    // Clears the counts but keeps the last press and the keys still held so
+    // gaps and holds span flushes
+    pub fn reset(&mut self) {
+        *self = Self {
+            last_press: self.last_press,
+            held: std::mem::take(&mut self.held),
+            ..Self::default()
+        };
+    }
+}
+
+// Presses and releases of one key can be spelled differently when a modifier
+// goes down or up in between ("<Ctrl+C>" then "c"), so holds are matched on
+// the key alone
+fn hold_key(key: &str) -> String {
+    let key = key.strip_prefix('<').and_then(|chord| chord.strip_suffix('>')).unwrap_or(key);
+    key.rsplit('+').next().unwrap_or(key).to_lowercase()
+}
+
+// Backspace or forward delete, as named by the platform key hooks, including
+// word-wise deletes such as "<Ctrl+BackSpace>"
+pub fn is_correction(key: &str) -> bool {
+    let key = key.to_ascii_lowercase();
+    matches!(
+        key.as_str(),
+        "backspace" | "[backspace]" | "delete" | "[delete]" | "\u{8}"
+    ) || (key.starts_with('<') && (key.ends_with("+backspace>") || key.ends_with("+delete>")))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FatigueIndicator {
+    pub day: NaiveDate,
+    // Coefficient of variation of inter-key gaps over the whole day
+    pub rhythm_variation: f64,
+    pub backspace_ratio: f64,
+    // Average time keys were held down, when releases were recorded
+    pub mean_hold_ms: Option<f64>,
+    // Change per hour through the day (least-squares slopes)
+    pub rhythm_variation_trend: f64,
+    pub backspace_ratio_trend: f64,
+    pub hours: usize,
+}
+
+impl FatigueIndicator {
+    pub fn likely_fatigued(&self) -> bool {
+        self.backspace_ratio_trend > BACKSPACE_TREND_LIMIT || self.rhythm_variation_trend > VARIATION_TREND_LIMIT
+    }
+}
+
+// `hours` are the per-hour totals of one day
+pub fn daily_indicator(day: NaiveDate, hours: &[HourlyRhythm]) -> Option<FatigueIndicator> {
+    let total = hours.iter().fold(HourlyRhythm::default(), |mut total, hour| {
+        total.keys += hour.keys;
+        total.backspaces += hour.backspaces;
+        total.intervals += hour.intervals;
+        total.interval_sum_ms += hour.interval_sum_ms;
+        total.interval_sq_sum_ms += hour.interval_sq_sum_ms;
+        total.holds += hour.holds;
+        total.hold_sum_ms += hour.hold_sum_ms;
+        total
+    });
+    let rhythm_variation = variation(&total)?;
+    
+    let samples: Vec<&HourlyRhythm> = hours.iter().filter(|hour| hour.keys >= MIN_HOURLY_KEYS).collect();
+    let variation_points: Vec<(f64, f64)> = samples
+        .iter()
+        .filter_map(|hour| Some((hour.hour as f64, variation(hour)?)))
+        .collect();
+    let backspace_points: Vec<(f64, f64)> = samples
+        .iter()
+        .map(|hour| (hour.hour as f64, hour.backspaces as f64 / hour.keys as f64))
+        .collect();
+    
+    Some(FatigueIndicator {
+        day,
+        rhythm_variation,
+        backspace_ratio: total.backspaces as f64 / total.keys.max(1) as f64,
+        mean_hold_ms: (total.holds > 0).then(|| total.hold_sum_ms as f64 / total.holds as f64),
+        rhythm_variation_trend: slope(&variation_points),
+        backspace_ratio_trend: slope(&backspace_points),
+        hours: samples.len(),
+    })
+}
+
+fn variation(rhythm: &HourlyRhythm) -> Option<f64> {
+    if rhythm.intervals < 2 || rhythm.interval_sum_ms == 0 {
+        return None;
+    }
+    let n = rhythm.intervals as f64;
+    let mean = rhythm.interval_sum_ms as f64 / n;
+    let variance = (rhythm.interval_sq_sum_ms as f64 / n - mean * mean).max(0.0);
+    Some(variance.sqrt() / mean)
+}
+
+// Least-squares slope; flat when there are too few points
+fn slope(points: &[(f64, f64)]) -> f64 {
+    if points.len() < 3 {
+        return 0.0;
+    }
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum();
+    let spread: f64 = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum();
+    if spread == 0.0 {
+        0.0
+    } else {
+        covariance / spread
+    }
+}
\ No newline at end of file
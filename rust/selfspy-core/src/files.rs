@@ -0,0 +1,60 @@
+// Files holding personal data are created readable by their owner only (on
+// Unix; on Windows the profile directory's ACL already limits them), and
+// files several processes rewrite are guarded by an advisory lock file.
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+// Creates or truncates `path` with mode 0600
+pub fn create_private(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+// Writes `contents` aside and renames it over `path`, so a crash never
+// leaves half a file
+pub fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(".tmp");
+    let temp = path.with_file_name(temp_name);
+    let mut file = create_private(&temp)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    drop(file);
+    std::fs::rename(&temp, path)
+}
+
+// Blocks until this process holds the lock on `path` (created if missing);
+// released when the returned file is dropped
+pub fn lock(path: &Path) -> std::io::Result<File> {
+    let file = open_lock_file(path)?;
+    file.lock()?;
+    Ok(file)
+}
+
+// Like `lock`, but None right away when another process holds it
+pub fn try_lock(path: &Path) -> std::io::Result<Option<File>> {
+    let file = open_lock_file(path)?;
+    match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(std::fs::TryLockError::WouldBlock) => Ok(None),
+        Err(std::fs::TryLockError::Error(e)) => Err(e),
+    }
+}
+
+fn open_lock_file(path: &Path) -> std::io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true).truncate(false);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
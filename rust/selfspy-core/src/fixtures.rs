@@ -0,0 +1,212 @@
+// Databases with exactly known contents, for tests and benchmarks of the
+// stats queries (behind the `test-support` feature), for example
+//
+//   let db = FixtureBuilder::new()
+//       .keystrokes("Code", day, 9, 1200)
+//       .clicks("Code", day, 9, 40)
+//       .active_minutes("Code", day, 9, 45)
+//       .keystrokes("Slack", day, 9, 300)
+//       .build(&path)
+//       .await?;
+//
+// Each app's hour becomes one window opened when its input starts. The input
+// is spread over its active minutes: keystrokes in one batch per minute (the
+// first also carrying the hour's corrections),
+// clicks one at a time, both from the first of those minutes. Apps sharing an
+// hour follow each other in the order they were first added, and an activity
+// period covers each hour's input, so every report sees the same totals as
+// `expected_day`.
+use chrono::{DateTime, Duration, Local, NaiveDate, TimeZone, Utc};
+use std::path::Path;
+
+use crate::error::{Result, SelfspyError};
+use crate::models::DailySummary;
+use crate::Database;
+
+// One app's input in one local hour
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FixtureHour {
+    pub app: String,
+    pub day: NaiveDate,
+    pub hour: u32,
+    pub keystrokes: i64,
+    // Backspace/delete presses, on top of the keystrokes that committed a
+    // character; reports count both as keystrokes
+    pub corrections: i64,
+    pub clicks: i64,
+    // Minutes the input is spread over; at least one
+    pub minutes: i64,
+}
+
+impl FixtureHour {
+    // Minutes with at least one keystroke or click
+    pub fn active_minutes(&self) -> i64 {
+        self.keystrokes.min(self.minutes).max(self.clicks.min(self.minutes))
+    }
+}
+
+// A window with its input, as written by `Database::insert_fixture`
+#[derive(Debug, Clone)]
+pub struct FixtureWindow {
+    pub app: String,
+    pub opened_at: DateTime<Utc>,
+    // Time, committed characters and corrections of each key batch
+    pub keys: Vec<(DateTime<Utc>, i64, i64)>,
+    pub clicks: Vec<DateTime<Utc>>,
+}
+
+// Start and end of an activity period
+type Period = (DateTime<Utc>, DateTime<Utc>);
+
+#[derive(Debug, Clone, Default)]
+pub struct FixtureBuilder {
+    hours: Vec<FixtureHour>,
+}
+
+impl FixtureBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    // Adds `count` keystrokes for `app` in local hour `hour` of `day`
+    pub fn keystrokes(mut self, app: &str, day: NaiveDate, hour: u32, count: i64) -> Self {
+        self.hour(app, day, hour).keystrokes += count;
+        self
+    }
+    
+    // Adds `count` correction presses, stored with the first keystroke batch
+    pub fn corrections(mut self, app: &str, day: NaiveDate, hour: u32, count: i64) -> Self {
+        self.hour(app, day, hour).corrections += count;
+        self
+    }
+    
+    pub fn clicks(mut self, app: &str, day: NaiveDate, hour: u32, count: i64) -> Self {
+        self.hour(app, day, hour).clicks += count;
+        self
+    }
+    
+    // Spreads the app's input in that hour over `minutes` minutes instead
+    // of one
+    pub fn active_minutes(mut self, app: &str, day: NaiveDate, hour: u32, minutes: i64) -> Self {
+        self.hour(app, day, hour).minutes = minutes.max(1);
+        self
+    }
+    
+    pub fn hours(&self) -> &[FixtureHour] {
+        &self.hours
+    }
+    
+    // What the daily summary of `day` must report for the built database
+    pub fn expected_day(&self, day: NaiveDate) -> DailySummary {
+        let hours = || self.hours.iter().filter(|hour| hour.day == day);
+        DailySummary {
+            date: day,
+            active_seconds: hours().map(|hour| hour.active_minutes() * 60).sum(),
+            keystrokes: hours().map(|hour| hour.keystrokes + hour.corrections).sum(),
+            clicks: hours().map(|hour| hour.clicks).sum(),
+        }
+    }
+    
+    // Creates the database at `path`, which must not hold any data yet
+    pub async fn build(&self, path: &Path) -> Result<Database> {
+        let (windows, periods) = self.layout()?;
+        let db = Database::new(path).await?;
+        db.insert_fixture(&windows, &periods).await?;
+        Ok(db)
+    }
+    
+    // The windows to write, and an activity period per hour with input
+    fn layout(&self) -> Result<(Vec<FixtureWindow>, Vec<Period>)> {
+        let mut windows = Vec::new();
+        let mut periods: Vec<Period> = Vec::new();
+        let mut used: Vec<(NaiveDate, u32, i64)> = Vec::new();
+        
+        for hour in &self.hours {
+            let active = hour.active_minutes();
+            if active == 0 {
+                continue;
+            }
+            let top = local_hour(hour.day, hour.hour)?;
+            let offset = match used.iter_mut().find(|(day, h, _)| *day == hour.day && *h == hour.hour) {
+                Some((_, _, minutes)) => {
+                    *minutes += hour.minutes;
+                    *minutes - hour.minutes
+                }
+                None => {
+                    used.push((hour.day, hour.hour, hour.minutes));
+                    0
+                }
+            };
+            if offset + hour.minutes > 60 {
+                return Err(SelfspyError::InvalidData(format!(
+                    "more than 60 active minutes in {} {:02}:00",
+                    hour.day, hour.hour
+                )));
+            }
+            
+            // Events land half a minute into their minute, clear of its edges
+            let minute = |i: i64| top + Duration::minutes(offset + i) + Duration::seconds(30);
+            let batches = hour.keystrokes.min(hour.minutes);
+            let keys = (0..batches)
+                .map(|i| {
+                    let share = hour.keystrokes / batches + i64::from(i < hour.keystrokes % batches);
+                    let corrections = if i == 0 { hour.corrections } else { 0 };
+                    (minute(i), share, corrections)
+                })
+                .collect();
+            let clicks = (0..hour.clicks).map(|i| minute(i % hour.minutes)).collect();
+            windows.push(FixtureWindow {
+                app: hour.app.clone(),
+                opened_at: top + Duration::minutes(offset),
+                keys,
+                clicks,
+            });
+            
+            periods.push((top + Duration::minutes(offset), top + Duration::minutes(offset + active)));
+        }
+        
+        // Written in time order, like the monitor would, with back-to-back
+        // periods joined
+        windows.sort_by_key(|window| window.opened_at);
+        periods.sort();
+        let mut joined: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+        for (start, end) in periods {
+            match joined.last_mut() {
+                Some(last) if last.1 == start => last.1 = end,
+                _ => joined.push((start, end)),
+            }
+        }
+        
+        Ok((windows, joined))
+    }
+    
+    fn hour(&mut self, app: &str, day: NaiveDate, hour: u32) -> &mut FixtureHour {
+        let index = match self.hours.iter().position(|h| h.app == app && h.day == day && h.hour == hour) {
+            Some(index) => index,
+            None => {
+                self.hours.push(FixtureHour {
+                    app: app.to_string(),
+                    day,
+                    hour,
+                    keystrokes: 0,
+                    corrections: 0,
+                    clicks: 0,
+                    minutes: 1,
+                });
+                self.hours.len() - 1
+            }
+        };
+        &mut self.hours[index]
+    }
+}
+
+fn local_hour(day: NaiveDate, hour: u32) -> Result<DateTime<Utc>> {
+    let time = day
+        .and_hms_opt(hour, 0, 0)
+        .ok_or_else(|| SelfspyError::InvalidData(format!("no hour {} in a day", hour)))?;
+    Ok(Local
+        .from_local_datetime(&time)
+        .earliest()
+        .unwrap_or_else(|| Local.from_utc_datetime(&time))
+        .with_timezone(&Utc))
+}
@@ -0,0 +1,107 @@
+// Weekly goals and the review of how each week measured up against the goal
+// that was in effect at the time.
+use chrono::{Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+use crate::calendar::week_start;
+use crate::{DailySummary, Goal};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GoalMetric {
+    ActiveMinutes,
+    Keystrokes,
+    Clicks,
+}
+
+impl GoalMetric {
+    pub const ALL: [GoalMetric; 3] = [GoalMetric::ActiveMinutes, GoalMetric::Keystrokes, GoalMetric::Clicks];
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            GoalMetric::ActiveMinutes => "active_minutes",
+            GoalMetric::Keystrokes => "keystrokes",
+            GoalMetric::Clicks => "clicks",
+        }
+    }
+    
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.as_str() == value)
+    }
+    
+    fn value(&self, summary: &DailySummary) -> i64 {
+        match self {
+            GoalMetric::ActiveMinutes => summary.active_seconds / 60,
+            GoalMetric::Keystrokes => summary.keystrokes,
+            GoalMetric::Clicks => summary.clicks,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeekAttainment {
+    pub week_start: NaiveDate,
+    pub metric: GoalMetric,
+    pub actual: i64,
+    pub target: i64,
+}
+
+impl WeekAttainment {
+    pub fn met(&self) -> bool {
+        self.actual >= self.target
+    }
+    
+    pub fn percent(&self) -> f64 {
+        if self.target == 0 {
+            100.0
+        } else {
+            self.actual as f64 / self.target as f64 * 100.0
+        }
+    }
+}
+
+// The goal for a metric that applied at the start of a week: the most
+// recent one effective on or before that day
+pub fn goal_in_effect(goals: &[Goal], metric: GoalMetric, week: NaiveDate) -> Option<&Goal> {
+    goals
+        .iter()
+        .filter(|goal| goal.metric == metric.as_str() && goal.effective_from <= week)
+        .max_by_key(|goal| (goal.effective_from, goal.id))
+}
+
+// Attainment for every week starting at `first_week` that had a goal in effect
+pub fn weekly_review(
+    summaries: &[DailySummary],
+    goals: &[Goal],
+    first_week: NaiveDate,
+    weeks: i64,
+    first_day: Weekday,
+) -> Vec<WeekAttainment> {
+    let mut review = Vec::new();
+    
+    for offset in 0..weeks {
+        let week = week_start(first_week, first_day) + Duration::weeks(offset);
+        let week_end = week + Duration::days(7);
+        
+        for metric in GoalMetric::ALL {
+            let Some(goal) = goal_in_effect(goals, metric, week) else {
+                continue;
+            };
+            
+            let actual = summaries
+                .iter()
+                .filter(|summary| summary.date >= week && summary.date < week_end)
+                .map(|summary| metric.value(summary))
+                .sum();
+            
+            review.push(WeekAttainment {
+                week_start: week,
+                metric,
+                actual,
+                target: goal.weekly_target,
+            });
+        }
+    }
+    
+    review
+}
\ No newline at end of file
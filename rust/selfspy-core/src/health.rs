@@ -0,0 +1,220 @@
+// Daily steps and sleep imported from a phone or watch, for correlating with
+// computer activity (less sleep, more typos?). Two formats are understood:
+//
+// - A CSV with a header row naming a date column ("date" or "day") and any of
+//   "steps", "sleep_hours" or "sleep_minutes"; other columns are ignored.
+// - The export.xml inside an Apple Health export. Step counts are summed per
+//   day and source, keeping the source with the most steps so phone and
+//   watch aren't double counted; asleep time is handled the same way and is
+//   credited to the day it ends.
+use chrono::{NaiveDate, NaiveDateTime};
+use std::collections::{BTreeMap, HashMap};
+use std::io::BufRead;
+
+use crate::error::{Result, SelfspyError};
+use crate::HealthDay;
+
+const APPLE_STEPS: &str = "HKQuantityTypeIdentifierStepCount";
+const APPLE_SLEEP: &str = "HKCategoryTypeIdentifierSleepAnalysis";
+
+// Picks the parser from the file contents: Apple exports are XML
+pub fn parse(reader: impl BufRead) -> Result<Vec<HealthDay>> {
+    let mut lines = reader.lines().peekable();
+    let is_xml = match lines.peek() {
+        Some(Ok(first)) => first.trim_start().starts_with('<'),
+        _ => false,
+    };
+    
+    if is_xml {
+        parse_apple_health(lines)
+    } else {
+        parse_csv(lines)
+    }
+}
+
+fn parse_csv(mut lines: impl Iterator<Item = std::io::Result<String>>) -> Result<Vec<HealthDay>> {
+    let header = lines.next().transpose()?.unwrap_or_default();
+    let columns: Vec<String> = split_csv_line(&header)
+        .iter()
+        .map(|name| name.trim().to_ascii_lowercase().replace(' ', "_"))
+        .collect();
+    let find = |names: &[&str]| columns.iter().position(|column| names.contains(&column.as_str()));
+    
+    let date_column = find(&["date", "day"])
+        .ok_or_else(|| SelfspyError::InvalidData("CSV needs a 'date' column".to_string()))?;
+    let steps_column = find(&["steps", "step_count"]);
+    let sleep_hours_column = find(&["sleep_hours", "sleep"]);
+    let sleep_minutes_column = find(&["sleep_minutes"]);
+    if steps_column.is_none() && sleep_hours_column.is_none() && sleep_minutes_column.is_none() {
+        return Err(SelfspyError::InvalidData(
+            "CSV needs a 'steps', 'sleep_hours' or 'sleep_minutes' column".to_string(),
+        ));
+    }
+    
+    let mut days = BTreeMap::new();
+    for (index, line) in lines.enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(&line);
+        let field = |column: Option<usize>| {
+            column
+                .and_then(|column| fields.get(column))
+                .map(|value| value.trim())
+                .filter(|value| !value.is_empty())
+        };
+        
+        let row = index + 2;
+        let day = field(Some(date_column))
+            .and_then(parse_date)
+            .ok_or_else(|| SelfspyError::InvalidData(format!("line {}: missing or invalid date", row)))?;
+        let number = |value: &str| {
+            value
+                .parse::<f64>()
+                .map_err(|_| SelfspyError::InvalidData(format!("line {}: '{}' is not a number", row, value)))
+        };
+        
+        let entry: &mut HealthDay = days.entry(day).or_insert_with(|| HealthDay { day, ..Default::default() });
+        if let Some(steps) = field(steps_column) {
+            entry.steps = Some(number(steps)?.round() as i64);
+        }
+        if let Some(hours) = field(sleep_hours_column) {
+            entry.sleep_minutes = Some((number(hours)? * 60.0).round() as i64);
+        }
+        if let Some(minutes) = field(sleep_minutes_column) {
+            entry.sleep_minutes = Some(number(minutes)?.round() as i64);
+        }
+    }
+    
+    Ok(days.into_values().collect())
+}
+
+// Plain comma separation with double-quoted fields; enough for spreadsheet exports
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                current.push('"');
+                chars.next();
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    fields.push(current);
+    fields
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    // Accept full timestamps too, keeping the date as written
+    let date = value.get(..10).unwrap_or(value);
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .or_else(|_| NaiveDate::parse_from_str(value, "%m/%d/%Y"))
+        .ok()
+}
+
+// export.xml has one <Record .../> element per line; the file can be several
+// hundred megabytes, so it is scanned line by line rather than parsed whole
+fn parse_apple_health(lines: impl Iterator<Item = std::io::Result<String>>) -> Result<Vec<HealthDay>> {
+    let mut steps: HashMap<(NaiveDate, String), f64> = HashMap::new();
+    let mut sleep: HashMap<(NaiveDate, String), i64> = HashMap::new();
+    
+    for line in lines {
+        let line = line?;
+        let line = line.trim_start();
+        if !line.starts_with("<Record ") {
+            continue;
+        }
+        
+        match attribute(line, "type") {
+            Some(APPLE_STEPS) => {
+                let (Some(start), Some(value)) = (attribute(line, "startDate"), attribute(line, "value")) else {
+                    continue;
+                };
+                let (Some(day), Ok(value)) = (parse_date(start), value.parse::<f64>()) else {
+                    continue;
+                };
+                let source = attribute(line, "sourceName").unwrap_or_default().to_string();
+                *steps.entry((day, source)).or_default() += value;
+            }
+            Some(APPLE_SLEEP) => {
+                // Asleep, AsleepCore, AsleepDeep, AsleepREM, AsleepUnspecified; not InBed or Awake
+                if !attribute(line, "value").is_some_and(|value| value.contains("Asleep")) {
+                    continue;
+                }
+                let (Some(start), Some(end)) = (
+                    attribute(line, "startDate").and_then(parse_timestamp),
+                    attribute(line, "endDate").and_then(parse_timestamp),
+                ) else {
+                    continue;
+                };
+                if end > start {
+                    let source = attribute(line, "sourceName").unwrap_or_default().to_string();
+                    // Saturating: a damaged file can hold absurd spans
+                    let minutes = sleep.entry((end.date(), source)).or_default();
+                    *minutes = minutes.saturating_add((end - start).num_minutes());
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    let mut days: BTreeMap<NaiveDate, HealthDay> = BTreeMap::new();
+    for ((day, _), count) in steps {
+        let entry = days.entry(day).or_insert_with(|| HealthDay { day, ..Default::default() });
+        let count = count.round() as i64;
+        if entry.steps.is_none_or(|steps| count > steps) {
+            entry.steps = Some(count);
+        }
+    }
+    for ((day, _), minutes) in sleep {
+        let entry = days.entry(day).or_insert_with(|| HealthDay { day, ..Default::default() });
+        if entry.sleep_minutes.is_none_or(|sleep_minutes| minutes > sleep_minutes) {
+            entry.sleep_minutes = Some(minutes);
+        }
+    }
+    
+    Ok(days.into_values().collect())
+}
+
+fn attribute<'a>(element: &'a str, name: &str) -> Option<&'a str> {
+    let marker = format!(" {}=\"", name);
+    let start = element.find(&marker)? + marker.len();
+    let end = element[start..].find('"')?;
+    Some(&element[start..start + end])
+}
+
+// "2024-03-01 07:12:44 +0100": the wall-clock time where it was recorded
+fn parse_timestamp(value: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(value.get(..19)?, "%Y-%m-%d %H:%M:%S").ok()
+}
+
+// Pearson correlation of paired samples; None with fewer than three pairs or
+// when either side never varies
+pub fn correlation(pairs: &[(f64, f64)]) -> Option<f64> {
+    if pairs.len() < 3 {
+        return None;
+    }
+    
+    let n = pairs.len() as f64;
+    let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (mut covariance, mut variance_x, mut variance_y) = (0.0, 0.0, 0.0);
+    for (x, y) in pairs {
+        covariance += (x - mean_x) * (y - mean_y);
+        variance_x += (x - mean_x).powi(2);
+        variance_y += (y - mean_y).powi(2);
+    }
+    
+    if variance_x == 0.0 || variance_y == 0.0 {
+        return None;
+    }
+    Some(covariance / (variance_x * variance_y).sqrt())
+}
@@ -0,0 +1,157 @@
+// Plausibility checks on raw input so an auto-clicker or a key stuck down
+// overnight doesn't end up as millions of "real" events. Excess events are
+// dropped and summarized as diagnostics notes instead.
+use std::time::{Duration, Instant};
+
+const RATE_WINDOW: Duration = Duration::from_secs(1);
+// Triple-click is the most a person does on one spot in quick succession
+const MAX_SAME_SPOT_CLICKS: u32 = 3;
+// A stuck key auto-repeats every few dozen milliseconds. A longer pause ends
+// the run, so the same key pressed by hand now and then is never "stuck".
+const STUCK_KEY_GAP: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Accept,
+    Drop,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AnomalyKind {
+    KeyRate,
+    StuckKey,
+    ClickRate,
+    DuplicateClick,
+}
+
+impl AnomalyKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            AnomalyKind::KeyRate => "key_rate",
+            AnomalyKind::StuckKey => "stuck_key",
+            AnomalyKind::ClickRate => "click_rate",
+            AnomalyKind::DuplicateClick => "duplicate_click",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    pub dropped: u64,
+    pub detail: String,
+}
+
+struct RateCounter {
+    window_start: Instant,
+    count: u32,
+}
+
+impl RateCounter {
+    fn new(now: Instant) -> Self {
+        Self { window_start: now, count: 0 }
+    }
+    
+    // Returns the number of events seen in the current window, including this one
+    fn hit(&mut self, now: Instant) -> u32 {
+        if now.duration_since(self.window_start) >= RATE_WINDOW {
+            self.window_start = now;
+            self.count = 0;
+        }
+        self.count += 1;
+        self.count
+    }
+}
+
+pub struct InputGuard {
+    max_keys_per_second: u32,
+    max_clicks_per_second: u32,
+    stuck_key_repeat_limit: u32,
+    key_rate: RateCounter,
+    click_rate: RateCounter,
+    last_key: Option<(String, Instant)>,
+    key_repeats: u32,
+    last_click: Option<(i32, i32, String)>,
+    same_spot_clicks: RateCounter,
+    dropped: Vec<(AnomalyKind, u64, String)>,
+}
+
+impl InputGuard {
+    pub fn new(max_keys_per_second: u32, max_clicks_per_second: u32, stuck_key_repeat_limit: u32) -> Self {
+        let now = Instant::now();
+        Self {
+            max_keys_per_second,
+            max_clicks_per_second,
+            stuck_key_repeat_limit,
+            key_rate: RateCounter::new(now),
+            click_rate: RateCounter::new(now),
+            last_key: None,
+            key_repeats: 0,
+            last_click: None,
+            same_spot_clicks: RateCounter::new(now),
+            dropped: Vec::new(),
+        }
+    }
+    
+    pub fn check_key(&mut self, key: &str, now: Instant) -> Verdict {
+        match &mut self.last_key {
+            Some((last, at)) if last == key && now.duration_since(*at) < STUCK_KEY_GAP => {
+                *at = now;
+                self.key_repeats += 1;
+            }
+            _ => {
+                self.last_key = Some((key.to_string(), now));
+                self.key_repeats = 1;
+            }
+        }
+        
+        if self.key_repeats > self.stuck_key_repeat_limit {
+            self.record(AnomalyKind::StuckKey, format!("key {:?} repeated more than {} times", key, self.stuck_key_repeat_limit));
+            return Verdict::Drop;
+        }
+        
+        if self.key_rate.hit(now) > self.max_keys_per_second {
+            self.record(AnomalyKind::KeyRate, format!("more than {} keys per second", self.max_keys_per_second));
+            return Verdict::Drop;
+        }
+        
+        Verdict::Accept
+    }
+    
+    pub fn check_click(&mut self, x: i32, y: i32, button: &str, now: Instant) -> Verdict {
+        let spot = (x, y, button.to_string());
+        
+        if self.last_click.as_ref() == Some(&spot) {
+            if self.same_spot_clicks.hit(now) > MAX_SAME_SPOT_CLICKS {
+                self.record(AnomalyKind::DuplicateClick, format!("repeated {} clicks at ({}, {})", button, x, y));
+                return Verdict::Drop;
+            }
+        } else {
+            self.last_click = Some(spot);
+            self.same_spot_clicks = RateCounter::new(now);
+            self.same_spot_clicks.hit(now);
+        }
+        
+        if self.click_rate.hit(now) > self.max_clicks_per_second {
+            self.record(AnomalyKind::ClickRate, format!("more than {} clicks per second", self.max_clicks_per_second));
+            return Verdict::Drop;
+        }
+        
+        Verdict::Accept
+    }
+    
+    // Drained on every flush; one entry per kind of anomaly seen since the last call
+    pub fn take_anomalies(&mut self) -> Vec<Anomaly> {
+        self.dropped
+            .drain(..)
+            .map(|(kind, dropped, detail)| Anomaly { kind, dropped, detail })
+            .collect()
+    }
+    
+    fn record(&mut self, kind: AnomalyKind, detail: String) {
+        match self.dropped.iter_mut().find(|(existing, _, _)| *existing == kind) {
+            Some(entry) => entry.1 += 1,
+            None => self.dropped.push((kind, 1, detail)),
+        }
+    }
+}
\ No newline at end of file
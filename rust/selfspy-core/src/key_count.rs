@@ -0,0 +1,84 @@
+// Keystroke counting. A batch records both the raw key presses and the
+// characters they committed, counted as grapheme clusters so that emoji and
+// other multi-byte input count once. Named keys such as "[Return]" press a
+// key but commit no character, as do shortcuts such as "<Ctrl+C>". A single
+// event that inserts several characters is a paste (or an input method
+// commit) and is counted apart from typing.
+use once_cell::sync::Lazy;
+use regex::Regex;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::fatigue;
+
+// How keys.key_count was obtained (keys.count_method)
+pub const COUNT_GRAPHEMES: &str = "graphemes";
+// Historic row recounted from its stored plaintext
+pub const COUNT_RECOUNTED: &str = "recounted";
+// Historic row left with its original byte-length count (text was encrypted)
+pub const COUNT_BYTES: &str = "bytes";
+// Imported from the original Python selfspy, which stored key presses only
+pub const COUNT_LEGACY: &str = "legacy";
+
+// A single event committing at least this many characters counts as a paste
+pub const PASTE_MIN_CHARACTERS: i32 = 4;
+
+static NAMED_KEY: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[[A-Za-z0-9_]{2,}\]").unwrap());
+// Chords as spelled by platform::normalize_key: modifiers, then a key name or one character
+static CHORD: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"<(?:(?:Ctrl|Alt|Shift|Cmd|Super)\+)+(?:[A-Za-z0-9_]{2,}|.)>").unwrap());
+
+// Counts for the keystrokes since the last flush
+#[derive(Debug, Default, Clone, Copy)]
+pub struct KeyCounts {
+    pub events: i32,
+    // Typed characters only; pasted ones are in `pasted`
+    pub characters: i32,
+    pub corrections: i32,
+    pub pasted: i32,
+}
+
+impl KeyCounts {
+    pub fn record(&mut self, key: &str) {
+        self.events += 1;
+        let characters = committed_characters(key);
+        if characters >= PASTE_MIN_CHARACTERS {
+            self.pasted += characters;
+        } else {
+            self.characters += characters;
+        }
+        if fatigue::is_correction(key) {
+            self.corrections += 1;
+        }
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.events == 0
+    }
+}
+
+// Characters one key press commits: 0 for named keys and chords, otherwise its graphemes
+pub fn committed_characters(key: &str) -> i32 {
+    if is_named_key(key) || is_chord(key) {
+        0
+    } else {
+        key.graphemes(true).count() as i32
+    }
+}
+
+pub fn is_paste(key: &str) -> bool {
+    committed_characters(key) >= PASTE_MIN_CHARACTERS
+}
+
+fn is_named_key(key: &str) -> bool {
+    NAMED_KEY.find(key).is_some_and(|found| found.len() == key.len())
+}
+
+fn is_chord(key: &str) -> bool {
+    CHORD.find(key).is_some_and(|found| found.len() == key.len())
+}
+
+// Characters in a stored keystroke buffer, skipping named keys and chords
+pub fn count_buffer(text: &str) -> i32 {
+    let text = CHORD.replace_all(text, "");
+    NAMED_KEY.replace_all(&text, "").graphemes(true).count() as i32
+}
\ No newline at end of file
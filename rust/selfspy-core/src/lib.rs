@@ -1,16 +1,63 @@
+pub mod accounts;
+pub mod after_hours;
+pub mod alerts;
+pub mod aggregate;
+pub mod away;
+pub mod backup;
+pub mod baseline;
+pub mod browser;
+pub mod calendar;
+pub mod categories;
+pub mod clock;
 pub mod config;
+pub mod control;
+pub mod custom_metrics;
 pub mod db;
+pub mod deep_work;
+pub mod distractions;
 pub mod encryption;
+pub mod error;
+pub mod export;
+pub mod fatigue;
+pub mod files;
+#[cfg(feature = "test-support")]
+pub mod fixtures;
+pub mod goals;
+pub mod health;
+pub mod input_guard;
+pub mod key_count;
+pub mod line_protocol;
+pub mod meetings;
+pub mod migrations;
 pub mod models;
 pub mod monitor;
+pub mod mqtt;
+pub mod notifications;
+pub mod pipeline;
 pub mod platform;
+pub mod power;
+pub mod process_names;
+pub mod query_stats;
+pub mod resume_cost;
+pub mod progress;
+pub mod routines;
+pub mod schema;
+pub mod slash;
+pub mod stats_cache;
+pub mod titles;
+pub mod typing_practice;
+pub mod webhook;
 
-pub use config::Config;
+pub use config::{CaptureSettings, Config, PinnedMetric, PinnedReport};
 pub use db::Database;
+pub use error::{ErrorKind, SelfspyError};
 pub use models::*;
-pub use monitor::ActivityMonitor;
+pub use monitor::{ActivityMonitor, MonitorStatus};
+pub use notifications::{Notification, Notifier};
+pub use progress::{Progress, ProgressUpdate};
+pub use stats_cache::StatsCache;
 
-use anyhow::Result;
+use crate::error::Result;
 
 pub async fn init() -> Result<()> {
     // Simple tracing setup - can be enhanced later
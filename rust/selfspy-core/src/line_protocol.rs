@@ -0,0 +1,85 @@
+// Per-minute activity in InfluxDB line protocol, for InfluxDB, VictoriaMetrics
+// and anything else that accepts it on a /write endpoint:
+//
+//   selfspy keystrokes=42i,clicks=7i,active_seconds=60i 1700000040000000000
+//
+// Timestamps are nanoseconds, the default precision of every such endpoint.
+use std::fmt::Write as _;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::error::{Result, SelfspyError};
+use crate::MinuteTotals;
+
+pub const DEFAULT_MEASUREMENT: &str = "selfspy";
+
+pub fn encode(measurement: &str, minutes: &[MinuteTotals]) -> String {
+    let measurement = escape_measurement(measurement);
+    let mut lines = String::new();
+    for minute in minutes {
+        let _ = writeln!(
+            lines,
+            "{} keystrokes={}i,clicks={}i,active_seconds=60i {}",
+            measurement,
+            minute.keystrokes,
+            minute.clicks,
+            minute.minute.timestamp() * 1_000_000_000
+        );
+    }
+    lines
+}
+
+fn escape_measurement(name: &str) -> String {
+    name.replace(',', "\\,").replace(' ', "\\ ")
+}
+
+// POSTs a batch to a plain-http write endpoint such as
+// http://localhost:8428/write (VictoriaMetrics) or
+// http://localhost:8086/api/v2/write?org=me&bucket=selfspy (InfluxDB 2, with a token)
+pub async fn push(url: &str, token: Option<&str>, body: &str) -> Result<()> {
+    let target = url
+        .strip_prefix("http://")
+        .ok_or_else(|| invalid(format!("only http:// write URLs are supported, got {}", url)))?;
+    let (authority, path) = match target.find('/') {
+        Some(index) => (&target[..index], &target[index..]),
+        None => (target, "/"),
+    };
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+    
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n",
+        path,
+        authority,
+        body.len()
+    );
+    if let Some(token) = token {
+        let _ = write!(request, "Authorization: Token {}\r\n", token);
+    }
+    request.push_str("\r\n");
+    
+    let mut stream = TcpStream::connect(&address).await?;
+    stream.write_all(request.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).await?;
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status = status_line.split_whitespace().nth(1).and_then(|code| code.parse::<u16>().ok());
+    
+    match status {
+        Some(code) if (200..300).contains(&code) => Ok(()),
+        _ => {
+            let detail = response.split("\r\n\r\n").nth(1).unwrap_or_default().trim();
+            Err(invalid(format!("write to {} failed: {} {}", url, status_line, detail)))
+        }
+    }
+}
+
+fn invalid(message: String) -> SelfspyError {
+    SelfspyError::Io(std::io::Error::other(message))
+}
@@ -0,0 +1,44 @@
+// Meetings, taken to be the stretches when the microphone or a camera was in
+// use, paired up from the meeting_start / meeting_end system events.
+use chrono::{DateTime, Utc};
+use std::ops::Range;
+
+use crate::SystemEvent;
+
+// Meetings from events sorted by time. A meeting still open runs until
+// `until`. The monitor ends meetings a crash left open when it next starts
+// (Database::close_interrupted_spans); in older data a start without an end
+// ends at the next start.
+pub fn meeting_intervals(events: &[SystemEvent], until: DateTime<Utc>) -> Vec<Range<DateTime<Utc>>> {
+    let mut intervals = Vec::new();
+    let mut started: Option<DateTime<Utc>> = None;
+    
+    for event in events {
+        match event.kind.as_str() {
+            "meeting_start" => {
+                if let Some(start) = started.replace(event.occurred_at) {
+                    intervals.push(start..event.occurred_at);
+                }
+            }
+            "meeting_end" => {
+                if let Some(start) = started.take() {
+                    intervals.push(start..event.occurred_at);
+                }
+            }
+            _ => {}
+        }
+    }
+    
+    if let Some(start) = started.filter(|start| *start < until) {
+        intervals.push(start..until);
+    }
+    intervals.retain(|interval| interval.end > interval.start);
+    intervals
+}
+
+// Seconds of `interval` falling inside `range`
+pub fn seconds_within(interval: &Range<DateTime<Utc>>, range: &Range<DateTime<Utc>>) -> i64 {
+    let start = interval.start.max(range.start);
+    let end = interval.end.min(range.end);
+    (end - start).num_seconds().max(0)
+}
@@ -0,0 +1,608 @@
+// Versioned schema migrations. Each database records the migrations applied
+// to it in schema_version; on open, the ones it lacks run in order inside a
+// single write transaction, so a failure leaves the database as it was and
+// two processes opening it at once don't both migrate.
+//
+// To change the schema, append a migration with the next version number.
+// Never edit one that has shipped: databases that already applied it won't
+// run it again.
+use sqlx::sqlite::SqliteConnection;
+use sqlx::{Row, SqlitePool};
+use tracing::info;
+
+use crate::error::{Result, SelfspyError};
+use crate::key_count;
+
+pub(crate) enum Step {
+    Sql(&'static str),
+    // Databases from before versioning may already have the column
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+    // Rows written before grapheme counting stored the byte length of the buffer.
+    // Plaintext buffers (whose length matches that count) are recounted; the rest
+    // are encrypted and keep their approximate count, flagged as such.
+    RecountLegacyKeys,
+}
+
+pub(crate) struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub steps: &'static [Step],
+}
+
+pub(crate) const MIGRATIONS: &[Migration] = &[
+    // Everything up to the introduction of versioning. Written to also bring
+    // any unversioned database up to date, whichever release created it.
+    Migration {
+        version: 1,
+        description: "baseline schema",
+        steps: &[
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS processes (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL UNIQUE,
+                    bundle_id TEXT,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS windows (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    process_id INTEGER NOT NULL,
+                    title TEXT NOT NULL,
+                    x INTEGER,
+                    y INTEGER,
+                    width INTEGER,
+                    height INTEGER,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (process_id) REFERENCES processes(id)
+                )
+                "#,
+            ),
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS keys (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    window_id INTEGER NOT NULL,
+                    encrypted_keys BLOB NOT NULL,
+                    key_count INTEGER NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (window_id) REFERENCES windows(id)
+                )
+                "#,
+            ),
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS clicks (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    window_id INTEGER NOT NULL,
+                    x INTEGER NOT NULL,
+                    y INTEGER NOT NULL,
+                    button TEXT NOT NULL,
+                    double_click BOOLEAN DEFAULT FALSE,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (window_id) REFERENCES windows(id)
+                )
+                "#,
+            ),
+            
+            // Active tab reported by the browser extension, see browser.rs
+            Step::AddColumn { table: "windows", column: "domain", definition: "TEXT" },
+            Step::AddColumn { table: "windows", column: "url", definition: "TEXT" },
+            
+            // File path or tab name of the open document, see platform::WindowInfo
+            Step::AddColumn { table: "windows", column: "document", definition: "TEXT" },
+            
+            // Scale factor of the display that received the click, see platform::display
+            Step::AddColumn { table: "clicks", column: "scale", definition: "REAL" },
+            
+            // Set by `selfstats fix --detect-outliers`; flagged rows are left out of statistics
+            Step::AddColumn { table: "keys", column: "outlier_reason", definition: "TEXT" },
+            Step::AddColumn { table: "clicks", column: "outlier_reason", definition: "TEXT" },
+            
+            // Backspace/delete presses within the batch, counted without their content
+            Step::AddColumn { table: "keys", column: "correction_count", definition: "INTEGER NOT NULL DEFAULT 0" },
+            
+            // key_count holds committed characters and event_count raw key presses;
+            // count_method records how key_count was obtained, see key_count.rs
+            Step::AddColumn { table: "keys", column: "event_count", definition: "INTEGER" },
+            Step::AddColumn { table: "keys", column: "count_method", definition: "TEXT" },
+            // Characters inserted by single multi-character events (pastes), not in key_count
+            Step::AddColumn { table: "keys", column: "paste_count", definition: "INTEGER NOT NULL DEFAULT 0" },
+            Step::RecountLegacyKeys,
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS mouse_moves (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    window_id INTEGER NOT NULL,
+                    distance INTEGER NOT NULL,
+                    event_count INTEGER NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (window_id) REFERENCES windows(id)
+                )
+                "#,
+            ),
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS scrolls (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    window_id INTEGER NOT NULL,
+                    event_count INTEGER NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (window_id) REFERENCES windows(id)
+                )
+                "#,
+            ),
+            
+            // Scroll distance along each axis regardless of direction, in platform wheel units
+            Step::AddColumn { table: "scrolls", column: "distance_x", definition: "REAL NOT NULL DEFAULT 0" },
+            Step::AddColumn { table: "scrolls", column: "distance_y", definition: "REAL NOT NULL DEFAULT 0" },
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS typing_rhythm (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    key_count INTEGER NOT NULL,
+                    backspace_count INTEGER NOT NULL,
+                    interval_count INTEGER NOT NULL,
+                    interval_sum_ms INTEGER NOT NULL,
+                    interval_sq_sum_ms INTEGER NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            
+            // Key hold (dwell) times; min and max stay NULL when no key was released
+            Step::AddColumn { table: "typing_rhythm", column: "hold_count", definition: "INTEGER NOT NULL DEFAULT 0" },
+            Step::AddColumn { table: "typing_rhythm", column: "hold_sum_ms", definition: "INTEGER NOT NULL DEFAULT 0" },
+            Step::AddColumn { table: "typing_rhythm", column: "hold_min_ms", definition: "INTEGER" },
+            Step::AddColumn { table: "typing_rhythm", column: "hold_max_ms", definition: "INTEGER" },
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS deep_work (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    day DATE NOT NULL,
+                    started_at DATETIME NOT NULL,
+                    ended_at DATETIME NOT NULL,
+                    process TEXT NOT NULL,
+                    minutes INTEGER NOT NULL,
+                    switches INTEGER NOT NULL
+                )
+                "#,
+            ),
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS diagnostics (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    kind TEXT NOT NULL,
+                    window_id INTEGER,
+                    dropped_events INTEGER NOT NULL DEFAULT 0,
+                    detail TEXT NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (window_id) REFERENCES windows(id)
+                )
+                "#,
+            ),
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS sessions (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    label TEXT,
+                    started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    ended_at DATETIME
+                )
+                "#,
+            ),
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS goals (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    metric TEXT NOT NULL,
+                    weekly_target INTEGER NOT NULL,
+                    effective_from DATE NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS tagged_periods (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    kind TEXT NOT NULL,
+                    started_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    ended_at DATETIME
+                )
+                "#,
+            ),
+            
+            // One row per uninterrupted stretch of a track playing
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS media_plays (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    player TEXT NOT NULL,
+                    title TEXT NOT NULL,
+                    artist TEXT,
+                    started_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+                    ended_at DATETIME
+                )
+                "#,
+            ),
+            
+            // One row per day; a re-import overwrites only the values it provides
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS health_days (
+                    day DATE PRIMARY KEY,
+                    steps INTEGER,
+                    sleep_minutes INTEGER,
+                    imported_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            
+            // kind is one of lock, unlock, sleep, wake, meeting_start, meeting_end
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS system_events (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    kind TEXT NOT NULL,
+                    occurred_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            
+            // Written when the power source changes and as the battery drains
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS power_samples (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    on_battery BOOLEAN NOT NULL,
+                    percent REAL,
+                    recorded_at DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            
+            // Keyboards and pointers seen by backends that tell devices apart
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS input_devices (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    name TEXT NOT NULL,
+                    kind TEXT NOT NULL,
+                    built_in BOOLEAN NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    UNIQUE (name, kind, built_in)
+                )
+                "#,
+            ),
+            
+            // Key, click and scroll counts per device, one row per device and flush
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS device_input (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    device_id INTEGER NOT NULL,
+                    keys INTEGER NOT NULL,
+                    clicks INTEGER NOT NULL,
+                    scrolls INTEGER NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (device_id) REFERENCES input_devices(id)
+                )
+                "#,
+            ),
+            
+            // Point-in-time notes dropped on the timeline, see control.rs
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS markers (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    window_id INTEGER,
+                    note TEXT,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+                    FOREIGN KEY (window_id) REFERENCES windows(id)
+                )
+                "#,
+            ),
+        ],
+    },
+    Migration {
+        version: 2,
+        description: "bigram and trigram timings",
+        steps: &[
+            // One row per n-gram per flush, see typing_practice
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS key_ngrams (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    ngram TEXT NOT NULL,
+                    key_count INTEGER NOT NULL,
+                    interval_sum_ms INTEGER NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_key_ngrams_created_at ON key_ngrams(created_at)"),
+        ],
+    },
+    Migration {
+        version: 3,
+        description: "activity periods",
+        steps: &[
+            // Contiguous stretches of input, as in the original selfspy. A
+            // period opens on the first input after idle; ended_at is the last
+            // input seen, saved every few seconds while the period is open.
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS periods (
+                    id INTEGER PRIMARY KEY AUTOINCREMENT,
+                    started_at DATETIME NOT NULL,
+                    ended_at DATETIME NOT NULL
+                )
+                "#,
+            ),
+            Step::Sql("CREATE INDEX IF NOT EXISTS idx_periods_started_at ON periods(started_at)"),
+        ],
+    },
+    Migration {
+        version: 4,
+        description: "daily rollups of pruned days",
+        steps: &[
+            // Totals kept for local days whose events were deleted by
+            // retention pruning, see Database::prune_before
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS daily_rollups (
+                    day DATE PRIMARY KEY,
+                    keystrokes INTEGER NOT NULL,
+                    clicks INTEGER NOT NULL,
+                    active_seconds INTEGER NOT NULL
+                )
+                "#,
+            ),
+        ],
+    },
+    Migration {
+        version: 5,
+        description: "window categories and projects",
+        steps: &[
+            // Set from the category rules when a window is recorded, see categories.rs
+            Step::AddColumn { table: "windows", column: "category", definition: "TEXT" },
+            Step::AddColumn { table: "windows", column: "project", definition: "TEXT" },
+        ],
+    },
+    Migration {
+        version: 6,
+        description: "key derivation parameters",
+        steps: &[
+            // One row: how the current encryption key is derived, see encryption.rs
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS key_derivation (
+                    id INTEGER PRIMARY KEY CHECK (id = 1),
+                    algorithm TEXT NOT NULL,
+                    memory_kib INTEGER NOT NULL,
+                    iterations INTEGER NOT NULL,
+                    parallelism INTEGER NOT NULL,
+                    salt BLOB NOT NULL,
+                    check_value BLOB NOT NULL,
+                    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+                )
+                "#,
+            ),
+        ],
+    },
+    Migration {
+        version: 7,
+        description: "sealed encryption keys",
+        steps: &[
+            // Set instead of a salt when key_storage is "platform"
+            Step::AddColumn { table: "key_derivation", column: "sealed_key", definition: "BLOB" },
+        ],
+    },
+    Migration {
+        version: 8,
+        description: "recovery passwords for sealed keys",
+        steps: &[
+            // The sealed key escrowed under a key derived from the recovery password
+            Step::AddColumn { table: "key_derivation", column: "recovery_key", definition: "BLOB" },
+        ],
+    },
+    Migration {
+        version: 9,
+        description: "daily n-gram totals",
+        steps: &[
+            // Replaces key_ngrams, whose row per n-gram per flush kept when
+            // each was typed; see typing_practice
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS key_ngram_days (
+                    day DATE NOT NULL,
+                    ngram TEXT NOT NULL,
+                    key_count INTEGER NOT NULL,
+                    interval_sum_ms INTEGER NOT NULL,
+                    PRIMARY KEY (day, ngram)
+                )
+                "#,
+            ),
+            Step::Sql(
+                r#"
+                INSERT OR IGNORE INTO key_ngram_days (day, ngram, key_count, interval_sum_ms)
+                SELECT date(created_at, 'localtime'), ngram, SUM(key_count), SUM(interval_sum_ms)
+                FROM key_ngrams
+                GROUP BY date(created_at, 'localtime'), ngram
+                "#,
+            ),
+            Step::Sql("DROP TABLE IF EXISTS key_ngrams"),
+        ],
+    },
+    Migration {
+        version: 10,
+        description: "alert rules fired",
+        steps: &[
+            // The local day each alert rule last fired, so a restart
+            // doesn't fire it again
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS alerts_fired (
+                    rule TEXT PRIMARY KEY,
+                    day DATE NOT NULL
+                )
+                "#,
+            ),
+        ],
+    },
+    Migration {
+        version: 11,
+        description: "imported legacy databases",
+        steps: &[
+            // One row per original selfspy database imported, keyed by a
+            // hash of its first window, so it isn't imported twice
+            Step::Sql(
+                r#"
+                CREATE TABLE IF NOT EXISTS legacy_imports (
+                    source TEXT PRIMARY KEY,
+                    path TEXT NOT NULL,
+                    imported_at DATETIME NOT NULL
+                )
+                "#,
+            ),
+        ],
+    },
+];
+
+// The version this build creates and understands
+pub fn latest_version() -> i64 {
+    MIGRATIONS.last().map_or(0, |migration| migration.version)
+}
+
+// 0 for a database that predates versioning or is empty
+pub(crate) async fn current_version(conn: &mut SqliteConnection) -> Result<i64> {
+    let versioned = sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version'")
+        .fetch_optional(&mut *conn)
+        .await?
+        .is_some();
+    if !versioned {
+        return Ok(0);
+    }
+    
+    Ok(sqlx::query_scalar("SELECT COALESCE(MAX(version), 0) FROM schema_version")
+        .fetch_one(&mut *conn)
+        .await?)
+}
+
+pub(crate) async fn run(pool: &SqlitePool) -> Result<()> {
+    let mut conn = pool.acquire().await?;
+    // IMMEDIATE takes the write lock up front, so a second process waits here
+    // and then sees the version the first one left
+    sqlx::query("BEGIN IMMEDIATE").execute(&mut *conn).await?;
+    let result = apply_pending(&mut conn).await;
+    let end = if result.is_ok() { "COMMIT" } else { "ROLLBACK" };
+    sqlx::query(end).execute(&mut *conn).await?;
+    result
+}
+
+async fn apply_pending(conn: &mut SqliteConnection) -> Result<()> {
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS schema_version (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at DATETIME DEFAULT CURRENT_TIMESTAMP
+        )
+        "#,
+    )
+    .execute(&mut *conn)
+    .await?;
+    
+    let current = current_version(conn).await?;
+    if current > latest_version() {
+        return Err(SelfspyError::InvalidData(format!(
+            "database schema version {} is newer than this build supports ({}); upgrade Selfspy",
+            current,
+            latest_version()
+        )));
+    }
+    
+    for migration in MIGRATIONS.iter().filter(|migration| migration.version > current) {
+        info!("Migrating database to schema version {} ({})", migration.version, migration.description);
+        for step in migration.steps {
+            apply_step(conn, step).await?;
+        }
+        sqlx::query("INSERT INTO schema_version (version, description) VALUES (?, ?)")
+            .bind(migration.version)
+            .bind(migration.description)
+            .execute(&mut *conn)
+            .await?;
+    }
+    Ok(())
+}
+
+async fn apply_step(conn: &mut SqliteConnection, step: &Step) -> Result<()> {
+    match step {
+        Step::Sql(sql) => {
+            sqlx::query(sql).execute(&mut *conn).await?;
+        }
+        Step::AddColumn { table, column, definition } => {
+            let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+                .fetch_all(&mut *conn)
+                .await?;
+            if !columns.iter().any(|row| row.get::<String, _>("name") == *column) {
+                sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+                    .execute(&mut *conn)
+                    .await?;
+            }
+        }
+        Step::RecountLegacyKeys => recount_legacy_keys(conn).await?,
+    }
+    Ok(())
+}
+
+async fn recount_legacy_keys(conn: &mut SqliteConnection) -> Result<()> {
+    loop {
+        let rows = sqlx::query(
+            "SELECT id, encrypted_keys, key_count FROM keys WHERE count_method IS NULL LIMIT 1000"
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+        if rows.is_empty() {
+            return Ok(());
+        }
+        
+        for row in rows {
+            let id: i64 = row.get("id");
+            let data: Vec<u8> = row.get("encrypted_keys");
+            let key_count: i64 = row.get("key_count");
+            
+            let plaintext = std::str::from_utf8(&data).ok().filter(|_| data.len() as i64 == key_count);
+            let (count, method) = match plaintext {
+                Some(text) => (key_count::count_buffer(text) as i64, key_count::COUNT_RECOUNTED),
+                None => (key_count, key_count::COUNT_BYTES),
+            };
+            
+            sqlx::query("UPDATE keys SET key_count = ?, count_method = ? WHERE id = ?")
+                .bind(count)
+                .bind(method)
+                .bind(id)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+}
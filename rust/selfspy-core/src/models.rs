@@ -1,8 +1,12 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Timelike, Utc, Weekday};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sqlx::FromRow;
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+use crate::categories::WindowTags;
+use crate::custom_metrics::CustomMetricValue;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, JsonSchema)]
 pub struct Process {
     pub id: i64,
     pub name: String,
@@ -10,7 +14,7 @@ pub struct Process {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, JsonSchema)]
 pub struct Window {
     pub id: i64,
     pub process_id: i64,
@@ -22,7 +26,7 @@ pub struct Window {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, JsonSchema)]
 pub struct Keys {
     pub id: i64,
     pub window_id: i64,
@@ -31,7 +35,7 @@ pub struct Keys {
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, JsonSchema)]
 pub struct Click {
     pub id: i64,
     pub window_id: i64,
@@ -39,16 +43,506 @@ pub struct Click {
     pub y: i32,
     pub button: String,
     pub double_click: bool,
+    // Logical points on the virtual desktop; multiply by scale for device pixels
+    pub scale: Option<f64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, JsonSchema)]
+pub struct MouseMoves {
+    pub id: i64,
+    pub window_id: i64,
+    pub distance: i64,
+    pub event_count: i32,
     pub created_at: DateTime<Utc>,
 }
 
+// Scroll events in a window since the previous flush, with the distance
+// scrolled along each axis regardless of direction
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Scrolls {
+    pub id: i64,
+    pub window_id: i64,
+    pub event_count: i32,
+    pub distance_x: f64,
+    pub distance_y: f64,
+    pub created_at: DateTime<Utc>,
+}
+
+// A note dropped on the timeline, optionally tied to the window in focus
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Marker {
+    pub id: i64,
+    pub window_id: Option<i64>,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Diagnostic {
+    pub id: i64,
+    pub kind: String,
+    pub window_id: Option<i64>,
+    pub dropped_events: i64,
+    pub detail: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// A stored record that can't plausibly be real input, found by `Database::find_outliers`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Outlier {
+    pub table: String,
+    pub id: i64,
+    pub created_at: DateTime<Utc>,
+    pub reason: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlierLimits {
+    pub max_keys_per_hour: i64,
+    pub max_clicks_per_hour: i64,
+    // Largest coordinate magnitude any platform's virtual desktop can report
+    pub max_coordinate: i32,
+}
+
+impl Default for OutlierLimits {
+    fn default() -> Self {
+        Self {
+            max_keys_per_hour: 25 * 3600,
+            max_clicks_per_hour: 15 * 3600,
+            max_coordinate: 32767,
+        }
+    }
+}
+
+// One run of the monitor, optionally labelled at start time ("conference day")
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, JsonSchema)]
+pub struct Session {
+    pub id: i64,
+    pub label: Option<String>,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+// Typing vs clicking vs scrolling for one app or one hour of the day
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct InputMix {
+    pub group: String,
+    pub keystrokes: i64,
+    pub clicks: i64,
+    pub scrolls: i64,
+}
+
+impl InputMix {
+    pub fn total(&self) -> i64 {
+        self.keystrokes + self.clicks + self.scrolls
+    }
+    
+    // Percentages of (keystrokes, clicks, scrolls)
+    pub fn shares(&self) -> (f64, f64, f64) {
+        let total = self.total();
+        if total == 0 {
+            return (0.0, 0.0, 0.0);
+        }
+        let share = |count: i64| count as f64 / total as f64 * 100.0;
+        (share(self.keystrokes), share(self.clicks), share(self.scrolls))
+    }
+    
+    // Keystrokes per click; high values mean the tool drives creation,
+    // low values mean it is mostly consumed
+    pub fn keys_per_click(&self) -> Option<f64> {
+        (self.clicks > 0).then(|| self.keystrokes as f64 / self.clicks as f64)
+    }
+}
+
+// Backspace/delete presses relative to all keystrokes for one app or hour
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ErrorRate {
+    pub group: String,
+    pub keystrokes: i64,
+    pub corrections: i64,
+}
+
+impl ErrorRate {
+    // Corrections as a percentage of keystrokes
+    pub fn rate(&self) -> f64 {
+        if self.keystrokes == 0 {
+            0.0
+        } else {
+            self.corrections as f64 / self.keystrokes as f64 * 100.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputMixGrouping {
+    App,
+    HourOfDay,
+}
+
+// Input totals for one UTC minute; a minute with any input counts as 60 active seconds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinuteTotals {
+    pub minute: DateTime<Utc>,
+    pub keystrokes: i64,
+    pub clicks: i64,
+}
+
+// The app that received most input during one local minute
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MinuteActivity {
+    pub minute: NaiveDateTime,
+    pub process: String,
+}
+
+// The foreground window at the moment it was first seen, in local time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowVisit {
+    pub at: NaiveDateTime,
+    pub process: String,
+    pub title: String,
+    // File path or tab name, when the backend reported one
+    pub document: Option<String>,
+}
+
+// Typing rhythm totals for one local hour of a day (no key content)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
+pub struct HourlyRhythm {
+    pub hour: i64,
+    pub keys: i64,
+    pub backspaces: i64,
+    pub intervals: i64,
+    pub interval_sum_ms: i64,
+    pub interval_sq_sum_ms: i64,
+    // Key hold (dwell) times, see fatigue::TypingRhythm
+    pub holds: i64,
+    pub hold_sum_ms: i64,
+    pub hold_min_ms: Option<i64>,
+    pub hold_max_ms: Option<i64>,
+}
+
+// One flushed batch of keystrokes, in local time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyActivity {
+    pub at: NaiveDateTime,
+    pub process: String,
+    pub keys: i64,
+}
+
+// A stretch of focused work on one app; times are local wall-clock times
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeepWorkInterval {
+    pub id: i64,
+    pub day: NaiveDate,
+    pub started_at: NaiveDateTime,
+    pub ended_at: NaiveDateTime,
+    pub process: String,
+    pub minutes: i64,
+    pub switches: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeepWorkDay {
+    pub day: NaiveDate,
+    pub minutes: i64,
+    pub intervals: i64,
+    pub longest_minutes: i64,
+}
+
+// Listening time per artist (or player when the artist is unknown) and the
+// keystrokes typed while it played
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MediaListening {
+    pub label: String,
+    pub seconds: i64,
+    pub keystrokes: i64,
+}
+
+// Steps and sleep for one day, imported from a phone or watch (see health.rs)
+#[derive(Debug, Clone, Default, Serialize, Deserialize, FromRow)]
+pub struct HealthDay {
+    pub day: NaiveDate,
+    pub steps: Option<i64>,
+    pub sleep_minutes: Option<i64>,
+}
+
+// Active minutes within one local clock hour
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct HourlyActivity {
+    pub hour: NaiveDateTime,
+    pub active_minutes: i64,
+}
+
+// Width of the buckets returned by Database::get_activity_buckets. Buckets
+// follow local time; weeks start on the given day.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BucketSize {
+    Hour,
+    Day,
+    Week(Weekday),
+}
+
+impl BucketSize {
+    // The local start of the bucket holding `at`
+    pub fn start_of(&self, at: NaiveDateTime) -> NaiveDateTime {
+        let day = match self {
+            BucketSize::Hour => return at.date().and_hms_opt(at.hour(), 0, 0).unwrap_or(at),
+            BucketSize::Day => at.date(),
+            BucketSize::Week(first_day) => crate::calendar::week_start(at.date(), *first_day),
+        };
+        day.and_hms_opt(0, 0, 0).unwrap_or(at)
+    }
+    
+    pub fn length(&self) -> chrono::Duration {
+        match self {
+            BucketSize::Hour => chrono::Duration::hours(1),
+            BucketSize::Day => chrono::Duration::days(1),
+            BucketSize::Week(_) => chrono::Duration::weeks(1),
+        }
+    }
+}
+
+// Input and active time within one bucket, starting at a local time
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ActivityBucket {
+    pub start: NaiveDateTime,
+    pub keystrokes: i64,
+    pub clicks: i64,
+    // Minutes with any input, as in DailySummary
+    pub active_seconds: i64,
+}
+
+// A weekly goal; setting a new target adds a row effective from a date so
+// earlier weeks keep being judged against the goal that applied then
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow, JsonSchema)]
+pub struct Goal {
+    pub id: i64,
+    pub metric: String,
+    pub weekly_target: i64,
+    pub effective_from: NaiveDate,
+    pub created_at: DateTime<Utc>,
+}
+
+// Restricts which records statistics queries look at
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StatsFilter {
+    pub session_label: Option<String>,
+    // Records created at or after `start` and before `end`; open-ended when unset
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+impl StatsFilter {
+    pub fn is_empty(&self) -> bool {
+        self.session_label.is_none() && self.start.is_none() && self.end.is_none()
+    }
+}
+
+// Power source and battery charge, recorded when either changes
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct PowerSample {
+    pub id: i64,
+    pub on_battery: bool,
+    pub percent: Option<f64>,
+    pub recorded_at: DateTime<Utc>,
+}
+
+// Input totals for one keyboard or pointer, see platform::DeviceId
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct DeviceUsage {
+    pub name: String,
+    // "keyboard", "mouse", "trackpad" or "other"
+    pub kind: String,
+    pub built_in: bool,
+    pub keys: i64,
+    pub clicks: i64,
+    pub scrolls: i64,
+}
+
+// Screen lock/unlock and system sleep/wake, see monitor::update_system_state
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct SystemEvent {
+    pub id: i64,
+    pub kind: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TaggedPeriod {
+    pub id: i64,
+    pub kind: String,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+// Page usage of the database file, for `selfspy doctor`
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageInfo {
+    pub page_size: i64,
+    pub page_count: i64,
+    // Pages freed by deletes that VACUUM would give back
+    pub freelist_count: i64,
+}
+
+// A row whose foreign key points at a missing row, from PRAGMA foreign_key_check
+#[derive(Debug, Clone, Serialize)]
+pub struct ForeignKeyViolation {
+    pub table: String,
+    pub rowid: Option<i64>,
+    pub parent: String,
+}
+
+// Rows that lost what they belong to, see Database::count_orphans
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct Orphans {
+    pub windows: u64,
+    pub keys: u64,
+    pub clicks: u64,
+}
+
+impl Orphans {
+    pub fn total(&self) -> u64 {
+        self.windows + self.keys + self.clicks
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ActivityStats {
     pub total_keystrokes: i64,
+    // Backspace/delete presses included in total_keystrokes
+    #[serde(default)]
+    pub total_corrections: i64,
+    // Characters inserted by pastes, kept out of total_keystrokes
+    #[serde(default)]
+    pub total_pasted_characters: i64,
     pub total_clicks: i64,
+    // Scroll events and the wheel distance scrolled horizontally/vertically
+    #[serde(default)]
+    pub total_scrolls: i64,
+    #[serde(default)]
+    pub total_scroll_distance_x: f64,
+    #[serde(default)]
+    pub total_scroll_distance_y: f64,
     pub total_windows: i64,
     pub total_processes: i64,
     pub session_duration: i64,
     pub most_active_process: Option<String>,
     pub most_active_window: Option<String>,
+    // Filled in by reports that evaluate the configured custom metrics
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_metrics: Vec<CustomMetricValue>,
+}
+
+impl ActivityStats {
+    // Backspace/delete presses as a percentage of all keystrokes
+    pub fn correction_rate(&self) -> f64 {
+        if self.total_keystrokes == 0 {
+            0.0
+        } else {
+            self.total_corrections as f64 / self.total_keystrokes as f64 * 100.0
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DailySummary {
+    pub date: NaiveDate,
+    pub active_seconds: i64,
+    pub keystrokes: i64,
+    pub clicks: i64,
+}
+
+// A window whose stored tags differ from what the current category rules
+// give, see `selfstats reclassify`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowTagChange {
+    pub window_id: i64,
+    pub created_at: DateTime<Utc>,
+    pub process: String,
+    pub title: String,
+    pub old: WindowTags,
+    pub new: WindowTags,
+}
+
+// Rows of one table recorded on one local day and a checksum of their
+// contents, for comparing copies of a database, see Database::get_table_days
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableDay {
+    pub table: String,
+    pub day: NaiveDate,
+    pub rows: i64,
+    // Independent of row order and ids, so a merged copy checksums the same
+    pub checksum: u64,
+}
+
+// Rows carried over from an original selfspy database, or that would be in a
+// dry run, see Database::import_legacy
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LegacyImport {
+    pub processes: u64,
+    pub windows: u64,
+    pub key_batches: u64,
+    pub keystrokes: i64,
+    pub clicks: u64,
+    pub scrolls: u64,
+    // Key batches whose typed text came along
+    pub texts: u64,
+}
+
+// Typed text moved to a new key, see encryption::change_password
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Rekey {
+    pub reencrypted: u64,
+    // Batches the old key couldn't decrypt, left as they were: recorded
+    // before keys were stored, or as plain text with encryption off
+    pub skipped: u64,
+}
+
+// What one retention pass removed, see Database::prune_before
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PruneSummary {
+    // Events from before local midnight of this day were deleted
+    pub cutoff: NaiveDate,
+    pub rows_deleted: u64,
+    // Days whose totals were stored in daily_rollups
+    pub days_rolled_up: u64,
+}
+
+// Input and time for one process over a range, see Database::get_process_stats
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct ProcessStats {
+    pub process: String,
+    pub keystrokes: i64,
+    pub clicks: i64,
+    // Windows (focus changes) recorded for the process
+    pub windows: i64,
+    // Timed like `distractions::visit_seconds`
+    pub active_seconds: i64,
+}
+
+// Time in one app on one local day, with nothing about the windows themselves
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AppDay {
+    pub date: NaiveDate,
+    pub process: String,
+    pub seconds: i64,
+}
+
+// Times a bigram or trigram was typed and the summed transition time, see
+// typing_practice
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct NgramTiming {
+    pub ngram: String,
+    pub count: i64,
+    pub interval_sum_ms: i64,
+}
+
+impl NgramTiming {
+    pub fn mean_ms(&self) -> f64 {
+        if self.count == 0 {
+            0.0
+        } else {
+            self.interval_sum_ms as f64 / self.count as f64
+        }
+    }
 }
\ No newline at end of file
@@ -1,128 +1,1270 @@
-use anyhow::Result;
-use chrono::Utc;
+use crate::error::{ErrorKind, Result, SelfspyError};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::Path;
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, RwLock};
 use tokio::time;
-use tracing::{info, debug, error};
+use tokio_util::sync::CancellationToken;
+use tracing::{info, debug, error, warn};
 
-use crate::{Config, Database};
-use crate::encryption::Encryptor;
-use crate::platform::{create_tracker, PlatformTracker, WindowInfo, InputEvent, MouseButton};
+use crate::{accounts, after_hours, aggregate, alerts, calendar, categories, files, Config, Database};
+use crate::db::PendingWrite;
+use crate::clock::{Clock, SystemClock};
+use crate::encryption::{self, Encryptor, KeyStorage, Zeroize, Zeroizing};
+use crate::fatigue::TypingRhythm;
+use crate::key_count::{self, KeyCounts};
+use crate::input_guard::{InputGuard, Verdict};
+use crate::notifications::{Notification, Notifier};
+use crate::pipeline::{self, InputCollector, PipelineEvent, SessionWatcher, Source, WindowWatcher};
+use crate::titles::TitleNormalizer;
+use crate::typing_practice::NgramTimer;
+use crate::platform::{av_usage, create_tracker, Capabilities, DeviceId, disk, display, focus, media, power, screen_share, session, PlatformTracker, WindowInfo, InputEvent};
+use crate::platform::display::DisplayInfo;
+use crate::platform::media::NowPlaying;
+use crate::platform::session::SessionEvent;
+use crate::platform::power::PowerState;
+use crate::platform::supervisor::SupervisedTracker;
 
-pub struct ActivityMonitor {
+// How often (in loop ticks) the OS Do Not Disturb state is re-checked
+const DND_POLL_TICKS: u64 = 30;
+// Screen sharing is checked more often since it gates text capture
+const PRESENTING_POLL_TICKS: u64 = 5;
+// Display layout (for click coordinate normalization) rarely changes
+const DISPLAY_POLL_TICKS: u64 = 30;
+// Idle time may come from a D-Bus round trip on Linux; a few seconds of lag is fine
+const IDLE_POLL_TICKS: u64 = 5;
+// Lock screen probes shell out on every platform
+const LOCK_POLL_TICKS: u64 = 5;
+// Tracks rarely change more often than every few minutes
+const MEDIA_POLL_TICKS: u64 = 10;
+// Microphone and camera checks can shell out; meetings last minutes
+const MEETING_POLL_TICKS: u64 = 10;
+// A percent of charge takes minutes; plugging in is caught within a minute
+const POWER_POLL_TICKS: u64 = 60;
+// Charge changes alone are sampled at most this often
+const POWER_SAMPLE_INTERVAL: Duration = Duration::from_secs(300);
+// Free disk space shrinks slowly; a failed write enters low-disk mode at once anyway
+const DISK_POLL_TICKS: u64 = 60;
+// Free space that ends low-disk mode entered by a full disk when
+// min_free_disk_mb doesn't set a higher bar
+const DISK_FULL_RECOVERY_MB: u64 = 64;
+// Without sleep signals, a tick arriving this much later than expected means
+// the machine was asleep. Generous, since a loaded or swapping machine can
+// stall the loop for a minute or more while awake.
+const SLEEP_GAP_SECONDS: i64 = 600;
+// Alerts held by quiet hours or rate limits go out within a minute of being allowed
+const NOTIFICATION_FLUSH_TICKS: u64 = 60;
+// How long shutdown waits for notifications still being delivered
+const NOTIFICATION_SETTLE_TIMEOUT: Duration = Duration::from_secs(15);
+// After-hours totals only need to be re-checked every few minutes
+const AFTER_HOURS_POLL_TICKS: u64 = 300;
+// The daily jobs run at most once a day; checking hourly catches the day change
+const DAILY_POLL_TICKS: u64 = 3600;
+// Alert rules read the database; a minute's delay is fine for thresholds in minutes
+const ALERT_POLL_TICKS: u64 = 60;
+// No input for this long while windows keep changing suggests the OS
+// revoked our input permissions
+const INPUT_STALL_SECONDS: u64 = 300;
+const INPUT_STALL_WINDOW_CHANGES: u32 = 3;
+// Lock file in the data directory, held for as long as a monitor records
+const MONITOR_LOCK: &str = "monitor.lock";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MonitorStatus {
+    pub running: bool,
+    pub do_not_disturb: bool,
+    pub presenting: bool,
+    pub input_stalled: bool,
+    pub idle: bool,
+    pub paused: bool,
+    pub locked: bool,
+    // Free space fell below `min_free_disk_mb`; only counts are recorded
+    pub low_disk: bool,
+    // The microphone or a camera is in use (with `track_meetings`)
+    pub in_meeting: bool,
+    // This account's daily active time is shared with an administrator, see accounts.rs
+    pub shared_with_admin: bool,
+    // Tracking backend in use and what it can record
+    pub backend: String,
+    pub capabilities: Capabilities,
+}
+
+// Takes the data directory's monitor lock, or fails with MonitorRunning.
+// The monitor holds it while it records; commands that replace the key or
+// the database file take it too, so they never run underneath a monitor
+// that would go on writing with the old key or into the old file.
+pub fn lock_data_dir(data_dir: &Path) -> Result<File> {
+    files::try_lock(&data_dir.join(MONITOR_LOCK))?.ok_or(SelfspyError::MonitorRunning)
+}
+
+// Keys, clicks and scrolls counted per input device
+type DeviceCounts = HashMap<Arc<DeviceId>, (i64, i64, i64)>;
+// Open row in `periods` and the time of the latest input in it
+type OpenActivityPeriod = (i64, DateTime<Utc>);
+
+struct InputHealth {
+    last_input: Instant,
+    window_changes: u32,
+    stalled: bool,
+}
+
+impl InputHealth {
+    fn new(now: Instant) -> Self {
+        Self {
+            last_input: now,
+            window_changes: 0,
+            stalled: false,
+        }
+    }
+}
+
+// Generic over the tracker so embedders can inject their own statically;
+// the default `ActivityMonitor` uses the boxed, supervised platform tracker.
+pub struct ActivityMonitor<T: PlatformTracker + ?Sized + 'static = dyn PlatformTracker> {
     config: Config,
     db: Arc<Database>,
-    tracker: Box<dyn PlatformTracker>,
+    tracker: Arc<T>,
+    sources: Vec<Arc<dyn Source>>,
     encryptor: Option<Encryptor>,
+    titles: TitleNormalizer,
+    notifier: Notifier,
+    clock: Arc<dyn Clock>,
     current_window: Arc<RwLock<Option<(i64, WindowInfo)>>>,
     keystroke_buffer: Arc<RwLock<String>>,
+    typing_rhythm: Arc<RwLock<TypingRhythm>>,
+    key_ngrams: Arc<RwLock<NgramTimer>>,
     running: Arc<RwLock<bool>>,
+    shutdown: CancellationToken,
+    key_counts: Arc<RwLock<KeyCounts>>,
+    displays: Arc<RwLock<Vec<DisplayInfo>>>,
+    last_mouse_position: Arc<RwLock<Option<(i32, i32)>>>,
+    mouse_movement: Arc<RwLock<(i64, i32)>>,
+    // Scroll events and absolute x/y distance since the last flush
+    scroll_events: Arc<RwLock<(i32, f64, f64)>>,
+    // Keys, clicks and scrolls per input device since the last flush, and
+    // the database id of each device seen
+    device_input: Arc<RwLock<DeviceCounts>>,
+    device_rows: Arc<RwLock<HashMap<Arc<DeviceId>, i64>>>,
+    do_not_disturb: Arc<RwLock<bool>>,
+    dnd_period: Arc<RwLock<Option<i64>>>,
+    presenting: Arc<RwLock<bool>>,
+    presenting_period: Arc<RwLock<Option<i64>>>,
+    idle: Arc<RwLock<bool>>,
+    idle_period: Arc<RwLock<Option<i64>>>,
+    activity_period: Arc<RwLock<Option<OpenActivityPeriod>>>,
+    paused: Arc<RwLock<bool>>,
+    paused_period: Arc<RwLock<Option<i64>>>,
+    // Latest window seen while paused, recorded when recording resumes
+    paused_window: Arc<RwLock<Option<WindowInfo>>>,
+    locked: Arc<RwLock<bool>>,
+    // Lock and sleep arrive as session manager signals, so neither is polled
+    session_signals: Arc<RwLock<bool>>,
+    in_meeting: Arc<RwLock<bool>>,
+    // Last recorded power state and when it was recorded
+    power: Arc<RwLock<Option<(PowerState, Instant)>>>,
+    low_disk: Arc<RwLock<bool>>,
+    low_disk_period: Arc<RwLock<Option<i64>>>,
+    // Open media_plays row and the track it belongs to
+    now_playing: Arc<RwLock<Option<(i64, NowPlaying)>>>,
+    // Wall-clock time of the previous tick, for spotting sleep
+    last_tick: Arc<RwLock<Option<DateTime<Utc>>>>,
+    input_health: Arc<RwLock<InputHealth>>,
+    input_guard: Arc<RwLock<InputGuard>>,
+    session_label: Option<String>,
+    session_id: Arc<RwLock<Option<i64>>>,
+    after_hours_alerted: Arc<RwLock<Option<NaiveDate>>>,
+    last_daily_run: Arc<RwLock<Option<NaiveDate>>>,
 }
 
 impl ActivityMonitor {
     pub async fn new(config: Config, password: Option<String>) -> Result<Self> {
+        let tracker: Arc<dyn PlatformTracker> = Arc::new(SupervisedTracker::new(create_tracker));
+        Self::with_tracker(config, password, tracker).await
+    }
+}
+
+impl<T: PlatformTracker + ?Sized + 'static> ActivityMonitor<T> {
+    pub async fn with_tracker(config: Config, password: Option<String>, tracker: Arc<T>) -> Result<Self> {
         config.ensure_directories()?;
         
-        let db = Arc::new(Database::new(&config.database_path).await?);
-        let tracker = create_tracker();
+        let db = Database::connect(&config.database_path, &config.database).await?;
+        let db = Arc::new(db.with_slow_query_threshold(config.slow_query_threshold()));
+        let sources: Vec<Arc<dyn Source>> = vec![
+            Arc::new(WindowWatcher::new(
+                tracker.clone(),
+                config.capture.clone(),
+                &config.volatile_title_patterns,
+            )),
+            Arc::new(InputCollector::new(tracker.clone())),
+            Arc::new(SessionWatcher),
+        ];
         
-        let encryptor = if config.encryption_enabled {
-            password.map(|p| Encryptor::new(&p).ok()).flatten()
-        } else {
-            None
+        let encryptor = match (config.encryption_enabled, config.key_storage, password.map(Zeroizing::new)) {
+            (true, KeyStorage::Platform, recovery) => {
+                Some(encryption::unlock_sealed(&db, recovery.as_deref().map_or("", String::as_str)).await?)
+            }
+            (true, KeyStorage::Password, Some(password)) => Some(encryption::unlock(&db, &password, config.kdf).await?),
+            _ => None,
         };
         
+        let titles = TitleNormalizer::new(&config.volatile_title_patterns);
+        let notifier = Notifier::from_settings(&config.notifications, &config.data_dir);
+        let input_guard = InputGuard::new(
+            config.max_keys_per_second,
+            config.max_clicks_per_second,
+            config.stuck_key_repeat_limit,
+        );
+        
         Ok(Self {
             config,
             db,
             tracker,
+            sources,
             encryptor,
+            titles,
+            notifier,
+            clock: Arc::new(SystemClock),
             current_window: Arc::new(RwLock::new(None)),
             keystroke_buffer: Arc::new(RwLock::new(String::new())),
+            typing_rhythm: Arc::new(RwLock::new(TypingRhythm::default())),
+            key_ngrams: Arc::new(RwLock::new(NgramTimer::default())),
             running: Arc::new(RwLock::new(false)),
+            shutdown: CancellationToken::new(),
+            key_counts: Arc::new(RwLock::new(KeyCounts::default())),
+            displays: Arc::new(RwLock::new(Vec::new())),
+            last_mouse_position: Arc::new(RwLock::new(None)),
+            mouse_movement: Arc::new(RwLock::new((0, 0))),
+            scroll_events: Arc::new(RwLock::new((0, 0.0, 0.0))),
+            device_input: Arc::new(RwLock::new(HashMap::new())),
+            device_rows: Arc::new(RwLock::new(HashMap::new())),
+            do_not_disturb: Arc::new(RwLock::new(false)),
+            dnd_period: Arc::new(RwLock::new(None)),
+            presenting: Arc::new(RwLock::new(false)),
+            presenting_period: Arc::new(RwLock::new(None)),
+            idle: Arc::new(RwLock::new(false)),
+            idle_period: Arc::new(RwLock::new(None)),
+            activity_period: Arc::new(RwLock::new(None)),
+            paused: Arc::new(RwLock::new(false)),
+            paused_period: Arc::new(RwLock::new(None)),
+            paused_window: Arc::new(RwLock::new(None)),
+            locked: Arc::new(RwLock::new(false)),
+            session_signals: Arc::new(RwLock::new(false)),
+            in_meeting: Arc::new(RwLock::new(false)),
+            power: Arc::new(RwLock::new(None)),
+            low_disk: Arc::new(RwLock::new(false)),
+            low_disk_period: Arc::new(RwLock::new(None)),
+            now_playing: Arc::new(RwLock::new(None)),
+            last_tick: Arc::new(RwLock::new(None)),
+            input_health: Arc::new(RwLock::new(InputHealth::new(Instant::now()))),
+            input_guard: Arc::new(RwLock::new(input_guard)),
+            session_label: None,
+            session_id: Arc::new(RwLock::new(None)),
+            after_hours_alerted: Arc::new(RwLock::new(None)),
+            last_daily_run: Arc::new(RwLock::new(None)),
         })
     }
     
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = notifier.with_clock(self.clock.clone());
+        self
+    }
+    
+    // Replaces the system clock, e.g. with a ManualClock to drive the idle,
+    // stall and sleep checks without waiting
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.input_health = Arc::new(RwLock::new(InputHealth::new(clock.instant())));
+        self.notifier = self.notifier.with_clock(clock.clone());
+        self.clock = clock;
+        self
+    }
+    
+    // Adds an extra event source (e.g. a plugin collector) to the pipeline
+    pub fn with_source(mut self, source: Arc<dyn Source>) -> Self {
+        self.sources.push(source);
+        self
+    }
+    
+    // Label stored on the session record, usable later as a report filter
+    pub fn with_session_label(mut self, label: Option<String>) -> Self {
+        self.session_label = label;
+        self
+    }
+    
     pub async fn start(&self) -> Result<()> {
-        info!("Starting activity monitor");
+        let _lock = lock_data_dir(&self.config.data_dir)?;
+        info!("Starting activity monitor with the {} backend", self.tracker.backend());
+        let missing = self.tracker.capabilities().missing();
+        if !missing.is_empty() {
+            warn!("The {} backend can't record {}", self.tracker.backend(), missing.join(", "));
+        }
+        
+        if let Some(consent) = accounts::read_consent(&self.config.data_dir) {
+            warn!(
+                "Daily active time for this account is shared with the computer's administrator (since {}); \
+                 run `selfspy share-screen-time revoke` to stop",
+                consent.granted_at.with_timezone(&chrono::Local).format("%Y-%m-%d")
+            );
+            self.notifier.notify(Notification::new(
+                "Screen time is shared",
+                "The administrator of this computer can see how long this account is active each day.",
+            ));
+        }
+        
+        // Holding the data directory lock means no other monitor is writing,
+        // so anything still open was left by one that crashed or was killed
+        let last_activity = self.db.get_last_activity().await?.unwrap_or_else(|| self.clock.now());
+        let closed = self.db.close_interrupted_spans(last_activity).await?;
+        if closed > 0 {
+            info!("Closed {} records left open by an unclean shutdown", closed);
+        }
         
         *self.running.write().await = true;
         self.tracker.start_input_tracking().await?;
         
-        // Simple main loop for now
+        let session_id = self.db.start_session(self.session_label.as_deref()).await?;
+        *self.session_id.write().await = Some(session_id);
+        
+        let result = self.run_loop().await;
+        
+        // Runs whether the loop was cancelled or failed, so buffered input
+        // and open records are never lost to an abort mid-write
+        let teardown = self.finish().await;
+        *self.running.write().await = false;
+        result.and(teardown)
+    }
+    
+    // The persister: drains source events into the database and runs the
+    // periodic state checks and flushes on its own tick
+    async fn run_loop(&self) -> Result<()> {
+        let (events_tx, mut events) = mpsc::channel(pipeline::EVENT_BUFFER);
+        let sources_cancel = self.shutdown.child_token();
+        let handles: Vec<_> = self
+            .sources
+            .iter()
+            .map(|source| pipeline::spawn_source(source.clone(), events_tx.clone(), sources_cancel.clone()))
+            .collect();
+        drop(events_tx);
+        
         let mut interval = time::interval(Duration::from_secs(1));
+        let mut ticks: u64 = 0;
+        let mut received_input = false;
         
-        while *self.running.read().await {
-            interval.tick().await;
-            
-            // Track window changes
-            if let Ok(window) = self.tracker.get_active_window().await {
-                let mut current = self.current_window.write().await;
-                
-                let should_update = current.as_ref()
-                    .map(|(_, w)| w.process_name != window.process_name || w.window_title != window.window_title)
-                    .unwrap_or(true);
-                
-                if should_update && !self.config.exclude_apps.contains(&window.process_name) {
-                    debug!("Window changed to: {} - {}", window.process_name, window.window_title);
-                    
-                    let process_id = self.db.insert_process(
-                        &window.process_name,
-                        window.bundle_id.as_deref()
-                    ).await?;
-                    
-                    let window_id = self.db.insert_window(
-                        process_id,
-                        &window.window_title,
-                        window.x,
-                        window.y,
-                        window.width,
-                        window.height,
-                    ).await?;
-                    
-                    *current = Some((window_id, window));
+        let result = loop {
+            // Cancellation is only observed between events, never inside a write
+            tokio::select! {
+                _ = self.shutdown.cancelled() => break Ok(()),
+                Some(event) = events.recv() => {
+                    if matches!(event, PipelineEvent::Input(_)) {
+                        received_input = true;
+                    }
+                    match self.handle_event(event).await {
+                        // A full disk degrades recording instead of stopping the monitor
+                        Err(e) if e.kind() == ErrorKind::DiskFull => {
+                            error!("Write failed, disk is full: {}", e);
+                            self.set_low_disk(true, "the disk is full".to_string()).await;
+                        }
+                        Err(e) => break Err(e),
+                        Ok(()) => {}
+                    }
                 }
+                _ = interval.tick() => {
+                    self.on_tick(ticks, received_input).await;
+                    received_input = false;
+                    ticks += 1;
+                }
+            }
+        };
+        
+        // Stop the sources and persist whatever they already queued; recv()
+        // returns None once every source task has dropped its sender
+        sources_cancel.cancel();
+        while let Some(event) = events.recv().await {
+            self.handle_event(event).await?;
+        }
+        for handle in handles {
+            let _ = handle.await;
+        }
+        
+        result
+    }
+    
+    // One pass of the checks run_loop makes every second; `ticks` counts the
+    // passes before it and picks which of the slower checks are due. Public
+    // so embedders and tests can drive a monitor on a ManualClock
+    pub async fn on_tick(&self, ticks: u64, received_input: bool) {
+        if let Err(e) = self.check_sleep().await {
+            error!("Failed to record sleep: {}", e);
+        }
+        if ticks.is_multiple_of(DND_POLL_TICKS) {
+            if let Err(e) = self.update_do_not_disturb().await {
+                error!("Failed to record Do Not Disturb state: {}", e);
+            }
+        }
+        if ticks.is_multiple_of(DISPLAY_POLL_TICKS) {
+            if let Ok(displays) = tokio::task::spawn_blocking(display::displays).await {
+                *self.displays.write().await = displays;
+            }
+        }
+        if self.config.track_presenting && ticks.is_multiple_of(PRESENTING_POLL_TICKS) {
+            if let Err(e) = self.update_presenting().await {
+                error!("Failed to record presenting state: {}", e);
+            }
+        }
+        if ticks.is_multiple_of(LOCK_POLL_TICKS) && !*self.session_signals.read().await {
+            if let Err(e) = self.update_locked().await {
+                error!("Failed to record screen lock state: {}", e);
+            }
+        }
+        if self.config.track_meetings && ticks.is_multiple_of(MEETING_POLL_TICKS) {
+            if let Err(e) = self.update_meeting().await {
+                error!("Failed to record meeting state: {}", e);
+            }
+        }
+        if self.config.track_power && ticks.is_multiple_of(POWER_POLL_TICKS) {
+            if let Err(e) = self.update_power().await {
+                error!("Failed to record power state: {}", e);
+            }
+        }
+        if self.config.track_media && ticks.is_multiple_of(MEDIA_POLL_TICKS) {
+            if let Err(e) = self.update_now_playing().await {
+                error!("Failed to record media playback: {}", e);
+            }
+        }
+        // A full disk enters low-disk mode even with the check disabled, and
+        // only this notices the space coming back
+        if (self.config.min_free_disk_mb > 0 || *self.low_disk.read().await) && ticks.is_multiple_of(DISK_POLL_TICKS) {
+            self.check_disk_space().await;
+        }
+        if ticks.is_multiple_of(IDLE_POLL_TICKS) {
+            if let Err(e) = self.update_idle().await {
+                error!("Failed to record idle state: {}", e);
+            }
+            if let Err(e) = self.save_activity_period().await {
+                error!("Failed to record activity period: {}", e);
+            }
+        }
+        if ticks.is_multiple_of(NOTIFICATION_FLUSH_TICKS) && !self.should_suppress_notifications() {
+            self.notifier.flush();
+        }
+        if self.config.after_hours_alert_minutes.is_some() && ticks.is_multiple_of(AFTER_HOURS_POLL_TICKS) {
+            if let Err(e) = self.check_after_hours().await {
+                error!("Failed to check after-hours activity: {}", e);
+            }
+        }
+        if ticks.is_multiple_of(DAILY_POLL_TICKS) {
+            if let Err(e) = self.run_daily_jobs().await {
+                error!("Failed to run the daily jobs: {}", e);
+            }
+        }
+        if !self.config.alert_rules.is_empty() && ticks.is_multiple_of(ALERT_POLL_TICKS) {
+            if let Err(e) = self.check_alert_rules().await {
+                error!("Failed to check alert rules: {}", e);
+            }
+        }
+        
+        self.check_input_health(received_input).await;
+        
+        // Flush keystrokes periodically
+        if let Err(e) = self.flush_keystrokes().await {
+            error!("Failed to flush keystrokes: {}", e);
+        }
+        
+        if let Err(e) = self.flush_typing_rhythm().await {
+            error!("Failed to flush typing rhythm: {}", e);
+        }
+        
+        if let Err(e) = self.flush_mouse_moves().await {
+            error!("Failed to flush mouse movement: {}", e);
+        }
+        
+        if let Err(e) = self.flush_scrolls().await {
+            error!("Failed to flush scroll events: {}", e);
+        }
+        
+        if let Err(e) = self.flush_device_input().await {
+            error!("Failed to flush per-device input counts: {}", e);
+        }
+        
+        // The rows queued above (and clicks) are committed together once per flush interval
+        if ticks.is_multiple_of(self.config.flush_interval_seconds.max(1)) {
+            match self.db.flush().await {
+                Err(e) if e.kind() == ErrorKind::DiskFull => {
+                    error!("Write failed, disk is full: {}", e);
+                    self.set_low_disk(true, "the disk is full".to_string()).await;
+                }
+                Err(e) => error!("Failed to write buffered input: {}", e),
+                Ok(_) => {}
+            }
+        }
+        
+        if let Err(e) = self.flush_diagnostics().await {
+            error!("Failed to record input diagnostics: {}", e);
+        }
+    }
+    
+    // Persists one event as run_loop does. Public, like on_tick, so tests
+    // can feed a monitor events without running its sources
+    pub async fn handle_event(&self, event: PipelineEvent) -> Result<()> {
+        // Lock and sleep are recorded while paused, as when they were polled
+        if let PipelineEvent::Session(event) = event {
+            return self.handle_session(event).await;
+        }
+        if *self.paused.read().await {
+            if let PipelineEvent::Window(window) = event {
+                *self.paused_window.write().await = Some(window);
+            }
+            return Ok(());
+        }
+        
+        match event {
+            PipelineEvent::Window(window) => self.handle_window(window).await,
+            PipelineEvent::Input(event) => self.handle_input(event).await,
+            PipelineEvent::Session(_) => Ok(()),
+        }
+    }
+    
+    async fn handle_window(&self, mut window: WindowInfo) -> Result<()> {
+        if *self.low_disk.read().await {
+            window.window_title = String::new();
+            window.document = None;
+        }
+        let mut current = self.current_window.write().await;
+        
+        // Titles that only differ in volatile parts (timers, counters) are the same window
+        let fingerprint = self.titles.fingerprint(&window.process_name, &window.window_title);
+        let should_update = current.as_ref()
+            .map(|(_, w)| self.titles.fingerprint(&w.process_name, &w.window_title) != fingerprint)
+            .unwrap_or(true);
+        
+        if should_update && !self.config.exclude_apps.contains(&window.process_name) {
+            debug!("Window changed to: {} - {}", window.process_name, window.window_title);
+            
+            let process_id = self.db.insert_process(
+                &window.process_name,
+                window.bundle_id.as_deref()
+            ).await?;
+            
+            // Rows queued for earlier windows are written first so ids and
+            // timestamps stay in the order things happened
+            self.db.flush().await?;
+            let window_id = self.db.insert_window(process_id, &window).await?;
+            
+            let tags = categories::classify(
+                &self.config.category_rules,
+                &window.process_name,
+                &window.window_title,
+                None,
+            );
+            if !tags.is_empty() {
+                self.db.set_window_tags(window_id, &tags).await?;
             }
             
-            // Process input events
-            let events = self.tracker.get_input_events();
-            for event in events {
-                match event {
-                    InputEvent::KeyPress { key } => {
-                        let mut buffer = self.keystroke_buffer.write().await;
-                        buffer.push_str(&key);
+            *current = Some((window_id, window));
+            self.input_health.write().await.window_changes += 1;
+        }
+        
+        Ok(())
+    }
+    
+    async fn handle_input(&self, event: InputEvent) -> Result<()> {
+        if !matches!(event, InputEvent::KeyRelease { .. }) {
+            self.mark_active().await?;
+        }
+        let device = event.device().cloned();
+        match event {
+            InputEvent::KeyPress { key, .. } => {
+                let capture = &self.config.capture;
+                if !capture.key_counts && !capture.key_text && !capture.key_ngrams {
+                    return Ok(());
+                }
+                
+                let now = self.clock.instant();
+                if self.input_guard.write().await.check_key(&key, now) == Verdict::Drop {
+                    return Ok(());
+                }
+                
+                // Timing and counts only, whatever the text capture settings.
+                // Pastes arrive as one event and say nothing about typing rhythm.
+                let low_disk = *self.low_disk.read().await;
+                if capture.key_counts {
+                    if !key_count::is_paste(&key) && !low_disk {
+                        self.typing_rhythm.write().await.record(&key, now);
                     }
-                    InputEvent::MouseClick { x, y, button } => {
-                        if let Some((window_id, _)) = *self.current_window.read().await {
-                            self.db.insert_click(window_id, x, y, button.as_str(), false).await?;
-                        }
+                    self.key_counts.write().await.record(&key);
+                    self.count_device_input(device, |counts| counts.0 += 1).await;
+                }
+                
+                let text_paused = self.text_capture_paused().await;
+                // Which keys follow which says something about the text, so
+                // n-grams stop whenever text capture does
+                if capture.key_ngrams {
+                    let mut ngrams = self.key_ngrams.write().await;
+                    if key_count::is_paste(&key) || low_disk || text_paused {
+                        ngrams.interrupt();
+                    } else {
+                        ngrams.record(&key, now);
                     }
-                    _ => {}
+                }
+                
+                // Keys typed while text capture is paused or disabled are counted but not stored
+                if capture.key_text && !low_disk && !text_paused {
+                    let mut buffer = self.keystroke_buffer.write().await;
+                    buffer.push_str(&key);
                 }
             }
-            
-            // Flush keystrokes periodically
-            if let Err(e) = self.flush_keystrokes().await {
-                error!("Failed to flush keystrokes: {}", e);
+            InputEvent::KeyRelease { key, .. } => {
+                if self.config.capture.key_counts && !*self.low_disk.read().await {
+                    self.typing_rhythm.write().await.release(&key, self.clock.instant());
+                }
+            }
+            InputEvent::MouseClick { x, y, button, .. } => {
+                if !self.config.capture.clicks {
+                    return Ok(());
+                }
+                
+                if self.input_guard.write().await.check_click(x, y, button.as_str(), self.clock.instant()) == Verdict::Drop {
+                    return Ok(());
+                }
+                self.count_device_input(device, |counts| counts.1 += 1).await;
+                
+                if let Some((window_id, _)) = *self.current_window.read().await {
+                    let point = display::normalize_point(
+                        &self.displays.read().await,
+                        x,
+                        y,
+                        display::NATIVE_SPACE,
+                    );
+                    self.db.queue(PendingWrite::Click {
+                        window_id,
+                        x: point.x,
+                        y: point.y,
+                        button: button.as_str().to_string(),
+                        double_click: false,
+                        scale: point.scale,
+                        at: self.clock.now(),
+                    });
+                }
+            }
+            InputEvent::MouseMove { x, y } => {
+                if self.config.capture.mouse_movement && !*self.low_disk.read().await {
+                    self.record_mouse_move(x, y).await;
+                }
+            }
+            InputEvent::MouseScroll { delta_x, delta_y, .. } => {
+                if self.config.capture.scrolls && !*self.low_disk.read().await {
+                    let mut scrolls = self.scroll_events.write().await;
+                    scrolls.0 += 1;
+                    scrolls.1 += delta_x.abs();
+                    scrolls.2 += delta_y.abs();
+                    drop(scrolls);
+                    self.count_device_input(device, |counts| counts.2 += 1).await;
+                }
             }
         }
         
         Ok(())
     }
     
+    // Wakes the loop immediately; start() then flushes and returns
     pub async fn stop(&self) -> Result<()> {
         info!("Stopping activity monitor");
-        *self.running.write().await = false;
+        self.shutdown.cancel();
+        Ok(())
+    }
+    
+    // Token cancelled by stop(), for embedders tying other tasks to the monitor
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
+    
+    async fn finish(&self) -> Result<()> {
         self.tracker.stop_input_tracking().await?;
         self.flush_keystrokes().await?;
+        self.flush_typing_rhythm().await?;
+        self.flush_mouse_moves().await?;
+        self.flush_scrolls().await?;
+        self.flush_device_input().await?;
+        self.db.flush().await?;
+        self.close_activity_period().await?;
+        
+        for period in [
+            &self.dnd_period,
+            &self.presenting_period,
+            &self.idle_period,
+            &self.paused_period,
+            &self.low_disk_period,
+        ] {
+            if let Some(period_id) = period.write().await.take() {
+                self.db.close_tagged_period(period_id).await?;
+            }
+        }
+        
+        if let Some((play_id, _)) = self.now_playing.write().await.take() {
+            self.db.close_media_play(play_id).await?;
+        }
+        
+        // Unlike a lock, a meeting can't be followed once we stop watching
+        if std::mem::take(&mut *self.in_meeting.write().await) {
+            self.db.insert_system_event("meeting_end", self.clock.now()).await?;
+        }
+        
+        if let Some(session_id) = self.session_id.write().await.take() {
+            self.db.end_session(session_id).await?;
+        }
+        
+        // Alerts already handed to a sink would otherwise die with the runtime
+        if time::timeout(NOTIFICATION_SETTLE_TIMEOUT, self.notifier.settle()).await.is_err() {
+            warn!("Gave up waiting for notifications to be delivered");
+        }
+        Ok(())
+    }
+    
+    pub async fn status(&self) -> MonitorStatus {
+        MonitorStatus {
+            running: *self.running.read().await,
+            do_not_disturb: *self.do_not_disturb.read().await,
+            presenting: *self.presenting.read().await,
+            input_stalled: self.input_health.read().await.stalled,
+            idle: *self.idle.read().await,
+            paused: *self.paused.read().await,
+            locked: *self.locked.read().await,
+            low_disk: *self.low_disk.read().await,
+            in_meeting: *self.in_meeting.read().await,
+            shared_with_admin: accounts::read_consent(&self.config.data_dir).is_some(),
+            backend: self.tracker.backend().to_string(),
+            capabilities: self.tracker.capabilities(),
+        }
+    }
+    
+    // Stops recording input and windows until resume(); the gap is stored as a
+    // "paused" period so reports can tell it apart from idle time
+    pub async fn pause(&self) -> Result<()> {
+        let mut paused = self.paused.write().await;
+        if *paused {
+            return Ok(());
+        }
+        *paused = true;
+        
+        // Whatever was typed before the pause still belongs to the current window
+        self.flush_keystrokes().await?;
+        self.flush_typing_rhythm().await?;
+        self.flush_mouse_moves().await?;
+        self.flush_scrolls().await?;
+        self.flush_device_input().await?;
+        self.db.flush().await?;
+        self.close_activity_period().await?;
+        
+        *self.paused_period.write().await = Some(self.db.open_tagged_period("paused").await?);
+        info!("Recording paused");
+        Ok(())
+    }
+    
+    pub async fn resume(&self) -> Result<()> {
+        let mut paused = self.paused.write().await;
+        if !*paused {
+            return Ok(());
+        }
+        *paused = false;
+        drop(paused);
+        
+        if let Some(period_id) = self.paused_period.write().await.take() {
+            self.db.close_tagged_period(period_id).await?;
+        }
+        // The window watcher only reports changes, so replay the last one it saw
+        if let Some(window) = self.paused_window.write().await.take() {
+            self.handle_window(window).await?;
+        }
+        *self.last_mouse_position.write().await = None;
+        info!("Recording resumed");
+        Ok(())
+    }
+    
+    pub async fn is_paused(&self) -> bool {
+        *self.paused.read().await
+    }
+    
+    // Drops a timeline marker at the current time, e.g. from a macro pad button
+    pub async fn annotate(&self, note: Option<&str>) -> Result<i64> {
+        let window_id = self.current_window.read().await.as_ref().map(|(id, _)| *id);
+        let marker_id = self.db.insert_marker(window_id, note).await?;
+        info!("Added timeline marker{}", note.map(|n| format!(": {}", n)).unwrap_or_default());
+        Ok(marker_id)
+    }
+    
+    // False when the notification won't go out, see `Notifier::notify`
+    fn notify(&self, notification: Notification) -> bool {
+        if self.should_suppress_notifications() {
+            debug!("Suppressed notification during Do Not Disturb: {}", notification.title);
+            return false;
+        }
+        self.notifier.notify(notification)
+    }
+    
+    // Warns once per week when after-hours activity crosses the configured limit
+    async fn check_after_hours(&self) -> Result<()> {
+        let Some(limit) = self.config.after_hours_alert_minutes else {
+            return Ok(());
+        };
+        
+        let first_day = self.config.first_day_of_week.weekday();
+        let week = calendar::week_start(self.clock.today(), first_day);
+        if *self.after_hours_alerted.read().await == Some(week) {
+            return Ok(());
+        }
+        
+        let activity = self.db.get_hourly_activity(week, week + chrono::Duration::days(7)).await?;
+        let totals = after_hours::weekly_after_hours(&activity, &self.config.working_hours, week, 1, first_day);
+        let minutes = totals.first().map(|t| t.total_minutes()).unwrap_or(0);
+        
+        if minutes > limit {
+            *self.after_hours_alerted.write().await = Some(week);
+            self.notify(Notification::new(
+                "After-hours work",
+                format!("{} minutes outside working hours this week (limit {})", minutes, limit),
+            ));
+        }
+        
+        Ok(())
+    }
+    
+    // Once per local day, the first time at startup: forgets the rare
+    // n-grams of the days before, publishes totals for aggregation (see
+    // aggregate.rs) and screen time for the administrator (see accounts.rs)
+    // and applies `retention_days`
+    async fn run_daily_jobs(&self) -> Result<()> {
+        let today = self.clock.today();
+        if *self.last_daily_run.read().await == Some(today) {
+            return Ok(());
+        }
+        *self.last_daily_run.write().await = Some(today);
+        
+        let forgotten = self.db.forget_rare_ngrams(today).await?;
+        if forgotten > 0 {
+            debug!("Forgot {} n-grams typed too rarely to keep", forgotten);
+        }
+        
+        if self.config.aggregation.publish {
+            aggregate::publish(&self.db, &self.config.aggregation, &self.config.data_dir, today).await?;
+        } else if aggregate::unpublish(&self.config.data_dir)? {
+            info!("Removed the totals published for aggregation, as aggregation.publish is off");
+        }
+        
+        if accounts::read_consent(&self.config.data_dir).is_some() {
+            accounts::publish(&self.db, &self.config.data_dir, today).await?;
+        } else if accounts::unpublish(&self.config.data_dir)? {
+            info!("Removed the screen time shared with the administrator, as sharing was revoked");
+        }
+        
+        let Some(days) = self.config.retention_days else {
+            return Ok(());
+        };
+        let cutoff = today - chrono::Duration::days(days.max(1));
+        let summary = self.db.prune_before(cutoff, self.config.keep_daily_rollups).await?;
+        if summary.rows_deleted > 0 {
+            info!(
+                "Pruned {} rows from before {} ({} days kept as daily totals)",
+                summary.rows_deleted, cutoff, summary.days_rolled_up
+            );
+        }
+        Ok(())
+    }
+    
+    // A rule counts as fired for the day only once its alert went out;
+    // during quiet hours or Do Not Disturb it is checked again afterwards
+    async fn check_alert_rules(&self) -> Result<()> {
+        let today = self.clock.today();
+        for rule in &self.config.alert_rules {
+            if self.notifier.is_quiet(rule.channel) || self.db.get_alert_fired_day(&rule.name).await? == Some(today) {
+                continue;
+            }
+            
+            let check = alerts::check(&self.db, rule, self.clock.now()).await?;
+            if check.fires && self.notify(rule.notification(&check)) {
+                info!("Alert rule fired: {}", rule.name);
+                self.db.record_alert_fired(&rule.name, today).await?;
+            }
+        }
+        Ok(())
+    }
+    
+    async fn check_input_health(&self, received_input: bool) {
+        let mut health = self.input_health.write().await;
+        
+        if received_input {
+            if health.stalled {
+                info!("Input events resumed");
+            }
+            health.last_input = self.clock.instant();
+            health.window_changes = 0;
+            health.stalled = false;
+            return;
+        }
+        
+        if health.stalled
+            || self.clock.instant().duration_since(health.last_input) < Duration::from_secs(INPUT_STALL_SECONDS)
+            || health.window_changes < INPUT_STALL_WINDOW_CHANGES
+        {
+            return;
+        }
+        
+        health.stalled = true;
+        warn!(
+            "No input events for {} minutes while {} window changes were seen; input permissions may have been revoked",
+            INPUT_STALL_SECONDS / 60,
+            health.window_changes
+        );
+        self.notify(Notification::new("Selfspy is not receiving input", permission_guidance()));
+    }
+    
+    // Low-disk mode keeps counts, clicks and window changes but drops key text,
+    // typing rhythm, titles, mouse movement and scrolls, the bulk of each day's
+    // rows. Recording resumes in full once free space is back above 1.5x the
+    // threshold, so hovering around it doesn't flap.
+    async fn check_disk_space(&self) {
+        let data_dir = self.config.data_dir.clone();
+        let Ok(Some(free)) = tokio::task::spawn_blocking(move || disk::free_bytes(&data_dir)).await else {
+            return;
+        };
+        let free_mb = free / (1024 * 1024);
+        let threshold = self.config.min_free_disk_mb;
+        
+        let low = *self.low_disk.read().await;
+        if !low && free_mb < threshold {
+            self.set_low_disk(true, format!("{} MB free, below the {} MB threshold", free_mb, threshold)).await;
+        } else if low && free_mb >= (threshold + threshold / 2).max(DISK_FULL_RECOVERY_MB) {
+            self.set_low_disk(false, format!("{} MB free", free_mb)).await;
+        }
+    }
+    
+    async fn set_low_disk(&self, active: bool, reason: String) {
+        let mut current = self.low_disk.write().await;
+        if *current == active {
+            return;
+        }
+        *current = active;
+        drop(current);
+        
+        if active {
+            warn!("Low disk space ({}); recording counts only", reason);
+            // Text typed so far can't be stored without the space it needs
+            self.keystroke_buffer.write().await.zeroize();
+            self.typing_rhythm.write().await.reset();
+            self.key_ngrams.write().await.reset();
+            self.notify(Notification::new(
+                "Selfspy is low on disk space",
+                format!("{}. Only keystroke and click counts are recorded until space is freed.", reason),
+            ));
+        } else {
+            info!("Disk space recovered ({}); recording everything again", reason);
+        }
+        
+        // The period row is tiny and usually still fits; losing it only loses the marker
+        if let Err(e) = self.set_tagged_period(&self.low_disk_period, "low_disk", active).await {
+            warn!("Failed to record low disk period: {}", e);
+        }
+    }
+    
+    pub async fn is_presenting(&self) -> bool {
+        *self.presenting.read().await
+    }
+    
+    async fn record_mouse_move(&self, x: i32, y: i32) {
+        let mut last = self.last_mouse_position.write().await;
+        
+        if let Some((last_x, last_y)) = *last {
+            let dx = (x - last_x) as f64;
+            let dy = (y - last_y) as f64;
+            let mut movement = self.mouse_movement.write().await;
+            movement.0 += (dx * dx + dy * dy).sqrt().round() as i64;
+            movement.1 += 1;
+        }
+        
+        *last = Some((x, y));
+    }
+    
+    async fn text_capture_paused(&self) -> bool {
+        self.config.pause_text_while_presenting && *self.presenting.read().await
+    }
+    
+    // True while the OS reports Focus / Do Not Disturb; notification senders
+    // should stay quiet when this is set and `respect_do_not_disturb` is on.
+    pub async fn is_do_not_disturb(&self) -> bool {
+        *self.do_not_disturb.read().await
+    }
+    
+    pub fn should_suppress_notifications(&self) -> bool {
+        self.config.respect_do_not_disturb
+            && self.do_not_disturb.try_read().map(|dnd| *dnd).unwrap_or(false)
+    }
+    
+    async fn update_do_not_disturb(&self) -> Result<()> {
+        // Probing may shell out to the OS, keep it off the async workers
+        let active = tokio::task::spawn_blocking(focus::is_do_not_disturb_active)
+            .await
+            .unwrap_or(false);
+        
+        let mut current = self.do_not_disturb.write().await;
+        if *current == active {
+            return Ok(());
+        }
+        *current = active;
+        info!("Do Not Disturb {}", if active { "enabled" } else { "disabled" });
+        
+        if !self.config.tag_do_not_disturb_periods {
+            return Ok(());
+        }
+        
+        self.set_tagged_period(&self.dnd_period, "do_not_disturb", active).await
+    }
+    
+    async fn update_presenting(&self) -> Result<()> {
+        let active = tokio::task::spawn_blocking(screen_share::is_screen_shared)
+            .await
+            .unwrap_or(false);
+        
+        let mut current = self.presenting.write().await;
+        if *current == active {
+            return Ok(());
+        }
+        *current = active;
+        info!("Screen sharing {}", if active { "started" } else { "stopped" });
+        
+        self.set_tagged_period(&self.presenting_period, "presenting", active).await
+    }
+    
+    // Idle once the OS reports no input for `idle_timeout_seconds`. Platforms
+    // that can't tell fall back to the time since our last recorded input.
+    async fn update_idle(&self) -> Result<()> {
+        let tracker = self.tracker.clone();
+        let idle_seconds = match tokio::task::spawn_blocking(move || tracker.idle_seconds()).await {
+            Ok(Some(seconds)) => seconds,
+            _ => self
+                .clock
+                .instant()
+                .duration_since(self.input_health.read().await.last_input)
+                .as_secs(),
+        };
+        let active = idle_seconds >= self.config.idle_timeout_seconds;
+        
+        let mut current = self.idle.write().await;
+        if *current == active {
+            return Ok(());
+        }
+        *current = active;
+        info!("User {}", if active { "went idle" } else { "is back" });
+        if active {
+            self.close_activity_period().await?;
+        }
+        
+        self.set_tagged_period(&self.idle_period, "idle", active).await
+    }
+    
+    // Any input opens an activity period, or extends the open one unless
+    // `idle_timeout_seconds` passed since the last input, in which case that
+    // period ends at the last input and a new one starts
+    async fn mark_active(&self) -> Result<()> {
+        let now = self.clock.now();
+        let mut current = self.activity_period.write().await;
+        match current.as_mut() {
+            Some((_, last))
+                if (now - *last).num_seconds() < self.config.idle_timeout_seconds as i64 =>
+            {
+                *last = now;
+                return Ok(());
+            }
+            Some((period_id, last)) => self.db.extend_period(*period_id, *last).await?,
+            None => {}
+        }
+        *current = Some((self.db.open_period(now).await?, now));
+        Ok(())
+    }
+    
+    // Writes the latest input time of the open period so a crash loses at
+    // most one idle poll of active time
+    async fn save_activity_period(&self) -> Result<()> {
+        if let Some((period_id, last)) = *self.activity_period.read().await {
+            self.db.extend_period(period_id, last).await?;
+        }
+        Ok(())
+    }
+    
+    async fn close_activity_period(&self) -> Result<()> {
+        if let Some((period_id, last)) = self.activity_period.write().await.take() {
+            self.db.extend_period(period_id, last).await?;
+        }
+        Ok(())
+    }
+    
+    // Nothing is recorded while paused; a track still playing afterwards
+    // starts a new row on the next poll
+    async fn update_now_playing(&self) -> Result<()> {
+        let playing = if *self.paused.read().await {
+            None
+        } else {
+            tokio::task::spawn_blocking(media::now_playing).await.ok().flatten()
+        };
+        
+        let mut current = self.now_playing.write().await;
+        if current.as_ref().map(|(_, track)| track) == playing.as_ref() {
+            return Ok(());
+        }
+        
+        if let Some((play_id, _)) = current.take() {
+            self.db.close_media_play(play_id).await?;
+        }
+        if let Some(track) = playing {
+            debug!("Now playing: {} ({})", track.title, track.player);
+            let play_id = self
+                .db
+                .open_media_play(&track.player, &track.title, track.artist.as_deref())
+                .await?;
+            *current = Some((play_id, track));
+        }
+        
+        Ok(())
+    }
+    
+    async fn handle_session(&self, event: SessionEvent) -> Result<()> {
+        match event {
+            SessionEvent::Watching(watching) => {
+                *self.session_signals.write().await = watching;
+                Ok(())
+            }
+            SessionEvent::Locked(locked) => self.set_locked(locked).await,
+            SessionEvent::Sleeping(true) => {
+                info!("System going to sleep");
+                self.db.insert_system_event("sleep", self.clock.now()).await?;
+                Ok(())
+            }
+            SessionEvent::Sleeping(false) => {
+                info!("System woke up");
+                self.db.insert_system_event("wake", self.clock.now()).await?;
+                Ok(())
+            }
+        }
+    }
+    
+    // Fallback for platforms without lock signals
+    async fn update_locked(&self) -> Result<()> {
+        let active = tokio::task::spawn_blocking(session::is_screen_locked)
+            .await
+            .unwrap_or(false);
+        self.set_locked(active).await
+    }
+    
+    // Lock and unlock are stored as system events rather than tagged periods:
+    // a lock can outlive the monitor (logout, shutdown), and reports pair them up
+    async fn set_locked(&self, active: bool) -> Result<()> {
+        let mut current = self.locked.write().await;
+        if *current == active {
+            return Ok(());
+        }
+        *current = active;
+        info!("Screen {}", if active { "locked" } else { "unlocked" });
+        
+        let kind = if active { "lock" } else { "unlock" };
+        self.db.insert_system_event(kind, self.clock.now()).await?;
+        Ok(())
+    }
+    
+    // Meetings are stored as system events next to lock and sleep, so the
+    // away and meeting reports read one table
+    async fn update_meeting(&self) -> Result<()> {
+        let usage = tokio::task::spawn_blocking(av_usage::av_usage)
+            .await
+            .unwrap_or_default();
+        let active = usage.any();
+        
+        let mut current = self.in_meeting.write().await;
+        if *current == active {
+            return Ok(());
+        }
+        *current = active;
+        info!(
+            "Meeting {} (microphone {}, camera {})",
+            if active { "started" } else { "ended" },
+            if usage.microphone { "on" } else { "off" },
+            if usage.camera { "on" } else { "off" }
+        );
+        
+        let kind = if active { "meeting_start" } else { "meeting_end" };
+        self.db.insert_system_event(kind, self.clock.now()).await?;
+        Ok(())
+    }
+    
+    // Records plugging in and out right away, and the draining (or charging)
+    // battery every few minutes. Machines without a battery record nothing.
+    async fn update_power(&self) -> Result<()> {
+        let now = self.clock.instant();
+        let Some(state) = tokio::task::spawn_blocking(power::power_state).await.ok().flatten() else {
+            return Ok(());
+        };
+        
+        let mut last = self.power.write().await;
+        let record = match &*last {
+            None => true,
+            Some((previous, at)) => {
+                previous.on_battery != state.on_battery
+                    || (previous.percent != state.percent && now.duration_since(*at) >= POWER_SAMPLE_INTERVAL)
+            }
+        };
+        if !record {
+            return Ok(());
+        }
+        
+        if last.as_ref().is_some_and(|(previous, _)| previous.on_battery != state.on_battery) {
+            info!("Running on {}", if state.on_battery { "battery" } else { "mains power" });
+        }
+        self.db.insert_power_sample(state.on_battery, state.percent).await?;
+        *last = Some((state, now));
+        Ok(())
+    }
+    
+    // Fallback for platforms without sleep signals. The tick interval is
+    // monotonic and stops while the machine sleeps, so a large jump in
+    // wall-clock time between ticks is a suspend. The sleep is dated to the
+    // last tick before it, the wake to now.
+    async fn check_sleep(&self) -> Result<()> {
+        let now = self.clock.now();
+        let previous = self.last_tick.write().await.replace(now);
+        if *self.session_signals.read().await {
+            return Ok(());
+        }
+        
+        if let Some(previous) = previous {
+            if (now - previous).num_seconds() >= SLEEP_GAP_SECONDS {
+                info!("System slept from {} to {}", previous, now);
+                self.db.insert_system_event("sleep", previous).await?;
+                self.db.insert_system_event("wake", now).await?;
+            }
+        }
+        
+        Ok(())
+    }
+    
+    async fn set_tagged_period(&self, period: &RwLock<Option<i64>>, kind: &str, active: bool) -> Result<()> {
+        let mut period = period.write().await;
+        if active {
+            if period.is_none() {
+                *period = Some(self.db.open_tagged_period(kind).await?);
+            }
+        } else if let Some(period_id) = period.take() {
+            self.db.close_tagged_period(period_id).await?;
+        }
+        
         Ok(())
     }
     
     async fn flush_keystrokes(&self) -> Result<()> {
         let mut buffer = self.keystroke_buffer.write().await;
+        let mut counts = self.key_counts.write().await;
         
-        if buffer.is_empty() {
+        // Text is stored with zero counts when only key_text is captured
+        if counts.is_empty() && buffer.is_empty() {
             return Ok(());
         }
         
@@ -133,13 +1275,130 @@ impl ActivityMonitor {
                 buffer.as_bytes().to_vec()
             };
             
-            let key_count = buffer.len() as i32;
-            self.db.insert_keys(window_id, key_data, key_count).await?;
+            self.db.queue(PendingWrite::Keys {
+                window_id,
+                encrypted_keys: key_data,
+                counts: *counts,
+                at: self.clock.now(),
+            });
             
-            debug!("Flushed {} keystrokes", key_count);
-            buffer.clear();
+            debug!(
+                "Queued {} keystrokes ({} characters typed, {} pasted)",
+                counts.events, counts.characters, counts.pasted
+            );
+            // Wiped rather than cleared, so the text doesn't linger in memory
+            buffer.zeroize();
+            *counts = KeyCounts::default();
         }
         
         Ok(())
     }
+    
+    async fn flush_typing_rhythm(&self) -> Result<()> {
+        let mut rhythm = self.typing_rhythm.write().await;
+        if !rhythm.is_empty() {
+            self.db.queue(PendingWrite::TypingRhythm { rhythm: rhythm.take(), at: self.clock.now() });
+        }
+        drop(rhythm);
+        
+        let mut ngrams = self.key_ngrams.write().await;
+        if !ngrams.is_empty() {
+            self.db.queue(PendingWrite::KeyNgrams { timer: ngrams.take(), at: self.clock.now() });
+        }
+        
+        Ok(())
+    }
+    
+    async fn flush_mouse_moves(&self) -> Result<()> {
+        let mut movement = self.mouse_movement.write().await;
+        
+        if movement.1 == 0 {
+            return Ok(());
+        }
+        
+        if let Some((window_id, _)) = *self.current_window.read().await {
+            let (distance, event_count) = *movement;
+            self.db.queue(PendingWrite::MouseMoves { window_id, distance, event_count, at: self.clock.now() });
+            *movement = (0, 0);
+        }
+        
+        Ok(())
+    }
+    
+    async fn flush_scrolls(&self) -> Result<()> {
+        let mut scrolls = self.scroll_events.write().await;
+        
+        if scrolls.0 == 0 {
+            return Ok(());
+        }
+        
+        if let Some((window_id, _)) = *self.current_window.read().await {
+            let (event_count, distance_x, distance_y) = *scrolls;
+            self.db.queue(PendingWrite::Scrolls {
+                window_id,
+                event_count,
+                distance_x,
+                distance_y,
+                at: self.clock.now(),
+            });
+            *scrolls = (0, 0.0, 0.0);
+        }
+        
+        Ok(())
+    }
+    
+    // Tallies an event against its device, on backends that report devices
+    async fn count_device_input(&self, device: Option<Arc<DeviceId>>, count: impl FnOnce(&mut (i64, i64, i64))) {
+        if let Some(device) = device {
+            count(self.device_input.write().await.entry(device).or_default());
+        }
+    }
+    
+    async fn flush_device_input(&self) -> Result<()> {
+        let counts = std::mem::take(&mut *self.device_input.write().await);
+        for (device, (keys, clicks, scrolls)) in counts {
+            let cached = self.device_rows.read().await.get(&device).copied();
+            let device_id = match cached {
+                Some(id) => id,
+                None => {
+                    let id = self
+                        .db
+                        .insert_input_device(&device.name, device.kind.as_str(), device.built_in)
+                        .await?;
+                    self.device_rows.write().await.insert(device, id);
+                    id
+                }
+            };
+            self.db.queue(PendingWrite::DeviceInput { device_id, keys, clicks, scrolls, at: self.clock.now() });
+        }
+        
+        Ok(())
+    }
+    
+    async fn flush_diagnostics(&self) -> Result<()> {
+        let anomalies = self.input_guard.write().await.take_anomalies();
+        if anomalies.is_empty() {
+            return Ok(());
+        }
+        
+        let window_id = self.current_window.read().await.as_ref().map(|(id, _)| *id);
+        for anomaly in anomalies {
+            warn!("Dropped {} implausible input events: {}", anomaly.dropped, anomaly.detail);
+            self.db
+                .insert_diagnostic(anomaly.kind.as_str(), window_id, anomaly.dropped as i64, &anomaly.detail)
+                .await?;
+        }
+        
+        Ok(())
+    }
+}
+
+fn permission_guidance() -> &'static str {
+    if cfg!(target_os = "macos") {
+        "Keyboard and mouse events stopped arriving. Re-enable Selfspy under System Settings > Privacy & Security > Accessibility and Input Monitoring, then restart monitoring (see `selfspy check-permissions`)."
+    } else if cfg!(target_os = "linux") {
+        "Keyboard and mouse events stopped arriving. Check that the X server is reachable and that your user can read input devices (see `selfspy check-input`), then restart monitoring."
+    } else {
+        "Keyboard and mouse events stopped arriving. Restart monitoring, and check that security software isn't blocking input hooks."
+    }
 }
\ No newline at end of file
@@ -0,0 +1,243 @@
+// Publishes the monitor's state and today's totals to an MQTT broker, with
+// Home Assistant discovery messages so the sensors show up on their own.
+//
+// Topics, with <node> from MqttSettings::node():
+//   selfspy/<node>/availability   "online" / "offline" (broker-side last will)
+//   selfspy/<node>/state          JSON: activity, present, deep_work, totals
+//   homeassistant/.../config      discovery, retained
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "mqtt")]
+use {
+    crate::deep_work,
+    crate::error::{Result, SelfspyError},
+    crate::platform::PlatformTracker,
+    crate::{ActivityMonitor, Config, Database},
+    chrono::{Duration as ChronoDuration, Local},
+    std::sync::Arc,
+    std::time::Duration,
+    tracing::{info, warn},
+};
+
+// Settings are always available so config.json round-trips; publishing needs the `mqtt` feature
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    // Kept in config.json, which Config::save writes readable by its owner only
+    pub password: Option<String>,
+    // Distinguishes computers sharing a broker; defaults to the host name
+    pub node_id: Option<String>,
+    pub topic_prefix: String,
+    pub discovery_prefix: String,
+    pub publish_interval_seconds: u64,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            host: "localhost".to_string(),
+            port: 1883,
+            username: None,
+            password: None,
+            node_id: None,
+            topic_prefix: "selfspy".to_string(),
+            discovery_prefix: "homeassistant".to_string(),
+            publish_interval_seconds: 60,
+        }
+    }
+}
+
+impl MqttSettings {
+    // Lowercase letters, digits and underscores, as Home Assistant object ids require
+    pub fn node(&self) -> String {
+        let raw = self
+            .node_id
+            .clone()
+            .or_else(|| std::env::var("HOSTNAME").ok())
+            .or_else(|| std::env::var("COMPUTERNAME").ok())
+            .unwrap_or_else(|| "desktop".to_string());
+        raw.to_lowercase()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect()
+    }
+}
+
+// Entities announced through discovery: (component, object id, name, extra config)
+#[cfg(feature = "mqtt")]
+fn entities() -> Vec<(&'static str, &'static str, &'static str, serde_json::Value)> {
+    vec![
+        (
+            "sensor",
+            "activity",
+            "Activity",
+            serde_json::json!({ "value_template": "{{ value_json.activity }}", "icon": "mdi:keyboard" }),
+        ),
+        (
+            "binary_sensor",
+            "presence",
+            "Presence",
+            serde_json::json!({
+                "value_template": "{{ 'ON' if value_json.present else 'OFF' }}",
+                "device_class": "presence",
+            }),
+        ),
+        (
+            "binary_sensor",
+            "deep_work",
+            "Deep work",
+            serde_json::json!({
+                "value_template": "{{ 'ON' if value_json.deep_work else 'OFF' }}",
+                "icon": "mdi:head-lightbulb",
+            }),
+        ),
+        (
+            "sensor",
+            "keystrokes_today",
+            "Keystrokes today",
+            serde_json::json!({ "value_template": "{{ value_json.keystrokes_today }}", "state_class": "total_increasing" }),
+        ),
+        (
+            "sensor",
+            "clicks_today",
+            "Clicks today",
+            serde_json::json!({ "value_template": "{{ value_json.clicks_today }}", "state_class": "total_increasing" }),
+        ),
+        (
+            "sensor",
+            "active_minutes_today",
+            "Active time today",
+            serde_json::json!({
+                "value_template": "{{ value_json.active_minutes_today }}",
+                "unit_of_measurement": "min",
+                "device_class": "duration",
+                "state_class": "total_increasing",
+            }),
+        ),
+    ]
+}
+
+// Publishes until the monitor shuts down, then marks the node offline
+#[cfg(feature = "mqtt")]
+pub async fn publish<T: PlatformTracker + ?Sized + 'static>(
+    monitor: Arc<ActivityMonitor<T>>,
+    config: Config,
+    settings: MqttSettings,
+) -> Result<()> {
+    use rumqttc::{AsyncClient, LastWill, MqttOptions, QoS};
+    
+    let node = settings.node();
+    let base = format!("{}/{}", settings.topic_prefix, node);
+    let availability_topic = format!("{}/availability", base);
+    let state_topic = format!("{}/state", base);
+    
+    let mut options = MqttOptions::new(format!("selfspy-{}", node), &settings.host, settings.port);
+    options.set_keep_alive(Duration::from_secs(30));
+    options.set_last_will(LastWill::new(&availability_topic, "offline", QoS::AtLeastOnce, true));
+    if let Some(username) = &settings.username {
+        options.set_credentials(username, settings.password.clone().unwrap_or_default());
+    }
+    
+    let (client, mut event_loop) = AsyncClient::new(options, 16);
+    // rumqttc reconnects on the next poll after an error; just keep polling
+    let connection = tokio::spawn(async move {
+        loop {
+            if let Err(e) = event_loop.poll().await {
+                warn!("MQTT connection error: {}", e);
+                tokio::time::sleep(Duration::from_secs(5)).await;
+            }
+        }
+    });
+    
+    let mqtt_error = |e: rumqttc::ClientError| SelfspyError::Mqtt(e.to_string());
+    let device = serde_json::json!({
+        "identifiers": [format!("selfspy_{}", node)],
+        "name": format!("Selfspy ({})", node),
+        "manufacturer": "selfspy",
+    });
+    for (component, object_id, name, mut entity) in entities() {
+        entity["name"] = name.into();
+        entity["unique_id"] = format!("selfspy_{}_{}", node, object_id).into();
+        entity["state_topic"] = state_topic.clone().into();
+        entity["availability_topic"] = availability_topic.clone().into();
+        entity["device"] = device.clone();
+        let topic = format!("{}/{}/{}/{}/config", settings.discovery_prefix, component, node, object_id);
+        client
+            .publish(topic, QoS::AtLeastOnce, true, entity.to_string())
+            .await
+            .map_err(mqtt_error)?;
+    }
+    client
+        .publish(&availability_topic, QoS::AtLeastOnce, true, "online")
+        .await
+        .map_err(mqtt_error)?;
+    info!("Publishing activity to MQTT broker {}:{} as {}", settings.host, settings.port, base);
+    
+    let db = Database::open_read_only(&config.database_path).await?;
+    let shutdown = monitor.shutdown_token();
+    let mut interval = tokio::time::interval(Duration::from_secs(settings.publish_interval_seconds.max(5)));
+    loop {
+        tokio::select! {
+            _ = shutdown.cancelled() => break,
+            _ = interval.tick() => {}
+        }
+        
+        match current_state(&monitor, &db, &config).await {
+            Ok(state) => client
+                .publish(&state_topic, QoS::AtLeastOnce, true, state.to_string())
+                .await
+                .map_err(mqtt_error)?,
+            Err(e) => warn!("Failed to compute MQTT state: {}", e),
+        }
+    }
+    
+    client
+        .publish(&availability_topic, QoS::AtLeastOnce, true, "offline")
+        .await
+        .map_err(mqtt_error)?;
+    // Give the event loop a moment to send the offline message before dropping it
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    connection.abort();
+    Ok(())
+}
+
+#[cfg(feature = "mqtt")]
+async fn current_state<T: PlatformTracker + ?Sized + 'static>(
+    monitor: &ActivityMonitor<T>,
+    db: &Database,
+    config: &Config,
+) -> Result<serde_json::Value> {
+    let status = monitor.status().await;
+    let today = Local::now().date_naive();
+    let summary = db.get_daily_summary(today).await?;
+    
+    // In deep work when a qualifying stretch is still running: its last active
+    // minute is within the idle gap detection tolerates
+    let activity = db.get_minute_activity(today).await?;
+    let switches = db.get_window_switches(today).await?;
+    let now = Local::now().naive_local();
+    let tolerance = ChronoDuration::minutes(config.deep_work.max_idle_gap_minutes + 1);
+    let deep_work = !status.paused
+        && deep_work::detect(today, &activity, &switches, &config.deep_work)
+            .last()
+            .is_some_and(|interval| now - interval.ended_at <= tolerance);
+    
+    let activity = if status.paused {
+        "paused"
+    } else if status.idle {
+        "idle"
+    } else {
+        "active"
+    };
+    
+    Ok(serde_json::json!({
+        "activity": activity,
+        "present": !status.idle,
+        "deep_work": deep_work,
+        "keystrokes_today": summary.keystrokes,
+        "clicks_today": summary.clicks,
+        "active_minutes_today": summary.active_seconds / 60,
+    }))
+}
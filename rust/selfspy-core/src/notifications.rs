@@ -0,0 +1,476 @@
+// Alerts go through the Notifier, which coordinates delivery to every sink:
+// the same alert (by title) is sent at most once per repeat interval, each
+// channel has an hourly budget, and alerts held back by quiet hours or the
+// budget are delivered later as one digest (see `flush`). Sinks run as
+// background tasks, so a slow sendmail or webhook never holds up the monitor
+// or the other channels; `settle` waits for the ones in flight.
+use crate::clock::{Clock, SystemClock};
+use crate::error::{Result, SelfspyError};
+use crate::webhook::{Webhook, WebhookEvent, WebhookSettings};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tokio::task::JoinSet;
+use tracing::{debug, info, warn};
+
+// Held alerts beyond this are dropped; the digest only needs the gist
+const MAX_HELD: usize = 50;
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub title: String,
+    pub body: String,
+    // Deliver only on this kind of channel; None sends to every channel
+    pub channel: Option<NotificationChannel>,
+}
+
+impl Notification {
+    pub fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            body: body.into(),
+            channel: None,
+        }
+    }
+    
+    pub fn only_on(mut self, channel: Option<NotificationChannel>) -> Self {
+        self.channel = channel;
+        self
+    }
+}
+
+// The configurable channels, for routing a notification to one of them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationChannel {
+    Desktop,
+    Webhook,
+    Email,
+}
+
+impl NotificationChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NotificationChannel::Desktop => "desktop",
+            NotificationChannel::Webhook => "webhook",
+            NotificationChannel::Email => "email",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    // An alert with the same title is sent at most once per this many seconds
+    pub repeat_interval_seconds: u64,
+    // Alerts per channel per hour; the rest go out together in the next digest
+    pub max_per_hour: usize,
+    pub desktop: DesktopChannel,
+    pub webhook: Option<WebhookChannel>,
+    pub email: Option<EmailChannel>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            repeat_interval_seconds: 600,
+            max_per_hour: 10,
+            desktop: DesktopChannel::default(),
+            webhook: None,
+            email: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DesktopChannel {
+    pub enabled: bool,
+    pub quiet_hours: Option<QuietHours>,
+}
+
+impl Default for DesktopChannel {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            quiet_hours: None,
+        }
+    }
+}
+
+// POSTs each alert to a webhook as event "alert"; failed deliveries are
+// retried from the outbox, see webhook.rs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookChannel {
+    #[serde(flatten)]
+    pub settings: WebhookSettings,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+// Mails `to` through the local sendmail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailChannel {
+    pub to: String,
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHours>,
+}
+
+// Local hours, start inclusive and end exclusive; 22 to 7 spans midnight
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct QuietHours {
+    pub start_hour: u32,
+    pub end_hour: u32,
+}
+
+impl QuietHours {
+    pub fn contains(&self, hour: u32) -> bool {
+        if self.start_hour <= self.end_hour {
+            hour >= self.start_hour && hour < self.end_hour
+        } else {
+            hour >= self.start_hour || hour < self.end_hour
+        }
+    }
+}
+
+#[async_trait]
+pub trait NotificationSink: Send + Sync {
+    async fn send(&self, notification: &Notification) -> Result<()>;
+    
+    // Re-sends earlier failed deliveries, for sinks that keep them
+    async fn retry(&self, _now: DateTime<Utc>) {}
+}
+
+// Native desktop notifications (Notification Center, libnotify, toast)
+pub struct DesktopSink;
+
+#[async_trait]
+impl NotificationSink for DesktopSink {
+    // show() talks to the notification daemon synchronously
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let notification = notification.clone();
+        tokio::task::spawn_blocking(move || {
+            notify_rust::Notification::new()
+                .appname("Selfspy")
+                .summary(&notification.title)
+                .body(&notification.body)
+                .show()
+                .map(|_| ())
+        })
+        .await
+        .map_err(|e| SelfspyError::Notification(e.to_string()))?
+        .map_err(|e| SelfspyError::Notification(e.to_string()))
+    }
+}
+
+pub struct WebhookSink {
+    webhook: Arc<Webhook>,
+    // Set while a retry pass runs, so passes never overlap
+    retrying: Arc<AtomicBool>,
+}
+
+impl WebhookSink {
+    pub fn new(settings: WebhookSettings, data_dir: &Path) -> Self {
+        Self {
+            webhook: Arc::new(Webhook::new(settings, data_dir)),
+            retrying: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for WebhookSink {
+    // Failed requests are also queued for retrying
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let event = WebhookEvent {
+            event: "alert".to_string(),
+            title: notification.title.clone(),
+            body: notification.body.clone(),
+            timestamp: Utc::now(),
+        };
+        self.webhook.send(&event).await
+    }
+    
+    async fn retry(&self, now: DateTime<Utc>) {
+        if self.retrying.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        self.webhook.retry_pending(now).await;
+        self.retrying.store(false, Ordering::Release);
+    }
+}
+
+pub struct EmailSink {
+    to: String,
+}
+
+impl EmailSink {
+    pub fn new(to: impl Into<String>) -> Self {
+        Self { to: to.into() }
+    }
+}
+
+#[async_trait]
+impl NotificationSink for EmailSink {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        let mut child = Command::new("sendmail")
+            .arg("-t")
+            .stdin(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| SelfspyError::Notification(format!("Failed to run sendmail: {}", e)))?;
+        
+        let message = format!(
+            "To: {}\nSubject: Selfspy: {}\nContent-Type: text/plain; charset=utf-8\n\n{}\n",
+            self.to, notification.title, notification.body
+        );
+        // Dropping stdin closes it, which ends the message
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(message.as_bytes()).await?;
+        }
+        
+        let status = child.wait().await?;
+        if !status.success() {
+            return Err(SelfspyError::Notification(format!("sendmail failed ({})", status)));
+        }
+        Ok(())
+    }
+}
+
+struct Channel {
+    sink: Arc<dyn NotificationSink>,
+    // None for sinks added with `with_channel`, which only get unrouted alerts
+    kind: Option<NotificationChannel>,
+    quiet_hours: Option<QuietHours>,
+    // Delivery times within the last hour, and alerts waiting for the digest
+    sent: VecDeque<Instant>,
+    held: Vec<Notification>,
+}
+
+// When an alert title was last sent and how many repeats were dropped since
+struct Recent {
+    sent_at: Instant,
+    repeats: u32,
+}
+
+pub struct Notifier {
+    channels: Mutex<Vec<Channel>>,
+    recent: Mutex<HashMap<String, Recent>>,
+    // Sends and retries in flight
+    deliveries: Mutex<JoinSet<()>>,
+    repeat_interval: Duration,
+    max_per_hour: usize,
+    clock: Arc<dyn Clock>,
+}
+
+impl Notifier {
+    pub fn new() -> Self {
+        let defaults = NotificationSettings::default();
+        Self {
+            channels: Mutex::new(Vec::new()),
+            recent: Mutex::new(HashMap::new()),
+            deliveries: Mutex::new(JoinSet::new()),
+            repeat_interval: Duration::from_secs(defaults.repeat_interval_seconds),
+            max_per_hour: defaults.max_per_hour,
+            clock: Arc::new(SystemClock),
+        }
+    }
+    
+    pub fn desktop() -> Self {
+        Self::new().add_channel(Box::new(DesktopSink), None, Some(NotificationChannel::Desktop))
+    }
+    
+    // Webhook channels keep undelivered requests in `data_dir`
+    pub fn from_settings(settings: &NotificationSettings, data_dir: &Path) -> Self {
+        let mut notifier = Self {
+            repeat_interval: Duration::from_secs(settings.repeat_interval_seconds),
+            max_per_hour: settings.max_per_hour,
+            ..Self::new()
+        };
+        if settings.desktop.enabled {
+            notifier = notifier.add_channel(
+                Box::new(DesktopSink),
+                settings.desktop.quiet_hours,
+                Some(NotificationChannel::Desktop),
+            );
+        }
+        if let Some(webhook) = &settings.webhook {
+            notifier = notifier.add_channel(
+                Box::new(WebhookSink::new(webhook.settings.clone(), data_dir)),
+                webhook.quiet_hours,
+                Some(NotificationChannel::Webhook),
+            );
+        }
+        if let Some(email) = &settings.email {
+            notifier = notifier.add_channel(
+                Box::new(EmailSink::new(&email.to)),
+                email.quiet_hours,
+                Some(NotificationChannel::Email),
+            );
+        }
+        notifier
+    }
+    
+    pub fn with_sink(self, sink: Box<dyn NotificationSink>) -> Self {
+        self.with_channel(sink, None)
+    }
+    
+    pub fn with_channel(self, sink: Box<dyn NotificationSink>, quiet_hours: Option<QuietHours>) -> Self {
+        self.add_channel(sink, quiet_hours, None)
+    }
+    
+    fn add_channel(
+        self,
+        sink: Box<dyn NotificationSink>,
+        quiet_hours: Option<QuietHours>,
+        kind: Option<NotificationChannel>,
+    ) -> Self {
+        self.channels.lock().unwrap().push(Channel {
+            sink: Arc::from(sink),
+            kind,
+            quiet_hours,
+            sent: VecDeque::new(),
+            held: Vec::new(),
+        });
+        self
+    }
+    
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    // Delivery failures are logged rather than returned; a missing
+    // notification daemon shouldn't stop monitoring. Returns false when the
+    // alert won't go out on its own: dropped as a repeat, or held by quiet
+    // hours on every channel (it may still go out in a digest).
+    pub fn notify(&self, mut notification: Notification) -> bool {
+        let now = self.clock.instant();
+        
+        {
+            let mut recent = self.recent.lock().unwrap();
+            recent.retain(|_, entry| now.duration_since(entry.sent_at) < self.repeat_interval || entry.repeats > 0);
+            match recent.get_mut(&notification.title) {
+                Some(entry) if now.duration_since(entry.sent_at) < self.repeat_interval => {
+                    entry.repeats += 1;
+                    debug!("Dropped repeated notification: {}", notification.title);
+                    return false;
+                }
+                Some(entry) => {
+                    if entry.repeats > 0 {
+                        notification.body.push_str(&format!(" (repeated {} more times)", entry.repeats));
+                    }
+                    *entry = Recent { sent_at: now, repeats: 0 };
+                }
+                None => {
+                    recent.insert(notification.title.clone(), Recent { sent_at: now, repeats: 0 });
+                }
+            }
+        }
+        
+        debug!("Notification: {} - {}", notification.title, notification.body);
+        let hour = self.clock.now().with_timezone(&Local).hour();
+        let mut accepted = false;
+        for channel in self.channels.lock().unwrap().iter_mut() {
+            if notification.channel.is_some() && channel.kind != notification.channel {
+                continue;
+            }
+            channel.sent.retain(|sent| now.duration_since(*sent) < Duration::from_secs(3600));
+            
+            let quiet = channel.quiet_hours.is_some_and(|quiet| quiet.contains(hour));
+            accepted |= !quiet;
+            if quiet || channel.sent.len() >= self.max_per_hour {
+                if channel.held.len() < MAX_HELD {
+                    channel.held.push(notification.clone());
+                }
+                continue;
+            }
+            
+            self.deliver(channel, &notification, now);
+        }
+        accepted
+    }
+    
+    // Whether every channel a notification for `channel` would go to (every
+    // channel for None) is in its quiet hours now
+    pub fn is_quiet(&self, channel: Option<NotificationChannel>) -> bool {
+        let hour = self.clock.now().with_timezone(&Local).hour();
+        self.channels
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|candidate| channel.is_none() || candidate.kind == channel)
+            .all(|candidate| candidate.quiet_hours.is_some_and(|quiet| quiet.contains(hour)))
+    }
+    
+    // Sends held alerts as one digest on each channel that is out of its
+    // quiet hours and has budget left, and retries failed deliveries; the
+    // monitor calls this periodically
+    pub fn flush(&self) {
+        let now = self.clock.instant();
+        let hour = self.clock.now().with_timezone(&Local).hour();
+        
+        for channel in self.channels.lock().unwrap().iter_mut() {
+            let (sink, at) = (channel.sink.clone(), self.clock.now());
+            self.spawn(async move { sink.retry(at).await });
+            channel.sent.retain(|sent| now.duration_since(*sent) < Duration::from_secs(3600));
+            if channel.held.is_empty()
+                || channel.quiet_hours.is_some_and(|quiet| quiet.contains(hour))
+                || channel.sent.len() >= self.max_per_hour
+            {
+                continue;
+            }
+            
+            let held = std::mem::take(&mut channel.held);
+            info!("Sending a digest of {} held notifications", held.len());
+            let digest = match held.as_slice() {
+                [only] => only.clone(),
+                _ => Notification::new(
+                    format!("{} Selfspy alerts", held.len()),
+                    held.iter()
+                        .map(|notification| format!("{}: {}", notification.title, notification.body))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                ),
+            };
+            self.deliver(channel, &digest, now);
+        }
+    }
+    
+    // Waits for the sends and retries started so far, e.g. before the
+    // runtime shuts down
+    pub async fn settle(&self) {
+        let mut deliveries = std::mem::take(&mut *self.deliveries.lock().unwrap());
+        while deliveries.join_next().await.is_some() {}
+    }
+    
+    fn deliver(&self, channel: &mut Channel, notification: &Notification, now: Instant) {
+        channel.sent.push_back(now);
+        let (sink, notification) = (channel.sink.clone(), notification.clone());
+        self.spawn(async move {
+            if let Err(e) = sink.send(&notification).await {
+                warn!("Failed to deliver notification: {}", e);
+            }
+        });
+    }
+    
+    fn spawn(&self, task: impl std::future::Future<Output = ()> + Send + 'static) {
+        let mut deliveries = self.deliveries.lock().unwrap();
+        while deliveries.try_join_next().is_some() {}
+        deliveries.spawn(task);
+    }
+}
+
+impl Default for Notifier {
+    fn default() -> Self {
+        Self::desktop()
+    }
+}
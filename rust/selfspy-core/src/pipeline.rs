@@ -0,0 +1,209 @@
+// The monitor runs as a small pipeline: sources (window watcher, input
+// collector, or plugins) talk to the platform layer and push events into a
+// channel, and the persister inside ActivityMonitor drains it into the
+// database. A failing source is restarted on its own without stopping
+// persistence.
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time;
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info};
+
+use crate::config::CaptureSettings;
+use crate::error::{Result, SelfspyError};
+use crate::platform::session::{self, SessionEvent};
+use crate::platform::{InputEvent, PlatformTracker, WindowInfo};
+use crate::process_names;
+use crate::titles::TitleNormalizer;
+
+// Capacity of the channel between sources and the persister
+pub const EVENT_BUFFER: usize = 4096;
+
+const WINDOW_POLL_INTERVAL: Duration = Duration::from_secs(1);
+const INPUT_POLL_INTERVAL: Duration = Duration::from_millis(250);
+// Delay before a failed source is started again
+const SOURCE_RESTART_DELAY: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub enum PipelineEvent {
+    // The foreground window changed (already normalized and filtered by capture settings)
+    Window(WindowInfo),
+    Input(InputEvent),
+    // Lock or sleep reported by the session manager
+    Session(SessionEvent),
+}
+
+// A pipeline stage producing events. Returning Err restarts the source;
+// returning Ok(()) means it finished for good.
+#[async_trait]
+pub trait Source: Send + Sync {
+    fn name(&self) -> &str;
+    async fn run(&self, events: mpsc::Sender<PipelineEvent>, cancel: CancellationToken) -> Result<()>;
+}
+
+// Polls the foreground window and emits an event whenever it changes
+pub struct WindowWatcher<T: PlatformTracker + ?Sized = dyn PlatformTracker> {
+    tracker: Arc<T>,
+    capture: CaptureSettings,
+    titles: TitleNormalizer,
+}
+
+impl<T: PlatformTracker + ?Sized> WindowWatcher<T> {
+    pub fn new(tracker: Arc<T>, capture: CaptureSettings, title_patterns: &[String]) -> Self {
+        Self {
+            tracker,
+            capture,
+            titles: TitleNormalizer::new(title_patterns),
+        }
+    }
+    
+    // Strip the window fields the user opted out of before they are compared or stored
+    fn apply_capture_settings(&self, mut window: WindowInfo) -> WindowInfo {
+        if !self.capture.window_titles {
+            window.window_title = String::new();
+        }
+        
+        // A file path or tab name says as much as the title it came from
+        if !self.capture.window_titles || !self.capture.documents {
+            window.document = None;
+        }
+        
+        if !self.capture.window_geometry {
+            window.x = None;
+            window.y = None;
+            window.width = None;
+            window.height = None;
+        }
+        
+        window
+    }
+}
+
+#[async_trait]
+impl<T: PlatformTracker + ?Sized + 'static> Source for WindowWatcher<T> {
+    fn name(&self) -> &str {
+        "window watcher"
+    }
+    
+    async fn run(&self, events: mpsc::Sender<PipelineEvent>, cancel: CancellationToken) -> Result<()> {
+        let mut interval = time::interval(WINDOW_POLL_INTERVAL);
+        let mut last_fingerprint = None;
+        
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => return Ok(()),
+                _ = interval.tick() => {}
+            }
+            
+            let Ok(mut window) = self.tracker.get_active_window().await else {
+                continue;
+            };
+            process_names::normalize(&mut window);
+            let window = self.apply_capture_settings(window);
+            
+            let fingerprint = self.titles.fingerprint(&window.process_name, &window.window_title);
+            if last_fingerprint == Some(fingerprint) {
+                continue;
+            }
+            last_fingerprint = Some(fingerprint);
+            
+            if events.send(PipelineEvent::Window(window)).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Drains the platform input hooks into the pipeline
+pub struct InputCollector<T: PlatformTracker + ?Sized = dyn PlatformTracker> {
+    tracker: Arc<T>,
+}
+
+impl<T: PlatformTracker + ?Sized> InputCollector<T> {
+    pub fn new(tracker: Arc<T>) -> Self {
+        Self { tracker }
+    }
+}
+
+#[async_trait]
+impl<T: PlatformTracker + ?Sized + 'static> Source for InputCollector<T> {
+    fn name(&self) -> &str {
+        "input collector"
+    }
+    
+    async fn run(&self, events: mpsc::Sender<PipelineEvent>, cancel: CancellationToken) -> Result<()> {
+        let mut interval = time::interval(INPUT_POLL_INTERVAL);
+        
+        loop {
+            tokio::select! {
+                _ = cancel.cancelled() => return Ok(()),
+                _ = interval.tick() => {}
+            }
+            
+            for event in self.tracker.get_input_events() {
+                if events.send(PipelineEvent::Input(event)).await.is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+// Forwards lock and sleep signals from the session manager. Finishes at once
+// where there is none, leaving the monitor to poll.
+pub struct SessionWatcher;
+
+#[async_trait]
+impl Source for SessionWatcher {
+    fn name(&self) -> &str {
+        "session watcher"
+    }
+    
+    async fn run(&self, events: mpsc::Sender<PipelineEvent>, cancel: CancellationToken) -> Result<()> {
+        let Some(mut signals) = session::subscribe().await else {
+            info!("No session manager signals; polling for screen lock and sleep");
+            return Ok(());
+        };
+        if events.send(PipelineEvent::Session(SessionEvent::Watching(true))).await.is_err() {
+            return Ok(());
+        }
+        
+        loop {
+            let event = tokio::select! {
+                _ = cancel.cancelled() => return Ok(()),
+                event = signals.next() => event,
+            };
+            let Some(event) = event else {
+                let _ = events.send(PipelineEvent::Session(SessionEvent::Watching(false))).await;
+                return Err(SelfspyError::Platform("lost the connection to logind".to_string()));
+            };
+            if events.send(PipelineEvent::Session(event)).await.is_err() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+// Runs a source until it finishes or the pipeline is cancelled, restarting it after failures
+pub fn spawn_source(
+    source: Arc<dyn Source>,
+    events: mpsc::Sender<PipelineEvent>,
+    cancel: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            match source.run(events.clone(), cancel.clone()).await {
+                Ok(()) => return,
+                Err(e) => error!("Pipeline source {} failed: {}", source.name(), e),
+            }
+            
+            tokio::select! {
+                _ = cancel.cancelled() => return,
+                _ = time::sleep(SOURCE_RESTART_DELAY) => {}
+            }
+            info!("Restarting pipeline source {}", source.name());
+        }
+    })
+}
\ No newline at end of file
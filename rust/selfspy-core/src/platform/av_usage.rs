@@ -0,0 +1,245 @@
+// Whether any application is using the microphone or a camera, for spotting
+// meetings. Only whether a device is in use is probed, never what it
+// captures. Best-effort like `focus`: a device that can't be checked counts
+// as not in use.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AvUsage {
+    pub microphone: bool,
+    pub camera: bool,
+}
+
+impl AvUsage {
+    pub fn any(&self) -> bool {
+        self.microphone || self.camera
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn av_usage() -> AvUsage {
+    AvUsage {
+        microphone: linux_microphone_in_use(),
+        camera: linux_camera_in_use(),
+    }
+}
+
+// pactl talks to PulseAudio and to PipeWire's pulse server alike. Streams
+// recording a sink's monitor (screen recorders, visualizers) aren't the mic.
+#[cfg(target_os = "linux")]
+fn linux_microphone_in_use() -> bool {
+    let (Some(sources), Some(outputs)) = (
+        super::command_output("pactl", &["list", "short", "sources"]),
+        super::command_output("pactl", &["list", "short", "source-outputs"]),
+    ) else {
+        return false;
+    };
+    
+    let monitors: Vec<&str> = sources
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let index = fields.next()?;
+            fields.next()?.ends_with(".monitor").then_some(index)
+        })
+        .collect();
+    
+    // "index  source-index  client-index  sample-spec"
+    outputs
+        .lines()
+        .filter_map(|line| line.split('\t').nth(1))
+        .any(|source| !monitors.contains(&source))
+}
+
+// A camera is in use while some process holds a /dev/video* node open, which
+// is what fuser would report; only our user's processes are visible
+#[cfg(target_os = "linux")]
+fn linux_camera_in_use() -> bool {
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return false;
+    };
+    let own_pid = std::process::id().to_string();
+    
+    processes
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            name.bytes().all(|b| b.is_ascii_digit()) && name != own_pid
+        })
+        .any(|entry| {
+            let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+                return false;
+            };
+            fds.filter_map(|fd| fd.ok())
+                .filter_map(|fd| std::fs::read_link(fd.path()).ok())
+                .any(|target| target.to_string_lossy().starts_with("/dev/video"))
+        })
+}
+
+#[cfg(target_os = "macos")]
+pub fn av_usage() -> AvUsage {
+    AvUsage {
+        microphone: macos::microphone_in_use(),
+        camera: macos::camera_in_use(),
+    }
+}
+
+// CoreAudio and CoreMediaIO both answer "is this device running in any
+// process" through the same kind of property query
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::ffi::c_void;
+    
+    #[repr(C)]
+    struct PropertyAddress {
+        selector: u32,
+        scope: u32,
+        element: u32,
+    }
+    
+    const SYSTEM_OBJECT: u32 = 1;
+    const SCOPE_GLOBAL: u32 = u32::from_be_bytes(*b"glob");
+    const ELEMENT_MAIN: u32 = 0;
+    const DEFAULT_INPUT_DEVICE: u32 = u32::from_be_bytes(*b"dIn ");
+    const DEVICES: u32 = u32::from_be_bytes(*b"dev#");
+    const IS_RUNNING_SOMEWHERE: u32 = u32::from_be_bytes(*b"gone");
+    
+    #[link(name = "CoreAudio", kind = "framework")]
+    extern "C" {
+        fn AudioObjectGetPropertyData(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            data_size: *mut u32,
+            data: *mut c_void,
+        ) -> i32;
+    }
+    
+    #[link(name = "CoreMediaIO", kind = "framework")]
+    extern "C" {
+        fn CMIOObjectGetPropertyDataSize(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            data_size: *mut u32,
+        ) -> i32;
+        fn CMIOObjectGetPropertyData(
+            object: u32,
+            address: *const PropertyAddress,
+            qualifier_size: u32,
+            qualifier: *const c_void,
+            data_size: u32,
+            data_used: *mut u32,
+            data: *mut c_void,
+        ) -> i32;
+    }
+    
+    fn address(selector: u32) -> PropertyAddress {
+        PropertyAddress {
+            selector,
+            scope: SCOPE_GLOBAL,
+            element: ELEMENT_MAIN,
+        }
+    }
+    
+    fn audio_u32(object: u32, selector: u32) -> Option<u32> {
+        let mut value = 0u32;
+        let mut size = std::mem::size_of::<u32>() as u32;
+        let status = unsafe {
+            AudioObjectGetPropertyData(
+                object,
+                &address(selector),
+                0,
+                std::ptr::null(),
+                &mut size,
+                &mut value as *mut u32 as *mut c_void,
+            )
+        };
+        (status == 0).then_some(value)
+    }
+    
+    // The default input device; other inputs in use are rare enough to ignore
+    pub fn microphone_in_use() -> bool {
+        audio_u32(SYSTEM_OBJECT, DEFAULT_INPUT_DEVICE)
+            .filter(|device| *device != 0)
+            .and_then(|device| audio_u32(device, IS_RUNNING_SOMEWHERE))
+            .is_some_and(|running| running != 0)
+    }
+    
+    fn media_u32(object: u32, selector: u32) -> Option<u32> {
+        let mut value = 0u32;
+        let mut used = 0u32;
+        let status = unsafe {
+            CMIOObjectGetPropertyData(
+                object,
+                &address(selector),
+                0,
+                std::ptr::null(),
+                std::mem::size_of::<u32>() as u32,
+                &mut used,
+                &mut value as *mut u32 as *mut c_void,
+            )
+        };
+        (status == 0).then_some(value)
+    }
+    
+    pub fn camera_in_use() -> bool {
+        let devices = address(DEVICES);
+        let mut size = 0u32;
+        if unsafe { CMIOObjectGetPropertyDataSize(SYSTEM_OBJECT, &devices, 0, std::ptr::null(), &mut size) } != 0 {
+            return false;
+        }
+        
+        let mut ids = vec![0u32; size as usize / std::mem::size_of::<u32>()];
+        let mut used = 0u32;
+        let status = unsafe {
+            CMIOObjectGetPropertyData(
+                SYSTEM_OBJECT,
+                &devices,
+                0,
+                std::ptr::null(),
+                size,
+                &mut used,
+                ids.as_mut_ptr() as *mut c_void,
+            )
+        };
+        if status != 0 {
+            return false;
+        }
+        ids.truncate(used as usize / std::mem::size_of::<u32>());
+        
+        ids.into_iter()
+            .any(|device| media_u32(device, IS_RUNNING_SOMEWHERE).is_some_and(|running| running != 0))
+    }
+}
+
+// Windows records every app's use of the mic and webcam for the privacy
+// settings page; an entry whose LastUsedTimeStop is 0 is still in use
+#[cfg(target_os = "windows")]
+pub fn av_usage() -> AvUsage {
+    let in_use = |capability: &str| {
+        let key = format!(
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\{}",
+            capability
+        );
+        super::command_output("reg", &["query", &key, "/s", "/v", "LastUsedTimeStop"])
+            .map(|out| {
+                out.lines()
+                    .filter(|line| line.contains("LastUsedTimeStop"))
+                    .any(|line| line.split_whitespace().last() == Some("0x0"))
+            })
+            .unwrap_or(false)
+    };
+    
+    AvUsage {
+        microphone: in_use("microphone"),
+        camera: in_use("webcam"),
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+pub fn av_usage() -> AvUsage {
+    AvUsage::default()
+}
@@ -0,0 +1,35 @@
+// Free space on the filesystem holding a path, for the low-disk guard.
+// Best-effort like the other probes: None when it can't be determined.
+use std::path::Path;
+
+#[cfg(unix)]
+pub fn free_bytes(path: &Path) -> Option<u64> {
+    // POSIX output: one header line, then "fs blocks used available capacity mount"
+    // in 1024-byte blocks
+    let output = std::process::Command::new("df").arg("-Pk").arg(path).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    
+    let text = String::from_utf8(output.stdout).ok()?;
+    let available = text.lines().nth(1)?.split_whitespace().nth(3)?;
+    available.parse::<u64>().ok().map(|blocks| blocks * 1024)
+}
+
+#[cfg(target_os = "windows")]
+pub fn free_bytes(path: &Path) -> Option<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(std::iter::once(0)).collect();
+    let mut available = 0u64;
+    // Free space available to this user, which honours disk quotas
+    unsafe { GetDiskFreeSpaceExW(PCWSTR(wide.as_ptr()), Some(&mut available as *mut u64), None, None) }.ok()?;
+    Some(available)
+}
+
+#[cfg(not(any(unix, target_os = "windows")))]
+pub fn free_bytes(_path: &Path) -> Option<u64> {
+    None
+}
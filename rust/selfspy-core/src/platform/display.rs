@@ -0,0 +1,217 @@
+// Display geometry and click coordinate normalization.
+//
+// Stored click coordinates use one documented space: logical points on the
+// virtual desktop, with the origin at the top-left corner of the primary
+// display. The scale factor of the display that received the click is stored
+// alongside, so physical pixels can be recovered (x * scale). Where the
+// platform works in pixels, a display's logical bounds, origin included, are
+// its pixel bounds divided by its scale.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CoordinateSpace {
+    // Points, already independent of display density (macOS)
+    Logical,
+    // Device pixels of the display under the cursor (Windows, X11)
+    Physical,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DisplayInfo {
+    pub id: u32,
+    // Bounds in logical points on the virtual desktop, origin and extent alike
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+    pub scale: f64,
+}
+
+impl DisplayInfo {
+    fn contains_logical(&self, x: f64, y: f64) -> bool {
+        x >= self.x && x < self.x + self.width && y >= self.y && y < self.y + self.height
+    }
+    
+    fn contains_physical(&self, x: f64, y: f64) -> bool {
+        self.contains_logical(x / self.scale, y / self.scale)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NormalizedPoint {
+    pub x: i32,
+    pub y: i32,
+    pub scale: f64,
+}
+
+#[cfg(target_os = "macos")]
+pub const NATIVE_SPACE: CoordinateSpace = CoordinateSpace::Logical;
+
+#[cfg(not(target_os = "macos"))]
+pub const NATIVE_SPACE: CoordinateSpace = CoordinateSpace::Physical;
+
+// Convert a point reported by the platform backend into the storage space
+pub fn normalize_point(displays: &[DisplayInfo], x: i32, y: i32, space: CoordinateSpace) -> NormalizedPoint {
+    let (fx, fy) = (x as f64, y as f64);
+    
+    let display = displays.iter().find(|display| match space {
+        CoordinateSpace::Logical => display.contains_logical(fx, fy),
+        CoordinateSpace::Physical => display.contains_physical(fx, fy),
+    });
+    
+    match (display, space) {
+        (Some(display), CoordinateSpace::Physical) => NormalizedPoint {
+            x: (fx / display.scale).round() as i32,
+            y: (fy / display.scale).round() as i32,
+            scale: display.scale,
+        },
+        (Some(display), CoordinateSpace::Logical) => NormalizedPoint { x, y, scale: display.scale },
+        // Off-screen or unknown layout: keep the raw value at scale 1
+        (None, _) => NormalizedPoint { x, y, scale: 1.0 },
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn displays() -> Vec<DisplayInfo> {
+    use std::ffi::c_void;
+    
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct CGPoint { x: f64, y: f64 }
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct CGSize { width: f64, height: f64 }
+    #[repr(C)]
+    #[derive(Clone, Copy)]
+    struct CGRect { origin: CGPoint, size: CGSize }
+    
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        fn CGGetActiveDisplayList(max: u32, displays: *mut u32, count: *mut u32) -> i32;
+        fn CGDisplayBounds(display: u32) -> CGRect;
+        fn CGDisplayCopyDisplayMode(display: u32) -> *mut c_void;
+        fn CGDisplayModeGetPixelWidth(mode: *mut c_void) -> usize;
+        fn CGDisplayModeGetWidth(mode: *mut c_void) -> usize;
+        fn CGDisplayModeRelease(mode: *mut c_void);
+    }
+    
+    let mut ids = [0u32; 16];
+    let mut count = 0u32;
+    
+    unsafe {
+        if CGGetActiveDisplayList(ids.len() as u32, ids.as_mut_ptr(), &mut count) != 0 {
+            return Vec::new();
+        }
+        
+        ids[..count as usize]
+            .iter()
+            .map(|&id| {
+                let bounds = CGDisplayBounds(id);
+                let mode = CGDisplayCopyDisplayMode(id);
+                let scale = if mode.is_null() {
+                    1.0
+                } else {
+                    let pixels = CGDisplayModeGetPixelWidth(mode) as f64;
+                    let points = CGDisplayModeGetWidth(mode) as f64;
+                    CGDisplayModeRelease(mode);
+                    if points > 0.0 { pixels / points } else { 1.0 }
+                };
+                
+                DisplayInfo {
+                    id,
+                    x: bounds.origin.x,
+                    y: bounds.origin.y,
+                    width: bounds.size.width,
+                    height: bounds.size.height,
+                    scale,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn displays() -> Vec<DisplayInfo> {
+    use windows::Win32::Foundation::{BOOL, LPARAM, RECT};
+    use windows::Win32::Graphics::Gdi::{EnumDisplayMonitors, GetMonitorInfoW, HDC, HMONITOR, MONITORINFO};
+    use windows::Win32::UI::HiDpi::{GetDpiForMonitor, MDT_EFFECTIVE_DPI};
+    
+    unsafe extern "system" fn collect(monitor: HMONITOR, _: HDC, _: *mut RECT, data: LPARAM) -> BOOL {
+        let displays = &mut *(data.0 as *mut Vec<DisplayInfo>);
+        
+        let mut info = MONITORINFO {
+            cbSize: std::mem::size_of::<MONITORINFO>() as u32,
+            ..Default::default()
+        };
+        if !GetMonitorInfoW(monitor, &mut info).as_bool() {
+            return BOOL(1);
+        }
+        
+        let (mut dpi_x, mut dpi_y) = (96u32, 96u32);
+        let _ = GetDpiForMonitor(monitor, MDT_EFFECTIVE_DPI, &mut dpi_x, &mut dpi_y);
+        let scale = dpi_x as f64 / 96.0;
+        
+        let rect = info.rcMonitor;
+        displays.push(DisplayInfo {
+            id: displays.len() as u32,
+            x: rect.left as f64 / scale,
+            y: rect.top as f64 / scale,
+            width: (rect.right - rect.left) as f64 / scale,
+            height: (rect.bottom - rect.top) as f64 / scale,
+            scale,
+        });
+        
+        BOOL(1)
+    }
+    
+    let mut displays: Vec<DisplayInfo> = Vec::new();
+    unsafe {
+        let _ = EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(collect),
+            LPARAM(&mut displays as *mut Vec<DisplayInfo> as isize),
+        );
+    }
+    displays
+}
+
+#[cfg(target_os = "linux")]
+pub fn displays() -> Vec<DisplayInfo> {
+    // X11 has no per-output scale; toolkits apply one global factor
+    let scale = std::env::var("GDK_SCALE")
+        .ok()
+        .and_then(|value| value.parse::<f64>().ok())
+        .filter(|scale| *scale > 0.0)
+        .unwrap_or(1.0);
+    
+    let Ok(output) = std::process::Command::new("xrandr").arg("--query").output() else {
+        return Vec::new();
+    };
+    
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.contains(" connected"))
+        .filter_map(|line| {
+            // e.g. "DP-1 connected primary 2560x1440+1920+0 (normal ...)"
+            let geometry = line.split_whitespace().find(|part| part.contains('x') && part.contains('+'))?;
+            let (size, offsets) = geometry.split_once('+')?;
+            let (width, height) = size.split_once('x')?;
+            let (x, y) = offsets.split_once('+')?;
+            Some((width.parse::<f64>().ok()?, height.parse::<f64>().ok()?, x.parse::<f64>().ok()?, y.parse::<f64>().ok()?))
+        })
+        .enumerate()
+        .map(|(id, (width, height, x, y))| DisplayInfo {
+            id: id as u32,
+            x: x / scale,
+            y: y / scale,
+            width: width / scale,
+            height: height / scale,
+            scale,
+        })
+        .collect()
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+pub fn displays() -> Vec<DisplayInfo> {
+    Vec::new()
+}
\ No newline at end of file
@@ -0,0 +1,280 @@
+// Input-only backend for Linux sessions where neither X11 nor a supported
+// Wayland compositor is available (text consoles, GNOME or KDE on Wayland
+// without XWayland, kiosks): keyboards and pointers are read straight from
+// /dev/input, which needs the same `input` group membership as libinput.
+// There is no window information, so everything is recorded against one
+// placeholder window; counts, clicks and typing rhythm still work. Devices
+// are rediscovered every few seconds to pick up ones plugged in later.
+use async_trait::async_trait;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::input_devices::{self, DeviceAccess};
+use super::{keymap, layout};
+use super::{DeviceId, InputEvent, Modifiers, MouseButton, PlatformTracker, WindowInfo};
+use crate::error::{Result, SelfspyError};
+
+// How long the input thread waits for events before checking for stop
+const INPUT_POLL_TIMEOUT_MS: i32 = 250;
+const RESCAN_INTERVAL: Duration = Duration::from_secs(5);
+
+// linux/input-event-codes.h
+const EV_SYN: u16 = 0x00;
+const EV_KEY: u16 = 0x01;
+const EV_REL: u16 = 0x02;
+const REL_X: u16 = 0x00;
+const REL_Y: u16 = 0x01;
+const REL_HWHEEL: u16 = 0x06;
+const REL_WHEEL: u16 = 0x08;
+// Codes below this are keyboard keys, above it buttons
+const BTN_MISC: u16 = 0x100;
+
+pub struct EvdevTracker {
+    events: Arc<Mutex<Vec<InputEvent>>>,
+    input: Mutex<Option<(Arc<AtomicBool>, JoinHandle<()>)>>,
+}
+
+impl EvdevTracker {
+    pub fn new() -> Self {
+        Self {
+            events: Arc::new(Mutex::new(Vec::new())),
+            input: Mutex::new(None),
+        }
+    }
+    
+    // At least one keyboard or pointer can be read
+    pub fn is_available() -> bool {
+        input_devices::discover()
+            .iter()
+            .any(|device| input_devices::check_access(device) == DeviceAccess::Readable)
+    }
+}
+
+#[async_trait]
+impl PlatformTracker for EvdevTracker {
+    fn backend(&self) -> &'static str {
+        "evdev"
+    }
+    
+    async fn get_active_window(&self) -> Result<WindowInfo> {
+        Ok(WindowInfo {
+            process_name: "Unknown".to_string(),
+            window_title: String::new(),
+            document: None,
+            bundle_id: None,
+            x: None,
+            y: None,
+            width: None,
+            height: None,
+            pid: None,
+        })
+    }
+    
+    async fn start_input_tracking(&self) -> Result<()> {
+        let mut input = self.input.lock().unwrap();
+        if input.is_some() {
+            return Ok(());
+        }
+        
+        let mut readable = 0;
+        let mut advice = None;
+        for device in input_devices::discover() {
+            let access = input_devices::check_access(&device);
+            if access == DeviceAccess::Readable {
+                readable += 1;
+            } else {
+                tracing::warn!("Cannot read {} ({}): {:?}", device.path.display(), device.name, access);
+                advice = advice.or_else(|| input_devices::advice(&access));
+            }
+        }
+        if readable == 0 {
+            return Err(SelfspyError::Platform(format!(
+                "no readable keyboards or pointers under /dev/input; {}",
+                advice.unwrap_or_else(|| "run `selfspy check-input` for details".to_string())
+            )));
+        }
+        
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            let events = self.events.clone();
+            std::thread::Builder::new()
+                .name("selfspy-evdev".to_string())
+                .spawn(move || read_devices(events, stop))?
+        };
+        
+        *input = Some((stop, thread));
+        Ok(())
+    }
+    
+    async fn stop_input_tracking(&self) -> Result<()> {
+        if let Some((stop, thread)) = self.input.lock().unwrap().take() {
+            stop.store(true, Ordering::Relaxed);
+            let _ = thread.join();
+        }
+        Ok(())
+    }
+    
+    fn get_input_events(&self) -> Vec<InputEvent> {
+        let mut events = self.events.lock().unwrap();
+        let result = events.clone();
+        events.clear();
+        result
+    }
+    
+    fn is_healthy(&self) -> bool {
+        self.input
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(true, |(_, thread)| !thread.is_finished())
+    }
+}
+
+// Modifier, layout and pointer state shared by every device, since a shortcut can
+// span two keyboards. Pointer coordinates are a virtual position built from
+// relative motion, as in the Wayland backend.
+#[derive(Default)]
+struct ReaderState {
+    modifiers: Modifiers,
+    keyboard: keymap::Keymap,
+    x: i32,
+    y: i32,
+    moved: bool,
+}
+
+impl ReaderState {
+    fn handle(&mut self, kind: u16, code: u16, value: i32, device: &Arc<DeviceId>, batch: &mut Vec<InputEvent>) {
+        let device = Some(device.clone());
+        match (kind, code) {
+            // Auto-repeat (value 2) is skipped, matching libinput
+            (EV_KEY, code) if code < BTN_MISC && value != 2 => {
+                let code = code as u32;
+                let pressed = value == 1;
+                keymap::update_modifiers(&mut self.modifiers, code, pressed);
+                if let Some(name) = self.keyboard.key(code, pressed, self.modifiers.shift) {
+                    batch.push(InputEvent::key(&name, layout::us_latin(code), self.modifiers, pressed).from_device(device));
+                }
+            }
+            // Touchpad taps are only turned into clicks by libinput; physical buttons count
+            (EV_KEY, code) if value == 1 => {
+                let button = match code as u32 {
+                    keymap::BTN_LEFT => MouseButton::Left,
+                    keymap::BTN_RIGHT => MouseButton::Right,
+                    keymap::BTN_MIDDLE => MouseButton::Middle,
+                    _ => return,
+                };
+                batch.push(InputEvent::MouseClick { x: self.x, y: self.y, button, device });
+            }
+            (EV_REL, REL_X) => {
+                self.x += value;
+                self.moved = true;
+            }
+            (EV_REL, REL_Y) => {
+                self.y += value;
+                self.moved = true;
+            }
+            // Positive wheel values scroll up / right
+            (EV_REL, REL_WHEEL) => batch.push(InputEvent::MouseScroll { delta_x: 0.0, delta_y: value as f64, device }),
+            (EV_REL, REL_HWHEEL) => batch.push(InputEvent::MouseScroll { delta_x: value as f64, delta_y: 0.0, device }),
+            // X and Y of one motion arrive separately, closed by a sync
+            (EV_SYN, _) if self.moved => {
+                self.moved = false;
+                batch.push(InputEvent::MouseMove { x: self.x, y: self.y });
+            }
+            _ => {}
+        }
+    }
+}
+
+fn read_devices(events: Arc<Mutex<Vec<InputEvent>>>, stop: Arc<AtomicBool>) {
+    let mut devices: Vec<(PathBuf, File, Arc<DeviceId>)> = Vec::new();
+    let mut state = ReaderState::default();
+    let mut last_scan: Option<Instant> = None;
+    
+    while !stop.load(Ordering::Relaxed) {
+        if last_scan.is_none_or(|scanned| scanned.elapsed() >= RESCAN_INTERVAL) {
+            last_scan = Some(Instant::now());
+            for device in input_devices::discover() {
+                if devices.iter().any(|(path, ..)| *path == device.path) {
+                    continue;
+                }
+                let opened = OpenOptions::new()
+                    .read(true)
+                    .custom_flags(libc::O_NONBLOCK)
+                    .open(&device.path);
+                if let Ok(file) = opened {
+                    tracing::info!("Reading input from {} ({})", device.path.display(), device.name);
+                    let id = Arc::new(device.id());
+                    devices.push((device.path, file, id));
+                }
+            }
+        }
+        
+        let mut polls: Vec<libc::pollfd> = devices
+            .iter()
+            .map(|(_, file, _)| libc::pollfd {
+                fd: file.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            })
+            .collect();
+        if polls.is_empty() {
+            std::thread::sleep(Duration::from_millis(INPUT_POLL_TIMEOUT_MS as u64));
+            continue;
+        }
+        if unsafe { libc::poll(polls.as_mut_ptr(), polls.len() as libc::nfds_t, INPUT_POLL_TIMEOUT_MS) } <= 0 {
+            continue;
+        }
+        
+        let mut batch = Vec::new();
+        let mut gone = Vec::new();
+        for (index, poll) in polls.iter().enumerate() {
+            let failed = poll.revents & (libc::POLLERR | libc::POLLHUP | libc::POLLNVAL) != 0;
+            let (_, file, device) = &mut devices[index];
+            if failed || (poll.revents & libc::POLLIN != 0 && read_events(file, device, &mut state, &mut batch).is_err()) {
+                gone.push(index);
+            }
+        }
+        // Unplugged devices; they are reopened by a later scan if they come back
+        for index in gone.into_iter().rev() {
+            let (path, ..) = devices.remove(index);
+            tracing::info!("Input device {} went away", path.display());
+        }
+        
+        if !batch.is_empty() {
+            events.lock().unwrap().extend(batch);
+        }
+    }
+}
+
+// Drains a device's queued events; Err once the device is gone
+fn read_events(
+    file: &mut File,
+    device: &Arc<DeviceId>,
+    state: &mut ReaderState,
+    batch: &mut Vec<InputEvent>,
+) -> io::Result<()> {
+    const EVENT_SIZE: usize = std::mem::size_of::<libc::input_event>();
+    let mut buffer = [0u8; EVENT_SIZE * 64];
+    
+    loop {
+        let read = match file.read(&mut buffer) {
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        for chunk in buffer[..read].chunks_exact(EVENT_SIZE) {
+            let event = unsafe { std::ptr::read_unaligned(chunk.as_ptr() as *const libc::input_event) };
+            state.handle(event.type_, event.code, event.value, device, batch);
+        }
+    }
+}
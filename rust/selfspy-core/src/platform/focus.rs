@@ -0,0 +1,80 @@
+// Detection of the OS-level Focus / Do Not Disturb state. Every backend is
+// best-effort: if the state can't be determined we report "not active".
+
+#[cfg(target_os = "macos")]
+pub fn is_do_not_disturb_active() -> bool {
+    // Focus modes (Monterey and later) write their active assertions here
+    let Some(home) = std::env::var_os("HOME") else {
+        return false;
+    };
+    let path = std::path::Path::new(&home).join("Library/DoNotDisturb/DB/Assertions.json");
+    
+    let Ok(contents) = std::fs::read_to_string(path) else {
+        return legacy_macos_dnd();
+    };
+    
+    serde_json::from_str::<serde_json::Value>(&contents)
+        .ok()
+        .and_then(|json| {
+            json["data"].as_array().map(|entries| {
+                entries.iter().any(|entry| {
+                    entry["storeAssertionRecords"]
+                        .as_array()
+                        .map(|records| !records.is_empty())
+                        .unwrap_or(false)
+                })
+            })
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "macos")]
+fn legacy_macos_dnd() -> bool {
+    super::command_output("defaults", &["-currentHost", "read", "com.apple.notificationcenterui", "doNotDisturb"])
+        .map(|out| out.trim() == "1")
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+pub fn is_do_not_disturb_active() -> bool {
+    // GNOME turns banners off while Do Not Disturb is on
+    if let Some(out) = super::command_output("gsettings", &["get", "org.gnome.desktop.notifications", "show-banners"]) {
+        return out.trim() == "false";
+    }
+    
+    // KDE and other freedesktop notification servers expose an Inhibited flag
+    super::command_output(
+        "busctl",
+        &[
+            "--user",
+            "get-property",
+            "org.freedesktop.Notifications",
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+            "Inhibited",
+        ],
+    )
+    .map(|out| out.trim() == "b true")
+    .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_do_not_disturb_active() -> bool {
+    // Focus assist / Do not disturb disables toasts globally
+    super::command_output(
+        "reg",
+        &[
+            "query",
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\Notifications\Settings",
+            "/v",
+            "NOC_GLOBAL_SETTING_TOASTS_ENABLED",
+        ],
+    )
+    .map(|out| out.contains("0x0"))
+    .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+pub fn is_do_not_disturb_active() -> bool {
+    false
+}
@@ -0,0 +1,46 @@
+// Session idle time from the desktop over D-Bus, for Linux backends that
+// can't ask the display server directly (Wayland) or as a fallback when the
+// X server lacks the MIT-SCREEN-SAVER extension. Best-effort like focus.rs:
+// None when no service answers.
+
+// Seconds since the last user input in this session
+pub fn session_idle_seconds() -> Option<u64> {
+    // KDE and other freedesktop screen savers
+    busctl_call(&[
+        "org.freedesktop.ScreenSaver",
+        "/org/freedesktop/ScreenSaver",
+        "org.freedesktop.ScreenSaver",
+        "GetSessionIdleTime",
+    ])
+    // GNOME leaves GetSessionIdleTime unimplemented; Mutter has its own monitor
+    .or_else(|| {
+        busctl_call(&[
+            "org.gnome.Mutter.IdleMonitor",
+            "/org/gnome/Mutter/IdleMonitor/Core",
+            "org.gnome.Mutter.IdleMonitor",
+            "GetIdletime",
+        ])
+    })
+    .map(|millis| millis / 1000)
+}
+
+// Calls a method returning a single integer; busctl prints e.g. "u 12345"
+fn busctl_call(target: &[&str]) -> Option<u64> {
+    let output = std::process::Command::new("busctl")
+        .arg("--user")
+        .arg("call")
+        .args(target)
+        .output()
+        .ok()?;
+    
+    if !output.status.success() {
+        return None;
+    }
+    
+    String::from_utf8(output.stdout)
+        .ok()?
+        .split_whitespace()
+        .nth(1)?
+        .parse()
+        .ok()
+}
\ No newline at end of file
@@ -0,0 +1,179 @@
+// Input device discovery for backends that read /dev/input directly, and the
+// permission checks behind `selfspy check-input`. Devices are found through
+// sysfs and classified by the properties udev recorded for them
+// (ID_INPUT_KEYBOARD, ID_INPUT_MOUSE, ...), read from its database under
+// /run/udev/data so libudev isn't needed at build time.
+use std::fs::{self, File};
+use std::io;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Path, PathBuf};
+
+use super::{DeviceId, DeviceKind};
+
+#[derive(Debug, Clone)]
+pub struct InputDevice {
+    // Device node, e.g. /dev/input/event3
+    pub path: PathBuf,
+    pub name: String,
+    pub keyboard: bool,
+    pub pointer: bool,
+    pub touchpad: bool,
+    // Attached over the laptop's internal buses rather than USB or Bluetooth
+    pub built_in: bool,
+}
+
+impl InputDevice {
+    // How events from this device are attributed
+    pub fn id(&self) -> DeviceId {
+        let kind = if self.touchpad {
+            DeviceKind::Trackpad
+        } else if self.keyboard {
+            DeviceKind::Keyboard
+        } else if self.pointer {
+            DeviceKind::Mouse
+        } else {
+            DeviceKind::Other
+        };
+        DeviceId {
+            name: self.name.clone(),
+            kind,
+            built_in: self.built_in,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceAccess {
+    Readable,
+    // Opening failed for lack of permission. `group` owns the device node;
+    // `member` says whether this process already runs with that group.
+    Denied { group: Option<String>, member: bool },
+    Failed(String),
+}
+
+// linux/input.h bus types of built-in devices: i8042 (PS/2), I2C, host,
+// SPI (Apple keyboards and trackpads) and RMI (Synaptics touchpads)
+const BUILT_IN_BUSES: &[u16] = &[0x11, 0x18, 0x19, 0x1c, 0x1d];
+
+// Keyboards and pointers, ordered by device node. Without udev data (e.g. in
+// containers) the device name decides.
+pub fn discover() -> Vec<InputDevice> {
+    let Ok(entries) = fs::read_dir("/sys/class/input") else {
+        return Vec::new();
+    };
+    
+    let mut devices: Vec<InputDevice> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| find(&entry.file_name().into_string().ok()?))
+        .collect();
+    
+    devices.sort_by(|a, b| a.path.cmp(&b.path));
+    devices
+}
+
+// A keyboard or pointer by its event node name, e.g. "event3"
+pub fn find(node: &str) -> Option<InputDevice> {
+    if !node.starts_with("event") {
+        return None;
+    }
+    
+    let sys = Path::new("/sys/class/input").join(node);
+    let name = fs::read_to_string(sys.join("device/name"))
+        .map(|name| name.trim().to_string())
+        .unwrap_or_default();
+    let (keyboard, pointer, touchpad) = match udev_properties(&sys) {
+        Some(properties) => {
+            let has = |key: &str| properties.iter().any(|property| property == &format!("{}=1", key));
+            let touchpad = has("ID_INPUT_TOUCHPAD");
+            (
+                has("ID_INPUT_KEYBOARD"),
+                has("ID_INPUT_MOUSE") || touchpad || has("ID_INPUT_POINTINGSTICK") || has("ID_INPUT_TRACKBALL"),
+                touchpad,
+            )
+        }
+        None => {
+            let lower = name.to_lowercase();
+            let touchpad = lower.contains("touchpad") || lower.contains("trackpad");
+            (lower.contains("keyboard"), lower.contains("mouse") || touchpad, touchpad)
+        }
+    };
+    let bus = fs::read_to_string(sys.join("device/id/bustype"))
+        .ok()
+        .and_then(|bus| u16::from_str_radix(bus.trim(), 16).ok());
+    
+    (keyboard || pointer).then(|| InputDevice {
+        path: Path::new("/dev/input").join(node),
+        name,
+        keyboard,
+        pointer,
+        touchpad,
+        built_in: bus.is_some_and(|bus| BUILT_IN_BUSES.contains(&bus)),
+    })
+}
+
+// "E:KEY=value" lines of the udev database entry for a sysfs input node
+fn udev_properties(sys: &Path) -> Option<Vec<String>> {
+    let dev = fs::read_to_string(sys.join("dev")).ok()?;
+    let data = fs::read_to_string(format!("/run/udev/data/c{}", dev.trim())).ok()?;
+    Some(data.lines().filter_map(|line| line.strip_prefix("E:")).map(str::to_string).collect())
+}
+
+pub fn check_access(device: &InputDevice) -> DeviceAccess {
+    match File::open(&device.path) {
+        Ok(_) => DeviceAccess::Readable,
+        Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {
+            let gid = fs::metadata(&device.path).ok().map(|metadata| metadata.gid());
+            DeviceAccess::Denied {
+                group: gid.and_then(group_name),
+                member: gid.is_some_and(|gid| process_groups().contains(&gid)),
+            }
+        }
+        Err(e) => DeviceAccess::Failed(e.to_string()),
+    }
+}
+
+// What the user can do about a device they can't read
+pub fn advice(access: &DeviceAccess) -> Option<String> {
+    match access {
+        DeviceAccess::Denied { group: Some(group), member: false } => Some(format!(
+            "add your user to the `{0}` group (sudo usermod -aG {0} $USER), then log out and back in",
+            group
+        )),
+        DeviceAccess::Denied { group: Some(group), member: true } => Some(format!(
+            "the device isn't readable by `{}`; add a udev rule such as \
+             KERNEL==\"event*\", SUBSYSTEM==\"input\", MODE=\"0660\"",
+            group
+        )),
+        DeviceAccess::Denied { group: None, .. } => {
+            Some("add a udev rule giving your user read access to /dev/input/event*".to_string())
+        }
+        DeviceAccess::Readable | DeviceAccess::Failed(_) => None,
+    }
+}
+
+// Effective and supplementary groups of this process. Groups granted since
+// login only show up in new sessions, which is the usual surprise.
+fn process_groups() -> Vec<u32> {
+    let Ok(status) = fs::read_to_string("/proc/self/status") else {
+        return Vec::new();
+    };
+    
+    let mut groups = Vec::new();
+    for line in status.lines() {
+        if let Some(gids) = line.strip_prefix("Gid:") {
+            groups.extend(gids.split_whitespace().nth(1).and_then(|gid| gid.parse::<u32>().ok()));
+        } else if let Some(gids) = line.strip_prefix("Groups:") {
+            groups.extend(gids.split_whitespace().filter_map(|gid| gid.parse::<u32>().ok()));
+        }
+    }
+    groups
+}
+
+fn group_name(gid: u32) -> Option<String> {
+    let groups = fs::read_to_string("/etc/group").ok()?;
+    groups.lines().find_map(|line| {
+        let mut fields = line.split(':');
+        let name = fields.next()?;
+        (fields.nth(1)?.parse::<u32>().ok()? == gid).then(|| name.to_string())
+    })
+}
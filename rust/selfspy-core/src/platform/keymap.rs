@@ -0,0 +1,176 @@
+// Names for Linux evdev key codes, for backends that read raw input devices
+// and get no keymap from a display server. Keys are translated through the
+// system's XKB keymap (XKB_DEFAULT_* or the layout set with localectl), with
+// a US layout as the fallback when none compiles.
+
+use xkbcommon::xkb;
+
+use super::layout;
+use super::Modifiers;
+
+pub const KEY_LEFTCTRL: u32 = 29;
+pub const KEY_LEFTSHIFT: u32 = 42;
+pub const KEY_RIGHTSHIFT: u32 = 54;
+pub const KEY_LEFTALT: u32 = 56;
+pub const KEY_RIGHTCTRL: u32 = 97;
+pub const KEY_RIGHTALT: u32 = 100;
+pub const KEY_LEFTMETA: u32 = 125;
+pub const KEY_RIGHTMETA: u32 = 126;
+
+// X11/XKB keycodes are evdev codes offset by 8
+const XKB_KEYCODE_OFFSET: u32 = 8;
+// Where systemd-localed and Debian's keyboard-configuration keep the layout
+const LOCALED_KEYBOARD_CONF: &str = "/etc/X11/xorg.conf.d/00-keyboard.conf";
+const DEBIAN_KEYBOARD_CONF: &str = "/etc/default/keyboard";
+
+pub const BTN_LEFT: u32 = 0x110;
+pub const BTN_RIGHT: u32 = 0x111;
+pub const BTN_MIDDLE: u32 = 0x112;
+
+// Tracks held modifiers from raw key presses and releases. Left and right
+// keys are not told apart, so releasing one clears the modifier.
+pub fn update_modifiers(modifiers: &mut Modifiers, code: u32, pressed: bool) {
+    match code {
+        KEY_LEFTCTRL | KEY_RIGHTCTRL => modifiers.ctrl = pressed,
+        KEY_LEFTSHIFT | KEY_RIGHTSHIFT => modifiers.shift = pressed,
+        KEY_LEFTALT | KEY_RIGHTALT => modifiers.alt = pressed,
+        KEY_LEFTMETA | KEY_RIGHTMETA => modifiers.meta = pressed,
+        _ => {}
+    }
+}
+
+// The keyboard state of one reader: held modifiers, locks and the active
+// layout group, as XKB tracks them
+pub struct Keymap {
+    state: Option<xkb::State>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Keymap {
+    pub fn new() -> Self {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        // Empty names make libxkbcommon use XKB_DEFAULT_* and then its defaults
+        let (layout, variant, options) = system_layout().unwrap_or_default();
+        let keymap = xkb::Keymap::new_from_names(
+            &context,
+            "",
+            "",
+            &layout,
+            &variant,
+            options,
+            xkb::COMPILE_NO_FLAGS,
+        );
+        if keymap.is_none() {
+            tracing::warn!("No XKB keymap for layout '{}'; naming keys as on a US layout", layout);
+        }
+        Self {
+            state: keymap.map(|keymap| xkb::State::new(&keymap)),
+        }
+    }
+    
+    // Names a key press or release and updates the keyboard state with it;
+    // every key event must come through here, modifiers included. `shifted`
+    // is only used without a keymap
+    pub fn key(&mut self, code: u32, pressed: bool, shifted: bool) -> Option<String> {
+        let Some(state) = &mut self.state else {
+            return key_name(code, shifted);
+        };
+        let keycode = xkb::Keycode::new(code + XKB_KEYCODE_OFFSET);
+        // Looked up before the update, so a key is named by the state it was
+        // pressed in rather than the one it leaves
+        let name = state_key_name(state, keycode).or_else(|| key_name(code, shifted));
+        let direction = if pressed { xkb::KeyDirection::Down } else { xkb::KeyDirection::Up };
+        state.update_key(keycode, direction);
+        name
+    }
+}
+
+// Printable keys become their character in the active layout group, others
+// "[KeysymName]", like the X11 backend
+fn state_key_name(state: &xkb::State, keycode: xkb::Keycode) -> Option<String> {
+    let keysym = state.key_get_one_sym(keycode);
+    if keysym.raw() == 0 {
+        return None;
+    }
+    if let Some(c) = char::from_u32(xkb::keysym_to_utf32(keysym)).filter(|c| !c.is_control()) {
+        return Some(c.to_string());
+    }
+    Some(format!("[{}]", xkb::keysym_get_name(keysym)))
+}
+
+// Layout, variant and options the system keyboard is configured with, unless
+// XKB_DEFAULT_LAYOUT already says
+fn system_layout() -> Option<(String, String, Option<String>)> {
+    if std::env::var_os("XKB_DEFAULT_LAYOUT").is_some() {
+        return None;
+    }
+    
+    // localed writes `Option "XkbLayout" "de"`, Debian `XKBLAYOUT="de"`
+    let localed = std::fs::read_to_string(LOCALED_KEYBOARD_CONF).ok();
+    let debian = std::fs::read_to_string(DEBIAN_KEYBOARD_CONF).ok();
+    let setting = |localed_name: &str, debian_name: &str| -> Option<String> {
+        let from_localed = localed.as_deref().and_then(|text| {
+            text.lines().find_map(|line| {
+                let rest = line.trim().strip_prefix("Option")?.trim();
+                let rest = rest.strip_prefix(&format!("\"{}\"", localed_name))?;
+                Some(rest.trim().trim_matches('"').to_string())
+            })
+        });
+        let from_debian = || {
+            debian.as_deref().and_then(|text| {
+                text.lines().find_map(|line| {
+                    let value = line.trim().strip_prefix(debian_name)?.strip_prefix('=')?;
+                    Some(value.trim().trim_matches('"').to_string())
+                })
+            })
+        };
+        from_localed.or_else(from_debian).filter(|value| !value.is_empty())
+    };
+    
+    let layout = setting("XkbLayout", "XKBLAYOUT")?;
+    Some((
+        layout,
+        setting("XkbVariant", "XKBVARIANT").unwrap_or_default(),
+        setting("XkbOptions", "XKBOPTIONS"),
+    ))
+}
+
+// Printable keys become their character on a US layout, others a "[Name]"
+// like the X11 backend
+pub fn key_name(code: u32, shifted: bool) -> Option<String> {
+    if let Some((plain, shift)) = layout::us_key(code) {
+        return Some(if shifted { shift } else { plain }.to_string());
+    }
+    
+    let name = match code {
+        1 => "Escape",
+        14 => "BackSpace",
+        15 => "Tab",
+        28 | 96 => "Return",
+        29 | 97 => "Control",
+        42 | 54 => "Shift",
+        56 | 100 => "Alt",
+        58 => "Caps_Lock",
+        59..=68 => return Some(format!("[F{}]", code - 58)),
+        87 => "F11",
+        88 => "F12",
+        102 => "Home",
+        103 => "Up",
+        104 => "Prior",
+        105 => "Left",
+        106 => "Right",
+        107 => "End",
+        108 => "Down",
+        109 => "Next",
+        110 => "Insert",
+        111 => "Delete",
+        125 | 126 => "Super",
+        _ => return None,
+    };
+    Some(format!("[{}]", name))
+}
@@ -0,0 +1,103 @@
+// Keyboard layout normalization, so the same key is recorded the same way on
+// every machine and backend:
+//
+// - Characters are recorded as the Unicode character the layout produced.
+//   X11 reports non-ASCII characters as keysyms ("Cyrillic_a", "eacute"),
+//   which would otherwise be stored as named keys and never counted as typing.
+// - Named keys use one spelling per key; backend-specific aliases
+//   ("ISO_Left_Tab", "Page_Up", "KP_Enter") map to it.
+// - Shortcuts are named by the Latin letter applications act on. On Latin
+//   layouts (QWERTY, AZERTY, Dvorak) that is the typed letter, so Ctrl+Z is
+//   "<Ctrl+Z>" wherever Z sits. On non-Latin layouts it is the US letter at the
+//   key's position, which is what applications fall back to.
+
+// US QWERTY rows by Linux evdev key code: first code, unshifted, shifted
+const US_ROWS: &[(u32, &str, &str)] = &[
+    (2, "1234567890-=", "!@#$%^&*()_+"),
+    (16, "qwertyuiop[]", "QWERTYUIOP{}"),
+    (30, "asdfghjkl;'`", "ASDFGHJKL:\"~"),
+    (43, "\\zxcvbnm,./", "|ZXCVBNM<>?"),
+];
+
+// X11 legacy keysyms for Cyrillic, 0x6c0..=0x6ff
+const CYRILLIC: &str = "юабцдефгхийклмнопярстужвьызшэщчъЮАБЦДЕФГХИЙКЛМНОПЯРСТУЖВЬЫЗШЭЩЧЪ";
+// Greek capitals 0x7c1..=0x7d9 and small letters 0x7e1..=0x7f9; 0x7d3 is unassigned
+const GREEK_CAPITAL: &str = "ΑΒΓΔΕΖΗΘΙΚΛΜΝΞΟΠΡΣ\0ΤΥΦΧΨΩ";
+const GREEK_SMALL: &str = "αβγδεζηθικλμνξοπρσςτυφχψω";
+
+// The characters (unshifted, shifted) at a key position on a US layout
+pub fn us_key(code: u32) -> Option<(&'static str, &'static str)> {
+    if code == 57 {
+        return Some((" ", " "));
+    }
+    US_ROWS.iter().find_map(|(first, plain, shift)| {
+        let index = code.checked_sub(*first)? as usize;
+        Some((plain.get(index..index + 1)?, shift.get(index..index + 1)?))
+    })
+}
+
+// The US letter or digit at a key position, for naming shortcuts
+pub fn us_latin(code: u32) -> Option<char> {
+    us_key(code)
+        .and_then(|(plain, _)| plain.chars().next())
+        .filter(char::is_ascii_alphanumeric)
+}
+
+// The character an X11 keysym types, for the printable keysyms outside ASCII
+pub fn keysym_char(keysym: u32) -> Option<char> {
+    let nth = |table: &str, index: u32| table.chars().nth(index as usize).filter(|c| *c != '\0');
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        // Keysyms for arbitrary Unicode characters
+        0x0100_0100..=0x0110_ffff => char::from_u32(keysym - 0x0100_0000),
+        0x6c0..=0x6ff => nth(CYRILLIC, keysym - 0x6c0),
+        0x7c1..=0x7d9 => nth(GREEK_CAPITAL, keysym - 0x7c1),
+        0x7e1..=0x7f9 => nth(GREEK_SMALL, keysym - 0x7e1),
+        // Arabic letters, Hebrew letters and Thai letters are contiguous with their Unicode blocks
+        0x5c1..=0x5da => char::from_u32(keysym - 0x5c1 + 0x621),
+        0xce0..=0xcfa => char::from_u32(keysym - 0xce0 + 0x5d0),
+        0xda1..=0xdf9 => char::from_u32(keysym - 0xda1 + 0xe01),
+        // Keypad digits and operators
+        0xffaa..=0xffb9 => char::from_u32(keysym - 0xffaa + 0x2a),
+        _ => None,
+    }
+}
+
+// One spelling per named key, following the X11 keysym names the backends
+// already share; None when the name is already canonical
+pub fn canonical_key_name(name: &str) -> Option<&'static str> {
+    let canonical = match name {
+        "ISO_Left_Tab" | "KP_Tab" => "Tab",
+        "KP_Enter" | "Linefeed" => "Return",
+        "Page_Up" | "KP_Prior" | "KP_Page_Up" => "Prior",
+        "Page_Down" | "KP_Next" | "KP_Page_Down" => "Next",
+        "KP_Home" => "Home",
+        "KP_End" => "End",
+        "KP_Insert" => "Insert",
+        "KP_Delete" => "Delete",
+        "KP_Left" => "Left",
+        "KP_Right" => "Right",
+        "KP_Up" => "Up",
+        "KP_Down" => "Down",
+        _ => return None,
+    };
+    Some(canonical)
+}
+
+// The key to name a shortcut by: the typed key when it is Latin, else the
+// Latin letter the backend found at the key's position
+pub fn chord_key(key: &str, latin: Option<char>) -> String {
+    let mut chars = key.chars();
+    match (chars.next(), chars.next(), latin) {
+        (Some(c), None, Some(latin)) if c.is_alphabetic() && !c.is_ascii() && !is_latin_letter(c) => {
+            latin.to_ascii_uppercase().to_string()
+        }
+        _ => key.to_uppercase(),
+    }
+}
+
+// Latin letters with diacritics (é, ß, ø) count as Latin: AZERTY and the
+// Nordic layouts keep their shortcuts on them
+fn is_latin_letter(c: char) -> bool {
+    matches!(c as u32, 0x00c0..=0x024f | 0x1e00..=0x1eff)
+}
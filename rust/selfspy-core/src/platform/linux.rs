@@ -1,42 +1,211 @@
+// X11 backend: the active window comes from EWMH properties on the root
+// window and input is captured globally with the XRecord extension.
 use async_trait::async_trait;
-use anyhow::Result;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int, c_uchar, c_ulong};
+use std::ptr;
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use x11::xlib;
+use x11::xrecord;
+use x11::xss;
 
-use super::{PlatformTracker, WindowInfo, InputEvent};
+use super::{idle, layout, Modifiers, PlatformTracker, WindowInfo, InputEvent, MouseButton};
+use crate::error::{Result, SelfspyError};
+
+// Core protocol event codes as they appear in recorded data
+const KEY_PRESS: u8 = 2;
+const KEY_RELEASE: u8 = 3;
+const BUTTON_PRESS: u8 = 4;
+const MOTION_NOTIFY: u8 = 6;
+
+// Offsets into the 32-byte xEvent wire format (keyButtonPointer)
+const EVENT_ROOT_X: usize = 20;
+const EVENT_ROOT_Y: usize = 22;
+const EVENT_STATE: usize = 28;
+
+// Xlib connection usable from the async side; every call holds the mutex
+struct DisplayHandle(*mut xlib::Display);
+
+unsafe impl Send for DisplayHandle {}
+
+impl DisplayHandle {
+    fn open() -> Result<Self> {
+        let display = unsafe { xlib::XOpenDisplay(ptr::null()) };
+        if display.is_null() {
+            return Err(SelfspyError::Platform("Cannot open X display; is DISPLAY set?".to_string()));
+        }
+        Ok(Self(display))
+    }
+}
+
+impl Drop for DisplayHandle {
+    fn drop(&mut self) {
+        unsafe { xlib::XCloseDisplay(self.0) };
+    }
+}
+
+struct Recording {
+    context: xrecord::XRecordContext,
+    thread: JoinHandle<()>,
+}
 
 pub struct LinuxTracker {
     events: Arc<Mutex<Vec<InputEvent>>>,
+    display: Mutex<Option<DisplayHandle>>,
+    // Control connection for the XRecord context, separate from the data connection
+    control: Mutex<Option<DisplayHandle>>,
+    recording: Mutex<Option<Recording>>,
 }
 
 impl LinuxTracker {
     pub fn new() -> Self {
+        // Several connections are used from different threads
+        unsafe { xlib::XInitThreads() };
+        
         Self {
             events: Arc::new(Mutex::new(Vec::new())),
+            display: Mutex::new(DisplayHandle::open().ok()),
+            control: Mutex::new(None),
+            recording: Mutex::new(None),
+        }
+    }
+    
+    // True when an X server is reachable, so callers can pick another backend
+    pub fn is_available() -> bool {
+        DisplayHandle::open().is_ok()
+    }
+    
+    // Disables the record context, which makes the record thread return
+    fn stop_recording(&self) {
+        let Some(recording) = self.recording.lock().unwrap().take() else {
+            return;
+        };
+        let Some(control) = self.control.lock().unwrap().take() else {
+            return;
+        };
+        
+        unsafe {
+            xrecord::XRecordDisableContext(control.0, recording.context);
+            xlib::XFlush(control.0);
+        }
+        let _ = recording.thread.join();
+        unsafe { xrecord::XRecordFreeContext(control.0, recording.context) };
+    }
+    
+    fn active_window(display: *mut xlib::Display) -> Result<WindowInfo> {
+        unsafe {
+            let root = xlib::XDefaultRootWindow(display);
+            let active = window_property(display, root, "_NET_ACTIVE_WINDOW", xlib::XA_WINDOW)
+                .and_then(|data| data.first().copied())
+                .filter(|window| *window != 0)
+                .ok_or_else(|| SelfspyError::Platform("No active window (EWMH unsupported?)".to_string()))?;
+            
+            let title = utf8_property(display, active, "_NET_WM_NAME")
+                .or_else(|| fetch_name(display, active))
+                .unwrap_or_default();
+            let pid = window_property(display, active, "_NET_WM_PID", xlib::XA_CARDINAL)
+                .and_then(|data| data.first().copied())
+                .map(|pid| pid as u32);
+            let process_name = pid
+                .and_then(process_name)
+                .or_else(|| class_name(display, active))
+                .unwrap_or_else(|| "Unknown".to_string());
+            let geometry = geometry(display, root, active);
+            
+            Ok(WindowInfo {
+                process_name,
+                window_title: title,
+                document: None,
+                bundle_id: None,
+                x: geometry.map(|g| g.0),
+                y: geometry.map(|g| g.1),
+                width: geometry.map(|g| g.2),
+                height: geometry.map(|g| g.3),
+                pid,
+            })
         }
     }
 }
 
 #[async_trait]
 impl PlatformTracker for LinuxTracker {
+    fn backend(&self) -> &'static str {
+        "x11"
+    }
+    
     async fn get_active_window(&self) -> Result<WindowInfo> {
-        // Linux implementation would use X11 or Wayland APIs
-        Ok(WindowInfo {
-            process_name: "Unknown".to_string(),
-            window_title: "Linux Window".to_string(),
-            bundle_id: None,
-            x: None,
-            y: None,
-            width: None,
-            height: None,
-        })
+        let mut display = self.display.lock().unwrap();
+        if display.is_none() {
+            *display = Some(DisplayHandle::open()?);
+        }
+        Self::active_window(display.as_ref().unwrap().0)
     }
     
     async fn start_input_tracking(&self) -> Result<()> {
-        // Would set up X11 event monitoring
+        let mut recording = self.recording.lock().unwrap();
+        if recording.is_some() {
+            return Ok(());
+        }
+        
+        let control = DisplayHandle::open()?;
+        let data = DisplayHandle::open()?;
+        let lookup = DisplayHandle::open()?;
+        
+        let context = unsafe {
+            let mut major = 0;
+            let mut minor = 0;
+            if xrecord::XRecordQueryVersion(control.0, &mut major, &mut minor) == 0 {
+                return Err(SelfspyError::Platform("The X server has no RECORD extension".to_string()));
+            }
+            
+            let range = xrecord::XRecordAllocRange();
+            if range.is_null() {
+                return Err(SelfspyError::Platform("XRecordAllocRange failed".to_string()));
+            }
+            (*range).device_events.first = KEY_PRESS;
+            (*range).device_events.last = MOTION_NOTIFY;
+            
+            let mut clients: xrecord::XRecordClientSpec = xrecord::XRecordAllClients;
+            let mut ranges = range;
+            let context = xrecord::XRecordCreateContext(control.0, 0, &mut clients, 1, &mut ranges, 1);
+            xlib::XFree(range as *mut _);
+            if context == 0 {
+                return Err(SelfspyError::Platform("XRecordCreateContext failed".to_string()));
+            }
+            // The context must reach the server before the data connection enables it
+            xlib::XSync(control.0, xlib::False);
+            context
+        };
+        
+        let state = Box::new(RecordState {
+            events: self.events.clone(),
+            lookup,
+        });
+        
+        let thread = std::thread::Builder::new()
+            .name("selfspy-xrecord".to_string())
+            .spawn(move || {
+                let state = Box::into_raw(state);
+                // Blocks until the context is disabled from the control connection
+                let enabled = unsafe {
+                    xrecord::XRecordEnableContext(data.0, context, Some(record_callback), state as *mut c_char)
+                };
+                if enabled == 0 {
+                    tracing::error!("XRecordEnableContext failed; no input will be recorded");
+                }
+                unsafe { drop(Box::from_raw(state)) };
+                drop(data);
+            })
+            .map_err(SelfspyError::Io)?;
+        
+        *self.control.lock().unwrap() = Some(control);
+        *recording = Some(Recording { context, thread });
         Ok(())
     }
     
     async fn stop_input_tracking(&self) -> Result<()> {
+        self.stop_recording();
         Ok(())
     }
     
@@ -46,4 +215,233 @@ impl PlatformTracker for LinuxTracker {
         events.clear();
         result
     }
+    
+    // The record thread only returns early if the server dropped the context
+    fn is_healthy(&self) -> bool {
+        self.recording
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(true, |recording| !recording.thread.is_finished())
+    }
+    
+    // MIT-SCREEN-SAVER tracks idle time server-side; fall back to the desktop's D-Bus service
+    fn idle_seconds(&self) -> Option<u64> {
+        let display = self.display.lock().unwrap();
+        let server_idle = display.as_ref().and_then(|display| unsafe {
+            let (mut event_base, mut error_base) = (0, 0);
+            if xss::XScreenSaverQueryExtension(display.0, &mut event_base, &mut error_base) == 0 {
+                return None;
+            }
+            let info = xss::XScreenSaverAllocInfo();
+            if info.is_null() {
+                return None;
+            }
+            let root = xlib::XDefaultRootWindow(display.0);
+            let idle_ms = (xss::XScreenSaverQueryInfo(display.0, root, info) != 0).then(|| (*info).idle);
+            xlib::XFree(info as *mut _);
+            idle_ms.map(|ms| ms as u64 / 1000)
+        });
+        server_idle.or_else(idle::session_idle_seconds)
+    }
+}
+
+impl Drop for LinuxTracker {
+    fn drop(&mut self) {
+        self.stop_recording();
+    }
+}
+
+// Owned by the record thread; `lookup` is only used there for keysym names
+struct RecordState {
+    events: Arc<Mutex<Vec<InputEvent>>>,
+    lookup: DisplayHandle,
+}
+
+unsafe extern "C" fn record_callback(closure: *mut c_char, data: *mut xrecord::XRecordInterceptData) {
+    let state = &*(closure as *const RecordState);
+    let intercepted = &*data;
+    
+    if intercepted.category == xrecord::XRecordFromServer && intercepted.data_len * 4 >= 32 {
+        let bytes = std::slice::from_raw_parts(intercepted.data, 32);
+        if let Some(event) = decode_event(state, bytes) {
+            state.events.lock().unwrap().push(event);
+        }
+    }
+    
+    xrecord::XRecordFreeData(data);
+}
+
+fn decode_event(state: &RecordState, bytes: &[u8]) -> Option<InputEvent> {
+    let read_i16 = |offset: usize| i16::from_ne_bytes([bytes[offset], bytes[offset + 1]]) as i32;
+    let detail = bytes[1];
+    let (x, y) = (read_i16(EVENT_ROOT_X), read_i16(EVENT_ROOT_Y));
+    
+    match bytes[0] & 0x7f {
+        KEY_PRESS | KEY_RELEASE => {
+            let mask = u16::from_ne_bytes([bytes[EVENT_STATE], bytes[EVENT_STATE + 1]]) as u32;
+            let modifiers = Modifiers {
+                ctrl: mask & xlib::ControlMask != 0,
+                alt: mask & xlib::Mod1Mask != 0,
+                shift: mask & xlib::ShiftMask != 0,
+                meta: mask & xlib::Mod4Mask != 0,
+            };
+            // XKB keeps the active layout group in bits 13-14 of the state
+            let group = (mask >> 13) & 0x3;
+            let key = key_name(state.lookup.0, detail, group, modifiers.shift)?;
+            // X11 keycodes are evdev codes offset by 8
+            let latin = layout::us_latin((detail as u32).saturating_sub(8));
+            Some(InputEvent::key(&key, latin, modifiers, bytes[0] & 0x7f == KEY_PRESS))
+        }
+        BUTTON_PRESS => match detail {
+            1 => Some(InputEvent::MouseClick { x, y, button: MouseButton::Left, device: None }),
+            2 => Some(InputEvent::MouseClick { x, y, button: MouseButton::Middle, device: None }),
+            3 => Some(InputEvent::MouseClick { x, y, button: MouseButton::Right, device: None }),
+            // Wheel "buttons": 4/5 vertical, 6/7 horizontal
+            4 => Some(InputEvent::MouseScroll { delta_x: 0.0, delta_y: 1.0, device: None }),
+            5 => Some(InputEvent::MouseScroll { delta_x: 0.0, delta_y: -1.0, device: None }),
+            6 => Some(InputEvent::MouseScroll { delta_x: -1.0, delta_y: 0.0, device: None }),
+            7 => Some(InputEvent::MouseScroll { delta_x: 1.0, delta_y: 0.0, device: None }),
+            _ => None,
+        },
+        MOTION_NOTIFY => Some(InputEvent::MouseMove { x, y }),
+        _ => None,
+    }
+}
+
+// Printable keys become their character in the active layout group,
+// everything else "[KeysymName]"
+fn key_name(display: *mut xlib::Display, keycode: c_uchar, group: u32, shifted: bool) -> Option<String> {
+    unsafe {
+        let keysym = xlib::XkbKeycodeToKeysym(display, keycode, group as c_int, shifted as c_int);
+        if keysym == 0 {
+            return None;
+        }
+        if let Some(c) = layout::keysym_char(keysym as u32) {
+            return Some(c.to_string());
+        }
+        
+        let name = xlib::XKeysymToString(keysym);
+        if name.is_null() {
+            return None;
+        }
+        Some(format!("[{}]", CStr::from_ptr(name).to_string_lossy()))
+    }
+}
+
+fn atom(display: *mut xlib::Display, name: &str) -> xlib::Atom {
+    let name = CString::new(name).expect("atom names have no NUL bytes");
+    unsafe { xlib::XInternAtom(display, name.as_ptr(), xlib::False) }
+}
+
+// Format-32 property values (window ids, cardinals) as c_ulongs
+unsafe fn window_property(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    name: &str,
+    property_type: xlib::Atom,
+) -> Option<Vec<c_ulong>> {
+    let (format, items, data) = get_property(display, window, atom(display, name), property_type)?;
+    let values = (format == 32).then(|| std::slice::from_raw_parts(data as *const c_ulong, items).to_vec());
+    xlib::XFree(data as *mut _);
+    values
+}
+
+unsafe fn utf8_property(display: *mut xlib::Display, window: xlib::Window, name: &str) -> Option<String> {
+    let (format, items, data) = get_property(display, window, atom(display, name), atom(display, "UTF8_STRING"))?;
+    let value = (format == 8).then(|| {
+        String::from_utf8_lossy(std::slice::from_raw_parts(data, items)).into_owned()
+    });
+    xlib::XFree(data as *mut _);
+    value
+}
+
+unsafe fn get_property(
+    display: *mut xlib::Display,
+    window: xlib::Window,
+    property: xlib::Atom,
+    property_type: xlib::Atom,
+) -> Option<(c_int, usize, *mut c_uchar)> {
+    let mut actual_type = 0;
+    let mut format = 0;
+    let mut items = 0;
+    let mut remaining = 0;
+    let mut data: *mut c_uchar = ptr::null_mut();
+    
+    let status = xlib::XGetWindowProperty(
+        display,
+        window,
+        property,
+        0,
+        1024,
+        xlib::False,
+        property_type,
+        &mut actual_type,
+        &mut format,
+        &mut items,
+        &mut remaining,
+        &mut data,
+    );
+    if status != xlib::Success as c_int || data.is_null() {
+        return None;
+    }
+    if items == 0 {
+        xlib::XFree(data as *mut _);
+        return None;
+    }
+    Some((format, items as usize, data))
+}
+
+// Legacy WM_NAME for windows without _NET_WM_NAME
+unsafe fn fetch_name(display: *mut xlib::Display, window: xlib::Window) -> Option<String> {
+    let mut name: *mut c_char = ptr::null_mut();
+    if xlib::XFetchName(display, window, &mut name) == 0 || name.is_null() {
+        return None;
+    }
+    let value = CStr::from_ptr(name).to_string_lossy().into_owned();
+    xlib::XFree(name as *mut _);
+    Some(value)
+}
+
+// WM_CLASS class part, used when the pid is unknown (e.g. remote clients)
+unsafe fn class_name(display: *mut xlib::Display, window: xlib::Window) -> Option<String> {
+    let mut hint = xlib::XClassHint {
+        res_name: ptr::null_mut(),
+        res_class: ptr::null_mut(),
+    };
+    if xlib::XGetClassHint(display, window, &mut hint) == 0 {
+        return None;
+    }
+    let class = (!hint.res_class.is_null()).then(|| CStr::from_ptr(hint.res_class).to_string_lossy().into_owned());
+    if !hint.res_name.is_null() {
+        xlib::XFree(hint.res_name as *mut _);
+    }
+    if !hint.res_class.is_null() {
+        xlib::XFree(hint.res_class as *mut _);
+    }
+    class
+}
+
+fn process_name(pid: u32) -> Option<String> {
+    std::fs::read_to_string(format!("/proc/{}/comm", pid))
+        .ok()
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+}
+
+// Root-relative (x, y, width, height) of a window
+unsafe fn geometry(
+    display: *mut xlib::Display,
+    root: xlib::Window,
+    window: xlib::Window,
+) -> Option<(i32, i32, i32, i32)> {
+    let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+    if xlib::XGetWindowAttributes(display, window, &mut attributes) == 0 {
+        return None;
+    }
+    
+    let (mut x, mut y) = (0, 0);
+    let mut child = 0;
+    xlib::XTranslateCoordinates(display, window, root, 0, 0, &mut x, &mut y, &mut child);
+    Some((x, y, attributes.width, attributes.height))
 }
\ No newline at end of file
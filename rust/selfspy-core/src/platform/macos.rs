@@ -1,38 +1,42 @@
 use async_trait::async_trait;
-use anyhow::{Result, anyhow};
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use core_foundation::base::TCFType;
-use core_foundation::string::CFString;
-use core_graphics::event::{CGEvent, CGEventType, CGEventTapLocation, CGEventTapPlacement, CGEventTapOptions};
+use core_foundation::base::{CFGetTypeID, CFRelease, CFTypeRef, TCFType};
+use core_foundation::string::{CFString, CFStringRef};
+use core_graphics::geometry::{CGPoint, CGSize};
 use cocoa::base::{id, nil};
-use cocoa::appkit::{NSWorkspace, NSRunningApplication};
-use objc::runtime::{Object, Sel};
-use objc::{msg_send, sel, sel_impl};
+use objc::{class, msg_send, sel, sel_impl};
 
-use super::{PlatformTracker, WindowInfo, InputEvent, MouseButton};
+use super::{PlatformTracker, WindowInfo, InputEvent};
+use crate::error::{Result, SelfspyError};
 
 pub struct MacOSTracker {
     events: Arc<Mutex<Vec<InputEvent>>>,
+    // Set once we've told the user Accessibility access is missing
+    accessibility_warned: AtomicBool,
 }
 
 impl MacOSTracker {
     pub fn new() -> Self {
         Self {
             events: Arc::new(Mutex::new(Vec::new())),
+            accessibility_warned: AtomicBool::new(false),
         }
     }
     
-    fn get_frontmost_app() -> Result<(String, Option<String>)> {
+    fn get_frontmost_app() -> Result<(String, Option<String>, Option<u32>)> {
         unsafe {
             let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
             let frontmost_app: id = msg_send![workspace, frontmostApplication];
             
             if frontmost_app == nil {
-                return Err(anyhow!("No frontmost application"));
+                return Err(SelfspyError::Platform("No frontmost application".to_string()));
             }
             
             let localized_name: id = msg_send![frontmost_app, localizedName];
             let bundle_id: id = msg_send![frontmost_app, bundleIdentifier];
+            let pid: i32 = msg_send![frontmost_app, processIdentifier];
             
             let name = if localized_name != nil {
                 let name_str: id = msg_send![localized_name, UTF8String];
@@ -54,24 +58,146 @@ impl MacOSTracker {
                 None
             };
             
-            Ok((name, bundle))
+            Ok((name, bundle, u32::try_from(pid).ok()))
         }
     }
+    
+    // Title and frame of the app's focused window; None without Accessibility access
+    fn focused_window(&self, pid: u32) -> Option<FocusedWindow> {
+        if !unsafe { AXIsProcessTrusted() } {
+            if !self.accessibility_warned.swap(true, Ordering::Relaxed) {
+                tracing::warn!(
+                    "Accessibility permission not granted; window titles and geometry will not be recorded. \
+                     Enable it in System Settings > Privacy & Security > Accessibility."
+                );
+            }
+            return None;
+        }
+        self.accessibility_warned.store(false, Ordering::Relaxed);
+        
+        let app = AxElement::application(pid as i32)?;
+        let window = app.copy_attribute("AXFocusedWindow").and_then(AxElement::from_value)?;
+        
+        Some(FocusedWindow {
+            title: window.copy_attribute("AXTitle").and_then(cf_string).unwrap_or_default(),
+            position: window.copy_attribute("AXPosition").and_then(|value| {
+                let mut point = CGPoint::new(0.0, 0.0);
+                ax_value(value, K_AX_VALUE_CG_POINT_TYPE, &mut point).then_some(point)
+            }),
+            size: window.copy_attribute("AXSize").and_then(|value| {
+                let mut size = CGSize::new(0.0, 0.0);
+                ax_value(value, K_AX_VALUE_CG_SIZE_TYPE, &mut size).then_some(size)
+            }),
+        })
+    }
+}
+
+struct FocusedWindow {
+    title: String,
+    position: Option<CGPoint>,
+    size: Option<CGSize>,
+}
+
+type AXUIElementRef = CFTypeRef;
+type AXError = i32;
+
+const K_AX_ERROR_SUCCESS: AXError = 0;
+const K_AX_VALUE_CG_POINT_TYPE: u32 = 1;
+const K_AX_VALUE_CG_SIZE_TYPE: u32 = 2;
+
+// CGEventSourceStateID and CGEventType values for "any input in this session"
+const K_CG_EVENT_SOURCE_STATE_COMBINED_SESSION: i32 = 0;
+const K_CG_ANY_INPUT_EVENT_TYPE: u32 = !0;
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn CGEventSourceSecondsSinceLastEventType(source_state: i32, event_type: u32) -> f64;
+    fn AXIsProcessTrusted() -> bool;
+    fn AXUIElementCreateApplication(pid: i32) -> AXUIElementRef;
+    fn AXUIElementCopyAttributeValue(element: AXUIElementRef, attribute: CFStringRef, value: *mut CFTypeRef) -> AXError;
+    fn AXUIElementGetTypeID() -> usize;
+    fn AXValueGetValue(value: CFTypeRef, value_type: u32, value_ptr: *mut c_void) -> bool;
+}
+
+// Owned AXUIElementRef, released on drop
+struct AxElement(AXUIElementRef);
+
+impl AxElement {
+    fn application(pid: i32) -> Option<Self> {
+        let element = unsafe { AXUIElementCreateApplication(pid) };
+        (!element.is_null()).then(|| Self(element))
+    }
+    
+    fn from_value(value: OwnedCf) -> Option<Self> {
+        if unsafe { CFGetTypeID(value.0) as usize != AXUIElementGetTypeID() } {
+            return None;
+        }
+        let element = value.0;
+        std::mem::forget(value);
+        Some(Self(element))
+    }
+    
+    fn copy_attribute(&self, attribute: &str) -> Option<OwnedCf> {
+        let attribute = CFString::new(attribute);
+        let mut value: CFTypeRef = std::ptr::null();
+        let error = unsafe {
+            AXUIElementCopyAttributeValue(self.0, attribute.as_concrete_TypeRef(), &mut value)
+        };
+        (error == K_AX_ERROR_SUCCESS && !value.is_null()).then(|| OwnedCf(value))
+    }
+}
+
+impl Drop for AxElement {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0) };
+    }
+}
+
+// Owned CF object returned by a Copy function
+struct OwnedCf(CFTypeRef);
+
+impl Drop for OwnedCf {
+    fn drop(&mut self) {
+        unsafe { CFRelease(self.0) };
+    }
+}
+
+fn cf_string(value: OwnedCf) -> Option<String> {
+    unsafe {
+        if CFGetTypeID(value.0) != CFString::type_id() {
+            return None;
+        }
+        Some(CFString::wrap_under_get_rule(value.0 as CFStringRef).to_string())
+    }
+}
+
+fn ax_value<T>(value: OwnedCf, value_type: u32, out: &mut T) -> bool {
+    unsafe { AXValueGetValue(value.0, value_type, out as *mut T as *mut c_void) }
 }
 
 #[async_trait]
 impl PlatformTracker for MacOSTracker {
+    fn backend(&self) -> &'static str {
+        "macos"
+    }
+    
     async fn get_active_window(&self) -> Result<WindowInfo> {
-        let (process_name, bundle_id) = Self::get_frontmost_app()?;
+        let (process_name, bundle_id, pid) = Self::get_frontmost_app()?;
+        // Without Accessibility access only the app is known
+        let window = pid.and_then(|pid| self.focused_window(pid));
+        let position = window.as_ref().and_then(|window| window.position);
+        let size = window.as_ref().and_then(|window| window.size);
         
         Ok(WindowInfo {
             process_name,
-            window_title: "".to_string(), // macOS doesn't easily provide window titles
+            window_title: window.as_ref().map(|window| window.title.clone()).unwrap_or_default(),
+            document: None,
             bundle_id,
-            x: None,
-            y: None,
-            width: None,
-            height: None,
+            x: position.map(|point| point.x as i32),
+            y: position.map(|point| point.y as i32),
+            width: size.map(|size| size.width as i32),
+            height: size.map(|size| size.height as i32),
+            pid,
         })
     }
     
@@ -91,13 +217,12 @@ impl PlatformTracker for MacOSTracker {
         events.clear();
         result
     }
+    
+    fn idle_seconds(&self) -> Option<u64> {
+        let seconds = unsafe {
+            CGEventSourceSecondsSinceLastEventType(K_CG_EVENT_SOURCE_STATE_COMBINED_SESSION, K_CG_ANY_INPUT_EVENT_TYPE)
+        };
+        seconds.is_finite().then(|| seconds.max(0.0) as u64)
+    }
 }
 
-// Helper to get Objective-C class
-fn class(name: &str) -> *mut Object {
-    unsafe {
-        objc::runtime::Class::get(name)
-            .expect(&format!("Class {} not found", name))
-            as *mut Object
-    }
-}
\ No newline at end of file
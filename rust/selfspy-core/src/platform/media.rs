@@ -0,0 +1,106 @@
+// What the system media session is currently playing, for correlating
+// listening habits with activity. Best-effort like `focus`: None when nothing
+// is playing (paused media doesn't count) or the player can't be queried.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NowPlaying {
+    // Player that owns the session, e.g. "spotify" or "Music"
+    pub player: String,
+    pub title: String,
+    pub artist: Option<String>,
+}
+
+#[cfg(target_os = "linux")]
+pub fn now_playing() -> Option<NowPlaying> {
+    // playerctl speaks MPRIS to whichever player is active
+    let output = std::process::Command::new("playerctl")
+        .args(["metadata", "--format", "{{status}}\t{{playerName}}\t{{artist}}\t{{title}}"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    
+    let line = String::from_utf8(output.stdout).ok()?;
+    let mut fields = line.trim_end_matches('\n').splitn(4, '\t');
+    if fields.next()? != "Playing" {
+        return None;
+    }
+    
+    now_playing_from(fields.next()?, fields.next()?, fields.next()?)
+}
+
+#[cfg(target_os = "macos")]
+pub fn now_playing() -> Option<NowPlaying> {
+    // MediaRemote is private; nowplaying-cli wraps it and covers every player.
+    // Without it, ask the two common players directly.
+    if let Ok(output) = std::process::Command::new("nowplaying-cli")
+        .args(["get", "playbackRate", "artist", "title", "clientBundleIdentifier"])
+        .output()
+    {
+        if output.status.success() {
+            let text = String::from_utf8_lossy(&output.stdout);
+            let lines: Vec<&str> = text.lines().map(|line| line.trim()).collect();
+            if let [rate, artist, title, player, ..] = lines[..] {
+                let playing = rate.parse::<f64>().is_ok_and(|rate| rate > 0.0);
+                return if playing { now_playing_from(player, artist, title) } else { None };
+            }
+        }
+    }
+    
+    ["Spotify", "Music"].into_iter().find_map(apple_script_player)
+}
+
+#[cfg(target_os = "macos")]
+fn apple_script_player(app: &str) -> Option<NowPlaying> {
+    // Guarded by `running` so the query doesn't launch the app
+    let script = format!(
+        "if application \"{app}\" is running then tell application \"{app}\" to \
+         if player state is playing then return (artist of current track) & tab & (name of current track)"
+    );
+    let output = std::process::Command::new("osascript").args(["-e", &script]).output().ok()?;
+    let text = String::from_utf8(output.stdout).ok()?;
+    let (artist, title) = text.trim_end().split_once('\t')?;
+    now_playing_from(app, artist, title)
+}
+
+#[cfg(target_os = "windows")]
+pub fn now_playing() -> Option<NowPlaying> {
+    use windows::Media::Control::{
+        GlobalSystemMediaTransportControlsSessionManager as SessionManager,
+        GlobalSystemMediaTransportControlsSessionPlaybackStatus as PlaybackStatus,
+    };
+    
+    // Called from a blocking thread, so waiting on the async operations is fine
+    let manager = SessionManager::RequestAsync().ok()?.get().ok()?;
+    let session = manager.GetCurrentSession().ok()?;
+    if session.GetPlaybackInfo().ok()?.PlaybackStatus().ok()? != PlaybackStatus::Playing {
+        return None;
+    }
+    
+    let properties = session.TryGetMediaPropertiesAsync().ok()?.get().ok()?;
+    let player = session.SourceAppUserModelId().ok()?.to_string();
+    let artist = properties.Artist().ok()?.to_string();
+    let title = properties.Title().ok()?.to_string();
+    now_playing_from(&player, &artist, &title)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+pub fn now_playing() -> Option<NowPlaying> {
+    None
+}
+
+#[allow(dead_code)]
+fn now_playing_from(player: &str, artist: &str, title: &str) -> Option<NowPlaying> {
+    let title = title.trim();
+    if title.is_empty() {
+        return None;
+    }
+    
+    let artist = artist.trim();
+    Some(NowPlaying {
+        player: player.trim().to_string(),
+        title: title.to_string(),
+        artist: (!artist.is_empty() && artist != "null").then(|| artist.to_string()),
+    })
+}
@@ -1,24 +1,194 @@
 use async_trait::async_trait;
-use anyhow::Result;
+use serde::Serialize;
+use std::sync::Arc;
+use crate::error::Result;
 
-#[derive(Debug, Clone)]
+pub mod av_usage;
+pub mod disk;
+pub mod display;
+#[cfg(all(target_os = "linux", feature = "evdev"))]
+pub mod evdev;
+pub mod focus;
+#[cfg(all(target_os = "linux", any(feature = "x11", feature = "wayland")))]
+mod idle;
+#[cfg(target_os = "linux")]
+pub mod input_devices;
+#[cfg(all(target_os = "linux", any(feature = "wayland", feature = "evdev")))]
+mod keymap;
+#[cfg(all(target_os = "linux", feature = "x11"))]
+pub mod linux;
+pub mod layout;
+#[cfg(target_os = "macos")]
+pub mod macos;
+pub mod media;
+pub mod power;
+pub mod screen_share;
+pub mod secure_element;
+pub mod session;
+pub mod supervisor;
+#[cfg(all(target_os = "linux", feature = "wayland"))]
+pub mod wayland;
+#[cfg(target_os = "windows")]
+pub mod windows;
+
+#[derive(Debug, Clone, Default)]
 pub struct WindowInfo {
     pub process_name: String,
     pub window_title: String,
+    // File path or tab name of the open document, where the backend can
+    // read it (Windows UI Automation)
+    pub document: Option<String>,
     pub bundle_id: Option<String>,
     pub x: Option<i32>,
     pub y: Option<i32>,
     pub width: Option<i32>,
     pub height: Option<i32>,
+    pub pid: Option<u32>,
 }
 
+// Key, click and scroll events carry the device they came from on backends
+// that read devices individually (evdev, libinput); None elsewhere
 #[derive(Debug, Clone)]
 pub enum InputEvent {
-    KeyPress { key: String },
-    KeyRelease { key: String },
+    KeyPress { key: String, modifiers: Modifiers, device: Option<Arc<DeviceId>> },
+    KeyRelease { key: String, modifiers: Modifiers, device: Option<Arc<DeviceId>> },
     MouseMove { x: i32, y: i32 },
-    MouseClick { x: i32, y: i32, button: MouseButton },
-    MouseScroll { delta_x: f64, delta_y: f64 },
+    MouseClick { x: i32, y: i32, button: MouseButton, device: Option<Arc<DeviceId>> },
+    MouseScroll { delta_x: f64, delta_y: f64, device: Option<Arc<DeviceId>> },
+}
+
+impl InputEvent {
+    // Backends build key events through here so every platform records the
+    // same spelling: "[Shift]" for a modifier key, "<Ctrl+Shift+T>" for a chord.
+    // `latin` is the Latin letter at the key's position when the backend knows
+    // it, used to name shortcuts typed on non-Latin layouts (see `layout`).
+    pub fn key(key: &str, latin: Option<char>, modifiers: Modifiers, pressed: bool) -> Self {
+        let key = normalize_key(key, latin, modifiers);
+        if pressed {
+            InputEvent::KeyPress { key, modifiers, device: None }
+        } else {
+            InputEvent::KeyRelease { key, modifiers, device: None }
+        }
+    }
+    
+    // Attributes the event to a device; moves are never attributed
+    pub fn from_device(mut self, source: Option<Arc<DeviceId>>) -> Self {
+        match &mut self {
+            InputEvent::KeyPress { device, .. }
+            | InputEvent::KeyRelease { device, .. }
+            | InputEvent::MouseClick { device, .. }
+            | InputEvent::MouseScroll { device, .. } => *device = source,
+            InputEvent::MouseMove { .. } => {}
+        }
+        self
+    }
+    
+    pub fn device(&self) -> Option<&Arc<DeviceId>> {
+        match self {
+            InputEvent::KeyPress { device, .. }
+            | InputEvent::KeyRelease { device, .. }
+            | InputEvent::MouseClick { device, .. }
+            | InputEvent::MouseScroll { device, .. } => device.as_ref(),
+            InputEvent::MouseMove { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeviceKind {
+    Keyboard,
+    Mouse,
+    Trackpad,
+    Other,
+}
+
+impl DeviceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeviceKind::Keyboard => "keyboard",
+            DeviceKind::Mouse => "mouse",
+            DeviceKind::Trackpad => "trackpad",
+            DeviceKind::Other => "other",
+        }
+    }
+}
+
+// The physical device behind an input event
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DeviceId {
+    // Product name as the OS reports it, e.g. "Logitech MX Keys"
+    pub name: String,
+    pub kind: DeviceKind,
+    // Part of the machine (laptop keyboard, trackpad) rather than plugged in
+    pub built_in: bool,
+}
+
+// Modifier keys held when a key event happened. `meta` is Cmd on macOS and
+// the Super / Windows key elsewhere.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Modifiers {
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub meta: bool,
+}
+
+impl Modifiers {
+    // Shift on its own only changes the character typed; the others make a shortcut
+    pub fn is_chord(&self) -> bool {
+        self.ctrl || self.alt || self.meta
+    }
+}
+
+#[cfg(target_os = "macos")]
+const META_LABEL: &str = "Cmd";
+#[cfg(not(target_os = "macos"))]
+const META_LABEL: &str = "Super";
+
+// Backends name keys as typed characters or "[Name]"; this unifies the
+// modifier and named key spellings and turns shortcuts into "<Mods+Key>"
+pub fn normalize_key(key: &str, latin: Option<char>, modifiers: Modifiers) -> String {
+    let name = key
+        .strip_prefix('[')
+        .and_then(|inner| inner.strip_suffix(']'))
+        .filter(|inner| inner.len() >= 2)
+        .map(|inner| layout::canonical_key_name(inner).unwrap_or(inner));
+    
+    if let Some(modifier) = name.and_then(modifier_key_name) {
+        return format!("[{}]", modifier);
+    }
+    if !modifiers.is_chord() {
+        return name.map_or_else(|| key.to_string(), |name| format!("[{}]", name));
+    }
+    
+    let mut parts = Vec::new();
+    if modifiers.ctrl {
+        parts.push("Ctrl".to_string());
+    }
+    if modifiers.alt {
+        parts.push("Alt".to_string());
+    }
+    if modifiers.shift {
+        parts.push("Shift".to_string());
+    }
+    if modifiers.meta {
+        parts.push(META_LABEL.to_string());
+    }
+    parts.push(name.map_or_else(|| layout::chord_key(key, latin), str::to_string));
+    format!("<{}>", parts.join("+"))
+}
+
+// X11 keysyms distinguish left and right ("Control_L"); the other backends don't
+fn modifier_key_name(name: &str) -> Option<&'static str> {
+    match name.to_ascii_lowercase().as_str() {
+        "shift" | "shift_l" | "shift_r" => Some("Shift"),
+        "control" | "control_l" | "control_r" => Some("Control"),
+        "alt" | "alt_l" | "alt_r" | "meta_l" | "meta_r" => Some("Alt"),
+        "super" | "super_l" | "super_r" | "command" => Some("Super"),
+        // The AltGr key of European layouts, which types characters rather than shortcuts
+        "altgr" | "iso_level3_shift" | "mode_switch" => Some("AltGr"),
+        _ => None,
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -38,28 +208,88 @@ impl MouseButton {
     }
 }
 
+// What a tracking backend can record, so users can tell why data is missing
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Capabilities {
+    pub window_titles: bool,
+    pub input: bool,
+    pub idle: bool,
+    pub geometry: bool,
+}
+
+impl Capabilities {
+    pub const ALL: Capabilities = Capabilities {
+        window_titles: true,
+        input: true,
+        idle: true,
+        geometry: true,
+    };
+    
+    // Names of what isn't recorded, e.g. ["window geometry"]
+    pub fn missing(&self) -> Vec<&'static str> {
+        [
+            (self.window_titles, "window titles"),
+            (self.input, "keyboard and mouse input"),
+            (self.idle, "idle time"),
+            (self.geometry, "window geometry"),
+        ]
+        .into_iter()
+        .filter(|(supported, _)| !supported)
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
+
 #[async_trait]
 pub trait PlatformTracker: Send + Sync {
+    // Short name of the backend, e.g. "x11", shown next to its capabilities
+    fn backend(&self) -> &'static str;
+    
+    fn capabilities(&self) -> Capabilities {
+        BACKENDS
+            .iter()
+            .find(|backend| backend.name == self.backend())
+            .map_or_else(Capabilities::default, |backend| backend.capabilities)
+    }
+    
     async fn get_active_window(&self) -> Result<WindowInfo>;
     async fn start_input_tracking(&self) -> Result<()>;
     async fn stop_input_tracking(&self) -> Result<()>;
     fn get_input_events(&self) -> Vec<InputEvent>;
+    
+    // False once the platform hooks have died (e.g. the event tap was
+    // invalidated); the supervisor then recreates the tracker
+    fn is_healthy(&self) -> bool {
+        true
+    }
+    
+    // Seconds since the user last touched any input device, as the OS sees
+    // it (including input we don't record); None when the platform can't tell
+    fn idle_seconds(&self) -> Option<u64> {
+        None
+    }
 }
 
-// Simple fallback implementation for now
+// Used when no backend works here: one "Unknown" window and no input
 pub struct FallbackTracker;
 
 #[async_trait]
 impl PlatformTracker for FallbackTracker {
+    fn backend(&self) -> &'static str {
+        "none"
+    }
+    
     async fn get_active_window(&self) -> Result<WindowInfo> {
         Ok(WindowInfo {
             process_name: "Unknown".to_string(),
             window_title: "Unknown Window".to_string(),
+            document: None,
             bundle_id: None,
             x: None,
             y: None,
             width: None,
             height: None,
+            pid: None,
         })
     }
     
@@ -76,6 +306,194 @@ impl PlatformTracker for FallbackTracker {
     }
 }
 
+// A tracking backend this build knows about, in order of preference
+struct Backend {
+    name: &'static str,
+    capabilities: Capabilities,
+    // Err says why the backend can't run here
+    probe: fn() -> std::result::Result<(), String>,
+    create: fn() -> Result<Box<dyn PlatformTracker>>,
+}
+
+// On Linux, Wayland comes first when the compositor supports it (X11 under
+// XWayland only sees X clients), then X11, then raw input devices without
+// window information
+#[cfg(target_os = "linux")]
+const BACKENDS: &[Backend] = &[
+    Backend {
+        name: "wayland",
+        capabilities: Capabilities {
+            window_titles: true,
+            input: true,
+            idle: true,
+            geometry: false,
+        },
+        probe: probe_wayland,
+        create: create_wayland,
+    },
+    Backend {
+        name: "x11",
+        capabilities: Capabilities::ALL,
+        probe: probe_x11,
+        create: create_x11,
+    },
+    Backend {
+        name: "evdev",
+        capabilities: Capabilities {
+            window_titles: false,
+            input: true,
+            idle: false,
+            geometry: false,
+        },
+        probe: probe_evdev,
+        create: create_evdev,
+    },
+];
+
+#[cfg(target_os = "windows")]
+const BACKENDS: &[Backend] = &[Backend {
+    name: "windows",
+    capabilities: Capabilities::ALL,
+    probe: || Ok(()),
+    create: || Ok(Box::new(windows::WindowsTracker::new())),
+}];
+
+// Titles and geometry need Accessibility access; input isn't captured yet
+#[cfg(target_os = "macos")]
+const BACKENDS: &[Backend] = &[Backend {
+    name: "macos",
+    capabilities: Capabilities {
+        window_titles: true,
+        input: false,
+        idle: true,
+        geometry: true,
+    },
+    probe: || Ok(()),
+    create: || Ok(Box::new(macos::MacOSTracker::new())),
+}];
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+const BACKENDS: &[Backend] = &[];
+
+// Whether a backend can run here, and why not
+#[derive(Debug, Clone, Serialize)]
+pub struct BackendProbe {
+    pub name: &'static str,
+    pub capabilities: Capabilities,
+    // None when the backend is usable
+    pub unavailable: Option<String>,
+}
+
+// Checks every backend without starting any, for `selfspy doctor` and the
+// settings window
+pub fn probe_backends() -> Vec<BackendProbe> {
+    BACKENDS
+        .iter()
+        .map(|backend| BackendProbe {
+            name: backend.name,
+            capabilities: backend.capabilities,
+            unavailable: (backend.probe)().err(),
+        })
+        .collect()
+}
+
+// The most capable backend that starts here, or FallbackTracker when none does
 pub fn create_tracker() -> Box<dyn PlatformTracker> {
+    for backend in BACKENDS {
+        if let Err(reason) = (backend.probe)() {
+            tracing::debug!("Skipping the {} backend: {}", backend.name, reason);
+            continue;
+        }
+        match (backend.create)() {
+            Ok(tracker) => return tracker,
+            Err(e) => tracing::warn!("The {} backend failed to start: {}", backend.name, e),
+        }
+    }
+    
+    tracing::warn!("No tracking backend works here; windows and input won't be recorded");
     Box::new(FallbackTracker)
-}
\ No newline at end of file
+}
+
+#[allow(dead_code)]
+fn not_built(feature: &str) -> String {
+    format!("this build lacks the `{}` feature", feature)
+}
+
+#[cfg(target_os = "linux")]
+fn probe_wayland() -> std::result::Result<(), String> {
+    #[cfg(feature = "wayland")]
+    return if wayland::WaylandTracker::is_available() {
+        Ok(())
+    } else {
+        Err("no Wayland session, or the compositor lacks wlr-foreign-toplevel-management".to_string())
+    };
+    
+    #[cfg(not(feature = "wayland"))]
+    Err(not_built("wayland"))
+}
+
+#[cfg(target_os = "linux")]
+fn create_wayland() -> Result<Box<dyn PlatformTracker>> {
+    #[cfg(feature = "wayland")]
+    return Ok(Box::new(wayland::WaylandTracker::new()?));
+    
+    #[cfg(not(feature = "wayland"))]
+    Err(crate::error::SelfspyError::Platform(not_built("wayland")))
+}
+
+#[cfg(target_os = "linux")]
+fn probe_x11() -> std::result::Result<(), String> {
+    #[cfg(feature = "x11")]
+    return if linux::LinuxTracker::is_available() {
+        Ok(())
+    } else {
+        Err("no X server is reachable through $DISPLAY".to_string())
+    };
+    
+    #[cfg(not(feature = "x11"))]
+    Err(not_built("x11"))
+}
+
+#[cfg(target_os = "linux")]
+fn create_x11() -> Result<Box<dyn PlatformTracker>> {
+    #[cfg(feature = "x11")]
+    return Ok(Box::new(linux::LinuxTracker::new()));
+    
+    #[cfg(not(feature = "x11"))]
+    Err(crate::error::SelfspyError::Platform(not_built("x11")))
+}
+
+#[cfg(target_os = "linux")]
+fn probe_evdev() -> std::result::Result<(), String> {
+    #[cfg(feature = "evdev")]
+    return if evdev::EvdevTracker::is_available() {
+        Ok(())
+    } else {
+        Err("no keyboard or pointer under /dev/input is readable; see `selfspy check-input`".to_string())
+    };
+    
+    #[cfg(not(feature = "evdev"))]
+    Err(not_built("evdev"))
+}
+
+#[cfg(target_os = "linux")]
+fn create_evdev() -> Result<Box<dyn PlatformTracker>> {
+    #[cfg(feature = "evdev")]
+    return Ok(Box::new(evdev::EvdevTracker::new()));
+    
+    #[cfg(not(feature = "evdev"))]
+    Err(crate::error::SelfspyError::Platform(not_built("evdev")))
+}
+
+// Stdout of a command that succeeded, for backends that read state from
+// system tools; None when it can't run or fails
+#[cfg(any(target_os = "macos", target_os = "linux", target_os = "windows"))]
+pub(crate) fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = std::process::Command::new(program).args(args).output().ok()?;
+    
+    if !output.status.success() {
+        return None;
+    }
+    
+    String::from_utf8(output.stdout).ok()
+}
@@ -0,0 +1,84 @@
+// Power source and battery charge. Best-effort like the other probes: None on
+// machines without a battery or when the state can't be read.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PowerState {
+    pub on_battery: bool,
+    // Charge in percent, when the battery reports it
+    pub percent: Option<f64>,
+}
+
+#[cfg(target_os = "linux")]
+pub fn power_state() -> Option<PowerState> {
+    let supplies = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    let read = |dir: &std::path::Path, name: &str| {
+        std::fs::read_to_string(dir.join(name)).map(|value| value.trim().to_string()).ok()
+    };
+    
+    let mut plugged_in = false;
+    let mut discharging = false;
+    let mut charges = Vec::new();
+    for supply in supplies.filter_map(|entry| entry.ok()) {
+        let dir = supply.path();
+        match read(&dir, "type").as_deref() {
+            Some("Mains") | Some("USB") => plugged_in |= read(&dir, "online").as_deref() == Some("1"),
+            // Mice and headsets report batteries too, with scope Device
+            Some("Battery") if read(&dir, "scope").as_deref() != Some("Device") => {
+                discharging |= read(&dir, "status").as_deref() == Some("Discharging");
+                charges.extend(read(&dir, "capacity").and_then(|capacity| capacity.parse::<f64>().ok()));
+            }
+            _ => {}
+        }
+    }
+    
+    if charges.is_empty() {
+        return None;
+    }
+    Some(PowerState {
+        on_battery: discharging && !plugged_in,
+        percent: Some(charges.iter().sum::<f64>() / charges.len() as f64),
+    })
+}
+
+#[cfg(target_os = "macos")]
+pub fn power_state() -> Option<PowerState> {
+    // Now drawing from 'Battery Power'
+    //  -InternalBattery-0 (id=4653155)	85%; discharging; 3:10 remaining present: true
+    let output = std::process::Command::new("pmset").args(["-g", "batt"]).output().ok()?;
+    let text = String::from_utf8(output.stdout).ok()?;
+    let battery = text.lines().find(|line| line.contains("InternalBattery"))?;
+    let percent = battery
+        .split_whitespace()
+        .find_map(|field| field.strip_suffix("%;"))
+        .and_then(|percent| percent.parse::<f64>().ok());
+    
+    Some(PowerState {
+        on_battery: text.lines().next().is_some_and(|line| line.contains("Battery Power")),
+        percent,
+    })
+}
+
+#[cfg(target_os = "windows")]
+pub fn power_state() -> Option<PowerState> {
+    use windows::Win32::System::Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+    
+    const AC_OFFLINE: u8 = 0;
+    const NO_SYSTEM_BATTERY: u8 = 128;
+    const UNKNOWN: u8 = 255;
+    
+    let mut status = SYSTEM_POWER_STATUS::default();
+    unsafe { GetSystemPowerStatus(&mut status) }.ok()?;
+    if status.BatteryFlag == NO_SYSTEM_BATTERY || status.BatteryFlag == UNKNOWN {
+        return None;
+    }
+    
+    Some(PowerState {
+        on_battery: status.ACLineStatus == AC_OFFLINE,
+        percent: (status.BatteryLifePercent != UNKNOWN).then_some(status.BatteryLifePercent as f64),
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+pub fn power_state() -> Option<PowerState> {
+    None
+}
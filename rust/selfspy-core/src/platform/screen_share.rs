@@ -0,0 +1,67 @@
+// Detection of active screen sharing / mirroring so text capture can be
+// paused while the screen is visible to others. Best-effort like `focus`.
+
+#[cfg(target_os = "macos")]
+mod ffi {
+    pub type CGDirectDisplayID = u32;
+    
+    #[link(name = "CoreGraphics", kind = "framework")]
+    extern "C" {
+        pub fn CGMainDisplayID() -> CGDirectDisplayID;
+        pub fn CGDisplayIsCaptured(display: CGDirectDisplayID) -> u32;
+        pub fn CGDisplayIsInMirrorSet(display: CGDirectDisplayID) -> u32;
+    }
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_screen_shared() -> bool {
+    // A captured or mirrored main display means someone else can see it
+    unsafe {
+        let display = ffi::CGMainDisplayID();
+        ffi::CGDisplayIsCaptured(display) != 0 || ffi::CGDisplayIsInMirrorSet(display) != 0
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn is_screen_shared() -> bool {
+    // Portal screencast and remote desktop sessions live under
+    // /org/freedesktop/portal/desktop/session while they are active
+    let Ok(output) = std::process::Command::new("busctl")
+        .args(["--user", "tree", "--list", "org.freedesktop.portal.Desktop"])
+        .output()
+    else {
+        return false;
+    };
+    
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .any(|path| path.trim().starts_with("/org/freedesktop/portal/desktop/session/"))
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_screen_shared() -> bool {
+    // Graphics capture consent records keep LastUsedTimeStop at zero while a
+    // capture session is running
+    let Ok(output) = std::process::Command::new("reg")
+        .args([
+            "query",
+            r"HKCU\Software\Microsoft\Windows\CurrentVersion\CapabilityAccessManager\ConsentStore\graphicsCaptureProgrammatic",
+            "/s",
+            "/v",
+            "LastUsedTimeStop",
+        ])
+        .output()
+    else {
+        return false;
+    };
+    
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.contains("LastUsedTimeStop"))
+        .any(|line| line.trim_end().ends_with("0x0"))
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+pub fn is_screen_shared() -> bool {
+    false
+}
\ No newline at end of file
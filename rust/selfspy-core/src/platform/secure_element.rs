@@ -0,0 +1,389 @@
+// Sealing the data-encryption key to this machine's secure element, for
+// `"key_storage": "platform"`. The sealed key can only be unsealed by the
+// chip that sealed it, so a copy of the database is useless elsewhere:
+//
+//   Linux    TPM 2.0 through tpm2-tools (tpm2_createprimary, tpm2_create,
+//            tpm2_load, tpm2_unseal), under the owner hierarchy
+//   Windows  an RSA key in the TPM-backed Microsoft Platform Crypto Provider
+//   macOS    an EC key in the Secure Enclave, kept in the data protection
+//            keychain (the binary needs a keychain access group entitlement)
+//
+// Unsealed keys only pass through wiped buffers.
+use zeroize::Zeroizing;
+
+use crate::error::Result;
+
+pub use imp::BACKEND;
+
+pub fn seal(key: &[u8]) -> Result<Vec<u8>> {
+    imp::seal(key)
+}
+
+pub fn unseal(sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    imp::unseal(sealed)
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::process::{Command, Stdio};
+    use zeroize::Zeroizing;
+    
+    use crate::error::{Result, SelfspyError};
+    
+    pub const BACKEND: &str = "tpm2";
+    
+    // The sealed form: the object's public part, length-prefixed (u32 LE),
+    // then its private part, which only this TPM can load
+    pub fn seal(key: &[u8]) -> Result<Vec<u8>> {
+        let dir = WorkDir::new()?;
+        let primary = create_primary(&dir)?;
+        let (public, private) = (dir.file("sealed.pub"), dir.file("sealed.priv"));
+        tpm2(
+            Command::new("tpm2_create")
+                .arg("-C")
+                .arg(&primary)
+                .args(["-i", "-", "-u"])
+                .arg(&public)
+                .arg("-r")
+                .arg(&private),
+            Some(key),
+        )?;
+        
+        let public = std::fs::read(&public)?;
+        let private = std::fs::read(&private)?;
+        let mut sealed = (public.len() as u32).to_le_bytes().to_vec();
+        sealed.extend_from_slice(&public);
+        sealed.extend_from_slice(&private);
+        Ok(sealed)
+    }
+    
+    pub fn unseal(sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let invalid = || SelfspyError::Encryption("Invalid sealed key".to_string());
+        let (length, rest) = sealed.split_first_chunk::<4>().ok_or_else(invalid)?;
+        let length = u32::from_le_bytes(*length) as usize;
+        if length > rest.len() {
+            return Err(invalid());
+        }
+        
+        let dir = WorkDir::new()?;
+        let (public, private, object) = (dir.file("sealed.pub"), dir.file("sealed.priv"), dir.file("sealed.ctx"));
+        std::fs::write(&public, &rest[..length])?;
+        std::fs::write(&private, &rest[length..])?;
+        let primary = create_primary(&dir)?;
+        tpm2(
+            Command::new("tpm2_load")
+                .arg("-C")
+                .arg(&primary)
+                .arg("-u")
+                .arg(&public)
+                .arg("-r")
+                .arg(&private)
+                .arg("-c")
+                .arg(&object),
+            None,
+        )?;
+        tpm2(Command::new("tpm2_unseal").arg("-c").arg(&object), None)
+    }
+    
+    // The TPM derives the same primary key from its owner seed every time,
+    // so it never has to be stored
+    fn create_primary(dir: &WorkDir) -> Result<PathBuf> {
+        let context = dir.file("primary.ctx");
+        tpm2(Command::new("tpm2_createprimary").args(["-C", "o", "-c"]).arg(&context), None)?;
+        Ok(context)
+    }
+    
+    // Runs a tpm2-tools command, feeding `input` on stdin rather than through
+    // a file, and returns its stdout
+    fn tpm2(command: &mut Command, input: Option<&[u8]>) -> Result<Zeroizing<Vec<u8>>> {
+        let program = command.get_program().to_string_lossy().into_owned();
+        let mut child = command
+            .stdin(if input.is_some() { Stdio::piped() } else { Stdio::null() })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::NotFound => SelfspyError::Encryption(format!(
+                    "{} not found; install tpm2-tools to seal keys with the TPM",
+                    program
+                )),
+                _ => SelfspyError::Io(e),
+            })?;
+        if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+            stdin.write_all(input)?;
+        }
+        
+        let output = child.wait_with_output()?;
+        let stdout = Zeroizing::new(output.stdout);
+        if !output.status.success() {
+            return Err(SelfspyError::Encryption(format!(
+                "{} failed: {}",
+                program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            )));
+        }
+        Ok(stdout)
+    }
+    
+    // A private directory for the context files, removed afterwards. They
+    // hold no secrets (private parts are encrypted by the TPM), but loaded
+    // contexts are only meaningful to this process.
+    struct WorkDir(PathBuf);
+    
+    impl WorkDir {
+        fn new() -> Result<Self> {
+            use std::os::unix::fs::DirBuilderExt;
+            let nanos = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.subsec_nanos());
+            let path = std::env::temp_dir().join(format!("selfspy-tpm-{}-{}", std::process::id(), nanos));
+            std::fs::DirBuilder::new().mode(0o700).create(&path)?;
+            Ok(Self(path))
+        }
+        
+        fn file(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+    
+    impl Drop for WorkDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod imp {
+    use windows::core::HSTRING;
+    use windows::Win32::Security::Cryptography::{
+        NCryptCreatePersistedKey, NCryptDecrypt, NCryptEncrypt, NCryptFinalizeKey, NCryptFreeObject, NCryptOpenKey,
+        NCryptOpenStorageProvider, BCRYPT_OAEP_PADDING_INFO, BCRYPT_RSA_ALGORITHM, BCRYPT_SHA256_ALGORITHM,
+        CERT_KEY_SPEC, MS_PLATFORM_CRYPTO_PROVIDER, NCRYPT_FLAGS, NCRYPT_HANDLE, NCRYPT_KEY_HANDLE,
+        NCRYPT_PAD_OAEP_FLAG, NCRYPT_PROV_HANDLE,
+    };
+    use zeroize::Zeroizing;
+    
+    use crate::error::{Result, SelfspyError};
+    
+    pub const BACKEND: &str = "windows-pcp";
+    
+    // Persisted per user in the provider, created on first seal
+    const KEY_NAME: &str = "selfspy data key";
+    
+    pub fn seal(key: &[u8]) -> Result<Vec<u8>> {
+        let handle = KeyHandle::open(true)?;
+        crypt(&handle, key, true).map(|sealed| sealed.to_vec())
+    }
+    
+    pub fn unseal(sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let handle = KeyHandle::open(false)?;
+        crypt(&handle, sealed, false)
+    }
+    
+    // RSA-OAEP with SHA-256, done inside the TPM
+    fn crypt(handle: &KeyHandle, input: &[u8], encrypt: bool) -> Result<Zeroizing<Vec<u8>>> {
+        let padding = BCRYPT_OAEP_PADDING_INFO {
+            pszAlgId: BCRYPT_SHA256_ALGORITHM,
+            pbLabel: std::ptr::null_mut(),
+            cbLabel: 0,
+        };
+        let padding = Some(&padding as *const BCRYPT_OAEP_PADDING_INFO as *const std::ffi::c_void);
+        let call = |output: Option<&mut [u8]>, size: &mut u32| unsafe {
+            if encrypt {
+                NCryptEncrypt(handle.key, Some(input), padding, output, size, NCRYPT_PAD_OAEP_FLAG)
+            } else {
+                NCryptDecrypt(handle.key, Some(input), padding, output, size, NCRYPT_PAD_OAEP_FLAG)
+            }
+        };
+        
+        let mut size = 0u32;
+        call(None, &mut size).map_err(|e| failed("sizing", e))?;
+        let mut output = Zeroizing::new(vec![0u8; size as usize]);
+        let action = if encrypt { "sealing" } else { "unsealing" };
+        call(Some(&mut output[..]), &mut size).map_err(|e| failed(action, e))?;
+        output.truncate(size as usize);
+        Ok(output)
+    }
+    
+    struct KeyHandle {
+        provider: NCRYPT_PROV_HANDLE,
+        key: NCRYPT_KEY_HANDLE,
+    }
+    
+    impl KeyHandle {
+        fn open(create: bool) -> Result<Self> {
+            let name = HSTRING::from(KEY_NAME);
+            let mut handle = Self {
+                provider: NCRYPT_PROV_HANDLE::default(),
+                key: NCRYPT_KEY_HANDLE::default(),
+            };
+            unsafe {
+                NCryptOpenStorageProvider(&mut handle.provider, MS_PLATFORM_CRYPTO_PROVIDER, 0)
+                    .map_err(|e| failed("opening the Platform Crypto Provider (is there a TPM?)", e))?;
+                let opened = NCryptOpenKey(handle.provider, &mut handle.key, &name, CERT_KEY_SPEC(0), NCRYPT_FLAGS(0));
+                if opened.is_err() {
+                    if !create {
+                        return Err(SelfspyError::Encryption(format!(
+                            "The TPM key '{}' that sealed the encryption key is gone",
+                            KEY_NAME
+                        )));
+                    }
+                    NCryptCreatePersistedKey(
+                        handle.provider,
+                        &mut handle.key,
+                        BCRYPT_RSA_ALGORITHM,
+                        &name,
+                        CERT_KEY_SPEC(0),
+                        NCRYPT_FLAGS(0),
+                    )
+                    .map_err(|e| failed("creating the TPM key", e))?;
+                    NCryptFinalizeKey(handle.key, NCRYPT_FLAGS(0)).map_err(|e| failed("creating the TPM key", e))?;
+                }
+            }
+            Ok(handle)
+        }
+    }
+    
+    impl Drop for KeyHandle {
+        fn drop(&mut self) {
+            unsafe {
+                if self.key.0 != 0 {
+                    let _ = NCryptFreeObject(NCRYPT_HANDLE(self.key.0));
+                }
+                if self.provider.0 != 0 {
+                    let _ = NCryptFreeObject(NCRYPT_HANDLE(self.provider.0));
+                }
+            }
+        }
+    }
+    
+    fn failed(action: &str, error: windows::core::Error) -> SelfspyError {
+        SelfspyError::Encryption(format!("TPM {} failed: {}", action, error))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod imp {
+    use core_foundation::base::TCFType;
+    use core_foundation::data::{CFData, CFDataRef};
+    use core_foundation::error::{CFError, CFErrorRef};
+    use security_framework::access_control::{ProtectionMode, SecAccessControl};
+    use security_framework::item::{ItemClass, ItemSearchOptions, KeyClass, Location, Reference, SearchResult};
+    use security_framework::key::{Algorithm, GenerateKeyOptions, KeyType, SecKey, Token};
+    use security_framework_sys::access_control::kSecAccessControlPrivateKeyUsage;
+    use security_framework_sys::base::SecKeyRef;
+    use security_framework_sys::key::{SecKeyAlgorithm, SecKeyCreateDecryptedData, SecKeyCreateEncryptedData};
+    use zeroize::Zeroizing;
+    
+    use crate::error::{Result, SelfspyError};
+    
+    pub const BACKEND: &str = "secure-enclave";
+    
+    // Label of the Secure Enclave key, created on first seal
+    const KEY_LABEL: &str = "selfspy data key";
+    
+    // ECIES: an ephemeral key agreed with the enclave key, then AES-GCM
+    const ALGORITHM: Algorithm = Algorithm::ECIESEncryptionCofactorVariableIVX963SHA256AESGCM;
+    
+    type Operation = unsafe extern "C" fn(SecKeyRef, SecKeyAlgorithm, CFDataRef, *mut CFErrorRef) -> CFDataRef;
+    
+    pub fn seal(key: &[u8]) -> Result<Vec<u8>> {
+        let enclave_key = match find_key() {
+            Some(enclave_key) => enclave_key,
+            None => create_key()?,
+        };
+        let public = enclave_key
+            .public_key()
+            .ok_or_else(|| SelfspyError::Encryption("The Secure Enclave key has no public key".to_string()))?;
+        run(&public, SecKeyCreateEncryptedData, key)
+            .map_err(|e| SelfspyError::Encryption(format!("Sealing with the Secure Enclave failed: {}", e)))
+    }
+    
+    pub fn unseal(sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        let enclave_key = find_key().ok_or_else(|| {
+            SelfspyError::Encryption(format!(
+                "The Secure Enclave key '{}' that sealed the encryption key is gone",
+                KEY_LABEL
+            ))
+        })?;
+        run(&enclave_key, SecKeyCreateDecryptedData, sealed)
+            .map(Zeroizing::new)
+            .map_err(|e| SelfspyError::Encryption(format!("Unsealing with the Secure Enclave failed: {}", e)))
+    }
+    
+    // SecKeyCreateEncryptedData / SecKeyCreateDecryptedData, which the
+    // security-framework wrapper doesn't expose. CoreFoundation's own copy of
+    // the output is released unwiped; only ours is wiped.
+    fn run(key: &SecKey, operation: Operation, input: &[u8]) -> std::result::Result<Vec<u8>, CFError> {
+        let input = CFData::from_buffer(input);
+        let mut error: CFErrorRef = std::ptr::null_mut();
+        let output = unsafe {
+            operation(key.as_concrete_TypeRef(), ALGORITHM.into(), input.as_concrete_TypeRef(), &mut error)
+        };
+        if output.is_null() {
+            return Err(unsafe { CFError::wrap_under_create_rule(error) });
+        }
+        let output = unsafe { CFData::wrap_under_create_rule(output) };
+        Ok(output.bytes().to_vec())
+    }
+    
+    fn find_key() -> Option<SecKey> {
+        let results = ItemSearchOptions::new()
+            .class(ItemClass::key())
+            .key_class(KeyClass::private())
+            .label(KEY_LABEL)
+            .load_refs(true)
+            .search()
+            .ok()?;
+        results.into_iter().find_map(|result| match result {
+            SearchResult::Ref(Reference::Key(key)) => Some(key),
+            _ => None,
+        })
+    }
+    
+    // The key never leaves the enclave. Its access control only lets it be
+    // used for private key operations while the Mac is unlocked and never
+    // migrates it to another device; the keychain only hands it to binaries
+    // signed with the same access group.
+    fn create_key() -> Result<SecKey> {
+        let access_control = SecAccessControl::create_with_protection(
+            Some(ProtectionMode::AccessibleWhenUnlockedThisDeviceOnly),
+            kSecAccessControlPrivateKeyUsage,
+        )
+        .map_err(|e| SelfspyError::Encryption(format!("Creating the Secure Enclave access control failed: {}", e)))?;
+        let mut options = GenerateKeyOptions::default();
+        options
+            .set_key_type(KeyType::ec())
+            .set_size_in_bits(256)
+            .set_label(KEY_LABEL)
+            .set_token(Token::SecureEnclave)
+            .set_location(Location::DataProtectionKeychain)
+            .set_access_control(access_control);
+        SecKey::new(&options)
+            .map_err(|e| SelfspyError::Encryption(format!("Creating the Secure Enclave key failed: {}", e)))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+mod imp {
+    use zeroize::Zeroizing;
+    
+    use crate::error::{Result, SelfspyError};
+    
+    pub const BACKEND: &str = "none";
+    
+    pub fn seal(_key: &[u8]) -> Result<Vec<u8>> {
+        Err(unsupported())
+    }
+    
+    pub fn unseal(_sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+        Err(unsupported())
+    }
+    
+    fn unsupported() -> SelfspyError {
+        SelfspyError::Encryption("No secure element support on this platform".to_string())
+    }
+}
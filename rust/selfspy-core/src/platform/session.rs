@@ -0,0 +1,160 @@
+// Detection of a locked screen, so time behind the lock screen isn't mistaken
+// for the user sitting idle, and of system sleep. Where logind is running its
+// signals report both as they happen. Elsewhere the monitor polls
+// `is_screen_locked`, which is best-effort like `focus` ("not locked" when the
+// state can't be determined), and takes long jumps in the wall clock between
+// ticks for sleep.
+
+// A change pushed by the session manager
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    // Signals started (true) or stopped (false) arriving; while they aren't,
+    // the monitor falls back to polling
+    Watching(bool),
+    Locked(bool),
+    // Sent just before a suspend (true) and right after the resume (false)
+    Sleeping(bool),
+}
+
+#[cfg(target_os = "linux")]
+pub use logind::{subscribe, SessionSignals};
+
+// logind's PrepareForSleep signal and the LockedHint property of this
+// session, which screen lockers integrated with systemd keep up to date
+#[cfg(target_os = "linux")]
+mod logind {
+    use super::SessionEvent;
+    use futures::StreamExt;
+    use zbus::proxy::{PropertyStream, SignalStream};
+    use zbus::zvariant::OwnedObjectPath;
+    use zbus::{Connection, Proxy};
+    
+    const LOGIND: &str = "org.freedesktop.login1";
+    
+    pub struct SessionSignals {
+        // Kept alive with the streams they feed
+        _manager: Proxy<'static>,
+        session: Proxy<'static>,
+        sleep: SignalStream<'static>,
+        locked: PropertyStream<'static, bool>,
+        started: bool,
+    }
+    
+    // None when there is no system bus or no logind on it
+    pub async fn subscribe() -> Option<SessionSignals> {
+        let connection = Connection::system().await.ok()?;
+        let manager = Proxy::new(&connection, LOGIND, "/org/freedesktop/login1", "org.freedesktop.login1.Manager")
+            .await
+            .ok()?;
+        let id = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+        let path: OwnedObjectPath = manager.call("GetSession", &(id.as_str(),)).await.ok()?;
+        let session = Proxy::new(&connection, LOGIND, path.into_inner(), "org.freedesktop.login1.Session")
+            .await
+            .ok()?;
+        
+        let sleep = manager.receive_signal("PrepareForSleep").await.ok()?;
+        let locked = session.receive_property_changed::<bool>("LockedHint").await;
+        Some(SessionSignals {
+            _manager: manager,
+            session,
+            sleep,
+            locked,
+            started: false,
+        })
+    }
+    
+    impl SessionSignals {
+        // The current lock state first, then changes as they arrive. None
+        // once the connection to logind is lost.
+        pub async fn next(&mut self) -> Option<SessionEvent> {
+            if !self.started {
+                self.started = true;
+                let locked = self.session.get_property::<bool>("LockedHint").await.unwrap_or(false);
+                return Some(SessionEvent::Locked(locked));
+            }
+            
+            loop {
+                tokio::select! {
+                    message = self.sleep.next() => {
+                        if let Ok(sleeping) = message?.body().deserialize::<bool>() {
+                            return Some(SessionEvent::Sleeping(sleeping));
+                        }
+                    }
+                    changed = self.locked.next() => {
+                        if let Ok(locked) = changed?.get().await {
+                            return Some(SessionEvent::Locked(locked));
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// No session manager to listen to; the monitor polls instead
+#[cfg(not(target_os = "linux"))]
+pub struct SessionSignals;
+
+#[cfg(not(target_os = "linux"))]
+impl SessionSignals {
+    pub async fn next(&mut self) -> Option<SessionEvent> {
+        None
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub async fn subscribe() -> Option<SessionSignals> {
+    None
+}
+
+#[cfg(target_os = "macos")]
+pub fn is_screen_locked() -> bool {
+    // The window server publishes the lock flag in the session dictionary,
+    // which ioreg prints for the root node
+    super::command_output("ioreg", &["-n", "Root", "-d1"])
+        .map(|out| {
+            out.lines()
+                .any(|line| line.contains("CGSSessionScreenIsLocked") && line.contains("Yes"))
+        })
+        .unwrap_or(false)
+}
+
+#[cfg(target_os = "linux")]
+pub fn is_screen_locked() -> bool {
+    // logind tracks the lock state for screen lockers that report it
+    // (GNOME, KDE, swaylock via systemd integration, xss-lock)
+    let session = std::env::var("XDG_SESSION_ID").unwrap_or_else(|_| "auto".to_string());
+    if let Some(out) = super::command_output("loginctl", &["show-session", &session, "-p", "LockedHint", "--value"]) {
+        if out.trim() == "yes" {
+            return true;
+        }
+    }
+    
+    // Freedesktop screen savers that don't update logind
+    super::command_output(
+        "busctl",
+        &[
+            "--user",
+            "call",
+            "org.freedesktop.ScreenSaver",
+            "/org/freedesktop/ScreenSaver",
+            "org.freedesktop.ScreenSaver",
+            "GetActive",
+        ],
+    )
+    .map(|out| out.trim() == "b true")
+    .unwrap_or(false)
+}
+
+#[cfg(target_os = "windows")]
+pub fn is_screen_locked() -> bool {
+    // The lock screen runs in LogonUI.exe, which only exists while it is shown
+    super::command_output("tasklist", &["/FI", "IMAGENAME eq LogonUI.exe", "/NH"])
+        .map(|out| out.contains("LogonUI.exe"))
+        .unwrap_or(false)
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", target_os = "windows")))]
+pub fn is_screen_locked() -> bool {
+    false
+}
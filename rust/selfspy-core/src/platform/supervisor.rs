@@ -0,0 +1,161 @@
+// Keeps the platform layer alive: when the tracker reports itself unhealthy
+// (event tap invalidated, X server restarted) or keeps failing, it is torn
+// down and recreated, backing off exponentially between attempts so a
+// tracker that dies again right away isn't rebuilt on every poll.
+use async_trait::async_trait;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{error, info, warn};
+
+use super::{Capabilities, InputEvent, PlatformTracker, WindowInfo};
+use crate::error::Result;
+
+// Consecutive window lookups that must fail before the tracker is considered dead
+const FAILURE_THRESHOLD: u32 = 10;
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(300);
+// A tracker that lasts this long after a restart starts the backoff over
+const STABLE_PERIOD: Duration = Duration::from_secs(600);
+
+type TrackerFactory = Box<dyn Fn() -> Box<dyn PlatformTracker> + Send + Sync>;
+
+struct SupervisorState {
+    consecutive_failures: u32,
+    backoff: Duration,
+    next_attempt: Option<Instant>,
+    last_restart: Option<Instant>,
+    input_started: bool,
+    restarts: u64,
+}
+
+pub struct SupervisedTracker {
+    factory: TrackerFactory,
+    inner: RwLock<Arc<dyn PlatformTracker>>,
+    state: Mutex<SupervisorState>,
+}
+
+impl SupervisedTracker {
+    pub fn new(factory: impl Fn() -> Box<dyn PlatformTracker> + Send + Sync + 'static) -> Self {
+        let inner: Arc<dyn PlatformTracker> = Arc::from(factory());
+        Self {
+            factory: Box::new(factory),
+            inner: RwLock::new(inner),
+            state: Mutex::new(SupervisorState {
+                consecutive_failures: 0,
+                backoff: INITIAL_BACKOFF,
+                next_attempt: None,
+                last_restart: None,
+                input_started: false,
+                restarts: 0,
+            }),
+        }
+    }
+    
+    // Number of times the tracker has been recreated
+    pub fn restarts(&self) -> u64 {
+        self.state.lock().unwrap().restarts
+    }
+    
+    fn current(&self) -> Arc<dyn PlatformTracker> {
+        self.inner.read().unwrap().clone()
+    }
+    
+    fn record_result(&self, ok: bool) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if ok {
+            state.consecutive_failures = 0;
+        } else {
+            state.consecutive_failures += 1;
+        }
+        state.consecutive_failures >= FAILURE_THRESHOLD
+    }
+    
+    async fn restart(&self, reason: &str) {
+        let input_started = {
+            let mut state = self.state.lock().unwrap();
+            let now = Instant::now();
+            if state.next_attempt.is_some_and(|at| now < at) {
+                return;
+            }
+            if state.last_restart.is_some_and(|at| now.duration_since(at) >= STABLE_PERIOD) {
+                state.backoff = INITIAL_BACKOFF;
+            }
+            // Every attempt, failed or not, pushes the next one further out
+            state.last_restart = Some(now);
+            state.next_attempt = Some(now + state.backoff);
+            state.backoff = (state.backoff * 2).min(MAX_BACKOFF);
+            state.input_started
+        };
+        
+        warn!("Platform tracker {}; recreating it", reason);
+        let old = self.current();
+        if let Err(e) = old.stop_input_tracking().await {
+            warn!("Failed to stop the old tracker cleanly: {}", e);
+        }
+        
+        let replacement: Arc<dyn PlatformTracker> = Arc::from((self.factory)());
+        if input_started {
+            if let Err(e) = replacement.start_input_tracking().await {
+                let state = self.state.lock().unwrap();
+                let retry = state.next_attempt.map(|at| at.saturating_duration_since(Instant::now()));
+                error!("Failed to restart input tracking, retrying in {:?}: {}", retry.unwrap_or_default(), e);
+                return;
+            }
+        }
+        
+        *self.inner.write().unwrap() = replacement;
+        
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures = 0;
+        state.restarts += 1;
+        info!("Platform tracker recreated (restart #{})", state.restarts);
+    }
+}
+
+#[async_trait]
+impl PlatformTracker for SupervisedTracker {
+    fn backend(&self) -> &'static str {
+        self.current().backend()
+    }
+    
+    fn capabilities(&self) -> Capabilities {
+        self.current().capabilities()
+    }
+    
+    async fn get_active_window(&self) -> Result<WindowInfo> {
+        let tracker = self.current();
+        
+        if !tracker.is_healthy() {
+            self.restart("reported its hooks are no longer working").await;
+        }
+        
+        let result = self.current().get_active_window().await;
+        if self.record_result(result.is_ok()) {
+            self.restart("keeps failing to read the active window").await;
+        }
+        result
+    }
+    
+    async fn start_input_tracking(&self) -> Result<()> {
+        self.current().start_input_tracking().await?;
+        self.state.lock().unwrap().input_started = true;
+        Ok(())
+    }
+    
+    async fn stop_input_tracking(&self) -> Result<()> {
+        self.state.lock().unwrap().input_started = false;
+        self.current().stop_input_tracking().await
+    }
+    
+    fn get_input_events(&self) -> Vec<InputEvent> {
+        self.current().get_input_events()
+    }
+    
+    fn is_healthy(&self) -> bool {
+        self.current().is_healthy()
+    }
+    
+    fn idle_seconds(&self) -> Option<u64> {
+        self.current().idle_seconds()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,351 @@
+// Wayland backend for wlroots compositors (Sway, Hyprland, river, ...): the
+// focused window comes from wlr-foreign-toplevel-management and input from
+// libinput, which reads /dev/input directly (the user must be in the `input`
+// group). Wayland exposes no global pointer position, so click and motion
+// coordinates are a virtual position built from relative motion.
+use async_trait::async_trait;
+use input::event::keyboard::{KeyState, KeyboardEventTrait};
+use input::event::pointer::{Axis, ButtonState, PointerEvent, PointerScrollEvent};
+use input::event::{Event, EventTrait, KeyboardEvent};
+use input::{Libinput, LibinputInterface};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use wayland_client::backend::ObjectId;
+use wayland_client::globals::{registry_queue_init, GlobalListContents};
+use wayland_client::protocol::wl_registry;
+use wayland_client::{event_created_child, Connection, Dispatch, Proxy, QueueHandle};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_handle_v1::{
+    self, ZwlrForeignToplevelHandleV1,
+};
+use wayland_protocols_wlr::foreign_toplevel::v1::client::zwlr_foreign_toplevel_manager_v1::{
+    self, ZwlrForeignToplevelManagerV1,
+};
+
+use super::{input_devices, keymap, layout};
+use super::{idle, DeviceId, Modifiers, PlatformTracker, WindowInfo, InputEvent, MouseButton};
+use crate::error::{Result, SelfspyError};
+
+// How long the input thread waits for events before checking for stop
+const INPUT_POLL_TIMEOUT_MS: i32 = 250;
+
+#[derive(Debug, Clone, Default)]
+struct Toplevel {
+    title: String,
+    app_id: String,
+    activated: bool,
+}
+
+pub struct WaylandTracker {
+    active: Arc<Mutex<Option<Toplevel>>>,
+    toplevel_thread: JoinHandle<()>,
+    events: Arc<Mutex<Vec<InputEvent>>>,
+    input: Mutex<Option<(Arc<AtomicBool>, JoinHandle<()>)>>,
+}
+
+impl WaylandTracker {
+    // Connects to the compositor; fails if it lacks wlr-foreign-toplevel-management
+    pub fn new() -> Result<Self> {
+        let connection = Connection::connect_to_env().map_err(platform_error)?;
+        let (globals, mut queue) = registry_queue_init::<ToplevelState>(&connection).map_err(platform_error)?;
+        let handle = queue.handle();
+        let manager: ZwlrForeignToplevelManagerV1 = globals.bind(&handle, 1..=3, ()).map_err(platform_error)?;
+        
+        let active = Arc::new(Mutex::new(None));
+        let mut state = ToplevelState {
+            toplevels: HashMap::new(),
+            active: active.clone(),
+        };
+        
+        let toplevel_thread = std::thread::Builder::new()
+            .name("selfspy-wayland".to_string())
+            .spawn(move || {
+                let _manager = manager;
+                while queue.blocking_dispatch(&mut state).is_ok() {}
+                tracing::warn!("Lost the Wayland connection; window tracking stopped");
+            })?;
+        
+        Ok(Self {
+            active,
+            toplevel_thread,
+            events: Arc::new(Mutex::new(Vec::new())),
+            input: Mutex::new(None),
+        })
+    }
+    
+    // A Wayland session whose compositor supports the toplevel protocol
+    pub fn is_available() -> bool {
+        std::env::var_os("WAYLAND_DISPLAY").is_some()
+            && Connection::connect_to_env()
+                .ok()
+                .and_then(|connection| registry_queue_init::<ToplevelState>(&connection).ok())
+                .is_some_and(|(globals, _)| {
+                    let wanted = ZwlrForeignToplevelManagerV1::interface().name;
+                    globals
+                        .contents()
+                        .with_list(|list| list.iter().any(|global| global.interface == wanted))
+                })
+    }
+}
+
+fn platform_error(error: impl std::fmt::Display) -> SelfspyError {
+    SelfspyError::Platform(format!("Wayland: {}", error))
+}
+
+#[async_trait]
+impl PlatformTracker for WaylandTracker {
+    fn backend(&self) -> &'static str {
+        "wayland"
+    }
+    
+    async fn get_active_window(&self) -> Result<WindowInfo> {
+        let active = self
+            .active
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| SelfspyError::Platform("No focused Wayland window".to_string()))?;
+        
+        // The protocol carries no pid or geometry; the app id stands in for the process
+        Ok(WindowInfo {
+            process_name: active.app_id,
+            window_title: active.title,
+            document: None,
+            bundle_id: None,
+            x: None,
+            y: None,
+            width: None,
+            height: None,
+            pid: None,
+        })
+    }
+    
+    async fn start_input_tracking(&self) -> Result<()> {
+        let mut input = self.input.lock().unwrap();
+        if input.is_some() {
+            return Ok(());
+        }
+        
+        let mut libinput = Libinput::new_with_udev(DeviceAccess);
+        libinput
+            .udev_assign_seat("seat0")
+            .map_err(|_| SelfspyError::Platform("libinput could not assign seat0".to_string()))?;
+        
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread = {
+            let stop = stop.clone();
+            let events = self.events.clone();
+            std::thread::Builder::new()
+                .name("selfspy-libinput".to_string())
+                .spawn(move || read_input(libinput, events, stop))?
+        };
+        
+        *input = Some((stop, thread));
+        Ok(())
+    }
+    
+    async fn stop_input_tracking(&self) -> Result<()> {
+        if let Some((stop, thread)) = self.input.lock().unwrap().take() {
+            stop.store(true, Ordering::Relaxed);
+            let _ = thread.join();
+        }
+        Ok(())
+    }
+    
+    fn get_input_events(&self) -> Vec<InputEvent> {
+        let mut events = self.events.lock().unwrap();
+        let result = events.clone();
+        events.clear();
+        result
+    }
+    
+    fn is_healthy(&self) -> bool {
+        let input_alive = self
+            .input
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(true, |(_, thread)| !thread.is_finished());
+        input_alive && !self.toplevel_thread.is_finished()
+    }
+    
+    // Wayland has no core idle query for clients; ask the desktop instead
+    fn idle_seconds(&self) -> Option<u64> {
+        idle::session_idle_seconds()
+    }
+}
+
+struct ToplevelState {
+    toplevels: HashMap<ObjectId, Toplevel>,
+    active: Arc<Mutex<Option<Toplevel>>>,
+}
+
+impl Dispatch<wl_registry::WlRegistry, GlobalListContents> for ToplevelState {
+    fn event(
+        _: &mut Self,
+        _: &wl_registry::WlRegistry,
+        _: wl_registry::Event,
+        _: &GlobalListContents,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+    }
+}
+
+impl Dispatch<ZwlrForeignToplevelManagerV1, ()> for ToplevelState {
+    fn event(
+        state: &mut Self,
+        _: &ZwlrForeignToplevelManagerV1,
+        event: zwlr_foreign_toplevel_manager_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let zwlr_foreign_toplevel_manager_v1::Event::Toplevel { toplevel } = event {
+            state.toplevels.insert(toplevel.id(), Toplevel::default());
+        }
+    }
+    
+    event_created_child!(ToplevelState, ZwlrForeignToplevelManagerV1, [
+        zwlr_foreign_toplevel_manager_v1::EVT_TOPLEVEL_OPCODE => (ZwlrForeignToplevelHandleV1, ()),
+    ]);
+}
+
+impl Dispatch<ZwlrForeignToplevelHandleV1, ()> for ToplevelState {
+    fn event(
+        state: &mut Self,
+        handle: &ZwlrForeignToplevelHandleV1,
+        event: zwlr_foreign_toplevel_handle_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        use zwlr_foreign_toplevel_handle_v1::Event as ToplevelEvent;
+        
+        let id = handle.id();
+        match event {
+            ToplevelEvent::Title { title } => state.toplevels.entry(id).or_default().title = title,
+            ToplevelEvent::AppId { app_id } => state.toplevels.entry(id).or_default().app_id = app_id,
+            ToplevelEvent::State { state: flags } => {
+                let activated = flags
+                    .chunks_exact(4)
+                    .map(|chunk| u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                    .any(|flag| flag == zwlr_foreign_toplevel_handle_v1::State::Activated as u32);
+                state.toplevels.entry(id).or_default().activated = activated;
+            }
+            // Changes are atomic per toplevel; publish once they are complete
+            ToplevelEvent::Done => {
+                if let Some(toplevel) = state.toplevels.get(&id).filter(|toplevel| toplevel.activated) {
+                    *state.active.lock().unwrap() = Some(toplevel.clone());
+                }
+            }
+            ToplevelEvent::Closed => {
+                state.toplevels.remove(&id);
+                handle.destroy();
+            }
+            _ => {}
+        }
+    }
+}
+
+// Opens input devices on libinput's behalf
+struct DeviceAccess;
+
+impl LibinputInterface for DeviceAccess {
+    fn open_restricted(&mut self, path: &Path, flags: i32) -> std::result::Result<OwnedFd, i32> {
+        OpenOptions::new()
+            .custom_flags(flags)
+            .read(true)
+            .write((flags & libc::O_ACCMODE == libc::O_WRONLY) || (flags & libc::O_ACCMODE == libc::O_RDWR))
+            .open(path)
+            .map(OwnedFd::from)
+            .map_err(|e| {
+                tracing::warn!("Cannot open {}: {} (is the user in the `input` group?)", path.display(), e);
+                e.raw_os_error().unwrap_or(libc::EACCES)
+            })
+    }
+    
+    fn close_restricted(&mut self, fd: OwnedFd) {
+        drop(File::from(fd));
+    }
+}
+
+fn read_input(mut libinput: Libinput, events: Arc<Mutex<Vec<InputEvent>>>, stop: Arc<AtomicBool>) {
+    let mut modifiers = Modifiers::default();
+    let mut keyboard = keymap::Keymap::new();
+    let (mut x, mut y) = (0.0f64, 0.0f64);
+    let mut devices = HashMap::new();
+    
+    while !stop.load(Ordering::Relaxed) {
+        let mut poll = libc::pollfd {
+            fd: libinput.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        if unsafe { libc::poll(&mut poll, 1, INPUT_POLL_TIMEOUT_MS) } <= 0 {
+            continue;
+        }
+        if let Err(e) = libinput.dispatch() {
+            tracing::error!("libinput dispatch failed: {}", e);
+            return;
+        }
+        
+        let mut batch = Vec::new();
+        for event in &mut libinput {
+            match event {
+                Event::Keyboard(KeyboardEvent::Key(key)) => {
+                    let code = key.key();
+                    let pressed = key.key_state() == KeyState::Pressed;
+                    keymap::update_modifiers(&mut modifiers, code, pressed);
+                    if let Some(name) = keyboard.key(code, pressed, modifiers.shift) {
+                        let device = device_id(&mut devices, &key.device());
+                        batch.push(InputEvent::key(&name, layout::us_latin(code), modifiers, pressed).from_device(device));
+                    }
+                }
+                Event::Pointer(PointerEvent::Motion(motion)) => {
+                    x += motion.dx();
+                    y += motion.dy();
+                    batch.push(InputEvent::MouseMove { x: x as i32, y: y as i32 });
+                }
+                Event::Pointer(PointerEvent::Button(event)) if event.button_state() == ButtonState::Pressed => {
+                    let button = match event.button() {
+                        keymap::BTN_LEFT => MouseButton::Left,
+                        keymap::BTN_RIGHT => MouseButton::Right,
+                        keymap::BTN_MIDDLE => MouseButton::Middle,
+                        _ => continue,
+                    };
+                    let device = device_id(&mut devices, &event.device());
+                    batch.push(InputEvent::MouseClick { x: x as i32, y: y as i32, button, device });
+                }
+                Event::Pointer(PointerEvent::ScrollWheel(scroll)) => {
+                    let value = |axis: Axis| if scroll.has_axis(axis) { scroll.scroll_value(axis) } else { 0.0 };
+                    batch.push(InputEvent::MouseScroll {
+                        delta_x: value(Axis::Horizontal),
+                        delta_y: -value(Axis::Vertical),
+                        device: device_id(&mut devices, &scroll.device()),
+                    });
+                }
+                _ => {}
+            }
+        }
+        
+        if !batch.is_empty() {
+            events.lock().unwrap().extend(batch);
+        }
+    }
+}
+
+// The device behind a libinput device, looked up in sysfs once per event node
+fn device_id(cache: &mut HashMap<String, Option<Arc<DeviceId>>>, device: &input::Device) -> Option<Arc<DeviceId>> {
+    if let Some(id) = cache.get(device.sysname()) {
+        return id.clone();
+    }
+    let id = input_devices::find(device.sysname()).map(|found| Arc::new(found.id()));
+    cache.insert(device.sysname().to_string(), id.clone());
+    id
+}
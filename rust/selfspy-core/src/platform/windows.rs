@@ -1,42 +1,379 @@
+// Windows backend: the foreground window from GetForegroundWindow, input from
+// low-level keyboard and mouse hooks. Low-level hooks are called on the thread
+// that installed them, which must pump messages, so they live on a dedicated
+// thread that is stopped by posting WM_QUIT to it. The open document or tab
+// of known editors and browsers is read through UI Automation.
 use async_trait::async_trait;
-use anyhow::Result;
+use crate::error::{Result, SelfspyError};
+use std::cell::RefCell;
+use std::sync::mpsc;
 use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tracing::warn;
+use windows::core::{PWSTR, VARIANT};
+use windows::Win32::Foundation::{CloseHandle, BOOL, HANDLE, HINSTANCE, HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Storage::Packaging::Appx::GetApplicationUserModelId;
+use windows::Win32::System::Com::{CoCreateInstance, CoInitializeEx, CLSCTX_INPROC_SERVER, COINIT_MULTITHREADED};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::System::SystemInformation::GetTickCount;
+use windows::Win32::System::Threading::{
+    GetCurrentThreadId, OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+    PROCESS_QUERY_LIMITED_INFORMATION,
+};
+use windows::Win32::UI::Accessibility::{
+    CUIAutomation, IUIAutomation, TreeScope_Descendants, UIA_ControlTypePropertyId,
+    UIA_SelectionItemIsSelectedPropertyId, UIA_TabItemControlTypeId,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{
+    GetKeyState, GetLastInputInfo, ToUnicode, LASTINPUTINFO, VIRTUAL_KEY, VK_BACK, VK_CAPITAL, VK_CONTROL, VK_DELETE, VK_DOWN, VK_END,
+    VK_ESCAPE, VK_F1, VK_F24, VK_HOME, VK_INSERT, VK_LCONTROL, VK_LEFT, VK_LMENU, VK_LSHIFT, VK_LWIN,
+    VK_MENU, VK_NEXT, VK_PRIOR, VK_RCONTROL, VK_RETURN, VK_RIGHT, VK_RMENU, VK_RSHIFT, VK_RWIN, VK_SHIFT,
+    VK_TAB, VK_UP,
+};
+use windows::Win32::UI::WindowsAndMessaging::{
+    CallNextHookEx, DispatchMessageW, EnumChildWindows, GetForegroundWindow, GetMessageW, GetWindowRect,
+    GetWindowTextW, GetWindowThreadProcessId, PostThreadMessageW, SetWindowsHookExW, TranslateMessage,
+    UnhookWindowsHookEx, HHOOK, KBDLLHOOKSTRUCT, MSG, MSLLHOOKSTRUCT, WHEEL_DELTA, WH_KEYBOARD_LL,
+    WH_MOUSE_LL, WM_KEYDOWN, WM_KEYUP, WM_LBUTTONDOWN, WM_MBUTTONDOWN, WM_MOUSEHWHEEL, WM_MOUSEMOVE,
+    WM_MOUSEWHEEL, WM_QUIT, WM_RBUTTONDOWN, WM_SYSKEYDOWN, WM_SYSKEYUP,
+};
 
-use super::{PlatformTracker, WindowInfo, InputEvent};
+use super::{Modifiers, PlatformTracker, WindowInfo, InputEvent, MouseButton};
+
+// Store apps are hosted inside this frame process; the real app owns a child CoreWindow
+const UWP_FRAME_HOST: &str = "ApplicationFrameHost.exe";
+
+// AppUserModelID package names that don't turn into a readable name on their own
+const KNOWN_PACKAGES: &[(&str, &str)] = &[
+    ("microsoft.windowscommunicationsapps", "Mail"),
+    ("Microsoft.WindowsCalculator", "Calculator"),
+    ("Microsoft.WindowsStore", "Microsoft Store"),
+    ("Microsoft.ZuneMusic", "Media Player"),
+    ("Microsoft.ZuneVideo", "Movies & TV"),
+    ("5319275A.WhatsAppDesktop", "WhatsApp"),
+];
+
+// Apps whose selected tab names the open file or page. Editors that show the
+// full path in the tab's tooltip expose it as the tab's help text.
+const DOCUMENT_APPS: &[&str] = &[
+    "chrome.exe",
+    "msedge.exe",
+    "firefox.exe",
+    "brave.exe",
+    "Code.exe",
+    "devenv.exe",
+    "notepad.exe",
+    "notepad++.exe",
+    "sublime_text.exe",
+];
+
+// A hung app can stall a UI Automation query indefinitely; past this the
+// window is recorded without its document
+const DOCUMENT_LOOKUP_TIMEOUT: Duration = Duration::from_secs(2);
+
+// ToUnicode flag: translate without touching the keyboard state, so dead keys
+// still compose in the application the user is typing into
+const TO_UNICODE_NO_STATE_CHANGE: u32 = 0x4;
+
+// Buffer the hook procedures on the hook thread write into
+thread_local! {
+    static HOOK_EVENTS: RefCell<Option<Arc<Mutex<Vec<InputEvent>>>>> = const { RefCell::new(None) };
+}
+
+// UI Automation client for the calling thread, created on first use
+thread_local! {
+    static AUTOMATION: RefCell<Option<IUIAutomation>> = const { RefCell::new(None) };
+}
+
+struct HookThread {
+    thread_id: u32,
+    thread: JoinHandle<()>,
+}
 
 pub struct WindowsTracker {
     events: Arc<Mutex<Vec<InputEvent>>>,
+    hooks: Mutex<Option<HookThread>>,
+    // Document of the last window looked up, keyed by window handle and
+    // title; a new tab or file changes the title, so the UI tree is only
+    // walked when it does
+    document: Mutex<Option<(isize, String, Option<String>)>>,
 }
 
 impl WindowsTracker {
     pub fn new() -> Self {
         Self {
             events: Arc::new(Mutex::new(Vec::new())),
+            hooks: Mutex::new(None),
+            document: Mutex::new(None),
         }
     }
+    
+    fn stop_hooks(&self) {
+        if let Some(hooks) = self.hooks.lock().unwrap().take() {
+            unsafe {
+                let _ = PostThreadMessageW(hooks.thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+            }
+            let _ = hooks.thread.join();
+        }
+    }
+    
+    fn foreground_process() -> Option<(HWND, u32, String)> {
+        unsafe {
+            let hwnd = GetForegroundWindow();
+            if hwnd.0.is_null() {
+                return None;
+            }
+            
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid));
+            let name = process_image_name(pid)?;
+            Some((hwnd, pid, name))
+        }
+    }
+    
+    // `handle` is the window's HWND as an integer, which can cross threads
+    async fn document(&self, handle: isize, process_name: &str, title: &str) -> Option<String> {
+        if !DOCUMENT_APPS.iter().any(|app| app.eq_ignore_ascii_case(process_name)) {
+            return None;
+        }
+        
+        if let Some((cached_handle, cached_title, document)) = &*self.document.lock().unwrap() {
+            if *cached_handle == handle && cached_title == title {
+                return document.clone();
+            }
+        }
+        
+        // The UI tree is walked off the async runtime. A lookup that times out
+        // is cached as no document, so a hung window isn't queried again until
+        // its title changes.
+        let lookup = tokio::task::spawn_blocking(move || selected_tab(HWND(handle as *mut _)));
+        let document = match tokio::time::timeout(DOCUMENT_LOOKUP_TIMEOUT, lookup).await {
+            Ok(Ok(document)) => document,
+            Ok(Err(_)) => None,
+            Err(_) => {
+                warn!("Timed out reading the selected tab of {}", process_name);
+                None
+            }
+        };
+        *self.document.lock().unwrap() = Some((handle, title.to_string(), document.clone()));
+        document
+    }
+    
+    // Resolve the Store app behind an ApplicationFrameHost window to
+    // (display name, AppUserModelID, pid)
+    fn resolve_uwp_app(frame: HWND, frame_pid: u32) -> Option<(String, String, u32)> {
+        let mut search = ChildSearch { frame_pid, app_pid: None };
+        
+        unsafe {
+            let _ = EnumChildWindows(
+                frame,
+                Some(find_app_child),
+                LPARAM(&mut search as *mut ChildSearch as isize),
+            );
+        }
+        
+        let app_pid = search.app_pid?;
+        let aumid = app_user_model_id(app_pid)?;
+        Some((friendly_app_name(&aumid), aumid, app_pid))
+    }
+}
+
+struct ChildSearch {
+    frame_pid: u32,
+    app_pid: Option<u32>,
+}
+
+unsafe extern "system" fn find_app_child(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    let search = &mut *(lparam.0 as *mut ChildSearch);
+    
+    let mut pid = 0u32;
+    GetWindowThreadProcessId(hwnd, Some(&mut pid));
+    
+    if pid != 0 && pid != search.frame_pid {
+        search.app_pid = Some(pid);
+        return BOOL(0); // Stop enumerating
+    }
+    
+    BOOL(1)
+}
+
+fn window_title(hwnd: HWND) -> String {
+    let mut buffer = [0u16; 512];
+    let len = unsafe { GetWindowTextW(hwnd, &mut buffer) };
+    String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+}
+
+fn window_rect(hwnd: HWND) -> Option<RECT> {
+    let mut rect = RECT::default();
+    unsafe { GetWindowRect(hwnd, &mut rect) }.ok()?;
+    Some(rect)
+}
+
+fn automation() -> Option<IUIAutomation> {
+    AUTOMATION.with(|automation| {
+        let mut automation = automation.borrow_mut();
+        if automation.is_none() {
+            unsafe {
+                // Fails harmlessly when the thread already joined an apartment
+                let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+                *automation = CoCreateInstance(&CUIAutomation, None, CLSCTX_INPROC_SERVER).ok();
+            }
+        }
+        automation.clone()
+    })
+}
+
+// The full path from the selected tab's tooltip when it has one, otherwise
+// the tab's name
+fn selected_tab(hwnd: HWND) -> Option<String> {
+    let automation = automation()?;
+    
+    let tab = unsafe {
+        let window = automation.ElementFromHandle(hwnd).ok()?;
+        let is_tab = automation
+            .CreatePropertyCondition(UIA_ControlTypePropertyId, &VARIANT::from(UIA_TabItemControlTypeId.0))
+            .ok()?;
+        let is_selected = automation
+            .CreatePropertyCondition(UIA_SelectionItemIsSelectedPropertyId, &VARIANT::from(true))
+            .ok()?;
+        let condition = automation.CreateAndCondition(&is_tab, &is_selected).ok()?;
+        window.FindFirst(TreeScope_Descendants, &condition).ok()?
+    };
+    
+    let help_text = unsafe { tab.CurrentHelpText() }.map(|text| text.to_string()).unwrap_or_default();
+    let first_line = help_text.lines().next().unwrap_or_default().trim();
+    if is_windows_path(first_line) {
+        return Some(first_line.to_string());
+    }
+    
+    let name = unsafe { tab.CurrentName() }.ok()?.to_string();
+    let name = name.trim();
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+// "C:\..." or "\\server\share\..."
+fn is_windows_path(text: &str) -> bool {
+    text.starts_with("\\\\")
+        || (text.get(1..3) == Some(":\\") && text.starts_with(|c: char| c.is_ascii_alphabetic()))
+}
+
+fn open_process(pid: u32) -> Option<HANDLE> {
+    unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok() }
+}
+
+fn process_image_name(pid: u32) -> Option<String> {
+    let handle = open_process(pid)?;
+    let mut buffer = [0u16; 1024];
+    let mut len = buffer.len() as u32;
+    
+    let result = unsafe {
+        QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(buffer.as_mut_ptr()), &mut len)
+    };
+    unsafe {
+        let _ = CloseHandle(handle);
+    }
+    result.ok()?;
+    
+    let path = String::from_utf16_lossy(&buffer[..len as usize]);
+    path.rsplit('\\').next().map(str::to_string)
+}
+
+fn app_user_model_id(pid: u32) -> Option<String> {
+    let handle = open_process(pid)?;
+    let mut buffer = [0u16; 512];
+    let mut len = buffer.len() as u32;
+    
+    let result = unsafe { GetApplicationUserModelId(handle, &mut len, PWSTR(buffer.as_mut_ptr())) };
+    unsafe {
+        let _ = CloseHandle(handle);
+    }
+    
+    if result.is_err() || len == 0 {
+        return None;
+    }
+    
+    // Length includes the terminating null
+    Some(String::from_utf16_lossy(&buffer[..(len as usize).saturating_sub(1)]))
+}
+
+// "5319275A.WhatsAppDesktop_cv1g1gvanyjgm!App" -> "WhatsApp"
+fn friendly_app_name(aumid: &str) -> String {
+    let package = aumid.split('_').next().unwrap_or(aumid);
+    
+    KNOWN_PACKAGES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(package))
+        .map(|(_, name)| name.to_string())
+        .unwrap_or_else(|| package.rsplit('.').next().unwrap_or(package).to_string())
 }
 
 #[async_trait]
 impl PlatformTracker for WindowsTracker {
+    fn backend(&self) -> &'static str {
+        "windows"
+    }
+    
     async fn get_active_window(&self) -> Result<WindowInfo> {
-        // Windows implementation would use Win32 APIs
+        // HWND isn't Send, so it must not live across the await below
+        let (handle, process_name, bundle_id, pid, rect, window_title) = {
+            let (hwnd, foreground_pid, mut process_name) = Self::foreground_process()
+                .ok_or_else(|| SelfspyError::Platform("No foreground window".to_string()))?;
+            let mut bundle_id = None;
+            let mut pid = Some(foreground_pid);
+            
+            if process_name.eq_ignore_ascii_case(UWP_FRAME_HOST) {
+                if let Some((app_name, aumid, app_pid)) = Self::resolve_uwp_app(hwnd, foreground_pid) {
+                    process_name = app_name;
+                    bundle_id = Some(aumid);
+                    pid = Some(app_pid);
+                }
+            }
+            
+            (hwnd.0 as isize, process_name, bundle_id, pid, window_rect(hwnd), window_title(hwnd))
+        };
+        let document = self.document(handle, &process_name, &window_title).await;
         Ok(WindowInfo {
-            process_name: "Unknown".to_string(),
-            window_title: "Windows Window".to_string(),
-            bundle_id: None,
-            x: None,
-            y: None,
-            width: None,
-            height: None,
+            process_name,
+            window_title,
+            document,
+            bundle_id,
+            x: rect.map(|r| r.left),
+            y: rect.map(|r| r.top),
+            width: rect.map(|r| r.right - r.left),
+            height: rect.map(|r| r.bottom - r.top),
+            pid,
         })
     }
     
     async fn start_input_tracking(&self) -> Result<()> {
-        // Would set up Windows hooks
-        Ok(())
+        let mut hooks = self.hooks.lock().unwrap();
+        if hooks.is_some() {
+            return Ok(());
+        }
+        
+        // The thread reports its id once the hooks are installed, or why they weren't
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let events = self.events.clone();
+        let thread = std::thread::Builder::new()
+            .name("selfspy-hooks".to_string())
+            .spawn(move || run_hooks(events, ready_tx))?;
+        
+        match ready_rx.recv() {
+            Ok(Ok(thread_id)) => {
+                *hooks = Some(HookThread { thread_id, thread });
+                Ok(())
+            }
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                Err(e)
+            }
+            Err(_) => Err(SelfspyError::Platform("Input hook thread exited during setup".to_string())),
+        }
     }
     
     async fn stop_input_tracking(&self) -> Result<()> {
+        self.stop_hooks();
         Ok(())
     }
     
@@ -46,4 +383,210 @@ impl PlatformTracker for WindowsTracker {
         events.clear();
         result
     }
+    
+    fn is_healthy(&self) -> bool {
+        self.hooks
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map_or(true, |hooks| !hooks.thread.is_finished())
+    }
+    
+    fn idle_seconds(&self) -> Option<u64> {
+        let mut info = LASTINPUTINFO {
+            cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+            dwTime: 0,
+        };
+        if !unsafe { GetLastInputInfo(&mut info) }.as_bool() {
+            return None;
+        }
+        // Both are 32-bit tick counts, so the difference survives the 49-day wrap
+        let idle_ms = unsafe { GetTickCount() }.wrapping_sub(info.dwTime);
+        Some(idle_ms as u64 / 1000)
+    }
+}
+
+impl Drop for WindowsTracker {
+    fn drop(&mut self) {
+        self.stop_hooks();
+    }
+}
+
+fn run_hooks(events: Arc<Mutex<Vec<InputEvent>>>, ready: mpsc::Sender<Result<u32>>) {
+    HOOK_EVENTS.with(|slot| *slot.borrow_mut() = Some(events));
+    
+    let hooks = unsafe {
+        let module = match GetModuleHandleW(None) {
+            Ok(module) => HINSTANCE(module.0),
+            Err(e) => {
+                let _ = ready.send(Err(hook_error(e)));
+                return;
+            }
+        };
+        let keyboard = SetWindowsHookExW(WH_KEYBOARD_LL, Some(keyboard_hook), module, 0);
+        let mouse = SetWindowsHookExW(WH_MOUSE_LL, Some(mouse_hook), module, 0);
+        match (keyboard, mouse) {
+            (Ok(keyboard), Ok(mouse)) => [keyboard, mouse],
+            (keyboard, mouse) => {
+                let error = keyboard.as_ref().err().or(mouse.as_ref().err()).cloned();
+                for hook in [keyboard, mouse].into_iter().flatten() {
+                    let _ = UnhookWindowsHookEx(hook);
+                }
+                let _ = ready.send(Err(hook_error(error.unwrap_or_else(windows::core::Error::from_win32))));
+                return;
+            }
+        }
+    };
+    let _ = ready.send(Ok(unsafe { GetCurrentThreadId() }));
+    
+    // Hooks are delivered while this thread waits for messages; WM_QUIT ends it
+    let mut message = MSG::default();
+    unsafe {
+        while GetMessageW(&mut message, HWND::default(), 0, 0).as_bool() {
+            let _ = TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+        for hook in hooks {
+            let _ = UnhookWindowsHookEx(hook);
+        }
+    }
+}
+
+fn hook_error(error: windows::core::Error) -> SelfspyError {
+    SelfspyError::Platform(format!("Could not install input hooks: {}", error))
+}
+
+fn push_event(event: InputEvent) {
+    HOOK_EVENTS.with(|slot| {
+        if let Some(events) = slot.borrow().as_ref() {
+            events.lock().unwrap().push(event);
+        }
+    });
+}
+
+unsafe extern "system" fn keyboard_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 {
+        let info = &*(lparam.0 as *const KBDLLHOOKSTRUCT);
+        let message = wparam.0 as u32;
+        let pressed = message == WM_KEYDOWN || message == WM_SYSKEYDOWN;
+        let released = message == WM_KEYUP || message == WM_SYSKEYUP;
+        
+        if pressed || released {
+            let mut modifiers = held_modifiers();
+            // AltGr arrives as Ctrl+Alt; when it types a character in the
+            // current layout (AZERTY "@", German "€") that is typing, not a shortcut
+            let altgr_text = (modifiers.ctrl && modifiers.alt && GetKeyState(VK_RMENU.0 as i32) < 0)
+                .then(|| typed_text(info.vkCode, info.scanCode, true))
+                .flatten();
+            let key = match altgr_text {
+                Some(text) => {
+                    modifiers.ctrl = false;
+                    modifiers.alt = false;
+                    Some(text)
+                }
+                None => key_name(info.vkCode, info.scanCode),
+            };
+            if let Some(key) = key {
+                push_event(InputEvent::key(&key, latin_key(info.vkCode), modifiers, pressed));
+            }
+        }
+    }
+    CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+}
+
+unsafe extern "system" fn mouse_hook(code: i32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    if code >= 0 {
+        let info = &*(lparam.0 as *const MSLLHOOKSTRUCT);
+        let (x, y) = (info.pt.x, info.pt.y);
+        // The wheel delta is the high word of mouseData, in multiples of WHEEL_DELTA
+        let wheel = (info.mouseData >> 16) as u16 as i16 as f64 / WHEEL_DELTA as f64;
+        
+        let event = match wparam.0 as u32 {
+            WM_MOUSEMOVE => Some(InputEvent::MouseMove { x, y }),
+            WM_LBUTTONDOWN => Some(InputEvent::MouseClick { x, y, button: MouseButton::Left, device: None }),
+            WM_RBUTTONDOWN => Some(InputEvent::MouseClick { x, y, button: MouseButton::Right, device: None }),
+            WM_MBUTTONDOWN => Some(InputEvent::MouseClick { x, y, button: MouseButton::Middle, device: None }),
+            WM_MOUSEWHEEL => Some(InputEvent::MouseScroll { delta_x: 0.0, delta_y: wheel, device: None }),
+            WM_MOUSEHWHEEL => Some(InputEvent::MouseScroll { delta_x: wheel, delta_y: 0.0, device: None }),
+            _ => None,
+        };
+        if let Some(event) = event {
+            push_event(event);
+        }
+    }
+    CallNextHookEx(HHOOK::default(), code, wparam, lparam)
+}
+
+// Modifiers already down when this key event arrived
+fn held_modifiers() -> Modifiers {
+    let down = |vk: VIRTUAL_KEY| unsafe { GetKeyState(vk.0 as i32) } < 0;
+    Modifiers {
+        ctrl: down(VK_CONTROL),
+        alt: down(VK_MENU),
+        shift: down(VK_SHIFT),
+        meta: down(VK_LWIN) || down(VK_RWIN),
+    }
+}
+
+// Named keys use the X11 keysym spelling so both backends record the same
+// "[BackSpace]", "[Return]", ...; anything else is the character it types
+fn key_name(vk_code: u32, scan_code: u32) -> Option<String> {
+    let vk = VIRTUAL_KEY(vk_code as u16);
+    let name = match vk {
+        VK_BACK => "BackSpace",
+        VK_TAB => "Tab",
+        VK_RETURN => "Return",
+        VK_ESCAPE => "Escape",
+        VK_DELETE => "Delete",
+        VK_INSERT => "Insert",
+        VK_HOME => "Home",
+        VK_END => "End",
+        VK_PRIOR => "Prior",
+        VK_NEXT => "Next",
+        VK_LEFT => "Left",
+        VK_RIGHT => "Right",
+        VK_UP => "Up",
+        VK_DOWN => "Down",
+        VK_CAPITAL => "Caps_Lock",
+        VK_SHIFT | VK_LSHIFT | VK_RSHIFT => "Shift",
+        VK_CONTROL | VK_LCONTROL | VK_RCONTROL => "Control",
+        VK_MENU | VK_LMENU | VK_RMENU => "Alt",
+        VK_LWIN | VK_RWIN => "Super",
+        _ if (VK_F1.0..=VK_F24.0).contains(&vk.0) => return Some(format!("[F{}]", vk.0 - VK_F1.0 + 1)),
+        _ => return typed_text(vk_code, scan_code, false),
+    };
+    Some(format!("[{}]", name))
+}
+
+// Virtual key codes for letters and digits follow the layout's Latin
+// letters, even on non-Latin layouts, so they name shortcuts
+fn latin_key(vk_code: u32) -> Option<char> {
+    char::from_u32(vk_code).filter(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+// The text a key types in the current layout, with Shift, Caps Lock and
+// optionally AltGr applied. The hook sees keys before the target application
+// does, so the modifier state is read from GetKeyState rather than GetKeyboardState.
+fn typed_text(vk_code: u32, scan_code: u32, altgr: bool) -> Option<String> {
+    let mut state = [0u8; 256];
+    unsafe {
+        for vk in [VK_SHIFT, VK_LSHIFT, VK_RSHIFT] {
+            state[vk.0 as usize] = (GetKeyState(vk.0 as i32) as u16 >> 8) as u8;
+        }
+        state[VK_CAPITAL.0 as usize] = (GetKeyState(VK_CAPITAL.0 as i32) & 1) as u8;
+    }
+    if altgr {
+        for vk in [VK_CONTROL, VK_LCONTROL, VK_MENU, VK_RMENU] {
+            state[vk.0 as usize] = 0x80;
+        }
+    }
+    
+    let mut buffer = [0u16; 8];
+    let len = unsafe { ToUnicode(vk_code, scan_code, Some(&state), &mut buffer, TO_UNICODE_NO_STATE_CHANGE) };
+    if len <= 0 {
+        return None;
+    }
+    
+    let text = String::from_utf16_lossy(&buffer[..len as usize]);
+    (!text.chars().any(char::is_control)).then_some(text)
 }
\ No newline at end of file
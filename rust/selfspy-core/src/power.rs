@@ -0,0 +1,98 @@
+// Battery analysis from the power_samples table: how long the machine ran
+// unplugged, how fast the battery drained, and how intensely it was used on
+// battery compared to mains power.
+use chrono::{DateTime, Utc};
+use std::ops::Range;
+
+use crate::{MinuteTotals, PowerSample};
+
+#[derive(Debug, Clone, Default)]
+pub struct PowerBreakdown {
+    pub battery_seconds: i64,
+    // Percentage points of charge used while unplugged
+    pub battery_used: f64,
+    // Keystrokes and minutes with input, split by power source
+    pub battery_keystrokes: i64,
+    pub battery_active_minutes: i64,
+    pub mains_keystrokes: i64,
+    pub mains_active_minutes: i64,
+}
+
+impl PowerBreakdown {
+    // Percentage points per hour on battery; None with under ten minutes
+    // unplugged, where the figure is noise
+    pub fn drain_per_hour(&self) -> Option<f64> {
+        (self.battery_seconds >= 600).then(|| self.battery_used / (self.battery_seconds as f64 / 3600.0))
+    }
+    
+    pub fn battery_keys_per_minute(&self) -> Option<f64> {
+        (self.battery_active_minutes > 0).then(|| self.battery_keystrokes as f64 / self.battery_active_minutes as f64)
+    }
+    
+    pub fn mains_keys_per_minute(&self) -> Option<f64> {
+        (self.mains_active_minutes > 0).then(|| self.mains_keystrokes as f64 / self.mains_active_minutes as f64)
+    }
+}
+
+// Spans on battery from samples sorted by time. Each sample holds until the
+// next, the last one until `until` but no later than `last_activity`: samples
+// stop when the monitor does, and the machine may have been off since.
+pub fn battery_intervals(
+    samples: &[PowerSample],
+    until: DateTime<Utc>,
+    last_activity: Option<DateTime<Utc>>,
+) -> Vec<Range<DateTime<Utc>>> {
+    let last_end = last_activity.map_or(until, |last_activity| last_activity.min(until));
+    let mut intervals: Vec<Range<DateTime<Utc>>> = Vec::new();
+    for (index, sample) in samples.iter().enumerate() {
+        if !sample.on_battery {
+            continue;
+        }
+        let end = samples.get(index + 1).map_or(last_end, |next| next.recorded_at);
+        match intervals.last_mut() {
+            Some(last) if last.end == sample.recorded_at => last.end = end,
+            _ if end > sample.recorded_at => intervals.push(sample.recorded_at..end),
+            _ => {}
+        }
+    }
+    intervals
+}
+
+pub fn breakdown(
+    range: Range<DateTime<Utc>>,
+    samples: &[PowerSample],
+    battery: &[Range<DateTime<Utc>>],
+    minutes: &[MinuteTotals],
+) -> PowerBreakdown {
+    let mut result = PowerBreakdown::default();
+    
+    for interval in battery {
+        let start = interval.start.max(range.start);
+        let end = interval.end.min(range.end);
+        result.battery_seconds += (end - start).num_seconds().max(0);
+    }
+    
+    // Charge only counts between consecutive unplugged samples, so charging
+    // in between is never subtracted
+    for pair in samples.windows(2) {
+        let (before, after) = (&pair[0], &pair[1]);
+        if !before.on_battery || !after.on_battery || !range.contains(&after.recorded_at) {
+            continue;
+        }
+        if let (Some(from), Some(to)) = (before.percent, after.percent) {
+            result.battery_used += (from - to).max(0.0);
+        }
+    }
+    
+    for minute in minutes.iter().filter(|minute| range.contains(&minute.minute)) {
+        if battery.iter().any(|interval| interval.contains(&minute.minute)) {
+            result.battery_keystrokes += minute.keystrokes;
+            result.battery_active_minutes += 1;
+        } else {
+            result.mains_keystrokes += minute.keystrokes;
+            result.mains_active_minutes += 1;
+        }
+    }
+    
+    result
+}
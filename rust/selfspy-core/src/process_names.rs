@@ -0,0 +1,154 @@
+// Roll Chromium/Electron helper processes ("Google Chrome Helper (Renderer)",
+// "com.microsoft.VSCode.helper.GPU", ...) up under their parent application.
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use crate::platform::WindowInfo;
+
+static HELPER_NAME: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<app>.+?) Helper(?: \((?:Renderer|GPU|Plugin|Alerts|Utility)\))?(?:\.exe)?$").unwrap()
+});
+
+static HELPER_BUNDLE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?P<app>.+?)\.helper(?:\.[A-Za-z]+)?$").unwrap()
+});
+
+pub fn normalize(window: &mut WindowInfo) {
+    if let Some(app) = window.pid.and_then(resolve_sandboxed_app) {
+        window.process_name = app.name;
+        window.bundle_id = Some(app.app_id);
+        return;
+    }
+    
+    if let Some(bundle_id) = &window.bundle_id {
+        if let Some(caps) = HELPER_BUNDLE.captures(bundle_id) {
+            window.bundle_id = Some(caps["app"].to_string());
+        }
+    }
+    
+    if let Some(caps) = HELPER_NAME.captures(&window.process_name) {
+        window.process_name = caps["app"].to_string();
+        return;
+    }
+    
+    if let Some(name) = window.pid.and_then(resolve_parent_app) {
+        window.process_name = name;
+    }
+}
+
+// Chromium subprocesses are started with a --type= switch; walk up to the
+// first ancestor that isn't one and report its name.
+#[cfg(target_os = "linux")]
+fn resolve_parent_app(pid: u32) -> Option<String> {
+    let mut current = pid;
+    let mut resolved = None;
+    
+    // Bounded walk in case /proc is inconsistent
+    for _ in 0..8 {
+        let cmdline = std::fs::read(format!("/proc/{}/cmdline", current)).ok()?;
+        let is_helper = cmdline
+            .split(|byte| *byte == 0)
+            .any(|arg| arg.starts_with(b"--type="));
+        
+        if !is_helper {
+            break;
+        }
+        
+        current = parent_pid(current)?;
+        resolved = std::fs::read_to_string(format!("/proc/{}/comm", current))
+            .ok()
+            .map(|name| name.trim().to_string());
+    }
+    
+    resolved
+}
+
+#[cfg(target_os = "linux")]
+fn parent_pid(pid: u32) -> Option<u32> {
+    let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).ok()?;
+    // The command name may contain spaces, so parse after the closing paren
+    let after_comm = &stat[stat.rfind(')')? + 2..];
+    after_comm.split_whitespace().nth(1)?.parse().ok()
+}
+
+// Helpers share the parent's executable name on Windows, and macOS is fully
+// handled by the bundle identifier.
+#[cfg(not(target_os = "linux"))]
+fn resolve_parent_app(_pid: u32) -> Option<String> {
+    None
+}
+
+// Identity of an app running inside a Flatpak or Snap sandbox, where the
+// process itself is just "bwrap", "snap-confine" or a generic binary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SandboxedApp {
+    pub name: String,
+    pub app_id: String,
+}
+
+#[cfg(target_os = "linux")]
+pub fn resolve_sandboxed_app(pid: u32) -> Option<SandboxedApp> {
+    flatpak_app(pid).or_else(|| snap_app(pid))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn resolve_sandboxed_app(_pid: u32) -> Option<SandboxedApp> {
+    None
+}
+
+#[cfg(target_os = "linux")]
+fn flatpak_app(pid: u32) -> Option<SandboxedApp> {
+    // Every Flatpak sandbox has an ini-style /.flatpak-info with the app ID
+    let app_id = std::fs::read_to_string(format!("/proc/{}/root/.flatpak-info", pid))
+        .ok()
+        .and_then(|info| {
+            info.lines()
+                .skip_while(|line| line.trim() != "[Application]")
+                .find_map(|line| line.trim().strip_prefix("name=").map(str::to_string))
+        })
+        // Without access to the sandbox root, the systemd scope still names it
+        .or_else(|| cgroup_unit(pid, "app-flatpak-"))?;
+    
+    let name = app_id.rsplit('.').next().unwrap_or(&app_id).to_string();
+    Some(SandboxedApp { name, app_id })
+}
+
+#[cfg(target_os = "linux")]
+fn snap_app(pid: u32) -> Option<SandboxedApp> {
+    let snap_name = std::fs::read(format!("/proc/{}/environ", pid))
+        .ok()
+        .and_then(|environ| {
+            environ
+                .split(|byte| *byte == 0)
+                .find_map(|var| var.strip_prefix(b"SNAP_NAME="))
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+        })
+        .or_else(|| {
+            // Scopes look like snap.<name>.<app>-<uuid>.scope
+            cgroup_unit(pid, "snap.").and_then(|unit| unit.split('.').next().map(str::to_string))
+        })?;
+    
+    Some(SandboxedApp {
+        app_id: format!("snap.{}", snap_name),
+        name: snap_name,
+    })
+}
+
+// Returns the part of the process's systemd scope name after `prefix`, with
+// the trailing "-<id>.scope" removed for Flatpak scopes.
+#[cfg(target_os = "linux")]
+fn cgroup_unit(pid: u32, prefix: &str) -> Option<String> {
+    let cgroup = std::fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    
+    cgroup.lines().find_map(|line| {
+        let unit = line.rsplit('/').next()?;
+        let rest = unit.strip_prefix(prefix)?;
+        let rest = rest.strip_suffix(".scope").unwrap_or(rest);
+        
+        if prefix.starts_with("app-flatpak") {
+            rest.rsplit_once('-').map(|(app_id, _)| app_id.to_string())
+        } else {
+            Some(rest.to_string())
+        }
+    })
+}
\ No newline at end of file
@@ -0,0 +1,87 @@
+// Progress and cancellation for long-running operations (exports, imports,
+// re-encryption, bulk fixes). Core code reports through a Progress handle;
+// each frontend turns the updates into its own bars.
+use std::sync::Arc;
+use tokio_util::sync::CancellationToken;
+
+use crate::error::{Result, SelfspyError};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressUpdate {
+    pub stage: String,
+    pub done: u64,
+    // None when the amount of work isn't known up front
+    pub total: Option<u64>,
+}
+
+type ProgressCallback = Arc<dyn Fn(&ProgressUpdate) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct Progress {
+    callback: Option<ProgressCallback>,
+    cancel: CancellationToken,
+    current: Arc<std::sync::Mutex<ProgressUpdate>>,
+}
+
+impl Progress {
+    pub fn new(callback: impl Fn(&ProgressUpdate) + Send + Sync + 'static) -> Self {
+        Self {
+            callback: Some(Arc::new(callback)),
+            ..Self::none()
+        }
+    }
+    
+    // Reports nowhere and is never cancelled
+    pub fn none() -> Self {
+        Self {
+            callback: None,
+            cancel: CancellationToken::new(),
+            current: Arc::new(std::sync::Mutex::new(ProgressUpdate {
+                stage: String::new(),
+                done: 0,
+                total: None,
+            })),
+        }
+    }
+    
+    pub fn with_cancel(mut self, cancel: CancellationToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+    
+    pub fn cancel_token(&self) -> CancellationToken {
+        self.cancel.clone()
+    }
+    
+    pub fn start_stage(&self, stage: &str, total: Option<u64>) {
+        let mut current = self.current.lock().unwrap();
+        *current = ProgressUpdate {
+            stage: stage.to_string(),
+            done: 0,
+            total,
+        };
+        self.emit(&current);
+    }
+    
+    pub fn advance(&self, amount: u64) {
+        let mut current = self.current.lock().unwrap();
+        current.done += amount;
+        self.emit(&current);
+    }
+    
+    // Called between units of work; long operations stop (and roll back)
+    // with SelfspyError::Cancelled once cancellation was requested
+    pub fn check_cancelled(&self) -> Result<()> {
+        if self.cancel.is_cancelled() {
+            Err(SelfspyError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+    
+    fn emit(&self, update: &ProgressUpdate) {
+        if let Some(callback) = &self.callback {
+            callback(update);
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,161 @@
+// Timing of Database calls. Every call runs inside a `db` tracing span (the
+// `instrument` attributes in db.rs) and adds to per-query statistics for
+// this process; calls slower than the threshold are logged and appended to
+// slow_queries.log next to the database, so `selfspy doctor` can show which
+// reports were slow in any of the tools (monitor, GUI, selfstats).
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+use crate::error::Result;
+
+pub const SLOW_LOG_NAME: &str = "slow_queries.log";
+pub const DEFAULT_SLOW_QUERY_MS: u64 = 500;
+// The log is rotated to slow_queries.log.old past this size
+const SLOW_LOG_MAX_BYTES: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryStat {
+    pub query: String,
+    pub calls: u64,
+    pub total: Duration,
+    pub max: Duration,
+    // Calls slower than the threshold
+    pub slow: u64,
+}
+
+// One line of slow_queries.log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlowQuery {
+    pub query: String,
+    pub millis: u64,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug)]
+pub struct QueryTimings {
+    stats: Mutex<HashMap<&'static str, QueryStat>>,
+    // 0 disables the slow-query log
+    threshold_ms: AtomicU64,
+    log_path: Option<PathBuf>,
+}
+
+impl QueryTimings {
+    // Slow queries of the database at `database` are logged next to it
+    pub fn new(database: &Path) -> Self {
+        Self {
+            stats: Mutex::new(HashMap::new()),
+            threshold_ms: AtomicU64::new(DEFAULT_SLOW_QUERY_MS),
+            log_path: Some(database.with_file_name(SLOW_LOG_NAME)),
+        }
+    }
+    
+    // For databases with no file to log beside, such as in-memory ones
+    pub fn unlogged() -> Self {
+        Self {
+            stats: Mutex::new(HashMap::new()),
+            threshold_ms: AtomicU64::new(DEFAULT_SLOW_QUERY_MS),
+            log_path: None,
+        }
+    }
+    
+    pub fn set_threshold(&self, threshold: Option<Duration>) {
+        let millis = threshold.map_or(0, |threshold| threshold.as_millis().max(1) as u64);
+        self.threshold_ms.store(millis, Ordering::Relaxed);
+    }
+    
+    // Starts timing a call; the returned guard records it when dropped, so
+    // calls that fail early with `?` are counted too
+    pub fn start(&self, query: &'static str) -> QueryTimer<'_> {
+        QueryTimer {
+            timings: self,
+            query,
+            started: Instant::now(),
+        }
+    }
+    
+    // Statistics so far, slowest total first
+    pub fn snapshot(&self) -> Vec<QueryStat> {
+        let mut stats: Vec<QueryStat> = self.stats.lock().unwrap().values().cloned().collect();
+        stats.sort_by_key(|stat| std::cmp::Reverse(stat.total));
+        stats
+    }
+    
+    fn record(&self, query: &'static str, elapsed: Duration) {
+        let threshold = self.threshold_ms.load(Ordering::Relaxed);
+        let slow = threshold > 0 && elapsed.as_millis() as u64 >= threshold;
+        
+        {
+            let mut stats = self.stats.lock().unwrap();
+            let stat = stats.entry(query).or_insert_with(|| QueryStat {
+                query: query.to_string(),
+                ..Default::default()
+            });
+            stat.calls += 1;
+            stat.total += elapsed;
+            stat.max = stat.max.max(elapsed);
+            stat.slow += slow as u64;
+        }
+        
+        if slow {
+            warn!("Slow query {} took {} ms", query, elapsed.as_millis());
+            if let Some(path) = &self.log_path {
+                let entry = SlowQuery {
+                    query: query.to_string(),
+                    millis: elapsed.as_millis() as u64,
+                    at: Utc::now(),
+                };
+                if let Err(e) = append_slow_query(path, &entry) {
+                    debug!("Could not write {}: {}", path.display(), e);
+                }
+            }
+        }
+    }
+}
+
+pub struct QueryTimer<'a> {
+    timings: &'a QueryTimings,
+    query: &'static str,
+    started: Instant,
+}
+
+impl Drop for QueryTimer<'_> {
+    fn drop(&mut self) {
+        let elapsed = self.started.elapsed();
+        debug!(elapsed_ms = elapsed.as_millis() as u64, "query finished");
+        self.timings.record(self.query, elapsed);
+    }
+}
+
+fn append_slow_query(path: &Path, entry: &SlowQuery) -> Result<()> {
+    if std::fs::metadata(path).is_ok_and(|metadata| metadata.len() > SLOW_LOG_MAX_BYTES) {
+        std::fs::rename(path, path.with_extension("log.old"))?;
+    }
+    
+    let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    Ok(())
+}
+
+// Entries of a slow-query log, oldest first; unreadable lines are skipped
+pub fn read_slow_log(path: &Path) -> Result<Vec<SlowQuery>> {
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(entry) = serde_json::from_str(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
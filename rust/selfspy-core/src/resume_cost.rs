@@ -0,0 +1,124 @@
+// Resume cost: after leaving the day's primary app and coming back, how long
+// it takes before typing is back to its normal rate.
+use chrono::{Duration, NaiveDate, NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::{KeyActivity, WindowVisit};
+
+// Typing counts as resumed once a minute reaches this share of the normal rate
+const RESUMED_RATE_SHARE: f64 = 0.5;
+// Returns without normal typing within this long are not counted as resumed
+const MAX_RESUME_MINUTES: i64 = 15;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResumeCostDay {
+    pub day: NaiveDate,
+    // The app that received the most keystrokes
+    pub primary_app: String,
+    // Times the user left the primary app and came back
+    pub interruptions: usize,
+    // Returns after which typing got back to normal in time
+    pub resumed: usize,
+    pub total_resume_seconds: i64,
+}
+
+impl ResumeCostDay {
+    pub fn average_resume_seconds(&self) -> i64 {
+        if self.resumed == 0 {
+            0
+        } else {
+            self.total_resume_seconds / self.resumed as i64
+        }
+    }
+}
+
+// Both slices must be sorted by time and cover a single day
+pub fn estimate_day(day: NaiveDate, visits: &[WindowVisit], keys: &[KeyActivity]) -> Option<ResumeCostDay> {
+    let mut per_process: HashMap<&str, i64> = HashMap::new();
+    for batch in keys {
+        *per_process.entry(batch.process.as_str()).or_default() += batch.keys;
+    }
+    let (primary_app, _) = per_process.into_iter().max_by_key(|(_, keys)| *keys)?;
+    
+    let primary_keys: Vec<&KeyActivity> = keys.iter().filter(|batch| batch.process == primary_app).collect();
+    let threshold = normal_rate(&primary_keys)? * RESUMED_RATE_SHARE;
+    
+    let mut result = ResumeCostDay {
+        day,
+        primary_app: primary_app.to_string(),
+        interruptions: 0,
+        resumed: 0,
+        total_resume_seconds: 0,
+    };
+    
+    let mut left_primary = false;
+    let mut was_primary = false;
+    for (index, visit) in visits.iter().enumerate() {
+        let is_primary = visit.process == primary_app;
+        if !is_primary && was_primary {
+            left_primary = true;
+        }
+        
+        if is_primary && left_primary {
+            left_primary = false;
+            result.interruptions += 1;
+            
+            // Typing has to pick up before the next switch to another app
+            let cap = visit.at + Duration::minutes(MAX_RESUME_MINUTES);
+            let deadline = visits[index + 1..]
+                .iter()
+                .find(|next| next.process != primary_app)
+                .map_or(cap, |next| next.at.min(cap));
+            if let Some(resumed_at) = resumed_at(&primary_keys, visit.at, deadline, threshold) {
+                result.resumed += 1;
+                result.total_resume_seconds += (resumed_at - visit.at).num_seconds();
+            }
+        }
+        was_primary = is_primary;
+    }
+    
+    Some(result)
+}
+
+// Median keystrokes per minute over the minutes with any typing
+fn normal_rate(keys: &[&KeyActivity]) -> Option<f64> {
+    let mut per_minute: BTreeMap<NaiveDateTime, i64> = BTreeMap::new();
+    for batch in keys {
+        let minute = batch.at.with_second(0).unwrap_or(batch.at);
+        *per_minute.entry(minute).or_default() += batch.keys;
+    }
+    
+    let mut rates: Vec<i64> = per_minute.into_values().filter(|keys| *keys > 0).collect();
+    if rates.is_empty() {
+        return None;
+    }
+    rates.sort_unstable();
+    Some(rates[rates.len() / 2] as f64)
+}
+
+// Start of the first batch after `from` whose following minute reaches `threshold` keystrokes
+fn resumed_at(
+    keys: &[&KeyActivity],
+    from: NaiveDateTime,
+    deadline: NaiveDateTime,
+    threshold: f64,
+) -> Option<NaiveDateTime> {
+    let start = keys.partition_point(|batch| batch.at < from);
+    let candidates = &keys[start..];
+    
+    candidates
+        .iter()
+        .enumerate()
+        .take_while(|(_, batch)| batch.at <= deadline)
+        .find(|(offset, batch)| {
+            let window_end = batch.at + Duration::minutes(1);
+            let typed: i64 = candidates[*offset..]
+                .iter()
+                .take_while(|later| later.at < window_end)
+                .map(|later| later.keys)
+                .sum();
+            typed as f64 >= threshold
+        })
+        .map(|(_, batch)| batch.at)
+}
\ No newline at end of file
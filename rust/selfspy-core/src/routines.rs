@@ -0,0 +1,157 @@
+// Routine fingerprinting: the apps opened in the first and last half hour of
+// each day, the typical sequence across many days, and the days that break it.
+use chrono::{Duration, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::WindowVisit;
+
+// Length of the morning and evening windows
+pub const ROUTINE_MINUTES: i64 = 30;
+// Only the first few distinct apps make up a routine
+const MAX_ROUTINE_APPS: usize = 5;
+// Days whose routine overlaps the typical one less than this are flagged
+pub const DEVIATION_THRESHOLD: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RoutinePart {
+    Morning,
+    Evening,
+}
+
+impl RoutinePart {
+    pub const ALL: [RoutinePart; 2] = [RoutinePart::Morning, RoutinePart::Evening];
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RoutinePart::Morning => "morning",
+            RoutinePart::Evening => "evening",
+        }
+    }
+}
+
+// Distinct apps in the order they were first used, per part of one day
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DayRoutine {
+    pub day: NaiveDate,
+    pub morning: Vec<String>,
+    pub evening: Vec<String>,
+}
+
+impl DayRoutine {
+    pub fn part(&self, part: RoutinePart) -> &[String] {
+        match part {
+            RoutinePart::Morning => &self.morning,
+            RoutinePart::Evening => &self.evening,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutineFingerprint {
+    pub part: RoutinePart,
+    // Apps seen on at least half of the days, ordered by their usual position
+    pub apps: Vec<String>,
+    pub days: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoutineDeviation {
+    pub day: NaiveDate,
+    pub part: RoutinePart,
+    // Overlap with the fingerprint, 0.0 to 1.0
+    pub similarity: f64,
+    pub missing: Vec<String>,
+    pub unexpected: Vec<String>,
+}
+
+// `visits` must be sorted by time
+pub fn day_routines(visits: &[WindowVisit]) -> Vec<DayRoutine> {
+    let mut by_day: BTreeMap<NaiveDate, Vec<&WindowVisit>> = BTreeMap::new();
+    for visit in visits {
+        by_day.entry(visit.at.date()).or_default().push(visit);
+    }
+    
+    by_day
+        .into_iter()
+        .filter_map(|(day, visits)| {
+            let (first, last) = (visits.first()?.at, visits.last()?.at);
+            let window = Duration::minutes(ROUTINE_MINUTES);
+            
+            let morning = distinct_apps(visits.iter().take_while(|visit| visit.at - first <= window));
+            let evening = distinct_apps(visits.iter().filter(|visit| last - visit.at <= window));
+            
+            Some(DayRoutine { day, morning, evening })
+        })
+        .collect()
+}
+
+fn distinct_apps<'a>(visits: impl Iterator<Item = &'a &'a WindowVisit>) -> Vec<String> {
+    let mut apps: Vec<String> = Vec::new();
+    for visit in visits {
+        if apps.len() == MAX_ROUTINE_APPS {
+            break;
+        }
+        if !apps.contains(&visit.process) {
+            apps.push(visit.process.clone());
+        }
+    }
+    apps
+}
+
+pub fn fingerprint(routines: &[DayRoutine], part: RoutinePart) -> RoutineFingerprint {
+    // App -> (days seen, sum of positions)
+    let mut seen: HashMap<&str, (usize, usize)> = HashMap::new();
+    for routine in routines {
+        for (position, app) in routine.part(part).iter().enumerate() {
+            let entry = seen.entry(app.as_str()).or_default();
+            entry.0 += 1;
+            entry.1 += position;
+        }
+    }
+    
+    let mut common: Vec<(&str, f64)> = seen
+        .into_iter()
+        .filter(|(_, (days, _))| *days * 2 >= routines.len())
+        .map(|(app, (days, positions))| (app, positions as f64 / days as f64))
+        .collect();
+    common.sort_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(b.0)));
+    
+    RoutineFingerprint {
+        part,
+        apps: common.into_iter().map(|(app, _)| app.to_string()).collect(),
+        days: routines.len(),
+    }
+}
+
+// Days whose routine shares less than `threshold` of its apps with the fingerprint
+pub fn deviations(
+    routines: &[DayRoutine],
+    fingerprint: &RoutineFingerprint,
+    threshold: f64,
+) -> Vec<RoutineDeviation> {
+    if fingerprint.apps.is_empty() {
+        return Vec::new();
+    }
+    
+    routines
+        .iter()
+        .filter_map(|routine| {
+            let apps = routine.part(fingerprint.part);
+            let shared = apps.iter().filter(|app| fingerprint.apps.contains(app)).count();
+            let union = fingerprint.apps.len() + apps.len() - shared;
+            let similarity = shared as f64 / union as f64;
+            if similarity >= threshold {
+                return None;
+            }
+            
+            Some(RoutineDeviation {
+                day: routine.day,
+                part: fingerprint.part,
+                similarity,
+                missing: fingerprint.apps.iter().filter(|app| !apps.contains(app)).cloned().collect(),
+                unexpected: apps.iter().filter(|app| !fingerprint.apps.contains(app)).cloned().collect(),
+            })
+        })
+        .collect()
+}
\ No newline at end of file
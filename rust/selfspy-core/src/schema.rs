@@ -0,0 +1,51 @@
+// JSON Schemas for everything selfspy writes out as JSON, generated from the
+// serde types so the published contract can't drift from the real output.
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+use crate::{
+    ActivityStats, Click, DailySummary, Goal, HourlyActivity, Keys, MouseMoves, Process, ProcessStats, Session, Window,
+};
+
+// Bumped whenever a schema changes incompatibly
+pub const SCHEMA_VERSION: u32 = 1;
+
+pub const SCHEMA_NAMES: [&str; 11] = [
+    "activity_stats",
+    "process_stats",
+    "daily_summary",
+    "timeline_entry",
+    "session",
+    "goal",
+    "process",
+    "window",
+    "keys",
+    "click",
+    "mouse_moves",
+];
+
+pub fn schema(name: &str) -> Option<RootSchema> {
+    let schema = match name {
+        "activity_stats" => schema_for!(ActivityStats),
+        "process_stats" => schema_for!(ProcessStats),
+        "daily_summary" => schema_for!(DailySummary),
+        "timeline_entry" => schema_for!(HourlyActivity),
+        "session" => schema_for!(Session),
+        "goal" => schema_for!(Goal),
+        // Raw table records, as exported row by row
+        "process" => schema_for!(Process),
+        "window" => schema_for!(Window),
+        "keys" => schema_for!(Keys),
+        "click" => schema_for!(Click),
+        "mouse_moves" => schema_for!(MouseMoves),
+        _ => return None,
+    };
+    Some(schema)
+}
+
+pub fn all_schemas() -> Vec<(&'static str, RootSchema)> {
+    SCHEMA_NAMES
+        .iter()
+        .filter_map(|name| schema(name).map(|schema| (*name, schema)))
+        .collect()
+}
\ No newline at end of file
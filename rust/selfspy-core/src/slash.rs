@@ -0,0 +1,319 @@
+// Answers Slack slash commands and Teams outgoing webhooks ("/selfspy today")
+// with a short summary of the local user's own activity, for teams that
+// choose to share their stats. Only requests signed with the configured
+// secret are answered. Routes:
+//
+//   POST /slack   Slack slash command, verified with the app's signing secret
+//   POST /teams   Teams outgoing webhook, verified with its security token
+//
+// Both platforms need a public HTTPS URL, so the listener is meant to sit
+// behind a tunnel or reverse proxy; it binds to localhost by default.
+use base64::Engine;
+use chrono::{DateTime, Duration, Local, NaiveDate, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, info, warn};
+
+use crate::control::{read_request, write_response, Request};
+use crate::distractions::visit_seconds;
+use crate::error::Result;
+use crate::platform::PlatformTracker;
+use crate::{ActivityMonitor, Config, Database};
+
+// Slack rejects replays older than this, and so do we, for both platforms
+const MAX_REQUEST_AGE_SECONDS: i64 = 300;
+const TOP_APPS: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SlashCommandSettings {
+    // Address the listener binds to
+    pub bind: String,
+    // Signing secret from the Slack app's Basic Information page
+    pub slack_signing_secret: Option<String>,
+    // Security token Teams shows when the outgoing webhook is created
+    pub teams_security_token: Option<String>,
+    // Post answers to the whole channel instead of only to whoever asked
+    // (Slack only; Teams always replies in the channel)
+    pub share_in_channel: bool,
+}
+
+impl Default for SlashCommandSettings {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:8789".to_string(),
+            slack_signing_secret: None,
+            teams_security_token: None,
+            share_in_channel: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Period {
+    Today,
+    Yesterday,
+    Week,
+}
+
+impl Period {
+    // None for anything we don't understand, which gets the help text
+    fn parse(text: &str) -> Option<Self> {
+        match text.trim().to_lowercase().as_str() {
+            "" | "today" => Some(Period::Today),
+            "yesterday" => Some(Period::Yesterday),
+            "week" => Some(Period::Week),
+            _ => None,
+        }
+    }
+    
+    // Label and the local days it covers, end exclusive
+    fn range(&self, today: NaiveDate) -> (&'static str, NaiveDate, NaiveDate) {
+        match self {
+            Period::Today => ("today", today, today + Duration::days(1)),
+            Period::Yesterday => ("yesterday", today - Duration::days(1), today),
+            Period::Week => ("the last 7 days", today - Duration::days(6), today + Duration::days(1)),
+        }
+    }
+}
+
+const HELP: &str = "Usage: /selfspy [today | yesterday | week]";
+
+// Serves slash commands until the monitor shuts down
+pub async fn serve<T: PlatformTracker + ?Sized + 'static>(
+    monitor: Arc<ActivityMonitor<T>>,
+    config: Config,
+    settings: SlashCommandSettings,
+) -> Result<()> {
+    if settings.slack_signing_secret.is_none() && settings.teams_security_token.is_none() {
+        warn!("Slash commands are configured without a Slack or Teams secret; not listening");
+        return Ok(());
+    }
+    
+    let listener = TcpListener::bind(settings.bind.as_str()).await?;
+    info!("Answering slash commands on http://{}", settings.bind);
+    
+    let db = Arc::new(Database::open_read_only(&config.database_path).await?);
+    let settings = Arc::new(settings);
+    let shutdown = monitor.shutdown_token();
+    loop {
+        let (stream, _) = tokio::select! {
+            _ = shutdown.cancelled() => return Ok(()),
+            accepted = listener.accept() => accepted?,
+        };
+        
+        let (db, settings) = (db.clone(), settings.clone());
+        tokio::spawn(async move {
+            if let Err(e) = handle_connection(stream, &db, &settings).await {
+                debug!("Slash command request failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(mut stream: TcpStream, db: &Database, settings: &SlashCommandSettings) -> Result<()> {
+    let (status, body) = match read_request(&mut stream).await? {
+        None => (400, serde_json::json!({ "error": "malformed request" })),
+        Some(request) => route(&request, db, settings).await,
+    };
+    write_response(&mut stream, status, &body).await
+}
+
+async fn route(request: &Request, db: &Database, settings: &SlashCommandSettings) -> (u16, serde_json::Value) {
+    let path = request.path.split('?').next().unwrap_or_default();
+    if request.method != "POST" {
+        return (405, serde_json::json!({ "error": "method not allowed" }));
+    }
+    
+    match path {
+        "/slack" => {
+            let Some(secret) = &settings.slack_signing_secret else {
+                return (404, serde_json::json!({ "error": "unknown route" }));
+            };
+            if !verify_slack(request, secret, Utc::now()) {
+                return (401, serde_json::json!({ "error": "bad signature" }));
+            }
+            
+            let form = parse_form(&request.body_text());
+            let text = answer(db, form.get("text").map(String::as_str).unwrap_or_default()).await;
+            let response_type = if settings.share_in_channel { "in_channel" } else { "ephemeral" };
+            (200, serde_json::json!({ "response_type": response_type, "text": text }))
+        }
+        "/teams" => {
+            let Some(token) = &settings.teams_security_token else {
+                return (404, serde_json::json!({ "error": "unknown route" }));
+            };
+            if !verify_teams(request, token, Utc::now()) {
+                return (401, serde_json::json!({ "error": "bad signature" }));
+            }
+            
+            let message: serde_json::Value = serde_json::from_slice(&request.body).unwrap_or_default();
+            let text = answer(db, &teams_command(message["text"].as_str().unwrap_or_default())).await;
+            (200, serde_json::json!({ "type": "message", "text": text }))
+        }
+        _ => (404, serde_json::json!({ "error": "unknown route" })),
+    }
+}
+
+async fn answer(db: &Database, text: &str) -> String {
+    let Some(period) = Period::parse(text) else {
+        return HELP.to_string();
+    };
+    match summary(db, period, Local::now().date_naive()).await {
+        Ok(summary) => summary,
+        Err(e) => {
+            warn!("Failed to build slash command summary: {}", e);
+            "Selfspy couldn't read its activity data just now.".to_string()
+        }
+    }
+}
+
+async fn summary(db: &Database, period: Period, today: NaiveDate) -> Result<String> {
+    let (label, start, end) = period.range(today);
+    let days = db.get_daily_summaries(start, end).await?;
+    let active_seconds: i64 = days.iter().map(|day| day.active_seconds).sum();
+    let keystrokes: i64 = days.iter().map(|day| day.keystrokes).sum();
+    let clicks: i64 = days.iter().map(|day| day.clicks).sum();
+    
+    let visits = db.get_window_visits(start, end).await?;
+    let mut apps: HashMap<&str, i64> = HashMap::new();
+    for (index, visit) in visits.iter().enumerate() {
+        *apps.entry(visit.process.as_str()).or_default() += visit_seconds(&visits, index);
+    }
+    let mut apps: Vec<_> = apps.into_iter().filter(|(_, seconds)| *seconds >= 60).collect();
+    apps.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    
+    let mut text = format!(
+        "Selfspy, {}: {} active, {} keystrokes, {} clicks",
+        label,
+        format_minutes(active_seconds / 60),
+        keystrokes,
+        clicks
+    );
+    if !apps.is_empty() {
+        let top: Vec<String> = apps
+            .iter()
+            .take(TOP_APPS)
+            .map(|(app, seconds)| format!("{} {}", app, format_minutes(seconds / 60)))
+            .collect();
+        text.push_str(&format!("\nTop apps: {}", top.join(", ")));
+    }
+    Ok(text)
+}
+
+fn format_minutes(minutes: i64) -> String {
+    if minutes >= 60 {
+        format!("{}h {}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+// Slack signs "v0:<timestamp>:<body>" with HMAC-SHA256 and sends the hex
+// digest as "v0=<hex>". Requests sent more than MAX_REQUEST_AGE_SECONDS
+// from `now` are refused as replays
+pub fn verify_slack(request: &Request, secret: &str, now: DateTime<Utc>) -> bool {
+    let (Some(timestamp), Some(signature)) = (
+        request.header("X-Slack-Request-Timestamp"),
+        request.header("X-Slack-Signature"),
+    ) else {
+        return false;
+    };
+    let fresh = timestamp
+        .parse::<i64>()
+        .is_ok_and(|sent| (now.timestamp() - sent).abs() <= MAX_REQUEST_AGE_SECONDS);
+    let Some(signature) = signature.strip_prefix("v0=").and_then(decode_hex) else {
+        return false;
+    };
+    
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(format!("v0:{}:", timestamp).as_bytes());
+    mac.update(&request.body);
+    fresh && mac.verify_slice(&signature).is_ok()
+}
+
+// Teams signs the body with HMAC-SHA256 keyed by the base64-decoded security
+// token and sends "HMAC <base64 digest>" in the Authorization header. The
+// signed message carries its own send time ("timestamp"), which has to be
+// within MAX_REQUEST_AGE_SECONDS of `now`
+pub fn verify_teams(request: &Request, token: &str, now: DateTime<Utc>) -> bool {
+    let engine = base64::engine::general_purpose::STANDARD;
+    let Some(signature) = request
+        .header("Authorization")
+        .and_then(|value| value.strip_prefix("HMAC "))
+        .and_then(|value| engine.decode(value.trim()).ok())
+    else {
+        return false;
+    };
+    let Ok(key) = engine.decode(token.trim()) else {
+        return false;
+    };
+    
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(&key) else {
+        return false;
+    };
+    mac.update(&request.body);
+    if mac.verify_slice(&signature).is_err() {
+        return false;
+    }
+    
+    let message: serde_json::Value = serde_json::from_slice(&request.body).unwrap_or_default();
+    message["timestamp"]
+        .as_str()
+        .and_then(|sent| DateTime::parse_from_rfc3339(sent).ok())
+        .is_some_and(|sent| (now - sent.with_timezone(&Utc)).num_seconds().abs() <= MAX_REQUEST_AGE_SECONDS)
+}
+
+// Teams sends the whole message, mention included: "<at>Selfspy</at> today"
+fn teams_command(text: &str) -> String {
+    let text = match text.rfind("</at>") {
+        Some(end) => &text[end + "</at>".len()..],
+        None => text,
+    };
+    text.replace("&nbsp;", " ").trim().to_string()
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+// application/x-www-form-urlencoded, as Slack posts slash commands
+fn parse_form(body: &str) -> HashMap<String, String> {
+    body.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(name, value)| (decode_component(name), decode_component(value)))
+        .collect()
+}
+
+fn decode_component(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => match value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    decoded.push(byte);
+                    i += 2;
+                }
+                None => decoded.push(b'%'),
+            },
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
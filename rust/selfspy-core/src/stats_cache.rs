@@ -0,0 +1,49 @@
+use crate::error::Result;
+use chrono::{Local, NaiveDate};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+use crate::{Database, DailySummary};
+
+// Short-lived cache in front of the summary queries so frequently refreshing
+// surfaces (tray tooltip, status widgets) don't hit SQLite every time.
+pub struct StatsCache {
+    db: Arc<Database>,
+    ttl: Duration,
+    today: RwLock<Option<(Instant, DailySummary)>>,
+}
+
+impl StatsCache {
+    pub fn new(db: Arc<Database>, ttl: Duration) -> Self {
+        Self {
+            db,
+            ttl,
+            today: RwLock::new(None),
+        }
+    }
+    
+    pub async fn today(&self) -> Result<DailySummary> {
+        let date = Local::now().date_naive();
+        
+        if let Some(summary) = self.cached(date).await {
+            return Ok(summary);
+        }
+        
+        let summary = self.db.get_daily_summary(date).await?;
+        *self.today.write().await = Some((Instant::now(), summary.clone()));
+        Ok(summary)
+    }
+    
+    pub async fn invalidate(&self) {
+        *self.today.write().await = None;
+    }
+    
+    async fn cached(&self, date: NaiveDate) -> Option<DailySummary> {
+        let cached = self.today.read().await;
+        cached
+            .as_ref()
+            .filter(|(fetched_at, summary)| fetched_at.elapsed() < self.ttl && summary.date == date)
+            .map(|(_, summary)| summary.clone())
+    }
+}
\ No newline at end of file
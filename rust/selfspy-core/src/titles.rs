@@ -0,0 +1,55 @@
+use regex::Regex;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use tracing::warn;
+
+// Patterns for title fragments that change without the window changing:
+// clocks and timers, unread badges, progress percentages.
+pub fn default_volatile_patterns() -> Vec<String> {
+    vec![
+        r"\b\d{1,2}:\d{2}(:\d{2})?\b".to_string(),
+        r"^\(\d+\)\s*".to_string(),
+        r"\[\d+\]".to_string(),
+        r"\b\d{1,3}(\.\d+)?%".to_string(),
+    ]
+}
+
+pub struct TitleNormalizer {
+    patterns: Vec<Regex>,
+}
+
+impl TitleNormalizer {
+    pub fn new(patterns: &[String]) -> Self {
+        let patterns = patterns
+            .iter()
+            .filter_map(|pattern| match Regex::new(pattern) {
+                Ok(regex) => Some(regex),
+                Err(e) => {
+                    warn!("Ignoring invalid title pattern {:?}: {}", pattern, e);
+                    None
+                }
+            })
+            .collect();
+        
+        Self { patterns }
+    }
+    
+    pub fn normalize(&self, title: &str) -> String {
+        let mut normalized = title.to_string();
+        
+        for pattern in &self.patterns {
+            normalized = pattern.replace_all(&normalized, "").into_owned();
+        }
+        
+        normalized.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+    
+    // Hash of the process and normalized title; two windows with the same
+    // fingerprint are treated as the same window
+    pub fn fingerprint(&self, process_name: &str, title: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        process_name.hash(&mut hasher);
+        self.normalize(title).hash(&mut hasher);
+        hasher.finish()
+    }
+}
\ No newline at end of file
@@ -0,0 +1,142 @@
+// Bigram and trigram timings for typing practice: how often each pair and
+// triple of characters is typed and how long the transitions take, so the
+// slowest ones can be drilled in a typing trainer. Unlike fatigue's rhythm
+// this depends on which keys were typed, so it is only recorded when
+// `capture.key_ngrams` is on and pauses with text capture. Only counts and
+// summed times per n-gram per day are stored, never the order n-grams came
+// in, and n-grams typed fewer than MIN_NGRAM_COUNT times in a day are never
+// reported and are deleted once the day is over, so rare sequences (a
+// password, a name) don't outlive it.
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Instant;
+
+use crate::fatigue::MAX_RHYTHM_GAP_MS;
+use crate::NgramTiming;
+
+pub const MIN_NGRAM_COUNT: i64 = 5;
+
+// Fewer samples than this make a mean too noisy to call a transition slow
+pub const MIN_SLOW_SAMPLES: i64 = 20;
+// How often each n-gram appears in a drill
+const DRILL_REPEATS: usize = 5;
+
+// Per-flush accumulator kept by the monitor
+#[derive(Debug, Default)]
+pub struct NgramTimer {
+    // The last two characters typed and when, oldest first
+    recent: Vec<(char, Instant)>,
+    // Times typed and summed transition time in ms; for a trigram the time
+    // runs from its first key to its last
+    pub ngrams: HashMap<String, (i64, i64)>,
+}
+
+impl NgramTimer {
+    pub fn record(&mut self, key: &str, now: Instant) {
+        let Some(typed) = typed_char(key) else {
+            self.interrupt();
+            return;
+        };
+        if self
+            .recent
+            .last()
+            .is_some_and(|(_, last)| now.duration_since(*last).as_millis() as u64 > MAX_RHYTHM_GAP_MS)
+        {
+            self.recent.clear();
+        }
+        
+        for start in 0..self.recent.len() {
+            let mut ngram: String = self.recent[start..].iter().map(|(c, _)| *c).collect();
+            ngram.push(typed);
+            let elapsed = now.duration_since(self.recent[start].1).as_millis() as i64;
+            let entry = self.ngrams.entry(ngram).or_default();
+            entry.0 += 1;
+            entry.1 += elapsed;
+        }
+        
+        self.recent.push((typed, now));
+        if self.recent.len() > 2 {
+            self.recent.remove(0);
+        }
+    }
+    
+    // Spaces, named keys, shortcuts and pastes end the current run of characters
+    pub fn interrupt(&mut self) {
+        self.recent.clear();
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.ngrams.is_empty()
+    }
+    
+    // Moves the counts out for storage, keeping the current run so n-grams
+    // span flushes
+    pub fn take(&mut self) -> Self {
+        Self {
+            recent: self.recent.clone(),
+            ngrams: std::mem::take(&mut self.ngrams),
+        }
+    }
+    
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+}
+
+// Letters, digits and punctuation, folded to lowercase so Shift doesn't split
+// an n-gram in two
+fn typed_char(key: &str) -> Option<char> {
+    let mut chars = key.chars();
+    let c = chars.next()?;
+    if chars.next().is_some() || !(c.is_alphanumeric() || c.is_ascii_punctuation()) {
+        return None;
+    }
+    c.to_lowercase().next()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NgramKind {
+    Bigram,
+    Trigram,
+}
+
+impl NgramKind {
+    pub fn of(ngram: &str) -> Option<Self> {
+        match ngram.chars().count() {
+            2 => Some(NgramKind::Bigram),
+            3 => Some(NgramKind::Trigram),
+            _ => None,
+        }
+    }
+}
+
+// The n-grams of one kind typed most often
+pub fn most_common(timings: &[NgramTiming], kind: NgramKind, limit: usize) -> Vec<&NgramTiming> {
+    let mut common: Vec<_> = timings.iter().filter(|t| NgramKind::of(&t.ngram) == Some(kind)).collect();
+    common.sort_by(|a, b| b.count.cmp(&a.count).then(a.ngram.cmp(&b.ngram)));
+    common.truncate(limit);
+    common
+}
+
+// The n-grams of one kind with the longest mean transition time, among
+// those typed often enough to trust the mean
+pub fn slowest(timings: &[NgramTiming], kind: NgramKind, limit: usize) -> Vec<&NgramTiming> {
+    let mut slow: Vec<_> = timings
+        .iter()
+        .filter(|t| NgramKind::of(&t.ngram) == Some(kind) && t.count >= MIN_SLOW_SAMPLES)
+        .collect();
+    slow.sort_by(|a, b| b.mean_ms().total_cmp(&a.mean_ms()).then(a.ngram.cmp(&b.ngram)));
+    slow.truncate(limit);
+    slow
+}
+
+// Space-separated practice text, each n-gram repeated, as pasted into the
+// custom text mode of Monkeytype, keybr or similar trainers
+pub fn drill_text(ngrams: &[&NgramTiming]) -> String {
+    ngrams
+        .iter()
+        .flat_map(|t| std::iter::repeat_n(t.ngram.as_str(), DRILL_REPEATS))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -0,0 +1,310 @@
+// Outgoing webhooks with a templated body. A delivery that fails is kept in
+// an outbox file in the data directory and retried with exponential backoff,
+// so integrations survive network outages and restarts. Requests are sent
+// with curl, which every supported platform ships, configured through its
+// stdin so URLs, headers (often tokens) and bodies never appear in process
+// listings.
+//
+// The outbox is private to the user and shared by every selfspy process, so
+// it is only rewritten under a lock file. A retry pass first claims the
+// requests that are due, by pushing their next attempt past the time curl
+// may take, so a concurrent pass doesn't send them twice.
+use crate::error::{Result, SelfspyError};
+use crate::files;
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use tracing::{debug, info, warn};
+
+pub const OUTBOX_NAME: &str = "webhook_outbox.jsonl";
+const LOCK_NAME: &str = "webhook_outbox.lock";
+
+const DEFAULT_TEMPLATE: &str =
+    r#"{"event": "{{event}}", "title": "{{title}}", "body": "{{body}}", "timestamp": "{{timestamp}}"}"#;
+// First retry after this long, doubling per attempt up to MAX_BACKOFF_SECONDS
+const BASE_BACKOFF_SECONDS: i64 = 30;
+const MAX_BACKOFF_SECONDS: i64 = 3600;
+// Undelivered requests beyond this are dropped, oldest first
+const MAX_OUTBOX: usize = 500;
+// curl gives up on a request after this long
+const REQUEST_TIMEOUT_SECONDS: i64 = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookSettings {
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    // Request body with {{event}}, {{title}}, {{body}} and {{timestamp}}
+    // placeholders. Values are JSON-escaped, so they can sit inside JSON
+    // strings; None sends a JSON object with all four fields.
+    #[serde(default)]
+    pub body_template: Option<String>,
+    // Attempts before an undelivered request is dropped
+    #[serde(default = "default_max_attempts")]
+    pub max_attempts: u32,
+}
+
+fn default_max_attempts() -> u32 {
+    8
+}
+
+// Something worth telling an integration about
+#[derive(Debug, Clone)]
+pub struct WebhookEvent {
+    // "alert", or whatever the caller names its events
+    pub event: String,
+    pub title: String,
+    pub body: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+// A rendered request waiting in the outbox
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingRequest {
+    // Identifies the request across the claim and the update after sending;
+    // 0 in outboxes from before ids, which get one when next claimed
+    #[serde(default)]
+    id: u64,
+    url: String,
+    headers: BTreeMap<String, String>,
+    body: String,
+    attempts: u32,
+    max_attempts: u32,
+    next_attempt: DateTime<Utc>,
+}
+
+pub struct Webhook {
+    settings: WebhookSettings,
+    outbox: PathBuf,
+    lock: PathBuf,
+}
+
+impl Webhook {
+    pub fn new(settings: WebhookSettings, data_dir: &Path) -> Self {
+        Self {
+            settings,
+            outbox: data_dir.join(OUTBOX_NAME),
+            lock: data_dir.join(LOCK_NAME),
+        }
+    }
+    
+    pub fn render(&self, event: &WebhookEvent) -> String {
+        let template = self.settings.body_template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+        template
+            .replace("{{event}}", &json_escape(&event.event))
+            .replace("{{title}}", &json_escape(&event.title))
+            .replace("{{body}}", &json_escape(&event.body))
+            .replace("{{timestamp}}", &event.timestamp.to_rfc3339())
+    }
+    
+    // Tries once right away; a failed request is queued for `retry_pending`
+    // and the error returned for logging
+    pub async fn send(&self, event: &WebhookEvent) -> Result<()> {
+        let request = PendingRequest {
+            id: new_id(),
+            url: self.settings.url.clone(),
+            headers: self.settings.headers.clone(),
+            body: self.render(event),
+            attempts: 1,
+            max_attempts: self.settings.max_attempts.max(1),
+            next_attempt: event.timestamp + backoff(1),
+        };
+        
+        let Err(e) = post(&request).await else {
+            return Ok(());
+        };
+        if request.attempts < request.max_attempts {
+            let _lock = files::lock(&self.lock)?;
+            let mut pending = read_outbox(&self.outbox);
+            pending.push(request);
+            write_outbox(&self.outbox, &pending)?;
+        }
+        Err(e)
+    }
+    
+    // Re-sends queued requests whose backoff has passed. Returns how many
+    // were delivered.
+    pub async fn retry_pending(&self, now: DateTime<Utc>) -> usize {
+        let due = match self.claim_due(now) {
+            Ok(due) => due,
+            Err(e) => {
+                warn!("Failed to read the webhook outbox: {}", e);
+                return 0;
+            }
+        };
+        if due.is_empty() {
+            return 0;
+        }
+        
+        // None for delivered or abandoned requests
+        let mut outcomes = HashMap::new();
+        let mut delivered = 0;
+        for mut request in due {
+            match post(&request).await {
+                Ok(()) => {
+                    delivered += 1;
+                    outcomes.insert(request.id, None);
+                }
+                Err(e) => {
+                    request.attempts += 1;
+                    if request.attempts >= request.max_attempts {
+                        warn!("Giving up on webhook to {} after {} attempts: {}", request.url, request.attempts, e);
+                        outcomes.insert(request.id, None);
+                        continue;
+                    }
+                    debug!("Webhook to {} failed again: {}", request.url, e);
+                    request.next_attempt = now + backoff(request.attempts);
+                    outcomes.insert(request.id, Some(request));
+                }
+            }
+        }
+        
+        if delivered > 0 {
+            info!("Delivered {} queued webhook requests", delivered);
+        }
+        if let Err(e) = self.apply(outcomes) {
+            warn!("Failed to update the webhook outbox: {}", e);
+        }
+        delivered
+    }
+    
+    // Takes the requests that are due, leaving them in the outbox with their
+    // next attempt pushed past the time sending them can take
+    fn claim_due(&self, now: DateTime<Utc>) -> Result<Vec<PendingRequest>> {
+        let _lock = files::lock(&self.lock)?;
+        let mut pending = read_outbox(&self.outbox);
+        let due_count = pending.iter().filter(|request| request.next_attempt <= now).count();
+        if due_count == 0 {
+            return Ok(Vec::new());
+        }
+        
+        // They are sent one after the other
+        let claimed_until = now + chrono::Duration::seconds(REQUEST_TIMEOUT_SECONDS * (due_count as i64 + 1));
+        let mut due = Vec::with_capacity(due_count);
+        for request in pending.iter_mut().filter(|request| request.next_attempt <= now) {
+            if request.id == 0 {
+                request.id = new_id();
+            }
+            due.push(request.clone());
+            request.next_attempt = claimed_until;
+        }
+        write_outbox(&self.outbox, &pending)?;
+        Ok(due)
+    }
+    
+    // Writes back what became of the claimed requests, keeping any queued
+    // meanwhile
+    fn apply(&self, mut outcomes: HashMap<u64, Option<PendingRequest>>) -> Result<()> {
+        let _lock = files::lock(&self.lock)?;
+        let remaining: Vec<PendingRequest> = read_outbox(&self.outbox)
+            .into_iter()
+            .filter_map(|request| match outcomes.remove(&request.id) {
+                Some(outcome) => outcome,
+                None => Some(request),
+            })
+            .collect();
+        write_outbox(&self.outbox, &remaining)
+    }
+}
+
+fn new_id() -> u64 {
+    rand::thread_rng().gen_range(1..u64::MAX)
+}
+
+fn backoff(attempts: u32) -> chrono::Duration {
+    let seconds = BASE_BACKOFF_SECONDS.saturating_mul(1 << attempts.saturating_sub(1).min(16));
+    chrono::Duration::seconds(seconds.min(MAX_BACKOFF_SECONDS))
+}
+
+// The JSON string literal without its quotes
+fn json_escape(value: &str) -> String {
+    let quoted = serde_json::Value::String(value.to_string()).to_string();
+    quoted[1..quoted.len() - 1].to_string()
+}
+
+async fn post(request: &PendingRequest) -> Result<()> {
+    let mut config = format!("url = {}\n", config_quote(&request.url));
+    if !request.headers.keys().any(|name| name.eq_ignore_ascii_case("content-type")) {
+        config.push_str("header = \"Content-Type: application/json\"\n");
+    }
+    for (name, value) in &request.headers {
+        config.push_str(&format!("header = {}\n", config_quote(&format!("{}: {}", name, value))));
+    }
+    // data-raw sends the body as is, without reading a file for a leading @
+    config.push_str(&format!("data-raw = {}\n", config_quote(&request.body)));
+    
+    let mut child = Command::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--max-time"])
+        .arg(REQUEST_TIMEOUT_SECONDS.to_string())
+        .args(["--config", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| SelfspyError::Notification(format!("Failed to run curl: {}", e)))?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(config.as_bytes()).await?;
+    }
+    
+    let output = child.wait_with_output().await?;
+    if !output.status.success() {
+        return Err(SelfspyError::Notification(format!(
+            "Webhook request failed ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(())
+}
+
+// A double-quoted curl config value; curl unescapes \\, \", \n, \r and \t
+fn config_quote(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+// Unreadable lines are skipped rather than blocking the rest of the queue
+fn read_outbox(path: &Path) -> Vec<PendingRequest> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn write_outbox(path: &Path, pending: &[PendingRequest]) -> Result<()> {
+    if pending.is_empty() {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+        return Ok(());
+    }
+    
+    let skip = pending.len().saturating_sub(MAX_OUTBOX);
+    let mut contents = String::new();
+    for request in &pending[skip..] {
+        contents.push_str(&serde_json::to_string(request)?);
+        contents.push('\n');
+    }
+    
+    files::write_private(path, contents.as_bytes())?;
+    Ok(())
+}
@@ -0,0 +1,69 @@
+// accounts::screen_time: reads only what consenting accounts published, and
+// revoking takes the published days back
+mod common;
+
+use chrono::{NaiveDate, Utc};
+use selfspy_core::accounts::{self, AccountDay, AccountReportSettings, AccountSource, AccountStatus, SharedScreenTime};
+
+use common::TempDir;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+}
+
+fn publish(dir: &TempDir, days: &[(NaiveDate, i64)]) {
+    let shared = SharedScreenTime {
+        published_at: Utc::now(),
+        days: days
+            .iter()
+            .map(|(date, active_seconds)| AccountDay {
+                date: *date,
+                active_seconds: *active_seconds,
+            })
+            .collect(),
+    };
+    std::fs::write(dir.path().join(accounts::SHARED_FILE), serde_json::to_string(&shared).unwrap()).unwrap();
+}
+
+fn source(name: &str, dir: &TempDir) -> AccountSource {
+    AccountSource {
+        name: name.to_string(),
+        data_dir: dir.path().to_path_buf(),
+    }
+}
+
+#[test]
+fn reports_published_days_of_consenting_accounts() {
+    let shared = TempDir::new("account-shared");
+    accounts::grant_consent(shared.path()).unwrap();
+    publish(&shared, &[(date(1), 600), (date(2), 3600), (date(9), 60)]);
+    // Granted, but its monitor hasn't published since
+    let pending = TempDir::new("account-pending");
+    accounts::grant_consent(pending.path()).unwrap();
+    // Published once, then sharing was turned off behind the file's back
+    let private = TempDir::new("account-private");
+    publish(&private, &[(date(2), 7200)]);
+    std::fs::write(private.db_path(), b"not read").unwrap();
+    
+    let settings = AccountReportSettings {
+        accounts: vec![source("shared", &shared), source("pending", &pending), source("private", &private)],
+    };
+    let reports = accounts::screen_time(&settings, date(2), date(9));
+    
+    assert!(matches!(reports[0].status, AccountStatus::Shared(_)));
+    let days: Vec<(NaiveDate, i64)> = reports[0].days.iter().map(|day| (day.date, day.active_seconds)).collect();
+    assert_eq!(days, [(date(2), 3600)]);
+    assert!(matches!(reports[1].status, AccountStatus::NotPublished(_)));
+    assert!(matches!(reports[2].status, AccountStatus::NotShared));
+    assert!(reports[2].days.is_empty());
+}
+
+#[test]
+fn revoking_removes_what_was_published() {
+    let dir = TempDir::new("account-revoke");
+    accounts::grant_consent(dir.path()).unwrap();
+    publish(&dir, &[(date(2), 3600)]);
+    
+    assert!(accounts::revoke_consent(dir.path()).unwrap());
+    assert!(!dir.path().join(accounts::SHARED_FILE).exists());
+}
@@ -0,0 +1,67 @@
+// aggregate::aggregate: reads only what members published, and withholds
+// days and categories too few members contributed to
+mod common;
+
+use chrono::{NaiveDate, Utc};
+use selfspy_core::aggregate::{self, AggregationSettings, PublishedDay, PublishedTotals, PUBLISHED_FILE};
+
+use common::TempDir;
+
+fn date(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+}
+
+// A date, its active seconds and the seconds per category
+type Day<'a> = (NaiveDate, i64, &'a [(&'a str, i64)]);
+
+fn publish(dir: &TempDir, days: &[Day]) {
+    let totals = PublishedTotals {
+        published_at: Utc::now(),
+        days: days
+            .iter()
+            .map(|(date, active_seconds, categories)| PublishedDay {
+                date: *date,
+                active_seconds: *active_seconds,
+                categories: categories.iter().map(|(name, seconds)| (name.to_string(), *seconds)).collect(),
+            })
+            .collect(),
+    };
+    std::fs::write(dir.path().join(PUBLISHED_FILE), serde_json::to_string(&totals).unwrap()).unwrap();
+}
+
+#[tokio::test]
+async fn combines_published_totals_and_withholds_small_groups() {
+    let members: Vec<TempDir> = (0..3).map(|i| TempDir::new(&format!("member-{}", i))).collect();
+    publish(&members[0], &[(date(2), 3600, &[("Work", 3600)]), (date(3), 1800, &[("Work", 1800)])]);
+    publish(&members[1], &[(date(2), 7200, &[("Work", 5400), ("Games", 1800)])]);
+    publish(&members[2], &[(date(2), 1800, &[("Work", 1800)])]);
+    // Has a database but published nothing, so it counts toward no figure
+    let unpublished = TempDir::new("unpublished");
+    std::fs::write(unpublished.db_path(), b"not read").unwrap();
+    
+    let settings = AggregationSettings {
+        members: members.iter().chain([&unpublished]).map(|dir| dir.path().to_path_buf()).collect(),
+        min_members: 3,
+        rounding_minutes: 30,
+        ..AggregationSettings::default()
+    };
+    let days = aggregate::aggregate(&settings, date(1), date(10)).await.unwrap();
+    
+    // March 3rd had one member, and Games one member on the 2nd
+    assert_eq!(days.len(), 1);
+    assert_eq!(days[0].date, date(2));
+    assert_eq!(days[0].members, 3);
+    assert_eq!(days[0].active_seconds, 12600);
+    assert_eq!(days[0].categories.len(), 1);
+    assert_eq!(days[0].categories[0].category, "Work");
+    assert_eq!(days[0].categories[0].seconds, 10800);
+    
+    let outside = aggregate::aggregate(&settings, date(3), date(10)).await.unwrap();
+    assert!(outside.is_empty());
+}
+
+#[test]
+fn two_members_are_never_enough() {
+    let settings = AggregationSettings { min_members: 2, ..AggregationSettings::default() };
+    assert_eq!(settings.min_members(), 3);
+}
@@ -0,0 +1,50 @@
+// Database::get_app_active_seconds: activity periods split by the window in
+// focus, with long visits and the window still open counted in full
+mod common;
+
+use chrono::{DateTime, TimeZone, Utc};
+use selfspy_core::Database;
+
+use common::{new_db, raw_pool, window_info, TempDir};
+
+fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap()
+}
+
+async fn open_window(db: &Database, pool: &sqlx::SqlitePool, process: &str, opened: DateTime<Utc>) {
+    let process_id = db.insert_process(process, None).await.unwrap();
+    let window_id = db.insert_window(process_id, &window_info(process, process)).await.unwrap();
+    sqlx::query("UPDATE windows SET created_at = ? WHERE id = ?")
+        .bind(opened.format("%Y-%m-%d %H:%M:%S").to_string())
+        .bind(window_id)
+        .execute(pool)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn splits_activity_by_the_focused_app() {
+    let dir = TempDir::new("app-active");
+    let db = new_db(&dir).await;
+    let pool = raw_pool(&dir).await;
+    
+    open_window(&db, &pool, "Editor", at(9, 0)).await;
+    open_window(&db, &pool, "Slack", at(9, 30)).await;
+    open_window(&db, &pool, "Editor", at(10, 30)).await;
+    for (start, end) in [(at(9, 10), at(9, 50)), (at(10, 0), at(11, 0))] {
+        let period = db.open_period(start).await.unwrap();
+        db.extend_period(period, end).await.unwrap();
+    }
+    
+    let seconds = db.get_app_active_seconds(at(9, 0)..at(10, 45)).await.unwrap();
+    assert_eq!(seconds.get("Editor"), Some(&(35 * 60)));
+    // An hour in one window, past the cap on visits in get_process_stats
+    assert_eq!(seconds.get("Slack"), Some(&(50 * 60)));
+    
+    // The window opened before the range is in focus at its start
+    let seconds = db.get_app_active_seconds(at(9, 15)..at(10, 45)).await.unwrap();
+    assert_eq!(seconds.get("Editor"), Some(&(30 * 60)));
+    
+    let seconds = db.get_app_active_seconds(at(11, 0)..at(12, 0)).await.unwrap();
+    assert!(seconds.is_empty());
+}
@@ -0,0 +1,163 @@
+// backup::create and restore: a restore brings back the backed-up rows,
+// keeps the database it replaces and leaves no working files behind, and a
+// copy that fails verification never replaces the database. Incrementals
+// chain onto the backup before them, even one made within the same second
+mod common;
+
+use selfspy_core::backup::{self, BackupSettings};
+use selfspy_core::{Config, Database, SelfspyError};
+
+use common::{new_db, new_window, raw_pool, TempDir};
+
+fn config(dir: &TempDir) -> Config {
+    Config::new().with_data_dir(dir.path().to_path_buf())
+}
+
+async fn clicks_in(path: &std::path::Path) -> i64 {
+    let db = Database::open_read_only(path).await.unwrap();
+    let clicks = db.get_stats().await.unwrap().total_clicks;
+    db.close().await;
+    clicks
+}
+
+fn files_in(dir: &TempDir) -> Vec<String> {
+    std::fs::read_dir(dir.path())
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[tokio::test]
+async fn restore_brings_back_a_backup_and_keeps_the_replaced_database() {
+    let dir = TempDir::new("backup-restore");
+    let config = config(&dir);
+    let settings = BackupSettings::default();
+    let db = new_db(&dir).await;
+    let window_id = new_window(&db, "Editor").await;
+    db.insert_click(window_id, 0, 0, "left", false, 1.0).await.unwrap();
+    
+    let entry = backup::create(&config, &settings, false).await.unwrap();
+    assert!(entry.parent.is_none());
+    assert!(BackupSettings::local_dir(&config).join(&entry.name).exists());
+    assert_eq!(backup::list(&config, &settings).unwrap().len(), 1);
+    
+    db.insert_click(window_id, 0, 0, "left", false, 1.0).await.unwrap();
+    db.close().await;
+    backup::restore(&config, &settings, &entry.name).await.unwrap();
+    assert_eq!(clicks_in(&config.database_path).await, 1);
+    
+    let files = files_in(&dir);
+    let kept: Vec<&String> = files
+        .iter()
+        .filter(|name| name.starts_with("selfspy.db.before-restore-") && !name.ends_with("-wal") && !name.ends_with("-shm"))
+        .collect();
+    assert_eq!(kept.len(), 1, "{:?}", files);
+    assert_eq!(clicks_in(&dir.path().join(kept[0])).await, 2);
+    assert!(!files.iter().any(|name| name.starts_with("selfspy.db.restore")), "{:?}", files);
+}
+
+#[tokio::test]
+async fn a_damaged_copy_is_refused_and_the_database_kept() {
+    let dir = TempDir::new("backup-damaged");
+    let config = config(&dir);
+    let db = new_db(&dir).await;
+    let window_id = new_window(&db, "Editor").await;
+    db.insert_click(window_id, 0, 0, "left", false, 1.0).await.unwrap();
+    db.close().await;
+    
+    // Every page after the header page overwritten with garbage
+    let copy = dir.path().join("copy.db");
+    backup::copy_to(&config, &copy).await.unwrap();
+    let mut bytes = std::fs::read(&copy).unwrap();
+    let page_size = u16::from_be_bytes([bytes[16], bytes[17]]) as usize;
+    bytes[page_size..].fill(0xA5);
+    std::fs::write(&copy, bytes).unwrap();
+    
+    assert!(backup::restore_file(&config, &copy).await.is_err());
+    assert_eq!(clicks_in(&config.database_path).await, 1);
+    let files = files_in(&dir);
+    assert!(!files.iter().any(|name| name.contains(".restore") || name.contains("before-restore")), "{:?}", files);
+}
+
+#[tokio::test]
+async fn incrementals_chain_and_restore_to_the_newest() {
+    let dir = TempDir::new("backup-chain");
+    let config = config(&dir);
+    let settings = BackupSettings::default();
+    let db = new_db(&dir).await;
+    let window_id = new_window(&db, "Editor").await;
+    
+    let mut entries = Vec::new();
+    for _ in 0..3 {
+        db.insert_click(window_id, 0, 0, "left", false, 1.0).await.unwrap();
+        entries.push(backup::create(&config, &settings, false).await.unwrap());
+    }
+    db.close().await;
+    
+    let manifest = backup::Manifest { backups: backup::list(&config, &settings).unwrap() };
+    let chain: Vec<&str> = manifest.chain(&entries[2].name).unwrap().iter().map(|entry| entry.name.as_str()).collect();
+    let names: Vec<&str> = entries.iter().map(|entry| entry.name.as_str()).collect();
+    assert_eq!(chain, names);
+    assert!(entries[1].name.ends_with(".delta") && entries[1].pages < entries[1].page_count);
+    assert_eq!(entries[2].parent.as_deref(), Some(names[1]));
+    
+    backup::restore(&config, &settings, &entries[2].name).await.unwrap();
+    assert_eq!(clicks_in(&config.database_path).await, 3);
+}
+
+#[tokio::test]
+async fn an_incremental_after_a_vacuum_restores_the_smaller_database() {
+    let dir = TempDir::new("backup-shrink");
+    let config = config(&dir);
+    let settings = BackupSettings::default();
+    let db = new_db(&dir).await;
+    let pool = raw_pool(&dir).await;
+    let window_id = new_window(&db, "Editor").await;
+    db.insert_click(window_id, 0, 0, "left", false, 1.0).await.unwrap();
+    sqlx::query("CREATE TABLE filler (data BLOB)").execute(&pool).await.unwrap();
+    sqlx::query(
+        "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 200)
+         INSERT INTO filler SELECT zeroblob(4000) FROM n",
+    )
+    .execute(&pool)
+    .await
+    .unwrap();
+    let full = backup::create(&config, &settings, false).await.unwrap();
+    
+    sqlx::query("DROP TABLE filler").execute(&pool).await.unwrap();
+    sqlx::query("VACUUM").execute(&pool).await.unwrap();
+    pool.close().await;
+    let delta = backup::create(&config, &settings, false).await.unwrap();
+    db.close().await;
+    assert_eq!(delta.parent.as_deref(), Some(full.name.as_str()));
+    assert!(delta.page_count < full.page_count);
+    
+    backup::restore(&config, &settings, &delta.name).await.unwrap();
+    let size = std::fs::metadata(&config.database_path).unwrap().len();
+    assert_eq!(size, delta.page_count * delta.page_size as u64);
+    assert_eq!(clicks_in(&config.database_path).await, 1);
+}
+
+#[tokio::test]
+async fn a_chain_that_does_not_reproduce_its_hash_is_refused() {
+    let dir = TempDir::new("backup-hash");
+    let config = config(&dir);
+    let settings = BackupSettings::default();
+    let db = new_db(&dir).await;
+    let window_id = new_window(&db, "Editor").await;
+    backup::create(&config, &settings, false).await.unwrap();
+    db.insert_click(window_id, 0, 0, "left", false, 1.0).await.unwrap();
+    let delta = backup::create(&config, &settings, false).await.unwrap();
+    db.close().await;
+    
+    // One flipped byte in the last page the incremental stores
+    let path = BackupSettings::local_dir(&config).join(&delta.name);
+    let mut bytes = std::fs::read(&path).unwrap();
+    *bytes.last_mut().unwrap() ^= 0xFF;
+    std::fs::write(&path, bytes).unwrap();
+    
+    let err = backup::restore(&config, &settings, &delta.name).await.unwrap_err();
+    assert!(matches!(err, SelfspyError::InvalidData(_)), "{:?}", err);
+    assert_eq!(clicks_in(&config.database_path).await, 1);
+    assert!(!files_in(&dir).iter().any(|name| name.contains("restore")));
+}
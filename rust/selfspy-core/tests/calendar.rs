@@ -0,0 +1,22 @@
+// calendar::week_label around the turn of the year
+use chrono::{NaiveDate, Weekday};
+use selfspy_core::calendar::week_label;
+
+fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(y, m, d).unwrap()
+}
+
+#[test]
+fn a_sunday_week_holding_new_year_is_week_one_of_the_new_year() {
+    // Sunday 2025-12-28 to Saturday 2026-01-03
+    assert_eq!(week_label(date(2025, 12, 28), Weekday::Sun), "2026-W01");
+    assert_eq!(week_label(date(2026, 1, 3), Weekday::Sun), "2026-W01");
+    assert_eq!(week_label(date(2025, 12, 27), Weekday::Sun), "2025-W52");
+    assert_eq!(week_label(date(2026, 1, 4), Weekday::Sun), "2026-W02");
+}
+
+#[test]
+fn monday_weeks_follow_iso_8601() {
+    assert_eq!(week_label(date(2025, 12, 29), Weekday::Mon), "2026-W01");
+    assert_eq!(week_label(date(2027, 1, 1), Weekday::Mon), "2026-W53");
+}
@@ -0,0 +1,39 @@
+// CaptureSettings: key_text and key_counts are independent switches, so text
+// is still recorded when keystrokes aren't counted
+mod common;
+
+use std::sync::Arc;
+
+use selfspy_core::pipeline::PipelineEvent;
+use selfspy_core::platform::{FallbackTracker, InputEvent, Modifiers};
+use selfspy_core::{ActivityMonitor, Config};
+
+use common::{raw_pool, window_info, TempDir};
+
+#[tokio::test]
+async fn text_is_recorded_without_counts() {
+    let dir = TempDir::new("capture-text");
+    let mut config = Config::new().with_data_dir(dir.path().to_path_buf());
+    config.capture.key_counts = false;
+    config.track_presenting = false;
+    config.track_media = false;
+    config.track_meetings = false;
+    config.track_power = false;
+    let monitor = ActivityMonitor::with_tracker(config, None, Arc::new(FallbackTracker)).await.unwrap();
+    
+    monitor.handle_event(PipelineEvent::Window(window_info("Editor", "notes.txt"))).await.unwrap();
+    for key in ["h", "i"] {
+        let event = InputEvent::key(key, None, Modifiers::default(), true);
+        monitor.handle_event(PipelineEvent::Input(event)).await.unwrap();
+    }
+    monitor.on_tick(0, true).await;
+    
+    let pool = raw_pool(&dir).await;
+    let (events, stored): (i64, i64) =
+        sqlx::query_as("SELECT SUM(event_count), SUM(length(encrypted_keys)) FROM keys")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(events, 0);
+    assert!(stored > 0);
+}
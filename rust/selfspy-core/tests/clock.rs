@@ -0,0 +1,83 @@
+// ActivityMonitor::on_tick on a ManualClock: the daily jobs follow the local
+// date across midnight and a DST change, and the DST hour isn't taken for sleep
+mod common;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{TimeZone, Utc};
+use selfspy_core::aggregate::PUBLISHED_FILE;
+use selfspy_core::clock::ManualClock;
+use selfspy_core::platform::FallbackTracker;
+use selfspy_core::{ActivityMonitor, Config};
+
+use common::{raw_pool, TempDir};
+
+// Central European time, spelled as a POSIX rule so no tz database is needed;
+// every test sets the same value
+fn use_central_european_time() {
+    std::env::set_var("TZ", "CET-1CEST,M3.5.0,M10.5.0/3");
+}
+
+async fn monitor(dir: &TempDir, clock: Arc<ManualClock>) -> ActivityMonitor<FallbackTracker> {
+    let mut config = Config::new().with_data_dir(dir.path().to_path_buf());
+    config.aggregation.publish = true;
+    config.track_presenting = false;
+    config.track_media = false;
+    config.track_meetings = false;
+    config.track_power = false;
+    ActivityMonitor::with_tracker(config, None, Arc::new(FallbackTracker))
+        .await
+        .unwrap()
+        .with_clock(clock)
+}
+
+// Whether the daily jobs ran since the last call, going by the totals they publish
+fn daily_jobs_ran(dir: &TempDir) -> bool {
+    std::fs::remove_file(dir.path().join(PUBLISHED_FILE)).is_ok()
+}
+
+#[tokio::test]
+async fn daily_jobs_run_again_after_local_midnight() {
+    use_central_european_time();
+    let dir = TempDir::new("clock-midnight");
+    // 23:59:30 local time
+    let clock = Arc::new(ManualClock::new(Utc.with_ymd_and_hms(2026, 3, 2, 22, 59, 30).unwrap()));
+    let monitor = monitor(&dir, clock.clone()).await;
+    
+    monitor.on_tick(0, false).await;
+    assert!(daily_jobs_ran(&dir));
+    
+    clock.advance(Duration::from_secs(20));
+    monitor.on_tick(0, false).await;
+    assert!(!daily_jobs_ran(&dir));
+    
+    clock.advance(Duration::from_secs(20));
+    monitor.on_tick(0, false).await;
+    assert!(daily_jobs_ran(&dir));
+}
+
+#[tokio::test]
+async fn the_spring_forward_hour_is_neither_a_new_day_nor_sleep() {
+    use_central_european_time();
+    let dir = TempDir::new("clock-dst");
+    // 00:59:58 local on the day clocks go from 02:00 to 03:00, which has 23 hours
+    let clock = Arc::new(ManualClock::new(Utc.with_ymd_and_hms(2026, 3, 29, 0, 59, 58).unwrap()));
+    let monitor = monitor(&dir, clock.clone()).await;
+    
+    monitor.on_tick(0, false).await;
+    assert!(daily_jobs_ran(&dir));
+    // Local time goes from 01:59:59 to 03:00:00 between these ticks
+    for _ in 0..4 {
+        clock.advance(Duration::from_secs(1));
+        monitor.on_tick(0, false).await;
+    }
+    assert!(!daily_jobs_ran(&dir));
+    
+    let pool = raw_pool(&dir).await;
+    let sleeps: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM system_events WHERE kind = 'sleep'")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(sleeps, 0);
+}
@@ -0,0 +1,66 @@
+// Helpers shared by the integration tests
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use selfspy_core::db::DatabaseSettings;
+use selfspy_core::platform::WindowInfo;
+use selfspy_core::Database;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::SqlitePool;
+
+// A directory under the system temp dir, removed on drop
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        static NEXT: AtomicU32 = AtomicU32::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "selfspy-test-{}-{}-{}",
+            name,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+    
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+    
+    pub fn db_path(&self) -> PathBuf {
+        self.0.join("selfspy.db")
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
+
+pub async fn new_db(dir: &TempDir) -> Database {
+    Database::connect(&dir.db_path(), &DatabaseSettings::default()).await.unwrap()
+}
+
+// A second connection to the same file, for checking what was written
+pub async fn raw_pool(dir: &TempDir) -> SqlitePool {
+    SqlitePool::connect_with(SqliteConnectOptions::new().filename(dir.db_path())).await.unwrap()
+}
+
+// A window of a fresh "Test" process
+pub async fn new_window(db: &Database, title: &str) -> i64 {
+    let process_id = db.insert_process("Test", None).await.unwrap();
+    db.insert_window(process_id, &window_info("Test", title)).await.unwrap()
+}
+
+// A window with no document or geometry
+pub fn window_info(process: &str, title: &str) -> WindowInfo {
+    WindowInfo {
+        process_name: process.to_string(),
+        window_title: title.to_string(),
+        ..WindowInfo::default()
+    }
+}
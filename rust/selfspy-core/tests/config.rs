@@ -0,0 +1,22 @@
+// Config::save: config.json can hold the MQTT password, so only its owner
+// may read it
+mod common;
+
+use selfspy_core::mqtt::MqttSettings;
+use selfspy_core::Config;
+
+use common::TempDir;
+
+#[cfg(unix)]
+#[test]
+fn saved_config_is_private() {
+    use std::os::unix::fs::PermissionsExt;
+    
+    let dir = TempDir::new("config-save");
+    let mut config = Config::default().with_data_dir(dir.path().to_path_buf());
+    config.mqtt = Some(MqttSettings { password: Some("secret".to_string()), ..MqttSettings::default() });
+    config.save().unwrap();
+    
+    let mode = std::fs::metadata(config.config_path()).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
@@ -0,0 +1,61 @@
+// control::read_request and refusal: a request only reaches the routes when
+// it names localhost and wasn't sent by a browser, so a page can't drive the
+// endpoint by rebinding its own domain to 127.0.0.1; and requests that are
+// too large or end early are not requests at all
+use selfspy_core::control::{read_request, refusal, Request};
+
+async fn read(raw: &[u8]) -> Option<Request> {
+    read_request(&mut &raw[..]).await.unwrap()
+}
+
+fn get(host: Option<&str>) -> Vec<u8> {
+    let host = host.map(|host| format!("Host: {}\r\n", host)).unwrap_or_default();
+    format!("GET /status HTTP/1.1\r\n{}\r\n", host).into_bytes()
+}
+
+async fn refused(raw: &[u8]) -> bool {
+    refusal(&read(raw).await.unwrap()).is_some()
+}
+
+#[tokio::test]
+async fn only_localhost_names_are_accepted() {
+    for host in ["localhost", "LOCALHOST", "localhost:8788", "127.0.0.1:8788", "[::1]", "[::1]:8788"] {
+        assert!(!refused(&get(Some(host))).await, "{}", host);
+    }
+    // HTTP/1.0 clients send no Host at all
+    assert!(!refused(&get(None)).await);
+    
+    for host in ["localhost.evil.com", "127.0.0.1.evil.com:8080", "evil.com", "localhost:8788.evil.com", "::1", ""] {
+        assert!(refused(&get(Some(host))).await, "{}", host);
+    }
+}
+
+#[tokio::test]
+async fn browser_requests_are_refused() {
+    let raw = b"POST /pause HTTP/1.1\r\nHost: localhost:8788\r\nOrigin: http://localhost:8788\r\n\r\n";
+    let (status, _) = refusal(&read(raw).await.unwrap()).unwrap();
+    assert_eq!(status, 403);
+    
+    let raw = b"POST /pause HTTP/1.1\r\nhost: 127.0.0.1\r\norigin: null\r\n\r\n";
+    assert!(refused(raw).await);
+}
+
+#[tokio::test]
+async fn bodies_are_read_in_full_and_bounded() {
+    let raw = b"POST /annotate HTTP/1.1\r\nHost: localhost\r\nContent-Length: 5\r\n\r\nbreak";
+    let request = read(raw).await.unwrap();
+    assert_eq!((request.method.as_str(), request.path.as_str()), ("POST", "/annotate"));
+    assert_eq!(request.header("content-length"), Some("5"));
+    assert_eq!(request.body, b"break");
+    
+    // The connection closes before the promised body arrives
+    assert!(read(b"POST /annotate HTTP/1.1\r\nContent-Length: 10\r\n\r\nbre").await.is_none());
+    assert!(read(b"GET /status HTTP/1.1\r\nHost: local").await.is_none());
+    
+    let mut oversized = b"POST /annotate HTTP/1.1\r\nContent-Length: 20000\r\n\r\n".to_vec();
+    oversized.resize(oversized.len() + 20000, b'x');
+    assert!(read(&oversized).await.is_none());
+    let mut endless = b"GET /status HTTP/1.1\r\n".to_vec();
+    endless.extend(std::iter::repeat_n(b"X-Pad: 0\r\n".as_slice(), 2000).flatten());
+    assert!(read(&endless).await.is_none());
+}
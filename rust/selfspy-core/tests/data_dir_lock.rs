@@ -0,0 +1,20 @@
+// monitor::lock_data_dir: one holder at a time, released on drop
+mod common;
+
+use selfspy_core::monitor::lock_data_dir;
+use selfspy_core::{ErrorKind, SelfspyError};
+
+use common::TempDir;
+
+#[test]
+fn a_held_lock_refuses_a_second_holder() {
+    let dir = TempDir::new("data-dir-lock");
+    let lock = lock_data_dir(dir.path()).unwrap();
+    
+    let err = lock_data_dir(dir.path()).unwrap_err();
+    assert!(matches!(err, SelfspyError::MonitorRunning));
+    assert_eq!(err.kind(), ErrorKind::DatabaseLocked);
+    
+    drop(lock);
+    assert!(lock_data_dir(dir.path()).is_ok());
+}
@@ -0,0 +1,24 @@
+// display::normalize_point: physical pixels and display bounds end up in the
+// same logical space
+use selfspy_core::platform::display::{normalize_point, CoordinateSpace, DisplayInfo};
+
+fn display(id: u32, x: f64, y: f64, width: f64, height: f64, scale: f64) -> DisplayInfo {
+    DisplayInfo { id, x, y, width, height, scale }
+}
+
+#[test]
+fn a_click_on_a_scaled_secondary_display_maps_back_to_its_pixels() {
+    // 1920x1080 at 100%, then a 3840x2160 panel at 200% to its right
+    let displays = [
+        display(0, 0.0, 0.0, 1920.0, 1080.0, 1.0),
+        display(1, 960.0, 0.0, 1920.0, 1080.0, 2.0),
+    ];
+    
+    let point = normalize_point(&displays, 3000, 400, CoordinateSpace::Physical);
+    assert_eq!(point.scale, 2.0);
+    assert_eq!((point.x, point.y), (1500, 200));
+    assert_eq!(point.x as f64 * point.scale, 3000.0);
+    
+    let point = normalize_point(&displays, 1000, 400, CoordinateSpace::Physical);
+    assert_eq!((point.x, point.y, point.scale), (1000, 400, 1.0));
+}
@@ -0,0 +1,49 @@
+// export::write_events: a key batch the password can't decrypt, such as text
+// from before the key derivation was stored, doesn't stop the export; it is
+// written without its text and counted
+mod common;
+
+use chrono::{Duration, Utc};
+use selfspy_core::encryption::{Encryptor, KdfSettings};
+use selfspy_core::export::{write_events, ExportFormat, KeyText};
+use selfspy_core::key_count::KeyCounts;
+use selfspy_core::Progress;
+
+use common::{new_db, new_window, TempDir};
+
+const KDF: KdfSettings = KdfSettings { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+#[tokio::test]
+async fn undecryptable_batches_are_exported_without_text() {
+    let dir = TempDir::new("export");
+    let db = new_db(&dir).await;
+    let window_id = new_window(&db, "Editor").await;
+    
+    let (encryptor, _) = Encryptor::create("password", KDF).unwrap();
+    let (lost_key, _) = Encryptor::create("password", KDF).unwrap();
+    let counts = KeyCounts { events: 5, characters: 5, ..KeyCounts::default() };
+    db.insert_keys(window_id, encryptor.encrypt(b"hello").unwrap(), &counts).await.unwrap();
+    db.insert_keys(window_id, lost_key.encrypt(b"older").unwrap(), &counts).await.unwrap();
+    
+    let mut out = Vec::new();
+    let range = Utc::now() - Duration::hours(1)..Utc::now() + Duration::hours(1);
+    let summary = write_events(&db, range, ExportFormat::Jsonl, KeyText::Decrypt(&encryptor), &mut out, &Progress::none())
+        .await
+        .unwrap();
+    assert_eq!((summary.key_batches, summary.undecryptable), (2, 1));
+    
+    let texts: Vec<Option<String>> = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .filter(|record| record["kind"] == "keys")
+        .map(|record| record["text"].as_str().map(str::to_string))
+        .collect();
+    assert_eq!(texts, vec![Some("hello".to_string()), None]);
+    
+    // The streams are timed like every other query
+    let timed: Vec<String> = db.query_stats().into_iter().map(|stat| stat.query).collect();
+    for query in ["stream_windows", "stream_clicks", "stream_keys"] {
+        assert!(timed.iter().any(|timed| timed == query), "{}", query);
+    }
+}
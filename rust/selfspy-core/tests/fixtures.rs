@@ -0,0 +1,121 @@
+// The stats queries against FixtureBuilder databases, whose totals are known
+// exactly: per day, per hour, per app and over a time range
+mod common;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone, Utc};
+use selfspy_core::fixtures::FixtureBuilder;
+use selfspy_core::{BucketSize, InputMixGrouping};
+
+use common::TempDir;
+
+fn day(day: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(2026, 3, day).unwrap()
+}
+
+fn local(day: NaiveDate, hour: u32, minute: u32) -> DateTime<Utc> {
+    Local
+        .from_local_datetime(&day.and_hms_opt(hour, minute, 0).unwrap())
+        .earliest()
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+// Two apps sharing the morning of the 2nd, one app in its afternoon, and a
+// quiet 3rd
+fn fixture() -> FixtureBuilder {
+    FixtureBuilder::new()
+        .keystrokes("Code", day(2), 9, 1200)
+        .clicks("Code", day(2), 9, 40)
+        .active_minutes("Code", day(2), 9, 45)
+        .keystrokes("Slack", day(2), 9, 300)
+        .clicks("Slack", day(2), 14, 12)
+        .active_minutes("Slack", day(2), 14, 6)
+        .keystrokes("Code", day(3), 22, 50)
+        .active_minutes("Code", day(3), 22, 10)
+}
+
+#[tokio::test]
+async fn daily_summaries_match_the_fixture() {
+    let dir = TempDir::new("fixtures-daily");
+    let fixture = fixture();
+    let db = fixture.build(&dir.db_path()).await.unwrap();
+    
+    assert_eq!(fixture.expected_day(day(2)).active_seconds, 52 * 60);
+    for date in [day(2), day(3)] {
+        let expected = fixture.expected_day(date);
+        let summary = db.get_daily_summary(date).await.unwrap();
+        assert_eq!(
+            (summary.active_seconds, summary.keystrokes, summary.clicks),
+            (expected.active_seconds, expected.keystrokes, expected.clicks)
+        );
+    }
+    
+    let summaries = db.get_daily_summaries(day(1), day(5)).await.unwrap();
+    assert_eq!(summaries.iter().map(|summary| summary.date).collect::<Vec<_>>(), [day(2), day(3)]);
+    assert_eq!(summaries[0].keystrokes, 1500);
+    assert_eq!(summaries[1].active_seconds, 600);
+}
+
+#[tokio::test]
+async fn hourly_activity_matches_the_fixture() {
+    let dir = TempDir::new("fixtures-hourly");
+    let db = fixture().build(&dir.db_path()).await.unwrap();
+    
+    let hours: Vec<(u32, i64)> = db
+        .get_hourly_activity(day(2), day(3))
+        .await
+        .unwrap()
+        .iter()
+        .map(|hour| (chrono::Timelike::hour(&hour.hour), hour.active_minutes))
+        .collect();
+    assert_eq!(hours, [(9, 46), (14, 6)]);
+}
+
+#[tokio::test]
+async fn range_and_app_totals_match_the_fixture() {
+    let dir = TempDir::new("fixtures-apps");
+    let db = fixture().build(&dir.db_path()).await.unwrap();
+    let morning = local(day(2), 9, 0)..local(day(2), 10, 0);
+    
+    let stats = db.get_stats_between(morning.start, morning.end).await.unwrap();
+    assert_eq!((stats.total_keystrokes, stats.total_clicks), (1500, 40));
+    assert_eq!(stats.session_duration, 46 * 60);
+    
+    let apps = db.get_process_stats(morning.clone()).await.unwrap();
+    let totals: Vec<(&str, i64, i64)> =
+        apps.iter().map(|app| (app.process.as_str(), app.keystrokes, app.clicks)).collect();
+    assert_eq!(totals, [("Code", 1200, 40), ("Slack", 300, 0)]);
+    
+    let seconds = db.get_app_active_seconds(morning).await.unwrap();
+    assert_eq!(seconds.get("Code"), Some(&(45 * 60)));
+    assert_eq!(seconds.get("Slack"), Some(&60));
+}
+
+// Keystrokes are key presses everywhere, corrections included, so no two
+// reports disagree about the same input
+#[tokio::test]
+async fn keystrokes_count_corrections_in_every_report() {
+    let dir = TempDir::new("fixtures-corrections");
+    let fixture = FixtureBuilder::new()
+        .keystrokes("Code", day(2), 9, 100)
+        .corrections("Code", day(2), 9, 20)
+        .active_minutes("Code", day(2), 9, 5);
+    let db = fixture.build(&dir.db_path()).await.unwrap();
+    let morning = local(day(2), 9, 0)..local(day(2), 10, 0);
+    assert_eq!(fixture.expected_day(day(2)).keystrokes, 120);
+    
+    assert_eq!(db.get_daily_summary(day(2)).await.unwrap().keystrokes, 120);
+    assert_eq!(db.get_daily_summaries(day(2), day(3)).await.unwrap()[0].keystrokes, 120);
+    assert_eq!(db.get_stats_between(morning.start, morning.end).await.unwrap().total_keystrokes, 120);
+    assert_eq!(db.get_process_stats(morning.clone()).await.unwrap()[0].keystrokes, 120);
+    let minutes = db.get_minute_totals(morning.clone()).await.unwrap();
+    assert_eq!(minutes.iter().map(|minute| minute.keystrokes).sum::<i64>(), 120);
+    let buckets = db.get_activity_buckets(morning, BucketSize::Day).await.unwrap();
+    assert_eq!(buckets.iter().map(|bucket| bucket.keystrokes).sum::<i64>(), 120);
+    let rates = db.get_error_rates(day(2), day(3), InputMixGrouping::App).await.unwrap();
+    assert_eq!((rates[0].keystrokes, rates[0].corrections), (120, 20));
+    
+    // Rolled up, the day still counts the same
+    db.prune_before(day(3), true).await.unwrap();
+    assert_eq!(db.get_daily_summaries(day(2), day(3)).await.unwrap()[0].keystrokes, 120);
+}
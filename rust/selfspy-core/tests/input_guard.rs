@@ -0,0 +1,27 @@
+// InputGuard's stuck-key check: only an unbroken run of repeats is dropped
+use selfspy_core::input_guard::{InputGuard, Verdict};
+use std::time::{Duration, Instant};
+
+#[test]
+fn a_key_held_down_is_dropped_after_the_limit() {
+    let mut guard = InputGuard::new(1000, 15, 5);
+    let start = Instant::now();
+    
+    let verdicts: Vec<_> = (0..8)
+        .map(|i| guard.check_key("a", start + Duration::from_millis(30 * i)))
+        .collect();
+    assert_eq!(&verdicts[..5], &[Verdict::Accept; 5]);
+    assert_eq!(&verdicts[5..], &[Verdict::Drop; 3]);
+    assert_eq!(guard.take_anomalies()[0].dropped, 3);
+}
+
+#[test]
+fn the_same_key_pressed_now_and_then_is_never_stuck() {
+    let mut guard = InputGuard::new(1000, 15, 5);
+    let start = Instant::now();
+    
+    for i in 0..50 {
+        assert_eq!(guard.check_key("Down", start + Duration::from_secs(2 * i)), Verdict::Accept);
+    }
+    assert!(guard.take_anomalies().is_empty());
+}
@@ -0,0 +1,48 @@
+// Database::close_interrupted_spans: what a crashed monitor left open ends at
+// its last recorded activity, and what it closed stays closed
+mod common;
+
+use chrono::{TimeZone, Utc};
+
+use common::{new_db, raw_pool, TempDir};
+
+#[tokio::test]
+async fn open_plays_end_at_the_last_activity() {
+    let dir = TempDir::new("interrupted");
+    let db = new_db(&dir).await;
+    let pool = raw_pool(&dir).await;
+    
+    let play_id = db.open_media_play("player", "Track", None).await.unwrap();
+    sqlx::query("UPDATE media_plays SET started_at = '2026-03-02 09:00:00'").execute(&pool).await.unwrap();
+    let period = db.open_period(Utc.with_ymd_and_hms(2026, 3, 2, 9, 5, 0).unwrap()).await.unwrap();
+    db.extend_period(period, Utc.with_ymd_and_hms(2026, 3, 2, 9, 40, 0).unwrap()).await.unwrap();
+    
+    let last_activity = db.get_last_activity().await.unwrap().unwrap();
+    assert_eq!(last_activity, Utc.with_ymd_and_hms(2026, 3, 2, 9, 40, 0).unwrap());
+    assert_eq!(db.close_interrupted_spans(last_activity).await.unwrap(), 1);
+    
+    let ended: Option<String> = sqlx::query_scalar("SELECT ended_at FROM media_plays WHERE id = ?")
+        .bind(play_id)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(ended.as_deref(), Some("2026-03-02 09:40:00"));
+    assert_eq!(db.close_interrupted_spans(last_activity).await.unwrap(), 0);
+}
+
+#[tokio::test]
+async fn an_open_meeting_ends_at_the_last_activity() {
+    let dir = TempDir::new("interrupted-meeting");
+    let db = new_db(&dir).await;
+    let at = |hour, minute| Utc.with_ymd_and_hms(2026, 3, 2, hour, minute, 0).unwrap();
+    
+    db.insert_system_event("meeting_start", at(9, 0)).await.unwrap();
+    db.insert_system_event("meeting_end", at(9, 30)).await.unwrap();
+    db.insert_system_event("meeting_start", at(10, 0)).await.unwrap();
+    assert_eq!(db.close_interrupted_spans(at(10, 20)).await.unwrap(), 1);
+    assert_eq!(db.close_interrupted_spans(at(10, 20)).await.unwrap(), 0);
+    
+    let events = db.get_system_events(at(0, 0)..at(23, 0)).await.unwrap();
+    let meetings = selfspy_core::meetings::meeting_intervals(&events, at(23, 0));
+    assert_eq!(meetings, [at(9, 0)..at(9, 30), at(10, 0)..at(10, 20)]);
+}
@@ -0,0 +1,64 @@
+// Database::import_legacy: typed text from an unencrypted original selfspy
+// database comes along, and the same database can't be imported twice
+mod common;
+
+use selfspy_core::db::LegacyText;
+use selfspy_core::{Progress, SelfspyError};
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::SqlitePool;
+use std::path::{Path, PathBuf};
+
+use common::{new_db, raw_pool, TempDir};
+
+// The tables of the original selfspy, with one window typed into
+async fn legacy_db(dir: &Path) -> PathBuf {
+    let path = dir.join("selfspy.sqlite");
+    let pool = SqlitePool::connect_with(SqliteConnectOptions::new().filename(&path).create_if_missing(true))
+        .await
+        .unwrap();
+    for sql in [
+        "CREATE TABLE process (id INTEGER PRIMARY KEY, name TEXT, created_at TEXT)",
+        "CREATE TABLE window (id INTEGER PRIMARY KEY, title TEXT, process_id INTEGER, created_at TEXT)",
+        "CREATE TABLE geometry (id INTEGER PRIMARY KEY, xpos INTEGER, ypos INTEGER, width INTEGER, height INTEGER)",
+        "CREATE TABLE keys (id INTEGER PRIMARY KEY, text BLOB, nrkeys INTEGER, window_id INTEGER,
+            geometry_id INTEGER, created_at TEXT)",
+        "CREATE TABLE click (id INTEGER PRIMARY KEY, button INTEGER, press INTEGER, x INTEGER, y INTEGER,
+            window_id INTEGER, geometry_id INTEGER, created_at TEXT)",
+        "INSERT INTO process VALUES (1, 'vim', '2014-03-01 09:00:00.000000')",
+        "INSERT INTO window VALUES (1, 'notes.txt', 1, '2014-03-01 09:00:00.000000')",
+        "INSERT INTO keys VALUES (1, CAST('hello' AS BLOB), 5, 1, NULL, '2014-03-01 09:00:05.000000')",
+    ] {
+        sqlx::query(sql).execute(&pool).await.unwrap();
+    }
+    pool.close().await;
+    path
+}
+
+#[tokio::test]
+async fn plaintext_is_imported_once() {
+    let dir = TempDir::new("legacy-import");
+    let legacy = legacy_db(dir.path()).await;
+    let db = new_db(&dir).await;
+    
+    let imported = db.import_legacy(&legacy, LegacyText::Plain, false, &Progress::none()).await.unwrap();
+    assert_eq!((imported.key_batches, imported.texts), (1, 1));
+    let text: Vec<u8> = sqlx::query_scalar("SELECT encrypted_keys FROM keys")
+        .fetch_one(&raw_pool(&dir).await)
+        .await
+        .unwrap();
+    assert_eq!(text, b"hello");
+    
+    let again = db.import_legacy(&legacy, LegacyText::Plain, false, &Progress::none()).await;
+    assert!(matches!(again, Err(SelfspyError::AlreadyImported(..))));
+}
+
+#[tokio::test]
+async fn text_of_a_password_protected_database_stays_behind() {
+    let dir = TempDir::new("legacy-import-encrypted");
+    let legacy = legacy_db(dir.path()).await;
+    std::fs::write(dir.path().join("password.digest"), b"digest").unwrap();
+    let db = new_db(&dir).await;
+    
+    let imported = db.import_legacy(&legacy, LegacyText::Plain, false, &Progress::none()).await.unwrap();
+    assert_eq!((imported.key_batches, imported.texts), (1, 0));
+}
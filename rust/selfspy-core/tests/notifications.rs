@@ -0,0 +1,38 @@
+// Notifier: a slow sink doesn't hold up notify, and settle waits for it
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use selfspy_core::error::Result;
+use selfspy_core::notifications::NotificationSink;
+use selfspy_core::{Notification, Notifier};
+
+struct SlowSink(Arc<Mutex<Vec<String>>>);
+
+#[async_trait]
+impl NotificationSink for SlowSink {
+    async fn send(&self, notification: &Notification) -> Result<()> {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        self.0.lock().unwrap().push(notification.title.clone());
+        Ok(())
+    }
+}
+
+#[tokio::test]
+async fn slow_sinks_deliver_in_the_background() {
+    let sent = Arc::new(Mutex::new(Vec::new()));
+    let notifier = Notifier::new()
+        .with_sink(Box::new(SlowSink(sent.clone())))
+        .with_sink(Box::new(SlowSink(sent.clone())));
+    
+    let started = Instant::now();
+    notifier.notify(Notification::new("first", ""));
+    notifier.notify(Notification::new("second", ""));
+    assert!(started.elapsed() < Duration::from_millis(300));
+    assert!(sent.lock().unwrap().is_empty());
+    
+    notifier.settle().await;
+    let mut sent = sent.lock().unwrap().clone();
+    sent.sort();
+    assert_eq!(sent, ["first", "first", "second", "second"]);
+}
@@ -0,0 +1,45 @@
+// Database::find_outliers: an hour over the limit gives up only its excess,
+// so removing the outliers keeps the real input around them
+mod common;
+
+use selfspy_core::key_count::KeyCounts;
+use selfspy_core::{OutlierLimits, Progress};
+
+use common::{new_db, new_window, raw_pool, TempDir};
+
+fn keys(events: i32) -> KeyCounts {
+    KeyCounts { events, characters: events, ..KeyCounts::default() }
+}
+
+#[tokio::test]
+async fn only_the_excess_of_an_hour_is_flagged() {
+    let dir = TempDir::new("outliers");
+    let db = new_db(&dir).await;
+    let pool = raw_pool(&dir).await;
+    let window_id = new_window(&db, "Editor").await;
+    
+    let typed = db.insert_keys(window_id, Vec::new(), &keys(40)).await.unwrap();
+    let stuck = db.insert_keys(window_id, Vec::new(), &keys(10_000_000)).await.unwrap();
+    db.insert_keys(window_id, Vec::new(), &keys(60)).await.unwrap();
+    let mut clicks = Vec::new();
+    for _ in 0..4 {
+        clicks.push(db.insert_click(window_id, 0, 0, "left", false, 1.0).await.unwrap());
+    }
+    for table in ["keys", "clicks"] {
+        sqlx::query(&format!("UPDATE {} SET created_at = '2026-03-02 09:' || printf('%02d', id) || ':00'", table))
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    
+    let limits = OutlierLimits { max_keys_per_hour: 1000, max_clicks_per_hour: 2, ..OutlierLimits::default() };
+    let outliers = db.find_outliers(&limits).await.unwrap();
+    let flagged: Vec<(&str, i64)> = outliers.iter().map(|o| (o.table.as_str(), o.id)).collect();
+    assert_eq!(flagged, vec![("keys", stuck), ("clicks", clicks[2]), ("clicks", clicks[3])]);
+    
+    db.remove_outliers(&outliers, &Progress::none()).await.unwrap();
+    let kept: i64 = sqlx::query_scalar("SELECT SUM(event_count) FROM keys").fetch_one(&pool).await.unwrap();
+    assert_eq!(kept, 100);
+    assert!(sqlx::query("SELECT 1 FROM keys WHERE id = ?").bind(typed).fetch_optional(&pool).await.unwrap().is_some());
+    assert!(db.find_outliers(&limits).await.unwrap().is_empty());
+}
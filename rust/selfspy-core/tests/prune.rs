@@ -0,0 +1,34 @@
+// Database::prune_before: old events give way to daily rollups that stats
+// still count, and spans a crash left open are pruned by their start
+mod common;
+
+use chrono::NaiveDate;
+
+use common::{new_db, new_window, raw_pool, TempDir};
+
+#[tokio::test]
+async fn pruned_days_stay_in_stats_and_open_spans_go() {
+    let dir = TempDir::new("prune");
+    let db = new_db(&dir).await;
+    let pool = raw_pool(&dir).await;
+    
+    let window_id = new_window(&db, "Editor").await;
+    for _ in 0..3 {
+        db.insert_click(window_id, 0, 0, "left", false, 1.0).await.unwrap();
+    }
+    db.open_media_play("player", "Old track", None).await.unwrap();
+    sqlx::query("UPDATE clicks SET created_at = '2026-01-05 12:00:00'").execute(&pool).await.unwrap();
+    sqlx::query("UPDATE media_plays SET started_at = '2026-01-05 12:00:00'").execute(&pool).await.unwrap();
+    db.insert_click(window_id, 0, 0, "left", false, 1.0).await.unwrap();
+    
+    let cutoff = NaiveDate::from_ymd_opt(2026, 2, 1).unwrap();
+    let summary = db.prune_before(cutoff, true).await.unwrap();
+    assert_eq!(summary.days_rolled_up, 1);
+    assert_eq!(summary.rows_deleted, 4);
+    
+    let plays: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM media_plays").fetch_one(&pool).await.unwrap();
+    assert_eq!(plays, 0);
+    let clicks: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clicks").fetch_one(&pool).await.unwrap();
+    assert_eq!(clicks, 1);
+    assert_eq!(db.get_stats().await.unwrap().total_clicks, 4);
+}
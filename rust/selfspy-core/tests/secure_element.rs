@@ -0,0 +1,102 @@
+// Sealing round trips against a software TPM. Needs swtpm and tpm2-tools on
+// PATH; skipped (with a note) without them.
+#![cfg(target_os = "linux")]
+
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+
+use selfspy_core::encryption::{Encryptor, KdfSettings};
+use selfspy_core::error::SelfspyError;
+use selfspy_core::platform::secure_element;
+
+// Cheap enough for tests; the cost doesn't change the code path
+const RECOVERY_KDF: KdfSettings = KdfSettings { memory_kib: 64, iterations: 1, parallelism: 1 };
+
+struct Swtpm {
+    child: Child,
+    state: PathBuf,
+}
+
+impl Swtpm {
+    // Starts swtpm and points tpm2-tools at it, or None when either is missing
+    fn start() -> Option<Self> {
+        for tool in ["swtpm", "tpm2_createprimary"] {
+            if Command::new("which").arg(tool).stdout(Stdio::null()).status().map_or(true, |s| !s.success()) {
+                eprintln!("skipping: {} not found", tool);
+                return None;
+            }
+        }
+        
+        let port = TcpListener::bind("127.0.0.1:0").ok()?.local_addr().ok()?.port();
+        let state = std::env::temp_dir().join(format!("selfspy-swtpm-{}-{}", std::process::id(), port));
+        std::fs::create_dir_all(&state).ok()?;
+        let child = Command::new("swtpm")
+            .args(["socket", "--tpm2", "--flags", "not-need-init,startup-clear"])
+            .arg("--tpmstate")
+            .arg(format!("dir={}", state.display()))
+            .arg("--server")
+            .arg(format!("type=tcp,port={}", port))
+            .arg("--ctrl")
+            .arg(format!("type=tcp,port={}", port + 1))
+            .spawn()
+            .ok()?;
+        let swtpm = Self { child, state };
+        
+        let started = Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            if started.elapsed() > Duration::from_secs(10) {
+                panic!("swtpm didn't start listening on port {}", port);
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        std::env::set_var("TPM2TOOLS_TCTI", format!("swtpm:host=127.0.0.1,port={}", port));
+        Some(swtpm)
+    }
+}
+
+impl Drop for Swtpm {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.state);
+    }
+}
+
+// One test, as the TCTI is process-wide
+#[test]
+fn seals_with_the_tpm() {
+    let Some(_swtpm) = Swtpm::start() else {
+        return;
+    };
+    assert_eq!(secure_element::BACKEND, "tpm2");
+    
+    let key = [0x5au8; 32];
+    let sealed = secure_element::seal(&key).unwrap();
+    assert!(!sealed.windows(key.len()).any(|window| window == key));
+    assert_eq!(secure_element::unseal(&sealed).unwrap().as_slice(), key);
+    
+    let mut tampered = sealed.clone();
+    let last = tampered.len() - 1;
+    tampered[last] ^= 0xff;
+    assert!(secure_element::unseal(&tampered).is_err());
+    assert!(secure_element::unseal(&sealed[..3]).is_err());
+    
+    // A sealed key opens without a password, and through its recovery
+    // password once the TPM can no longer unseal it
+    let (encryptor, mut kdf) = Encryptor::create_sealed(Some(("recovery", RECOVERY_KDF))).unwrap();
+    let ciphertext = encryptor.encrypt(b"typed text").unwrap();
+    let reopened = Encryptor::open("", &kdf).unwrap();
+    assert_eq!(reopened.decrypt(&ciphertext).unwrap().as_slice(), b"typed text");
+    
+    kdf.sealed_key = Some(tampered);
+    assert!(Encryptor::open("", &kdf).is_err());
+    assert!(matches!(Encryptor::open("wrong", &kdf), Err(SelfspyError::WrongPassword)));
+    let recovered = Encryptor::open("recovery", &kdf).unwrap();
+    assert_eq!(recovered.decrypt(&ciphertext).unwrap().as_slice(), b"typed text");
+    
+    let (_, kdf) = Encryptor::create_sealed(None).unwrap();
+    assert!(kdf.recovery_key.is_none());
+    assert!(Encryptor::open("", &kdf).is_ok());
+}
@@ -0,0 +1,78 @@
+// slash::verify_slack / verify_teams: signatures are checked over the body
+// bytes exactly as received, and requests outside the replay window fail
+// even when correctly signed. The signatures were computed independently.
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use selfspy_core::control::Request;
+use selfspy_core::slash::{verify_slack, verify_teams};
+
+// The example from Slack's "Verifying requests from Slack" guide
+const SLACK_SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+const SLACK_TIMESTAMP: i64 = 1531420618;
+const SLACK_BODY: &str = "token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+const SLACK_SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+// base64 of "selfspy-teams-token"
+const TEAMS_TOKEN: &str = "c2VsZnNweS10ZWFtcy10b2tlbg==";
+const TEAMS_BODY: &str =
+    r#"{"type":"message","timestamp":"2026-03-02T09:00:00.2316658Z","text":"<at>Selfspy</at> today"}"#;
+const TEAMS_SIGNATURE: &str = "HMAC EqfYBYyfV0/k5SXeCkCha4+e/LiljRROMnR1Fpg+pHs=";
+
+fn request(headers: &[(&str, &str)], body: &[u8]) -> Request {
+    Request {
+        method: "POST".to_string(),
+        path: "/".to_string(),
+        has_origin: false,
+        headers: headers.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect(),
+        body: body.to_vec(),
+    }
+}
+
+fn slack(signature: &str, body: &[u8]) -> Request {
+    request(
+        &[("X-Slack-Request-Timestamp", &SLACK_TIMESTAMP.to_string()), ("X-Slack-Signature", signature)],
+        body,
+    )
+}
+
+fn slack_sent() -> DateTime<Utc> {
+    Utc.timestamp_opt(SLACK_TIMESTAMP, 0).unwrap()
+}
+
+fn teams_sent() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 3, 2, 9, 0, 0).unwrap()
+}
+
+#[test]
+fn slack_accepts_its_own_example_within_the_replay_window() {
+    let signed = slack(SLACK_SIGNATURE, SLACK_BODY.as_bytes());
+    assert!(verify_slack(&signed, SLACK_SECRET, slack_sent()));
+    assert!(verify_slack(&signed, SLACK_SECRET, slack_sent() + Duration::seconds(300)));
+    
+    assert!(!verify_slack(&signed, SLACK_SECRET, slack_sent() + Duration::seconds(301)));
+    assert!(!verify_slack(&signed, SLACK_SECRET, slack_sent() - Duration::seconds(301)));
+    assert!(!verify_slack(&signed, "another secret", slack_sent()));
+    let tampered = slack(SLACK_SIGNATURE, SLACK_BODY.replace("text=", "text=week").as_bytes());
+    assert!(!verify_slack(&tampered, SLACK_SECRET, slack_sent()));
+    assert!(!verify_slack(&request(&[], SLACK_BODY.as_bytes()), SLACK_SECRET, slack_sent()));
+}
+
+#[test]
+fn slack_signatures_cover_bytes_that_are_not_utf8() {
+    // "text=caf\xe9", Latin-1 rather than UTF-8
+    let signature = "v0=a08eb72991ce1aafedb387446698a067d47c42b8ece762dbf9fa03b3cc2c4e8e";
+    assert!(verify_slack(&slack(signature, b"text=caf\xe9"), SLACK_SECRET, slack_sent()));
+    assert!(!verify_slack(&slack(signature, b"text=caf\xef"), SLACK_SECRET, slack_sent()));
+}
+
+#[test]
+fn teams_accepts_a_signed_message_within_the_replay_window() {
+    let signed = request(&[("Authorization", TEAMS_SIGNATURE)], TEAMS_BODY.as_bytes());
+    assert!(verify_teams(&signed, TEAMS_TOKEN, teams_sent()));
+    
+    // A captured message can't be replayed later
+    assert!(!verify_teams(&signed, TEAMS_TOKEN, teams_sent() + Duration::minutes(10)));
+    assert!(!verify_teams(&signed, "YW5vdGhlciB0b2tlbg==", teams_sent()));
+    let tampered = request(&[("Authorization", TEAMS_SIGNATURE)], TEAMS_BODY.replace("today", "week").as_bytes());
+    assert!(!verify_teams(&tampered, TEAMS_TOKEN, teams_sent()));
+    assert!(!verify_teams(&request(&[], TEAMS_BODY.as_bytes()), TEAMS_TOKEN, teams_sent()));
+}
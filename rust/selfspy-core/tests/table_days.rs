@@ -0,0 +1,45 @@
+// Database::get_table_days: the checksum follows what a row holds, not only
+// how big it is, and covers tables beyond the event ones
+mod common;
+
+use selfspy_core::Database;
+
+use common::{new_db, new_window, raw_pool, TempDir};
+
+async fn checksum(db: &Database, table: &str) -> Option<u64> {
+    db.get_table_days()
+        .await
+        .unwrap()
+        .into_iter()
+        .find(|day| day.table == table)
+        .map(|day| day.checksum)
+}
+
+#[tokio::test]
+async fn same_length_key_text_changes_the_checksum() {
+    let dir = TempDir::new("table-days-keys");
+    let db = new_db(&dir).await;
+    let pool = raw_pool(&dir).await;
+    let window_id = new_window(&db, "notes").await;
+    sqlx::query("INSERT INTO keys (window_id, encrypted_keys, key_count) VALUES (?, CAST('abc' AS BLOB), 3)")
+        .bind(window_id)
+        .execute(&pool)
+        .await
+        .unwrap();
+    let before = checksum(&db, "keys").await.unwrap();
+    
+    sqlx::query("UPDATE keys SET encrypted_keys = CAST('xyz' AS BLOB)").execute(&pool).await.unwrap();
+    assert_ne!(checksum(&db, "keys").await.unwrap(), before);
+}
+
+#[tokio::test]
+async fn nullable_columns_still_count() {
+    let dir = TempDir::new("table-days-health");
+    let db = new_db(&dir).await;
+    let pool = raw_pool(&dir).await;
+    sqlx::query("INSERT INTO health_days (day, steps) VALUES ('2024-05-01', 1000)").execute(&pool).await.unwrap();
+    let before = checksum(&db, "health_days").await.unwrap();
+    
+    sqlx::query("UPDATE health_days SET steps = 2000").execute(&pool).await.unwrap();
+    assert_ne!(checksum(&db, "health_days").await.unwrap(), before);
+}
@@ -0,0 +1,170 @@
+// Database::queue / flush: rows land in queue order, a row the database
+// rejects doesn't hold up the others, and the buffer is bounded
+mod common;
+
+use chrono::{Duration, Utc};
+use selfspy_core::db::{DatabaseSettings, PendingWrite, MAX_PENDING_WRITES};
+use selfspy_core::{Database, ErrorKind};
+use sqlx::Row;
+
+use common::{new_db, new_window, raw_pool, TempDir};
+
+fn click(window_id: i64, x: i32, at: chrono::DateTime<Utc>) -> PendingWrite {
+    PendingWrite::Click {
+        window_id,
+        x,
+        y: 0,
+        button: "left".to_string(),
+        double_click: false,
+        scale: 1.0,
+        at,
+    }
+}
+
+fn moves(window_id: i64, distance: i64) -> PendingWrite {
+    PendingWrite::MouseMoves { window_id, distance, event_count: 1, at: Utc::now() }
+}
+
+#[tokio::test]
+async fn flush_writes_rows_in_queue_order() {
+    let dir = TempDir::new("order");
+    let db = new_db(&dir).await;
+    let window = new_window(&db, "one").await;
+    
+    // Stamped in reverse, so only the queue order can put them in id order
+    let now = Utc::now();
+    for x in 0..10 {
+        db.queue(click(window, x, now - Duration::seconds(x as i64)));
+        db.queue(moves(window, x as i64));
+    }
+    assert_eq!(db.pending_writes(), 20);
+    assert_eq!(db.flush().await.unwrap(), 20);
+    assert_eq!(db.pending_writes(), 0);
+    assert_eq!(db.flush().await.unwrap(), 0);
+    
+    let pool = raw_pool(&dir).await;
+    let xs: Vec<i32> = sqlx::query("SELECT x FROM clicks ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get("x"))
+        .collect();
+    assert_eq!(xs, (0..10).collect::<Vec<_>>());
+    let distances: Vec<i64> = sqlx::query("SELECT distance FROM mouse_moves ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get("distance"))
+        .collect();
+    assert_eq!(distances, (0..10).collect::<Vec<_>>());
+}
+
+#[tokio::test]
+async fn flush_drops_rows_that_break_foreign_keys() {
+    let dir = TempDir::new("foreign-keys");
+    let db = new_db(&dir).await;
+    let window = new_window(&db, "one").await;
+    let missing = window + 1000;
+    
+    db.queue(moves(window, 1));
+    db.queue(moves(missing, 2));
+    db.queue(click(missing, 3, Utc::now()));
+    db.queue(moves(window, 4));
+    assert_eq!(db.flush().await.unwrap(), 2);
+    assert_eq!(db.pending_writes(), 0);
+    
+    let pool = raw_pool(&dir).await;
+    let distances: Vec<i64> = sqlx::query("SELECT distance FROM mouse_moves ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get("distance"))
+        .collect();
+    assert_eq!(distances, vec![1, 4]);
+    let clicks: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM clicks").fetch_one(&pool).await.unwrap();
+    assert_eq!(clicks, 0);
+    assert!(db.foreign_key_violations().await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn flush_keeps_rows_while_the_database_is_locked() {
+    let dir = TempDir::new("locked");
+    let settings = DatabaseSettings { busy_timeout_ms: 50, ..DatabaseSettings::default() };
+    let db = Database::connect(&dir.db_path(), &settings).await.unwrap();
+    let window = new_window(&db, "one").await;
+    
+    let pool = raw_pool(&dir).await;
+    let mut writer = pool.acquire().await.unwrap();
+    sqlx::query("BEGIN IMMEDIATE").execute(&mut *writer).await.unwrap();
+    
+    db.queue(moves(window, 1));
+    db.queue(moves(window, 2));
+    let err = db.flush().await.unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DatabaseLocked);
+    assert_eq!(db.pending_writes(), 2);
+    
+    // Rows queued meanwhile go after the ones put back
+    db.queue(moves(window, 3));
+    sqlx::query("COMMIT").execute(&mut *writer).await.unwrap();
+    assert_eq!(db.flush().await.unwrap(), 3);
+    
+    let distances: Vec<i64> = sqlx::query("SELECT distance FROM mouse_moves ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap()
+        .iter()
+        .map(|row| row.get("distance"))
+        .collect();
+    assert_eq!(distances, vec![1, 2, 3]);
+}
+
+// Rows held back by a locked database are stamped when they were queued,
+// not when the database came back
+#[tokio::test]
+async fn held_rows_keep_the_time_they_were_queued() {
+    let dir = TempDir::new("stamped");
+    let settings = DatabaseSettings { busy_timeout_ms: 50, ..DatabaseSettings::default() };
+    let db = Database::connect(&dir.db_path(), &settings).await.unwrap();
+    let window = new_window(&db, "one").await;
+    
+    let pool = raw_pool(&dir).await;
+    let mut writer = pool.acquire().await.unwrap();
+    sqlx::query("BEGIN IMMEDIATE").execute(&mut *writer).await.unwrap();
+    
+    let queued = Utc::now() - Duration::hours(1);
+    db.queue(PendingWrite::MouseMoves { window_id: window, distance: 1, event_count: 1, at: queued });
+    db.queue(PendingWrite::Scrolls { window_id: window, event_count: 1, distance_x: 0.0, distance_y: 1.0, at: queued });
+    assert!(db.flush().await.is_err());
+    sqlx::query("COMMIT").execute(&mut *writer).await.unwrap();
+    assert_eq!(db.flush().await.unwrap(), 2);
+    
+    let expected = queued.format("%Y-%m-%d %H:%M:%S").to_string();
+    for table in ["mouse_moves", "scrolls"] {
+        let at: String = sqlx::query_scalar(&format!("SELECT created_at FROM {}", table))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(at, expected, "{}", table);
+    }
+}
+
+#[tokio::test]
+async fn a_full_buffer_drops_the_oldest_rows() {
+    let dir = TempDir::new("bounded");
+    let db = new_db(&dir).await;
+    let window = new_window(&db, "one").await;
+    
+    for distance in 0..MAX_PENDING_WRITES as i64 + 3 {
+        db.queue(moves(window, distance));
+    }
+    assert_eq!(db.pending_writes(), MAX_PENDING_WRITES);
+    assert_eq!(db.dropped_writes(), 3);
+    assert_eq!(db.flush().await.unwrap(), MAX_PENDING_WRITES);
+    
+    let pool = raw_pool(&dir).await;
+    let first: i64 = sqlx::query_scalar("SELECT MIN(distance) FROM mouse_moves").fetch_one(&pool).await.unwrap();
+    assert_eq!(first, 3);
+}
@@ -1,10 +1,17 @@
 use eframe::egui;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
 use tokio::sync::RwLock;
-use selfspy_core::{ActivityMonitor, Config, Database};
+use selfspy_core::{ActivityMonitor, Config, DailySummary, Database, StatsCache};
 use crate::{dashboard::Dashboard, settings::Settings, statistics::Statistics, charts::Charts};
+use crate::command_palette::{CommandPalette, PaletteAction};
+use crate::pinned::PinAction;
+use crate::time_range::TimeRange;
+use crate::system_tray::{SystemTray, TrayEvent};
 
-#[derive(PartialEq)]
+#[derive(PartialEq, Clone, Copy)]
 pub enum AppTab {
     Dashboard,
     Statistics,
@@ -21,19 +28,47 @@ pub struct SelfspyApp {
     
     // UI state
     pub current_tab: AppTab,
+    pub time_range: TimeRange,
     pub dashboard: Dashboard,
     pub statistics: Statistics,
     pub charts: Charts,
     pub settings: Settings,
+    pub command_palette: CommandPalette,
     
     // UI state
     pub status_message: String,
     pub last_update: std::time::Instant,
+    
+    // System tray
+    pub tray: SystemTray,
+    tray_events: Receiver<TrayEvent>,
+    tray_stats: Arc<Mutex<Option<DailySummary>>>,
+    pub window_visible: bool,
+    quit_requested: bool,
 }
 
 impl SelfspyApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>) -> Self {
-        let config = Config::new();
+    pub fn new(cc: &eframe::CreationContext<'_>, config: Config, start_hidden: bool) -> Self {
+        let (tray_sender, tray_events) = mpsc::channel();
+        let tray = SystemTray::new(tray_sender, cc.egui_ctx.clone());
+        
+        // Without a tray icon a hidden window could never be reopened
+        let start_hidden = if start_hidden && !tray.is_available() {
+            tracing::warn!("No system tray available; starting with the window visible");
+            cc.egui_ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+            false
+        } else {
+            start_hidden
+        };
+        
+        let tray_stats = Arc::new(Mutex::new(None));
+        if tray.is_available() {
+            tokio::spawn(refresh_tray_stats(
+                config.database_path.clone(),
+                tray_stats.clone(),
+                cc.egui_ctx.clone(),
+            ));
+        }
         
         Self {
             config: config.clone(),
@@ -41,12 +76,97 @@ impl SelfspyApp {
             monitor: None,
             monitoring_active: Arc::new(RwLock::new(false)),
             current_tab: AppTab::Dashboard,
-            dashboard: Dashboard::new(),
-            statistics: Statistics::new(),
-            charts: Charts::new(),
+            time_range: TimeRange::Today,
+            dashboard: Dashboard::new(config.database_path.clone()),
+            statistics: Statistics::new(config.database_path.clone()),
+            charts: Charts::new(
+                config.database_path.clone(),
+                config.first_day_of_week.weekday(),
+                config.working_hours.usual_hours(),
+            ),
+            command_palette: CommandPalette::new(config.database_path.clone()),
             settings: Settings::new(config),
             status_message: "Ready".to_string(),
             last_update: std::time::Instant::now(),
+            tray,
+            tray_events,
+            tray_stats,
+            window_visible: !start_hidden,
+            quit_requested: false,
+        }
+    }
+    
+    pub fn show_window(&mut self, ctx: &egui::Context) {
+        self.window_visible = true;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Visible(true));
+        ctx.send_viewport_cmd(egui::ViewportCommand::Focus);
+    }
+    
+    pub fn hide_window(&mut self, ctx: &egui::Context) {
+        self.window_visible = false;
+        ctx.send_viewport_cmd(egui::ViewportCommand::Visible(false));
+    }
+    
+    fn handle_tray_events(&mut self, ctx: &egui::Context) {
+        while let Ok(event) = self.tray_events.try_recv() {
+            match event {
+                TrayEvent::Show => self.show_window(ctx),
+                TrayEvent::Hide => self.hide_window(ctx),
+                TrayEvent::ToggleMonitoring => {
+                    if self.is_monitoring_active() {
+                        self.stop_monitoring();
+                    } else {
+                        self.start_monitoring();
+                    }
+                    self.tray.update_monitoring_status(self.is_monitoring_active());
+                }
+                TrayEvent::ShowSettings => {
+                    self.current_tab = AppTab::Settings;
+                    self.show_window(ctx);
+                }
+                TrayEvent::Quit => {
+                    self.quit_requested = true;
+                    ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+                }
+            }
+        }
+    }
+    
+    fn apply_palette_action(&mut self, action: PaletteAction) {
+        match action {
+            PaletteAction::OpenTab(tab) => self.current_tab = tab,
+            PaletteAction::SetTimeRange(range) => self.time_range = range,
+            PaletteAction::Export => self.settings.export_data(),
+            PaletteAction::ToggleMonitoring => {
+                if self.is_monitoring_active() {
+                    self.stop_monitoring();
+                } else {
+                    self.start_monitoring();
+                }
+                self.tray.update_monitoring_status(self.is_monitoring_active());
+            }
+        }
+    }
+    
+    fn apply_pin_action(&mut self, action: PinAction) {
+        match action {
+            PinAction::Pin(report) => {
+                if !self.config.pinned_reports.contains(&report) {
+                    self.status_message = format!("Pinned \"{}\" to the dashboard", report.title);
+                    self.config.pinned_reports.push(report);
+                }
+            }
+            PinAction::Unpin(index) if index < self.config.pinned_reports.len() => {
+                let report = self.config.pinned_reports.remove(index);
+                self.status_message = format!("Unpinned \"{}\"", report.title);
+            }
+            PinAction::Unpin(_) => return,
+        }
+        
+        self.settings.sync_pinned_reports(&self.config.pinned_reports);
+        if let Err(e) = self.config.save() {
+            tracing::warn!("Failed to save pinned reports: {}", e);
+            self.status_message = format!("Failed to save pinned reports: {}", e);
         }
     }
     
@@ -79,12 +199,34 @@ impl SelfspyApp {
 
 impl eframe::App for SelfspyApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.handle_tray_events(ctx);
+        
+        // Closing the window keeps Selfspy running in the tray
+        if ctx.input(|i| i.viewport().close_requested()) && !self.quit_requested && self.tray.is_available() {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.hide_window(ctx);
+        }
+        
         // Update data periodically
         if self.last_update.elapsed().as_secs() >= 1 {
             self.refresh_data();
             self.last_update = std::time::Instant::now();
         }
         
+        // Tray-only: nothing to draw until the window is requested
+        if !self.window_visible {
+            ctx.request_repaint_after(std::time::Duration::from_secs(1));
+            return;
+        }
+        
+        // Ctrl+K (Cmd+K on macOS) toggles the command palette
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, egui::Key::K)) {
+            self.command_palette.toggle();
+        }
+        if let Some(action) = self.command_palette.show(ctx, self.is_monitoring_active()) {
+            self.apply_palette_action(action);
+        }
+        
         // Top panel with navigation
         egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
@@ -96,6 +238,17 @@ impl eframe::App for SelfspyApp {
                 ui.selectable_value(&mut self.current_tab, AppTab::Charts, "📉 Charts");
                 ui.selectable_value(&mut self.current_tab, AppTab::Settings, "⚙️ Settings");
                 
+                ui.separator();
+                
+                // One range for every view
+                egui::ComboBox::from_id_source("time_range")
+                    .selected_text(format!("🕐 {}", self.time_range.label()))
+                    .show_ui(ui, |ui| {
+                        for range in TimeRange::ALL {
+                            ui.selectable_value(&mut self.time_range, range, range.label());
+                        }
+                    });
+                
                 ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                     // Monitoring toggle
                     let monitoring = self.is_monitoring_active();
@@ -140,22 +293,35 @@ impl eframe::App for SelfspyApp {
         egui::CentralPanel::default().show(ctx, |ui| {
             let monitoring = self.is_monitoring_active();
             let database_connected = self.database.is_some();
+            let range = self.time_range;
+            let mut pin_action = None;
             
             match self.current_tab {
                 AppTab::Dashboard => {
-                    self.dashboard.show(ui, monitoring, database_connected);
+                    pin_action = self.dashboard.show(
+                        ui,
+                        monitoring,
+                        database_connected,
+                        range,
+                        &self.config.pinned_reports,
+                        &self.config.custom_metrics,
+                    );
                 },
                 AppTab::Statistics => {
-                    self.statistics.show(ui, database_connected);
+                    self.statistics.show(ui, database_connected, range);
                 },
                 AppTab::Charts => {
-                    self.charts.show(ui, database_connected);
+                    pin_action = self.charts.show(ui, database_connected, range, &self.config.custom_metrics);
                 },
                 AppTab::Settings => {
                     let config = self.config.clone();
                     self.settings.show(ui, config, database_connected);
                 },
             }
+            
+            if let Some(action) = pin_action {
+                self.apply_pin_action(action);
+            }
         });
         
         // Request repaint for live updates
@@ -165,7 +331,47 @@ impl eframe::App for SelfspyApp {
 
 impl SelfspyApp {
     fn refresh_data(&mut self) {
+        if let Some(summary) = self.tray_stats.lock().unwrap().take() {
+            self.tray.update_stats(&summary);
+        }
+        
         // For demo purposes, just update the last refresh time
         self.last_update = std::time::Instant::now();
     }
+}
+
+// Pulls today's totals once a minute for the tray badge. On first launch the
+// database may not exist yet, so opening it is retried every minute too
+async fn refresh_tray_stats(
+    database_path: PathBuf,
+    latest: Arc<Mutex<Option<DailySummary>>>,
+    ctx: egui::Context,
+) {
+    let mut cache = None;
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    
+    loop {
+        interval.tick().await;
+        
+        if cache.is_none() {
+            match Database::open_read_only(&database_path).await {
+                Ok(db) => cache = Some(StatsCache::new(Arc::new(db), Duration::from_secs(60))),
+                Err(e) => {
+                    tracing::debug!("Tray statistics unavailable yet: {}", e);
+                    continue;
+                }
+            }
+        }
+        let Some(cache) = &cache else {
+            continue;
+        };
+        
+        match cache.today().await {
+            Ok(summary) => {
+                *latest.lock().unwrap() = Some(summary);
+                ctx.request_repaint();
+            }
+            Err(e) => tracing::warn!("Failed to refresh tray statistics: {}", e),
+        }
+    }
 }
\ No newline at end of file
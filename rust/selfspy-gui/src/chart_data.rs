@@ -0,0 +1,78 @@
+use chrono::{Datelike, Timelike, Weekday};
+use selfspy_core::distractions::visit_seconds;
+use selfspy_core::WindowVisit;
+use std::collections::HashMap;
+
+// Drill-down state for the App Usage and Hourly Patterns charts. Each field
+// that is set narrows every view, so clicking a bar and then an hour shows
+// one app's windows in that hour.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChartQuery {
+    pub app: Option<String>,
+    pub hour: Option<u32>,
+}
+
+impl ChartQuery {
+    pub fn matches(&self, visit: &WindowVisit) -> bool {
+        self.app.as_ref().map_or(true, |app| &visit.process == app)
+            && self.hour.map_or(true, |hour| visit.at.hour() == hour)
+    }
+}
+
+// Selected visits with the seconds attributed to each
+pub fn timeline<'a>(visits: &'a [WindowVisit], query: &ChartQuery) -> Vec<(&'a WindowVisit, i64)> {
+    visits
+        .iter()
+        .enumerate()
+        .filter(|(_, visit)| query.matches(visit))
+        .map(|(index, visit)| (visit, visit_seconds(visits, index)))
+        .collect()
+}
+
+// Seconds per app, longest first
+pub fn app_usage(visits: &[WindowVisit], query: &ChartQuery) -> Vec<(String, i64)> {
+    ranked(timeline(visits, query).into_iter().map(|(visit, seconds)| (&visit.process, seconds)))
+}
+
+// Seconds per window title, longest first
+pub fn title_usage(visits: &[WindowVisit], query: &ChartQuery) -> Vec<(String, i64)> {
+    ranked(timeline(visits, query).into_iter().map(|(visit, seconds)| (&visit.title, seconds)))
+}
+
+// Seconds per local hour of the day
+pub fn hourly_usage(visits: &[WindowVisit], query: &ChartQuery) -> [i64; 24] {
+    let mut hours = [0; 24];
+    for (visit, seconds) in timeline(visits, query) {
+        hours[visit.at.hour() as usize] += seconds;
+    }
+    hours
+}
+
+// Seconds per local hour for each day of the week, rows starting with `first_day`
+pub fn weekly_usage(visits: &[WindowVisit], query: &ChartQuery, first_day: Weekday) -> [[i64; 24]; 7] {
+    let mut days = [[0; 24]; 7];
+    for (visit, seconds) in timeline(visits, query) {
+        let row = visit.at.weekday().days_since(first_day) as usize;
+        days[row][visit.at.hour() as usize] += seconds;
+    }
+    days
+}
+
+fn ranked<'a>(entries: impl Iterator<Item = (&'a String, i64)>) -> Vec<(String, i64)> {
+    let mut totals: HashMap<&String, i64> = HashMap::new();
+    for (key, seconds) in entries {
+        *totals.entry(key).or_default() += seconds;
+    }
+    
+    let mut ranked: Vec<(String, i64)> = totals
+        .into_iter()
+        .filter(|(_, seconds)| *seconds > 0)
+        .map(|(key, seconds)| (key.clone(), seconds))
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked
+}
+
+pub fn format_seconds(seconds: i64) -> String {
+    format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+}
\ No newline at end of file
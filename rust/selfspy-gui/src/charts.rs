@@ -1,31 +1,97 @@
+use chrono::{Duration, Local, NaiveDate, Utc, Weekday};
 use eframe::egui;
-use egui_plot::{Line, Plot, PlotPoints, Bar, BarChart, Legend};
+use egui_plot::{Line, Plot, PlotPoint, PlotPoints, PlotResponse, Bar, BarChart, Legend};
+use selfspy_core::calendar;
+use selfspy_core::custom_metrics::{self, CustomMetric, CustomMetricValue, MetricFormat};
+use selfspy_core::{
+    ActivityBucket, BucketSize, DailySummary, Database, DeepWorkDay, PinnedMetric, PinnedReport, WindowVisit,
+};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use crate::chart_data::{self, format_seconds, ChartQuery};
+use crate::pinned::PinAction;
+use crate::time_range::TimeRange;
+
+// Plot width used for the open-ended "All Time" range
+const ALL_TIME_DAYS: usize = 3650;
+const TOP_APPS: usize = 10;
+const TOP_TITLES: usize = 15;
+const TIMELINE_LIMIT: usize = 500;
 
 #[derive(PartialEq)]
 enum ChartType {
     ActivityOverTime,
     ApplicationUsage,
-    ProductivityTrends,
+    FocusTrends,
     HourlyPatterns,
+    DeepWork,
+    CustomMetrics,
 }
 
 pub struct Charts {
     selected_chart: ChartType,
-    time_range: usize, // Days
+    time_range: usize, // Days, from the shared range
     last_refresh: std::time::Instant,
+    database_path: PathBuf,
+    // Deep-work totals per day, loaded in the background for `deep_work_range` days
+    deep_work: Arc<Mutex<Vec<DeepWorkDay>>>,
+    deep_work_range: Option<usize>,
+    // Window visits for `visits_range` days, behind App Usage and Hourly Patterns
+    visits: Arc<Mutex<Vec<WindowVisit>>>,
+    visits_range: Option<usize>,
+    // Input per hour, day or week for `activity_range` days, behind Activity Over Time
+    activity: Arc<Mutex<Vec<ActivityBucket>>>,
+    activity_range: Option<usize>,
+    // Daily active time and deep work for `trends_range` days, behind Focus Trends
+    trends: Arc<Mutex<(Vec<DailySummary>, Vec<DeepWorkDay>)>>,
+    trends_range: Option<usize>,
+    // The configured custom metrics per active day, loaded for `metrics_range`
+    // days and the definitions in `loaded_metrics`
+    metric_days: Arc<Mutex<Vec<(NaiveDate, Vec<CustomMetricValue>)>>>,
+    metrics_range: Option<usize>,
+    loaded_metrics: Vec<CustomMetric>,
+    query: ChartQuery,
+    // Top row of the weekly heatmap
+    first_day: Weekday,
+    // Hours shaded as working time in the hourly chart, from the config
+    work_hours: [bool; 24],
 }
 
 impl Charts {
-    pub fn new() -> Self {
+    pub fn new(database_path: PathBuf, first_day: Weekday, work_hours: [bool; 24]) -> Self {
         Self {
             selected_chart: ChartType::ActivityOverTime,
             time_range: 7,
             last_refresh: std::time::Instant::now(),
+            database_path,
+            deep_work: Arc::new(Mutex::new(Vec::new())),
+            deep_work_range: None,
+            visits: Arc::new(Mutex::new(Vec::new())),
+            visits_range: None,
+            activity: Arc::new(Mutex::new(Vec::new())),
+            activity_range: None,
+            trends: Arc::new(Mutex::new((Vec::new(), Vec::new()))),
+            trends_range: None,
+            metric_days: Arc::new(Mutex::new(Vec::new())),
+            metrics_range: None,
+            loaded_metrics: Vec::new(),
+            query: ChartQuery::default(),
+            first_day,
+            work_hours,
         }
     }
     
-    pub fn show(&mut self, ui: &mut egui::Ui, database_connected: bool) {
-        ui.heading("📉 Activity Charts");
+    // Returns a pin request when the user pins the current chart to the dashboard
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        database_connected: bool,
+        range: TimeRange,
+        custom_metrics: &[CustomMetric],
+    ) -> Option<PinAction> {
+        self.time_range = range.days().map_or(ALL_TIME_DAYS, |days| days as usize);
+        
+        ui.heading(format!("📉 Activity Charts · {}", range.label()));
         ui.separator();
         
         // Chart selection and controls
@@ -33,27 +99,32 @@ impl Charts {
             ui.label("Chart Type:");
             ui.selectable_value(&mut self.selected_chart, ChartType::ActivityOverTime, "📈 Activity Over Time");
             ui.selectable_value(&mut self.selected_chart, ChartType::ApplicationUsage, "📱 App Usage");
-            ui.selectable_value(&mut self.selected_chart, ChartType::ProductivityTrends, "🎯 Productivity");
+            ui.selectable_value(&mut self.selected_chart, ChartType::FocusTrends, "🎯 Focus");
             ui.selectable_value(&mut self.selected_chart, ChartType::HourlyPatterns, "⏰ Hourly Patterns");
-            
-            ui.separator();
-            
-            ui.label("Time Range:");
-            ui.selectable_value(&mut self.time_range, 1, "1 Day");
-            ui.selectable_value(&mut self.time_range, 7, "1 Week");
-            ui.selectable_value(&mut self.time_range, 30, "1 Month");
-            ui.selectable_value(&mut self.time_range, 365, "1 Year");
+            ui.selectable_value(&mut self.selected_chart, ChartType::DeepWork, "🧠 Deep Work");
+            ui.selectable_value(&mut self.selected_chart, ChartType::CustomMetrics, "🧮 Custom Metrics");
         });
         
+        let mut action = None;
+        if let Some(report) = self.pinnable_report(range) {
+            if ui.button("📌 Pin to Dashboard").on_hover_text(&report.title).clicked() {
+                action = Some(PinAction::Pin(report));
+            }
+        }
+        
         ui.add_space(10.0);
         
-        // Main chart area
-        if database_connected {
+        // Main chart area; deep work and the drill-down charts read the database on their own
+        if self.selected_chart == ChartType::DeepWork {
+            self.show_deep_work_chart(ui);
+        } else if matches!(self.selected_chart, ChartType::ApplicationUsage | ChartType::HourlyPatterns) {
+            self.show_drill_down_chart(ui);
+        } else if database_connected {
             match self.selected_chart {
                 ChartType::ActivityOverTime => self.show_activity_over_time_chart(ui),
-                ChartType::ApplicationUsage => self.show_application_usage_chart(ui),
-                ChartType::ProductivityTrends => self.show_productivity_trends_chart(ui),
-                ChartType::HourlyPatterns => self.show_hourly_patterns_chart(ui),
+                ChartType::FocusTrends => self.show_focus_trends_chart(ui),
+                ChartType::CustomMetrics => self.show_custom_metrics_chart(ui, custom_metrics),
+                _ => {}
             }
         } else {
             ui.centered_and_justified(|ui| {
@@ -61,9 +132,84 @@ impl Charts {
                 ui.label("Connect to database to view charts");
             });
         }
+        action
     }
     
-    fn show_activity_over_time_chart(&self, ui: &mut egui::Ui) {
+    // The dashboard card matching what the chart shows, for the charts backed by real data
+    fn pinnable_report(&self, range: TimeRange) -> Option<PinnedReport> {
+        let (metric, title) = match self.selected_chart {
+            ChartType::DeepWork => (PinnedMetric::DeepWork, "Deep Work".to_string()),
+            ChartType::ApplicationUsage | ChartType::HourlyPatterns => {
+                let mut title = self.query.app.clone().unwrap_or_else(|| "All apps".to_string());
+                if let Some(hour) = self.query.hour {
+                    title.push_str(&format!(" · {:02}:00", hour));
+                }
+                (PinnedMetric::AppTime, title)
+            }
+            _ => return None,
+        };
+        let (app, hour) = if metric == PinnedMetric::AppTime {
+            (self.query.app.clone(), self.query.hour)
+        } else {
+            (None, None)
+        };
+        
+        Some(PinnedReport {
+            title,
+            metric,
+            days: range.days(),
+            app,
+            hour,
+            custom_metric: None,
+        })
+    }
+    
+    fn show_drill_down_chart(&mut self, ui: &mut egui::Ui) {
+        if self.visits_range != Some(self.time_range) {
+            self.visits_range = Some(self.time_range);
+            self.load_visits(ui.ctx().clone());
+        }
+        
+        self.show_breadcrumbs(ui);
+        ui.add_space(5.0);
+        
+        let app_usage = self.selected_chart == ChartType::ApplicationUsage;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            match self.query.app.clone() {
+                Some(app) if app_usage => self.show_app_drill_down(ui, &app),
+                None if app_usage => self.show_application_usage_chart(ui),
+                _ => self.show_hourly_patterns_chart(ui),
+            }
+            
+            if self.query.hour.is_some() {
+                ui.add_space(10.0);
+                self.show_timeline(ui);
+            }
+        });
+    }
+    
+    fn show_activity_over_time_chart(&mut self, ui: &mut egui::Ui) {
+        if self.activity_range != Some(self.time_range) {
+            self.activity_range = Some(self.time_range);
+            self.load_activity(ui.ctx().clone());
+        }
+        // One point per bucket, oldest first
+        let (keystrokes, clicks, active_hours) = {
+            let buckets = self.activity.lock().unwrap();
+            let series = |value: fn(&ActivityBucket) -> f64| -> PlotPoints {
+                buckets
+                    .iter()
+                    .enumerate()
+                    .map(|(i, bucket)| [i as f64, value(bucket)])
+                    .collect()
+            };
+            (
+                series(|bucket| bucket.keystrokes as f64),
+                series(|bucket| bucket.clicks as f64),
+                series(|bucket| bucket.active_seconds as f64 / 3600.0),
+            )
+        };
+        
         ui.group(|ui| {
             ui.heading("📈 Activity Over Time");
             ui.separator();
@@ -72,45 +218,20 @@ impl Charts {
                 .legend(Legend::default())
                 .height(400.0)
                 .show(ui, |plot_ui| {
-                    // Generate sample data
-                    let keystrokes_data: PlotPoints = (0..self.time_range)
-                        .map(|i| {
-                            let x = i as f64;
-                            let y = 1000.0 + 500.0 * (x * 0.1).sin() + 200.0 * (x * 0.3).cos();
-                            [x, y]
-                        })
-                        .collect();
-                    
-                    let clicks_data: PlotPoints = (0..self.time_range)
-                        .map(|i| {
-                            let x = i as f64;
-                            let y = 200.0 + 100.0 * (x * 0.15).sin() + 50.0 * (x * 0.25).cos();
-                            [x, y]
-                        })
-                        .collect();
-                    
-                    let active_time_data: PlotPoints = (0..self.time_range)
-                        .map(|i| {
-                            let x = i as f64;
-                            let y = 6.0 + 2.0 * (x * 0.2).sin() + (x * 0.1).cos();
-                            [x, y]
-                        })
-                        .collect();
-                    
                     plot_ui.line(
-                        Line::new(keystrokes_data)
+                        Line::new(keystrokes)
                             .color(egui::Color32::from_rgb(100, 150, 255))
                             .name("Keystrokes")
                     );
                     
                     plot_ui.line(
-                        Line::new(clicks_data)
+                        Line::new(clicks)
                             .color(egui::Color32::from_rgb(255, 150, 100))
                             .name("Mouse Clicks")
                     );
                     
                     plot_ui.line(
-                        Line::new(active_time_data)
+                        Line::new(active_hours)
                             .color(egui::Color32::from_rgb(150, 255, 100))
                             .name("Active Hours")
                     );
@@ -118,185 +239,485 @@ impl Charts {
         });
     }
     
-    fn show_application_usage_chart(&self, ui: &mut egui::Ui) {
+    fn load_activity(&self, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.activity.clone();
+        // Hours for a day or two, weeks beyond a quarter, days in between
+        let bucket = match self.time_range {
+            0..=2 => BucketSize::Hour,
+            3..=90 => BucketSize::Day,
+            _ => BucketSize::Week(self.first_day),
+        };
+        let now = Utc::now();
+        let start = (Local::now().date_naive() - Duration::days(self.time_range as i64 - 1))
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+            .map_or(now, |midnight| midnight.with_timezone(&Utc));
+        
+        tokio::spawn(async move {
+            let buckets = match Database::open_snapshot(&database_path).await {
+                Ok(db) => db.get_activity_buckets(start..now, bucket).await,
+                Err(e) => Err(e),
+            };
+            
+            match buckets {
+                Ok(buckets) => {
+                    *target.lock().unwrap() = buckets;
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to load activity over time: {}", e),
+            }
+        });
+    }
+    
+    fn show_deep_work_chart(&mut self, ui: &mut egui::Ui) {
+        if self.deep_work_range != Some(self.time_range) {
+            self.deep_work_range = Some(self.time_range);
+            self.load_deep_work(ui.ctx().clone());
+        }
+        
         ui.group(|ui| {
-            ui.heading("📱 Application Usage");
+            ui.heading("🧠 Deep Work");
             ui.separator();
             
-            Plot::new("app_usage")
+            let days = self.deep_work.lock().unwrap();
+            if days.is_empty() {
+                ui.label("No deep work detected in this range. Run `selfstats deep-work detect` to analyze recent days.");
+                return;
+            }
+            
+            let total: i64 = days.iter().map(|day| day.minutes).sum();
+            let best = days.iter().max_by_key(|day| day.minutes);
+            ui.horizontal(|ui| {
+                ui.label(format!("Total: {}h {:02}m", total / 60, total % 60));
+                if let Some(best) = best {
+                    ui.separator();
+                    ui.label(format!("Best day: {} ({} min)", best.day.format("%a %b %d"), best.minutes));
+                }
+            });
+            
+            let first_day = Local::now().date_naive() - Duration::days(self.time_range as i64 - 1);
+            let bars: Vec<Bar> = days
+                .iter()
+                .map(|day| {
+                    let x = (day.day - first_day).num_days() as f64;
+                    Bar::new(x, day.minutes as f64)
+                        .name(day.day.format("%Y-%m-%d").to_string())
+                        .fill(egui::Color32::from_rgb(130, 110, 230))
+                })
+                .collect();
+            
+            Plot::new("deep_work")
+                .legend(Legend::default())
                 .height(400.0)
                 .show(ui, |plot_ui| {
-                    let apps = vec![
-                        ("VS Code", 4.5),
-                        ("Chrome", 3.2),
-                        ("Terminal", 2.8),
-                        ("Slack", 1.5),
-                        ("Spotify", 1.0),
-                        ("Discord", 0.8),
-                        ("Notes", 0.5),
-                    ];
-                    
-                    let bars: Vec<Bar> = apps
-                        .into_iter()
-                        .enumerate()
-                        .map(|(i, (name, hours))| {
-                            Bar::new(i as f64, hours)
-                                .name(name)
-                                .fill(self.get_app_color(i))
-                        })
-                        .collect();
-                    
-                    plot_ui.bar_chart(BarChart::new(bars).name("Hours Used"));
+                    plot_ui.bar_chart(BarChart::new(bars).name("Deep Work (min)"));
                 });
         });
     }
     
-    fn show_productivity_trends_chart(&self, ui: &mut egui::Ui) {
+    // Fetch deep-work days for the selected range without blocking the UI thread
+    fn load_deep_work(&self, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.deep_work.clone();
+        let end = Local::now().date_naive() + Duration::days(1);
+        let start = end - Duration::days(self.time_range as i64);
+        
+        tokio::spawn(async move {
+            let days = match Database::open_snapshot(&database_path).await {
+                Ok(db) => db.get_deep_work_days(start, end).await,
+                Err(e) => Err(e),
+            };
+            
+            match days {
+                Ok(days) => {
+                    *target.lock().unwrap() = days;
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to load deep-work days: {}", e),
+            }
+        });
+    }
+    
+    fn show_application_usage_chart(&mut self, ui: &mut egui::Ui) {
+        let apps: Vec<(String, i64)> = chart_data::app_usage(&self.visits.lock().unwrap(), &self.query)
+            .into_iter()
+            .take(TOP_APPS)
+            .collect();
+        
         ui.group(|ui| {
-            ui.heading("🎯 Productivity Trends");
+            ui.heading("📱 Application Usage");
             ui.separator();
             
-            Plot::new("productivity_trends")
+            if apps.is_empty() {
+                ui.label("No window activity recorded in this range.");
+                return;
+            }
+            ui.small("Click a bar to see that app's windows.");
+            
+            let bars: Vec<Bar> = apps
+                .iter()
+                .enumerate()
+                .map(|(i, (name, seconds))| {
+                    Bar::new(i as f64, *seconds as f64 / 3600.0)
+                        .name(name)
+                        .fill(self.get_app_color(i))
+                })
+                .collect();
+            
+            let clicked = clicked_bar(
+                Plot::new("app_usage")
+                    .height(400.0)
+                    .show(ui, |plot_ui| {
+                        plot_ui.bar_chart(BarChart::new(bars).name("Hours Used"));
+                        plot_ui.pointer_coordinate()
+                    }),
+                apps.len(),
+            );
+            if let Some(index) = clicked {
+                self.query.app = Some(apps[index].0.clone());
+            }
+        });
+    }
+    
+    // Per-app drill-down: where the time went by window title and by hour
+    fn show_app_drill_down(&mut self, ui: &mut egui::Ui, app: &str) {
+        let (titles, hours) = {
+            let visits = self.visits.lock().unwrap();
+            (chart_data::title_usage(&visits, &self.query), self.hourly_usage(&visits))
+        };
+        let total: i64 = titles.iter().map(|(_, seconds)| seconds).sum();
+        
+        ui.group(|ui| {
+            ui.heading(format!("📱 {}", app));
+            ui.label(format!("Total: {}", format_seconds(total)));
+            ui.separator();
+            
+            egui::Grid::new("app_titles")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Window");
+                    ui.strong("Time");
+                    ui.end_row();
+                    for (title, seconds) in titles.iter().take(TOP_TITLES) {
+                        ui.label(title);
+                        ui.label(format_seconds(*seconds));
+                        ui.end_row();
+                    }
+                });
+        });
+        
+        ui.add_space(10.0);
+        self.show_hourly_bars(ui, hours);
+    }
+    
+    // Active hours per day, and how much of them was deep work
+    fn show_focus_trends_chart(&mut self, ui: &mut egui::Ui) {
+        if self.trends_range != Some(self.time_range) {
+            self.trends_range = Some(self.time_range);
+            self.load_trends(ui.ctx().clone());
+        }
+        let first_day = Local::now().date_naive() - Duration::days(self.time_range as i64 - 1);
+        let (active, deep_work): (PlotPoints, PlotPoints) = {
+            let trends = self.trends.lock().unwrap();
+            let day_x = |day: chrono::NaiveDate| (day - first_day).num_days() as f64;
+            (
+                trends.0.iter().map(|day| [day_x(day.date), day.active_seconds as f64 / 3600.0]).collect(),
+                trends.1.iter().map(|day| [day_x(day.day), day.minutes as f64 / 60.0]).collect(),
+            )
+        };
+        
+        ui.group(|ui| {
+            ui.heading("🎯 Focus Trends");
+            ui.small("Days since the start of the range; deep work comes from `selfstats deep-work detect`.");
+            ui.separator();
+            
+            Plot::new("focus_trends")
                 .legend(Legend::default())
                 .height(400.0)
                 .show(ui, |plot_ui| {
-                    // Productivity score over time
-                    let productivity_data: PlotPoints = (0..self.time_range)
-                        .map(|i| {
-                            let x = i as f64;
-                            let base = 75.0;
-                            let trend = x * 0.5; // Gradual improvement
-                            let variation = 10.0 * (x * 0.3).sin();
-                            let y = (base + trend + variation).clamp(0.0, 100.0);
-                            [x, y]
-                        })
-                        .collect();
-                    
-                    // Focus score
-                    let focus_data: PlotPoints = (0..self.time_range)
-                        .map(|i| {
-                            let x = i as f64;
-                            let base = 70.0;
-                            let variation = 15.0 * (x * 0.2).cos() + 8.0 * (x * 0.5).sin();
-                            let y = (base + variation).clamp(0.0, 100.0);
-                            [x, y]
-                        })
-                        .collect();
-                    
-                    // Efficiency score
-                    let efficiency_data: PlotPoints = (0..self.time_range)
-                        .map(|i| {
-                            let x = i as f64;
-                            let base = 80.0;
-                            let variation = 12.0 * (x * 0.25).sin();
-                            let y = (base + variation).clamp(0.0, 100.0);
-                            [x, y]
-                        })
-                        .collect();
-                    
-                    plot_ui.line(
-                        Line::new(productivity_data)
-                            .color(egui::Color32::from_rgb(100, 255, 100))
-                            .name("Productivity Score")
-                    );
-                    
                     plot_ui.line(
-                        Line::new(focus_data)
-                            .color(egui::Color32::from_rgb(255, 150, 100))
-                            .name("Focus Score")
+                        Line::new(active)
+                            .color(egui::Color32::from_rgb(150, 255, 100))
+                            .name("Active Hours")
                     );
                     
                     plot_ui.line(
-                        Line::new(efficiency_data)
-                            .color(egui::Color32::from_rgb(150, 150, 255))
-                            .name("Efficiency Score")
+                        Line::new(deep_work)
+                            .color(egui::Color32::from_rgb(130, 110, 230))
+                            .name("Deep Work Hours")
                     );
                 });
         });
     }
     
-    fn show_hourly_patterns_chart(&self, ui: &mut egui::Ui) {
+    fn load_trends(&self, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.trends.clone();
+        let end = Local::now().date_naive() + Duration::days(1);
+        let start = end - Duration::days(self.time_range as i64);
+        
+        tokio::spawn(async move {
+            let trends = match Database::open_snapshot(&database_path).await {
+                Ok(db) => match db.get_daily_summaries(start, end).await {
+                    Ok(days) => db.get_deep_work_days(start, end).await.map(|deep_work| (days, deep_work)),
+                    Err(e) => Err(e),
+                },
+                Err(e) => Err(e),
+            };
+            
+            match trends {
+                Ok(trends) => {
+                    *target.lock().unwrap() = trends;
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to load focus trends: {}", e),
+            }
+        });
+    }
+    
+    fn show_custom_metrics_chart(&mut self, ui: &mut egui::Ui, custom_metrics: &[CustomMetric]) {
+        if self.metrics_range != Some(self.time_range) || self.loaded_metrics != custom_metrics {
+            self.metrics_range = Some(self.time_range);
+            self.loaded_metrics = custom_metrics.to_vec();
+            self.load_metric_days(ui.ctx().clone());
+        }
+        
         ui.group(|ui| {
-            ui.heading("⏰ Hourly Activity Patterns");
+            ui.heading("🧮 Custom Metrics");
+            if custom_metrics.is_empty() {
+                ui.label("No custom metrics yet. Define them under custom_metrics in config.json.");
+                return;
+            }
+            ui.small("One value per active day; percentages are charted times 100.");
             ui.separator();
             
-            Plot::new("hourly_patterns")
+            let first_day = Local::now().date_naive() - Duration::days(self.time_range as i64 - 1);
+            let metric_days = self.metric_days.lock().unwrap();
+            Plot::new("custom_metrics")
+                .legend(Legend::default())
                 .height(400.0)
                 .show(ui, |plot_ui| {
-                    // Average activity by hour
-                    let hourly_activity: Vec<Bar> = (0..24)
-                        .map(|hour| {
-                            let activity = self.get_hourly_activity_level(hour);
-                            Bar::new(hour as f64, activity)
-                                .fill(self.get_hour_color(hour))
-                        })
-                        .collect();
-                    
-                    plot_ui.bar_chart(
-                        BarChart::new(hourly_activity)
-                            .name("Activity Level")
-                    );
+                    for (index, metric) in custom_metrics.iter().enumerate() {
+                        let scale = if metric.format == MetricFormat::Percent { 100.0 } else { 1.0 };
+                        let points: PlotPoints = metric_days
+                            .iter()
+                            .filter_map(|(day, values)| {
+                                let value = values.get(index)?.value?;
+                                Some([(*day - first_day).num_days() as f64, value * scale])
+                            })
+                            .collect();
+                        plot_ui.line(Line::new(points).color(self.get_app_color(index)).name(&metric.name));
+                    }
                 });
+        });
+    }
+    
+    fn load_metric_days(&self, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.metric_days.clone();
+        let metrics = self.loaded_metrics.clone();
+        let end = Local::now().date_naive() + Duration::days(1);
+        let start = end - Duration::days(self.time_range as i64);
+        
+        tokio::spawn(async move {
+            let days = match Database::open_snapshot(&database_path).await {
+                Ok(db) => custom_metrics::daily_values(&db, &metrics, start, end).await,
+                Err(e) => Err(e),
+            };
             
-            ui.add_space(10.0);
-            
-            // Heatmap-style hourly breakdown
-            ui.group(|ui| {
-                ui.heading("📅 Weekly Activity Heatmap");
-                ui.separator();
-                
-                let desired_size = egui::vec2(ui.available_width(), 200.0);
-                let (rect, _response) = ui.allocate_exact_size(desired_size, egui::Sense::hover());
-                
-                let days = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
-                let cell_width = rect.width() / 24.0;
-                let cell_height = rect.height() / 7.0;
-                
-                // Draw heatmap
-                for (day_idx, _day) in days.iter().enumerate() {
-                    for hour in 0..24 {
-                        let activity = self.get_activity_for_day_hour(day_idx, hour);
-                        let intensity = (activity * 255.0) as u8;
-                        let color = egui::Color32::from_rgba_unmultiplied(
-                            100, 150, 255, intensity
-                        );
-                        
-                        let cell_rect = egui::Rect::from_min_size(
-                            egui::pos2(
-                                rect.min.x + hour as f32 * cell_width,
-                                rect.min.y + day_idx as f32 * cell_height,
-                            ),
-                            egui::vec2(cell_width - 1.0, cell_height - 1.0),
-                        );
-                        
-                        ui.painter().rect_filled(cell_rect, 2.0, color);
-                    }
+            match days {
+                Ok(days) => {
+                    *target.lock().unwrap() = days;
+                    ctx.request_repaint();
                 }
-                
-                // Labels
-                for (day_idx, day) in days.iter().enumerate() {
-                    ui.painter().text(
-                        egui::pos2(rect.min.x - 30.0, rect.min.y + day_idx as f32 * cell_height + cell_height / 2.0),
-                        egui::Align2::RIGHT_CENTER,
-                        day,
-                        egui::FontId::proportional(12.0),
-                        egui::Color32::GRAY,
+                Err(e) => tracing::warn!("Failed to load custom metrics: {}", e),
+            }
+        });
+    }
+    
+    fn show_hourly_patterns_chart(&mut self, ui: &mut egui::Ui) {
+        let (hours, usage) = {
+            let visits = self.visits.lock().unwrap();
+            // Like the bars, the heatmap covers every hour
+            let query = ChartQuery { hour: None, ..self.query.clone() };
+            (self.hourly_usage(&visits), chart_data::weekly_usage(&visits, &query, self.first_day))
+        };
+        self.show_hourly_bars(ui, hours);
+        
+        ui.add_space(10.0);
+        
+        // Heatmap-style hourly breakdown
+        ui.group(|ui| {
+            ui.heading("📅 Weekly Activity Heatmap");
+            ui.separator();
+            
+            let desired_size = egui::vec2(ui.available_width(), 200.0);
+            let (rect, _response) = ui.allocate_exact_size(desired_size, egui::Sense::hover());
+            
+            let days = calendar::weekdays_from(self.first_day);
+            let peak = usage.iter().flatten().copied().max().unwrap_or(0).max(1);
+            let cell_width = rect.width() / 24.0;
+            let cell_height = rect.height() / 7.0;
+            
+            // Draw heatmap
+            for (day_idx, hours) in usage.iter().enumerate() {
+                for (hour, seconds) in hours.iter().enumerate() {
+                    let intensity = (*seconds as f32 / peak as f32 * 255.0) as u8;
+                    let color = egui::Color32::from_rgba_unmultiplied(
+                        100, 150, 255, intensity
                     );
-                }
-                
-                for hour in (0..24).step_by(4) {
-                    ui.painter().text(
-                        egui::pos2(rect.min.x + hour as f32 * cell_width + cell_width / 2.0, rect.max.y + 10.0),
-                        egui::Align2::CENTER_TOP,
-                        format!("{:02}", hour),
-                        egui::FontId::proportional(12.0),
-                        egui::Color32::GRAY,
+                    
+                    let cell_rect = egui::Rect::from_min_size(
+                        egui::pos2(
+                            rect.min.x + hour as f32 * cell_width,
+                            rect.min.y + day_idx as f32 * cell_height,
+                        ),
+                        egui::vec2(cell_width - 1.0, cell_height - 1.0),
                     );
+                    
+                    ui.painter().rect_filled(cell_rect, 2.0, color);
                 }
+            }
+            
+            // Labels
+            for (day_idx, day) in days.iter().enumerate() {
+                ui.painter().text(
+                    egui::pos2(rect.min.x - 30.0, rect.min.y + day_idx as f32 * cell_height + cell_height / 2.0),
+                    egui::Align2::RIGHT_CENTER,
+                    day,
+                    egui::FontId::proportional(12.0),
+                    egui::Color32::GRAY,
+                );
+            }
+            
+            for hour in (0..24).step_by(4) {
+                ui.painter().text(
+                    egui::pos2(rect.min.x + hour as f32 * cell_width + cell_width / 2.0, rect.max.y + 10.0),
+                    egui::Align2::CENTER_TOP,
+                    format!("{:02}", hour),
+                    egui::FontId::proportional(12.0),
+                    egui::Color32::GRAY,
+                );
+            }
+        });
+    }
+    
+    // Time per hour of the day for the current query, ignoring its hour: the
+    // bars always cover the whole day so an hour can be re-picked
+    fn hourly_usage(&self, visits: &[WindowVisit]) -> [i64; 24] {
+        let query = ChartQuery { hour: None, ..self.query.clone() };
+        chart_data::hourly_usage(visits, &query)
+    }
+    
+    // Bars from `hourly_usage`; clicking an hour filters the timeline
+    fn show_hourly_bars(&mut self, ui: &mut egui::Ui, hours: [i64; 24]) {
+        ui.group(|ui| {
+            ui.heading("⏰ Hourly Activity Patterns");
+            ui.small("Click an hour to list the windows used then.");
+            ui.separator();
+            
+            let bars: Vec<Bar> = hours
+                .iter()
+                .enumerate()
+                .map(|(hour, seconds)| {
+                    let fill = if self.query.hour == Some(hour as u32) {
+                        egui::Color32::from_rgb(255, 200, 100)
+                    } else {
+                        self.get_hour_color(hour)
+                    };
+                    Bar::new(hour as f64, *seconds as f64 / 60.0).fill(fill)
+                })
+                .collect();
+            
+            let clicked = clicked_bar(
+                Plot::new("hourly_patterns")
+                    .height(300.0)
+                    .show(ui, |plot_ui| {
+                        plot_ui.bar_chart(BarChart::new(bars).name("Minutes"));
+                        plot_ui.pointer_coordinate()
+                    }),
+                hours.len(),
+            );
+            if let Some(hour) = clicked {
+                self.query.hour = Some(hour as u32);
+            }
+        });
+    }
+    
+    // Windows selected by the current query, in order
+    fn show_timeline(&self, ui: &mut egui::Ui) {
+        let visits = self.visits.lock().unwrap();
+        let entries = chart_data::timeline(&visits, &self.query);
+        
+        ui.group(|ui| {
+            ui.heading("🕑 Timeline");
+            ui.separator();
+            
+            if entries.is_empty() {
+                ui.label("No windows match.");
+                return;
+            }
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("timeline")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (visit, seconds) in entries.iter().take(TIMELINE_LIMIT) {
+                            ui.label(visit.at.format("%Y-%m-%d %H:%M").to_string());
+                            ui.label(format_seconds(*seconds));
+                            ui.label(&visit.process);
+                            ui.label(&visit.title);
+                            ui.end_row();
+                        }
+                    });
             });
         });
     }
     
+    // "All apps › Firefox › 14:00"; clicking a crumb drops everything after it
+    fn show_breadcrumbs(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui.link("All apps").clicked() {
+                self.query = ChartQuery::default();
+            }
+            if let Some(app) = self.query.app.clone() {
+                ui.label("›");
+                if ui.link(&app).clicked() {
+                    self.query.hour = None;
+                }
+            }
+            if let Some(hour) = self.query.hour {
+                ui.label("›");
+                ui.label(format!("{:02}:00–{:02}:00", hour, hour + 1));
+            }
+        });
+    }
+    
+    // Fetch the range's window visits without blocking the UI thread
+    fn load_visits(&self, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.visits.clone();
+        let end = Local::now().date_naive() + Duration::days(1);
+        let start = end - Duration::days(self.time_range as i64);
+        
+        tokio::spawn(async move {
+            let visits = match Database::open_snapshot(&database_path).await {
+                Ok(db) => db.get_window_visits(start, end).await,
+                Err(e) => Err(e),
+            };
+            
+            match visits {
+                Ok(visits) => {
+                    *target.lock().unwrap() = visits;
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to load window visits: {}", e),
+            }
+        });
+    }
+    
     fn get_app_color(&self, index: usize) -> egui::Color32 {
         let colors = [
             egui::Color32::from_rgb(100, 150, 255),
@@ -311,7 +732,7 @@ impl Charts {
     }
     
     fn get_hour_color(&self, hour: usize) -> egui::Color32 {
-        if hour >= 9 && hour <= 17 {
+        if self.work_hours[hour] {
             egui::Color32::from_rgb(100, 150, 255) // Work hours - blue
         } else if hour >= 6 && hour <= 22 {
             egui::Color32::from_rgb(150, 255, 100) // Active hours - green
@@ -319,37 +740,13 @@ impl Charts {
             egui::Color32::from_rgb(100, 100, 100) // Sleep hours - gray
         }
     }
-    
-    fn get_hourly_activity_level(&self, hour: usize) -> f64 {
-        // Simulate realistic activity patterns
-        match hour {
-            0..=5 => 0.1,           // Late night/early morning
-            6..=7 => 0.3,           // Getting ready
-            8 => 0.6,               // Commute/start work
-            9..=11 => 0.9,          // Morning productivity peak
-            12 => 0.4,              // Lunch break
-            13..=15 => 0.85,        // Afternoon work
-            16..=17 => 0.7,         // End of work day
-            18..=19 => 0.5,         // Evening activities
-            20..=22 => 0.6,         // Evening relaxation/hobbies
-            _ => 0.2,               // Late evening
-        }
-    }
-    
-    fn get_activity_for_day_hour(&self, day: usize, hour: usize) -> f32 {
-        let base_activity = self.get_hourly_activity_level(hour) as f32;
-        
-        // Modify based on day of week
-        let day_modifier = match day {
-            0..=4 => 1.0,           // Weekdays - normal
-            5 => 0.8,               // Saturday - reduced
-            6 => 0.6,               // Sunday - more reduced
-            _ => 1.0,
-        };
-        
-        // Add some randomness
-        let variation = ((day * 7 + hour) as f32 * 0.1).sin() * 0.2;
-        
-        (base_activity * day_modifier + variation).clamp(0.0, 1.0)
+}
+
+// Index of the bar under the pointer when the plot was clicked
+fn clicked_bar(plot: PlotResponse<Option<PlotPoint>>, bars: usize) -> Option<usize> {
+    if !plot.response.clicked() {
+        return None;
     }
+    let x = plot.inner?.x.round();
+    (x >= 0.0 && (x as usize) < bars).then_some(x as usize)
 }
\ No newline at end of file
@@ -0,0 +1,200 @@
+use eframe::egui;
+use selfspy_core::{Database, WindowVisit};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use crate::app::AppTab;
+use crate::time_range::TimeRange;
+
+// Queries shorter than this only match commands, not window history
+const MIN_SEARCH_CHARS: usize = 3;
+const SEARCH_LIMIT: i64 = 20;
+
+#[derive(Clone, Copy)]
+pub enum PaletteAction {
+    OpenTab(AppTab),
+    SetTimeRange(TimeRange),
+    Export,
+    ToggleMonitoring,
+}
+
+struct PaletteCommand {
+    label: &'static str,
+    action: PaletteAction,
+}
+
+const COMMANDS: &[PaletteCommand] = &[
+    PaletteCommand { label: "📊 Go to Dashboard", action: PaletteAction::OpenTab(AppTab::Dashboard) },
+    PaletteCommand { label: "📈 Go to Statistics", action: PaletteAction::OpenTab(AppTab::Statistics) },
+    PaletteCommand { label: "📉 Go to Charts", action: PaletteAction::OpenTab(AppTab::Charts) },
+    PaletteCommand { label: "⚙️ Go to Settings", action: PaletteAction::OpenTab(AppTab::Settings) },
+    PaletteCommand { label: "🕐 Time range: Today", action: PaletteAction::SetTimeRange(TimeRange::Today) },
+    PaletteCommand { label: "🕐 Time range: This Week", action: PaletteAction::SetTimeRange(TimeRange::Week) },
+    PaletteCommand { label: "🕐 Time range: This Month", action: PaletteAction::SetTimeRange(TimeRange::Month) },
+    PaletteCommand { label: "🕐 Time range: This Year", action: PaletteAction::SetTimeRange(TimeRange::Year) },
+    PaletteCommand { label: "🕐 Time range: All Time", action: PaletteAction::SetTimeRange(TimeRange::All) },
+    PaletteCommand { label: "📤 Export Data", action: PaletteAction::Export },
+];
+
+// Ctrl/Cmd+K overlay: type to filter commands; longer queries also search
+// window titles and app names in the history
+pub struct CommandPalette {
+    open: bool,
+    query: String,
+    selected: usize,
+    database_path: PathBuf,
+    // History matches, tagged with the query they answer so stale results are ignored
+    results: Arc<Mutex<(String, Vec<WindowVisit>)>>,
+    searched_query: String,
+}
+
+impl CommandPalette {
+    pub fn new(database_path: PathBuf) -> Self {
+        Self {
+            open: false,
+            query: String::new(),
+            selected: 0,
+            database_path,
+            results: Arc::new(Mutex::new((String::new(), Vec::new()))),
+            searched_query: String::new(),
+        }
+    }
+    
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.query.clear();
+        self.selected = 0;
+    }
+    
+    // Draws the palette when open; returns the command the user picked
+    pub fn show(&mut self, ctx: &egui::Context, monitoring: bool) -> Option<PaletteAction> {
+        if !self.open {
+            return None;
+        }
+        
+        let pause_label = if monitoring { "⏹ Pause Monitoring" } else { "▶ Resume Monitoring" };
+        let pause = PaletteCommand { label: pause_label, action: PaletteAction::ToggleMonitoring };
+        let matches: Vec<&PaletteCommand> = COMMANDS
+            .iter()
+            .chain(std::iter::once(&pause))
+            .filter(|command| matches_query(command.label, &self.query))
+            .collect();
+        
+        let (escape, enter, up, down) = ctx.input(|i| {
+            (
+                i.key_pressed(egui::Key::Escape),
+                i.key_pressed(egui::Key::Enter),
+                i.key_pressed(egui::Key::ArrowUp),
+                i.key_pressed(egui::Key::ArrowDown),
+            )
+        });
+        if escape {
+            self.open = false;
+            return None;
+        }
+        if down && self.selected + 1 < matches.len() {
+            self.selected += 1;
+        }
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        }
+        self.selected = self.selected.min(matches.len().saturating_sub(1));
+        
+        let mut chosen = None;
+        egui::Window::new("command_palette")
+            .title_bar(false)
+            .collapsible(false)
+            .resizable(false)
+            .fixed_size([500.0, 0.0])
+            .anchor(egui::Align2::CENTER_TOP, [0.0, 80.0])
+            .show(ctx, |ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.query)
+                        .hint_text("Type a command or search history…")
+                        .desired_width(f32::INFINITY),
+                );
+                response.request_focus();
+                if response.changed() {
+                    self.selected = 0;
+                }
+                
+                ui.separator();
+                
+                for (index, command) in matches.iter().enumerate() {
+                    if ui.selectable_label(index == self.selected, command.label).clicked() {
+                        chosen = Some(command.action);
+                    }
+                }
+                if matches.is_empty() {
+                    ui.weak("No matching commands");
+                }
+                
+                self.show_history(ui);
+            });
+        
+        if enter {
+            chosen = chosen.or_else(|| matches.get(self.selected).map(|command| command.action));
+        }
+        if chosen.is_some() {
+            self.open = false;
+        }
+        chosen
+    }
+    
+    fn show_history(&mut self, ui: &mut egui::Ui) {
+        let query = self.query.trim().to_string();
+        if query.chars().count() < MIN_SEARCH_CHARS {
+            return;
+        }
+        if query != self.searched_query {
+            self.searched_query = query.clone();
+            self.search(query.clone(), ui.ctx().clone());
+        }
+        
+        ui.separator();
+        ui.strong("🔍 History");
+        
+        let results = self.results.lock().unwrap();
+        if results.0 != query {
+            ui.weak("Searching…");
+        } else if results.1.is_empty() {
+            ui.weak("No matching windows");
+        } else {
+            egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                for visit in &results.1 {
+                    ui.horizontal(|ui| {
+                        ui.weak(visit.at.format("%Y-%m-%d %H:%M").to_string());
+                        ui.label(&visit.process);
+                        ui.label(egui::RichText::new(&visit.title).italics());
+                    });
+                }
+            });
+        }
+    }
+    
+    // Search the history without blocking the UI thread
+    fn search(&self, query: String, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.results.clone();
+        
+        tokio::spawn(async move {
+            let visits = match Database::open_snapshot(&database_path).await {
+                Ok(db) => db.search_window_history(&query, SEARCH_LIMIT).await,
+                Err(e) => Err(e),
+            };
+            
+            match visits {
+                Ok(visits) => {
+                    *target.lock().unwrap() = (query, visits);
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to search window history: {}", e),
+            }
+        });
+    }
+}
+
+// Every word of the query appears in the label, ignoring case
+fn matches_query(label: &str, query: &str) -> bool {
+    let label = label.to_lowercase();
+    query.split_whitespace().all(|word| label.contains(&word.to_lowercase()))
+}
\ No newline at end of file
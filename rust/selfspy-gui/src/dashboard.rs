@@ -1,26 +1,82 @@
+use chrono::{Local, Timelike, Utc};
 use eframe::egui;
+use selfspy_core::fatigue::{self, FatigueIndicator};
+use selfspy_core::custom_metrics::CustomMetric;
+use selfspy_core::{ActivityBucket, BucketSize, DailySummary, Database, PinnedReport};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use crate::pinned::{PinAction, PinnedCards};
+use crate::time_range::TimeRange;
+
+// Today's typing rhythm changes slowly; reload it every few minutes
+const RHYTHM_REFRESH: Duration = Duration::from_secs(300);
+const TIMELINE_REFRESH: Duration = Duration::from_secs(60);
 
 pub struct Dashboard {
     last_refresh: std::time::Instant,
+    database_path: PathBuf,
+    typing_rhythm: Arc<Mutex<Option<FatigueIndicator>>>,
+    rhythm_loaded_at: Option<Instant>,
+    // Daily totals for `totals_range`, reloaded when the shared range changes
+    range_totals: Arc<Mutex<Vec<DailySummary>>>,
+    totals_range: Option<TimeRange>,
+    totals_loaded_at: Option<Instant>,
+    // Today's activity per local hour, for the timeline
+    today_hours: Arc<Mutex<Vec<ActivityBucket>>>,
+    today_loaded_at: Option<Instant>,
+    pinned: PinnedCards,
 }
 
 impl Dashboard {
-    pub fn new() -> Self {
+    pub fn new(database_path: PathBuf) -> Self {
         Self {
             last_refresh: std::time::Instant::now(),
+            database_path: database_path.clone(),
+            typing_rhythm: Arc::new(Mutex::new(None)),
+            rhythm_loaded_at: None,
+            range_totals: Arc::new(Mutex::new(Vec::new())),
+            totals_range: None,
+            totals_loaded_at: None,
+            today_hours: Arc::new(Mutex::new(Vec::new())),
+            today_loaded_at: None,
+            pinned: PinnedCards::new(database_path),
         }
     }
     
-    pub fn show(&mut self, ui: &mut egui::Ui, is_monitoring: bool, database_connected: bool) {
-        ui.heading("📊 Activity Dashboard");
+    // Returns a pin or unpin request from the pinned cards
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        is_monitoring: bool,
+        database_connected: bool,
+        range: TimeRange,
+        pinned_reports: &[PinnedReport],
+        custom_metrics: &[CustomMetric],
+    ) -> Option<PinAction> {
+        if self.totals_range != Some(range)
+            || self.totals_loaded_at.map_or(true, |at| at.elapsed() >= RHYTHM_REFRESH)
+        {
+            self.totals_range = Some(range);
+            self.totals_loaded_at = Some(Instant::now());
+            self.load_range_totals(range, ui.ctx().clone());
+        }
+        let (keystrokes, clicks) = self
+            .range_totals
+            .lock()
+            .unwrap()
+            .iter()
+            .fold((0, 0), |(keys, clicks), day| (keys + day.keystrokes, clicks + day.clicks));
+        
+        ui.heading(format!("📊 Activity Dashboard · {}", range.label()));
         ui.separator();
         
         // Live metrics cards
         ui.columns(4, |columns| {
-            self.show_metric_card(&mut columns[0], "⌨️ Keystrokes", 1234, 
+            self.show_metric_card(&mut columns[0], "⌨️ Keystrokes", keystrokes, 
                 egui::Color32::from_rgb(100, 150, 255));
                 
-            self.show_metric_card(&mut columns[1], "🖱️ Clicks", 567, 
+            self.show_metric_card(&mut columns[1], "🖱️ Clicks", clicks, 
                 egui::Color32::from_rgb(255, 150, 100));
                 
             self.show_metric_card(&mut columns[2], "🪟 Windows", 89, 
@@ -32,6 +88,10 @@ impl Dashboard {
         
         ui.add_space(20.0);
         
+        let action = self.pinned.show(ui, pinned_reports, custom_metrics, range);
+        
+        ui.add_space(20.0);
+        
         // Current activity section
         ui.group(|ui| {
             ui.heading("🔴 Current Activity");
@@ -64,6 +124,10 @@ impl Dashboard {
         
         ui.add_space(20.0);
         
+        self.show_typing_rhythm(ui);
+        
+        ui.add_space(20.0);
+        
         // Database connection status
         ui.group(|ui| {
             ui.heading("💾 Database Status");
@@ -90,13 +154,12 @@ impl Dashboard {
             ui.separator();
             
             if database_connected {
-                // Show activity summary
+                let active_today: i64 = self.today_hours.lock().unwrap().iter().map(|hour| hour.active_seconds).sum();
                 ui.horizontal(|ui| {
-                    ui.label("Session Duration:");
-                    ui.label("2h 45m");
+                    ui.label("Active Today:");
+                    ui.label(format!("{}h {}m", active_today / 3600, active_today % 3600 / 60));
                 });
                 
-                // Simple activity timeline visualization
                 self.show_activity_timeline(ui);
             } else {
                 ui.label("No activity data available - database not connected");
@@ -128,6 +191,98 @@ impl Dashboard {
                 }
             });
         });
+        
+        action
+    }
+    
+    fn show_typing_rhythm(&mut self, ui: &mut egui::Ui) {
+        if self.rhythm_loaded_at.map_or(true, |at| at.elapsed() >= RHYTHM_REFRESH) {
+            self.rhythm_loaded_at = Some(Instant::now());
+            self.load_typing_rhythm(ui.ctx().clone());
+        }
+        
+        ui.group(|ui| {
+            ui.heading("🫠 Typing Rhythm");
+            ui.separator();
+            
+            let Some(indicator) = self.typing_rhythm.lock().unwrap().clone() else {
+                ui.label("Not enough typing today to read a rhythm");
+                return;
+            };
+            
+            ui.horizontal(|ui| {
+                ui.label("Rhythm variation:");
+                ui.label(format!("{:.2}", indicator.rhythm_variation));
+                ui.separator();
+                ui.label("Corrections:");
+                ui.label(format!("{:.1}%", indicator.backspace_ratio * 100.0));
+                if let Some(hold) = indicator.mean_hold_ms {
+                    ui.separator();
+                    ui.label("Key hold:");
+                    ui.label(format!("{:.0} ms", hold));
+                }
+            });
+            
+            ui.horizontal(|ui| {
+                ui.label("Through the day:");
+                if indicator.hours < 3 {
+                    ui.label("too early to tell");
+                } else if indicator.likely_fatigued() {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 200, 100),
+                        "⚠ Rhythm is slipping, consider a break",
+                    );
+                } else {
+                    ui.colored_label(egui::Color32::from_rgb(100, 255, 100), "Steady");
+                }
+            });
+            
+            ui.small("Based on key timing and correction counts only; nothing you type is read.");
+        });
+    }
+    
+    fn load_typing_rhythm(&self, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.typing_rhythm.clone();
+        let today = Local::now().date_naive();
+        
+        tokio::spawn(async move {
+            let hours = match Database::open_snapshot(&database_path).await {
+                Ok(db) => db.get_hourly_rhythm(today).await,
+                Err(e) => Err(e),
+            };
+            
+            match hours {
+                Ok(hours) => {
+                    *target.lock().unwrap() = fatigue::daily_indicator(today, &hours);
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to load typing rhythm: {}", e),
+            }
+        });
+    }
+    
+    fn load_range_totals(&self, range: TimeRange, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.range_totals.clone();
+        let today = Local::now().date_naive();
+        let start = range.start(today);
+        let end = today + chrono::Duration::days(1);
+        
+        tokio::spawn(async move {
+            let days = match Database::open_snapshot(&database_path).await {
+                Ok(db) => db.get_daily_summaries(start, end).await,
+                Err(e) => Err(e),
+            };
+            
+            match days {
+                Ok(days) => {
+                    *target.lock().unwrap() = days;
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to load activity totals: {}", e),
+            }
+        });
     }
     
     fn show_metric_card(&self, ui: &mut egui::Ui, title: &str, value: i64, color: egui::Color32) {
@@ -150,10 +305,16 @@ impl Dashboard {
         }
     }
     
+    // Share of the current hour so far with any input, from today's hourly buckets
     fn calculate_activity_level(&self) -> f32 {
-        // Calculate based on recent activity
-        // This is a placeholder - would use real activity data
-        0.7 // 70% activity level
+        let now = Local::now();
+        let elapsed = (now.minute() * 60 + now.second()).max(60) as f32;
+        self.today_hours
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|bucket| bucket.start.date() == now.date_naive() && bucket.start.hour() == now.hour())
+            .map_or(0.0, |bucket| (bucket.active_seconds as f32 / elapsed).min(1.0))
     }
     
     fn show_activity_bar(&self, ui: &mut egui::Ui, level: f32) {
@@ -187,18 +348,24 @@ impl Dashboard {
         );
     }
     
-    fn show_activity_timeline(&self, ui: &mut egui::Ui) {
-        // Simple timeline visualization
+    fn show_activity_timeline(&mut self, ui: &mut egui::Ui) {
+        if self.today_loaded_at.map_or(true, |at| at.elapsed() >= TIMELINE_REFRESH) {
+            self.today_loaded_at = Some(Instant::now());
+            self.load_today_hours(ui.ctx().clone());
+        }
+        let hours = self.today_hours.lock().unwrap().clone();
+        
+        // Active minutes per local hour of today
         let desired_size = egui::vec2(ui.available_width(), 60.0);
         let (rect, _response) = ui.allocate_exact_size(desired_size, egui::Sense::hover());
         
         // Background
         ui.painter().rect_filled(rect, 3.0, egui::Color32::from_gray(30));
         
-        // Draw timeline bars (simulated data)
         let bar_width = rect.width() / 24.0; // 24 hours
-        for hour in 0..24 {
-            let activity = ((hour as f32 * 0.3).sin() + 1.0) * 0.5; // Simulated activity
+        for bucket in &hours {
+            let hour = bucket.start.hour();
+            let activity = (bucket.active_seconds as f32 / 3600.0).min(1.0);
             let bar_height = rect.height() * activity;
             let bar_rect = egui::Rect::from_min_size(
                 egui::pos2(rect.min.x + hour as f32 * bar_width, rect.max.y - bar_height),
@@ -221,6 +388,32 @@ impl Dashboard {
         }
     }
     
+    fn load_today_hours(&self, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.today_hours.clone();
+        let now = Utc::now();
+        let midnight = Local::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+            .map_or(now, |midnight| midnight.with_timezone(&Utc));
+        
+        tokio::spawn(async move {
+            let hours = match Database::open_snapshot(&database_path).await {
+                Ok(db) => db.get_activity_buckets(midnight..now, BucketSize::Hour).await,
+                Err(e) => Err(e),
+            };
+            
+            match hours {
+                Ok(hours) => {
+                    *target.lock().unwrap() = hours;
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to load today's activity: {}", e),
+            }
+        });
+    }
+    
     fn export_data(&self) {
         // Placeholder for data export functionality
         println!("Export data functionality would go here");
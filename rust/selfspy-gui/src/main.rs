@@ -2,28 +2,53 @@ mod app;
 mod dashboard;
 mod settings;
 mod statistics;
+mod chart_data;
 mod charts;
+mod command_palette;
+mod pinned;
+mod progress;
 mod system_tray;
+mod time_range;
 
 use app::SelfspyApp;
+use clap::Parser;
 use eframe::egui;
+use selfspy_core::{Config, ErrorKind};
+use std::process::ExitCode;
+
+#[derive(Parser)]
+#[command(name = "selfspy-gui")]
+#[command(about = "Graphical interface for Selfspy", version)]
+struct Cli {
+    /// Run only the system tray icon; the main window opens on demand
+    #[arg(long)]
+    tray_only: bool,
+}
 
 #[tokio::main]
-async fn main() -> Result<(), eframe::Error> {
+async fn main() -> ExitCode {
     // Initialize tracing
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
         .init();
 
+    let cli = Cli::parse();
+    let config = Config::load().unwrap_or_else(|e| {
+        tracing::warn!("Failed to load config, using defaults: {}", e);
+        Config::new()
+    });
+    let start_hidden = cli.tray_only || config.start_hidden;
+
     let options = eframe::NativeOptions {
         viewport: egui::ViewportBuilder::default()
             .with_inner_size([1200.0, 800.0])
             .with_min_inner_size([800.0, 600.0])
+            .with_visible(!start_hidden)
             .with_icon(load_icon()),
         ..Default::default()
     };
 
-    eframe::run_native(
+    let result = eframe::run_native(
         "Selfspy - Activity Monitor",
         options,
         Box::new(|cc| {
@@ -33,9 +58,17 @@ async fn main() -> Result<(), eframe::Error> {
             // Enable dark mode by default
             cc.egui_ctx.set_visuals(egui::Visuals::dark());
             
-            Ok(Box::new(SelfspyApp::new(cc)))
+            Ok(Box::new(SelfspyApp::new(cc, config, start_hidden)))
         }),
-    )
+    );
+    
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(ErrorKind::Other.exit_code())
+        }
+    }
 }
 
 fn load_icon() -> egui::IconData {
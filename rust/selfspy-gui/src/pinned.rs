@@ -0,0 +1,203 @@
+use chrono::{Duration, Local, NaiveDate};
+use eframe::egui;
+use selfspy_core::custom_metrics::{self, CustomMetric};
+use selfspy_core::error::Result;
+use selfspy_core::{Database, PinnedMetric, PinnedReport};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use crate::chart_data::{self, format_seconds, ChartQuery};
+use crate::time_range::{self, TimeRange};
+
+// Pinned values are totals over days; a few minutes of lag is fine
+const PINNED_REFRESH: std::time::Duration = std::time::Duration::from_secs(300);
+
+// Changes to the pinned list requested by a view; the app applies and saves them
+pub enum PinAction {
+    Pin(PinnedReport),
+    Unpin(usize),
+}
+
+// Dashboard cards for the reports pinned in config
+pub struct PinnedCards {
+    database_path: PathBuf,
+    // Formatted value per report, for the list in `loaded_for`
+    values: Arc<Mutex<Vec<Option<String>>>>,
+    loaded_for: Vec<PinnedReport>,
+    // Definitions the custom metric cards were computed with
+    loaded_metrics: Vec<CustomMetric>,
+    loaded_at: Option<Instant>,
+}
+
+impl PinnedCards {
+    pub fn new(database_path: PathBuf) -> Self {
+        Self {
+            database_path,
+            values: Arc::new(Mutex::new(Vec::new())),
+            loaded_for: Vec::new(),
+            loaded_metrics: Vec::new(),
+            loaded_at: None,
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        reports: &[PinnedReport],
+        custom_metrics: &[CustomMetric],
+        range: TimeRange,
+    ) -> Option<PinAction> {
+        if self.loaded_for != reports
+            || self.loaded_metrics != custom_metrics
+            || self.loaded_at.map_or(true, |at| at.elapsed() >= PINNED_REFRESH)
+        {
+            self.loaded_for = reports.to_vec();
+            self.loaded_metrics = custom_metrics.to_vec();
+            self.loaded_at = Some(Instant::now());
+            self.load(ui.ctx().clone());
+        }
+
+        let mut action = None;
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading("📌 Pinned");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    ui.menu_button("➕ Pin", |ui| {
+                        for metric in [PinnedMetric::Keystrokes, PinnedMetric::Clicks, PinnedMetric::ActiveTime] {
+                            if ui.button(format!("{} · {}", metric.label(), range.label())).clicked() {
+                                action = Some(PinAction::Pin(PinnedReport {
+                                    title: metric.label().to_string(),
+                                    metric,
+                                    days: range.days(),
+                                    app: None,
+                                    hour: None,
+                                    custom_metric: None,
+                                }));
+                                ui.close_menu();
+                            }
+                        }
+                        for metric in custom_metrics {
+                            if ui.button(format!("{} · {}", metric.name, range.label())).clicked() {
+                                action = Some(PinAction::Pin(PinnedReport {
+                                    title: metric.name.clone(),
+                                    metric: PinnedMetric::Custom,
+                                    days: range.days(),
+                                    app: None,
+                                    hour: None,
+                                    custom_metric: Some(metric.name.clone()),
+                                }));
+                                ui.close_menu();
+                            }
+                        }
+                    });
+                });
+            });
+            ui.separator();
+
+            if reports.is_empty() {
+                ui.label("Nothing pinned yet. Pin a metric here or a chart from the Charts tab.");
+                return;
+            }
+
+            let values = self.values.lock().unwrap().clone();
+            ui.horizontal_wrapped(|ui| {
+                for (index, report) in reports.iter().enumerate() {
+                    ui.group(|ui| {
+                        ui.set_min_width(160.0);
+                        ui.vertical(|ui| {
+                            ui.horizontal(|ui| {
+                                ui.strong(&report.title);
+                                if ui.small_button("✖").on_hover_text("Unpin").clicked() {
+                                    action = Some(PinAction::Unpin(index));
+                                }
+                            });
+                            ui.weak(time_range::days_label(report.days));
+                            let value = values.get(index).cloned().flatten();
+                            ui.heading(value.unwrap_or_else(|| "…".to_string()));
+                        });
+                    });
+                }
+            });
+        });
+        action
+    }
+
+    // Compute every card's value without blocking the UI thread
+    fn load(&self, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.values.clone();
+        let reports = self.loaded_for.clone();
+        let custom_metrics = self.loaded_metrics.clone();
+
+        tokio::spawn(async move {
+            let db = match Database::open_snapshot(&database_path).await {
+                Ok(db) => db,
+                Err(e) => {
+                    tracing::warn!("Failed to load pinned reports: {}", e);
+                    return;
+                }
+            };
+
+            let today = Local::now().date_naive();
+            let mut values = Vec::with_capacity(reports.len());
+            for report in &reports {
+                match report_value(&db, report, &custom_metrics, today).await {
+                    Ok(value) => values.push(Some(value)),
+                    Err(e) => {
+                        tracing::warn!("Failed to compute pinned report '{}': {}", report.title, e);
+                        values.push(None);
+                    }
+                }
+            }
+
+            *target.lock().unwrap() = values;
+            ctx.request_repaint();
+        });
+    }
+}
+
+async fn report_value(
+    db: &Database,
+    report: &PinnedReport,
+    custom_metrics: &[CustomMetric],
+    today: NaiveDate,
+) -> Result<String> {
+    let start = time_range::days_start(report.days, today);
+    let end = today + Duration::days(1);
+
+    let value = match report.metric {
+        PinnedMetric::Keystrokes | PinnedMetric::Clicks | PinnedMetric::ActiveTime => {
+            let days = db.get_daily_summaries(start, end).await?;
+            match report.metric {
+                PinnedMetric::Keystrokes => days.iter().map(|day| day.keystrokes).sum::<i64>().to_string(),
+                PinnedMetric::Clicks => days.iter().map(|day| day.clicks).sum::<i64>().to_string(),
+                _ => format_seconds(days.iter().map(|day| day.active_seconds).sum()),
+            }
+        }
+        PinnedMetric::AppTime => {
+            let visits = db.get_window_visits(start, end).await?;
+            let query = ChartQuery {
+                app: report.app.clone(),
+                hour: report.hour,
+            };
+            format_seconds(chart_data::timeline(&visits, &query).iter().map(|(_, seconds)| seconds).sum())
+        }
+        PinnedMetric::DeepWork => {
+            let days = db.get_deep_work_days(start, end).await?;
+            format_seconds(days.iter().map(|day| day.minutes * 60).sum())
+        }
+        PinnedMetric::Custom => {
+            // A metric removed from the config leaves its card empty
+            let Some(metric) = custom_metrics
+                .iter()
+                .find(|metric| Some(&metric.name) == report.custom_metric.as_ref())
+            else {
+                return Ok("—".to_string());
+            };
+            let aggregates = custom_metrics::aggregates(db, start, end).await?;
+            let value = custom_metrics::evaluate(std::slice::from_ref(metric), &aggregates).remove(0);
+            value.error.unwrap_or(value.display)
+        }
+    };
+    Ok(value)
+}
\ No newline at end of file
@@ -0,0 +1,71 @@
+use eframe::egui;
+use selfspy_core::{Progress, ProgressUpdate};
+use std::sync::{Arc, Mutex};
+
+// Shared state of one background operation, drawn as a progress bar with a
+// cancel button while it runs and as a status line afterwards
+#[derive(Clone, Default)]
+pub struct TaskProgress {
+    state: Arc<Mutex<TaskState>>,
+}
+
+#[derive(Default)]
+struct TaskState {
+    running: Option<Progress>,
+    update: Option<ProgressUpdate>,
+    message: Option<String>,
+}
+
+impl TaskProgress {
+    pub fn is_running(&self) -> bool {
+        self.state.lock().unwrap().running.is_some()
+    }
+    
+    // Starts tracking a new operation and returns the handle to pass into core
+    pub fn begin(&self, ctx: egui::Context) -> Progress {
+        let state = self.state.clone();
+        let progress = Progress::new(move |update| {
+            state.lock().unwrap().update = Some(update.clone());
+            ctx.request_repaint();
+        });
+        
+        let mut state = self.state.lock().unwrap();
+        state.running = Some(progress.clone());
+        state.update = None;
+        state.message = None;
+        progress
+    }
+    
+    pub fn finish(&self, message: String) {
+        let mut state = self.state.lock().unwrap();
+        state.running = None;
+        state.message = Some(message);
+    }
+    
+    pub fn show(&self, ui: &mut egui::Ui) {
+        let state = self.state.lock().unwrap();
+        
+        if let Some(progress) = &state.running {
+            ui.horizontal(|ui| {
+                let (label, fraction) = match &state.update {
+                    Some(update) => {
+                        let fraction = update
+                            .total
+                            .filter(|total| *total > 0)
+                            .map(|total| update.done as f32 / total as f32)
+                            .unwrap_or(0.0);
+                        (format!("{} ({})", update.stage, update.done), fraction)
+                    }
+                    None => ("Starting...".to_string(), 0.0),
+                };
+                
+                ui.add(egui::ProgressBar::new(fraction).text(label).desired_width(300.0));
+                if ui.button("Cancel").clicked() {
+                    progress.cancel_token().cancel();
+                }
+            });
+        } else if let Some(message) = &state.message {
+            ui.label(message);
+        }
+    }
+}
\ No newline at end of file
@@ -1,26 +1,46 @@
 use eframe::egui;
-use selfspy_core::Config;
+use selfspy_core::backup;
+use selfspy_core::calendar::FirstDayOfWeek;
+use selfspy_core::encryption::{Zeroize, Zeroizing};
+use selfspy_core::platform::{self, BackendProbe};
+use selfspy_core::{Config, Database, OutlierLimits, PinnedReport};
+
+use crate::progress::TaskProgress;
 
 pub struct Settings {
     config: Config,
     temp_config: Config,
     show_advanced: bool,
-    password_field: String,
-    confirm_password_field: String,
+    // Wiped when cleared or dropped
+    password_field: Zeroizing<String>,
+    confirm_password_field: Zeroizing<String>,
     excluded_apps_text: String,
+    title_patterns_text: String,
+    outlier_cleanup: TaskProgress,
+    backup_task: TaskProgress,
+    // Outcome of the last Save, shown next to the buttons until the next one
+    save_result: Option<Result<(), String>>,
+    // Probed once; re-checked with the button
+    backends: Vec<BackendProbe>,
 }
 
 impl Settings {
     pub fn new(config: Config) -> Self {
         let excluded_apps_text = config.exclude_apps.join("\n");
+        let title_patterns_text = config.volatile_title_patterns.join("\n");
         
         Self {
             temp_config: config.clone(),
             config,
             show_advanced: false,
-            password_field: String::new(),
-            confirm_password_field: String::new(),
+            password_field: Zeroizing::new(String::new()),
+            confirm_password_field: Zeroizing::new(String::new()),
             excluded_apps_text,
+            title_patterns_text,
+            outlier_cleanup: TaskProgress::default(),
+            backup_task: TaskProgress::default(),
+            save_result: None,
+            backends: platform::probe_backends(),
         }
     }
     
@@ -33,6 +53,10 @@ impl Settings {
             self.show_general_settings(ui);
             ui.add_space(20.0);
             
+            // Tracking Backends
+            self.show_tracking_backends(ui);
+            ui.add_space(20.0);
+            
             // Privacy Settings
             self.show_privacy_settings(ui);
             ui.add_space(20.0);
@@ -78,6 +102,31 @@ impl Settings {
                     ui.label("Idle Timeout (seconds):");
                     ui.add(egui::Slider::new(&mut self.temp_config.idle_timeout_seconds, 30..=3600));
                     ui.end_row();
+                    
+                    // Start Hidden
+                    ui.label("Start Hidden:");
+                    ui.checkbox(&mut self.temp_config.start_hidden, "Launch to the system tray only");
+                    ui.end_row();
+                    
+                    // Do Not Disturb
+                    ui.label("Do Not Disturb:");
+                    ui.vertical(|ui| {
+                        ui.checkbox(&mut self.temp_config.respect_do_not_disturb, "Silence notifications while Focus is on");
+                        ui.checkbox(&mut self.temp_config.tag_do_not_disturb_periods, "Record Focus periods in the data");
+                    });
+                    ui.end_row();
+                    
+                    // First Day of Week
+                    ui.label("First Day of Week:");
+                    let first_day = &mut self.temp_config.first_day_of_week;
+                    egui::ComboBox::from_id_source("first_day_of_week")
+                        .selected_text(first_day.label())
+                        .show_ui(ui, |ui| {
+                            for option in FirstDayOfWeek::ALL {
+                                ui.selectable_value(first_day, option, option.label());
+                            }
+                        });
+                    ui.end_row();
                 });
         });
     }
@@ -97,12 +146,12 @@ impl Settings {
                 ui.indent("encryption_settings", |ui| {
                     ui.horizontal(|ui| {
                         ui.label("Password:");
-                        ui.add(egui::TextEdit::singleline(&mut self.password_field).password(true));
+                        ui.add(egui::TextEdit::singleline(&mut *self.password_field).password(true));
                     });
                     
                     ui.horizontal(|ui| {
                         ui.label("Confirm Password:");
-                        ui.add(egui::TextEdit::singleline(&mut self.confirm_password_field).password(true));
+                        ui.add(egui::TextEdit::singleline(&mut *self.confirm_password_field).password(true));
                     });
                     
                     if !self.password_field.is_empty() && !self.confirm_password_field.is_empty() {
@@ -117,6 +166,42 @@ impl Settings {
             
             ui.add_space(10.0);
             
+            // Captured data types
+            ui.label("📋 Data to Capture:");
+            ui.indent("capture_settings", |ui| {
+                let capture = &mut self.temp_config.capture;
+                ui.checkbox(&mut capture.key_counts, "Keystroke counts");
+                ui.add_enabled_ui(capture.key_counts, |ui| {
+                    ui.checkbox(&mut capture.key_text, "Keystroke text");
+                });
+                ui.checkbox(&mut capture.clicks, "Mouse clicks");
+                ui.checkbox(&mut capture.mouse_movement, "Mouse movement");
+                ui.checkbox(&mut capture.scrolls, "Scroll counts");
+                ui.checkbox(&mut capture.window_titles, "Window titles");
+                ui.add_enabled_ui(capture.window_titles, |ui| {
+                    ui.checkbox(&mut capture.documents, "Open document or tab (Windows)");
+                });
+                ui.checkbox(&mut capture.window_geometry, "Window position and size");
+            });
+            
+            ui.add_space(10.0);
+            
+            // Screen sharing
+            ui.checkbox(&mut self.temp_config.track_presenting, "Detect screen sharing and log presenting periods");
+            if self.temp_config.track_presenting {
+                ui.indent("presenting_settings", |ui| {
+                    ui.checkbox(&mut self.temp_config.pause_text_while_presenting, "Pause keystroke text capture while presenting");
+                });
+            }
+            
+            ui.add_space(10.0);
+            
+            ui.checkbox(&mut self.temp_config.track_media, "Record currently playing media (artist and title)");
+            ui.checkbox(&mut self.temp_config.track_meetings, "Log meetings while the microphone or camera is in use");
+            ui.checkbox(&mut self.temp_config.track_power, "Log power source and battery charge");
+            
+            ui.add_space(10.0);
+            
             // Excluded Applications
             ui.label("🚫 Excluded Applications:");
             ui.label("(One application per line)");
@@ -141,6 +226,49 @@ impl Settings {
         });
     }
     
+    fn show_tracking_backends(&mut self, ui: &mut egui::Ui) {
+        ui.group(|ui| {
+            ui.horizontal(|ui| {
+                ui.heading("🖥️ Tracking Backends");
+                ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                    if ui.button("🔄 Check Again").clicked() {
+                        self.backends = platform::probe_backends();
+                    }
+                });
+            });
+            ui.separator();
+            
+            ui.label("The first usable backend records your activity; anything it can't see is missing from reports.");
+            if self.backends.is_empty() {
+                ui.colored_label(egui::Color32::from_rgb(255, 200, 100), "⚠️ No backend on this platform; windows and input aren't recorded");
+            }
+            
+            let active = self.backends.iter().position(|probe| probe.unavailable.is_none());
+            for (index, probe) in self.backends.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    match &probe.unavailable {
+                        None if Some(index) == active => {
+                            ui.colored_label(egui::Color32::from_rgb(100, 255, 100), format!("✅ {}", probe.name));
+                        }
+                        None => {
+                            ui.label(format!("✔ {} (standby)", probe.name));
+                        }
+                        Some(reason) => {
+                            ui.colored_label(egui::Color32::GRAY, format!("✖ {}: {}", probe.name, reason));
+                        }
+                    }
+                });
+                
+                let missing = probe.capabilities.missing();
+                if probe.unavailable.is_none() && !missing.is_empty() {
+                    ui.indent(probe.name, |ui| {
+                        ui.label(format!("Doesn't record {}", missing.join(", ")));
+                    });
+                }
+            }
+        });
+    }
+    
     fn show_data_settings(&mut self, ui: &mut egui::Ui, database_connected: bool) {
         ui.group(|ui| {
             ui.heading("💾 Data Management");
@@ -176,11 +304,19 @@ impl Settings {
                     self.import_data();
                 }
                 
-                if ui.button("🔄 Backup Data").clicked() {
-                    self.backup_data();
+                if ui.add_enabled(!self.backup_task.is_running(), egui::Button::new("🔄 Backup Data")).clicked() {
+                    self.backup_data(ui.ctx());
+                }
+                
+                let cleanup_button = egui::Button::new("🧹 Exclude Impossible Records");
+                if ui.add_enabled(database_connected && !self.outlier_cleanup.is_running(), cleanup_button).clicked() {
+                    self.annotate_outliers(ui.ctx());
                 }
             });
             
+            self.outlier_cleanup.show(ui);
+            self.backup_task.show(ui);
+            
             ui.add_space(10.0);
             
             // Dangerous Actions
@@ -241,6 +377,17 @@ impl Settings {
                 
                 ui.add_space(10.0);
                 
+                // Window title normalization
+                ui.label("Volatile Title Patterns:");
+                ui.label("(Regular expressions stripped before comparing window titles, one per line)");
+                ui.add(
+                    egui::TextEdit::multiline(&mut self.title_patterns_text)
+                        .desired_rows(4)
+                        .code_editor()
+                );
+                
+                ui.add_space(10.0);
+                
                 // System Integration
                 ui.group(|ui| {
                     ui.heading("🖥️ System Integration");
@@ -269,6 +416,16 @@ impl Settings {
                 self.reset_to_defaults();
             }
             
+            match &self.save_result {
+                Some(Ok(())) => {
+                    ui.colored_label(egui::Color32::from_rgb(100, 255, 100), "✓ Saved");
+                }
+                Some(Err(e)) => {
+                    ui.colored_label(egui::Color32::from_rgb(255, 100, 100), format!("⚠ Not saved: {}", e));
+                }
+                None => {}
+            }
+            
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if ui.button("ℹ️ Help").clicked() {
                     self.show_help();
@@ -277,6 +434,12 @@ impl Settings {
         });
     }
     
+    // Pins are edited outside this tab; keep them so saving here doesn't drop them
+    pub fn sync_pinned_reports(&mut self, reports: &[PinnedReport]) {
+        self.config.pinned_reports = reports.to_vec();
+        self.temp_config.pinned_reports = reports.to_vec();
+    }
+    
     fn save_settings(&mut self) {
         // Parse excluded apps from text
         self.temp_config.exclude_apps = self.excluded_apps_text
@@ -285,25 +448,35 @@ impl Settings {
             .filter(|s| !s.is_empty())
             .collect();
         
+        self.temp_config.volatile_title_patterns = self.title_patterns_text
+            .lines()
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        
         // Apply settings
         self.config = self.temp_config.clone();
         
-        // Show success message (would use a toast/notification in real app)
-        println!("Settings saved successfully!");
+        self.save_result = Some(self.config.save().map_err(|e| {
+            tracing::warn!("Failed to save settings: {}", e);
+            e.to_string()
+        }));
     }
     
     fn revert_changes(&mut self) {
         self.temp_config = self.config.clone();
         self.excluded_apps_text = self.config.exclude_apps.join("\n");
-        self.password_field.clear();
-        self.confirm_password_field.clear();
+        self.title_patterns_text = self.config.volatile_title_patterns.join("\n");
+        self.password_field.zeroize();
+        self.confirm_password_field.zeroize();
     }
     
     fn reset_to_defaults(&mut self) {
         self.temp_config = Config::new();
         self.excluded_apps_text = self.temp_config.exclude_apps.join("\n");
-        self.password_field.clear();
-        self.confirm_password_field.clear();
+        self.title_patterns_text = self.temp_config.volatile_title_patterns.join("\n");
+        self.password_field.zeroize();
+        self.confirm_password_field.zeroize();
     }
     
     fn reset_excluded_apps(&mut self) {
@@ -311,7 +484,7 @@ impl Settings {
         self.excluded_apps_text = default_config.exclude_apps.join("\n");
     }
     
-    fn export_data(&self) {
+    pub fn export_data(&self) {
         // File dialog and export logic would go here
         println!("Export data functionality");
     }
@@ -321,9 +494,46 @@ impl Settings {
         println!("Import data functionality");
     }
     
-    fn backup_data(&self) {
-        // Backup creation logic would go here
-        println!("Backup data functionality");
+    // Same as `selfstats fix --detect-outliers --apply`, run in the background
+    fn annotate_outliers(&self, ctx: &egui::Context) {
+        let progress = self.outlier_cleanup.begin(ctx.clone());
+        let task = self.outlier_cleanup.clone();
+        let database_path = self.config.database_path.clone();
+        let database_settings = self.config.database.clone();
+        let limits = OutlierLimits {
+            max_keys_per_hour: self.config.max_keys_per_second as i64 * 3600,
+            max_clicks_per_hour: self.config.max_clicks_per_second as i64 * 3600,
+            ..OutlierLimits::default()
+        };
+        
+        tokio::spawn(async move {
+            let result: selfspy_core::error::Result<u64> = async {
+                let db = Database::connect(&database_path, &database_settings).await?;
+                let outliers = db.find_outliers(&limits).await?;
+                db.annotate_outliers(&outliers, &progress).await
+            }
+            .await;
+            
+            task.finish(match result {
+                Ok(count) => format!("Excluded {} impossible records from statistics", count),
+                Err(e) => format!("Cleanup stopped: {}", e),
+            });
+        });
+    }
+    
+    // Same as `selfspy backup create`: incremental where possible, to the
+    // configured remote if any, rotated to `backup.keep`
+    fn backup_data(&self, ctx: &egui::Context) {
+        self.backup_task.begin(ctx.clone());
+        let task = self.backup_task.clone();
+        let config = self.config.clone();
+        
+        tokio::spawn(async move {
+            task.finish(match backup::create(&config, &config.backup, false).await {
+                Ok(entry) => format!("Backed up to {} ({} pages)", entry.name, entry.pages),
+                Err(e) => format!("Backup failed: {}", e),
+            });
+        });
     }
     
     fn show_help(&self) {
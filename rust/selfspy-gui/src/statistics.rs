@@ -1,43 +1,58 @@
+use chrono::{Local, Timelike, Utc};
 use eframe::egui;
+use selfspy_core::db::local_midnight;
+use selfspy_core::{ActivityStats, Database, StatsFilter};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use crate::time_range::TimeRange;
 
-#[derive(PartialEq)]
-enum StatsPeriod {
-    Today,
-    Week,
-    Month,
-    Year,
-    All,
-}
+// Totals keep growing while the monitor runs
+const REFRESH: Duration = Duration::from_secs(300);
 
 pub struct Statistics {
-    selected_period: StatsPeriod,
     last_refresh: std::time::Instant,
     detailed_view: bool,
+    database_path: PathBuf,
+    // Everything below is for `loaded_range`
+    loaded_range: Option<TimeRange>,
+    overview: Arc<Mutex<Option<Overview>>>,
+    // Active minutes per local hour of the day
+    hourly: Arc<Mutex<[i64; 24]>>,
+}
+
+// Totals for the range, the same number of days just before it (none for
+// all time), and the days they are averaged over
+struct Overview {
+    current: ActivityStats,
+    previous: Option<ActivityStats>,
+    days: i64,
 }
 
 impl Statistics {
-    pub fn new() -> Self {
+    pub fn new(database_path: PathBuf) -> Self {
         Self {
-            selected_period: StatsPeriod::Today,
             last_refresh: std::time::Instant::now(),
             detailed_view: false,
+            database_path,
+            loaded_range: None,
+            overview: Arc::new(Mutex::new(None)),
+            hourly: Arc::new(Mutex::new([0; 24])),
         }
     }
     
-    pub fn show(&mut self, ui: &mut egui::Ui, database_connected: bool) {
-        ui.heading("📈 Activity Statistics");
+    pub fn show(&mut self, ui: &mut egui::Ui, database_connected: bool, range: TimeRange) {
+        if self.loaded_range != Some(range) || self.last_refresh.elapsed() >= REFRESH {
+            self.loaded_range = Some(range);
+            self.last_refresh = std::time::Instant::now();
+            self.load_overview(range, ui.ctx().clone());
+            self.load_hourly(range, ui.ctx().clone());
+        }
+        
+        ui.heading(format!("📈 Activity Statistics · {}", range.label()));
         ui.separator();
         
-        // Period selection
         ui.horizontal(|ui| {
-            ui.label("Time Period:");
-            ui.selectable_value(&mut self.selected_period, StatsPeriod::Today, "Today");
-            ui.selectable_value(&mut self.selected_period, StatsPeriod::Week, "This Week");
-            ui.selectable_value(&mut self.selected_period, StatsPeriod::Month, "This Month");
-            ui.selectable_value(&mut self.selected_period, StatsPeriod::Year, "This Year");
-            ui.selectable_value(&mut self.selected_period, StatsPeriod::All, "All Time");
-            
-            ui.separator();
             ui.checkbox(&mut self.detailed_view, "Detailed View");
         });
         
@@ -66,7 +81,18 @@ impl Statistics {
             ui.heading("📊 Overview");
             ui.separator();
             
-            // Create a grid layout for stats
+            let overview = self.overview.lock().unwrap();
+            let Some(overview) = overview.as_ref() else {
+                ui.label("Loading…");
+                return;
+            };
+            let metrics: [(&str, fn(&ActivityStats) -> i64); 4] = [
+                ("⌨️ Keystrokes", |stats| stats.total_keystrokes),
+                ("🖱️ Mouse Clicks", |stats| stats.total_clicks),
+                ("🪟 Windows", |stats| stats.total_windows),
+                ("📱 Applications", |stats| stats.total_processes),
+            ];
+            
             egui::Grid::new("stats_grid")
                 .num_columns(4)
                 .spacing([20.0, 10.0])
@@ -78,37 +104,59 @@ impl Statistics {
                     ui.strong("Trend");
                     ui.end_row();
                     
-                    // Keystrokes
-                    ui.label("⌨️ Keystrokes");
-                    ui.label(self.format_number(25430));
-                    ui.label(self.format_number(3633));
-                    self.show_trend_indicator(ui, 0.15); // +15%
-                    ui.end_row();
-                    
-                    // Clicks
-                    ui.label("🖱️ Mouse Clicks");
-                    ui.label(self.format_number(8920));
-                    ui.label(self.format_number(1274));
-                    self.show_trend_indicator(ui, -0.05); // -5%
-                    ui.end_row();
-                    
-                    // Windows
-                    ui.label("🪟 Windows");
-                    ui.label(self.format_number(142));
-                    ui.label(self.format_number(20));
-                    self.show_trend_indicator(ui, 0.08); // +8%
-                    ui.end_row();
-                    
-                    // Processes
-                    ui.label("📱 Applications");
-                    ui.label(self.format_number(28));
-                    ui.label(self.format_number(4));
-                    self.show_trend_indicator(ui, 0.03); // +3%
-                    ui.end_row();
+                    for (label, value) in metrics {
+                        let total = value(&overview.current);
+                        ui.label(label);
+                        ui.label(self.format_number(total));
+                        ui.label(self.format_number(total / overview.days.max(1)));
+                        match overview.previous.as_ref().map(value) {
+                            Some(previous) if previous > 0 => {
+                                self.show_trend_indicator(ui, (total - previous) as f32 / previous as f32)
+                            }
+                            _ => {
+                                ui.label("—");
+                            }
+                        }
+                        ui.end_row();
+                    }
                 });
         });
     }
     
+    // Loads the range's totals and those of the period before it
+    fn load_overview(&self, range: TimeRange, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.overview.clone();
+        let today = Local::now().date_naive();
+        let now = Utc::now();
+        
+        tokio::spawn(async move {
+            let overview = async {
+                let db = Database::open_snapshot(&database_path).await?;
+                let Some(days) = range.days() else {
+                    let current = db.get_stats_filtered(&StatsFilter::default()).await?;
+                    let first = db.get_first_day().await?.unwrap_or(today);
+                    let days = (today - first).num_days() + 1;
+                    return Ok(Overview { current, previous: None, days });
+                };
+                
+                let start = local_midnight(range.start(today));
+                let previous_start = local_midnight(range.start(today) - chrono::Duration::days(days));
+                let current = db.get_stats_between(start, now).await?;
+                let previous = db.get_stats_between(previous_start, start).await?;
+                Ok::<_, selfspy_core::SelfspyError>(Overview { current, previous: Some(previous), days })
+            };
+            
+            match overview.await {
+                Ok(overview) => {
+                    *target.lock().unwrap() = Some(overview);
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to load the overview: {}", e),
+            }
+        });
+    }
+    
     fn show_summary_stats(&self, ui: &mut egui::Ui) {
         ui.columns(2, |columns| {
             // Left column - Activity Breakdown
@@ -210,15 +258,17 @@ impl Statistics {
             ui.heading("📊 Activity Patterns");
             ui.separator();
             
-            // Hourly pattern visualization
+            // Hourly pattern visualization, relative to the busiest hour
             ui.label("Hourly Activity Distribution:");
+            let hourly = *self.hourly.lock().unwrap();
+            let peak = hourly.iter().copied().max().unwrap_or(0).max(1);
             let desired_size = egui::vec2(ui.available_width(), 80.0);
             let (rect, _response) = ui.allocate_exact_size(desired_size, egui::Sense::hover());
             
             // Draw hourly activity bars
             let bar_width = rect.width() / 24.0;
             for hour in 0..24 {
-                let activity = self.get_hourly_activity(hour);
+                let activity = hourly[hour] as f32 / peak as f32;
                 let bar_height = rect.height() * activity;
                 let bar_rect = egui::Rect::from_min_size(
                     egui::pos2(rect.min.x + hour as f32 * bar_width, rect.max.y - bar_height),
@@ -341,18 +391,31 @@ impl Statistics {
         });
     }
     
-    fn get_hourly_activity(&self, hour: usize) -> f32 {
-        // Simulated hourly activity pattern
-        match hour {
-            0..=6 => 0.1,
-            7..=8 => 0.3,
-            9..=11 => 0.8,
-            12 => 0.4, // Lunch
-            13..=16 => 0.9,
-            17..=18 => 0.6,
-            19..=21 => 0.5,
-            _ => 0.2,
-        }
+    fn load_hourly(&self, range: TimeRange, ctx: egui::Context) {
+        let database_path = self.database_path.clone();
+        let target = self.hourly.clone();
+        let today = Local::now().date_naive();
+        let start = range.start(today);
+        let end = today + chrono::Duration::days(1);
+        
+        tokio::spawn(async move {
+            let activity = match Database::open_snapshot(&database_path).await {
+                Ok(db) => db.get_hourly_activity(start, end).await,
+                Err(e) => Err(e),
+            };
+            
+            match activity {
+                Ok(activity) => {
+                    let mut hourly = [0; 24];
+                    for hour in activity {
+                        hourly[hour.hour.hour() as usize] += hour.active_minutes;
+                    }
+                    *target.lock().unwrap() = hourly;
+                    ctx.request_repaint();
+                }
+                Err(e) => tracing::warn!("Failed to load hourly activity: {}", e),
+            }
+        });
     }
     
     fn format_number(&self, num: i64) -> String {
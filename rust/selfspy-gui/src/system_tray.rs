@@ -1,6 +1,9 @@
+use eframe::egui;
+use selfspy_core::DailySummary;
+use std::sync::mpsc::Sender;
 use tray_icon::{TrayIcon, TrayIconBuilder, menu::{Menu, MenuItem}};
-use winit::event_loop::EventLoopProxy;
 
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum TrayEvent {
     Show,
     Hide,
@@ -14,23 +17,27 @@ pub struct SystemTray {
 }
 
 impl SystemTray {
-    pub fn new(event_proxy: EventLoopProxy<TrayEvent>) -> Self {
-        let tray_icon = Self::create_tray_icon(event_proxy).ok();
+    pub fn new(event_sender: Sender<TrayEvent>, ctx: egui::Context) -> Self {
+        let tray_icon = Self::create_tray_icon(event_sender, ctx).ok();
         
         Self {
             _tray_icon: tray_icon,
         }
     }
     
-    fn create_tray_icon(event_proxy: EventLoopProxy<TrayEvent>) -> Result<TrayIcon, Box<dyn std::error::Error>> {
+    pub fn is_available(&self) -> bool {
+        self._tray_icon.is_some()
+    }
+    
+    fn create_tray_icon(event_sender: Sender<TrayEvent>, ctx: egui::Context) -> Result<TrayIcon, Box<dyn std::error::Error>> {
         // Create context menu
-        let show_item = MenuItem::new("Show Selfspy", true, None);
-        let hide_item = MenuItem::new("Hide Selfspy", true, None);
+        let show_item = MenuItem::with_id("show", "Show Selfspy", true, None);
+        let hide_item = MenuItem::with_id("hide", "Hide Selfspy", true, None);
         let separator1 = MenuItem::new("", false, None); // Separator
-        let toggle_monitoring = MenuItem::new("Start Monitoring", true, None);
-        let settings_item = MenuItem::new("Settings", true, None);
+        let toggle_monitoring = MenuItem::with_id("toggle_monitoring", "Start Monitoring", true, None);
+        let settings_item = MenuItem::with_id("settings", "Settings", true, None);
         let separator2 = MenuItem::new("", false, None); // Separator
-        let quit_item = MenuItem::new("Quit", true, None);
+        let quit_item = MenuItem::with_id("quit", "Quit", true, None);
         
         let menu = Menu::new();
         menu.append(&show_item)?;
@@ -48,30 +55,24 @@ impl SystemTray {
             .with_icon(Self::create_icon())
             .build()?;
         
-        // Handle menu events
+        // Handle menu events. The main window may be hidden (tray-only mode), so
+        // wake egui up after every event or it would never be processed.
         let menu_channel = tray_icon::menu::MenuEvent::receiver();
         std::thread::spawn(move || {
-            loop {
-                if let Ok(event) = menu_channel.recv() {
-                    match event.id.0.as_str() {
-                        "Show Selfspy" => {
-                            let _ = event_proxy.send_event(TrayEvent::Show);
-                        }
-                        "Hide Selfspy" => {
-                            let _ = event_proxy.send_event(TrayEvent::Hide);
-                        }
-                        "Start Monitoring" | "Stop Monitoring" => {
-                            let _ = event_proxy.send_event(TrayEvent::ToggleMonitoring);
-                        }
-                        "Settings" => {
-                            let _ = event_proxy.send_event(TrayEvent::ShowSettings);
-                        }
-                        "Quit" => {
-                            let _ = event_proxy.send_event(TrayEvent::Quit);
-                        }
-                        _ => {}
-                    }
+            while let Ok(event) = menu_channel.recv() {
+                let tray_event = match event.id.0.as_str() {
+                    "show" => TrayEvent::Show,
+                    "hide" => TrayEvent::Hide,
+                    "toggle_monitoring" => TrayEvent::ToggleMonitoring,
+                    "settings" => TrayEvent::ShowSettings,
+                    "quit" => TrayEvent::Quit,
+                    _ => continue,
+                };
+                
+                if event_sender.send(tray_event).is_err() {
+                    break;
                 }
+                ctx.request_repaint();
             }
         });
         
@@ -143,9 +144,29 @@ impl SystemTray {
         };
     }
     
-    pub fn show_notification(&self, title: &str, message: &str) {
-        // Show system notification
-        // This would use the notification crate or system APIs
-        println!("Notification: {} - {}", title, message);
+    pub fn update_stats(&self, summary: &DailySummary) {
+        let Some(tray_icon) = &self._tray_icon else {
+            return;
+        };
+        
+        let badge = Self::format_badge(summary);
+        let _ = tray_icon.set_tooltip(Some(format!("Selfspy - {}", badge)));
+        
+        // The menu bar has room for a short title next to the icon
+        #[cfg(target_os = "macos")]
+        tray_icon.set_title(Some(badge));
+    }
+    
+    fn format_badge(summary: &DailySummary) -> String {
+        let hours = summary.active_seconds / 3600;
+        let minutes = (summary.active_seconds % 3600) / 60;
+        
+        let keys = if summary.keystrokes >= 1_000 {
+            format!("{:.1}K", summary.keystrokes as f64 / 1_000.0)
+        } else {
+            summary.keystrokes.to_string()
+        };
+        
+        format!("{}h {:02}m · {} keys", hours, minutes, keys)
     }
 }
\ No newline at end of file
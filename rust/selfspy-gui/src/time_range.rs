@@ -0,0 +1,64 @@
+use chrono::{Duration, NaiveDate};
+
+// Period every tab reports on, chosen once in the top panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeRange {
+    Today,
+    Week,
+    Month,
+    Year,
+    All,
+}
+
+impl TimeRange {
+    pub const ALL: [TimeRange; 5] = [
+        TimeRange::Today,
+        TimeRange::Week,
+        TimeRange::Month,
+        TimeRange::Year,
+        TimeRange::All,
+    ];
+    
+    pub fn label(self) -> &'static str {
+        match self {
+            TimeRange::Today => "Today",
+            TimeRange::Week => "This Week",
+            TimeRange::Month => "This Month",
+            TimeRange::Year => "This Year",
+            TimeRange::All => "All Time",
+        }
+    }
+    
+    // Days covered, counting today; None for all time
+    pub fn days(self) -> Option<i64> {
+        match self {
+            TimeRange::Today => Some(1),
+            TimeRange::Week => Some(7),
+            TimeRange::Month => Some(30),
+            TimeRange::Year => Some(365),
+            TimeRange::All => None,
+        }
+    }
+    
+    // First local day in the range ending with `today`
+    pub fn start(self, today: NaiveDate) -> NaiveDate {
+        days_start(self.days(), today)
+    }
+}
+
+// First local day of `days` days ending with `today`; None reaches back to the epoch
+pub fn days_start(days: Option<i64>, today: NaiveDate) -> NaiveDate {
+    match days {
+        Some(days) => today - Duration::days(days - 1),
+        None => NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(),
+    }
+}
+
+// "This Week" for the standard ranges, "Last 14 days" otherwise
+pub fn days_label(days: Option<i64>) -> String {
+    TimeRange::ALL
+        .iter()
+        .find(|range| range.days() == days)
+        .map(|range| range.label().to_string())
+        .unwrap_or_else(|| format!("Last {} days", days.unwrap_or_default()))
+}
@@ -1,5 +1,5 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
 use crossterm::{
     event::{self, Event, KeyCode},
     execute,
@@ -10,17 +10,28 @@ use ratatui::{
     layout::{Alignment, Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, Gauge, Paragraph},
+    widgets::{Block, Borders, Gauge, Paragraph, Sparkline},
     Frame, Terminal,
 };
-use selfspy_core::{init, ActivityMonitor, Config, Database};
-use std::{io, path::PathBuf, time::Duration};
+use selfspy_core::accounts;
+use selfspy_core::alerts::{self, AlertMetric, AlertRule, AlertWindow, Comparison};
+use selfspy_core::backup;
+use selfspy_core::control::{self, ControlAction};
+use selfspy_core::db::{local_midnight, LegacyText};
+use selfspy_core::encryption::{self, KeyStorage, Zeroizing};
+use selfspy_core::export::{self, ExportFormat, KeyText};
+use selfspy_core::files;
+use selfspy_core::monitor;
+use selfspy_core::notifications::NotificationChannel;
+use selfspy_core::{init, ActivityBucket, ActivityMonitor, BucketSize, Config, Database, MonitorStatus, Notifier, Progress};
+use std::{io::{self, Write}, path::{Path, PathBuf}, process::ExitCode, sync::Arc, time::Duration};
 use tokio::time;
-use tracing::info;
+use tracing::{error, info};
 
 #[derive(Parser)]
 #[command(name = "selfspy")]
 #[command(about = "Monitor and analyze your computer activity", version)]
+#[command(after_help = selfspy_core::error::EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -34,7 +45,8 @@ enum Commands {
         #[arg(short, long)]
         data_dir: Option<PathBuf>,
         
-        /// Password for encryption
+        /// Password for encryption; with a sealed key, the recovery password,
+        /// tried when the key can't be unsealed. A wrong one exits with code 13.
         #[arg(short, long)]
         password: Option<String>,
         
@@ -45,15 +57,325 @@ enum Commands {
         /// Show live dashboard
         #[arg(long)]
         dashboard: bool,
+        
+        /// Label for this monitoring session (e.g. "conference day")
+        #[arg(long)]
+        label: Option<String>,
+        
+        /// Serve the pause/resume/annotate control routes on this localhost port
+        #[arg(long)]
+        control_port: Option<u16>,
+    },
+    
+    /// Back up the database, encrypted when age recipients are configured
+    Backup {
+        /// Data directory path
+        #[arg(short, long)]
+        data_dir: Option<PathBuf>,
+        
+        #[command(subcommand)]
+        action: BackupAction,
+    },
+    
+    /// Share this account's daily active time with the computer's administrator
+    ShareScreenTime {
+        /// Data directory path
+        #[arg(short, long)]
+        data_dir: Option<PathBuf>,
+        
+        #[command(subcommand)]
+        action: ShareAction,
+    },
+    
+    /// Manage alert rules checked by the running monitor
+    Alerts {
+        /// Data directory path
+        #[arg(short, long)]
+        data_dir: Option<PathBuf>,
+        
+        #[command(subcommand)]
+        action: AlertAction,
+    },
+    
+    /// Import data from another database
+    Import {
+        /// Data directory path
+        #[arg(short, long)]
+        data_dir: Option<PathBuf>,
+        
+        /// Database of the original Python selfspy (usually ~/.selfspy/selfspy.sqlite).
+        /// Each one can only be imported once
+        #[arg(long)]
+        legacy: PathBuf,
+        
+        /// Only count what would be imported
+        #[arg(long)]
+        dry_run: bool,
+        
+        /// Password for encryption, to carry over typed text from an old
+        /// database recorded without one
+        #[arg(short, long)]
+        password: Option<String>,
+    },
+    
+    /// Write every window, click and key batch with its timestamp
+    Export {
+        /// Data directory path
+        #[arg(short, long)]
+        data_dir: Option<PathBuf>,
+        
+        #[arg(long, value_enum, default_value = "jsonl")]
+        format: ExportFormatArg,
+        
+        /// First local day to export (default: the first recorded)
+        #[arg(long)]
+        from: Option<chrono::NaiveDate>,
+        
+        /// Last local day to export (default: today)
+        #[arg(long)]
+        to: Option<chrono::NaiveDate>,
+        
+        /// Include the typed text of key batches
+        #[arg(long)]
+        text: bool,
+        
+        /// Password the text was encrypted with
+        #[arg(short, long, requires = "text")]
+        password: Option<String>,
+        
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    
+    /// Re-encrypt stored typed text under a new password, deriving the new key
+    /// with the `kdf` cost from config.json, or under a key sealed by the TPM /
+    /// Secure Enclave. Stop the monitor first
+    ChangePassword {
+        /// Data directory path
+        #[arg(short, long)]
+        data_dir: Option<PathBuf>,
+        
+        /// Current password; not needed when the current key is sealed
+        #[arg(long)]
+        old_password: Option<String>,
+        
+        /// New password (default: keep the current one and only apply the new cost)
+        #[arg(long)]
+        new_password: Option<String>,
+        
+        /// Seal a new random key with this machine's secure element instead of a
+        /// password, and set `key_storage` to "platform"
+        #[arg(long, conflicts_with = "new_password")]
+        platform: bool,
+        
+        /// With --platform, also escrow the sealed key under this password, to
+        /// recover the text after a TPM clear or on another machine
+        #[arg(long, requires = "platform")]
+        recovery_password: Option<String>,
+        
+        /// With --platform, seal the key without a recovery password
+        #[arg(long, requires = "platform", conflicts_with = "recovery_password")]
+        no_recovery: bool,
+    },
+    
+    /// Delete events older than the retention period, keeping daily totals
+    Prune {
+        /// Data directory path
+        #[arg(short, long)]
+        data_dir: Option<PathBuf>,
+        
+        /// Keep this many days instead of `retention_days` from the config
+        #[arg(long)]
+        days: Option<i64>,
+        
+        /// Don't keep daily totals of the pruned days
+        #[arg(long)]
+        no_rollups: bool,
+    },
+    
+    /// Report database size and timings, and queries logged as slow
+    Doctor {
+        /// Data directory path
+        #[arg(short, long)]
+        data_dir: Option<PathBuf>,
+        
+        /// Also check the database for corruption, dangling foreign keys and
+        /// orphaned windows, keys and clicks
+        #[arg(long)]
+        db: bool,
+        
+        /// Repair orphans found by --db: windows move to an "(unknown)" process,
+        /// keys and clicks without a window are deleted
+        #[arg(long, requires = "db")]
+        fix: bool,
     },
     
     /// Check macOS permissions
     #[cfg(target_os = "macos")]
     CheckPermissions,
+    
+    /// List keyboards and pointers under /dev/input and whether they can be read
+    #[cfg(target_os = "linux")]
+    CheckInput,
+}
+
+#[derive(Subcommand)]
+enum BackupAction {
+    /// Snapshot the database and store it locally or upload it to the configured remote.
+    /// Only pages changed since the previous backup are stored, except every
+    /// `backup.full_every` backups
+    Create {
+        /// Store a full copy and start a new chain
+        #[arg(long)]
+        full: bool,
+        
+        /// Write a plain, unencrypted copy to this file instead, outside the
+        /// backup chain
+        #[arg(short, long, conflicts_with = "full")]
+        output: Option<PathBuf>,
+    },
+    
+    /// List stored backups, oldest first
+    List,
+    
+    /// Download, decrypt and verify a backup, then replace the database with it.
+    /// Stop the monitor first; the current database is kept as selfspy.db.before-restore-<time>
+    Restore {
+        /// Backup name as shown by `selfspy backup list`; defaults to the newest
+        name: Option<String>,
+        
+        /// Restore a copy made with `selfspy backup create --output` instead
+        #[arg(long, conflicts_with = "name")]
+        file: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand)]
+enum ShareAction {
+    /// Start sharing, after showing what is shared and asking to confirm
+    Grant {
+        /// Confirm without asking (the notice is still shown)
+        #[arg(long)]
+        yes: bool,
+    },
+    
+    /// Stop sharing
+    Revoke,
+    
+    /// Show whether this account shares its screen time
+    Status,
+}
+
+#[derive(Subcommand)]
+enum AlertAction {
+    /// List the configured alert rules
+    List,
+    
+    /// Add a rule, e.g. `--name "Slack morning" --metric active-minutes --process Slack --above 90 --until 12`
+    #[command(group(ArgGroup::new("comparison").required(true).args(["above", "below"])))]
+    Add {
+        #[arg(long)]
+        name: String,
+        
+        #[arg(long, value_enum)]
+        metric: AlertMetricArg,
+        
+        /// Only count this app
+        #[arg(long)]
+        process: Option<String>,
+        
+        /// Fire once the value passes this during the window
+        #[arg(long)]
+        above: Option<f64>,
+        
+        /// Fire when the window closes with the value under this
+        #[arg(long)]
+        below: Option<f64>,
+        
+        /// First local hour of the window
+        #[arg(long, default_value_t = 0)]
+        from: u32,
+        
+        /// Local hour the window ends at (exclusive)
+        #[arg(long, default_value_t = 24)]
+        until: u32,
+        
+        /// Deliver only on this channel instead of every configured one
+        #[arg(long, value_enum)]
+        channel: Option<ChannelArg>,
+    },
+    
+    /// Remove a rule by name
+    Remove {
+        name: String,
+    },
+    
+    /// Evaluate rules against today's data and show whether they would fire
+    Test {
+        /// Only this rule
+        name: Option<String>,
+        
+        /// Also send the notification of every rule that would fire
+        #[arg(long)]
+        send: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum AlertMetricArg {
+    ActiveMinutes,
+    Keystrokes,
+    Clicks,
+    Windows,
+}
+
+impl From<AlertMetricArg> for AlertMetric {
+    fn from(arg: AlertMetricArg) -> Self {
+        match arg {
+            AlertMetricArg::ActiveMinutes => AlertMetric::ActiveMinutes,
+            AlertMetricArg::Keystrokes => AlertMetric::Keystrokes,
+            AlertMetricArg::Clicks => AlertMetric::Clicks,
+            AlertMetricArg::Windows => AlertMetric::Windows,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ChannelArg {
+    Desktop,
+    Webhook,
+    Email,
+}
+
+impl From<ChannelArg> for NotificationChannel {
+    fn from(arg: ChannelArg) -> Self {
+        match arg {
+            ChannelArg::Desktop => NotificationChannel::Desktop,
+            ChannelArg::Webhook => NotificationChannel::Webhook,
+            ChannelArg::Email => NotificationChannel::Email,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum ExportFormatArg {
+    Jsonl,
+    Csv,
+    /// One typed events file; needs --output
+    #[cfg(feature = "parquet")]
+    Parquet,
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => selfspy_core::error::report(&*e),
+    }
+}
+
+async fn run() -> Result<()> {
     init().await?;
     
     let cli = Cli::parse();
@@ -64,32 +386,139 @@ async fn main() -> Result<()> {
             password,
             no_text,
             dashboard,
+            label,
+            control_port,
         } => {
-            let mut config = Config::new();
+            // Saved settings (e.g. control routes, MIDI mappings) apply to the CLI monitor too
+            let mut config = match data_dir {
+                Some(dir) => Config::load_from(dir)?,
+                None => Config::load()?,
+            };
             
-            if let Some(dir) = data_dir {
-                config = config.with_data_dir(dir);
+            if control_port.is_some() {
+                config.control.http_port = control_port;
             }
             
             if no_text {
                 config.encryption_enabled = false;
             }
             
-            let monitor = ActivityMonitor::new(config.clone(), password).await?;
+            let monitor = ActivityMonitor::new(config.clone(), password)
+                .await?
+                .with_session_label(label);
             
             if dashboard {
                 run_with_dashboard(monitor, config).await?;
             } else {
                 info!("Starting Selfspy monitor (press Ctrl+C to stop)...");
                 
+                let monitor = Arc::new(monitor);
+                let runner = monitor.clone();
                 let monitor_handle = tokio::spawn(async move {
-                    monitor.start().await
+                    runner.start().await
                 });
+                spawn_integrations(&monitor, &config);
                 
                 tokio::signal::ctrl_c().await?;
                 info!("Shutting down...");
                 
-                monitor_handle.abort();
+                // The loop flushes and closes the session before returning
+                monitor.stop().await?;
+                monitor_handle.await??;
+            }
+        }
+        
+        Commands::Backup { data_dir, action } => {
+            let config = match data_dir {
+                Some(dir) => Config::load_from(dir)?,
+                None => Config::load()?,
+            };
+            run_backup(&config, action).await?;
+        }
+        
+        Commands::ShareScreenTime { data_dir, action } => {
+            let config = match data_dir {
+                Some(dir) => Config::load_from(dir)?,
+                None => Config::load()?,
+            };
+            share_screen_time(&config, action).await?;
+        }
+        
+        Commands::Alerts { data_dir, action } => {
+            let mut config = match data_dir {
+                Some(dir) => Config::load_from(dir)?,
+                None => Config::load()?,
+            };
+            manage_alerts(&mut config, action).await?;
+        }
+        
+        Commands::Import { data_dir, legacy, dry_run, password } => {
+            let config = match data_dir {
+                Some(dir) => Config::load_from(dir)?,
+                None => Config::load()?,
+            };
+            import_legacy(&config, &legacy, dry_run, password).await?;
+        }
+        
+        Commands::Export { data_dir, format, from, to, text, password, output } => {
+            let config = match data_dir {
+                Some(dir) => Config::load_from(dir)?,
+                None => Config::load()?,
+            };
+            export_events(&config, format, from, to, text, password, output.as_deref()).await?;
+        }
+        
+        Commands::ChangePassword {
+            data_dir,
+            old_password,
+            new_password,
+            platform,
+            recovery_password,
+            no_recovery,
+        } => {
+            let mut config = match data_dir {
+                Some(dir) => Config::load_from(dir)?,
+                None => Config::load()?,
+            };
+            if platform && recovery_password.is_none() && !no_recovery {
+                anyhow::bail!(
+                    "a sealed key is lost with the TPM or Secure Enclave that sealed it; pass --recovery-password \
+                     to escrow it, or --no-recovery to accept that"
+                );
+            }
+            let recovery_password = recovery_password.map(Zeroizing::new);
+            change_password(&mut config, old_password, new_password, platform, recovery_password).await?;
+        }
+        
+        Commands::Prune { data_dir, days, no_rollups } => {
+            let config = match data_dir {
+                Some(dir) => Config::load_from(dir)?,
+                None => Config::load()?,
+            };
+            let Some(days) = days.or(config.retention_days) else {
+                anyhow::bail!("no retention period; pass --days or set retention_days in config.json");
+            };
+            if days < 1 {
+                anyhow::bail!("--days must be at least 1");
+            }
+            
+            let db = Database::connect(&config.database_path, &config.database).await?;
+            let cutoff = chrono::Local::now().date_naive() - chrono::Duration::days(days);
+            let summary = db.prune_before(cutoff, config.keep_daily_rollups && !no_rollups).await?;
+            println!("Deleted {} rows recorded before {}", summary.rows_deleted, summary.cutoff);
+            if summary.days_rolled_up > 0 {
+                println!("Kept daily totals for {} days", summary.days_rolled_up);
+            }
+        }
+        
+        Commands::Doctor { data_dir, db, fix } => {
+            let config = match data_dir {
+                Some(dir) => Config::load_from(dir)?,
+                None => Config::load()?,
+            };
+            run_doctor(&config).await?;
+            if db {
+                check_database(&config, fix).await?;
             }
         }
         
@@ -97,11 +526,686 @@ async fn main() -> Result<()> {
         Commands::CheckPermissions => {
             check_macos_permissions()?;
         }
+        #[cfg(target_os = "linux")]
+        Commands::CheckInput => {
+            check_input_devices();
+        }
+    }
+    
+    Ok(())
+}
+
+async fn run_backup(config: &Config, action: BackupAction) -> Result<()> {
+    let settings = &config.backup;
+    match action {
+        BackupAction::Create { output: Some(output), .. } => {
+            if output.exists() {
+                anyhow::bail!("{} already exists", output.display());
+            }
+            backup::copy_to(config, &output).await?;
+            println!("Copied the database to {}", output.display());
+        }
+        BackupAction::Create { full, output: None } => {
+            let entry = backup::create(config, settings, full).await?;
+            let kind = if entry.parent.is_some() { "incremental" } else { "full" };
+            match &settings.remote {
+                Some(remote) => println!("Uploaded {} backup {} ({} pages) to {}", kind, entry.name, entry.pages, remote),
+                None => println!(
+                    "Stored {} backup {} ({} pages) in {}",
+                    kind,
+                    entry.name,
+                    entry.pages,
+                    backup::BackupSettings::local_dir(config).display()
+                ),
+            }
+        }
+        BackupAction::List => {
+            let entries = backup::list(config, settings)?;
+            if entries.is_empty() {
+                println!("No backups yet; run `selfspy backup create`");
+            }
+            for entry in entries {
+                match &entry.parent {
+                    Some(parent) => println!("{}  {} pages, on top of {}", entry.name, entry.pages, parent),
+                    None => println!("{}  full, {} pages", entry.name, entry.pages),
+                }
+            }
+        }
+        BackupAction::Restore { file: Some(file), .. } => {
+            let path = backup::restore_file(config, &file).await?;
+            println!("Restored {} to {}", file.display(), path.display());
+        }
+        BackupAction::Restore { name, file: None } => {
+            let name = match name {
+                Some(name) => name,
+                None => backup::list(config, settings)?
+                    .pop()
+                    .map(|entry| entry.name)
+                    .ok_or_else(|| anyhow::anyhow!("no backups to restore"))?,
+            };
+            let path = backup::restore(config, settings, &name).await?;
+            println!("Restored {} to {}", name, path.display());
+        }
+    }
+    
+    Ok(())
+}
+
+async fn share_screen_time(config: &Config, action: ShareAction) -> Result<()> {
+    match action {
+        ShareAction::Grant { yes } => {
+            println!("{}\n", accounts::SHARING_NOTICE);
+            if !yes {
+                print!("Share daily active time for {}? [y/N] ", accounts::current_account());
+                io::stdout().flush()?;
+                let mut answer = String::new();
+                io::stdin().read_line(&mut answer)?;
+                if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+                    println!("Nothing shared");
+                    return Ok(());
+                }
+            }
+            let consent = accounts::grant_consent(&config.data_dir)?;
+            // The monitor publishes once a day; the days so far are shared now
+            if config.database_path.exists() {
+                let db = Database::open_read_only(&config.database_path).await?;
+                accounts::publish(&db, &config.data_dir, chrono::Local::now().date_naive()).await?;
+                db.close().await;
+            }
+            println!(
+                "Sharing daily active time for {}; the monitor will remind you while it is on",
+                consent.account
+            );
+        }
+        ShareAction::Revoke => {
+            if accounts::revoke_consent(&config.data_dir)? {
+                println!("Stopped sharing; the administrator's reports no longer include this account");
+            } else {
+                println!("This account wasn't sharing its screen time");
+            }
+        }
+        ShareAction::Status => match accounts::read_consent(&config.data_dir) {
+            Some(consent) => println!(
+                "Daily active time for {} is shared with the administrator since {}",
+                consent.account,
+                consent.granted_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+            ),
+            None => println!("Not shared"),
+        },
+    }
+    
+    Ok(())
+}
+
+async fn import_legacy(config: &Config, legacy: &Path, dry_run: bool, password: Option<String>) -> Result<()> {
+    config.ensure_directories()?;
+    let db = Database::connect(&config.database_path, &config.database).await?;
+    
+    // Typed text is stored the way the monitor would store it
+    let encryptor = match (config.capture.key_text, config.encryption_enabled, password) {
+        (true, true, Some(password)) => Some(encryption::open(&db, &Zeroizing::new(password)).await?),
+        (true, true, None) if config.key_storage == KeyStorage::Platform => Some(encryption::open(&db, "").await?),
+        _ => None,
+    };
+    let text = match (&encryptor, config.capture.key_text, config.encryption_enabled) {
+        (Some(encryptor), _, _) => LegacyText::Encrypt(encryptor),
+        (None, true, false) => LegacyText::Plain,
+        (None, _, _) => LegacyText::Omit,
+    };
+    let text_skipped = config.capture.key_text && matches!(text, LegacyText::Omit);
+    
+    // One status line on stderr, rewritten in place; Ctrl+C rolls the import back
+    let progress = Progress::new(|update| {
+        let total = update.total.map(|total| format!("/{}", total)).unwrap_or_default();
+        eprint!("\r{:<24} {}{}   ", update.stage, update.done, total);
+    });
+    let cancel = progress.cancel_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+    
+    let imported = db.import_legacy(legacy, text, dry_run, &progress).await;
+    if !dry_run {
+        eprintln!();
+    }
+    let imported = imported?;
+    
+    let verb = if dry_run { "Would import" } else { "Imported" };
+    println!(
+        "{} {} processes, {} windows, {} keystrokes in {} batches, {} clicks and {} scrolls from {}",
+        verb,
+        imported.processes,
+        imported.windows,
+        imported.keystrokes,
+        imported.key_batches,
+        imported.clicks,
+        imported.scrolls,
+        legacy.display()
+    );
+    if !dry_run && imported.texts < imported.key_batches {
+        if text_skipped {
+            println!("Typed text was not imported: this database encrypts it, so pass --password");
+        } else if imported.texts == 0 {
+            println!("Typed text from the old database is encrypted with its own password and was not imported");
+        }
+    }
+    Ok(())
+}
+
+async fn export_events(
+    config: &Config,
+    format: ExportFormatArg,
+    from: Option<chrono::NaiveDate>,
+    to: Option<chrono::NaiveDate>,
+    text: bool,
+    password: Option<String>,
+    output: Option<&Path>,
+) -> Result<()> {
+    let db = Database::open_read_only(&config.database_path).await?;
+    let start = match from {
+        Some(day) => local_midnight(day),
+        None => chrono::DateTime::UNIX_EPOCH,
+    };
+    let end = local_midnight(to.unwrap_or_else(|| chrono::Local::now().date_naive()) + chrono::Duration::days(1));
+    if end <= start {
+        anyhow::bail!("--to is before --from");
+    }
+    
+    let encryptor = match (text, config.encryption_enabled, password) {
+        (true, true, Some(password)) => Some(encryption::open(&db, &Zeroizing::new(password)).await?),
+        (true, true, None) if config.key_storage == KeyStorage::Platform => Some(encryption::open(&db, "").await?),
+        (true, true, None) => anyhow::bail!("typed text is encrypted; pass --password to export it"),
+        _ => None,
+    };
+    let key_text = match (&encryptor, text) {
+        (Some(encryptor), _) => KeyText::Decrypt(encryptor),
+        (None, true) => KeyText::Stored,
+        (None, false) => KeyText::Omit,
+    };
+    
+    // Counts go to stderr only when the records aren't going there too, via
+    // a terminal's stdout; Ctrl+C stops the export and removes a partial file
+    let progress = match output {
+        Some(_) => Progress::new(|update| eprint!("\r{:<24} {}   ", update.stage, update.done)),
+        None => Progress::none(),
+    };
+    let cancel = progress.cancel_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+    
+    let format = match format {
+        ExportFormatArg::Jsonl => ExportFormat::Jsonl,
+        ExportFormatArg::Csv => ExportFormat::Csv,
+        #[cfg(feature = "parquet")]
+        ExportFormatArg::Parquet => {
+            let Some(path) = output else {
+                anyhow::bail!("Parquet is binary; pass --output FILE");
+            };
+            let file = io::BufWriter::new(files::create_private(path)?);
+            let summary = export::write_parquet(&db, start..end, key_text, file, &progress).await;
+            eprintln!();
+            let summary = summary.inspect_err(|_| {
+                let _ = std::fs::remove_file(path);
+            })?;
+            eprintln!(
+                "Exported {} windows, {} clicks and {} key batches to {}",
+                summary.windows,
+                summary.clicks,
+                summary.key_batches,
+                path.display()
+            );
+            warn_undecryptable(&summary);
+            return Ok(());
+        }
+    };
+    let summary = match output {
+        Some(path) => {
+            let mut file = io::BufWriter::new(files::create_private(path)?);
+            let summary = export::write_events(&db, start..end, format, key_text, &mut file, &progress).await;
+            drop(file);
+            eprintln!();
+            summary.inspect_err(|_| {
+                let _ = std::fs::remove_file(path);
+            })?
+        }
+        None => {
+            let mut stdout = io::BufWriter::new(io::stdout().lock());
+            export::write_events(&db, start..end, format, key_text, &mut stdout, &progress).await?
+        }
+    };
+    eprintln!(
+        "Exported {} windows, {} clicks and {} key batches",
+        summary.windows, summary.clicks, summary.key_batches
+    );
+    warn_undecryptable(&summary);
+    Ok(())
+}
+
+fn warn_undecryptable(summary: &export::ExportSummary) {
+    if summary.undecryptable > 0 {
+        eprintln!(
+            "{} key batches couldn't be decrypted with this password and were exported without their text",
+            summary.undecryptable
+        );
+    }
+}
+
+// Saves the key storage the new key uses to config.json, so the monitor
+// unlocks it the same way
+async fn change_password(
+    config: &mut Config,
+    old_password: Option<String>,
+    new_password: Option<String>,
+    platform: bool,
+    recovery_password: Option<Zeroizing<String>>,
+) -> Result<()> {
+    let old_password = Zeroizing::new(old_password.unwrap_or_default());
+    let new_password = match new_password {
+        Some(new_password) => Zeroizing::new(new_password),
+        None if platform => Zeroizing::new(String::new()),
+        None if !old_password.is_empty() => old_password.clone(),
+        None => anyhow::bail!("pass --new-password, or --platform to seal the key instead"),
+    };
+    // A running monitor would go on encrypting with the old key
+    let _lock = monitor::lock_data_dir(&config.data_dir)?;
+    let db = Database::connect(&config.database_path, &config.database).await?;
+    
+    // Ctrl+C rolls the change back, leaving the old password in place
+    let progress = Progress::new(|update| {
+        let total = update.total.map(|total| format!("/{}", total)).unwrap_or_default();
+        eprint!("\r{:<24} {}{}   ", update.stage, update.done, total);
+    });
+    let cancel = progress.cancel_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+    
+    let rekey = if platform {
+        let recovery = recovery_password.as_deref().map(|password| (password.as_str(), config.kdf));
+        encryption::seal_key(&db, &old_password, recovery, &progress).await
+    } else {
+        encryption::change_password(&db, &old_password, &new_password, config.kdf, &progress).await
+    };
+    eprintln!();
+    let rekey = rekey?;
+    
+    if platform {
+        config.key_storage = KeyStorage::Platform;
+        println!(
+            "Re-encrypted {} key batches under a key sealed by {}",
+            rekey.reencrypted,
+            selfspy_core::platform::secure_element::BACKEND
+        );
+        if recovery_password.is_some() {
+            println!(
+                "If the TPM is cleared, or on another machine (including a backup restored there), the text can \
+                 only be decrypted with the recovery password: pass it as --old-password to change-password"
+            );
+        } else {
+            println!(
+                "Warning: no recovery password. If the TPM is cleared, or on another machine (including a backup \
+                 restored there), all stored text is unrecoverable"
+            );
+        }
+    } else {
+        config.key_storage = KeyStorage::Password;
+        println!(
+            "Re-encrypted {} key batches (argon2id, {} KiB, {} iterations, {} lanes)",
+            rekey.reencrypted, config.kdf.memory_kib, config.kdf.iterations, config.kdf.parallelism
+        );
+    }
+    config.save()?;
+    if rekey.skipped > 0 {
+        println!(
+            "{} batches couldn't be decrypted with the old key (recorded before keys were stored, or as plain text) and were left as they were",
+            rekey.skipped
+        );
+    }
+    Ok(())
+}
+
+// Rules live in config.json; a running monitor picks up changes when restarted
+async fn manage_alerts(config: &mut Config, action: AlertAction) -> Result<()> {
+    match action {
+        AlertAction::List => {
+            if config.alert_rules.is_empty() {
+                println!("No alert rules; add one with `selfspy alerts add`");
+            }
+            for rule in &config.alert_rules {
+                let channel = rule.channel.map_or("all channels", |channel| channel.as_str());
+                println!("{}: {} ({})", rule.name, rule.describe(), channel);
+            }
+        }
+        AlertAction::Add {
+            name,
+            metric,
+            process,
+            above,
+            below,
+            from,
+            until,
+            channel,
+        } => {
+            if config.alert_rules.iter().any(|rule| rule.name == name) {
+                anyhow::bail!("an alert rule named '{}' already exists", name);
+            }
+            if from >= until || until > 24 {
+                anyhow::bail!("the window must satisfy --from < --until <= 24");
+            }
+            let (comparison, threshold) = match (above, below) {
+                (Some(threshold), _) => (Comparison::Above, threshold),
+                (_, Some(threshold)) => (Comparison::Below, threshold),
+                (None, None) => unreachable!("clap requires --above or --below"),
+            };
+            let rule = AlertRule {
+                name,
+                metric: metric.into(),
+                process,
+                comparison,
+                threshold,
+                window: AlertWindow {
+                    start_hour: from,
+                    end_hour: until,
+                },
+                channel: channel.map(Into::into),
+            };
+            println!("Added {}: {}", rule.name, rule.describe());
+            config.alert_rules.push(rule);
+            config.save()?;
+        }
+        AlertAction::Remove { name } => {
+            let before = config.alert_rules.len();
+            config.alert_rules.retain(|rule| rule.name != name);
+            if config.alert_rules.len() == before {
+                anyhow::bail!("no alert rule named '{}'", name);
+            }
+            config.save()?;
+            println!("Removed {}", name);
+        }
+        AlertAction::Test { name, send } => {
+            let rules: Vec<&AlertRule> = config
+                .alert_rules
+                .iter()
+                .filter(|rule| name.as_ref().is_none_or(|name| &rule.name == name))
+                .collect();
+            if rules.is_empty() {
+                match name {
+                    Some(name) => anyhow::bail!("no alert rule named '{}'", name),
+                    None => println!("No alert rules; add one with `selfspy alerts add`"),
+                }
+                return Ok(());
+            }
+            
+            let db = Database::open_read_only(&config.database_path).await?;
+            let notifier = Notifier::from_settings(&config.notifications, &config.data_dir);
+            let now = chrono::Utc::now();
+            for rule in rules {
+                let check = alerts::check(&db, rule, now).await?;
+                let verdict = if check.fires {
+                    "fires"
+                } else if check.breached {
+                    "breached, decided when the window closes"
+                } else {
+                    "quiet"
+                };
+                println!("{}: {} now {:.1} - {}", rule.name, rule.metric.as_str(), check.value, verdict);
+                if send && check.fires {
+                    notifier.notify(rule.notification(&check));
+                }
+            }
+            notifier.settle().await;
+        }
     }
     
     Ok(())
 }
 
+// Reads the live file rather than a snapshot, so what --fix repairs is
+// what was reported
+async fn check_database(config: &Config, fix: bool) -> Result<()> {
+    println!("\nIntegrity:");
+    let db = Database::open_read_only(&config.database_path).await?;
+    let problems = db.integrity_check().await?;
+    if problems.is_empty() {
+        println!("  ✓ integrity check passed");
+    }
+    for problem in &problems {
+        println!("  ✗ {}", problem);
+    }
+    
+    // Per table and the table it points at
+    let violations = db.foreign_key_violations().await?;
+    let mut dangling: Vec<(String, String, usize)> = Vec::new();
+    for violation in &violations {
+        match dangling
+            .iter_mut()
+            .find(|(table, parent, _)| *table == violation.table && *parent == violation.parent)
+        {
+            Some((_, _, count)) => *count += 1,
+            None => dangling.push((violation.table.clone(), violation.parent.clone(), 1)),
+        }
+    }
+    if dangling.is_empty() {
+        println!("  ✓ no dangling foreign keys");
+    }
+    for (table, parent, count) in &dangling {
+        println!("  ✗ {} {} rows point at missing {} rows", count, table, parent);
+    }
+    
+    let orphans = db.count_orphans().await?;
+    db.close().await;
+    if orphans.total() == 0 {
+        println!("  ✓ no orphaned windows, keys or clicks");
+    } else {
+        println!(
+            "  ✗ orphaned: {} windows without their process, {} key batches and {} clicks without their window",
+            orphans.windows, orphans.keys, orphans.clicks
+        );
+    }
+    
+    let mut repaired = false;
+    if fix && orphans.total() > 0 {
+        let db = Database::connect(&config.database_path, &config.database).await?;
+        let fixed = db.repair_orphans().await?;
+        db.close().await;
+        println!(
+            "  Moved {} windows to the {} process, deleted {} key batches and {} clicks",
+            fixed.windows,
+            selfspy_core::db::UNKNOWN_PROCESS,
+            fixed.keys,
+            fixed.clicks
+        );
+        repaired = true;
+    } else if orphans.total() > 0 {
+        println!("  Run with --fix to repair orphans");
+    }
+    
+    // Orphans are the dangling keys of windows, keys and clicks; others remain
+    let unrepaired = violations
+        .iter()
+        .filter(|violation| !repaired || !["windows", "keys", "clicks"].contains(&violation.table.as_str()))
+        .count();
+    if !problems.is_empty() || unrepaired > 0 || (orphans.total() > 0 && !repaired) {
+        anyhow::bail!("the database has integrity problems");
+    }
+    Ok(())
+}
+
+async fn run_doctor(config: &Config) -> Result<()> {
+    println!("Tracking backends (first usable one is used):");
+    let probes = selfspy_core::platform::probe_backends();
+    if probes.is_empty() {
+        println!("  none on this platform; windows and input aren't recorded");
+    }
+    for probe in probes {
+        match &probe.unavailable {
+            None => {
+                let missing = probe.capabilities.missing();
+                if missing.is_empty() {
+                    println!("  ✓ {}", probe.name);
+                } else {
+                    println!("  ✓ {} (no {})", probe.name, missing.join(", "));
+                }
+            }
+            Some(reason) => println!("  ✗ {}: {}", probe.name, reason),
+        }
+    }
+    println!();
+    
+    let path = &config.database_path;
+    let file_size = |path: &std::path::Path| std::fs::metadata(path).map_or(0, |metadata| metadata.len());
+    println!("Database: {}", path.display());
+    println!(
+        "  {:.1} MB, WAL {:.1} MB",
+        file_size(path) as f64 / 1_048_576.0,
+        file_size(&path.with_extension("db-wal")) as f64 / 1_048_576.0
+    );
+    
+    // The probe reports run on a snapshot like selfstats does, so the timings
+    // match what reports see and the running monitor isn't held up
+    let db = Database::open_snapshot(path)
+        .await?
+        .with_slow_query_threshold(config.slow_query_threshold());
+    let storage = db.storage_info().await?;
+    println!(
+        "  {} pages of {} bytes, {} free",
+        storage.page_count, storage.page_size, storage.freelist_count
+    );
+    println!(
+        "  schema version {} (this build: {})",
+        db.schema_version().await?,
+        selfspy_core::migrations::latest_version()
+    );
+    
+    println!("\nRows:");
+    for (table, rows) in db.table_row_counts().await? {
+        println!("  {:<16} {:>10}", table, rows);
+    }
+    
+    let today = chrono::Local::now().date_naive();
+    let week_ago = today - chrono::Duration::days(7);
+    let now = chrono::Utc::now();
+    db.get_stats().await?;
+    db.get_hourly_activity(week_ago, today).await?;
+    db.get_minute_totals(now - chrono::Duration::days(7)..now).await?;
+    db.get_daily_summaries(week_ago, today).await?;
+    db.get_window_visits(week_ago, today).await?;
+    
+    println!("\nQuery timings (last 7 days of reports):");
+    println!("  {:<24} {:>6} {:>10} {:>10}", "Query", "Calls", "Total", "Max");
+    for stat in db.query_stats() {
+        println!(
+            "  {:<24} {:>6} {:>8}ms {:>8}ms",
+            stat.query,
+            stat.calls,
+            stat.total.as_millis(),
+            stat.max.as_millis()
+        );
+    }
+    db.close().await;
+    
+    let log_path = path.with_file_name(selfspy_core::query_stats::SLOW_LOG_NAME);
+    let slow = selfspy_core::query_stats::read_slow_log(&log_path)?;
+    match config.slow_query_threshold() {
+        Some(threshold) => println!("\nSlow queries (at least {} ms):", threshold.as_millis()),
+        None => println!("\nSlow queries (logging is off, slow_query_ms is 0):"),
+    }
+    if slow.is_empty() {
+        println!("  none logged");
+        return Ok(());
+    }
+    
+    // Per query: how often, the worst time and when it was last slow
+    let mut summary: Vec<(String, usize, u64, chrono::DateTime<chrono::Utc>)> = Vec::new();
+    for entry in slow {
+        match summary.iter_mut().find(|(query, ..)| *query == entry.query) {
+            Some((_, count, max, last)) => {
+                *count += 1;
+                *max = (*max).max(entry.millis);
+                *last = (*last).max(entry.at);
+            }
+            None => summary.push((entry.query, 1, entry.millis, entry.at)),
+        }
+    }
+    summary.sort_by_key(|&(_, count, _, _)| std::cmp::Reverse(count));
+    for (query, count, max, last) in summary {
+        println!(
+            "  {:<24} {:>4}x, up to {} ms, last {}",
+            query,
+            count,
+            max,
+            last.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+        );
+    }
+    
+    Ok(())
+}
+
+// Remote control endpoints, the MQTT publisher and the slash command listener
+// run until the monitor shuts down; a failure to start them is logged rather than stopping the monitor
+fn spawn_integrations(monitor: &Arc<ActivityMonitor>, config: &Config) {
+    if let Some(port) = config.control.http_port {
+        let monitor = monitor.clone();
+        tokio::spawn(async move {
+            if let Err(e) = control::serve(monitor, port).await {
+                error!("Control endpoint stopped: {}", e);
+            }
+        });
+    }
+    
+    if let Some(midi) = config.control.midi.clone() {
+        #[cfg(feature = "midi")]
+        {
+            let monitor = monitor.clone();
+            tokio::spawn(async move {
+                if let Err(e) = control::listen_midi(monitor, midi).await {
+                    error!("MIDI control stopped: {}", e);
+                }
+            });
+        }
+        #[cfg(not(feature = "midi"))]
+        {
+            let _ = midi;
+            tracing::warn!("MIDI control is configured but this build lacks the `midi` feature");
+        }
+    }
+    
+    if let Some(mqtt) = config.mqtt.clone() {
+        #[cfg(feature = "mqtt")]
+        {
+            let monitor = monitor.clone();
+            let config = config.clone();
+            tokio::spawn(async move {
+                if let Err(e) = selfspy_core::mqtt::publish(monitor, config, mqtt).await {
+                    error!("MQTT publisher stopped: {}", e);
+                }
+            });
+        }
+        #[cfg(not(feature = "mqtt"))]
+        {
+            let _ = mqtt;
+            tracing::warn!("MQTT publishing is configured but this build lacks the `mqtt` feature");
+        }
+    }
+    
+    if let Some(slash) = config.slash_commands.clone() {
+        let monitor = monitor.clone();
+        let config = config.clone();
+        tokio::spawn(async move {
+            if let Err(e) = selfspy_core::slash::serve(monitor, config, slash).await {
+                error!("Slash command listener stopped: {}", e);
+            }
+        });
+    }
+}
+
 async fn run_with_dashboard(monitor: ActivityMonitor, config: Config) -> Result<()> {
     enable_raw_mode()?;
     let mut stdout = io::stdout();
@@ -110,11 +1214,15 @@ async fn run_with_dashboard(monitor: ActivityMonitor, config: Config) -> Result<
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     
+    let monitor = Arc::new(monitor);
+    let runner = monitor.clone();
     let monitor_handle = tokio::spawn(async move {
-        monitor.start().await
+        runner.start().await
     });
+    spawn_integrations(&monitor, &config);
     
-    let db = Database::new(&config.database_path).await?;
+    // The monitor owns the schema; the dashboard only reads
+    let db = Database::open_read_only(&config.database_path).await?;
     
     let mut interval = time::interval(Duration::from_secs(1));
     
@@ -124,24 +1232,42 @@ async fn run_with_dashboard(monitor: ActivityMonitor, config: Config) -> Result<
                 if key.code == KeyCode::Char('q') || key.code == KeyCode::Esc {
                     break;
                 }
+                if key.code == KeyCode::Char('p') {
+                    ControlAction::Toggle.apply(&monitor).await?;
+                }
             }
         }
         
         interval.tick().await;
         let stats = db.get_stats().await?;
+        let status = monitor.status().await;
+        let now = chrono::Utc::now();
+        let midnight = chrono::Local::now()
+            .date_naive()
+            .and_hms_opt(0, 0, 0)
+            .and_then(|midnight| midnight.and_local_timezone(chrono::Local).earliest())
+            .map_or(now, |midnight| midnight.with_timezone(&chrono::Utc));
+        let today = db.get_activity_buckets(midnight..now, BucketSize::Hour).await?;
         
-        terminal.draw(|f| draw_dashboard(f, &stats))?;
+        terminal.draw(|f| draw_dashboard(f, &stats, &today, &status))?;
     }
     
-    monitor_handle.abort();
+    monitor.stop().await?;
+    let result = monitor_handle.await;
     
     disable_raw_mode()?;
     execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
     
+    result??;
     Ok(())
 }
 
-fn draw_dashboard(f: &mut Frame, stats: &selfspy_core::models::ActivityStats) {
+fn draw_dashboard(
+    f: &mut Frame,
+    stats: &selfspy_core::models::ActivityStats,
+    today: &[ActivityBucket],
+    status: &MonitorStatus,
+) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
@@ -149,6 +1275,7 @@ fn draw_dashboard(f: &mut Frame, stats: &selfspy_core::models::ActivityStats) {
             Constraint::Length(3),
             Constraint::Length(5),
             Constraint::Length(5),
+            Constraint::Length(5),
             Constraint::Min(0),
         ])
         .split(f.size());
@@ -177,6 +1304,11 @@ fn draw_dashboard(f: &mut Frame, stats: &selfspy_core::models::ActivityStats) {
                 stats.total_clicks.to_string(),
                 Style::default().fg(Color::Green),
             ),
+            Span::raw("  Scrolls: "),
+            Span::styled(
+                stats.total_scrolls.to_string(),
+                Style::default().fg(Color::Green),
+            ),
         ]),
         Line::from(vec![
             Span::raw("Windows: "),
@@ -196,16 +1328,69 @@ fn draw_dashboard(f: &mut Frame, stats: &selfspy_core::models::ActivityStats) {
         .block(Block::default().title("Statistics").borders(Borders::ALL));
     f.render_widget(stats_widget, chunks[1]);
     
+    // Active minutes per hour since midnight
+    let minutes: Vec<u64> = today.iter().map(|hour| (hour.active_seconds / 60) as u64).collect();
+    let active_today: i64 = today.iter().map(|hour| hour.active_seconds).sum();
+    let sparkline = Sparkline::default()
+        .block(
+            Block::default()
+                .title(format!("Today: {}h {:02}m active", active_today / 3600, active_today % 3600 / 60))
+                .borders(Borders::ALL),
+        )
+        .data(&minutes)
+        .max(60)
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(sparkline, chunks[2]);
+    
     // Active Process
+    let mut activity_lines = Vec::new();
     if let Some(process) = &stats.most_active_process {
-        let active = Paragraph::new(vec![
-            Line::from(vec![
-                Span::raw("Most Active: "),
-                Span::styled(process, Style::default().fg(Color::Cyan)),
-            ])
-        ])
-        .block(Block::default().title("Current Activity").borders(Borders::ALL));
-        f.render_widget(active, chunks[2]);
+        activity_lines.push(Line::from(vec![
+            Span::raw("Most Active: "),
+            Span::styled(process, Style::default().fg(Color::Cyan)),
+        ]));
+    }
+    if status.paused {
+        activity_lines.push(Line::from(vec![Span::styled("⏸ Paused", Style::default().fg(Color::Yellow))]));
+    }
+    if status.idle {
+        activity_lines.push(Line::from(vec![Span::styled("💤 Idle", Style::default().fg(Color::DarkGray))]));
+    }
+    if status.in_meeting {
+        activity_lines.push(Line::from(vec![Span::styled("🎙 In a meeting", Style::default().fg(Color::Magenta))]));
+    }
+    if status.shared_with_admin {
+        activity_lines.push(Line::from(vec![Span::styled(
+            "👁 Screen time shared with the administrator",
+            Style::default().fg(Color::Cyan),
+        )]));
+    }
+    if status.low_disk {
+        activity_lines.push(Line::from(vec![Span::styled(
+            "⚠ Low disk space - recording counts only",
+            Style::default().fg(Color::Red),
+        )]));
+    }
+    let missing = status.capabilities.missing();
+    if !missing.is_empty() {
+        activity_lines.push(Line::from(vec![Span::styled(
+            format!("⚠ {} backend: no {}", status.backend, missing.join(", ")),
+            Style::default().fg(Color::Yellow),
+        )]));
+    }
+    if status.input_stalled {
+        activity_lines.push(Line::from(vec![
+            Span::styled(
+                "⚠ No keyboard/mouse input received - check input permissions",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ),
+        ]));
+    }
+    
+    if !activity_lines.is_empty() {
+        let active = Paragraph::new(activity_lines)
+            .block(Block::default().title("Current Activity").borders(Borders::ALL));
+        f.render_widget(active, chunks[3]);
     }
     
     // Help
@@ -215,11 +1400,40 @@ fn draw_dashboard(f: &mut Frame, stats: &selfspy_core::models::ActivityStats) {
             Span::styled("q", Style::default().fg(Color::Red)),
             Span::raw(" or "),
             Span::styled("ESC", Style::default().fg(Color::Red)),
-            Span::raw(" to quit"),
+            Span::raw(" to quit, "),
+            Span::styled("p", Style::default().fg(Color::Yellow)),
+            Span::raw(" to pause/resume"),
         ])
     ])
     .alignment(Alignment::Center);
-    f.render_widget(help, chunks[3]);
+    f.render_widget(help, chunks[4]);
+}
+
+#[cfg(target_os = "linux")]
+fn check_input_devices() {
+    use selfspy_core::platform::input_devices::{self, DeviceAccess};
+    
+    let devices = input_devices::discover();
+    if devices.is_empty() {
+        println!("No keyboards or pointers found under /sys/class/input");
+        return;
+    }
+    
+    for device in devices {
+        let kind = match (device.keyboard, device.pointer) {
+            (true, true) => "keyboard+pointer",
+            (true, false) => "keyboard",
+            _ => "pointer",
+        };
+        let access = input_devices::check_access(&device);
+        let mark = if access == DeviceAccess::Readable { "✓" } else { "✗" };
+        println!("{} {} {} ({})", mark, device.path.display(), device.name, kind);
+        match &access {
+            DeviceAccess::Readable => {}
+            DeviceAccess::Failed(e) => println!("    {}", e),
+            denied => println!("    permission denied: {}", input_devices::advice(denied).unwrap_or_default()),
+        }
+    }
 }
 
 #[cfg(target_os = "macos")]
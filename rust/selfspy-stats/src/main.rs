@@ -1,25 +1,56 @@
 use anyhow::Result;
-use chrono::{DateTime, Duration, Utc};
-use clap::{Parser, ValueEnum};
+use chrono::{DateTime, Duration, DurationRound, Local, NaiveDate, Utc, Weekday};
+use clap::{Parser, Subcommand, ValueEnum};
 use comfy_table::{modifiers::UTF8_ROUND_CORNERS, presets::UTF8_FULL, Table};
-use selfspy_core::{init, Config, Database};
-use std::path::PathBuf;
+use indicatif::{ProgressBar, ProgressStyle};
+use selfspy_core::accounts::{self, AccountStatus};
+use selfspy_core::after_hours;
+use selfspy_core::aggregate;
+use selfspy_core::away;
+use selfspy_core::baseline::{self, ExcludedRange};
+use selfspy_core::calendar;
+use selfspy_core::custom_metrics;
+use selfspy_core::db::local_midnight;
+use selfspy_core::deep_work;
+use selfspy_core::distractions::{self, GatewayTransition};
+use selfspy_core::goals::{self, GoalMetric};
+use selfspy_core::health;
+use selfspy_core::line_protocol;
+use selfspy_core::meetings;
+use selfspy_core::power;
+use selfspy_core::resume_cost;
+use selfspy_core::routines::{self, RoutinePart};
+use selfspy_core::schema;
+use selfspy_core::typing_practice::{self, NgramKind};
+use selfspy_core::{
+    init, Config, DailySummary, Database, InputMixGrouping, Outlier, OutlierLimits, Progress, StatsCache, StatsFilter,
+    TableDay, WindowTagChange, WindowVisit,
+};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
 
 #[derive(Parser)]
 #[command(name = "selfstats")]
 #[command(about = "View activity statistics from Selfspy", version)]
+#[command(after_help = selfspy_core::error::EXIT_CODES_HELP)]
 struct Cli {
+    #[command(subcommand)]
+    command: Option<Commands>,
+    
     /// Data directory path
     #[arg(short, long)]
     data_dir: Option<PathBuf>,
     
-    /// Start date (YYYY-MM-DD)
+    /// First day to include (YYYY-MM-DD)
     #[arg(short, long)]
-    start: Option<String>,
+    start: Option<NaiveDate>,
     
-    /// End date (YYYY-MM-DD)
+    /// Last day to include (YYYY-MM-DD), defaults to today
     #[arg(short, long)]
-    end: Option<String>,
+    end: Option<NaiveDate>,
     
     /// Output format
     #[arg(short, long, value_enum, default_value = "table")]
@@ -28,6 +59,413 @@ struct Cli {
     /// Number of days to show (overrides start/end)
     #[arg(long)]
     days: Option<i64>,
+    
+    /// Only include monitoring sessions started with this label
+    #[arg(long)]
+    label: Option<String>,
+    
+    /// Open the database read-write and upgrade its schema if needed
+    /// (reports otherwise open it read-only)
+    #[arg(long)]
+    allow_migrate: bool,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Find and correct impossible records in historical data
+    Fix {
+        /// Look for statistically impossible keystroke and click records
+        #[arg(long)]
+        detect_outliers: bool,
+        
+        /// What to do with the outliers that were found
+        #[arg(long, value_enum, default_value = "annotate")]
+        action: FixAction,
+        
+        /// Apply the changes (without this flag only the planned diff is shown)
+        #[arg(long)]
+        apply: bool,
+    },
+    
+    /// Re-run the category rules over windows recorded since a date
+    Reclassify {
+        /// First day to re-tag (YYYY-MM-DD)
+        #[arg(long)]
+        from: NaiveDate,
+        
+        /// Last day to re-tag, defaults to today
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        
+        /// Apply the changes (without this flag only the planned diff is shown)
+        #[arg(long)]
+        apply: bool,
+    },
+    
+    /// List recent monitoring sessions and their labels
+    Sessions {
+        /// Number of sessions to show
+        #[arg(long, default_value = "20")]
+        limit: i64,
+    },
+    
+    /// Compare the last 7 days against your baseline
+    Trend {
+        /// Number of weeks before the current one used for the baseline
+        #[arg(long, default_value = "8")]
+        weeks: i64,
+    },
+    
+    /// Manage date ranges excluded from baselines (vacation, illness)
+    Exclude {
+        #[command(subcommand)]
+        action: ExcludeAction,
+    },
+    
+    /// Define weekly goals
+    Goal {
+        #[command(subcommand)]
+        action: GoalAction,
+    },
+    
+    /// Show weekly goal attainment over time and this week's routine changes
+    Review {
+        /// Number of weeks to review, ending with the current week
+        #[arg(long, default_value = "12")]
+        weeks: i64,
+    },
+    
+    /// Show activity outside working hours and on weekends, week by week
+    AfterHours {
+        /// Number of weeks to show, ending with the current week
+        #[arg(long, default_value = "8")]
+        weeks: i64,
+    },
+    
+    /// Split each day's activity into working hours and time off
+    WorkSplit {
+        /// Number of days to show, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Show the mix of typing, clicking and scrolling per app or per hour
+    InputMix {
+        /// Group by app or by hour of day
+        #[arg(long, value_enum, default_value = "app")]
+        by: MixGrouping,
+        
+        /// Number of days to include, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Show backspace/delete presses relative to all keystrokes per app or per hour
+    ErrorRate {
+        /// Group by app or by hour of day
+        #[arg(long, value_enum, default_value = "app")]
+        by: MixGrouping,
+        
+        /// Number of days to include, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Show keystrokes, clicks, windows and active time per process
+    Processes {
+        /// Number of days to include, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+        
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+    
+    /// Show which app switches most often lead into long distracting sessions
+    Distractions {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "30")]
+        days: i64,
+        
+        #[arg(long, default_value = "10")]
+        limit: usize,
+    },
+    
+    /// Estimate how long typing takes to recover after returning from an interruption
+    ResumeCost {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "14")]
+        days: i64,
+    },
+    
+    /// Split time away from the computer into asleep, locked and idle, per day
+    Away {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Show time in meetings (microphone or camera in use) per day
+    Meetings {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Show time on battery, battery drain and typing intensity unplugged vs plugged in, per day
+    Power {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Show keystrokes, clicks and scrolls per keyboard, mouse and trackpad
+    Devices {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Show combined, anonymized activity of the members in the aggregation config
+    Household {
+        /// Number of days to show, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Compare two database files table by table and day by day, e.g. to
+    /// check a backup or merge (limited by --start/--end/--days)
+    Diff {
+        a: PathBuf,
+        b: PathBuf,
+    },
+    
+    /// Show daily screen time of the accounts in the account_reports config
+    /// that chose to share it
+    Accounts {
+        /// Number of days to show, ending yesterday (the last day each account
+        /// has published)
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Show time spent per open document or tab (Windows)
+    Documents {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+        
+        #[arg(long, default_value = "20")]
+        limit: usize,
+    },
+    
+    /// Show listening time per artist and how much was typed while it played
+    Media {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+        
+        #[arg(long, default_value = "15")]
+        limit: usize,
+    },
+    
+    /// Show the most common and slowest bigrams and trigrams, for typing practice
+    /// (needs capture.key_ngrams in the config)
+    Typing {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "30")]
+        days: i64,
+        
+        #[arg(long, default_value = "10")]
+        limit: usize,
+        
+        /// Print the n-grams for a typing trainer instead of the report
+        #[arg(long, value_enum)]
+        export: Option<TypingExport>,
+    },
+    
+    /// Import daily steps and sleep and compare them with computer activity
+    Health {
+        #[command(subcommand)]
+        action: HealthAction,
+    },
+    
+    /// Detect deep-work stretches and show the best deep-work days
+    DeepWork {
+        #[command(subcommand)]
+        action: DeepWorkAction,
+    },
+    
+    /// Export per-minute keystrokes, clicks and active seconds as InfluxDB line protocol
+    Metrics {
+        /// Number of days to backfill, ending now
+        #[arg(long, default_value = "1")]
+        days: i64,
+        
+        /// POST to this http:// write endpoint instead of printing, e.g.
+        /// http://localhost:8428/write or http://localhost:8086/api/v2/write?org=me&bucket=selfspy
+        #[arg(long)]
+        push: Option<String>,
+        
+        /// InfluxDB 2 API token, sent as "Authorization: Token <token>"
+        #[arg(long, env = "SELFSPY_INFLUX_TOKEN")]
+        token: Option<String>,
+        
+        /// After the backfill, keep exporting newly completed minutes every this many seconds
+        #[arg(long)]
+        interval: Option<u64>,
+        
+        /// Measurement name
+        #[arg(long, default_value = line_protocol::DEFAULT_MEASUREMENT)]
+        measurement: String,
+    },
+    
+    /// Print today's totals and the current app as a desktop status bar snippet
+    Widget {
+        /// Status bar the snippet is formatted for
+        #[arg(long, value_enum)]
+        format: WidgetFormat,
+        
+        /// Keep running and print a fresh snippet every this many seconds
+        /// (for waybar/polybar modules that tail a long-running command)
+        #[arg(long)]
+        interval: Option<u64>,
+    },
+    
+    /// Print JSON Schemas for the JSON this tool and exports produce
+    Schema {
+        /// Only print this schema (e.g. activity_stats, timeline_entry)
+        name: Option<String>,
+        
+        /// List the available schema names
+        #[arg(long)]
+        list: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum DeepWorkAction {
+    /// Detect and store deep-work intervals for recent days
+    Detect {
+        /// Number of days to (re)process, ending today
+        #[arg(long, default_value = "7")]
+        days: i64,
+    },
+    
+    /// Leaderboard of the days with the most deep work
+    Top {
+        #[arg(long, default_value = "10")]
+        limit: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum HealthAction {
+    /// Import a CSV (date, steps, sleep_hours) or an Apple Health export.xml
+    Import {
+        file: PathBuf,
+    },
+    
+    /// Show steps and sleep next to activity and typo rate, with correlations
+    Report {
+        /// Number of days to analyze, ending today
+        #[arg(long, default_value = "30")]
+        days: i64,
+    },
+}
+
+#[derive(Subcommand)]
+enum GoalAction {
+    /// Set a weekly target; earlier weeks keep the goal they had
+    Set {
+        #[arg(long, value_enum)]
+        metric: GoalMetricArg,
+        
+        /// Weekly target (minutes for active-minutes)
+        #[arg(long)]
+        target: i64,
+        
+        /// Date the goal takes effect (YYYY-MM-DD), defaults to today
+        #[arg(long)]
+        from: Option<NaiveDate>,
+    },
+    
+    /// List all goal definitions, including superseded ones
+    List,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum GoalMetricArg {
+    ActiveMinutes,
+    Keystrokes,
+    Clicks,
+}
+
+impl From<GoalMetricArg> for GoalMetric {
+    fn from(arg: GoalMetricArg) -> Self {
+        match arg {
+            GoalMetricArg::ActiveMinutes => GoalMetric::ActiveMinutes,
+            GoalMetricArg::Keystrokes => GoalMetric::Keystrokes,
+            GoalMetricArg::Clicks => GoalMetric::Clicks,
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum ExcludeAction {
+    /// Exclude a date range from baselines
+    Add {
+        /// First excluded day (YYYY-MM-DD)
+        #[arg(long)]
+        from: NaiveDate,
+        
+        /// Last excluded day (YYYY-MM-DD), defaults to --from
+        #[arg(long)]
+        to: Option<NaiveDate>,
+        
+        /// Why the range is excluded, e.g. "vacation"
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    
+    /// List excluded date ranges
+    List,
+    
+    /// Stop excluding a date range
+    Remove {
+        /// Index shown by `selfstats exclude list`
+        index: usize,
+    },
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+enum FixAction {
+    /// Keep the records but exclude them from statistics
+    Annotate,
+    /// Delete the records
+    Remove,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum MixGrouping {
+    App,
+    Hour,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TypingExport {
+    /// Practice text of the slowest n-grams, for the custom text mode of
+    /// Monkeytype, keybr and similar trainers
+    Drill,
+    /// Every n-gram with its count and mean transition time
+    Csv,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum WidgetFormat {
+    Waybar,
+    Polybar,
+    Xbar,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -38,21 +476,213 @@ enum OutputFormat {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => selfspy_core::error::report(&*e),
+    }
+}
+
+async fn run() -> Result<()> {
     init().await?;
     
     let cli = Cli::parse();
     
-    let mut config = Config::new();
-    if let Some(dir) = cli.data_dir {
-        config = config.with_data_dir(dir);
+    let config = match cli.data_dir {
+        Some(dir) => Config::load_from(dir)?,
+        None => Config::load()?,
+    };
+    
+    // Editing exclusions only touches config.json and schemas need no data at all
+    let command = match cli.command {
+        Some(Commands::Exclude { action }) => return manage_exclusions(config, action),
+        Some(Commands::Schema { name, list }) => return print_schemas(name.as_deref(), list),
+        // Reads the members' databases, not this user's
+        Some(Commands::Household { days }) => return print_household(&config, days).await,
+        Some(Commands::Accounts { days }) => return print_accounts(&config, days),
+        Some(Commands::Diff { a, b }) => {
+            let (start, end) = match cli.days {
+                Some(days) => (Some(Local::now().date_naive() - Duration::days(days.max(1) - 1)), None),
+                None => (cli.start, cli.end),
+            };
+            return diff_databases(&a, &b, start, end).await;
+        }
+        command => command,
+    };
+    
+    // Only commands that change data get a writable connection
+    let writes = matches!(
+        command,
+        Some(Commands::Fix { apply: true, .. })
+            | Some(Commands::Reclassify { apply: true, .. })
+            | Some(Commands::Goal { action: GoalAction::Set { .. } })
+            | Some(Commands::DeepWork { action: DeepWorkAction::Detect { .. } })
+            | Some(Commands::Health { action: HealthAction::Import { .. } })
+    );
+    // Repeating commands need live data; one-off reports read a snapshot so
+    // they never hold up the monitor's writes
+    let repeats = matches!(
+        command,
+        Some(Commands::Metrics { interval: Some(_), .. }) | Some(Commands::Widget { interval: Some(_), .. })
+    );
+    let db = if writes || cli.allow_migrate {
+        Database::connect(&config.database_path, &config.database).await?
+    } else if repeats {
+        Database::open_read_only(&config.database_path).await?
+    } else {
+        Database::open_snapshot(&config.database_path).await?
+    };
+    // A read-only connection can't upgrade an older schema, and reports
+    // would fail on whatever it lacks
+    if !writes && !cli.allow_migrate {
+        let version = db.schema_version().await?;
+        let latest = selfspy_core::migrations::latest_version();
+        if version < latest {
+            anyhow::bail!(
+                "the database is at schema version {} and this build needs {}; reports open it read-only and \
+                 don't upgrade it, so pass --allow-migrate once (or start the monitor)",
+                version,
+                latest
+            );
+        }
+    }
+    let db = db.with_slow_query_threshold(config.slow_query_threshold());
+    
+    match command {
+        Some(Commands::Fix { detect_outliers, action, apply }) => {
+            if !detect_outliers {
+                println!("Nothing to fix; pass --detect-outliers to scan for impossible records");
+                return Ok(());
+            }
+            return fix_outliers(&db, &config, action, apply).await;
+        }
+        Some(Commands::Reclassify { from, to, apply }) => {
+            return reclassify(&db, &config, from, to, apply).await;
+        }
+        Some(Commands::Sessions { limit }) => {
+            print_sessions(&db, limit).await?;
+            return Ok(());
+        }
+        Some(Commands::Trend { weeks }) => {
+            print_trend(&db, &config, weeks).await?;
+            return Ok(());
+        }
+        Some(Commands::Goal { action }) => {
+            manage_goals(&db, action).await?;
+            return Ok(());
+        }
+        Some(Commands::Review { weeks }) => {
+            print_review(&db, &config, weeks).await?;
+            return Ok(());
+        }
+        Some(Commands::AfterHours { weeks }) => {
+            print_after_hours(&db, &config, weeks).await?;
+            return Ok(());
+        }
+        Some(Commands::WorkSplit { days }) => {
+            print_work_split(&db, &config, days).await?;
+            return Ok(());
+        }
+        Some(Commands::InputMix { by, days }) => {
+            print_input_mix(&db, by, days, &cli.format).await?;
+            return Ok(());
+        }
+        Some(Commands::ErrorRate { by, days }) => {
+            print_error_rates(&db, by, days, &cli.format).await?;
+            return Ok(());
+        }
+        Some(Commands::Processes { days, limit }) => {
+            print_processes(&db, days, limit, &cli.format).await?;
+            return Ok(());
+        }
+        Some(Commands::Distractions { days, limit }) => {
+            print_distractions(&db, &config, days, limit).await?;
+            return Ok(());
+        }
+        Some(Commands::ResumeCost { days }) => {
+            print_resume_cost(&db, days).await?;
+            return Ok(());
+        }
+        Some(Commands::Documents { days, limit }) => {
+            print_documents(&db, days, limit).await?;
+            return Ok(());
+        }
+        Some(Commands::Media { days, limit }) => {
+            print_media(&db, days, limit).await?;
+            return Ok(());
+        }
+        Some(Commands::Typing { days, limit, export }) => {
+            print_typing(&db, days, limit, export).await?;
+            return Ok(());
+        }
+        Some(Commands::Health { action }) => {
+            match action {
+                HealthAction::Import { file } => import_health(&db, &file).await?,
+                HealthAction::Report { days } => print_health_report(&db, days).await?,
+            }
+            return Ok(());
+        }
+        Some(Commands::Away { days }) => {
+            print_away(&db, days).await?;
+            return Ok(());
+        }
+        Some(Commands::Meetings { days }) => {
+            print_meetings(&db, days).await?;
+            return Ok(());
+        }
+        Some(Commands::Power { days }) => {
+            print_power(&db, days).await?;
+            return Ok(());
+        }
+        Some(Commands::Devices { days }) => {
+            print_devices(&db, days).await?;
+            return Ok(());
+        }
+        Some(Commands::DeepWork { action }) => {
+            match action {
+                DeepWorkAction::Detect { days } => detect_deep_work(&db, &config, days).await?,
+                DeepWorkAction::Top { limit } => print_deep_work_leaderboard(&db, limit).await?,
+            }
+            return Ok(());
+        }
+        Some(Commands::Metrics { days, push, token, interval, measurement }) => {
+            export_metrics(&db, days, push.as_deref(), token.as_deref(), interval, &measurement).await?;
+            return Ok(());
+        }
+        Some(Commands::Widget { format, interval }) => {
+            run_widget(db, format, interval).await?;
+            return Ok(());
+        }
+        Some(Commands::Exclude { .. }) | Some(Commands::Schema { .. }) | Some(Commands::Household { .. }) | Some(Commands::Accounts { .. }) | Some(Commands::Diff { .. }) | None => {}
     }
     
-    let db = Database::new(&config.database_path).await?;
-    let stats = db.get_stats().await?;
+    let (start, end) = match cli.days {
+        Some(days) => (Some(Local::now().date_naive() - Duration::days(days.max(1) - 1)), None),
+        None => (cli.start, cli.end),
+    };
+    if let (Some(start), Some(end)) = (start, end) {
+        if end < start {
+            anyhow::bail!("--end ({}) is before --start ({})", end, start);
+        }
+    }
+    let filter = StatsFilter {
+        session_label: cli.label.clone(),
+        start: start.map(local_midnight),
+        end: end.map(|end| local_midnight(end + Duration::days(1))),
+    };
+    let mut stats = db.get_stats_filtered(&filter).await?;
+    // Custom metrics are built from daily totals, which know nothing of sessions
+    if !config.custom_metrics.is_empty() && filter.session_label.is_none() {
+        let today = Local::now().date_naive();
+        if let Some(first) = start.or(db.get_first_day().await?) {
+            let last = end.unwrap_or(today).min(today);
+            let aggregates = custom_metrics::aggregates(&db, first, last + Duration::days(1)).await?;
+            stats.custom_metrics = custom_metrics::evaluate(&config.custom_metrics, &aggregates);
+        }
+    }
     
     match cli.format {
-        OutputFormat::Table => print_table_stats(&stats),
+        OutputFormat::Table => print_table_stats(&stats, &filter),
         OutputFormat::Json => print_json_stats(&stats)?,
         OutputFormat::Csv => print_csv_stats(&stats),
     }
@@ -60,21 +690,1595 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-fn print_table_stats(stats: &selfspy_core::models::ActivityStats) {
+async fn fix_outliers(db: &Database, config: &Config, action: FixAction, apply: bool) -> Result<()> {
+    let limits = OutlierLimits {
+        max_keys_per_hour: config.max_keys_per_second as i64 * 3600,
+        max_clicks_per_hour: config.max_clicks_per_second as i64 * 3600,
+        ..OutlierLimits::default()
+    };
+    
+    let outliers = db.find_outliers(&limits).await?;
+    if outliers.is_empty() {
+        println!("No outliers found");
+        return Ok(());
+    }
+    
+    print_outlier_diff(&outliers, &action);
+    
+    if !apply {
+        println!("Dry run: re-run with --apply to make these changes");
+        return Ok(());
+    }
+    
+    let (progress, bar) = cli_progress();
+    let changed = match action {
+        FixAction::Annotate => db.annotate_outliers(&outliers, &progress).await,
+        FixAction::Remove => db.remove_outliers(&outliers, &progress).await,
+    };
+    bar.finish_and_clear();
+    println!("Updated {} records", changed?);
+    
+    Ok(())
+}
+
+async fn reclassify(db: &Database, config: &Config, from: NaiveDate, to: Option<NaiveDate>, apply: bool) -> Result<()> {
+    let to = to.unwrap_or_else(|| Local::now().date_naive());
+    if to < from {
+        anyhow::bail!("--to ({}) is before --from ({})", to, from);
+    }
+    
+    let range = local_midnight(from)..local_midnight(to + Duration::days(1));
+    let changes = db.find_window_tag_changes(&config.category_rules, range).await?;
+    if changes.is_empty() {
+        println!("Every window from {} to {} already has the tags the current rules give", from, to);
+        return Ok(());
+    }
+    
+    print_tag_diff(&changes);
+    
+    if !apply {
+        println!("Dry run: re-run with --apply to make these changes");
+        return Ok(());
+    }
+    
+    let (progress, bar) = cli_progress();
+    let changed = db.update_window_tags(&changes, &progress).await;
+    bar.finish_and_clear();
+    println!("Re-tagged {} windows", changed?);
+    
+    Ok(())
+}
+
+// Windows listed individually before the diff only shows totals
+const TAG_DIFF_ROWS: usize = 50;
+
+fn print_tag_diff(changes: &[WindowTagChange]) {
+    let tag = |value: &Option<String>| value.clone().unwrap_or_else(|| "-".to_string());
+    let arrow = |old: &Option<String>, new: &Option<String>| {
+        if old == new {
+            tag(new)
+        } else {
+            format!("{} → {}", tag(old), tag(new))
+        }
+    };
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Recorded", "Process", "Title", "Category", "Project"]);
+    
+    for change in changes.iter().take(TAG_DIFF_ROWS) {
+        let mut title: String = change.title.chars().take(40).collect();
+        if title.len() < change.title.len() {
+            title.push('…');
+        }
+        table.add_row(vec![
+            change.created_at.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string(),
+            change.process.clone(),
+            title,
+            arrow(&change.old.category, &change.new.category),
+            arrow(&change.old.project, &change.new.project),
+        ]);
+    }
+    
+    println!("\n{table}");
+    if changes.len() > TAG_DIFF_ROWS {
+        println!("… and {} more", changes.len() - TAG_DIFF_ROWS);
+    }
+    println!();
+    
+    // The same change across many windows is easier to review as one line
+    let mut totals: BTreeMap<(String, String), usize> = BTreeMap::new();
+    for change in changes {
+        let key = (
+            arrow(&change.old.category, &change.new.category),
+            arrow(&change.old.project, &change.new.project),
+        );
+        *totals.entry(key).or_default() += 1;
+    }
+    for ((category, project), count) in totals {
+        println!("  {} windows: category {}, project {}", count, category, project);
+    }
+    println!("Would re-tag {} windows", changes.len());
+}
+
+// Renders core progress updates as a terminal bar; Ctrl+C cancels the operation
+fn cli_progress() -> (Progress, ProgressBar) {
+    let bar = ProgressBar::new(0);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("{msg:<24} [{bar:40.cyan/blue}] {pos}/{len}")
+            .unwrap()
+            .progress_chars("█▓▒░ "),
+    );
+    
+    let reporter = bar.clone();
+    let progress = Progress::new(move |update| {
+        reporter.set_message(update.stage.clone());
+        if let Some(total) = update.total {
+            reporter.set_length(total);
+        }
+        reporter.set_position(update.done);
+    });
+    
+    let cancel = progress.cancel_token();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            cancel.cancel();
+        }
+    });
+    
+    (progress, bar)
+}
+
+fn print_outlier_diff(outliers: &[Outlier], action: &FixAction) {
+    let marker = match action {
+        FixAction::Annotate => "~",
+        FixAction::Remove => "-",
+    };
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["", "Table", "Id", "Recorded", "Reason"]);
+    
+    for outlier in outliers {
+        table.add_row(vec![
+            marker.to_string(),
+            outlier.table.clone(),
+            outlier.id.to_string(),
+            outlier.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            outlier.reason.clone(),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    
+    let keys = outliers.iter().filter(|o| o.table == "keys").count();
+    let clicks = outliers.iter().filter(|o| o.table == "clicks").count();
+    let verb = match action {
+        FixAction::Annotate => "annotate",
+        FixAction::Remove => "remove",
+    };
+    println!("Would {} {} keys records and {} clicks records", verb, keys, clicks);
+}
+
+async fn print_sessions(db: &Database, limit: i64) -> Result<()> {
+    let sessions = db.get_sessions(limit).await?;
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Id", "Label", "Started", "Ended"]);
+    
+    for session in sessions {
+        table.add_row(vec![
+            session.id.to_string(),
+            session.label.unwrap_or_default(),
+            session.started_at.format("%Y-%m-%d %H:%M").to_string(),
+            session
+                .ended_at
+                .map(|ended| ended.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "running".to_string()),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    Ok(())
+}
+
+fn manage_exclusions(mut config: Config, action: ExcludeAction) -> Result<()> {
+    match action {
+        ExcludeAction::Add { from, to, reason } => {
+            let end = to.unwrap_or(from);
+            if end < from {
+                anyhow::bail!("--to ({}) is before --from ({})", end, from);
+            }
+            config.baseline_exclusions.push(ExcludedRange {
+                start: from,
+                end,
+                reason,
+            });
+            config.save()?;
+            println!("Excluded {} to {} from baselines", from, end);
+        }
+        ExcludeAction::List => {
+            if config.baseline_exclusions.is_empty() {
+                println!("No excluded date ranges");
+                return Ok(());
+            }
+            
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_header(vec!["#", "From", "To", "Reason"]);
+            
+            for (index, range) in config.baseline_exclusions.iter().enumerate() {
+                table.add_row(vec![
+                    index.to_string(),
+                    range.start.to_string(),
+                    range.end.to_string(),
+                    range.reason.clone().unwrap_or_default(),
+                ]);
+            }
+            
+            println!("\n{table}\n");
+        }
+        ExcludeAction::Remove { index } => {
+            if index >= config.baseline_exclusions.len() {
+                anyhow::bail!("No excluded range with index {}", index);
+            }
+            let range = config.baseline_exclusions.remove(index);
+            config.save()?;
+            println!("Removed exclusion {} to {}", range.start, range.end);
+        }
+    }
+    
+    Ok(())
+}
+
+async fn print_trend(db: &Database, config: &Config, weeks: i64) -> Result<()> {
+    let today = Local::now().date_naive();
+    let current_start = today - Duration::days(6);
+    let baseline_start = current_start - Duration::weeks(weeks);
+    
+    let current = db.get_daily_summaries(current_start, today + Duration::days(1)).await?;
+    let history = db.get_daily_summaries(baseline_start, current_start).await?;
+    
+    // The current week is compared as-is; only the baseline skips exclusions
+    let current = baseline::compute_baseline(&current, &[]);
+    let baseline = baseline::compute_baseline(&history, &config.baseline_exclusions);
+    
+    if baseline.days == 0 {
+        println!("Not enough history for a baseline in the previous {} weeks", weeks);
+        return Ok(());
+    }
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Daily average", "Last 7 days", "Baseline", "Change"]);
+    
+    let rows = [
+        ("Active minutes", current.avg_active_seconds / 60.0, baseline.avg_active_seconds / 60.0),
+        ("Keystrokes", current.avg_keystrokes, baseline.avg_keystrokes),
+        ("Clicks", current.avg_clicks, baseline.avg_clicks),
+    ];
+    for (metric, now, usual) in rows {
+        let change = baseline::percent_change(now, usual)
+            .map(|pct| format!("{:+.0}%", pct))
+            .unwrap_or_else(|| "-".to_string());
+        table.add_row(vec![
+            metric.to_string(),
+            format!("{:.0}", now),
+            format!("{:.0}", usual),
+            change,
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    println!(
+        "Baseline: {} active days since {}, {} excluded range(s)",
+        baseline.days,
+        baseline_start,
+        config.baseline_exclusions.len()
+    );
+    
+    Ok(())
+}
+
+async fn manage_goals(db: &Database, action: GoalAction) -> Result<()> {
+    match action {
+        GoalAction::Set { metric, target, from } => {
+            let metric = GoalMetric::from(metric);
+            let from = from.unwrap_or_else(|| Local::now().date_naive());
+            db.insert_goal(metric.as_str(), target, from).await?;
+            println!("Weekly {} goal of {} effective from {}", metric.as_str(), target, from);
+        }
+        GoalAction::List => {
+            let goals = db.get_goals().await?;
+            if goals.is_empty() {
+                println!("No goals defined; add one with `selfstats goal set`");
+                return Ok(());
+            }
+            
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_header(vec!["Metric", "Weekly Target", "Effective From"]);
+            
+            for goal in goals {
+                table.add_row(vec![
+                    goal.metric,
+                    goal.weekly_target.to_string(),
+                    goal.effective_from.to_string(),
+                ]);
+            }
+            
+            println!("\n{table}\n");
+        }
+    }
+    
+    Ok(())
+}
+
+async fn print_review(db: &Database, config: &Config, weeks: i64) -> Result<()> {
+    let first_day = config.first_day_of_week.weekday();
+    print_goal_review(db, weeks, first_day).await?;
+    print_routine_review(db, first_day).await?;
+    
+    // This week's worst distraction triggers
+    let current_week = calendar::week_start(Local::now().date_naive(), first_day);
+    let visits = db.get_window_visits(current_week, current_week + Duration::days(7)).await?;
+    let gateways = distractions::gateway_transitions(&visits, &config.distractions);
+    println!("\nDistraction triggers this week");
+    if gateways.is_empty() {
+        println!("  No long distracting sessions this week");
+    } else {
+        print_gateway_table(&gateways[..gateways.len().min(3)]);
+    }
+    
+    Ok(())
+}
+
+async fn print_goal_review(db: &Database, weeks: i64, first_day: Weekday) -> Result<()> {
+    let goals = db.get_goals().await?;
+    if goals.is_empty() {
+        println!("No goals defined; add one with `selfstats goal set`");
+        return Ok(());
+    }
+    
+    let current_week = calendar::week_start(Local::now().date_naive(), first_day);
+    let first_week = current_week - Duration::weeks(weeks.max(1) - 1);
+    let summaries = db
+        .get_daily_summaries(first_week, current_week + Duration::days(7))
+        .await?;
+    let review = goals::weekly_review(&summaries, &goals, first_week, weeks.max(1), first_day);
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Week", "Metric", "Actual", "Target", "Attainment", "Met"]);
+    
+    for week in &review {
+        table.add_row(vec![
+            week_label(week.week_start, first_day),
+            week.metric.as_str().to_string(),
+            week.actual.to_string(),
+            week.target.to_string(),
+            format!("{:.0}%", week.percent()),
+            if week.met() { "yes" } else { "no" }.to_string(),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    
+    let met = review.iter().filter(|week| week.met()).count();
+    println!("Goals met {} of {} times", met, review.len());
+    
+    Ok(())
+}
+
+// Compares this week's first and last half hours against the previous four weeks
+async fn print_routine_review(db: &Database, first_day: Weekday) -> Result<()> {
+    let current_week = calendar::week_start(Local::now().date_naive(), first_day);
+    let baseline_start = current_week - Duration::weeks(4);
+    let visits = db.get_window_visits(baseline_start, current_week + Duration::days(7)).await?;
+    let (baseline, this_week): (Vec<_>, Vec<_>) = routines::day_routines(&visits)
+        .into_iter()
+        .partition(|routine| routine.day < current_week);
+    
+    println!("\nRoutines (first and last {} minutes of the day)", routines::ROUTINE_MINUTES);
+    if baseline.is_empty() {
+        println!("Not enough history yet; routines are compared against the previous four weeks");
+        return Ok(());
+    }
+    
+    let mut flagged = Vec::new();
+    for part in RoutinePart::ALL {
+        let fingerprint = routines::fingerprint(&baseline, part);
+        let typical = if fingerprint.apps.is_empty() {
+            "no consistent pattern".to_string()
+        } else {
+            fingerprint.apps.join(" → ")
+        };
+        println!("  Typical {}: {} ({} days)", part.as_str(), typical, fingerprint.days);
+        flagged.extend(routines::deviations(&this_week, &fingerprint, routines::DEVIATION_THRESHOLD));
+    }
+    
+    if flagged.is_empty() {
+        println!("  No deviations this week");
+        return Ok(());
+    }
+    flagged.sort_by_key(|deviation| (deviation.day, deviation.part.as_str()));
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Day", "Part", "Match", "Missing", "Unusual"]);
+    
+    for deviation in &flagged {
+        table.add_row(vec![
+            deviation.day.format("%a %Y-%m-%d").to_string(),
+            deviation.part.as_str().to_string(),
+            format!("{:.0}%", deviation.similarity * 100.0),
+            deviation.missing.join(", "),
+            deviation.unexpected.join(", "),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    Ok(())
+}
+
+// "2026-W42 (from 2026-10-12)"
+fn week_label(week_start: NaiveDate, first_day: Weekday) -> String {
+    format!("{} (from {})", calendar::week_label(week_start, first_day), week_start)
+}
+
+async fn print_after_hours(db: &Database, config: &Config, weeks: i64) -> Result<()> {
+    let weeks = weeks.max(1);
+    let first_day = config.first_day_of_week.weekday();
+    let current_week = calendar::week_start(Local::now().date_naive(), first_day);
+    let first_week = current_week - Duration::weeks(weeks - 1);
+    
+    let activity = db
+        .get_hourly_activity(first_week, current_week + Duration::days(7))
+        .await?;
+    let totals = after_hours::weekly_after_hours(&activity, &config.working_hours, first_week, weeks, first_day);
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Week", "Evenings (min)", "Weekends (min)", "Total (min)", "Trend"]);
+    
+    let peak = totals.iter().map(|week| week.total_minutes()).max().unwrap_or(0).max(1);
+    for week in &totals {
+        let total = week.total_minutes();
+        let mut trend = "#".repeat((total * 20 / peak) as usize);
+        if config.after_hours_alert_minutes.is_some_and(|limit| total > limit) {
+            trend.push_str(" !");
+        }
+        
+        table.add_row(vec![
+            week_label(week.week_start, first_day),
+            week.evening_minutes.to_string(),
+            week.weekend_minutes.to_string(),
+            total.to_string(),
+            trend,
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    println!("Working hours: {}", config.working_hours.describe());
+    if let Some(limit) = config.after_hours_alert_minutes {
+        println!("Weeks marked ! exceeded the {} minute alert threshold", limit);
+    }
+    
+    Ok(())
+}
+
+async fn print_work_split(db: &Database, config: &Config, days: i64) -> Result<()> {
+    let today = Local::now().date_naive();
+    let first = today - Duration::days(days.max(1) - 1);
+    let minutes = db.get_minute_totals(local_midnight(first)..Utc::now()).await?;
+    let split = after_hours::work_split(&minutes, &config.working_hours, first, today);
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Day", "Hours", "Work Active", "Off Active", "Work Keys", "Off Keys", "Work Clicks", "Off Clicks"]);
+    
+    let mut total = after_hours::WorkSplit::default();
+    for day in &split {
+        let hours = match config.working_hours.hours_on(day.day) {
+            Some((start, end)) => format!("{:02}:00-{:02}:00", start, end),
+            None if config.working_hours.holidays.contains(&day.day) => "holiday".to_string(),
+            None => "day off".to_string(),
+        };
+        table.add_row(vec![
+            day.day.format("%a %Y-%m-%d").to_string(),
+            hours,
+            format_duration_secs(day.work_minutes * 60),
+            format_duration_secs(day.off_minutes * 60),
+            day.work_keystrokes.to_string(),
+            day.off_keystrokes.to_string(),
+            day.work_clicks.to_string(),
+            day.off_clicks.to_string(),
+        ]);
+        
+        total.work_minutes += day.work_minutes;
+        total.off_minutes += day.off_minutes;
+        total.work_keystrokes += day.work_keystrokes;
+        total.off_keystrokes += day.off_keystrokes;
+        total.work_clicks += day.work_clicks;
+        total.off_clicks += day.off_clicks;
+    }
+    
+    println!("\n{table}\n");
+    let active = total.work_minutes + total.off_minutes;
+    if active > 0 {
+        println!(
+            "{:.0}% of active time fell outside working hours ({})",
+            total.off_minutes as f64 / active as f64 * 100.0,
+            config.working_hours.describe()
+        );
+    }
+    
+    Ok(())
+}
+
+async fn print_input_mix(db: &Database, by: MixGrouping, days: i64, format: &OutputFormat) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(days.max(1) - 1);
+    let grouping = match by {
+        MixGrouping::App => InputMixGrouping::App,
+        MixGrouping::Hour => InputMixGrouping::HourOfDay,
+    };
+    let mix = db.get_input_mix(start, today + Duration::days(1), grouping).await?;
+    
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&mix)?),
+        OutputFormat::Csv => {
+            println!("group,keystrokes,clicks,scrolls");
+            for row in &mix {
+                println!("{},{},{},{}", row.group, row.keystrokes, row.clicks, row.scrolls);
+            }
+        }
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_header(vec![
+                    match by {
+                        MixGrouping::App => "App",
+                        MixGrouping::Hour => "Hour",
+                    },
+                    "Typing",
+                    "Clicking",
+                    "Scrolling",
+                    "Keys/Click",
+                ]);
+            
+            for row in &mix {
+                let (typing, clicking, scrolling) = row.shares();
+                let group = match by {
+                    MixGrouping::App => row.group.clone(),
+                    MixGrouping::Hour => format!("{}:00", row.group),
+                };
+                table.add_row(vec![
+                    group,
+                    format!("{:.0}%", typing),
+                    format!("{:.0}%", clicking),
+                    format!("{:.0}%", scrolling),
+                    row.keys_per_click()
+                        .map(|ratio| format!("{:.1}", ratio))
+                        .unwrap_or_else(|| "-".to_string()),
+                ]);
+            }
+            
+            println!("\n{table}\n");
+        }
+    }
+    
+    Ok(())
+}
+
+async fn print_error_rates(db: &Database, by: MixGrouping, days: i64, format: &OutputFormat) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(days.max(1) - 1);
+    let grouping = match by {
+        MixGrouping::App => InputMixGrouping::App,
+        MixGrouping::Hour => InputMixGrouping::HourOfDay,
+    };
+    let rates = db.get_error_rates(start, today + Duration::days(1), grouping).await?;
+    
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&rates)?),
+        OutputFormat::Csv => {
+            println!("group,keystrokes,corrections,rate");
+            for row in &rates {
+                println!("{},{},{},{:.2}", row.group, row.keystrokes, row.corrections, row.rate());
+            }
+        }
+        OutputFormat::Table => {
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_header(vec![
+                    match by {
+                        MixGrouping::App => "App",
+                        MixGrouping::Hour => "Hour",
+                    },
+                    "Keystrokes",
+                    "Corrections",
+                    "Error Rate",
+                ]);
+            
+            for row in &rates {
+                let group = match by {
+                    MixGrouping::App => row.group.clone(),
+                    MixGrouping::Hour => format!("{}:00", row.group),
+                };
+                table.add_row(vec![
+                    group,
+                    row.keystrokes.to_string(),
+                    row.corrections.to_string(),
+                    format!("{:.1}%", row.rate()),
+                ]);
+            }
+            
+            println!("\n{table}\n");
+        }
+    }
+    
+    Ok(())
+}
+
+async fn print_processes(db: &Database, days: i64, limit: usize, format: &OutputFormat) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(days.max(1) - 1);
+    let mut processes = db
+        .get_process_stats(local_midnight(start)..local_midnight(today + Duration::days(1)))
+        .await?;
+    processes.truncate(limit);
+    
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&processes)?),
+        OutputFormat::Csv => {
+            println!("process,active_seconds,keystrokes,clicks,windows");
+            for row in &processes {
+                println!(
+                    "\"{}\",{},{},{},{}",
+                    row.process.replace('"', "\"\""),
+                    row.active_seconds,
+                    row.keystrokes,
+                    row.clicks,
+                    row.windows
+                );
+            }
+        }
+        OutputFormat::Table => {
+            if processes.is_empty() {
+                println!("No activity recorded in the last {} days", days);
+                return Ok(());
+            }
+            
+            let mut table = Table::new();
+            table
+                .load_preset(UTF8_FULL)
+                .apply_modifier(UTF8_ROUND_CORNERS)
+                .set_header(vec!["Process", "Active", "Keystrokes", "Clicks", "Windows"]);
+            for row in &processes {
+                table.add_row(vec![
+                    row.process.clone(),
+                    format_duration_secs(row.active_seconds),
+                    row.keystrokes.to_string(),
+                    row.clicks.to_string(),
+                    row.windows.to_string(),
+                ]);
+            }
+            println!("\n{table}\n");
+        }
+    }
+    
+    Ok(())
+}
+
+async fn print_distractions(db: &Database, config: &Config, days: i64, limit: usize) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(days.max(1) - 1);
+    let visits = db.get_window_visits(start, today + Duration::days(1)).await?;
+    let gateways = distractions::gateway_transitions(&visits, &config.distractions);
+    
+    if gateways.is_empty() {
+        println!(
+            "No distracting sessions of {}+ minutes in the last {} days",
+            config.distractions.min_minutes, days
+        );
+        return Ok(());
+    }
+    
+    println!("Transitions that led into {}+ minute distracting sessions:", config.distractions.min_minutes);
+    print_gateway_table(&gateways[..gateways.len().min(limit)]);
+    Ok(())
+}
+
+fn print_gateway_table(gateways: &[GatewayTransition]) {
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Transition", "Spirals", "Time Lost", "Times Seen", "Spiral Rate"]);
+    
+    for gateway in gateways {
+        table.add_row(vec![
+            format!("{} → {}", gateway.from, gateway.to),
+            gateway.sessions.to_string(),
+            format!("{}h {:02}m", gateway.session_minutes / 60, gateway.session_minutes % 60),
+            gateway.occurrences.to_string(),
+            format!("{:.0}%", gateway.spiral_rate()),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+}
+
+async fn print_resume_cost(db: &Database, days: i64) -> Result<()> {
+    let today = Local::now().date_naive();
+    let mut estimates = Vec::new();
+    
+    for offset in (0..days.max(1)).rev() {
+        let day = today - Duration::days(offset);
+        let keys = db.get_key_activity(day).await?;
+        let visits = db.get_window_visits(day, day + Duration::days(1)).await?;
+        estimates.extend(resume_cost::estimate_day(day, &visits, &keys));
+    }
+    
+    if estimates.is_empty() {
+        println!("No typing recorded in the last {} days", days);
+        return Ok(());
+    }
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Day", "Primary App", "Interruptions", "Resumed", "Avg Resume", "Total Cost"]);
+    
+    for estimate in &estimates {
+        table.add_row(vec![
+            estimate.day.format("%a %Y-%m-%d").to_string(),
+            estimate.primary_app.clone(),
+            estimate.interruptions.to_string(),
+            estimate.resumed.to_string(),
+            format_duration_secs(estimate.average_resume_seconds()),
+            format_duration_secs(estimate.total_resume_seconds),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    
+    let resumed: usize = estimates.iter().map(|estimate| estimate.resumed).sum();
+    let total: i64 = estimates.iter().map(|estimate| estimate.total_resume_seconds).sum();
+    if resumed > 0 {
+        println!(
+            "Average interruption cost: {} over {} returns",
+            format_duration_secs(total / resumed as i64),
+            resumed
+        );
+    }
+    
+    Ok(())
+}
+
+async fn print_documents(db: &Database, days: i64, limit: usize) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(days.max(1) - 1);
+    let visits = db.get_window_visits(start, today + Duration::days(1)).await?;
+    
+    let mut totals: HashMap<(&str, &str), i64> = HashMap::new();
+    for (index, visit) in visits.iter().enumerate() {
+        if let Some(document) = &visit.document {
+            *totals.entry((document.as_str(), visit.process.as_str())).or_default() += distractions::visit_seconds(&visits, index);
+        }
+    }
+    if totals.is_empty() {
+        println!("No documents recorded in the last {} days; they are read on Windows from editors and browsers", days);
+        return Ok(());
+    }
+    
+    let mut ranked: Vec<_> = totals.into_iter().collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Document", "App", "Time"]);
+    for ((document, process), seconds) in ranked.iter().take(limit) {
+        table.add_row(vec![document.to_string(), process.to_string(), format_duration_secs(*seconds)]);
+    }
+    
+    println!("\n{table}\n");
+    Ok(())
+}
+
+async fn diff_databases(a: &Path, b: &Path, start: Option<NaiveDate>, end: Option<NaiveDate>) -> Result<()> {
+    let in_range = |day: &TableDay| start.is_none_or(|start| day.day >= start) && end.is_none_or(|end| day.day <= end);
+    let load = |path: &Path| {
+        let path = path.to_path_buf();
+        async move {
+            let db = Database::open_read_only(&path).await?;
+            let days: HashMap<(String, NaiveDate), TableDay> = db
+                .get_table_days()
+                .await?
+                .into_iter()
+                .filter(in_range)
+                .map(|day| ((day.table.clone(), day.day), day))
+                .collect();
+            anyhow::Ok(days)
+        }
+    };
+    let (days_a, days_b) = (load(a).await?, load(b).await?);
+    
+    let mut keys: Vec<&(String, NaiveDate)> = days_a.keys().chain(days_b.keys()).collect();
+    keys.sort_by(|x, y| x.1.cmp(&y.1).then(x.0.cmp(&y.0)));
+    keys.dedup();
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Day", "Table", "Rows in A", "Rows in B", "Difference"]);
+    let mut differing = 0;
+    for key in &keys {
+        let (row_a, row_b) = (days_a.get(*key), days_b.get(*key));
+        let difference = match (row_a, row_b) {
+            (Some(x), Some(y)) if x.rows == y.rows && x.checksum == y.checksum => continue,
+            (Some(x), Some(y)) if x.rows == y.rows => "same count, contents differ".to_string(),
+            (Some(x), Some(y)) if x.rows > y.rows => format!("{} missing from B", x.rows - y.rows),
+            (Some(x), Some(y)) => format!("{} missing from A", y.rows - x.rows),
+            (Some(_), None) => "missing from B".to_string(),
+            (None, _) => "missing from A".to_string(),
+        };
+        differing += 1;
+        let rows = |row: Option<&TableDay>| row.map_or("-".to_string(), |row| row.rows.to_string());
+        table.add_row(vec![key.1.to_string(), key.0.clone(), rows(row_a), rows(row_b), difference]);
+    }
+    
+    if differing == 0 {
+        println!("{} and {} match: {} table-days compared", a.display(), b.display(), keys.len());
+        return Ok(());
+    }
+    println!("A: {}\nB: {}", a.display(), b.display());
+    println!("\n{table}\n");
+    
+    // Days with data on one side only usually mean a sync or backup stopped
+    let days_of = |days: &HashMap<(String, NaiveDate), TableDay>| -> BTreeSet<NaiveDate> {
+        days.keys().map(|(_, day)| *day).collect()
+    };
+    let (all_a, all_b) = (days_of(&days_a), days_of(&days_b));
+    for (side, missing) in [("B", all_a.difference(&all_b)), ("A", all_b.difference(&all_a))] {
+        let missing: Vec<String> = missing.map(|day| day.to_string()).collect();
+        if !missing.is_empty() {
+            println!("Days missing entirely from {}: {}", side, missing.join(", "));
+        }
+    }
+    
+    anyhow::bail!("{} of {} table-days differ", differing, keys.len())
+}
+
+async fn print_household(config: &Config, days: i64) -> Result<()> {
+    let settings = &config.aggregation;
+    if settings.members.len() < settings.min_members() {
+        println!(
+            "Aggregation needs at least {} member data directories; add them under aggregation.members in the config, \
+             and have each member set aggregation.publish in theirs",
+            settings.min_members()
+        );
+        return Ok(());
+    }
+    
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(days.max(1) - 1);
+    let aggregate = aggregate::aggregate(settings, start, today + Duration::days(1)).await?;
+    if aggregate.is_empty() {
+        println!("No day in the last {} days had at least {} active members", days, settings.min_members());
+        return Ok(());
+    }
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Day", "Members", "Active", "By Category"]);
+    for day in &aggregate {
+        let categories: Vec<String> = day
+            .categories
+            .iter()
+            .map(|category| format!("{} {}", category.category, format_duration_secs(category.seconds)))
+            .collect();
+        table.add_row(vec![
+            day.date.format("%a %Y-%m-%d").to_string(),
+            day.members.to_string(),
+            format_duration_secs(day.active_seconds),
+            categories.join("\n"),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    println!(
+        "Totals are rounded to {} minutes; days and categories with fewer than {} members are withheld",
+        settings.rounding_minutes.max(1),
+        settings.min_members()
+    );
+    Ok(())
+}
+
+fn print_accounts(config: &Config, days: i64) -> Result<()> {
+    if config.account_reports.accounts.is_empty() {
+        println!("No accounts configured; add names and data directories under account_reports.accounts in the config");
+        return Ok(());
+    }
+    
+    let days = days.max(1);
+    let today = Local::now().date_naive();
+    let yesterday = today - Duration::days(1);
+    let reports = accounts::screen_time(&config.account_reports, today - Duration::days(days), today);
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Account", "Sharing", "Yesterday", "Total", "Daily Average", "Longest Day"]);
+    for report in &reports {
+        let sharing = match &report.status {
+            AccountStatus::Shared(consent) => format!("since {}", consent.granted_at.with_timezone(&Local).format("%Y-%m-%d")),
+            AccountStatus::NotShared => {
+                table.add_row(vec![report.name.clone(), "not shared".to_string()]);
+                continue;
+            }
+            AccountStatus::NotPublished(_) => {
+                table.add_row(vec![report.name.clone(), "nothing published yet".to_string()]);
+                continue;
+            }
+            AccountStatus::Unreadable(e) => {
+                table.add_row(vec![report.name.clone(), format!("unreadable: {}", e)]);
+                continue;
+            }
+        };
+        
+        let total: i64 = report.days.iter().map(|day| day.active_seconds).sum();
+        let yesterday_seconds =
+            report.days.iter().find(|day| day.date == yesterday).map_or(0, |day| day.active_seconds);
+        let longest = report.days.iter().max_by_key(|day| day.active_seconds);
+        table.add_row(vec![
+            report.name.clone(),
+            sharing,
+            format_duration_secs(yesterday_seconds),
+            format_duration_secs(total),
+            format_duration_secs(total / days),
+            longest.map_or("-".to_string(), |day| {
+                format!("{} ({})", format_duration_secs(day.active_seconds), day.date.format("%a %m-%d"))
+            }),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    println!(
+        "Accounts appear only after their user runs `selfspy share-screen-time grant`; \
+         each account's monitor publishes its days once a day"
+    );
+    Ok(())
+}
+
+async fn print_media(db: &Database, days: i64, limit: usize) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(days.max(1) - 1);
+    let listening = db.get_media_listening(start, today + Duration::days(1)).await?;
+    if listening.is_empty() {
+        println!("No media recorded in the last {} days; enable track_media in the config", days);
+        return Ok(());
+    }
+    
+    let per_minute = |keystrokes: i64, seconds: i64| {
+        if seconds > 0 {
+            format!("{:.1}", keystrokes as f64 / (seconds as f64 / 60.0))
+        } else {
+            "—".to_string()
+        }
+    };
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Artist / Player", "Listening", "Keystrokes", "Keys/min"]);
+    for entry in listening.iter().take(limit) {
+        table.add_row(vec![
+            entry.label.clone(),
+            format_duration_secs(entry.seconds),
+            entry.keystrokes.to_string(),
+            per_minute(entry.keystrokes, entry.seconds),
+        ]);
+    }
+    println!("\n{table}\n");
+    
+    // Typing rate over all active time, as the baseline to compare against
+    let (mut keystrokes, mut active_seconds) = (0, 0);
+    let mut day = start;
+    while day <= today {
+        let summary = db.get_daily_summary(day).await?;
+        keystrokes += summary.keystrokes;
+        active_seconds += summary.active_seconds;
+        day += Duration::days(1);
+    }
+    let listened: i64 = listening.iter().map(|entry| entry.seconds).sum();
+    let typed: i64 = listening.iter().map(|entry| entry.keystrokes).sum();
+    println!(
+        "Listening: {} at {} keys/min; all active time: {} at {} keys/min",
+        format_duration_secs(listened),
+        per_minute(typed, listened),
+        format_duration_secs(active_seconds),
+        per_minute(keystrokes, active_seconds)
+    );
+    
+    Ok(())
+}
+
+async fn print_typing(db: &Database, days: i64, limit: usize, export: Option<TypingExport>) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(days.max(1) - 1);
+    let mut timings = db.get_ngram_timings(start, today + Duration::days(1)).await?;
+    if timings.is_empty() {
+        println!("No bigrams recorded in the last {} days; enable capture.key_ngrams in the config", days);
+        return Ok(());
+    }
+    
+    match export {
+        Some(TypingExport::Drill) => {
+            let mut slow = typing_practice::slowest(&timings, NgramKind::Bigram, limit);
+            slow.extend(typing_practice::slowest(&timings, NgramKind::Trigram, limit));
+            if slow.is_empty() {
+                anyhow::bail!(
+                    "No n-gram has been typed {} times yet; try more --days",
+                    typing_practice::MIN_SLOW_SAMPLES
+                );
+            }
+            println!("{}", typing_practice::drill_text(&slow));
+        }
+        Some(TypingExport::Csv) => {
+            timings.sort_by(|a, b| b.count.cmp(&a.count).then(a.ngram.cmp(&b.ngram)));
+            println!("ngram,count,mean_ms");
+            for timing in &timings {
+                // Commas and quotes are n-grams too
+                println!("\"{}\",{},{:.0}", timing.ngram.replace('"', "\"\""), timing.count, timing.mean_ms());
+            }
+        }
+        None => {
+            for (title, kind) in [("Bigrams", NgramKind::Bigram), ("Trigrams", NgramKind::Trigram)] {
+                let mut table = Table::new();
+                table
+                    .load_preset(UTF8_FULL)
+                    .apply_modifier(UTF8_ROUND_CORNERS)
+                    .set_header(vec![title, "Typed", "Mean", "Slowest", "Typed", "Mean"]);
+                let common = typing_practice::most_common(&timings, kind, limit);
+                let slow = typing_practice::slowest(&timings, kind, limit);
+                for row in 0..common.len().max(slow.len()) {
+                    let mut cells = Vec::with_capacity(6);
+                    for entry in [common.get(row), slow.get(row)] {
+                        match entry {
+                            Some(timing) => cells.extend([
+                                timing.ngram.clone(),
+                                timing.count.to_string(),
+                                format!("{:.0} ms", timing.mean_ms()),
+                            ]),
+                            None => cells.extend([String::new(), String::new(), String::new()]),
+                        }
+                    }
+                    table.add_row(cells);
+                }
+                println!("\n{table}");
+            }
+            println!(
+                "\nSlowest lists only n-grams typed at least {} times. Practise them with --export drill.\n",
+                typing_practice::MIN_SLOW_SAMPLES
+            );
+        }
+    }
+    
+    Ok(())
+}
+
+async fn import_health(db: &Database, file: &std::path::Path) -> Result<()> {
+    let reader = std::io::BufReader::new(std::fs::File::open(file)?);
+    let days = health::parse(reader)?;
+    if days.is_empty() {
+        println!("No steps or sleep found in {}", file.display());
+        return Ok(());
+    }
+    
+    db.upsert_health_days(&days).await?;
+    println!(
+        "Imported {} days ({} to {})",
+        days.len(),
+        days[0].day,
+        days[days.len() - 1].day
+    );
+    Ok(())
+}
+
+async fn print_health_report(db: &Database, days: i64) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start = today - Duration::days(days.max(1) - 1);
+    let health_days = db.get_health_days(start, today + Duration::days(1)).await?;
+    if health_days.is_empty() {
+        println!("No health data for the last {} days; run `selfstats health import <file>` first", days);
+        return Ok(());
+    }
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Day", "Steps", "Sleep", "Active", "Keystrokes", "Typo Rate"]);
+    
+    // (sleep hours, typo rate), (sleep hours, active hours), (steps, active hours)
+    let mut sleep_typos = Vec::new();
+    let mut sleep_active = Vec::new();
+    let mut steps_active = Vec::new();
+    
+    for day in &health_days {
+        let summary = db.get_daily_summary(day.day).await?;
+        let rates = db
+            .get_error_rates(day.day, day.day + Duration::days(1), InputMixGrouping::App)
+            .await?;
+        let keystrokes: i64 = rates.iter().map(|rate| rate.keystrokes).sum();
+        let corrections: i64 = rates.iter().map(|rate| rate.corrections).sum();
+        let typo_rate = (keystrokes > 0).then(|| corrections as f64 / keystrokes as f64 * 100.0);
+        let active_hours = summary.active_seconds as f64 / 3600.0;
+        
+        if let Some(minutes) = day.sleep_minutes {
+            let sleep_hours = minutes as f64 / 60.0;
+            if let Some(rate) = typo_rate {
+                sleep_typos.push((sleep_hours, rate));
+            }
+            if summary.active_seconds > 0 {
+                sleep_active.push((sleep_hours, active_hours));
+            }
+        }
+        if let Some(steps) = day.steps.filter(|_| summary.active_seconds > 0) {
+            steps_active.push((steps as f64, active_hours));
+        }
+        
+        table.add_row(vec![
+            day.day.format("%a %Y-%m-%d").to_string(),
+            day.steps.map_or("—".to_string(), |steps| steps.to_string()),
+            day.sleep_minutes
+                .map_or("—".to_string(), |minutes| format_duration_secs(minutes * 60)),
+            format_duration_secs(summary.active_seconds),
+            summary.keystrokes.to_string(),
+            typo_rate.map_or("—".to_string(), |rate| format!("{:.1}%", rate)),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    
+    for (label, pairs) in [
+        ("Sleep vs typo rate", &sleep_typos),
+        ("Sleep vs active time", &sleep_active),
+        ("Steps vs active time", &steps_active),
+    ] {
+        match health::correlation(pairs) {
+            Some(r) => println!("{:<22} r = {:+.2} ({} days)", label, r, pairs.len()),
+            None => println!("{:<22} not enough days", label),
+        }
+    }
+    
+    Ok(())
+}
+
+async fn print_away(db: &Database, days: i64) -> Result<()> {
+    let today = Local::now().date_naive();
+    let first = today - Duration::days(days.max(1) - 1);
+    let now = Utc::now();
+    let start = local_midnight(first);
+    
+    // Query the whole span at once so a lock held across midnight is paired up
+    let events = db.get_system_events(start..now).await?;
+    let system = away::system_intervals(&events, now);
+    let idle = db.get_tagged_periods("idle", start..now).await?;
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Day", "Asleep / Off", "Locked", "Idle"]);
+    
+    let mut day = first;
+    while day <= today {
+        let range = local_midnight(day)..local_midnight(day + Duration::days(1)).min(now);
+        let totals = away::breakdown(range, &system, &idle);
+        table.add_row(vec![
+            day.format("%a %Y-%m-%d").to_string(),
+            format_duration_secs(totals.off_seconds),
+            format_duration_secs(totals.locked_seconds),
+            format_duration_secs(totals.idle_seconds),
+        ]);
+        day += Duration::days(1);
+    }
+    
+    println!("\n{table}\n");
+    if events.is_empty() {
+        println!("No lock or sleep events recorded yet; they are tracked while the monitor runs");
+    }
+    
+    Ok(())
+}
+
+async fn print_meetings(db: &Database, days: i64) -> Result<()> {
+    let today = Local::now().date_naive();
+    let first = today - Duration::days(days.max(1) - 1);
+    let now = Utc::now();
+    
+    let events = db.get_system_events(local_midnight(first)..now).await?;
+    let intervals = meetings::meeting_intervals(&events, now);
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Day", "Meetings", "Time in Meetings", "Longest"]);
+    
+    let mut total_seconds = 0;
+    let mut day = first;
+    while day <= today {
+        let range = local_midnight(day)..local_midnight(day + Duration::days(1)).min(now);
+        // A meeting across midnight counts on both days, for the part on each
+        let seconds: Vec<i64> = intervals
+            .iter()
+            .map(|interval| meetings::seconds_within(interval, &range))
+            .filter(|seconds| *seconds > 0)
+            .collect();
+        let day_total: i64 = seconds.iter().sum();
+        total_seconds += day_total;
+        
+        table.add_row(vec![
+            day.format("%a %Y-%m-%d").to_string(),
+            seconds.len().to_string(),
+            format_duration_secs(day_total),
+            format_duration_secs(seconds.iter().copied().max().unwrap_or(0)),
+        ]);
+        day += Duration::days(1);
+    }
+    
+    println!("\n{table}\n");
+    if intervals.is_empty() {
+        println!("No meetings recorded; enable track_meetings in the config to detect them");
+    } else {
+        println!("Total time in meetings: {}", format_duration_secs(total_seconds));
+    }
+    
+    Ok(())
+}
+
+async fn print_power(db: &Database, days: i64) -> Result<()> {
+    let today = Local::now().date_naive();
+    let first = today - Duration::days(days.max(1) - 1);
+    let now = Utc::now();
+    let start = local_midnight(first);
+    
+    let samples = db.get_power_samples(start..now).await?;
+    if samples.is_empty() {
+        println!("No power samples recorded; they are tracked on laptops while the monitor runs");
+        return Ok(());
+    }
+    let battery = power::battery_intervals(&samples, now, db.get_last_activity().await?);
+    let minutes = db.get_minute_totals(start..now).await?;
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Day", "On Battery", "Charge Used", "Drain / Hour", "Keys/min Battery", "Keys/min Plugged In"]);
+    
+    let rate = |value: Option<f64>, suffix: &str| value.map_or("-".to_string(), |value| format!("{:.1}{}", value, suffix));
+    let mut day = first;
+    while day <= today {
+        let range = local_midnight(day)..local_midnight(day + Duration::days(1)).min(now);
+        let totals = power::breakdown(range, &samples, &battery, &minutes);
+        table.add_row(vec![
+            day.format("%a %Y-%m-%d").to_string(),
+            format_duration_secs(totals.battery_seconds),
+            format!("{:.0}%", totals.battery_used),
+            rate(totals.drain_per_hour(), "%"),
+            rate(totals.battery_keys_per_minute(), ""),
+            rate(totals.mains_keys_per_minute(), ""),
+        ]);
+        day += Duration::days(1);
+    }
+    
+    println!("\n{table}\n");
+    println!("Keys/min counts minutes with any input, so idle time doesn't dilute it");
+    Ok(())
+}
+
+async fn print_devices(db: &Database, days: i64) -> Result<()> {
+    let today = Local::now().date_naive();
+    let start = local_midnight(today - Duration::days(days.max(1) - 1));
+    let usage = db.get_device_usage(start..Utc::now()).await?;
+    if usage.is_empty() {
+        println!("No per-device input recorded; the evdev and Wayland backends tell devices apart");
+        return Ok(());
+    }
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["Device", "Kind", "Keystrokes", "Clicks", "Scrolls"]);
+    
+    for device in &usage {
+        let placement = if device.built_in { "built-in" } else { "external" };
+        table.add_row(vec![
+            device.name.clone(),
+            format!("{} {}", placement, device.kind),
+            device.keys.to_string(),
+            device.clicks.to_string(),
+            device.scrolls.to_string(),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    Ok(())
+}
+
+// Only whole minutes are exported, so a minute is never sent twice with different totals
+async fn export_metrics(
+    db: &Database,
+    days: i64,
+    push: Option<&str>,
+    token: Option<&str>,
+    interval: Option<u64>,
+    measurement: &str,
+) -> Result<()> {
+    let mut end = Utc::now().duration_trunc(Duration::minutes(1))?;
+    let mut start = end - Duration::days(days.max(1));
+    
+    loop {
+        let minutes = db.get_minute_totals(start..end).await?;
+        if !minutes.is_empty() {
+            let lines = line_protocol::encode(measurement, &minutes);
+            match push {
+                Some(url) => {
+                    line_protocol::push(url, token, &lines).await?;
+                    if interval.is_none() {
+                        println!("Pushed {} minutes to {}", minutes.len(), url);
+                    }
+                }
+                None => {
+                    print!("{}", lines);
+                    std::io::stdout().flush()?;
+                }
+            }
+        }
+        
+        let Some(seconds) = interval else {
+            return Ok(());
+        };
+        tokio::time::sleep(std::time::Duration::from_secs(seconds.max(1))).await;
+        
+        start = end;
+        end = Utc::now().duration_trunc(Duration::minutes(1))?;
+    }
+}
+
+// Bars re-run or tail this command often, so totals come from the stats cache
+// and a long-running widget only queries SQLite once per interval
+async fn run_widget(db: Database, format: WidgetFormat, interval: Option<u64>) -> Result<()> {
+    let db = Arc::new(db);
+    let refresh = std::time::Duration::from_secs(interval.unwrap_or(60).max(1));
+    let cache = StatsCache::new(db.clone(), refresh);
+    
+    loop {
+        let today = cache.today().await?;
+        let current = db.get_latest_window_visit().await?;
+        println!("{}", widget_snippet(format, &today, current.as_ref()));
+        
+        if interval.is_none() {
+            return Ok(());
+        }
+        // Tailing bars read line by line; don't leave the snippet in the buffer
+        std::io::stdout().flush()?;
+        tokio::time::sleep(refresh).await;
+    }
+}
+
+fn widget_snippet(format: WidgetFormat, today: &DailySummary, current: Option<&WindowVisit>) -> String {
+    let active = format_duration_secs(today.active_seconds);
+    let app = current.map_or("—", |visit| visit.process.as_str());
+    let text = format!("{} · {} · {} keys", active, app, today.keystrokes);
+    
+    match format {
+        WidgetFormat::Waybar => {
+            let tooltip = format!(
+                "Active today: {}\nKeystrokes: {}\nClicks: {}\nCurrent app: {}",
+                active, today.keystrokes, today.clicks, app
+            );
+            serde_json::json!({
+                "text": text,
+                "tooltip": tooltip,
+                "class": "selfspy",
+                "alt": app,
+            })
+            .to_string()
+        }
+        WidgetFormat::Polybar => text,
+        // xbar shows the first line in the menu bar and the lines after `---` in its dropdown
+        WidgetFormat::Xbar => format!(
+            "{}\n---\nActive today: {}\nKeystrokes: {}\nClicks: {}\nCurrent app: {}",
+            text, active, today.keystrokes, today.clicks, app
+        ),
+    }
+}
+
+fn format_duration_secs(seconds: i64) -> String {
+    if seconds >= 3600 {
+        format!("{}h {:02}m", seconds / 3600, seconds % 3600 / 60)
+    } else if seconds >= 60 {
+        format!("{}m {:02}s", seconds / 60, seconds % 60)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+async fn detect_deep_work(db: &Database, config: &Config, days: i64) -> Result<()> {
+    let today = Local::now().date_naive();
+    let mut total = 0;
+    
+    for offset in (0..days.max(1)).rev() {
+        let day = today - Duration::days(offset);
+        let activity = db.get_minute_activity(day).await?;
+        let switches = db.get_window_switches(day).await?;
+        let intervals = deep_work::detect(day, &activity, &switches, &config.deep_work);
+        
+        for interval in &intervals {
+            println!(
+                "{} {}-{} {} ({} min, {} switches)",
+                day,
+                interval.started_at.format("%H:%M"),
+                interval.ended_at.format("%H:%M"),
+                interval.process,
+                interval.minutes,
+                interval.switches
+            );
+        }
+        total += intervals.len();
+        db.replace_deep_work(day, &intervals).await?;
+    }
+    
+    println!("Stored {} deep-work intervals", total);
+    Ok(())
+}
+
+async fn print_deep_work_leaderboard(db: &Database, limit: i64) -> Result<()> {
+    let days = db.get_top_deep_work_days(limit).await?;
+    if days.is_empty() {
+        println!("No deep work recorded yet; run `selfstats deep-work detect` first");
+        return Ok(());
+    }
+    
+    let mut table = Table::new();
+    table
+        .load_preset(UTF8_FULL)
+        .apply_modifier(UTF8_ROUND_CORNERS)
+        .set_header(vec!["#", "Day", "Deep Work", "Intervals", "Longest"]);
+    
+    for (rank, day) in days.iter().enumerate() {
+        table.add_row(vec![
+            (rank + 1).to_string(),
+            day.day.format("%a %Y-%m-%d").to_string(),
+            format!("{}h {:02}m", day.minutes / 60, day.minutes % 60),
+            day.intervals.to_string(),
+            format!("{} min", day.longest_minutes),
+        ]);
+    }
+    
+    println!("\n{table}\n");
+    Ok(())
+}
+
+fn print_schemas(name: Option<&str>, list: bool) -> Result<()> {
+    if list {
+        for name in schema::SCHEMA_NAMES {
+            println!("{}", name);
+        }
+        return Ok(());
+    }
+    
+    let json = match name {
+        Some(name) => {
+            let Some(schema) = schema::schema(name) else {
+                anyhow::bail!("Unknown schema '{}'; see `selfstats schema --list`", name);
+            };
+            serde_json::to_value(schema)?
+        }
+        None => {
+            let mut schemas = serde_json::Map::new();
+            for (name, schema) in schema::all_schemas() {
+                schemas.insert(name.to_string(), serde_json::to_value(schema)?);
+            }
+            serde_json::json!({
+                "version": schema::SCHEMA_VERSION,
+                "schemas": schemas,
+            })
+        }
+    };
+    
+    println!("{}", serde_json::to_string_pretty(&json)?);
+    Ok(())
+}
+
+fn print_table_stats(stats: &selfspy_core::models::ActivityStats, filter: &StatsFilter) {
     let mut table = Table::new();
     table
         .load_preset(UTF8_FULL)
         .apply_modifier(UTF8_ROUND_CORNERS)
         .set_header(vec!["Metric", "Value"]);
     
+    if let Some(label) = &filter.session_label {
+        table.add_row(vec!["Session Label", label]);
+    }
+    if filter.start.is_some() || filter.end.is_some() {
+        let day = |at: Option<DateTime<Utc>>, fallback: &str| {
+            at.map_or(fallback.to_string(), |at| at.with_timezone(&Local).format("%Y-%m-%d").to_string())
+        };
+        // `end` is the midnight after the last included day
+        let last = filter.end.map(|end| end - Duration::seconds(1));
+        table.add_row(vec!["Period", &format!("{} to {}", day(filter.start, "start"), day(last, "today"))]);
+    }
+    
     table.add_row(vec!["Total Keystrokes", &stats.total_keystrokes.to_string()]);
+    table.add_row(vec![
+        "Corrections",
+        &format!("{} ({:.1}%)", stats.total_corrections, stats.correction_rate()),
+    ]);
+    table.add_row(vec!["Pasted Characters", &stats.total_pasted_characters.to_string()]);
     table.add_row(vec!["Total Clicks", &stats.total_clicks.to_string()]);
+    table.add_row(vec![
+        "Total Scrolls",
+        &format!(
+            "{} ({:.0} vertical, {:.0} horizontal)",
+            stats.total_scrolls, stats.total_scroll_distance_y, stats.total_scroll_distance_x
+        ),
+    ]);
     table.add_row(vec!["Total Windows", &stats.total_windows.to_string()]);
     table.add_row(vec!["Total Processes", &stats.total_processes.to_string()]);
+    table.add_row(vec!["Active Time", &format_duration_secs(stats.session_duration)]);
     
     if let Some(process) = &stats.most_active_process {
         table.add_row(vec!["Most Active Process", process]);
     }
+    for metric in &stats.custom_metrics {
+        let value = match &metric.error {
+            Some(error) => error.clone(),
+            None => metric.display.clone(),
+        };
+        table.add_row(vec![metric.name.as_str(), &value]);
+    }
     
     println!("\n{table}\n");
 }
@@ -88,11 +2292,20 @@ fn print_json_stats(stats: &selfspy_core::models::ActivityStats) -> Result<()> {
 fn print_csv_stats(stats: &selfspy_core::models::ActivityStats) {
     println!("metric,value");
     println!("total_keystrokes,{}", stats.total_keystrokes);
+    println!("total_corrections,{}", stats.total_corrections);
+    println!("total_pasted_characters,{}", stats.total_pasted_characters);
     println!("total_clicks,{}", stats.total_clicks);
+    println!("total_scrolls,{}", stats.total_scrolls);
+    println!("total_scroll_distance_x,{:.1}", stats.total_scroll_distance_x);
+    println!("total_scroll_distance_y,{:.1}", stats.total_scroll_distance_y);
     println!("total_windows,{}", stats.total_windows);
     println!("total_processes,{}", stats.total_processes);
+    println!("session_duration,{}", stats.session_duration);
     
     if let Some(process) = &stats.most_active_process {
         println!("most_active_process,{}", process);
     }
+    for metric in &stats.custom_metrics {
+        println!("{},{}", metric.name, metric.value.map_or(String::new(), |value| value.to_string()));
+    }
 }
\ No newline at end of file
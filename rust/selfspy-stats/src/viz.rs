@@ -1,17 +1,22 @@
 use anyhow::Result;
+use chrono::{Local, Utc};
 use clap::{Parser, Subcommand};
 use crossterm::{
     execute,
     terminal::{Clear, ClearType},
 };
 use indicatif::{ProgressBar, ProgressStyle};
-use selfspy_core::{init, Config, Database};
-use std::{io::stdout, path::PathBuf, time::Duration};
+use selfspy_core::{init, BucketSize, Config, Database};
+use std::{io::stdout, path::PathBuf, process::ExitCode, time::Duration};
 use tokio::time;
 
+// Characters in the longest timeline bar
+const TIMELINE_WIDTH: i64 = 20;
+
 #[derive(Parser)]
 #[command(name = "selfviz")]
 #[command(about = "Enhanced visualizations for Selfspy", version)]
+#[command(after_help = selfspy_core::error::EXIT_CODES_HELP)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -50,7 +55,14 @@ enum Commands {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    match run().await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => selfspy_core::error::report(&*e),
+    }
+}
+
+async fn run() -> Result<()> {
     init().await?;
     
     let cli = Cli::parse();
@@ -76,7 +88,7 @@ async fn show_enhanced_stats(data_dir: Option<PathBuf>, days: i64) -> Result<()>
         config = config.with_data_dir(dir);
     }
     
-    let db = Database::new(&config.database_path).await?;
+    let db = Database::open_snapshot(&config.database_path).await?;
     let stats = db.get_stats().await?;
     
     execute!(stdout(), Clear(ClearType::All))?;
@@ -115,15 +127,38 @@ async fn show_timeline(data_dir: Option<PathBuf>, days: i64) -> Result<()> {
         config = config.with_data_dir(dir);
     }
     
+    let db = Database::open_snapshot(&config.database_path).await?;
+    let days = days.max(1);
+    let now = Utc::now();
+    let first_day = Local::now().date_naive() - chrono::Duration::days(days - 1);
+    let start = first_day
+        .and_hms_opt(0, 0, 0)
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .map_or(now - chrono::Duration::days(days), |start| start.with_timezone(&Utc));
+    
+    // Hours while they still fit on a screen, days after that
+    let (bucket, label) = if days <= 2 {
+        (BucketSize::Hour, "%a %H:00")
+    } else {
+        (BucketSize::Day, "%a %m-%d")
+    };
+    let buckets = db.get_activity_buckets(start..now, bucket).await?;
+    let longest = buckets.iter().map(|bucket| bucket.active_seconds).max().unwrap_or(0).max(1);
+    
     println!("📅 Activity Timeline (Last {} days)", days);
     println!("─────────────────────────────────────");
     
-    // This would show hourly activity in a real implementation
-    for hour in 0..24 {
-        let activity_level = (hour * 4) % 10;
-        let bar = "█".repeat(activity_level as usize);
-        let empty = "░".repeat((10 - activity_level) as usize);
-        println!("{:02}:00 │ {}{}", hour, bar, empty);
+    for entry in &buckets {
+        let filled = (entry.active_seconds * TIMELINE_WIDTH / longest) as usize;
+        println!(
+            "{} │ {}{} {:>4}m · {} keys · {} clicks",
+            entry.start.format(label),
+            "█".repeat(filled),
+            "░".repeat(TIMELINE_WIDTH as usize - filled),
+            entry.active_seconds / 60,
+            entry.keystrokes,
+            entry.clicks
+        );
     }
     
     Ok(())
@@ -135,7 +170,7 @@ async fn show_live_dashboard(data_dir: Option<PathBuf>) -> Result<()> {
         config = config.with_data_dir(dir);
     }
     
-    let db = Database::new(&config.database_path).await?;
+    let db = Database::open_snapshot(&config.database_path).await?;
     
     println!("🔴 Live Activity Dashboard (Press Ctrl+C to stop)");
     println!("──────────────────────────────────────────────────");